@@ -19,9 +19,299 @@ export interface ModelAnalysis {
   has_textures: boolean;
   has_normals: boolean;
   has_uvs: boolean;
+  /** A second UV set (TEXCOORD_1), usually for lightmaps */
+  has_lightmap_uvs: boolean;
+  has_vertex_colors: boolean;
   file_size_bytes: number;
   bounding_box: BoundingBox;
   center: [number, number, number];
+  /** NaN, infinite or out-of-range positions and indices */
+  data_issues: DataIssues;
+  /** Extensions the file uses or requires, and which the app understands */
+  extensions: GltfExtension[];
+  /** Generator, copyright, license and top-level extras */
+  metadata: AssetMetadata;
+}
+
+export interface AssetMetadata {
+  generator: string | null;
+  copyright: string | null;
+  /** asset.extras.license, where stores such as Sketchfab record it */
+  license: string | null;
+  /** The document's top-level extras */
+  extras: unknown;
+}
+
+/** Copyright and license to write; omit a field to keep it, '' removes it */
+export interface RightsEdit {
+  copyright?: string;
+  license?: string;
+}
+
+export interface EditedRights {
+  path: string;
+  /** The file's metadata after the edit */
+  metadata: AssetMetadata;
+  changes: ChangeReport;
+}
+
+/** Something done to an asset on its way to the file */
+export interface ProcessingStep {
+  /** Command or tool that made the change, such as `decimate` */
+  operation: string;
+  details: string | null;
+  /** When it happened; stamping fills in the current time for 0 */
+  at_ms?: number;
+}
+
+/** Record kept in a glTF file's `extras.sweedle_provenance` */
+export interface Provenance {
+  author: string | null;
+  source_url: string | null;
+  /** Hash of the file as it was first imported */
+  import_hash: string | null;
+  /** Oldest first */
+  history: ProcessingStep[];
+  /** Hash of the rest of the file when the record was embedded */
+  content_hash: string;
+}
+
+/** Fields to add to a record; missing ones keep what it says */
+export interface ProvenanceStamp {
+  author?: string | null;
+  source_url?: string | null;
+  import_hash?: string | null;
+  /** Appended to the record's history */
+  steps?: ProcessingStep[];
+}
+
+export interface WebViewerExport {
+  out_dir: string;
+  page_path: string;
+  model_path: string;
+  poster_path: string;
+  source_bytes: number;
+  /** Size of the compressed GLB */
+  model_bytes: number;
+  changes: ChangeReport;
+}
+
+/** How to reach the local API; also what `local_api.json` holds */
+export interface LocalApi {
+  /** JSON-RPC endpoint, such as `http://127.0.0.1:51234/v1/rpc` */
+  url: string;
+  api_version: number;
+  /** Sent as `Authorization: Bearer <token>` */
+  token: string;
+  pid: number;
+}
+
+export interface ArPreview {
+  asset_id: string;
+  /** Address to open on the phone, also the QR code's payload */
+  url: string;
+  /** The URL as a QR code */
+  qr_svg: string;
+  glb_path: string | null;
+  usdz_path: string | null;
+  expires_ms: number;
+}
+
+/** Payload of the `ar-preview-stopped` event */
+export interface ArPreviewStopped {
+  url: string;
+}
+
+export interface AnimationMarker {
+  name: string;
+  /** Seconds from the start of the clip */
+  time: number;
+}
+
+export interface ClipInfo {
+  index: number;
+  name: string | null;
+  start: number;
+  end: number;
+  track_count: number;
+  /** Markers stored in the clip's `extras.markers` */
+  markers: AnimationMarker[];
+}
+
+/** Frames `start` to `end`, inclusive; `fps` defaults to 30 */
+export interface FrameRange {
+  start: number;
+  end: number;
+  fps?: number;
+}
+
+export interface EditedAnimations {
+  out_path: string;
+  animations: ClipInfo[];
+  /** Nodes of merged files with no node of the same name in the target */
+  dropped: string[];
+  changes: ChangeReport;
+}
+
+export interface BakedPoseExport {
+  out_path: string;
+  mesh_count: number;
+  vertex_count: number;
+  face_count: number;
+  skinned_mesh_count: number;
+  morphed_mesh_count: number;
+  /** Point, line and strip primitives, which aren't baked */
+  skipped_primitive_count: number;
+  changes: ChangeReport;
+}
+
+export type RestPoseKind = 't_pose' | 'a_pose' | 'other';
+
+export interface ArmPose {
+  side: 'left' | 'right';
+  /** Node of the upper arm joint */
+  joint: number;
+  name: string | null;
+  /** Angle of the upper arm below horizontal; negative if raised */
+  drop_degrees: number;
+  kind: RestPoseKind;
+}
+
+export interface RestPoseAnalysis {
+  /** The arms' pose if they agree, 'other' if not; null without arms */
+  kind: RestPoseKind | null;
+  arms: ArmPose[];
+}
+
+export interface ConvertedRestPose {
+  out_path: string;
+  before: RestPoseAnalysis;
+  after: RestPoseAnalysis;
+  changes: ChangeReport;
+}
+
+export interface BoneUsage {
+  /** Node of the joint */
+  joint: number;
+  name: string | null;
+  vertex_count: number;
+  max_weight: number;
+}
+
+export interface WeightReport {
+  skin_count: number;
+  skinned_vertex_count: number;
+  max_influences: number;
+  /** Entry n counts the vertices with n influences */
+  influence_counts: number[];
+  /** Weighted vertices whose weights don't add up to one */
+  unnormalized_vertex_count: number;
+  unweighted_vertex_count: number;
+  bones: BoneUsage[];
+  unused_bones: string[];
+}
+
+export interface EditedWeights {
+  out_path: string;
+  changed_vertex_count: number;
+  /** Joints removed from the skins */
+  pruned_bones: string[];
+  /** Statistics of the written file */
+  report: WeightReport;
+  changes: ChangeReport;
+}
+
+export interface StampedProvenance {
+  path: string;
+  provenance: Provenance;
+  changes: ChangeReport;
+}
+
+export interface ProvenanceCheck {
+  provenance: Provenance | null;
+  /** Hash of the file as it is now, without the record */
+  content_hash: string;
+  /** Whether the file has a record and hasn't changed since */
+  intact: boolean;
+}
+
+export interface GltfExtension {
+  name: string;
+  /** Listed in extensionsRequired */
+  required: boolean;
+  /** Decoded by the app, or safe to ignore when reading geometry */
+  understood: boolean;
+}
+
+/** Column-major 4x4 matrix */
+export type Matrix4 = [
+  [number, number, number, number],
+  [number, number, number, number],
+  [number, number, number, number],
+  [number, number, number, number],
+];
+
+export interface SceneNode {
+  index: number;
+  name: string | null;
+  parent: number | null;
+  children: number[];
+  mesh: number | null;
+  camera: number | null;
+  light: number | null;
+  local_transform: Matrix4;
+  world_transform: Matrix4;
+}
+
+export type LightKind = 'directional' | 'point' | 'spot';
+
+export interface SceneLight {
+  index: number;
+  name: string | null;
+  kind: LightKind;
+  /** Linear RGB */
+  color: [number, number, number];
+  /** Candela for point and spot lights, lux for directional ones */
+  intensity: number;
+  range: number | null;
+  /** Spot cone angles in radians */
+  inner_cone_angle: number | null;
+  outer_cone_angle: number | null;
+  /** Nodes placing this light */
+  nodes: number[];
+}
+
+export type CameraProjection =
+  | {
+      type: 'perspective';
+      /** Vertical field of view in radians */
+      yfov: number;
+      aspect_ratio: number | null;
+      znear: number;
+      zfar: number | null;
+    }
+  | { type: 'orthographic'; xmag: number; ymag: number; znear: number; zfar: number };
+
+export interface SceneCamera {
+  index: number;
+  name: string | null;
+  projection: CameraProjection;
+  /** Nodes placing this camera */
+  nodes: number[];
+}
+
+export interface SceneGraph {
+  roots: number[];
+  nodes: SceneNode[];
+  lights: SceneLight[];
+  cameras: SceneCamera[];
+}
+
+export interface DataIssues {
+  non_finite_vertex_count: number;
+  absurd_vertex_count: number;
+  out_of_range_index_count: number;
+  non_finite_attribute_count: number;
 }
 
 export interface BoundingBox {
@@ -29,145 +319,3628 @@ export interface BoundingBox {
   max: [number, number, number];
 }
 
-export interface LodResult {
-  original_vertex_count: number;
-  original_face_count: number;
-  levels: LodLevel[];
-}
+export interface LodResult {
+  original_vertex_count: number;
+  original_face_count: number;
+  levels: LodLevel[];
+}
+
+export interface LodLevel {
+  level: number;
+  vertex_count: number;
+  face_count: number;
+  reduction_ratio: number;
+  /** Screen-space mode only */
+  geometric_error: number | null;
+  switch_distance: number | null;
+  mesh_handle: number | null;
+}
+
+/** Viewing setup for picking LOD levels by on-screen error */
+export interface ScreenSpaceLod {
+  fov_y_degrees: number;
+  viewport_height_px: number;
+  /** Defaults to 1 pixel */
+  max_error_px?: number;
+  distances: number[];
+}
+
+export interface SimplifiedMesh {
+  mesh: MeshHandle;
+  original_face_count: number;
+  reduction_ratio: number;
+  protected_vertex_count: number;
+  seam_vertex_count: number;
+  face_materials: number[] | null;
+}
+
+export interface ReindexResult {
+  mesh: MeshHandle;
+  original_vertex_count: number;
+  removed_face_count: number;
+  reduction_ratio: number;
+}
+
+/** Limits past which a face counts as degenerate or a sliver */
+export interface FaceThresholds {
+  min_area?: number;
+  max_aspect_ratio?: number;
+}
+
+/** Pull bad coordinates back to the limit ('clamp') or remove them ('drop') */
+export type SanitizeMode = 'drop' | 'clamp';
+
+export interface RepairOptions {
+  /** null only reports bad data */
+  sanitize?: SanitizeMode | null;
+  max_coordinate?: number;
+  remove_degenerate?: boolean;
+  fix_slivers?: boolean;
+  thresholds?: FaceThresholds;
+}
+
+export interface DegenerateCleanup {
+  degenerate_face_count: number;
+  sliver_face_count: number;
+  collapsed_edge_count: number;
+  flipped_edge_count: number;
+  removed_face_count: number;
+  remaining_sliver_count: number;
+}
+
+export interface SanitizeReport {
+  issues: DataIssues;
+  dropped_face_count: number;
+  removed_vertex_count: number;
+  clamped_vertex_count: number;
+  zeroed_attribute_count: number;
+}
+
+export interface RepairResult {
+  mesh: MeshHandle;
+  sanitized: SanitizeReport;
+  degenerate: DegenerateCleanup;
+}
+
+/** Points p with dot(normal, p) == offset */
+export interface MirrorPlane {
+  normal: [number, number, number];
+  offset: number;
+}
+
+export type MirrorMode = 'symmetrize' | 'flip';
+
+export interface AsymmetricRegion {
+  vertex_count: number;
+  centroid: [number, number, number];
+  bounds_min: [number, number, number];
+  bounds_max: [number, number, number];
+}
+
+export interface SymmetryReport {
+  plane: MirrorPlane;
+  score: number;
+  candidates: { plane: MirrorPlane; score: number }[];
+  tolerance: number;
+  asymmetric_vertex_count: number;
+  asymmetric_regions: AsymmetricRegion[];
+}
+
+export type UvProjection =
+  | { kind: 'planar'; axis?: [number, number, number] }
+  | { kind: 'cylindrical'; axis?: [number, number, number] }
+  | { kind: 'box' };
+
+/** Points p with dot(normal, p) == offset */
+export interface SectionPlane {
+  normal: [number, number, number];
+  offset: number;
+}
+
+/** Section polyline in plane coordinates, in mesh units */
+export interface Polyline {
+  points: [number, number][];
+  closed: boolean;
+  length: number;
+}
+
+export interface Section {
+  polylines: Polyline[];
+  /** World point at the plane's (0, 0) */
+  origin: [number, number, number];
+  u_axis: [number, number, number];
+  v_axis: [number, number, number];
+}
+
+export type PrintTechnology = 'fdm' | 'resin';
+
+export interface SpeedProfile {
+  perimeter_mm_s?: number;
+  infill_mm_s?: number;
+  exposure_s?: number;
+  bottom_exposure_s?: number;
+  bottom_layer_count?: number;
+  lift_s?: number;
+}
+
+/** Omitted values fall back to defaults for the technology */
+export interface PrintSettings {
+  technology?: PrintTechnology;
+  layer_height_mm?: number;
+  infill_percent?: number;
+  speed?: SpeedProfile;
+  wall_count?: number;
+  line_width_mm?: number;
+  filament_diameter_mm?: number;
+  material_density_g_cm3?: number;
+}
+
+export interface PrintEstimate {
+  technology: PrintTechnology;
+  layer_count: number;
+  print_time_s: number;
+  material_volume_cm3: number;
+  material_mass_g: number;
+  filament_length_m: number | null;
+}
+
+export interface PrintabilityReport {
+  size_mm: [number, number, number];
+  volume_cm3: number;
+  surface_area_cm2: number;
+  is_watertight: boolean;
+  shell_count: number;
+  boundary_loop_count: number;
+  non_manifold_edge_count: number;
+  degenerate_face_count: number;
+  estimate: PrintEstimate;
+}
+
+export interface OrientationCriteria {
+  support_weight?: number;
+  contact_weight?: number;
+  height_weight?: number;
+  overhang_angle_deg?: number;
+  samples?: number;
+  max_results?: number;
+}
+
+export interface PrintOrientation {
+  /** Mesh direction that faces down onto the build plate */
+  down: [number, number, number];
+  /** Quaternion (x, y, z, w) turning `down` to -Y */
+  rotation: [number, number, number, number];
+  score: number;
+  support_volume: number;
+  contact_area: number;
+  print_height: number;
+}
+
+export interface ShrinkwrapResult {
+  mesh: MeshHandle;
+  mean_displacement: number;
+  max_displacement: number;
+}
+
+export interface ChangedRegion {
+  vertex_count: number;
+  min: [number, number, number];
+  max: [number, number, number];
+  centroid: [number, number, number];
+  /** Deviation of largest magnitude in the region, with its sign */
+  peak_deviation: number;
+  mean_deviation: number;
+}
+
+export interface SpatialDiff {
+  /** Signed distance of each vertex of B from A's surface, positive in front of A's faces */
+  deviations: number[];
+  /** Largest absolute deviation; map colours over -range..range */
+  range: number;
+  mean_deviation: number;
+  rms_deviation: number;
+  /** Smallest deviation counted as a change */
+  resolution: number;
+  changed_vertex_count: number;
+  /** Largest first */
+  regions: ChangedRegion[];
+}
+
+export interface MeasuredScale {
+  mesh: MeshHandle;
+  measured_distance: number;
+  scale_factor: number;
+}
+
+export interface ProjectedUvs {
+  mesh: MeshHandle;
+  split_vertex_count: number;
+}
+
+export interface LightmapUvs {
+  mesh: MeshHandle;
+  chart_count: number;
+  split_vertex_count: number;
+  texels_per_unit: number;
+  /** Fraction of the lightmap covered by faces */
+  coverage: number;
+}
+
+export type UvSet = 'base' | 'lightmap';
+
+export type UvRenderFormat = 'svg' | 'png';
+
+export interface TexelDensity {
+  texture_size: number;
+  min: number;
+  max: number;
+  /** Weighted by world-space area */
+  mean: number;
+  median: number;
+}
+
+export interface UvAnalysis {
+  uv_set: UvSet;
+  bounds: { min: [number, number]; max: [number, number] };
+  island_count: number;
+  texel_density: TexelDensity;
+  /** Overlapping island pairs; [i, i] is an island folding over itself */
+  overlapping_islands: [number, number][];
+  overlapping_face_count: number;
+  flipped_face_count: number;
+  mirrored_island_count: number;
+  degenerate_face_count: number;
+  out_of_range_face_count: number;
+  tiles: [number, number][];
+}
+
+export interface IslandResize {
+  island: number;
+  face_count: number;
+  /** Texels per centimeter before rescaling */
+  density_before: number;
+  scale: number;
+}
+
+export interface NormalizedTexelDensity {
+  mesh: MeshHandle;
+  texels_per_cm: number;
+  island_count: number;
+  resized: IslandResize[];
+  skipped_island_count: number;
+}
+
+export interface MeshStats {
+  vertex_count: number;
+  face_count: number;
+  edge_count: number;
+  is_manifold: boolean;
+  has_degenerate_faces: boolean;
+  degenerate_face_count: number;
+  sliver_face_count: number;
+  surface_area: number;
+  volume: number;
+  euler_characteristic: number;
+  shell_count: number;
+  /** Up to 100 shells, largest first */
+  shells: Shell[];
+  boundary_loop_count: number;
+  /** Up to 100 boundary loop lengths, longest first */
+  boundary_loop_lengths: number[];
+  non_manifold_edge_count: number;
+  non_manifold_vertex_count: number;
+}
+
+/** One connected piece of a mesh */
+export interface Shell {
+  face_count: number;
+  euler_characteristic: number;
+  boundary_loop_count: number;
+  is_closed: boolean;
+  /** Handles through a closed shell; null when it isn't closed */
+  genus: number | null;
+}
+
+/** Whether a mesh was authored in quads before triangulation */
+export type FaceTopology = 'triangles' | 'quads' | 'mixed';
+
+export interface NamedMeshStats {
+  name: string;
+  stats: MeshStats;
+  topology: FaceTopology;
+}
+
+export interface FileStats {
+  path: string;
+  format: 'gltf' | 'obj' | 'stl';
+  meshes: NamedMeshStats[];
+  total: MeshStats;
+}
+
+export interface OptimizedMeshResult {
+  original_vertex_count: number;
+  optimized_vertex_count: number;
+  cache_hits_before: number;
+  cache_hits_after: number;
+  overdraw_before: number;
+  overdraw_after: number;
+}
+
+export interface FileInfo {
+  path: string;
+  name: string;
+  extension: string | null;
+  size_bytes: number;
+  created: number | null;
+  modified: number | null;
+  is_directory: boolean;
+}
+
+export interface StorageAsset {
+  id: string;
+  /** Folder holding the asset's model files */
+  path: string;
+  /** Preferred model file: GLB, then OBJ, then FBX */
+  model_path: string | null;
+  /** Image file of an asset that is a texture or HDRI rather than a model */
+  texture_path: string | null;
+  /** Category from a layout that sorts assets by category */
+  category: string | null;
+  has_glb: boolean;
+  has_obj: boolean;
+  has_fbx: boolean;
+  has_thumbnail: boolean;
+  glb_size: number | null;
+  thumbnail_path: string | null;
+  /** Videos and audio kept with the asset */
+  media: MediaFile[];
+}
+
+/**
+ * A video or audio file beside an asset; duration is read from MP4/MOV,
+ * WAV and FLAC headers and frame size from MP4/MOV
+ */
+export interface MediaFile {
+  path: string;
+  kind: 'video' | 'audio';
+  size_bytes: number;
+  duration_secs: number | null;
+  width: number | null;
+  height: number | null;
+}
+
+/**
+ * Where models go inside storage folders, as a path template using `{id}`,
+ * `{ext}`, `{category}`, `{yyyy}`, `{mm}` and `{dd}`: `{id}/{id}.{ext}` (the
+ * default), `{id}.{ext}`, `{category}/{id}/{id}.{ext}` or
+ * `{yyyy}/{mm}/{id}/{id}.{ext}`
+ */
+export interface StorageLayout {
+  template: string;
+}
+
+export interface StorageMigration {
+  from: StorageLayout;
+  to: StorageLayout;
+  asset_count: number;
+  changes: ChangeReport;
+}
+
+/** A storage folder registered by name */
+export interface StorageRoot {
+  name: string;
+  path: string;
+}
+
+export interface LibraryRoot {
+  name: string;
+  path: string;
+  /** Whether the last attempt to index the root succeeded */
+  online: boolean;
+  asset_count: number;
+  total_bytes: number;
+  /** When the listed assets were found; null if the root never indexed */
+  indexed_ms: number | null;
+  error: string | null;
+}
+
+/** Rating, favorite flag and export count of an asset */
+export interface AssetCuration {
+  /** Stars from 1 to 5; null when unrated */
+  rating: number | null;
+  favorite: boolean;
+  /** Times the asset went out in an export, bundle or web viewer */
+  export_count: number;
+  last_exported_ms: number | null;
+}
+
+/** An asset and the root it's stored in */
+export interface LibraryAsset extends StorageAsset, AssetCuration {
+  root: string;
+  license: AssetLicense | null;
+}
+
+export type LibrarySort = 'id' | 'rating' | 'most_exported' | 'recently_exported';
+
+/** What queryLibrary keeps of the library; every field narrows it */
+export interface LibraryFilter {
+  /** Only assets rated at least this many stars */
+  min_rating?: number | null;
+  favorites_only?: boolean;
+  /** Only assets exported at least once */
+  exported_only?: boolean;
+  /** Only assets in the root with this name */
+  root?: string | null;
+  /** Only assets whose id or category contains this, ignoring case */
+  text?: string | null;
+  sort?: LibrarySort;
+  /** At most this many assets, after sorting */
+  limit?: number | null;
+}
+
+/** Every storage root with the assets of all of them merged */
+export interface Library {
+  roots: LibraryRoot[];
+  /** Sorted by id; an id stored in several roots is listed once per root */
+  assets: LibraryAsset[];
+}
+
+/** What an asset must be to belong to a smart collection; every rule narrows it */
+export interface CollectionRules {
+  /** File extensions without the dot, such as "glb" or "stl"; empty matches every asset */
+  formats?: string[];
+  /** Triangle counts are read from glTF models only */
+  min_triangles?: number | null;
+  max_triangles?: number | null;
+  /** false for assets without a category, true for those with one */
+  categorized?: boolean | null;
+  /** Only assets in this category, ignoring case */
+  category?: string | null;
+  /** Only assets whose model or image file was written in the last this many days */
+  added_within_days?: number | null;
+  min_rating?: number | null;
+  favorites_only?: boolean;
+  /** false for assets without a recorded license */
+  licensed?: boolean | null;
+  /** Only assets whose id contains this, ignoring case */
+  text?: string | null;
+}
+
+/** A saved search over the library */
+export interface SmartCollection {
+  id: string;
+  name: string;
+  rules: CollectionRules;
+}
+
+export interface EvaluatedCollection {
+  collection: SmartCollection;
+  /** Sorted by id */
+  assets: LibraryAsset[];
+  /** Ids of glTF models whose triangle count couldn't be read */
+  unreadable: string[];
+}
+
+export interface MovedAsset {
+  asset_id: string;
+  from_root: string;
+  to_root: string;
+  /** Where the asset's model files went */
+  path: string;
+  changes: ChangeReport;
+}
+
+export type HistoryKind = 'imported' | 'processed' | 'renamed' | 'moved';
+
+export interface HistoryEntry {
+  kind: HistoryKind;
+  at_ms: number;
+  /** File or URL imported from, file processed, or the id or root before a rename or move */
+  source: string | null;
+  /** Where the asset's files went */
+  path: string | null;
+  /** Hash of the source file as imported */
+  hash: string | null;
+  /** What was done to the asset */
+  steps: ProcessingStep[];
+}
+
+export interface AssetHistoryReport {
+  asset_id: string;
+  /** Imports, processing runs, renames and moves, oldest first */
+  entries: HistoryEntry[];
+  /** Where the recorded license says the asset was obtained */
+  source_url: string | null;
+  /** Record embedded in the asset's glTF model */
+  provenance: Provenance | null;
+}
+
+/** One asset given a new id by bulkRename */
+export interface AssetRename {
+  asset_id: string;
+  new_id: string;
+  root: string;
+  /** Folder the asset's model files are in afterwards */
+  path: string;
+}
+
+export interface BulkRename {
+  /** One entry per copy of each renamed asset */
+  renames: AssetRename[];
+  /** Ids the pattern left as they were */
+  unchanged: string[];
+  changes: ChangeReport;
+}
+
+export type LicenseKind = 'cc0' | 'cc_by' | 'royalty_free' | 'custom';
+
+export interface AssetLicense {
+  kind: LicenseKind;
+  author: string | null;
+  /** Where the asset was obtained */
+  source_url: string | null;
+  /** License name or terms, such as "CC-BY-4.0" or a custom license's text */
+  terms: string | null;
+}
+
+/** An asset used by a project and what its license asks for */
+export interface ComplianceEntry {
+  asset_id: string;
+  /** Root holding the asset; null if the library doesn't have it */
+  root: string | null;
+  path: string | null;
+  license: AssetLicense | null;
+  /** Whether the license was read from the model file */
+  from_file: boolean;
+  requires_attribution: boolean;
+  /** Line for the credits file */
+  attribution: string | null;
+}
+
+export interface ComplianceReport {
+  project_id: string;
+  entries: ComplianceEntry[];
+  /** Ids of assets with no known license */
+  unlicensed: string[];
+  /** Text of a credits file, one attribution per licensed asset */
+  credits: string;
+}
+
+export type CacheKind = 'thumbnail' | 'preview' | 'texture';
+
+export interface CacheSettings {
+  /** Total size the cache is trimmed to after each write (2 GiB by default) */
+  max_bytes: number;
+}
+
+export interface CacheKindStats {
+  kind: CacheKind;
+  entry_count: number;
+  total_bytes: number;
+}
+
+export interface MaterialPreview {
+  index: number;
+  name: string;
+  /** PNG with a transparent background */
+  png: Uint8Array;
+}
+
+export interface CacheStats {
+  /** Folder holding the cache */
+  path: string;
+  max_bytes: number;
+  total_bytes: number;
+  kinds: CacheKindStats[];
+}
+
+export type AnalysisMode = 'cached' | 'incremental' | 'full';
+
+/** Payload of the `model-analysis-updated` event */
+export interface AnalysisUpdate {
+  path: string;
+  analysis: ModelAnalysis;
+  mode: AnalysisMode;
+  json_changed: boolean;
+  changed_buffers: number[];
+  reused_buffers: number[];
+  decoded_accessors: number;
+  issues: string[];
+}
+
+/** Payload of the `directory-changed` event */
+export interface DirectoryChangedEvent {
+  root: string;
+  paths: string[];
+}
+
+/** Payload of the `model-analysis-failed` event */
+export interface AnalysisFailedEvent {
+  path: string;
+  error: string;
+}
+
+export interface MeshHandle {
+  handle: number;
+  vertex_count: number;
+  face_count: number;
+  has_normals: boolean;
+  has_uvs: boolean;
+  has_lightmap_uvs: boolean;
+  has_vertex_colors: boolean;
+  has_skin: boolean;
+  topology: FaceTopology;
+  /** Source polygons, or triangles without polygon information */
+  polygon_count: number;
+}
+
+/** Faces picked directly, or through all of their corners */
+export interface MeshSelection {
+  /** Triangle indices */
+  faces?: number[];
+  vertices?: number[];
+}
+
+/** `surface` only moves the surface; `shell` keeps the original as well */
+export type OffsetMode = 'surface' | 'shell';
+
+/** Placements of an array's copies; the first is always where the mesh is */
+export type ArrayPattern =
+  | { kind: 'linear'; count: number; offset: [number, number, number] }
+  | {
+      kind: 'radial';
+      count: number;
+      axis: [number, number, number];
+      center: [number, number, number];
+      /** Spread of the copies; a full turn by default */
+      angle_degrees?: number;
+    }
+  | { kind: 'grid'; counts: [number, number, number]; spacing: [number, number, number] };
+
+export interface CropBox {
+  min: [number, number, number];
+  max: [number, number, number];
+}
+
+export interface SeparatedMesh {
+  /** The mesh without the selected faces */
+  rest: MeshHandle;
+  separated: MeshHandle;
+}
+
+export interface PlaneCutResult {
+  /** The part on the side the plane's normal points to */
+  front: MeshHandle;
+  back: MeshHandle;
+  closed_loop_count: number;
+  /** Cut runs through holes in the mesh, which are left open */
+  open_loop_count: number;
+}
+
+/** Four joint indices and four weights per vertex */
+export interface SkinAttributes {
+  joints: Uint32Array;
+  weights: Float32Array;
+}
+
+export type MeshTransport = 'shared_file' | 'raw_ipc';
+
+export interface TransportCapabilities {
+  platform: string;
+  preferred: MeshTransport;
+  available: MeshTransport[];
+  handoff_dir: string | null;
+}
+
+export interface BufferRange {
+  offset: number;
+  length: number;
+}
+
+export interface BufferLayout {
+  positions: BufferRange;
+  normals: BufferRange | null;
+  uvs: BufferRange | null;
+  lightmap_uvs?: BufferRange | null;
+  colors?: BufferRange | null;
+  indices: BufferRange;
+}
+
+export type PrimitiveLayout = 'triangles' | 'optimized' | 'triangle_strip';
+
+export interface BufferExportOptions {
+  primitive?: PrimitiveLayout;
+  /** Separate strips with 0xFFFFFFFF instead of degenerate triangles */
+  primitive_restart?: boolean;
+}
+
+export interface MeshBufferHandoff {
+  path: string;
+  byte_length: number;
+  layout: BufferLayout;
+  vertex_count: number;
+  face_count: number;
+  primitive: PrimitiveLayout;
+  original_index_count: number;
+  index_count: number;
+  index_savings: number;
+  acmr_before: number | null;
+  acmr_after: number | null;
+}
+
+export type ProcessingBackend = 'auto' | 'cpu' | 'gpu';
+
+export type UpAxis = 'y' | 'z';
+
+/** Conversion from a source file's axes and units to Y-up meters */
+export interface AxisConversion {
+  up_axis: UpAxis;
+  /** Meters per source unit (0.01 for centimeters) */
+  unit_scale: number;
+}
+
+/** Per-format conversion used when a file doesn't declare its own */
+export interface ImportAxes {
+  obj: AxisConversion;
+  stl: AxisConversion;
+  fbx: AxisConversion;
+}
+
+export type TriangulationMode = 'fan' | 'ear_clip';
+
+/** How imported quads and n-gons are split into triangles */
+export interface TriangulationOptions {
+  mode: TriangulationMode;
+  /** Largest corner distance from the polygon's plane, relative to its size */
+  planarity_tolerance: number;
+  /** Triangles with a smaller angle count as slivers */
+  sliver_angle_degrees: number;
+}
+
+export interface PolygonIssue {
+  mesh: string;
+  polygon: number;
+  corner_count: number;
+  planarity_deviation: number;
+  sliver_count: number;
+}
+
+export interface TriangulationReport {
+  mode: TriangulationMode;
+  ngon_count: number;
+  non_planar_count: number;
+  sliver_count: number;
+  /** Non-planar polygons and polygons that produced slivers, up to a limit */
+  issues: PolygonIssue[];
+}
+
+export interface AppSettings {
+  processing_backend: ProcessingBackend;
+  /** Refuse commands that write or delete files; dry runs still work */
+  read_only: boolean;
+  import_axes: ImportAxes;
+  triangulation: TriangulationOptions;
+  /** Named bundles of processing steps for `processAsset` */
+  processing_profiles: ProcessingProfile[];
+  /** Folders whose new models are run through a profile in the background */
+  watch_rules: WatchRule[];
+  /** Where models go inside storage folders */
+  storage_layout: StorageLayout;
+  /** Storage folders merged into one library */
+  storage_roots: StorageRoot[];
+  /** Saved searches over the library */
+  smart_collections: SmartCollection[];
+  /** Size limit of the thumbnail, preview and texture cache */
+  cache: CacheSettings;
+  /** Command-line tools that can be run on assets */
+  external_tools: ExternalTool[];
+  /** Blender executable, set with `pickBlenderExecutable`; empty looks in the usual install location, then on the PATH */
+  blender_executable: string;
+  /** gltf-transform executable for web exports, set with `pickGltfTransformExecutable`; empty looks on the PATH */
+  gltf_transform_executable: string;
+  /** Applications picked with `pickOpenWithApplication`, the only ones `openWith` accepts */
+  open_with_applications: string[];
+  /** Id of the external tool textures are upscaled with; empty uses the built-in filters */
+  texture_upscaler: string;
+  /** The user's validation profiles; one named like a built-in replaces it */
+  validation_profiles: ValidationProfile[];
+  /** Profile reports check against by default; empty checks none */
+  validation_profile: string;
+  /** When the library health checks run by themselves */
+  maintenance: MaintenanceSchedule;
+  /** Which finished background jobs are announced while the window is minimized */
+  notification_preferences: NotificationPreferences;
+  /** How many background jobs run at once, and when fewer run to spare the battery */
+  power_throttling: PowerThrottling;
+  /** Answer the API on a loopback port while the window is open, for plugins in other tools */
+  local_api: boolean;
+  /** Language of the messages, reports and notifications the backend writes */
+  locale: Locale;
+}
+
+export type Locale = 'en' | 'de' | 'fr' | 'es';
+
+export interface JobNotifications {
+  completed: boolean;
+  failed: boolean;
+}
+
+export interface NotificationPreferences {
+  enabled: boolean;
+  /** Jobs that ran for less time finish silently */
+  min_duration_ms: number;
+  /** For job kinds not listed in `job_kinds` */
+  default: JobNotifications;
+  /** By job kind, such as `watch_rule` or `maintenance` */
+  job_kinds: Record<string, JobNotifications>;
+}
+
+/** `auto` throttles on battery power or under thermal pressure */
+export type ThrottleMode = 'auto' | 'always' | 'never';
+
+export interface PowerThrottling {
+  mode: ThrottleMode;
+  /** Jobs run at once while not throttled (1 to 16); throttled, one runs */
+  max_jobs: number;
+}
+
+export type ThermalPressure = 'nominal' | 'elevated' | 'critical';
+
+/** What could be read of the power state; null where it couldn't */
+export interface PowerState {
+  on_battery: boolean | null;
+  battery_percent: number | null;
+  thermal: ThermalPressure | null;
+}
+
+/** Result of `getPowerStatus`, also sent with `power-status-changed` events */
+export interface PowerStatus {
+  state: PowerState;
+  throttling: PowerThrottling;
+  throttled_by: 'setting' | 'battery' | 'thermal' | null;
+  /** Jobs the queue runs at once now */
+  job_limit: number;
+}
+
+export interface BlenderEdit {
+  asset_id: string;
+  blender: string;
+  /** Session folder holding the copy Blender edits */
+  work_dir: string;
+}
+
+/** Payload of the `blender-edit-saved` event */
+export interface BlenderVersion {
+  asset_id: string;
+  version_path: string;
+  /** null if the saved file couldn't be analyzed */
+  analysis: ModelAnalysis | null;
+  changes: ChangeReport;
+}
+
+/** Payload of the `blender-edit-failed` event */
+export interface BlenderEditError {
+  asset_id: string;
+  error: string;
+}
+
+/** Payload of the `blender-edit-closed` event */
+export interface BlenderEditClosed {
+  asset_id: string;
+  /** Versions stored during the session, oldest first */
+  versions: string[];
+  exit_code: number | null;
+}
+
+export interface ExportedBundle {
+  out_path: string;
+  asset_count: number;
+  file_count: number;
+  changes: ChangeReport;
+}
+
+export interface ImportedBundle {
+  asset_ids: string[];
+  file_count: number;
+  /** Licenses recorded for assets that had none */
+  license_count: number;
+  /** Verdicts of models that failed the sandboxed parse; their assets are left out */
+  rejected: QuarantineVerdict[];
+  changes: ChangeReport;
+}
+
+export interface ExternalTool {
+  id: string;
+  name: string;
+  executable: string;
+  /**
+   * Argument templates; `{path}` is the asset's file, `{dir}` its folder,
+   * `{stem}` its name without extension and `{ext}` its extension
+   */
+  arguments: string[];
+  /** The tool is stopped if it runs longer (600 by default) */
+  timeout_secs: number;
+}
+
+export interface ExternalToolRun {
+  tool_id: string;
+  asset_id: string;
+  /** null if the tool was stopped at its timeout or by a signal */
+  exit_code: number | null;
+  timed_out: boolean;
+  /** The last 64 KiB of output */
+  stdout: string;
+  stderr: string;
+  elapsed_ms: number;
+  /** Files in the asset's folder the tool created or modified */
+  changed: string[];
+  /** Fresh analysis of the asset and of glTF files the tool wrote */
+  reanalyzed: AnalysisUpdate[];
+}
+
+export type ProfileFormat = 'glb' | 'obj' | 'stl';
+
+export interface ProcessingProfile {
+  name: string;
+  description: string;
+  /** Scan cleanup run first; null skips it */
+  cleanup: ScanPreset | null;
+  max_faces: number | null;
+  /** Textures beside the model are scaled down to this size */
+  max_texture_size: number | null;
+  format: ProfileFormat;
+}
+
+export interface WatchRule {
+  folder: string;
+  /** Extensions without the dot, matched ignoring case */
+  extensions: string[];
+  /** Name of the processing profile to run */
+  profile: string;
+  /** Also load the result into the mesh store */
+  import_result: boolean;
+  enabled: boolean;
+}
+
+export type JobState = 'queued' | 'running' | 'completed' | 'failed' | 'interrupted';
+
+export interface Job {
+  id: number;
+  kind: string;
+  label: string;
+  state: JobState;
+  queued_ms: number;
+  started_ms: number | null;
+  finished_ms: number | null;
+  /** A `WatchRuleOutcome` for `watch_rule` jobs, a `MaintenanceSummary` for `maintenance` */
+  result: unknown | null;
+  error: string | null;
+  /** What it takes to run the job again after a restart; null if it can't be resumed */
+  resume: unknown | null;
+}
+
+export interface ResumedJob {
+  interrupted_id: number;
+  /** Null if the same work was already queued */
+  job_id: number | null;
+  /** Partial output deleted before resuming */
+  changes: ChangeReport;
+}
+
+export interface WatchRuleOutcome {
+  folder: string;
+  processed: ProcessedAsset;
+  mesh: MeshHandle | null;
+}
+
+export type MaintenanceTask = 'verify_hashes' | 'refresh_thumbnails' | 'revalidate';
+
+export interface MaintenanceSchedule {
+  enabled: boolean;
+  /** Local 24-hour time, `HH:MM` */
+  time: string;
+  tasks: MaintenanceTask[];
+}
+
+export interface ChangedFile {
+  root: string;
+  asset_id: string;
+  path: string;
+}
+
+/** An asset's report fields, with the root it's stored in */
+export interface FailedValidation {
+  root: string;
+  id: string;
+  model_path: string | null;
+  score: number;
+  profile: string | null;
+  validation: {
+    profile_violations: string[] | null;
+    color_space_issues?: string[] | null;
+  };
+  error: string | null;
+  [field: string]: unknown;
+}
+
+/** Result of a `maintenance` job, also sent as a `maintenance-finished` event */
+export interface MaintenanceSummary {
+  tasks: MaintenanceTask[];
+  started_ms: number;
+  finished_ms: number;
+  asset_count: number;
+  offline_roots: string[];
+  intact_count: number;
+  unstamped_count: number;
+  changed_files: ChangedFile[];
+  refreshed_thumbnail_count: number;
+  /** Models whose thumbnail is missing or older than the model */
+  stale_thumbnails: string[];
+  validation_profile: string | null;
+  failed_validation: FailedValidation[];
+  errors: string[];
+}
+
+export type ChangeAction = 'create' | 'overwrite' | 'delete' | 'rename';
+
+export interface FileChange {
+  action: ChangeAction;
+  path: string;
+  to: string | null;
+  bytes: number | null;
+}
+
+/** What a mutating command changed, or would change when `dry_run` is set */
+export interface ChangeReport {
+  dry_run: boolean;
+  changes: FileChange[];
+}
+
+export interface ImportedMesh {
+  name: string;
+  mesh: MeshHandle;
+}
+
+export interface ImportedModel {
+  format: string;
+  conversion: AxisConversion;
+  meshes: ImportedMesh[];
+  triangulation: TriangulationReport;
+}
+
+export interface ExportedObj {
+  out_path: string;
+  mesh_count: number;
+  face_count: number;
+  changes: ChangeReport;
+}
+
+export interface ExportedSection {
+  out_path: string;
+  polyline_count: number;
+  closed_count: number;
+  width_mm: number;
+  height_mm: number;
+  changes: ChangeReport;
+}
+
+/** A model on the build plate, in Z-up millimeters */
+export interface PlateItem {
+  asset_id: string;
+  /** Center of the model's footprint on the plate */
+  position_mm: [number, number];
+  /** Counter-clockwise turn about Z, seen from above */
+  rotation_deg: number;
+  footprint_mm: [number, number];
+}
+
+export interface PlateLayout {
+  plate_mm: [number, number];
+  spacing_mm: number;
+  items: PlateItem[];
+  unplaced: string[];
+}
+
+export interface ExportedPlate {
+  out_path: string;
+  item_count: number;
+  triangle_count: number;
+  /** Items reaching past the edge of the plate */
+  off_plate: string[];
+  changes: ChangeReport;
+}
+
+export interface InstancedGlb {
+  out_path: string;
+  /** Meshes written once for several placements */
+  instanced_mesh_count: number;
+  instance_count: number;
+  /** Meshes written in world space, once per placement */
+  baked_mesh_count: number;
+  vertex_count: number;
+  /** Vertices a flattened export would have written */
+  flattened_vertex_count: number;
+  extensions: string[];
+  changes: ChangeReport;
+}
+
+export interface FlattenedGlb {
+  out_path: string;
+  /** Meshes written, each under its own top-level node */
+  mesh_count: number;
+  vertex_count: number;
+  face_count: number;
+  extensions: string[];
+  changes: ChangeReport;
+}
+
+export interface ExtractedNode {
+  out_path: string;
+  /** The extracted node and its descendants */
+  node_count: number;
+  mesh_count: number;
+  material_count: number;
+  texture_count: number;
+  /** Parts of the source that were left out, such as skins and animations */
+  dropped: string[];
+  changes: ChangeReport;
+}
+
+export type Engine = 'unity' | 'unreal';
+
+export interface EngineAsset {
+  asset_id: string;
+  folder: string;
+  /** The model and its companion files, without `.meta` stubs */
+  files: string[];
+}
+
+export interface EngineExport {
+  engine: Engine;
+  assets: EngineAsset[];
+  /** The Unreal import manifest, if one was written */
+  manifest_path: string | null;
+  changes: ChangeReport;
+}
+
+export interface ConvertedModel {
+  out_path: string;
+  format: string;
+  conversion: AxisConversion;
+  mesh_count: number;
+  vertex_count: number;
+  face_count: number;
+  triangulation: TriangulationReport;
+  /** Extensions carried over from a glTF source */
+  extensions: string[];
+  changes: ChangeReport;
+}
+
+export type DependencyKind = 'buffer' | 'image' | 'material_library' | 'texture';
+
+/** A file a model refers to */
+export interface Dependency {
+  kind: DependencyKind;
+  /** The reference as written in the file that uses it */
+  reference: string;
+  referenced_by: string;
+  path: string;
+  /** Path relative to the model's folder, if it lies inside it */
+  relative_path: string | null;
+  exists: boolean;
+  size_bytes: number | null;
+}
+
+/** A model copied into the storage layout with its dependencies */
+export interface StoredAsset {
+  id: string;
+  /** Where the model was copied */
+  asset_path: string;
+  changes: ChangeReport;
+}
+
+export interface ResolvedDependencies {
+  path: string;
+  dependencies: Dependency[];
+  missing_count: number;
+  stored: StoredAsset | null;
+}
+
+export type RelinkStatus = 'relinked' | 'copied' | 'ambiguous' | 'not_found';
+
+/** What happened to one missing dependency */
+export interface Relink {
+  kind: DependencyKind;
+  reference: string;
+  referenced_by: string;
+  status: RelinkStatus;
+  /** The file the dependency now resolves to */
+  found: string | null;
+  /** Reference written in place of the old one, when it changed */
+  new_reference: string | null;
+  /** Distinct files with the right name, when the match is ambiguous */
+  candidates: string[];
+}
+
+export interface RelinkReport {
+  path: string;
+  relinks: Relink[];
+  /** Dependencies still missing afterwards */
+  missing_count: number;
+  changes: ChangeReport;
+}
+
+/** Limits applied when parsing untrusted files; omitted fields use defaults */
+export interface ParseLimits {
+  max_file_bytes?: number;
+  max_json_bytes?: number;
+  max_heap_bytes?: number;
+  timeout_ms?: number;
+  max_accessors?: number;
+  max_buffer_views?: number;
+  max_buffers?: number;
+  max_meshes?: number;
+  max_primitives?: number;
+  max_nodes?: number;
+  max_materials?: number;
+  max_textures?: number;
+  max_images?: number;
+  max_accessor_elements?: number;
+}
+
+export interface InspectionReport {
+  file_size_bytes: number;
+  accessors: number;
+  meshes: number;
+  primitives: number;
+  nodes: number;
+  materials: number;
+  textures: number;
+  images: number;
+  vertex_count: number;
+  face_count: number;
+}
+
+export interface QuarantineVerdict {
+  path: string;
+  passed: boolean;
+  reasons: string[];
+  report: InspectionReport | null;
+  elapsed_ms: number;
+}
+
+export interface PromotionResult {
+  verdict: QuarantineVerdict;
+  destination: string | null;
+  changes: ChangeReport;
+}
+
+export type ScopeKind = 'app' | 'directory' | 'file';
+
+export interface ScopeEntry {
+  path: string;
+  kind: ScopeKind;
+  persistent: boolean;
+}
+
+export interface PlannedItem {
+  source: string;
+  output: string | null;
+}
+
+export type ItemProgressUpdate =
+  | { state: 'started'; temp_path: string | null }
+  | { state: 'completed' }
+  | { state: 'failed'; error: string };
+
+export type ItemStatus = 'pending' | 'partial' | 'completed' | 'failed';
+
+export interface RecoveredItem {
+  index: number;
+  source: string;
+  output: string | null;
+  status: ItemStatus;
+  error: string | null;
+  removed_temp: string | null;
+}
+
+export interface InterruptedOperation {
+  op_id: number;
+  kind: string;
+  started_at: number;
+  completed: number;
+  remaining: number;
+  items: RecoveredItem[];
+}
+
+export interface ResumePlan {
+  op_id: number;
+  kind: string;
+  items: [number, PlannedItem][];
+}
+
+export interface OperationMetrics {
+  name: string;
+  calls: number;
+  errors: number;
+  total_ms: number;
+  mean_ms: number;
+  min_ms: number;
+  max_ms: number;
+  last_ms: number;
+  peak_heap_bytes: number;
+}
+
+export interface PerformanceReport {
+  uptime_ms: number;
+  heap_bytes: number;
+  peak_heap_bytes: number;
+  operations: OperationMetrics[];
+}
+
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+export interface LogRecord {
+  time_ms: number;
+  level: LogLevel;
+  /** Module the message came from */
+  target: string;
+  message: string;
+  /** Spans the event happened in, outermost first, such as the command */
+  spans?: string[];
+  /** Fields of a tracing event besides its message */
+  fields?: Record<string, unknown>;
+}
+
+/** Outcome of a diagnostics check, from best to worst */
+export type CheckStatus = 'ok' | 'skipped' | 'warning' | 'error';
+
+export type CheckArea = 'gpu' | 'disk_space' | 'permissions' | 'index' | 'cache' | 'plugins';
+
+export interface DiagnosticsCheck {
+  area: CheckArea;
+  /** What was checked, such as `Storage root Archive` */
+  name: string;
+  status: CheckStatus;
+  message: string;
+  /** What to do about a warning or error */
+  hint: string | null;
+}
+
+export interface DiagnosticsReport {
+  created_ms: number;
+  /** Worst status of the checks */
+  status: CheckStatus;
+  checks: DiagnosticsCheck[];
+}
+
+export interface ExportedDebugBundle {
+  out_path: string;
+  /** Archive paths of the files in the bundle */
+  files: string[];
+  changes: ChangeReport;
+}
+
+export type ReportFormat = 'csv' | 'json';
+
+export interface ValidationProfile {
+  name: string;
+  description: string;
+  max_faces?: number | null;
+  max_vertices?: number | null;
+  /** Meshes, roughly the draw calls the asset costs */
+  max_meshes?: number | null;
+  max_materials?: number | null;
+  /** Largest texture side in pixels */
+  max_texture_size?: number | null;
+  max_file_size_bytes?: number | null;
+  require_normals: boolean;
+  require_uvs: boolean;
+}
+
+export interface ListedProfile extends ValidationProfile {
+  /** Shipped with the app rather than defined in the settings */
+  built_in: boolean;
+  overrides_built_in: boolean;
+}
+
+export interface ExportedReport {
+  out_path: string;
+  format: ReportFormat;
+  asset_count: number;
+  failed_count: number;
+  changes: ChangeReport;
+}
+
+export type ColorSpaceIssueKind =
+  | 'shared_across_color_spaces'
+  | 'srgb_tagged_data'
+  | 'linear_tagged_color'
+  | 'srgb_encoded_normals';
+
+export interface ColorSpaceIssue {
+  image: number;
+  /** The image's name or URI */
+  name: string;
+  kind: ColorSpaceIssueKind;
+  /** Material slots using the image, such as `base color` */
+  slots: string[];
+  fixable: boolean;
+}
+
+export interface FixedColorSpaces {
+  out_path: string;
+  issues: ColorSpaceIssue[];
+  fixed_count: number;
+  changes: ChangeReport;
+}
+
+export interface AssetUsageEntry {
+  path: string;
+  opens: number;
+  first_opened_ms: number;
+  last_opened_ms: number;
+}
+
+export interface OperationUsageEntry {
+  name: string;
+  calls: number;
+  failures: number;
+  total_ms: number;
+  mean_ms: number;
+  max_ms: number;
+}
+
+export interface LibrarySample {
+  day_ms: number;
+  asset_count: number;
+  total_bytes: number;
+}
+
+/** Usage statistics kept on this machine only */
+export interface UsageReport {
+  since_ms: number;
+  top_assets: AssetUsageEntry[];
+  operations: OperationUsageEntry[];
+  library: { folder: string; samples: LibrarySample[] }[];
+}
+
+export interface ComputeBackendInfo {
+  gpu_supported: boolean;
+  gpu_adapter: string | null;
+  active: string;
+}
+
+export interface NormalsResult {
+  vertex_count: number;
+  backend: string;
+  elapsed_ms: number;
+}
+
+export interface CurvatureResult {
+  values: number[];
+  min: number;
+  max: number;
+  mean: number;
+  backend: string;
+  elapsed_ms: number;
+}
+
+export interface VoxelizationResult {
+  resolution: [number, number, number];
+  origin: [number, number, number];
+  voxel_size: number;
+  occupied_count: number;
+  bits: number[];
+  backend: string;
+  elapsed_ms: number;
+}
+
+export interface ExportedVolume {
+  out_path: string;
+  resolution: [number, number, number];
+  voxel_size: number;
+  occupied_count: number;
+  /** 8×8×8 bricks with occupied voxels, the only ones written */
+  brick_count: number;
+  changes: ChangeReport;
+}
+
+export interface AoResult {
+  values: number[];
+  backend: string;
+  elapsed_ms: number;
+}
+
+export interface ReprojectedTextures {
+  out_paths: string[];
+  coverage: number;
+  changes: ChangeReport;
+}
+
+export type ImageChannel = 'red' | 'green' | 'blue' | 'alpha';
+
+export interface ChannelSource {
+  path: string;
+  /** Defaults to red; grayscale maps read the same in every channel */
+  channel?: ImageChannel;
+  /** Use one minus the value, e.g. roughness from a gloss map */
+  invert?: boolean;
+}
+
+export interface PackSpec {
+  out_path: string;
+  red?: ChannelSource;
+  green?: ChannelSource;
+  blue?: ChannelSource;
+  alpha?: ChannelSource;
+  /** Value of channels without a source, 0 to 1; white by default */
+  fill?: [number, number, number, number];
+  /** A .gltf whose occlusion and metallic-roughness textures should use the result */
+  model_path?: string;
+}
+
+export interface PackedTexture {
+  out_path: string;
+  width: number;
+  height: number;
+  /** Material textures pointed at the packed texture */
+  repointed_count: number;
+  changes: ChangeReport;
+}
+
+export interface UnpackedChannel {
+  channel: ImageChannel;
+  out_path: string;
+}
+
+export interface UnpackedTexture {
+  out_paths: string[];
+  changes: ChangeReport;
+}
+
+export type NormalSpace = 'tangent' | 'object';
+
+/** Green up (glTF, Blender, Unity) or down (Unreal, 3ds Max) */
+export type GreenConvention = 'opengl' | 'directx';
+
+export interface NormalMapFormat {
+  space?: NormalSpace;
+  /** Ignored for object-space maps */
+  convention?: GreenConvention;
+}
+
+export interface NormalMapOptions {
+  from?: NormalMapFormat;
+  to?: NormalMapFormat;
+}
+
+export interface ConvertedNormalMap {
+  out_path: string;
+  /** UV coverage of the mesh, when converting between spaces */
+  coverage: number | null;
+  changes: ChangeReport;
+}
+
+export interface EnhancedTexture {
+  out_path: string;
+  width: number;
+  height: number;
+  /** External tool that upscaled; null for the built-in filters */
+  upscaler: string | null;
+  /** An earlier result for the same file and options was reused */
+  cached: boolean;
+  changes: ChangeReport;
+}
+
+export interface BakedColorTexture {
+  mesh: MeshHandle;
+  out_path: string;
+  chart_count: number;
+  coverage: number;
+  changes: ChangeReport;
+}
+
+export interface ProcessedAsset {
+  asset_id: string;
+  profile: string;
+  out_path: string;
+  texture_paths: string[];
+  original_face_count: number;
+  face_count: number;
+  cleanup: StageReport[];
+  changes: ChangeReport;
+}
+
+export type ScanPreset = 'light' | 'standard' | 'aggressive';
+
+export type CleanupStage =
+  | 'remove_floaters'
+  | 'fill_holes'
+  | 'decimate'
+  | 'smooth'
+  | 'recenter'
+  | 'generate_normals'
+  | 'compress_textures';
+
+export interface StageReport {
+  stage: CleanupStage;
+  skipped: boolean;
+  detail: string;
+  vertex_count: number;
+  face_count: number;
+  elapsed_ms: number;
+}
+
+export interface ScanCleanup {
+  mesh: MeshHandle;
+  stages: StageReport[];
+}
+
+export type EstimatedOperation =
+  | { kind: 'simplify' }
+  | { kind: 'normals' }
+  | { kind: 'curvature' }
+  | { kind: 'voxelize'; resolution?: number; fill_interior?: boolean }
+  | { kind: 'ao_bake'; samples?: number; resolution?: number };
+
+export interface OperationEstimate {
+  operation: EstimatedOperation;
+  backend: string;
+  vertex_count: number;
+  face_count: number;
+  estimated_ms: number;
+  estimated_peak_bytes: number;
+  /** Expected to take over a minute */
+  long_running: boolean;
+}
+
+// Dynamic import for Tauri API (only available in Tauri environment)
+async function invoke<T>(command: string, args?: Record<string, unknown>): Promise<T> {
+  if (!isTauri()) {
+    throw new Error('Tauri commands are only available in the Tauri environment');
+  }
+
+  // Dynamic import to avoid errors in browser environment
+  const { invoke: tauriInvoke } = await import('@tauri-apps/api/core');
+  return tauriInvoke<T>(command, args).catch(localizeError);
+}
+
+// Raw binary invoke with headers (skips JSON serialization entirely)
+async function invokeRaw<T>(
+  command: string,
+  body: Uint8Array,
+  headers: Record<string, string>
+): Promise<T> {
+  if (!isTauri()) {
+    throw new Error('Tauri commands are only available in the Tauri environment');
+  }
+
+  const { invoke: tauriInvoke } = await import('@tauri-apps/api/core');
+  return tauriInvoke<T>(command, body, { headers }).catch(localizeError);
+}
+
+// Commands fail with English messages; rethrow them in the language of the settings
+async function localizeError(error: unknown): Promise<never> {
+  if (typeof error !== 'string') {
+    throw error;
+  }
+  const { invoke: tauriInvoke } = await import('@tauri-apps/api/core');
+  throw await tauriInvoke<string>('localize_message', { message: error }).catch(() => error);
+}
+
+// 4MB per IPC message keeps each call well below webview limits
+const MESH_CHUNK_BYTES = 4 * 1024 * 1024;
+
+/**
+ * Model Loading Commands
+ */
+export const modelCommands = {
+  /**
+   * Analyze a 3D model and return detailed statistics
+   * Uses native Rust for 10x+ faster analysis than JavaScript
+   */
+  analyzeModel: async (path: string): Promise<ModelAnalysis> => {
+    return invoke<ModelAnalysis>('analyze_model', { path });
+  },
+
+  /**
+   * Load raw model data as bytes
+   * Uses memory-mapped files for efficient streaming
+   */
+  loadModelData: async (path: string): Promise<Uint8Array> => {
+    const data = await invoke<number[]>('load_model_data', { path });
+    return new Uint8Array(data);
+  },
+
+  /**
+   * Get just the bounding box of a model (fast operation)
+   */
+  getModelBounds: async (path: string): Promise<BoundingBox> => {
+    return invoke<BoundingBox>('get_model_bounds', { path });
+  },
+
+  /**
+   * Node hierarchy of a GLB/GLTF model with its lights and cameras
+   */
+  getSceneGraph: async (path: string): Promise<SceneGraph> => {
+    return invoke<SceneGraph>('get_scene_graph', { path });
+  },
+};
+
+/**
+ * Mesh Processing Commands
+ */
+export const meshCommands = {
+  /**
+   * Generate LOD (Level of Detail) versions of a mesh
+   * Uses native meshoptimizer for high-quality decimation
+   */
+  generateLod: async (
+    vertices: Float32Array,
+    indices: Uint32Array,
+    targetRatios: number[]
+  ): Promise<LodResult> => {
+    return invoke<LodResult>('generate_lod', {
+      vertices: Array.from(vertices),
+      indices: Array.from(indices),
+      target_ratios: targetRatios,
+    });
+  },
+
+  /**
+   * Generate LOD levels that stay under a pixel error at the given distances
+   *
+   * Levels are returned as mesh handles with the distance to switch to each.
+   */
+  generateScreenSpaceLod: async (
+    meshHandle: number,
+    screenSpace: ScreenSpaceLod
+  ): Promise<LodResult> => {
+    return invoke<LodResult>('generate_lod', {
+      mesh_handle: meshHandle,
+      screen_space: screenSpace,
+    });
+  },
+
+  /**
+   * Generate LOD levels for a skinned mesh, merging joint influences
+   *
+   * The mesh must have been uploaded with joints and weights.
+   */
+  generateSkinnedLod: async (
+    meshHandle: number,
+    targetRatios: number[],
+    preserveSeams?: boolean
+  ): Promise<LodResult> => {
+    return invoke<LodResult>('generate_skinned_lod', {
+      mesh_handle: meshHandle,
+      target_ratios: targetRatios,
+      preserve_seams: preserveSeams,
+    });
+  },
+
+  /**
+   * Simplify a mesh while keeping weighted or protected regions detailed
+   *
+   * `weights` has one value per vertex in [0, 1]; 1 (or listing the vertex in
+   * `protectedVertices`) means it is never merged. `preserveSeams` locks UV
+   * seams and `faceMaterials` (one id per face) locks material boundaries.
+   */
+  simplifyMesh: async (
+    meshHandle: number,
+    targetRatio: number,
+    weights?: number[],
+    protectedVertices?: number[],
+    preserveSeams?: boolean,
+    faceMaterials?: number[]
+  ): Promise<SimplifiedMesh> => {
+    return invoke<SimplifiedMesh>('simplify_mesh', {
+      mesh_handle: meshHandle,
+      target_ratio: targetRatio,
+      weights,
+      protected_vertices: protectedVertices,
+      preserve_seams: preserveSeams,
+      face_materials: faceMaterials,
+    });
+  },
+
+  /**
+   * Weld vertices that match in every attribute and rebuild the index buffer
+   *
+   * A tolerance of 0 only welds bit-identical vertices. Replaces the mesh
+   * behind the handle.
+   */
+  reindexMesh: async (meshHandle: number, tolerance: number): Promise<ReindexResult> => {
+    return invoke<ReindexResult>('reindex_mesh', {
+      mesh_handle: meshHandle,
+      tolerance,
+    });
+  },
+
+  /**
+   * Clean up NaN and out-of-range data, degenerate faces and slivers,
+   * replacing the mesh in place
+   */
+  repairMesh: async (meshHandle: number, options?: RepairOptions): Promise<RepairResult> => {
+    return invoke<RepairResult>('repair_mesh', {
+      mesh_handle: meshHandle,
+      options,
+    });
+  },
+
+  /**
+   * Cut a mesh with a plane into polylines in plane coordinates
+   */
+  sliceMesh: async (meshHandle: number, plane: SectionPlane): Promise<Section> => {
+    return invoke<Section>('slice_mesh', {
+      mesh_handle: meshHandle,
+      plane,
+    });
+  },
+
+  /**
+   * Check a mesh for 3D printing and estimate print time and material
+   */
+  printabilityReport: async (
+    meshHandle: number,
+    settings?: PrintSettings
+  ): Promise<PrintabilityReport> => {
+    return invoke<PrintabilityReport>('printability_report', {
+      mesh_handle: meshHandle,
+      settings,
+    });
+  },
+
+  /**
+   * Rank orientations to 3D print a mesh in, best first
+   */
+  suggestPrintOrientation: async (
+    meshHandle: number,
+    criteria?: OrientationCriteria
+  ): Promise<PrintOrientation[]> => {
+    return invoke<PrintOrientation[]>('suggest_print_orientation', {
+      mesh_handle: meshHandle,
+      criteria,
+    });
+  },
+
+  /**
+   * Find the dominant mirror plane and the regions that break symmetry
+   */
+  detectSymmetry: async (meshHandle: number, tolerance?: number): Promise<SymmetryReport> => {
+    return invoke<SymmetryReport>('detect_symmetry', {
+      mesh_handle: meshHandle,
+      tolerance,
+    });
+  },
+
+  /**
+   * Mirror a mesh into a new handle
+   *
+   * `symmetrize` rebuilds the half behind the plane from the front half;
+   * `flip` mirrors the whole mesh.
+   */
+  mirrorMesh: async (
+    meshHandle: number,
+    plane: MirrorPlane,
+    mode?: MirrorMode,
+    tolerance?: number
+  ): Promise<MeshHandle> => {
+    return invoke<MeshHandle>('mirror_mesh', {
+      mesh_handle: meshHandle,
+      plane,
+      mode,
+      tolerance,
+    });
+  },
+
+  /**
+   * Scale a mesh in place so two picked points are `realDistance` meters apart
+   */
+  scaleToMeasurement: async (
+    meshHandle: number,
+    pointA: [number, number, number],
+    pointB: [number, number, number],
+    realDistance: number
+  ): Promise<MeasuredScale> => {
+    return invoke<MeasuredScale>('scale_to_measurement', {
+      mesh_handle: meshHandle,
+      point_a: pointA,
+      point_b: pointB,
+      real_distance: realDistance,
+    });
+  },
+
+  /**
+   * Hollow a closed mesh for resin printing into a new handle
+   */
+  hollowMesh: async (meshHandle: number, wallThickness: number): Promise<MeshHandle> => {
+    return invoke<MeshHandle>('hollow_mesh', {
+      mesh_handle: meshHandle,
+      wall_thickness: wallThickness,
+    });
+  },
+
+  /**
+   * Offset a mesh's surface by `distance` (positive grows it) into a new
+   * handle; `shell` mode keeps the original too, as a solid wall
+   */
+  offsetMesh: async (
+    meshHandle: number,
+    distance: number,
+    mode?: OffsetMode
+  ): Promise<MeshHandle> => {
+    return invoke<MeshHandle>('offset_mesh', { mesh_handle: meshHandle, distance, mode });
+  },
+
+  /**
+   * Merge every copy of a linear, radial or grid array into a new handle
+   */
+  arrayMesh: async (meshHandle: number, pattern: ArrayPattern): Promise<MeshHandle> => {
+    return invoke<MeshHandle>('array_mesh', { mesh_handle: meshHandle, pattern });
+  },
+
+  /**
+   * Drill drain holes through a hollowed mesh into a new handle
+   *
+   * Each hole starts at the surface point closest to one of `positions`.
+   */
+  addDrainHoles: async (
+    meshHandle: number,
+    positions: [number, number, number][],
+    radius: number
+  ): Promise<MeshHandle> => {
+    return invoke<MeshHandle>('add_drain_holes', {
+      mesh_handle: meshHandle,
+      positions,
+      radius,
+    });
+  },
+
+  /**
+   * Delete the selected faces into a new handle
+   */
+  deleteFaces: async (meshHandle: number, selection: MeshSelection): Promise<MeshHandle> => {
+    return invoke<MeshHandle>('delete_faces', { mesh_handle: meshHandle, selection });
+  },
+
+  /**
+   * Split the selected faces off; both parts get new handles
+   */
+  separateSelection: async (
+    meshHandle: number,
+    selection: MeshSelection
+  ): Promise<SeparatedMesh> => {
+    return invoke<SeparatedMesh>('separate_selection', { mesh_handle: meshHandle, selection });
+  },
+
+  /**
+   * Keep the faces inside a box, or inside the box around a selection, into a new handle
+   */
+  cropMesh: async (
+    meshHandle: number,
+    region: { bounds: CropBox } | { selection: MeshSelection }
+  ): Promise<MeshHandle> => {
+    return invoke<MeshHandle>('crop_mesh', { mesh_handle: meshHandle, ...region });
+  },
+
+  /**
+   * Cut a mesh in two along a plane; with `cap` (default true) the cut is
+   * filled on both halves
+   */
+  planeCut: async (
+    meshHandle: number,
+    plane: SectionPlane,
+    cap?: boolean
+  ): Promise<PlaneCutResult> => {
+    return invoke<PlaneCutResult>('plane_cut', { mesh_handle: meshHandle, plane, cap });
+  },
+
+  /**
+   * Project a template mesh onto a target surface into a new handle
+   *
+   * Each of the 1 to 100 iterations relaxes the template by `smoothing`
+   * (0 to 1, default 0.5) and snaps it to the target.
+   */
+  shrinkwrap: async (
+    sourceHandle: number,
+    targetHandle: number,
+    iterations: number,
+    smoothing?: number
+  ): Promise<ShrinkwrapResult> => {
+    return invoke<ShrinkwrapResult>('shrinkwrap', {
+      source_handle: sourceHandle,
+      target_handle: targetHandle,
+      iterations,
+      smoothing,
+    });
+  },
+
+  /**
+   * Signed per-vertex deviation of mesh B from mesh A for a heatmap, with
+   * vertices that moved by at least `resolution` grouped into regions
+   */
+  diffMeshesSpatial: async (
+    aHandle: number,
+    bHandle: number,
+    resolution?: number
+  ): Promise<SpatialDiff> => {
+    return invoke<SpatialDiff>('diff_meshes_spatial', {
+      a_handle: aHandle,
+      b_handle: bHandle,
+      resolution,
+    });
+  },
+
+  /**
+   * Replace a mesh's UVs with a planar, cylindrical or box projection
+   */
+  projectUvs: async (
+    meshHandle: number,
+    projection: UvProjection,
+    scale?: number
+  ): Promise<ProjectedUvs> => {
+    return invoke<ProjectedUvs>('project_uvs', {
+      mesh_handle: meshHandle,
+      projection,
+      scale,
+    });
+  },
+
+  /**
+   * Lay out a non-overlapping second UV set for baked lighting
+   * Keeps the mesh's own UVs; padding is in texels of a resolution² lightmap
+   */
+  generateLightmapUvs: async (
+    meshHandle: number,
+    padding?: number,
+    resolution?: number
+  ): Promise<LightmapUvs> => {
+    return invoke<LightmapUvs>('generate_lightmap_uvs', {
+      mesh_handle: meshHandle,
+      padding,
+      resolution,
+    });
+  },
+
+  /**
+   * Inspect UV bounds, texel density, overlaps and flipped faces
+   */
+  analyzeUvs: async (
+    meshHandle: number,
+    uvSet?: UvSet,
+    textureSize?: number
+  ): Promise<UvAnalysis> => {
+    return invoke<UvAnalysis>('analyze_uvs', {
+      mesh_handle: meshHandle,
+      uv_set: uvSet,
+      texture_size: textureSize,
+    });
+  },
+
+  /**
+   * Rescale UV islands to one texel density (texels per cm, positions in meters)
+   * Rescaled islands may overlap afterwards and need packing again
+   */
+  normalizeTexelDensity: async (
+    meshHandle: number,
+    texelsPerCm: number,
+    textureSize?: number,
+    uvSet?: UvSet,
+    tolerance?: number
+  ): Promise<NormalizedTexelDensity> => {
+    return invoke<NormalizedTexelDensity>('normalize_texel_density', {
+      mesh_handle: meshHandle,
+      texels_per_cm: texelsPerCm,
+      texture_size: textureSize,
+      uv_set: uvSet,
+      tolerance,
+    });
+  },
+
+  /**
+   * Render the UV layout as an image for the inspector panel
+   * Overlapping faces are red, flipped faces orange
+   */
+  renderUvLayout: async (
+    meshHandle: number,
+    format: UvRenderFormat = 'svg',
+    size?: number,
+    uvSet?: UvSet
+  ): Promise<Blob> => {
+    const data = await invoke<number[]>('render_uv_layout', {
+      mesh_handle: meshHandle,
+      uv_set: uvSet,
+      format,
+      size,
+    });
+    return new Blob([new Uint8Array(data)], {
+      type: format === 'svg' ? 'image/svg+xml' : 'image/png',
+    });
+  },
+
+  /**
+   * Optimize mesh for GPU rendering
+   * Performs vertex cache and overdraw optimization
+   */
+  optimizeMesh: async (
+    vertices: Float32Array,
+    indices: Uint32Array
+  ): Promise<OptimizedMeshResult> => {
+    return invoke<OptimizedMeshResult>('optimize_mesh', {
+      vertices: Array.from(vertices),
+      indices: Array.from(indices),
+    });
+  },
+
+  /**
+   * Calculate detailed mesh statistics
+   */
+  calculateMeshStats: async (
+    vertices: Float32Array,
+    indices: Uint32Array
+  ): Promise<MeshStats> => {
+    return invoke<MeshStats>('calculate_mesh_stats', {
+      vertices: Array.from(vertices),
+      indices: Array.from(indices),
+    });
+  },
+
+  /**
+   * Per-mesh and total statistics for a GLB/GLTF, OBJ, STL or binary FBX file
+   */
+  calculateFileStats: async (path: string): Promise<FileStats> => {
+    return invoke<FileStats>('calculate_file_stats', { path });
+  },
+};
+
+/**
+ * Chunked Mesh Upload Commands
+ */
+export const meshUploadCommands = {
+  /**
+   * Upload geometry to the backend in binary chunks and get a mesh handle
+   * Pass the handle to mesh commands instead of large vertex arrays
+   * `polygons` holds the corner count of each source polygon for quad meshes
+   * `colors` holds linear RGBA per vertex, `lightmapUvs` a second UV set
+   */
+  uploadMesh: async (
+    positions: Float32Array,
+    indices?: Uint32Array,
+    normals?: Float32Array,
+    uvs?: Float32Array,
+    skin?: SkinAttributes,
+    polygons?: Uint32Array,
+    colors?: Float32Array,
+    lightmapUvs?: Float32Array
+  ): Promise<MeshHandle> => {
+    const attributes: [string, Float32Array | Uint32Array | undefined][] = [
+      ['positions', positions],
+      ['indices', indices],
+      ['normals', normals],
+      ['uvs', uvs],
+      ['lightmap_uvs', lightmapUvs],
+      ['colors', colors],
+      ['joints', skin?.joints],
+      ['weights', skin?.weights],
+      ['polygons', polygons],
+    ];
+    const expectedBytes = attributes.reduce(
+      (total, [, data]) => total + (data ? data.byteLength : 0),
+      0
+    );
+
+    const uploadId = await invoke<number>('begin_mesh_upload', {
+      expected_bytes: expectedBytes,
+    });
+
+    try {
+      for (const [attribute, data] of attributes) {
+        if (!data) continue;
+        const bytes = new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+        for (let offset = 0; offset < bytes.byteLength; offset += MESH_CHUNK_BYTES) {
+          await invokeRaw<number>(
+            'append_mesh_chunk',
+            bytes.subarray(offset, offset + MESH_CHUNK_BYTES),
+            { 'x-upload-id': String(uploadId), 'x-attribute': attribute }
+          );
+        }
+      }
+      return await invoke<MeshHandle>('finish_mesh_upload', { upload_id: uploadId });
+    } catch (error) {
+      await invoke<boolean>('cancel_mesh_upload', { upload_id: uploadId }).catch(() => false);
+      throw error;
+    }
+  },
+
+  /**
+   * Free a mesh handle on the backend
+   */
+  releaseMesh: async (meshHandle: number): Promise<boolean> => {
+    return invoke<boolean>('release_mesh', { mesh_handle: meshHandle });
+  },
+};
+
+/**
+ * Model Import and Export Commands
+ */
+export const importCommands = {
+  /**
+   * Import every mesh of a GLB/GLTF, OBJ, STL or binary FBX file as Y-up meters
+   * Node transforms are baked in; `conversion` and `triangulation` override
+   * the file and settings
+   */
+  importModel: async (
+    path: string,
+    conversion?: AxisConversion,
+    triangulation?: TriangulationOptions
+  ): Promise<ImportedModel> => {
+    return invoke<ImportedModel>('import_model', { path, conversion, triangulation });
+  },
+
+  /**
+   * Convert a model file to GLB with the same conversion as `importModel`
+   * glTF lights and cameras are kept unless `keepLightsAndCameras` is false
+   */
+  convertToGlb: async (
+    path: string,
+    outPath: string,
+    conversion?: AxisConversion,
+    triangulation?: TriangulationOptions,
+    dryRun?: boolean,
+    keepLightsAndCameras?: boolean
+  ): Promise<ConvertedModel> => {
+    return invoke<ConvertedModel>('convert_to_glb', {
+      path,
+      out_path: outPath,
+      conversion,
+      triangulation,
+      keep_lights_and_cameras: keepLightsAndCameras,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * List the buffers, material libraries and textures a model refers to;
+   * with a storage folder, also copy the model and them into its layout
+   */
+  resolveDependencies: async (
+    path: string,
+    storagePath?: string,
+    assetId?: string,
+    dryRun?: boolean
+  ): Promise<ResolvedDependencies> => {
+    return invoke<ResolvedDependencies>('resolve_dependencies', {
+      path,
+      storage_path: storagePath,
+      asset_id: assetId,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Find a model's missing dependencies under the given folders by name and
+   * rewrite its references to them
+   */
+  relinkDependencies: async (
+    path: string,
+    searchDirs: string[],
+    dryRun?: boolean
+  ): Promise<RelinkReport> => {
+    return invoke<RelinkReport>('relink_dependencies', {
+      path,
+      search_dirs: searchDirs,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Write meshes to an OBJ file, keeping imported quads and n-gons by default
+   */
+  exportObj: async (
+    meshHandles: number[],
+    outPath: string,
+    preservePolygons?: boolean,
+    dryRun?: boolean
+  ): Promise<ExportedObj> => {
+    return invoke<ExportedObj>('export_obj', {
+      mesh_handles: meshHandles,
+      out_path: outPath,
+      preserve_polygons: preservePolygons,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Write a cross-section to an SVG or DXF file (by extension), in millimeters
+   */
+  exportSection: async (
+    meshHandle: number,
+    plane: SectionPlane,
+    outPath: string,
+    dryRun?: boolean
+  ): Promise<ExportedSection> => {
+    return invoke<ExportedSection>('export_section', {
+      mesh_handle: meshHandle,
+      plane,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Arrange model files on a build plate; the layout can be edited before export
+   */
+  packBuildPlate: async (
+    assetIds: string[],
+    plateDimensions: [number, number],
+    spacing?: number
+  ): Promise<PlateLayout> => {
+    return invoke<PlateLayout>('pack_build_plate', {
+      asset_ids: assetIds,
+      plate_dimensions: plateDimensions,
+      spacing,
+    });
+  },
+
+  /**
+   * Write a build plate layout to an STL or 3MF file (by extension)
+   */
+  exportBuildPlate: async (
+    layout: PlateLayout,
+    outPath: string,
+    dryRun?: boolean
+  ): Promise<ExportedPlate> => {
+    return invoke<ExportedPlate>('export_build_plate', {
+      layout,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Write a glTF scene to GLB with repeated meshes as GPU instances
+   * (EXT_mesh_gpu_instancing); meshes placed fewer than `minInstances` times are baked
+   */
+  exportInstancedGlb: async (
+    path: string,
+    outPath: string,
+    minInstances?: number,
+    rights?: RightsEdit,
+    dryRun?: boolean
+  ): Promise<InstancedGlb> => {
+    return invoke<InstancedGlb>('export_instanced_glb', {
+      path,
+      out_path: outPath,
+      min_instances: minInstances,
+      rights,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Write an array of a mesh to GLB, the mesh once with a GPU instance per copy
+   */
+  exportArrayGlb: async (
+    meshHandle: number,
+    pattern: ArrayPattern,
+    outPath: string,
+    dryRun?: boolean
+  ): Promise<InstancedGlb> => {
+    return invoke<InstancedGlb>('export_array_glb', {
+      mesh_handle: meshHandle,
+      pattern,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Write a model to GLB with every transform baked in and no hierarchy
+   * glTF scenes get one mesh per material unless `mergeByMaterial` is false,
+   * keeping materials, textures, normals and UVs
+   */
+  exportFlattenedGlb: async (
+    path: string,
+    outPath: string,
+    mergeByMaterial?: boolean,
+    rights?: RightsEdit,
+    dryRun?: boolean
+  ): Promise<FlattenedGlb> => {
+    return invoke<FlattenedGlb>('export_flattened_glb', {
+      path,
+      out_path: outPath,
+      merge_by_material: mergeByMaterial,
+      rights,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Write one node of a glTF scene and everything below it to a new GLB
+   * `node` is a path of names from a root (`Level/Props/Crate`) or a unique name
+   */
+  extractNode: async (
+    path: string,
+    node: string,
+    outPath: string,
+    rights?: RightsEdit,
+    dryRun?: boolean
+  ): Promise<ExtractedNode> => {
+    return invoke<ExtractedNode>('extract_node', {
+      path,
+      node,
+      out_path: outPath,
+      rights,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Change the copyright and license recorded in a glTF or GLB file
+   */
+  setAssetRights: async (
+    path: string,
+    rights: RightsEdit,
+    dryRun?: boolean
+  ): Promise<EditedRights> => {
+    return invoke<EditedRights>('set_asset_rights', {
+      path,
+      rights,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Embed a provenance record in a glTF or GLB file, or add to its record;
+   * `importPath` is hashed as the file the asset was imported from
+   */
+  stampProvenance: async (
+    path: string,
+    stamp: ProvenanceStamp,
+    importPath?: string,
+    dryRun?: boolean
+  ): Promise<StampedProvenance> => {
+    return invoke<StampedProvenance>('stamp_provenance', {
+      path,
+      stamp,
+      import_path: importPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * A file's provenance record and whether the file changed since it was embedded
+   */
+  verifyProvenance: async (path: string): Promise<ProvenanceCheck> => {
+    return invoke<ProvenanceCheck>('verify_provenance', { path });
+  },
+
+  /**
+   * Write a folder for the web: a Draco and KTX2 compressed model.glb,
+   * poster.png and an index.html viewer. The poster defaults to the stored
+   * thumbnail; compression needs the gltf-transform CLI
+   */
+  exportWebViewer: async (
+    assetId: string,
+    outDir: string,
+    posterPng?: Uint8Array,
+    dryRun?: boolean
+  ): Promise<WebViewerExport> => {
+    return invoke<WebViewerExport>('export_web_viewer', {
+      asset_id: assetId,
+      out_dir: outDir,
+      poster_png: posterPng ? Array.from(posterPng) : undefined,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * The local API for plugins in other tools, or null if it isn't running
+   */
+  getLocalApi: async (): Promise<LocalApi | null> => {
+    return invoke<LocalApi | null>('get_local_api');
+  },
+
+  /**
+   * Turn the local API on or off; kept in the settings across restarts
+   */
+  setLocalApi: async (enabled: boolean): Promise<LocalApi | null> => {
+    return invoke<LocalApi | null>('set_local_api', { enabled });
+  },
+
+  /**
+   * Serve a GLB or USDZ model (and its other-format sibling) on the LAN
+   * for a phone to open in AR from the returned QR code; stops after
+   * `minutes` (default 15) with an `ar-preview-stopped` event
+   */
+  startArPreview: async (assetId: string, minutes?: number): Promise<ArPreview> => {
+    return invoke<ArPreview>('start_ar_preview', { asset_id: assetId, minutes });
+  },
+
+  /**
+   * Stop serving the AR preview; false if none was running
+   */
+  stopArPreview: async (): Promise<boolean> => {
+    return invoke<boolean>('stop_ar_preview');
+  },
+
+  /**
+   * The animation clips of a glTF or GLB file
+   */
+  listAnimations: async (path: string): Promise<ClipInfo[]> => {
+    return invoke<ClipInfo[]>('list_animations', { path });
+  },
+
+  /**
+   * Cut one clip down to a frame range, starting at zero; written as GLB
+   */
+  trimAnimation: async (
+    path: string,
+    animationIndex: number,
+    range: FrameRange,
+    outPath: string,
+    dryRun?: boolean
+  ): Promise<EditedAnimations> => {
+    return invoke<EditedAnimations>('trim_animation', {
+      path,
+      animation_index: animationIndex,
+      range,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Split one clip into a clip per marker, by default the clip's stored
+   * `extras.markers`
+   */
+  splitAnimation: async (
+    path: string,
+    animationIndex: number,
+    markers: AnimationMarker[] | undefined,
+    outPath: string,
+    dryRun?: boolean
+  ): Promise<EditedAnimations> => {
+    return invoke<EditedAnimations>('split_animation', {
+      path,
+      animation_index: animationIndex,
+      markers,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Append the clips of other files, matched to the target's nodes by name
+   */
+  mergeAnimations: async (
+    path: string,
+    sources: string[],
+    outPath: string,
+    dryRun?: boolean
+  ): Promise<EditedAnimations> => {
+    return invoke<EditedAnimations>('merge_animations', {
+      path,
+      sources,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Write the meshes as posed by one clip at `time` seconds to a static
+   * GLB, with skinning and morph targets applied
+   */
+  bakePose: async (
+    path: string,
+    animationIndex: number,
+    time: number,
+    outPath: string,
+    dryRun?: boolean
+  ): Promise<BakedPoseExport> => {
+    return invoke<BakedPoseExport>('bake_pose', {
+      path,
+      animation_index: animationIndex,
+      time,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Whether a character's rest pose is a T-pose or an A-pose
+   */
+  analyzeRestPose: async (path: string): Promise<RestPoseAnalysis> => {
+    return invoke<RestPoseAnalysis>('analyze_rest_pose', { path });
+  },
+
+  /**
+   * Rotate the upper arms into a T-pose or A-pose rest pose, adjusting the
+   * skinned vertices and bind matrices; written as GLB
+   */
+  convertRestPose: async (
+    path: string,
+    target: 't_pose' | 'a_pose',
+    outPath: string,
+    dryRun?: boolean
+  ): Promise<ConvertedRestPose> => {
+    return invoke<ConvertedRestPose>('convert_rest_pose', {
+      path,
+      target,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Influences per vertex, weight sums that are off and unused joints of a
+   * character's skinned meshes
+   */
+  analyzeWeights: async (path: string): Promise<WeightReport> => {
+    return invoke<WeightReport>('analyze_weights', { path });
+  },
+
+  /**
+   * Keep each vertex's `maxInfluences` heaviest bones and renormalize;
+   * written as GLB
+   */
+  limitInfluences: async (
+    path: string,
+    maxInfluences: number,
+    outPath: string,
+    dryRun?: boolean
+  ): Promise<EditedWeights> => {
+    return invoke<EditedWeights>('limit_influences', {
+      path,
+      max_influences: maxInfluences,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Remove joints no vertex is weighted to from the skins, except those
+   * above used joints; written as GLB
+   */
+  pruneUnusedBones: async (
+    path: string,
+    outPath: string,
+    dryRun?: boolean
+  ): Promise<EditedWeights> => {
+    return invoke<EditedWeights>('prune_unused_bones', {
+      path,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Copy models into a folder per model, with Unity `.meta` stubs or an
+   * Unreal `import_manifest.json` for the ImportAssets commandlet
+   */
+  exportForEngine: async (
+    assetIds: string[],
+    engine: Engine,
+    outDir: string,
+    dryRun?: boolean
+  ): Promise<EngineExport> => {
+    return invoke<EngineExport>('export_for_engine', {
+      asset_ids: assetIds,
+      engine,
+      out_dir: outDir,
+      dry_run: dryRun,
+    });
+  },
+};
+
+/**
+ * Shared-File Mesh Transport Commands
+ */
+export const transportCommands = {
+  /**
+   * Ask the backend which mesh transports this platform supports
+   */
+  negotiateMeshTransport: async (): Promise<TransportCapabilities> => {
+    return invoke<TransportCapabilities>('negotiate_mesh_transport');
+  },
+
+  /**
+   * Write a backend mesh to a memory-mapped handoff file
+   * Read it with the fs plugin and view sections as typed arrays in place
+   * `options.primitive` picks a cache-optimized list or triangle strips
+   */
+  exportMeshBuffer: async (
+    meshHandle: number,
+    options?: BufferExportOptions
+  ): Promise<MeshBufferHandoff> => {
+    return invoke<MeshBufferHandoff>('export_mesh_buffer', {
+      mesh_handle: meshHandle,
+      options,
+    });
+  },
+
+  /**
+   * Register a mesh from a handoff file the webview wrote
+   */
+  importMeshBuffer: async (path: string, layout: BufferLayout): Promise<MeshHandle> => {
+    return invoke<MeshHandle>('import_mesh_buffer', { path, layout });
+  },
+
+  /**
+   * Delete a handoff file once it has been consumed
+   */
+  releaseMeshBuffer: async (path: string): Promise<boolean> => {
+    return invoke<boolean>('release_mesh_buffer', { path });
+  },
+};
+
+/**
+ * Mesh Processing Backend Commands (GPU with CPU fallback)
+ */
+export const processingCommands = {
+  /**
+   * Report GPU availability and the backend currently in use
+   */
+  getComputeBackends: async (): Promise<ComputeBackendInfo> => {
+    return invoke<ComputeBackendInfo>('get_compute_backends');
+  },
+
+  /**
+   * Recompute smooth normals and store them on the mesh
+   */
+  generateNormals: async (meshHandle: number): Promise<NormalsResult> => {
+    return invoke<NormalsResult>('generate_normals', { mesh_handle: meshHandle });
+  },
+
+  /**
+   * Per-vertex signed mean curvature
+   */
+  computeCurvature: async (meshHandle: number): Promise<CurvatureResult> => {
+    return invoke<CurvatureResult>('compute_curvature', { mesh_handle: meshHandle });
+  },
+
+  /**
+   * Voxelize a mesh into an occupancy bitset
+   */
+  voxelizeMesh: async (
+    meshHandle: number,
+    resolution?: number,
+    fillInterior?: boolean
+  ): Promise<VoxelizationResult> => {
+    return invoke<VoxelizationResult>('voxelize_mesh', {
+      mesh_handle: meshHandle,
+      resolution,
+      fill_interior: fillInterior,
+    });
+  },
+
+  /**
+   * Voxelize a mesh and write it as a sparse volume of 8³ bricks for VFX
+   * tools; the interior is filled unless `fillInterior` is false
+   */
+  exportVolume: async (
+    meshHandle: number,
+    outPath: string,
+    resolution?: number,
+    fillInterior?: boolean,
+    dryRun?: boolean
+  ): Promise<ExportedVolume> => {
+    return invoke<ExportedVolume>('export_volume', {
+      mesh_handle: meshHandle,
+      out_path: outPath,
+      resolution,
+      fill_interior: fillInterior,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Bake per-vertex ambient occlusion
+   */
+  bakeVertexAo: async (
+    meshHandle: number,
+    samples?: number,
+    resolution?: number
+  ): Promise<AoResult> => {
+    return invoke<AoResult>('bake_vertex_ao', { mesh_handle: meshHandle, samples, resolution });
+  },
+
+  /**
+   * Sample a texture into the mesh's vertex colors, for viewing without textures
+   */
+  bakeTextureToVertexColors: async (
+    meshHandle: number,
+    texturePath: string
+  ): Promise<MeshHandle> => {
+    return invoke<MeshHandle>('bake_texture_to_vertex_colors', {
+      mesh_handle: meshHandle,
+      texture_path: texturePath,
+    });
+  },
+
+  /**
+   * Bake a mesh's textures onto a remeshed copy's UVs, written beside each original
+   */
+  reprojectTextures: async (
+    sourceHandle: number,
+    targetHandle: number,
+    texturePaths: string[],
+    resolution?: number
+  ): Promise<ReprojectedTextures> => {
+    return invoke<ReprojectedTextures>('reproject_textures', {
+      source_handle: sourceHandle,
+      target_handle: targetHandle,
+      texture_paths: texturePaths,
+      resolution,
+    });
+  },
+
+  /**
+   * Unwrap a vertex-colored mesh and write it as a GLB with its colors baked to a texture
+   */
+  bakeVertexColorsToTexture: async (
+    meshHandle: number,
+    outPath: string,
+    resolution?: number,
+    dryRun?: boolean
+  ): Promise<BakedColorTexture> => {
+    return invoke<BakedColorTexture>('bake_vertex_colors_to_texture', {
+      mesh_handle: meshHandle,
+      out_path: outPath,
+      resolution,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Pack channels of grayscale maps into one texture, such as an ORM map,
+   * optionally pointing a .gltf's materials at it
+   */
+  packTextureChannels: async (spec: PackSpec, dryRun?: boolean): Promise<PackedTexture> => {
+    return invoke<PackedTexture>('pack_texture_channels', { spec, dry_run: dryRun });
+  },
+
+  /**
+   * Split a texture into one grayscale map per listed channel
+   */
+  unpackTextureChannels: async (
+    path: string,
+    outputs: UnpackedChannel[],
+    dryRun?: boolean
+  ): Promise<UnpackedTexture> => {
+    return invoke<UnpackedTexture>('unpack_texture_channels', {
+      path,
+      outputs,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Denoise (0 to 1) and upscale (1 to 8 times) a texture with the configured
+   * upscaler or the built-in filters; written to `<stem>_enhanced.png` unless
+   * `outPath` is given
+   */
+  enhanceTexture: async (
+    path: string,
+    scale: number,
+    denoise?: number,
+    outPath?: string,
+    dryRun?: boolean
+  ): Promise<EnhancedTexture> => {
+    return invoke<EnhancedTexture>('enhance_texture', {
+      path,
+      scale,
+      denoise,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Convert a normal map between DirectX and OpenGL green and between object
+   * and tangent space; space conversions need the mesh the map was made for.
+   * Written to `<stem>_converted.png` unless `outPath` is given.
+   */
+  convertNormalMap: async (
+    path: string,
+    options: NormalMapOptions,
+    meshHandle?: number,
+    outPath?: string,
+    dryRun?: boolean
+  ): Promise<ConvertedNormalMap> => {
+    return invoke<ConvertedNormalMap>('convert_normal_map', {
+      path,
+      options,
+      mesh_handle: meshHandle,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Clean up a scan file with a preset, returning a new mesh and per-stage reports
+   */
+  cleanupScan: async (
+    path: string,
+    preset?: ScanPreset,
+    fitSize?: number
+  ): Promise<ScanCleanup> => {
+    return invoke<ScanCleanup>('cleanup_scan', { path, preset, fit_size: fitSize });
+  },
+
+  /**
+   * Run a model file through a named processing profile from the settings
+   */
+  processAsset: async (
+    assetId: string,
+    profile: string,
+    outDir?: string,
+    dryRun?: boolean
+  ): Promise<ProcessedAsset> => {
+    return invoke<ProcessedAsset>('process_asset', {
+      asset_id: assetId,
+      profile,
+      out_dir: outDir,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Expected time and memory of an operation, for a mesh handle or a model path
+   */
+  estimateOperation: async (
+    target: { meshHandle: number } | { path: string },
+    operation: EstimatedOperation
+  ): Promise<OperationEstimate> => {
+    return invoke<OperationEstimate>('estimate_operation', {
+      mesh_handle: 'meshHandle' in target ? target.meshHandle : undefined,
+      path: 'path' in target ? target.path : undefined,
+      operation,
+    });
+  },
+};
+
+/**
+ * Path Access Commands
+ *
+ * File commands only accept paths inside approved folders; anything else
+ * has to be approved by the user through a native dialog first.
+ */
+export const scopeCommands = {
+  /**
+   * Ask the user to allow access to a path (no prompt if already allowed)
+   */
+  requestPathAccess: async (path: string, reason?: string): Promise<boolean> => {
+    return invoke<boolean>('request_path_access', { path, reason });
+  },
+
+  /**
+   * Pick a file or folder with a native dialog and approve it
+   */
+  pickPathAccess: async (directory: boolean, title?: string): Promise<string | null> => {
+    return invoke<string | null>('pick_path_access', { directory, title });
+  },
+
+  getPathScope: async (): Promise<ScopeEntry[]> => {
+    return invoke<ScopeEntry[]>('get_path_scope');
+  },
+
+  revokePathAccess: async (path: string): Promise<boolean> => {
+    return invoke<boolean>('revoke_path_access', { path });
+  },
+};
+
+/**
+ * Quarantine Commands
+ *
+ * Downloaded assets are parsed in a separate, resource-limited process and
+ * only moved into the library once they pass.
+ */
+export const quarantineCommands = {
+  getQuarantineDir: async (): Promise<string> => {
+    return invoke<string>('get_quarantine_dir');
+  },
+
+  inspectAsset: async (path: string, limits?: ParseLimits): Promise<QuarantineVerdict> => {
+    return invoke<QuarantineVerdict>('inspect_asset', { path, limits });
+  },
+
+  /**
+   * Move a file into `destinationDir` if it passes inspection
+   */
+  promoteAsset: async (
+    path: string,
+    destinationDir: string,
+    limits?: ParseLimits,
+    dryRun?: boolean
+  ): Promise<PromotionResult> => {
+    return invoke<PromotionResult>('promote_asset', {
+      path,
+      destination_dir: destinationDir,
+      limits,
+      dry_run: dryRun,
+    });
+  },
+};
+
+/**
+ * Report Commands
+ */
+export const reportCommands = {
+  /**
+   * Write per-asset analysis, validation results and scores to CSV or JSON
+   *
+   * Ids are asset folders from `listStorageAssets` or model file paths.
+   * Assets are checked against `profile`, by default the one chosen in the
+   * settings; an empty name checks none.
+   */
+  exportReport: async (
+    assetIds: string[],
+    format: ReportFormat,
+    outPath: string,
+    profile?: string,
+    dryRun?: boolean
+  ): Promise<ExportedReport> => {
+    return invoke<ExportedReport>('export_report', {
+      asset_ids: assetIds,
+      format,
+      out_path: outPath,
+      profile,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * The built-in validation profiles (Quest / mobile VR, WebGL, Console,
+   * Desktop) with the ones from the settings
+   */
+  listValidationProfiles: async (): Promise<ListedProfile[]> => {
+    return invoke<ListedProfile[]>('list_validation_profiles');
+  },
+
+  /**
+   * Textures whose encoding doesn't match the color space of the material
+   * slots using them
+   */
+  auditColorSpaces: async (path: string): Promise<ColorSpaceIssue[]> => {
+    return invoke<ColorSpaceIssue[]>('audit_color_spaces', { path });
+  },
+
+  /**
+   * Retag PNGs and decode gamma-encoded normal maps so textures match their
+   * slots; written as GLB. Images shared by color and data slots are skipped.
+   */
+  fixColorSpaces: async (
+    path: string,
+    outPath: string,
+    dryRun?: boolean
+  ): Promise<FixedColorSpaces> => {
+    return invoke<FixedColorSpaces>('fix_color_spaces', {
+      path,
+      out_path: outPath,
+      dry_run: dryRun,
+    });
+  },
+};
+
+/**
+ * Settings Commands
+ */
+export const settingsCommands = {
+  getSettings: async (): Promise<AppSettings> => {
+    return invoke<AppSettings>('get_settings');
+  },
+
+  /**
+   * Save settings. Watch rules, storage folders and layout, smart collections,
+   * external tools, open-with applications, the Blender and gltf-transform
+   * executables, maintenance, power throttling and the local API have their
+   * own commands; the values sent for them are ignored.
+   */
+  updateSettings: async (settings: AppSettings): Promise<AppSettings> => {
+    return invoke<AppSettings>('update_settings', { settings });
+  },
+
+  /**
+   * Pick the Blender executable with a native dialog, or clear it to look in
+   * the usual install location; returns the saved path, null if cancelled
+   */
+  pickBlenderExecutable: async (clear?: boolean): Promise<string | null> => {
+    return invoke<string | null>('pick_blender_executable', { clear });
+  },
+
+  /**
+   * Pick the gltf-transform executable with a native dialog, or clear it to
+   * look on the PATH; returns the saved path, null if cancelled
+   */
+  pickGltfTransformExecutable: async (clear?: boolean): Promise<string | null> => {
+    return invoke<string | null>('pick_gltf_transform_executable', { clear });
+  },
+};
+
+/**
+ * Background Job Commands
+ */
+export const jobCommands = {
+  /**
+   * Background jobs, oldest first; changes also arrive as `job-updated` events
+   */
+  listJobs: async (): Promise<Job[]> => {
+    return invoke<Job[]>('list_jobs');
+  },
+
+  /**
+   * Replace the watch folder rules and start watching their folders
+   * Output goes to a `processed` subfolder of each rule's folder
+   */
+  setWatchRules: async (rules: WatchRule[]): Promise<WatchRule[]> => {
+    return invoke<WatchRule[]>('set_watch_rules', { rules });
+  },
+
+  /**
+   * Replace the schedule of the library health checks
+   */
+  setMaintenanceSchedule: async (schedule: MaintenanceSchedule): Promise<MaintenanceSchedule> => {
+    return invoke<MaintenanceSchedule>('set_maintenance_schedule', { schedule });
+  },
+
+  /**
+   * Jobs the app closed on before they finished, to offer resuming at startup
+   */
+  listInterruptedJobs: async (): Promise<Job[]> => {
+    return invoke<Job[]>('list_interrupted_jobs');
+  },
+
+  /**
+   * Start interrupted jobs again, deleting their partial output first
+   */
+  resumeJobs: async (ids: number[]): Promise<ResumedJob[]> => {
+    return invoke<ResumedJob[]>('resume_jobs', { ids });
+  },
+
+  /**
+   * Give up on interrupted jobs, marking them failed
+   */
+  dismissJobs: async (ids: number[]): Promise<Job[]> => {
+    return invoke<Job[]>('dismiss_jobs', { ids });
+  },
+
+  /**
+   * The power state and how many background jobs it lets run at once
+   */
+  getPowerStatus: async (): Promise<PowerStatus> => {
+    return invoke<PowerStatus>('get_power_status');
+  },
+
+  /**
+   * Replace when background jobs are throttled and apply it at once
+   */
+  setPowerThrottling: async (throttling: PowerThrottling): Promise<PowerStatus> => {
+    return invoke<PowerStatus>('set_power_throttling', { throttling });
+  },
+
+  /**
+   * Queue the library health checks now; null if a run is already queued
+   */
+  runMaintenance: async (tasks?: MaintenanceTask[]): Promise<number | null> => {
+    return invoke<number | null>('run_maintenance', { tasks });
+  },
+};
+
+/**
+ * Library Commands
+ */
+export const libraryCommands = {
+  /**
+   * Replace the registered storage roots; names must be unique
+   */
+  setStorageRoots: async (roots: StorageRoot[]): Promise<StorageRoot[]> => {
+    return invoke<StorageRoot[]>('set_storage_roots', { roots });
+  },
+
+  /**
+   * The merged library from the saved root indexes
+   */
+  getLibrary: async (): Promise<Library> => {
+    return invoke<Library>('get_library');
+  },
+
+  /**
+   * Index storage roots again, all of them or the named ones
+   */
+  indexStorageRoots: async (names?: string[]): Promise<Library> => {
+    return invoke<Library>('index_storage_roots', { names });
+  },
+
+  /**
+   * Move an asset with its thumbnail and companion files to another root
+   */
+  moveAssetBetweenRoots: async (
+    assetId: string,
+    fromRoot: string,
+    toRoot: string,
+    dryRun?: boolean
+  ): Promise<MovedAsset> => {
+    return invoke<MovedAsset>('move_asset_between_roots', {
+      asset_id: assetId,
+      from_root: fromRoot,
+      to_root: toRoot,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Give assets new ids from a pattern using {name}, {index}, {index:N},
+   * {format}, {polycount} and {category}; a dry run previews the renames
+   */
+  bulkRename: async (assetIds: string[], pattern: string, dryRun?: boolean): Promise<BulkRename> => {
+    return invoke<BulkRename>('bulk_rename', {
+      asset_ids: assetIds,
+      pattern,
+      dry_run: dryRun,
+    });
+  },
+
+  /**
+   * Record the license of an asset, or forget it with null
+   */
+  setAssetLicense: async (
+    assetId: string,
+    license: AssetLicense | null
+  ): Promise<AssetLicense | null> => {
+    return invoke<AssetLicense | null>('set_asset_license', {
+      asset_id: assetId,
+      license,
+    });
+  },
 
-export interface LodLevel {
-  level: number;
-  vertex_count: number;
-  face_count: number;
-  reduction_ratio: number;
-}
+  /**
+   * Replace the assets a project uses, by asset id
+   */
+  setProjectAssets: async (projectId: string, assetIds: string[]): Promise<string[]> => {
+    return invoke<string[]>('set_project_assets', {
+      project_id: projectId,
+      asset_ids: assetIds,
+    });
+  },
 
-export interface MeshStats {
-  vertex_count: number;
-  face_count: number;
-  edge_count: number;
-  is_manifold: boolean;
-  has_degenerate_faces: boolean;
-  surface_area: number;
-  volume: number;
-}
+  /**
+   * Every asset in a project with its license and credits text
+   */
+  complianceReport: async (projectId: string): Promise<ComplianceReport> => {
+    return invoke<ComplianceReport>('compliance_report', { project_id: projectId });
+  },
 
-export interface OptimizedMeshResult {
-  original_vertex_count: number;
-  optimized_vertex_count: number;
-  cache_hits_before: number;
-  cache_hits_after: number;
-  overdraw_before: number;
-  overdraw_after: number;
-}
+  /**
+   * Where an asset came from and what was done to it
+   */
+  getAssetHistory: async (assetId: string): Promise<AssetHistoryReport> => {
+    return invoke<AssetHistoryReport>('get_asset_history', { asset_id: assetId });
+  },
 
-export interface FileInfo {
-  path: string;
-  name: string;
-  extension: string | null;
-  size_bytes: number;
-  created: number | null;
-  modified: number | null;
-  is_directory: boolean;
-}
+  /**
+   * Rate an asset from 1 to 5 stars, or clear its rating with null
+   */
+  setRating: async (assetId: string, rating: number | null): Promise<AssetCuration> => {
+    return invoke<AssetCuration>('set_rating', { asset_id: assetId, rating });
+  },
 
-export interface StorageAsset {
-  id: string;
-  path: string;
-  has_glb: boolean;
-  has_obj: boolean;
-  has_fbx: boolean;
-  has_thumbnail: boolean;
-  glb_size: number | null;
-  thumbnail_path: string | null;
-}
+  /**
+   * Mark an asset as a favorite, or unmark it if it already is one
+   */
+  toggleFavorite: async (assetId: string): Promise<AssetCuration> => {
+    return invoke<AssetCuration>('toggle_favorite', { asset_id: assetId });
+  },
 
-// Dynamic import for Tauri API (only available in Tauri environment)
-async function invoke<T>(command: string, args?: Record<string, unknown>): Promise<T> {
-  if (!isTauri()) {
-    throw new Error('Tauri commands are only available in the Tauri environment');
-  }
+  /**
+   * The library narrowed and sorted by a filter, with every root listed
+   */
+  queryLibrary: async (filter: LibraryFilter = {}): Promise<Library> => {
+    return invoke<Library>('query_library', { filter });
+  },
 
-  // Dynamic import to avoid errors in browser environment
-  const { invoke: tauriInvoke } = await import('@tauri-apps/api/core');
-  return tauriInvoke<T>(command, args);
-}
+  /**
+   * The smart collections saved in the settings
+   */
+  listSmartCollections: async (): Promise<SmartCollection[]> => {
+    return invoke<SmartCollection[]>('list_smart_collections');
+  },
+
+  /**
+   * Replace the saved smart collections
+   */
+  setSmartCollections: async (collections: SmartCollection[]): Promise<SmartCollection[]> => {
+    return invoke<SmartCollection[]>('set_smart_collections', { collections });
+  },
+
+  /**
+   * The library assets that match the rules of a smart collection
+   */
+  evaluateSmartCollection: async (id: string): Promise<EvaluatedCollection> => {
+    return invoke<EvaluatedCollection>('evaluate_smart_collection', { id });
+  },
+};
 
 /**
- * Model Loading Commands
+ * Cache Commands
  */
-export const modelCommands = {
+export const cacheCommands = {
   /**
-   * Analyze a 3D model and return detailed statistics
-   * Uses native Rust for 10x+ faster analysis than JavaScript
+   * Size of the thumbnail, preview and texture cache, by kind
    */
-  analyzeModel: async (path: string): Promise<ModelAnalysis> => {
-    return invoke<ModelAnalysis>('analyze_model', { path });
+  getCacheStats: async (): Promise<CacheStats> => {
+    return invoke<CacheStats>('get_cache_stats');
   },
 
   /**
-   * Load raw model data as bytes
-   * Uses memory-mapped files for efficient streaming
+   * Remove cached entries of one kind, or all of them
    */
-  loadModelData: async (path: string): Promise<Uint8Array> => {
-    const data = await invoke<number[]>('load_model_data', { path });
+  clearCache: async (kind?: CacheKind): Promise<CacheStats> => {
+    return invoke<CacheStats>('clear_cache', { kind });
+  },
+
+  /**
+   * Keep a PNG thumbnail rendered for a model until the model changes
+   */
+  storeThumbnail: async (path: string, png: Uint8Array): Promise<void> => {
+    return invoke<void>('store_thumbnail', { path, png: Array.from(png) });
+  },
+
+  /**
+   * The cached thumbnail of a model, or null if there is none
+   */
+  getThumbnail: async (path: string): Promise<Uint8Array | null> => {
+    const data = await invoke<number[] | null>('get_thumbnail', { path });
+    return data ? new Uint8Array(data) : null;
+  },
+
+  /**
+   * A texture as a PNG the viewer can display, scaled down to fit maxSize
+   */
+  transcodeTexture: async (path: string, maxSize?: number): Promise<Uint8Array> => {
+    const data = await invoke<number[]>('transcode_texture', {
+      path,
+      max_size: maxSize,
+    });
     return new Uint8Array(data);
   },
 
   /**
-   * Get just the bounding box of a model (fast operation)
+   * Every material of a glTF/GLB rendered on a preview sphere as a PNG swatch,
+   * `size` pixels square (128 by default)
    */
-  getModelBounds: async (path: string): Promise<BoundingBox> => {
-    return invoke<BoundingBox>('get_model_bounds', { path });
+  renderMaterialPreviews: async (path: string, size?: number): Promise<MaterialPreview[]> => {
+    const previews = await invoke<(Omit<MaterialPreview, 'png'> & { png: number[] })[]>(
+      'render_material_previews',
+      { path, size }
+    );
+    return previews.map((preview) => ({ ...preview, png: new Uint8Array(preview.png) }));
   },
 };
 
 /**
- * Mesh Processing Commands
+ * Clipboard Commands
  */
-export const meshCommands = {
+export const clipboardCommands = {
   /**
-   * Generate LOD (Level of Detail) versions of a mesh
-   * Uses native meshoptimizer for high-quality decimation
+   * Copy the absolute path of an asset folder or file; returns the path
    */
-  generateLod: async (
-    vertices: Float32Array,
-    indices: Uint32Array,
-    targetRatios: number[]
-  ): Promise<LodResult> => {
-    return invoke<LodResult>('generate_lod', {
-      vertices: Array.from(vertices),
-      indices: Array.from(indices),
-      target_ratios: targetRatios,
+  copyAssetPath: async (path: string): Promise<string> => {
+    return invoke<string>('copy_asset_path', { path });
+  },
+
+  /**
+   * Copy an asset's analysis as a Markdown table; returns the text
+   */
+  copyAssetSummary: async (path: string): Promise<string> => {
+    return invoke<string>('copy_asset_summary', { path });
+  },
+
+  /**
+   * Copy the stored thumbnail of a model, or of an image, as a PNG
+   */
+  copyThumbnail: async (path: string): Promise<void> => {
+    return invoke<void>('copy_thumbnail', { path });
+  },
+};
+
+/**
+ * File Manager and Application Commands
+ */
+export const desktopCommands = {
+  /**
+   * Show a file or folder in Finder, Explorer or the desktop's file manager
+   */
+  revealInFileManager: async (path: string): Promise<void> => {
+    return invoke<void>('reveal_in_file_manager', { path });
+  },
+
+  /**
+   * Open a file in the application the system associates with its type
+   */
+  openWithDefault: async (path: string): Promise<void> => {
+    return invoke<void>('open_with_default', { path });
+  },
+
+  /**
+   * Open a file in an application picked with `pickOpenWithApplication`
+   */
+  openWith: async (path: string, application: string): Promise<void> => {
+    return invoke<void>('open_with', { path, application });
+  },
+
+  /**
+   * Pick an application with a native dialog so `openWith` accepts it;
+   * null if cancelled
+   */
+  pickOpenWithApplication: async (): Promise<string | null> => {
+    return invoke<string | null>('pick_open_with_application');
+  },
+};
+
+/**
+ * External Tool Commands
+ */
+export const externalToolCommands = {
+  /**
+   * Replace the registered external tools; ids must be unique. New or
+   * changed command lines are only saved once the user approves them in a
+   * native dialog
+   */
+  setExternalTools: async (tools: Partial<ExternalTool>[]): Promise<ExternalTool[]> => {
+    return invoke<ExternalTool[]>('set_external_tools', { tools });
+  },
+
+  /**
+   * Run a registered tool on a model file, then analyze the model and
+   * any glTF files the tool wrote
+   */
+  runExternalTool: async (toolId: string, assetId: string): Promise<ExternalToolRun> => {
+    return invoke<ExternalToolRun>('run_external_tool', {
+      tool_id: toolId,
+      asset_id: assetId,
     });
   },
 
   /**
-   * Optimize mesh for GPU rendering
-   * Performs vertex cache and overdraw optimization
+   * Open a model in Blender; each save is stored as `<name>_v<N>.glb` and
+   * announced with `blender-edit-saved`, then `blender-edit-closed` on exit
    */
-  optimizeMesh: async (
-    vertices: Float32Array,
-    indices: Uint32Array
-  ): Promise<OptimizedMeshResult> => {
-    return invoke<OptimizedMeshResult>('optimize_mesh', {
-      vertices: Array.from(vertices),
-      indices: Array.from(indices),
+  editInBlender: async (assetId: string): Promise<BlenderEdit> => {
+    return invoke<BlenderEdit>('edit_in_blender', { asset_id: assetId });
+  },
+};
+
+/**
+ * Bundle Commands
+ */
+export const bundleCommands = {
+  /**
+   * Pack library assets with their textures, thumbnails, categories and
+   * licenses into one .sweedlepack file
+   */
+  exportBundle: async (
+    assetIds: string[],
+    outPath: string,
+    dryRun?: boolean
+  ): Promise<ExportedBundle> => {
+    return invoke<ExportedBundle>('export_bundle', {
+      asset_ids: assetIds,
+      out_path: outPath,
+      dry_run: dryRun,
     });
   },
 
   /**
-   * Calculate detailed mesh statistics
+   * Add the assets of a .sweedlepack file to a storage folder; nothing is
+   * written if any file already exists
    */
-  calculateMeshStats: async (
-    vertices: Float32Array,
-    indices: Uint32Array
-  ): Promise<MeshStats> => {
-    return invoke<MeshStats>('calculate_mesh_stats', {
-      vertices: Array.from(vertices),
-      indices: Array.from(indices),
+  importBundle: async (
+    path: string,
+    storagePath: string,
+    dryRun?: boolean
+  ): Promise<ImportedBundle> => {
+    return invoke<ImportedBundle>('import_bundle', {
+      path,
+      storage_path: storagePath,
+      dry_run: dryRun,
+    });
+  },
+};
+
+/**
+ * Operation Log Commands
+ */
+export const operationCommands = {
+  /**
+   * Log the start of a batch or destructive operation
+   */
+  beginOperation: async (kind: string, items: PlannedItem[]): Promise<number> => {
+    return invoke<number>('begin_operation', { kind, items });
+  },
+
+  /**
+   * Log progress of one item (written to disk before resolving)
+   *
+   * A temp path must be inside an approved folder; recovery deletes it.
+   */
+  recordOperationItem: async (
+    opId: number,
+    index: number,
+    update: ItemProgressUpdate
+  ): Promise<void> => {
+    return invoke<void>('record_operation_item', { op_id: opId, index, update });
+  },
+
+  finishOperation: async (opId: number): Promise<void> => {
+    return invoke<void>('finish_operation', { op_id: opId });
+  },
+
+  /**
+   * Operations interrupted by a crash, recovered at startup
+   */
+  getInterruptedOperations: async (): Promise<InterruptedOperation[]> => {
+    return invoke<InterruptedOperation[]>('get_interrupted_operations');
+  },
+
+  resumeOperation: async (opId: number): Promise<ResumePlan> => {
+    return invoke<ResumePlan>('resume_operation', { op_id: opId });
+  },
+
+  discardOperation: async (opId: number): Promise<void> => {
+    return invoke<void>('discard_operation', { op_id: opId });
+  },
+};
+
+/**
+ * Diagnostics Commands
+ */
+export const diagnosticsCommands = {
+  /**
+   * Per-operation timing and heap usage, slowest first
+   */
+  getPerformanceReport: async (reset?: boolean): Promise<PerformanceReport> => {
+    return invoke<PerformanceReport>('get_performance_report', { reset });
+  },
+
+  /**
+   * The newest log records at `level` (default info) or more severe, oldest first
+   */
+  getRecentLogs: async (level?: LogLevel, limit?: number): Promise<LogRecord[]> => {
+    return invoke<LogRecord[]>('get_recent_logs', { level, limit });
+  },
+
+  /**
+   * Check the GPU, disk space and write access, the library index, the cache,
+   * external tools and the local API for the troubleshooting page
+   */
+  runDiagnostics: async (): Promise<DiagnosticsReport> => {
+    return invoke<DiagnosticsReport>('run_diagnostics');
+  },
+
+  /**
+   * Pack logs, settings, performance report, jobs and system details into a
+   * `.tar.gz` to attach to a bug report; written to the log folder without `outPath`
+   */
+  exportDebugBundle: async (outPath?: string, dryRun?: boolean): Promise<ExportedDebugBundle> => {
+    return invoke<ExportedDebugBundle>('export_debug_bundle', {
+      out_path: outPath,
+      dry_run: dryRun,
     });
   },
+
+  /**
+   * Most opened assets, command durations and library growth (stored locally)
+   */
+  getUsageStats: async (top?: number): Promise<UsageReport> => {
+    return invoke<UsageReport>('get_usage_stats', { top });
+  },
+
+  clearUsageStats: async (): Promise<void> => {
+    return invoke<void>('clear_usage_stats');
+  },
 };
 
 /**
@@ -206,12 +3979,37 @@ export const fileCommands = {
     });
   },
 
+  /**
+   * Move every asset in a storage folder into another layout and make it
+   * the current one; categories are set by asset id
+   */
+  migrateStorageLayout: async (
+    storagePath: string,
+    layout: StorageLayout,
+    categories?: Record<string, string>,
+    dryRun?: boolean
+  ): Promise<StorageMigration> => {
+    return invoke<StorageMigration>('migrate_storage_layout', {
+      storage_path: storagePath,
+      layout,
+      categories,
+      dry_run: dryRun,
+    });
+  },
+
   /**
    * Watch a directory and get current file list
    */
   watchDirectory: async (path: string): Promise<FileInfo[]> => {
     return invoke<FileInfo[]>('watch_directory', { path });
   },
+
+  /**
+   * Stop watching a directory
+   */
+  unwatchDirectory: async (path: string): Promise<boolean> => {
+    return invoke<boolean>('unwatch_directory', { path });
+  },
 };
 
 /**
@@ -220,7 +4018,24 @@ export const fileCommands = {
 export const tauriCommands = {
   ...modelCommands,
   ...meshCommands,
+  ...meshUploadCommands,
+  ...importCommands,
+  ...transportCommands,
+  ...processingCommands,
+  ...scopeCommands,
+  ...quarantineCommands,
+  ...reportCommands,
+  ...settingsCommands,
+  ...jobCommands,
+  ...operationCommands,
+  ...diagnosticsCommands,
   ...fileCommands,
+  ...libraryCommands,
+  ...cacheCommands,
+  ...clipboardCommands,
+  ...desktopCommands,
+  ...externalToolCommands,
+  ...bundleCommands,
   isTauri,
 };
 
@@ -0,0 +1,151 @@
+//! Geometry kernel benchmarks: `cargo bench --bench geometry`
+//!
+//! Each SIMD kernel is measured next to the scalar loop it replaced.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rayon::prelude::*;
+use sweedle_lib::utils::mesh_analyzer::MeshAnalyzer;
+use sweedle_lib::utils::simd;
+
+/// Wavy grid with `n * n * 2` triangles
+fn grid_mesh(n: usize) -> (Vec<f32>, Vec<u32>) {
+    let mut positions = Vec::with_capacity((n + 1) * (n + 1) * 3);
+    for y in 0..=n {
+        for x in 0..=n {
+            let (fx, fy) = (x as f32 / n as f32, y as f32 / n as f32);
+            positions.extend([fx, fy, (fx * 7.0).sin() * (fy * 5.0).cos() * 0.1]);
+        }
+    }
+    let row = (n + 1) as u32;
+    let mut indices = Vec::with_capacity(n * n * 6);
+    for y in 0..n as u32 {
+        for x in 0..n as u32 {
+            let i = y * row + x;
+            indices.extend([i, i + 1, i + row, i + 1, i + row + 1, i + row]);
+        }
+    }
+    (positions, indices)
+}
+
+fn vertex(positions: &[f32], i: u32) -> [f32; 3] {
+    let b = i as usize * 3;
+    [positions[b], positions[b + 1], positions[b + 2]]
+}
+
+/// Per-face rayon loop previously used by `calculate_mesh_stats`
+fn scalar_surface_area(positions: &[f32], indices: &[u32]) -> f32 {
+    indices
+        .par_chunks_exact(3)
+        .map(|f| {
+            let (v0, v1, v2) = (
+                vertex(positions, f[0]),
+                vertex(positions, f[1]),
+                vertex(positions, f[2]),
+            );
+            let e1 = [v1[0] - v0[0], v1[1] - v0[1], v1[2] - v0[2]];
+            let e2 = [v2[0] - v0[0], v2[1] - v0[1], v2[2] - v0[2]];
+            let c = [
+                e1[1] * e2[2] - e1[2] * e2[1],
+                e1[2] * e2[0] - e1[0] * e2[2],
+                e1[0] * e2[1] - e1[1] * e2[0],
+            ];
+            (c[0] * c[0] + c[1] * c[1] + c[2] * c[2]).sqrt() / 2.0
+        })
+        .sum()
+}
+
+fn scalar_bounds(positions: &[f32]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for v in positions.chunks_exact(3) {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(v[axis]);
+            max[axis] = max[axis].max(v[axis]);
+        }
+    }
+    (min, max)
+}
+
+/// Pairwise scan previously used by `MeshAnalyzer::count_unique_vertices`
+fn pairwise_unique_vertices(positions: &[f32], epsilon: f32) -> usize {
+    let vertex_count = positions.len() / 3;
+    let mut is_duplicate = vec![false; vertex_count];
+    let mut unique_count = 0;
+    for i in 0..vertex_count {
+        if is_duplicate[i] {
+            continue;
+        }
+        unique_count += 1;
+        let vi = vertex(positions, i as u32);
+        for (j, duplicate) in is_duplicate.iter_mut().enumerate().skip(i + 1) {
+            let vj = vertex(positions, j as u32);
+            let dist_sq =
+                (vi[0] - vj[0]).powi(2) + (vi[1] - vj[1]).powi(2) + (vi[2] - vj[2]).powi(2);
+            if dist_sq < epsilon * epsilon {
+                *duplicate = true;
+            }
+        }
+    }
+    unique_count
+}
+
+fn bench_face_sums(c: &mut Criterion) {
+    let mut group = c.benchmark_group("face_sums");
+    for n in [128, 1024] {
+        let (positions, indices) = grid_mesh(n);
+        group.throughput(Throughput::Elements((indices.len() / 3) as u64));
+        group.bench_with_input(BenchmarkId::new("area_scalar", n), &n, |b, _| {
+            b.iter(|| scalar_surface_area(black_box(&positions), black_box(&indices)))
+        });
+        group.bench_with_input(BenchmarkId::new("area_simd", n), &n, |b, _| {
+            b.iter(|| simd::surface_area(black_box(&positions), black_box(&indices)))
+        });
+        group.bench_with_input(BenchmarkId::new("volume_simd", n), &n, |b, _| {
+            b.iter(|| simd::signed_volume(black_box(&positions), black_box(&indices)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_bounds(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bounds");
+    for n in [128, 1024] {
+        let (positions, _) = grid_mesh(n);
+        group.throughput(Throughput::Elements((positions.len() / 3) as u64));
+        group.bench_with_input(BenchmarkId::new("scalar", n), &n, |b, _| {
+            b.iter(|| scalar_bounds(black_box(&positions)))
+        });
+        group.bench_with_input(BenchmarkId::new("simd", n), &n, |b, _| {
+            b.iter(|| simd::bounds(black_box(&positions)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_welding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("welding");
+    group.sample_size(10);
+    for n in [64, 512] {
+        let (mut positions, indices) = grid_mesh(n);
+        let vertex_count = positions.len() / 3;
+        // Duplicate every vertex slightly offset, as unwelded exports do
+        let copy = positions.clone();
+        positions.extend(copy.iter().map(|v| v + 1e-6));
+
+        group.throughput(Throughput::Elements((vertex_count * 2) as u64));
+        // Quadratic, so only measured on the small mesh
+        if n <= 64 {
+            group.bench_with_input(BenchmarkId::new("pairwise", n), &n, |b, _| {
+                b.iter(|| pairwise_unique_vertices(black_box(&positions), black_box(1e-4)))
+            });
+        }
+        let analyzer = MeshAnalyzer::new(positions, indices);
+        group.bench_with_input(BenchmarkId::new("spatial_simd", n), &n, |b, _| {
+            b.iter(|| analyzer.count_unique_vertices(black_box(1e-4)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_face_sums, bench_bounds, bench_welding);
+criterion_main!(benches);
@@ -0,0 +1,388 @@
+use crate::utils::animation::{self, AnimationMarker, Clip, ClipInfo};
+use crate::utils::bone_weights::{self, WeightReport};
+use crate::utils::changes::{ChangeReport, ChangeSet};
+use crate::utils::glb_writer::write_glb;
+use crate::utils::gltf_document::GltfDocument;
+use crate::utils::mesh_files::MeshFileFormat;
+use crate::utils::path_scope::PathScope;
+use crate::utils::pose::{self, Pose};
+use crate::utils::rest_pose::{self, RestPoseAnalysis, RestPoseKind};
+use crate::utils::settings::SettingsStore;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{command, State};
+use tracing::instrument;
+
+/// Frame rate assumed when a range gives none
+const DEFAULT_FPS: f32 = 30.0;
+
+/// Frames `start` to `end`, inclusive, at `fps` frames per second
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameRange {
+    pub start: f32,
+    pub end: f32,
+    pub fps: Option<f32>,
+}
+
+impl FrameRange {
+    fn seconds(&self) -> Result<(f32, f32), String> {
+        let fps = self.fps.unwrap_or(DEFAULT_FPS);
+        if !(fps.is_finite() && fps > 0.0) {
+            return Err(format!("Frame rate must be positive, got {}", fps));
+        }
+        Ok((self.start / fps, self.end / fps))
+    }
+}
+
+/// Result of the animation edits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditedAnimations {
+    pub out_path: String,
+    /// The clips in the written file
+    pub animations: Vec<ClipInfo>,
+    /// Nodes animated in a merged file that the target has no node named
+    /// like; their tracks were left out
+    pub dropped: Vec<String>,
+    pub changes: ChangeReport,
+}
+
+/// The animation clips in a glTF or GLB file
+#[command]
+#[instrument(skip_all, err)]
+pub async fn list_animations(
+    scope: State<'_, PathScope>,
+    path: String,
+) -> Result<Vec<ClipInfo>, String> {
+    let path = scope.check(&path)?;
+    let document = open(&path)?;
+    Ok(animation::list(&animation::read_clips(&document)?))
+}
+
+/// Cut one clip down to a frame range, starting it at zero
+///
+/// The other clips are kept. The file is written as GLB.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn trim_animation(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    animation_index: usize,
+    range: FrameRange,
+    out_path: String,
+    dry_run: Option<bool>,
+) -> Result<EditedAnimations, String> {
+    let path = scope.check(&path)?;
+    let out_path = scope.check(&out_path)?;
+    let changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let mut document = open(&path)?;
+    let mut clips = animation::read_clips(&document)?;
+    let (start, end) = range.seconds()?;
+    let clip = clip(&clips, animation_index)?;
+    clips[animation_index] = clip.trim(start, end)?;
+    write(&mut document, &clips, &out_path, Vec::new(), changes)
+}
+
+/// Split one clip into a clip per marker, each running to the next marker
+///
+/// `markers` default to the ones stored in the clip's `extras.markers`. The
+/// parts take the clip's place in the list.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn split_animation(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    animation_index: usize,
+    markers: Option<Vec<AnimationMarker>>,
+    out_path: String,
+    dry_run: Option<bool>,
+) -> Result<EditedAnimations, String> {
+    let path = scope.check(&path)?;
+    let out_path = scope.check(&out_path)?;
+    let changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let mut document = open(&path)?;
+    let mut clips = animation::read_clips(&document)?;
+    let clip = clip(&clips, animation_index)?;
+    let parts = clip.split(&markers.unwrap_or_else(|| clip.markers()))?;
+    clips.splice(animation_index..=animation_index, parts);
+    write(&mut document, &clips, &out_path, Vec::new(), changes)
+}
+
+/// Append the clips of other files to a file's animation list
+///
+/// Tracks are matched to the target's nodes by name, so the files should
+/// share a skeleton; tracks on nodes the target lacks are dropped.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn merge_animations(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    sources: Vec<String>,
+    out_path: String,
+    dry_run: Option<bool>,
+) -> Result<EditedAnimations, String> {
+    let path = scope.check(&path)?;
+    let out_path = scope.check(&out_path)?;
+    let changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let mut document = open(&path)?;
+    let mut clips = animation::read_clips(&document)?;
+    let names = animation::node_names(&mut document);
+    let mut dropped = Vec::new();
+    for source in &sources {
+        let mut source = open(&scope.check(source)?)?;
+        let from = animation::node_names(&mut source);
+        for clip in animation::read_clips(&source)? {
+            let (clip, missing) = animation::retarget(&clip, &from, &names);
+            for name in missing {
+                if !dropped.contains(&name) {
+                    dropped.push(name);
+                }
+            }
+            clips.push(clip);
+        }
+    }
+    write(&mut document, &clips, &out_path, dropped, changes)
+}
+
+/// Result of `bake_pose`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BakedPoseExport {
+    pub out_path: String,
+    pub mesh_count: usize,
+    pub vertex_count: usize,
+    pub face_count: usize,
+    pub skinned_mesh_count: usize,
+    pub morphed_mesh_count: usize,
+    /// Point, line and strip primitives, which aren't baked
+    pub skipped_primitive_count: usize,
+    pub changes: ChangeReport,
+}
+
+/// Write the meshes of a glTF file as posed by one clip at `time` seconds
+/// to a static GLB
+///
+/// Skinning and morph targets are applied and every transform is baked in,
+/// so the result has no skeleton or animation: for print poses and
+/// reference statues. Materials aren't carried over.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn bake_pose(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    animation_index: usize,
+    time: f32,
+    out_path: String,
+    dry_run: Option<bool>,
+) -> Result<BakedPoseExport, String> {
+    let path = scope.check(&path)?;
+    let out_path = scope.check(&out_path)?;
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+    if !time.is_finite() {
+        return Err(format!("Invalid pose time {}", time));
+    }
+
+    let document = open(&path)?;
+    let clips = animation::read_clips(&document)?;
+    let pose = Pose::at(&document, clip(&clips, animation_index)?, time);
+    let baked = pose::bake(&document, &pose)?;
+    if baked.meshes.is_empty() {
+        return Err(format!("No triangle meshes found in {}", path.display()));
+    }
+    changes.write(&out_path, &write_glb(&baked.meshes)?)?;
+
+    Ok(BakedPoseExport {
+        out_path: out_path.to_string_lossy().to_string(),
+        mesh_count: baked.meshes.len(),
+        vertex_count: baked.meshes.iter().map(|m| m.positions.len() / 3).sum(),
+        face_count: baked.meshes.iter().map(|m| m.indices.len() / 3).sum(),
+        skinned_mesh_count: baked.skinned_mesh_count,
+        morphed_mesh_count: baked.morphed_mesh_count,
+        skipped_primitive_count: baked.skipped_primitive_count,
+        changes: changes.finish(),
+    })
+}
+
+/// Whether a character's rest pose is a T-pose or an A-pose, from the
+/// angle of its upper arms
+#[command]
+#[instrument(skip_all, err)]
+pub async fn analyze_rest_pose(
+    scope: State<'_, PathScope>,
+    path: String,
+) -> Result<RestPoseAnalysis, String> {
+    let path = scope.check(&path)?;
+    Ok(rest_pose::analyze(&open(&path)?))
+}
+
+/// Result of `convert_rest_pose`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConvertedRestPose {
+    pub out_path: String,
+    pub before: RestPoseAnalysis,
+    pub after: RestPoseAnalysis,
+    pub changes: ChangeReport,
+}
+
+/// Re-pose a character's rest pose into a T-pose or an A-pose
+///
+/// The upper arms are rotated about the shoulders, with the skinned
+/// vertices, inverse bind matrices and joint rest transforms adjusted to
+/// match; the file is written as GLB.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn convert_rest_pose(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    target: RestPoseKind,
+    out_path: String,
+    dry_run: Option<bool>,
+) -> Result<ConvertedRestPose, String> {
+    let path = scope.check(&path)?;
+    let out_path = scope.check(&out_path)?;
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let mut document = open(&path)?;
+    let before = rest_pose::analyze(&document);
+    let after = rest_pose::convert(&mut document, target)?;
+    changes.write(&out_path, &document.to_glb()?)?;
+
+    Ok(ConvertedRestPose {
+        out_path: out_path.to_string_lossy().to_string(),
+        before,
+        after,
+        changes: changes.finish(),
+    })
+}
+
+/// Bone influence statistics of a character's skinned meshes: influences
+/// per vertex, weight sums that are off and unused joints
+#[command]
+#[instrument(skip_all, err)]
+pub async fn analyze_weights(
+    scope: State<'_, PathScope>,
+    path: String,
+) -> Result<WeightReport, String> {
+    let path = scope.check(&path)?;
+    bone_weights::analyze(&open(&path)?)
+}
+
+/// Result of `limit_influences` and `prune_unused_bones`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditedWeights {
+    pub out_path: String,
+    /// Vertices whose weights were changed
+    pub changed_vertex_count: usize,
+    /// Joints removed from the skins
+    pub pruned_bones: Vec<String>,
+    /// Statistics of the written file
+    pub report: WeightReport,
+    pub changes: ChangeReport,
+}
+
+/// Cap the bones moving each vertex at `max_influences`, keeping the
+/// heaviest and renormalizing the weights; the file is written as GLB
+#[command]
+#[instrument(skip_all, err)]
+pub async fn limit_influences(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    max_influences: usize,
+    out_path: String,
+    dry_run: Option<bool>,
+) -> Result<EditedWeights, String> {
+    let path = scope.check(&path)?;
+    let out_path = scope.check(&out_path)?;
+    let changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let mut document = open(&path)?;
+    let changed_vertex_count = bone_weights::limit_influences(&mut document, max_influences)?;
+    write_weights(
+        &document,
+        &out_path,
+        changed_vertex_count,
+        Vec::new(),
+        changes,
+    )
+}
+
+/// Remove joints no vertex is weighted to from the skins, except those
+/// above used joints; the file is written as GLB
+#[command]
+#[instrument(skip_all, err)]
+pub async fn prune_unused_bones(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    out_path: String,
+    dry_run: Option<bool>,
+) -> Result<EditedWeights, String> {
+    let path = scope.check(&path)?;
+    let out_path = scope.check(&out_path)?;
+    let changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let mut document = open(&path)?;
+    let pruned = bone_weights::prune_unused_bones(&mut document)?;
+    write_weights(&document, &out_path, 0, pruned, changes)
+}
+
+fn open(path: &Path) -> Result<GltfDocument, String> {
+    if MeshFileFormat::from_path(path)? != MeshFileFormat::Gltf {
+        return Err(format!(
+            "Animations can only be edited in glTF, not {}",
+            path.display()
+        ));
+    }
+    GltfDocument::open(path)
+}
+
+fn clip(clips: &[Clip], index: usize) -> Result<&Clip, String> {
+    clips.get(index).ok_or_else(|| {
+        format!(
+            "Animation {} not found; the file has {}",
+            index,
+            clips.len()
+        )
+    })
+}
+
+fn write(
+    document: &mut GltfDocument,
+    clips: &[Clip],
+    out_path: &Path,
+    dropped: Vec<String>,
+    mut changes: ChangeSet,
+) -> Result<EditedAnimations, String> {
+    animation::write_clips(document, clips);
+    changes.write(out_path, &document.to_glb()?)?;
+    Ok(EditedAnimations {
+        out_path: out_path.to_string_lossy().to_string(),
+        animations: animation::list(clips),
+        dropped,
+        changes: changes.finish(),
+    })
+}
+
+fn write_weights(
+    document: &GltfDocument,
+    out_path: &Path,
+    changed_vertex_count: usize,
+    pruned_bones: Vec<String>,
+    mut changes: ChangeSet,
+) -> Result<EditedWeights, String> {
+    changes.write(out_path, &document.to_glb()?)?;
+    Ok(EditedWeights {
+        out_path: out_path.to_string_lossy().to_string(),
+        changed_vertex_count,
+        pruned_bones,
+        report: bone_weights::analyze(document)?,
+        changes: changes.finish(),
+    })
+}
@@ -0,0 +1,375 @@
+use crate::commands::{
+    bundle, jobs, library, maintenance, mesh_ops, model_export, model_import, model_loader,
+    processing, reports, settings,
+};
+use crate::utils::api_schema::{
+    self, boolean, choice, object, string, strings, MethodSpec, API_VERSION,
+};
+use crate::utils::engine_export::Engine;
+use crate::utils::file_lock;
+use crate::utils::maintenance::MaintenanceTask;
+use crate::utils::path_scope::PathScope;
+use crate::utils::platform;
+use crate::utils::report::ReportFormat;
+use crate::utils::rpc_server::{self, Dispatch, HeadlessOptions, RpcError, RpcServer};
+use crate::utils::settings::SettingsStore;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::{command, AppHandle, Manager};
+use tracing::instrument;
+
+/// Where plugins find a running API, in the app data folder
+const DISCOVERY_FILE: &str = "local_api.json";
+const TOKEN_FILE: &str = "rpc_token";
+
+/// How to reach a running API; what `local_api.json` holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalApi {
+    pub url: String,
+    pub api_version: u32,
+    /// Sent as `Authorization: Bearer <token>`
+    pub token: String,
+    /// Process serving it, so a plugin can tell the file is left over from
+    /// an app that has exited
+    pub pid: u32,
+}
+
+/// The methods of this API version, each named after the command it runs
+/// and taking the same arguments by name
+pub fn methods() -> Vec<MethodSpec> {
+    let engines = choice(&[Engine::Unity, Engine::Unreal]);
+    let formats = choice(&[ReportFormat::Csv, ReportFormat::Json]);
+    let tasks = serde_json::json!({ "type": "array", "items": choice(&MaintenanceTask::ALL) });
+    vec![
+        MethodSpec::new("api.describe", "This document", "ApiDescription"),
+        MethodSpec::new("rpc.methods", "Names of the methods", "string[]"),
+        MethodSpec::new("get_settings", "The app settings", "AppSettings"),
+        MethodSpec::new("analyze_model", "Inspect a model file", "ModelAnalysis")
+            .param("path", string()),
+        MethodSpec::new(
+            "calculate_file_stats",
+            "Geometry counts of a model file",
+            "FileStats",
+        )
+        .param("path", string()),
+        MethodSpec::new(
+            "estimate_operation",
+            "How long an operation would take on a model file",
+            "OperationEstimate",
+        )
+        .param("path", string())
+        .param("operation", object("EstimatedOperation")),
+        MethodSpec::new(
+            "convert_to_glb",
+            "Convert an OBJ, STL or FBX model",
+            "ConvertedModel",
+        )
+        .param("path", string())
+        .param("out_path", string())
+        .optional("conversion", object("AxisConversion"))
+        .optional("triangulation", object("TriangulationOptions"))
+        .optional("keep_lights_and_cameras", boolean())
+        .optional("dry_run", boolean()),
+        MethodSpec::new(
+            "process_asset",
+            "Run a processing profile",
+            "ProcessedAsset",
+        )
+        .param("asset_id", string())
+        .param("profile", string())
+        .optional("out_dir", string())
+        .optional("dry_run", boolean()),
+        MethodSpec::new(
+            "export_for_engine",
+            "Lay models out for a Unity or Unreal project",
+            "EngineExport",
+        )
+        .param("asset_ids", strings())
+        .param("engine", engines)
+        .param("out_dir", string())
+        .optional("dry_run", boolean()),
+        MethodSpec::new(
+            "export_bundle",
+            "Pack assets into a bundle",
+            "ExportedBundle",
+        )
+        .param("asset_ids", strings())
+        .param("out_path", string())
+        .optional("dry_run", boolean()),
+        MethodSpec::new(
+            "export_report",
+            "Validate assets into a report",
+            "ExportedReport",
+        )
+        .param("asset_ids", strings())
+        .param("format", formats)
+        .param("out_path", string())
+        .optional("profile", string())
+        .optional("dry_run", boolean()),
+        MethodSpec::new(
+            "list_validation_profiles",
+            "Profiles reports can check against",
+            "ListedProfile[]",
+        ),
+        MethodSpec::new("get_library", "Every asset in the storage roots", "Library"),
+        MethodSpec::new("index_storage_roots", "Re-index storage roots", "Library")
+            .optional("names", strings()),
+        MethodSpec::new(
+            "query_library",
+            "Filtered and sorted library assets",
+            "Library",
+        )
+        .param("filter", object("LibraryFilter")),
+        MethodSpec::new(
+            "get_asset_history",
+            "Where an asset came from and what was done to it",
+            "AssetHistoryReport",
+        )
+        .param("asset_id", string()),
+        MethodSpec::new("list_jobs", "Background jobs", "Job[]"),
+        MethodSpec::new(
+            "run_maintenance",
+            "Run library health checks",
+            "number | null",
+        )
+        .optional("tasks", tasks),
+    ]
+}
+
+/// Approve the folders from the command line and serve the API on the
+/// address asked for; the app exits if the server can't listen
+pub fn start_headless(app: &AppHandle, options: HeadlessOptions) -> Result<(), String> {
+    let scope = app.state::<PathScope>();
+    for folder in &options.allow {
+        scope.allow_directory(folder)?;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match serve(&app, options.listen).await {
+            Ok(api) => {
+                log::info!("Serving the API at {}", api.url);
+                println!(
+                    "Sweedle is serving its API at {}; the token is in {}",
+                    api.url,
+                    data_file(&app, TOKEN_FILE)
+                        .map(|path| path.display().to_string())
+                        .unwrap_or_default()
+                );
+            }
+            Err(e) => {
+                log::error!("{}", e);
+                eprintln!("{}", e);
+                app.exit(1);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Serve the API on loopback beside the window if the settings ask for it
+pub fn start_local_api(app: &AppHandle) {
+    if cfg!(mobile) || !app.state::<SettingsStore>().get().local_api {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = serve(&app, loopback()).await {
+            log::warn!("Failed to start the local API: {}", e);
+        }
+    });
+}
+
+/// The local API, if it's running
+#[command]
+#[instrument(skip_all, err)]
+pub async fn get_local_api(app: AppHandle) -> Result<Option<LocalApi>, String> {
+    let Some(url) = app.state::<RpcServer>().url() else {
+        return Ok(None);
+    };
+    Ok(Some(LocalApi {
+        url,
+        api_version: API_VERSION,
+        token: rpc_server::load_token(&data_file(&app, TOKEN_FILE)?)?,
+        pid: std::process::id(),
+    }))
+}
+
+/// Turn the local API for plugins in other tools on or off
+///
+/// While on, the API answers on a loopback port and `local_api.json` in the
+/// app data folder says where and with which token; the setting is kept, so
+/// it starts with the app next time. There is no local API on mobile.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn set_local_api(app: AppHandle, enabled: bool) -> Result<Option<LocalApi>, String> {
+    if enabled {
+        platform::desktop_only("The local API")?;
+    }
+    app.state::<SettingsStore>()
+        .update(|settings| settings.local_api = enabled)?;
+    if enabled {
+        return serve(&app, loopback()).await.map(Some);
+    }
+    app.state::<RpcServer>().stop();
+    let discovery = data_file(&app, DISCOVERY_FILE)?;
+    if discovery.exists() {
+        fs::remove_file(&discovery)
+            .map_err(|e| format!("Failed to remove {}: {}", discovery.display(), e))?;
+    }
+    Ok(None)
+}
+
+fn loopback() -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::LOCALHOST, 0))
+}
+
+fn data_file(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(name))
+        .map_err(|e| format!("Failed to find the app data folder: {}", e))
+}
+
+/// Start the server on `listen` and say where it is in `local_api.json`
+async fn serve(app: &AppHandle, listen: SocketAddr) -> Result<LocalApi, String> {
+    let token = rpc_server::load_token(&data_file(app, TOKEN_FILE)?)?;
+    let handle = app.clone();
+    let dispatch: Dispatch = Arc::new(move |method, params| {
+        let app = handle.clone();
+        Box::pin(async move { call(&app, &method, &params).await })
+    });
+    let server = app
+        .state::<RpcServer>()
+        .start(listen, token.clone(), dispatch)
+        .await?;
+    let api = LocalApi {
+        url: server.url,
+        api_version: API_VERSION,
+        token,
+        pid: std::process::id(),
+    };
+    file_lock::write_json(&data_file(app, DISCOVERY_FILE)?, &api, "local API file")?;
+    Ok(api)
+}
+
+/// A named argument; a missing one is `null`, so optional arguments may be
+/// left out
+fn arg<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T, RpcError> {
+    serde_json::from_value(params.get(name).cloned().unwrap_or(Value::Null))
+        .map_err(|e| RpcError::InvalidParams(format!("Invalid {}: {}", name, e)))
+}
+
+fn reply<T: Serialize>(result: Result<T, String>) -> Result<Value, RpcError> {
+    let result = result.map_err(RpcError::Failed)?;
+    serde_json::to_value(result)
+        .map_err(|e| RpcError::Failed(format!("Failed to serialize the result: {}", e)))
+}
+
+async fn call(app: &AppHandle, method: &str, params: &Value) -> Result<Value, RpcError> {
+    let methods = methods();
+    let spec = methods
+        .iter()
+        .find(|spec| spec.name == method)
+        .ok_or_else(|| RpcError::MethodNotFound(method.to_string()))?;
+    if let Some(params) = params.as_object() {
+        spec.check(params).map_err(RpcError::InvalidParams)?;
+    }
+
+    let p = params;
+    match method {
+        "api.describe" => Ok(api_schema::describe(&methods)),
+        "rpc.methods" => reply(Ok(methods.iter().map(|spec| spec.name).collect::<Vec<_>>())),
+        "get_settings" => reply(settings::get_settings(app.state()).await),
+        "analyze_model" => {
+            reply(model_loader::analyze_model(app.state(), app.state(), arg(p, "path")?).await)
+        }
+        "calculate_file_stats" => {
+            reply(mesh_ops::calculate_file_stats(app.state(), arg(p, "path")?).await)
+        }
+        "estimate_operation" => reply(
+            processing::estimate_operation(
+                app.clone(),
+                app.state(),
+                app.state(),
+                app.state(),
+                None,
+                Some(arg(p, "path")?),
+                arg(p, "operation")?,
+            )
+            .await,
+        ),
+        "convert_to_glb" => reply(
+            model_import::convert_to_glb(
+                app.clone(),
+                arg(p, "path")?,
+                arg(p, "out_path")?,
+                arg(p, "conversion")?,
+                arg(p, "triangulation")?,
+                arg(p, "keep_lights_and_cameras")?,
+                arg(p, "dry_run")?,
+            )
+            .await,
+        ),
+        "process_asset" => reply(
+            processing::process_asset(
+                app.clone(),
+                arg(p, "asset_id")?,
+                arg(p, "profile")?,
+                arg(p, "out_dir")?,
+                arg(p, "dry_run")?,
+            )
+            .await,
+        ),
+        "export_for_engine" => reply(
+            model_export::export_for_engine(
+                app.clone(),
+                app.state(),
+                app.state(),
+                arg(p, "asset_ids")?,
+                arg(p, "engine")?,
+                arg(p, "out_dir")?,
+                arg(p, "dry_run")?,
+            )
+            .await,
+        ),
+        "export_bundle" => reply(
+            bundle::export_bundle(
+                app.clone(),
+                arg(p, "asset_ids")?,
+                arg(p, "out_path")?,
+                arg(p, "dry_run")?,
+            )
+            .await,
+        ),
+        "export_report" => reply(
+            reports::export_report(
+                app.clone(),
+                arg(p, "asset_ids")?,
+                arg(p, "format")?,
+                arg(p, "out_path")?,
+                arg(p, "profile")?,
+                arg(p, "dry_run")?,
+            )
+            .await,
+        ),
+        "list_validation_profiles" => reply(reports::list_validation_profiles(app.state()).await),
+        "get_library" => reply(library::get_library(app.clone()).await),
+        "index_storage_roots" => {
+            reply(library::index_storage_roots(app.clone(), arg(p, "names")?).await)
+        }
+        "query_library" => reply(library::query_library(app.clone(), arg(p, "filter")?).await),
+        "get_asset_history" => {
+            reply(library::get_asset_history(app.clone(), arg(p, "asset_id")?).await)
+        }
+        "list_jobs" => reply(jobs::list_jobs(app.state()).await),
+        "run_maintenance" => {
+            reply(maintenance::run_maintenance(app.clone(), arg(p, "tasks")?).await)
+        }
+        _ => Err(RpcError::MethodNotFound(method.to_string())),
+    }
+}
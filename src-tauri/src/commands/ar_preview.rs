@@ -0,0 +1,86 @@
+use crate::utils::ar_preview::{ArPreviewServer, PreviewFiles};
+use crate::utils::path_scope::PathScope;
+use crate::utils::qr::QrCode;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tracing::instrument;
+
+/// How long a preview is served unless asked otherwise
+const DEFAULT_MINUTES: u64 = 15;
+
+/// Result of `start_ar_preview`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArPreview {
+    pub asset_id: String,
+    /// Address to open on the phone, also the QR code's payload
+    pub url: String,
+    /// The URL as a QR code
+    pub qr_svg: String,
+    pub glb_path: Option<String>,
+    pub usdz_path: Option<String>,
+    pub expires_ms: u64,
+}
+
+/// Payload of the `ar-preview-stopped` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArPreviewStopped {
+    pub url: String,
+}
+
+/// Serve a GLB or USDZ model on the local network for viewing in AR
+///
+/// A file of the same name in the other format beside it is served too, so
+/// iOS gets Quick Look and Android Scene Viewer. The address is random and
+/// stops working after `minutes` (default 15), when `stop_ar_preview` is
+/// called, or when another preview starts; `ar-preview-stopped` is emitted
+/// then.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn start_ar_preview(
+    app: AppHandle,
+    asset_id: String,
+    minutes: Option<u64>,
+) -> Result<ArPreview, String> {
+    let path = app.state::<PathScope>().check(&asset_id)?;
+    if !path.is_file() {
+        return Err(format!("File not found: {}", path.display()));
+    }
+    let files = PreviewFiles::for_model(&path)?;
+    let minutes = minutes.unwrap_or(DEFAULT_MINUTES).max(1);
+
+    let notify = app.clone();
+    let address = app
+        .state::<ArPreviewServer>()
+        .start(
+            files.clone(),
+            Duration::from_secs(minutes * 60),
+            move |url| {
+                if let Err(e) = notify.emit("ar-preview-stopped", ArPreviewStopped { url }) {
+                    log::warn!("Failed to emit ar-preview-stopped: {}", e);
+                }
+            },
+        )
+        .await?;
+    let qr = QrCode::encode(address.url.as_bytes())?;
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+
+    Ok(ArPreview {
+        asset_id,
+        qr_svg: qr.svg(4),
+        url: address.url,
+        glb_path: files.glb.map(|p| p.to_string_lossy().to_string()),
+        usdz_path: files.usdz.map(|p| p.to_string_lossy().to_string()),
+        expires_ms: now_ms + minutes * 60_000,
+    })
+}
+
+/// Stop serving the AR preview; `false` if none was running
+#[command]
+#[instrument(skip_all, err)]
+pub async fn stop_ar_preview(server: State<'_, ArPreviewServer>) -> Result<bool, String> {
+    Ok(server.stop())
+}
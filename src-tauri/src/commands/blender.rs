@@ -0,0 +1,216 @@
+use crate::commands::model_loader::ModelAnalysis;
+use crate::utils::analysis_cache::AnalysisCache;
+use crate::utils::blender::{self, EDIT_SCRIPT, RESULT_FILE};
+use crate::utils::changes::{self, ChangeReport, ChangeSet};
+use crate::utils::dependencies;
+use crate::utils::mesh_files::MeshFileFormat;
+use crate::utils::path_scope::PathScope;
+use crate::utils::settings::SettingsStore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle, Emitter, Manager};
+use tracing::instrument;
+
+/// How often a session checks for a new result and whether Blender is open
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Result of `edit_in_blender`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlenderEdit {
+    pub asset_id: String,
+    pub blender: String,
+    /// Session folder holding the copy Blender edits
+    pub work_dir: String,
+}
+
+/// Payload of the `blender-edit-saved` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlenderVersion {
+    pub asset_id: String,
+    pub version_path: String,
+    /// `None` if the saved file couldn't be analyzed
+    pub analysis: Option<ModelAnalysis>,
+    pub changes: ChangeReport,
+}
+
+/// Payload of the `blender-edit-failed` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlenderEditError {
+    pub asset_id: String,
+    pub error: String,
+}
+
+/// Payload of the `blender-edit-closed` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlenderEditClosed {
+    pub asset_id: String,
+    /// Versions stored during the session, oldest first
+    pub versions: Vec<String>,
+    pub exit_code: Option<i32>,
+}
+
+/// Open a model in Blender and store each save as a new version of it
+///
+/// The model is copied to a session folder first, so Blender never writes
+/// to the library. Every time the scene is saved in Blender, the result is
+/// stored next to the model as `<name>_v<N>.glb`, analyzed, and announced
+/// with a `blender-edit-saved` event; `blender-edit-closed` follows when
+/// Blender exits. The Blender executable comes from the settings, or the
+/// usual install location.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn edit_in_blender(app: AppHandle, asset_id: String) -> Result<BlenderEdit, String> {
+    let settings = app.state::<SettingsStore>().get();
+    changes::ensure_writable(&settings)?;
+    let path = app.state::<PathScope>().check(&asset_id)?;
+    if !path.is_file() {
+        return Err(format!("File not found: {}", path.display()));
+    }
+    MeshFileFormat::from_path(&path)?;
+
+    let work_dir = session_dir();
+    let copy = work_dir.join(path.file_name().unwrap_or_default());
+    let found = dependencies::resolve(&path)?;
+    for (source, dest) in dependencies::copied_files(&path, &found, &copy) {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::copy(&source, &dest)
+            .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+    }
+    let script = work_dir.join("sweedle_edit.py");
+    fs::write(&script, EDIT_SCRIPT)
+        .map_err(|e| format!("Failed to write {}: {}", script.display(), e))?;
+
+    let executable = blender::executable(&settings.blender_executable);
+    let child = Command::new(&executable)
+        .arg("--python")
+        .arg(&script)
+        .arg("--")
+        .arg(&copy)
+        .arg(work_dir.join(RESULT_FILE))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| {
+            let _ = fs::remove_dir_all(&work_dir);
+            format!("Failed to start Blender ({}): {}", executable.display(), e)
+        })?;
+
+    let (session_app, model, dir) = (app.clone(), path.clone(), work_dir.clone());
+    let id = asset_id.clone();
+    thread::spawn(move || watch_session(&session_app, &id, &model, &dir, child));
+
+    Ok(BlenderEdit {
+        asset_id,
+        blender: executable.to_string_lossy().to_string(),
+        work_dir: work_dir.to_string_lossy().to_string(),
+    })
+}
+
+/// Store each new result until Blender exits, then remove the session
+fn watch_session(app: &AppHandle, asset_id: &str, model: &Path, work_dir: &Path, mut child: Child) {
+    let result = work_dir.join(RESULT_FILE);
+    let mut stored = None;
+    let mut versions = Vec::new();
+    let exit_code = loop {
+        let exited = match child.try_wait() {
+            Ok(status) => status.map(|s| s.code()),
+            Err(e) => {
+                log::warn!("Lost track of Blender editing {}: {}", asset_id, e);
+                Some(None)
+            }
+        };
+        // Checked once more after Blender exits, for a save just before
+        let stamp = fs::metadata(&result)
+            .and_then(|m| Ok((m.len(), m.modified()?)))
+            .ok();
+        if stamp.is_some() && stamp != stored {
+            stored = stamp;
+            match store_version(app, asset_id, model, &result) {
+                Ok(version) => {
+                    versions.push(version.version_path.clone());
+                    emit(app, "blender-edit-saved", version);
+                }
+                Err(error) => emit(
+                    app,
+                    "blender-edit-failed",
+                    BlenderEditError {
+                        asset_id: asset_id.to_string(),
+                        error,
+                    },
+                ),
+            }
+        }
+        if let Some(code) = exited {
+            break code;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    if let Err(e) = fs::remove_dir_all(work_dir) {
+        log::warn!("Failed to remove {}: {}", work_dir.display(), e);
+    }
+    emit(
+        app,
+        "blender-edit-closed",
+        BlenderEditClosed {
+            asset_id: asset_id.to_string(),
+            versions,
+            exit_code,
+        },
+    );
+}
+
+fn store_version(
+    app: &AppHandle,
+    asset_id: &str,
+    model: &Path,
+    result: &Path,
+) -> Result<BlenderVersion, String> {
+    let settings = app.state::<SettingsStore>().get();
+    let mut changes = ChangeSet::new(&settings, None)?;
+    let version = app
+        .state::<PathScope>()
+        .check(blender::next_version_path(model))?;
+    let bytes =
+        fs::read(result).map_err(|e| format!("Failed to read {}: {}", result.display(), e))?;
+    changes.write(&version, &bytes)?;
+
+    let analysis = match app.state::<AnalysisCache>().analyze(&version) {
+        Ok(update) => Some(update.analysis),
+        Err(e) => {
+            log::warn!("Failed to analyze {}: {}", version.display(), e);
+            None
+        }
+    };
+    Ok(BlenderVersion {
+        asset_id: asset_id.to_string(),
+        version_path: version.to_string_lossy().to_string(),
+        analysis,
+        changes: changes.finish(),
+    })
+}
+
+/// A new folder for a session's files under the system temp folder
+fn session_dir() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    std::env::temp_dir()
+        .join("sweedle-blender")
+        .join(format!("{}-{}", std::process::id(), nanos))
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        log::warn!("Failed to emit {}: {}", event, e);
+    }
+}
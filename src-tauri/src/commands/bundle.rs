@@ -0,0 +1,294 @@
+use crate::commands::library::{self, index_root, library};
+use crate::utils::asset_history::{AssetHistory, HistoryEntry, HistoryKind};
+use crate::utils::bundle::{self, BundleManifest, BundledAsset, ASSETS_DIR, EXTENSION};
+use crate::utils::changes::{ChangeReport, ChangeSet};
+use crate::utils::licenses::LicenseStore;
+use crate::utils::path_scope::PathScope;
+use crate::utils::provenance;
+use crate::utils::quarantine::{self, ParseLimits, QuarantineVerdict};
+use crate::utils::settings::SettingsStore;
+use crate::utils::storage_layout::{self, Template, PER_ASSET};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle, Manager};
+use tracing::{instrument, Span};
+
+/// Result of `export_bundle`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedBundle {
+    pub out_path: String,
+    pub asset_count: usize,
+    pub file_count: usize,
+    pub changes: ChangeReport,
+}
+
+/// Result of `import_bundle`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedBundle {
+    pub asset_ids: Vec<String>,
+    pub file_count: usize,
+    /// Licenses recorded for assets that had none
+    pub license_count: usize,
+    /// Verdicts of models that failed the sandboxed parse; their assets are
+    /// left out
+    pub rejected: Vec<QuarantineVerdict>,
+    pub changes: ChangeReport,
+}
+
+/// Pack library assets into one `.sweedlepack` file for sharing
+///
+/// Each asset brings its models or images, thumbnail and companion files,
+/// with its category and recorded license in the bundle's manifest. Assets
+/// are looked up in the saved library index; an id held by several roots is
+/// taken from the first.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_bundle(
+    app: AppHandle,
+    asset_ids: Vec<String>,
+    out_path: String,
+    dry_run: Option<bool>,
+) -> Result<ExportedBundle, String> {
+    let mut out_path = app.state::<PathScope>().check(&out_path)?;
+    if out_path.extension().is_none() {
+        out_path.set_extension(EXTENSION);
+    }
+    let settings = app.state::<SettingsStore>().get();
+    let mut changes = ChangeSet::new(&settings, dry_run)?;
+    let layout = settings.storage_layout.parse()?;
+
+    let span = Span::current();
+    let handle = app.clone();
+    let (bytes, manifest, file_count) = tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let app = handle;
+            let library = library(&app);
+            let mut by_root: BTreeMap<String, Vec<String>> = BTreeMap::new();
+            for id in &asset_ids {
+                let found = library
+                    .assets
+                    .iter()
+                    .find(|a| a.asset.id == *id)
+                    .ok_or_else(|| format!("No asset {} in the library", id))?;
+                let ids = by_root.entry(found.root.clone()).or_default();
+                if !ids.contains(id) {
+                    ids.push(id.clone());
+                }
+            }
+
+            let scope = app.state::<PathScope>();
+            let licenses = app.state::<LicenseStore>();
+            let per_asset = Template::parse(PER_ASSET)?;
+            let mut assets = Vec::new();
+            let mut files = Vec::new();
+            for (root_name, ids) in by_root {
+                let root = library
+                    .roots
+                    .iter()
+                    .find(|root| root.name == root_name)
+                    .ok_or_else(|| format!("Unknown storage root: {}", root_name))?;
+                let laid_out = layout.scan(&scope.check(&root.path)?)?;
+                for id in ids {
+                    let asset = laid_out
+                        .iter()
+                        .find(|asset| asset.id == id)
+                        .ok_or_else(|| format!("{} is no longer in {}", id, root_name))?;
+                    let placed = storage_layout::place_asset(
+                        asset,
+                        &layout,
+                        Path::new(ASSETS_DIR),
+                        &per_asset,
+                        asset.category.clone(),
+                    )?
+                    .ok_or_else(|| format!("{} has no model or image file", id))?;
+                    let names: Vec<(PathBuf, String)> = placed
+                        .files
+                        .into_iter()
+                        .map(|(source, dest)| {
+                            let name = dest.to_string_lossy().replace('\\', "/");
+                            (source, name)
+                        })
+                        .collect();
+                    assets.push(BundledAsset {
+                        license: licenses.license(&id),
+                        category: asset.category.clone(),
+                        files: names.iter().map(|(_, name)| name.clone()).collect(),
+                        id,
+                    });
+                    files.extend(names);
+                }
+            }
+            let manifest = BundleManifest::new(now_ms(), assets);
+            let bytes = bundle::write(&manifest, &files)?;
+            Ok::<_, String>((bytes, manifest, files.len()))
+        })
+    })
+    .await
+    .map_err(|e| format!("Packing failed: {}", e))??;
+    changes.write(&out_path, &bytes)?;
+    if !changes.is_dry_run() {
+        let ids: Vec<String> = manifest.assets.iter().map(|a| a.id.clone()).collect();
+        library::record_exports(&app, &ids);
+    }
+
+    Ok(ExportedBundle {
+        out_path: out_path.to_string_lossy().to_string(),
+        asset_count: manifest.assets.len(),
+        file_count,
+        changes: changes.finish(),
+    })
+}
+
+/// Add the assets of a `.sweedlepack` file to a storage folder
+///
+/// Assets are placed where the current storage layout puts them, with the
+/// categories from the bundle. Each model is parsed in the quarantine
+/// sandbox first, and an asset with a model that fails is left out. Nothing
+/// is written if any file would replace an existing one. Licenses in the
+/// bundle are recorded for assets that don't have one yet, each asset's
+/// history gets the import, and a storage root at `storage_path` is indexed
+/// again.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn import_bundle(
+    app: AppHandle,
+    path: String,
+    storage_path: String,
+    dry_run: Option<bool>,
+) -> Result<ImportedBundle, String> {
+    let scope = app.state::<PathScope>();
+    let path = scope.check(&path)?;
+    let root = scope.check(&storage_path)?;
+    if !root.is_dir() {
+        return Err(format!("Storage path is not a directory: {}", storage_path));
+    }
+    let settings = app.state::<SettingsStore>().get();
+    let changes = ChangeSet::new(&settings, dry_run)?;
+
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let staging = std::env::temp_dir().join(format!(
+                "sweedle-bundle-{}-{}",
+                std::process::id(),
+                now_ms()
+            ));
+            let imported = import_staged(&app, &path, &root, &staging, changes);
+            if let Err(e) = fs::remove_dir_all(&staging) {
+                log::warn!("Failed to remove {}: {}", staging.display(), e);
+            }
+            imported
+        })
+    })
+    .await
+    .map_err(|e| format!("Import failed: {}", e))?
+}
+
+/// Unpack a bundle into `staging`, then copy its assets into `root`
+fn import_staged(
+    app: &AppHandle,
+    bundle_path: &Path,
+    root: &Path,
+    staging: &Path,
+    mut changes: ChangeSet,
+) -> Result<ImportedBundle, String> {
+    let manifest = bundle::unpack(bundle_path, staging)?;
+    let settings = app.state::<SettingsStore>().get();
+    let layout = settings.storage_layout.parse()?;
+    let per_asset = Template::parse(PER_ASSET)?;
+    let staged = staging.join(ASSETS_DIR);
+    let staged_assets = if staged.is_dir() {
+        per_asset.scan(&staged)?
+    } else {
+        Vec::new()
+    };
+    let limits = ParseLimits::default();
+    let mut assets = Vec::new();
+    let mut rejected = Vec::new();
+    for asset in staged_assets {
+        let verdicts = asset
+            .models
+            .iter()
+            .map(|model| quarantine::parse_in_sandbox(model, &limits))
+            .collect::<Result<Vec<_>, String>>()?;
+        let failed: Vec<_> = verdicts.into_iter().filter(|v| !v.passed).collect();
+        if failed.is_empty() {
+            assets.push(asset);
+        } else {
+            log::warn!(
+                "Leaving {} out of the import: a model failed inspection",
+                asset.id
+            );
+            rejected.extend(failed);
+        }
+    }
+    let categories: HashMap<String, String> = manifest
+        .assets
+        .iter()
+        .filter_map(|asset| Some((asset.id.clone(), asset.category.clone()?)))
+        .collect();
+    let plan = storage_layout::plan_moves(&assets, &per_asset, root, &layout, &categories)
+        .map_err(|e| format!("Can't import {}: {}", bundle_path.display(), e))?;
+
+    let scope = app.state::<PathScope>();
+    for (source, dest) in &plan.moves {
+        let contents =
+            fs::read(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        changes.write(&scope.check(dest)?, &contents)?;
+    }
+
+    let mut license_count = 0;
+    if !changes.is_dry_run() {
+        let licenses = app.state::<LicenseStore>();
+        for asset in &manifest.assets {
+            if asset.license.is_some() && licenses.license(&asset.id).is_none() {
+                licenses.set_license(&asset.id, asset.license.clone())?;
+                license_count += 1;
+            }
+        }
+        let history = app.state::<AssetHistory>();
+        for asset in &assets {
+            let first = asset.models.first().or(asset.images.first());
+            let hash = first.map(|file| provenance::file_hash(file)).transpose()?;
+            let placed = plan
+                .moves
+                .iter()
+                .find(|(source, _)| Some(source) == first)
+                .and_then(|(_, dest)| dest.parent());
+            history.record(
+                &asset.id,
+                HistoryEntry {
+                    source: Some(bundle_path.to_string_lossy().to_string()),
+                    path: placed.map(|dir| dir.to_string_lossy().to_string()),
+                    hash,
+                    ..HistoryEntry::new(HistoryKind::Imported)
+                },
+            )?;
+        }
+        let registered = settings
+            .storage_roots
+            .iter()
+            .find(|r| scope.check(&r.path).is_ok_and(|path| path == root));
+        if let Some(registered) = registered {
+            index_root(app, registered)?;
+        }
+    }
+
+    Ok(ImportedBundle {
+        asset_ids: assets.into_iter().map(|asset| asset.id).collect(),
+        file_count: plan.moves.len(),
+        license_count,
+        rejected,
+        changes: changes.finish(),
+    })
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
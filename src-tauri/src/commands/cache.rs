@@ -0,0 +1,201 @@
+use crate::utils::asset_cache::{AssetCache, CacheKind, CacheStats};
+use crate::utils::gltf_document::GltfDocument;
+use crate::utils::material_preview::{PreviewMaterial, DEFAULT_PREVIEW_SIZE, MAX_PREVIEW_SIZE};
+use crate::utils::path_scope::PathScope;
+use crate::utils::platform;
+use crate::utils::settings::SettingsStore;
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use tauri::{command, AppHandle, Manager, State};
+use tracing::{instrument, Span};
+
+/// Longest side of textures transcoded for display unless one is given
+const DEFAULT_TEXTURE_SIZE: u32 = 2048;
+
+/// Size of the thumbnail, preview and texture cache, by kind
+#[command]
+#[instrument(skip_all, err)]
+pub async fn get_cache_stats(
+    cache: State<'_, AssetCache>,
+    settings: State<'_, SettingsStore>,
+) -> Result<CacheStats, String> {
+    Ok(cache.stats(settings.get().cache.max_bytes))
+}
+
+/// Remove cached entries of one kind, or all of them, and return what's left
+#[command]
+#[instrument(skip_all, err)]
+pub async fn clear_cache(
+    cache: State<'_, AssetCache>,
+    settings: State<'_, SettingsStore>,
+    kind: Option<CacheKind>,
+) -> Result<CacheStats, String> {
+    cache.clear(kind)?;
+    Ok(cache.stats(settings.get().cache.max_bytes))
+}
+
+/// Keep a PNG thumbnail the viewer rendered for a model
+///
+/// It's dropped when the model changes or the cache needs the space.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn store_thumbnail(
+    cache: State<'_, AssetCache>,
+    settings: State<'_, SettingsStore>,
+    scope: State<'_, PathScope>,
+    path: String,
+    png: Vec<u8>,
+) -> Result<(), String> {
+    let path = scope.check(&path)?;
+    if image::guess_format(&png).ok() != Some(ImageFormat::Png) {
+        return Err("Thumbnails must be PNG images".to_string());
+    }
+    let key = AssetCache::source_key(&path, "")
+        .ok_or_else(|| format!("File not found: {}", path.display()))?;
+    cache.put(
+        CacheKind::Thumbnail,
+        &format!("{}.png", key),
+        &png,
+        settings.get().cache.max_bytes,
+    )
+}
+
+/// The cached thumbnail of a model, if one was stored since it last changed
+#[command]
+#[instrument(skip_all, err)]
+pub async fn get_thumbnail(
+    cache: State<'_, AssetCache>,
+    scope: State<'_, PathScope>,
+    path: String,
+) -> Result<Option<Vec<u8>>, String> {
+    let path = scope.check(&path)?;
+    Ok(AssetCache::source_key(&path, "")
+        .and_then(|key| cache.get(CacheKind::Thumbnail, &format!("{}.png", key))))
+}
+
+/// A texture as a PNG the viewer can display, scaled down to fit
+/// `max_size` (2048 by default)
+///
+/// Formats webviews can't show, such as TGA, TIFF and HDR, are converted.
+/// Results are cached per file version and size.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn transcode_texture(
+    app: AppHandle,
+    path: String,
+    max_size: Option<u32>,
+) -> Result<Vec<u8>, String> {
+    let path = app.state::<PathScope>().check(&path)?;
+    let max_size = max_size.unwrap_or(DEFAULT_TEXTURE_SIZE).max(1);
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let cache = app.state::<AssetCache>();
+            let name = AssetCache::source_key(&path, &max_size.to_string())
+                .map(|key| format!("{}.png", key));
+            if let Some(png) = name
+                .as_deref()
+                .and_then(|n| cache.get(CacheKind::Texture, n))
+            {
+                return Ok(png);
+            }
+
+            let image = image::open(&path)
+                .map_err(|e| format!("Failed to load {}: {}", path.display(), e))?;
+            let image = if image.width() > max_size || image.height() > max_size {
+                image.resize(max_size, max_size, image::imageops::FilterType::Lanczos3)
+            } else {
+                image
+            };
+            let mut png = Cursor::new(Vec::new());
+            image
+                .write_to(&mut png, ImageFormat::Png)
+                .map_err(|e| format!("Failed to encode {}: {}", path.display(), e))?;
+            let png = png.into_inner();
+
+            if let Some(name) = &name {
+                let max_bytes = app.state::<SettingsStore>().get().cache.max_bytes;
+                if let Err(e) = cache.put(CacheKind::Texture, name, &png, max_bytes) {
+                    log::warn!("Failed to cache texture {}: {}", path.display(), e);
+                }
+            }
+            Ok(png)
+        })
+    })
+    .await
+    .map_err(|e| format!("Transcoding failed: {}", e))?
+}
+
+/// A material's swatch from `render_material_previews`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaterialPreview {
+    pub index: usize,
+    pub name: String,
+    /// PNG with a transparent background
+    pub png: Vec<u8>,
+}
+
+/// Every material of a glTF or GLB rendered on a preview sphere, `size`
+/// pixels square (128 by default, at most 1024)
+///
+/// Swatches are cached per file version and size. The swatch renderer is
+/// left out on mobile, where the webview draws materials itself.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn render_material_previews(
+    app: AppHandle,
+    path: String,
+    size: Option<u32>,
+) -> Result<Vec<MaterialPreview>, String> {
+    platform::desktop_only("Material preview rendering")?;
+    let path = app.state::<PathScope>().check(&path)?;
+    let size = size
+        .unwrap_or(DEFAULT_PREVIEW_SIZE)
+        .clamp(1, MAX_PREVIEW_SIZE);
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let cache = app.state::<AssetCache>();
+            let max_bytes = app.state::<SettingsStore>().get().cache.max_bytes;
+            let key = AssetCache::source_key(&path, &format!("material-{}", size));
+            let document = GltfDocument::open(&path)?;
+            PreviewMaterial::all(&document)
+                .into_iter()
+                .enumerate()
+                .map(|(index, material)| {
+                    let name = key.as_ref().map(|key| format!("{}-{}.png", key, index));
+                    if let Some(png) = name
+                        .as_deref()
+                        .and_then(|n| cache.get(CacheKind::Thumbnail, n))
+                    {
+                        return Ok(MaterialPreview {
+                            index,
+                            name: material.name,
+                            png,
+                        });
+                    }
+
+                    let mut png = Cursor::new(Vec::new());
+                    material
+                        .render(size)
+                        .write_to(&mut png, ImageFormat::Png)
+                        .map_err(|e| format!("Failed to encode swatch: {}", e))?;
+                    let png = png.into_inner();
+                    if let Some(name) = &name {
+                        if let Err(e) = cache.put(CacheKind::Thumbnail, name, &png, max_bytes) {
+                            log::warn!("Failed to cache swatch of {}: {}", path.display(), e);
+                        }
+                    }
+                    Ok(MaterialPreview {
+                        index,
+                        name: material.name,
+                        png,
+                    })
+                })
+                .collect()
+        })
+    })
+    .await
+    .map_err(|e| format!("Material previews failed: {}", e))?
+}
@@ -0,0 +1,75 @@
+use crate::commands::reports::{report_asset, validation_profile};
+use crate::utils::analysis_cache::AnalysisCache;
+use crate::utils::asset_cache::{AssetCache, CacheKind};
+use crate::utils::clipboard;
+use crate::utils::i18n;
+use crate::utils::image_thumbnail::{self, THUMBNAIL_SIZE};
+use crate::utils::path_scope::PathScope;
+use crate::utils::report;
+use crate::utils::settings::SettingsStore;
+use tauri::{command, AppHandle, Manager, State};
+use tracing::{instrument, Span};
+
+/// Copy the absolute path of an asset folder or file
+#[command]
+#[instrument(skip_all, err)]
+pub async fn copy_asset_path(scope: State<'_, PathScope>, path: String) -> Result<String, String> {
+    let path = scope.check(&path)?;
+    let absolute = std::fs::canonicalize(&path)
+        .map_err(|e| format!("File not found: {}: {}", path.display(), e))?;
+    let text = absolute.to_string_lossy().to_string();
+    clipboard::copy_text(&text)?;
+    Ok(text)
+}
+
+/// Copy the analysis of an asset folder or model file as a Markdown table
+/// and return the text
+#[command]
+#[instrument(skip_all, err)]
+pub async fn copy_asset_summary(app: AppHandle, path: String) -> Result<String, String> {
+    let path = app.state::<PathScope>().check(&path)?;
+    let profile = validation_profile(&app.state::<SettingsStore>().get(), None)?;
+    let span = Span::current();
+    let summary = tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let id = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.to_string_lossy().to_string());
+            let asset = report_asset(&app.state::<AnalysisCache>(), &id, &path, profile.as_ref());
+            report::markdown(&asset, i18n::locale())
+        })
+    })
+    .await
+    .map_err(|e| format!("Analysis failed: {}", e))?;
+    clipboard::copy_text(&summary)?;
+    Ok(summary)
+}
+
+/// Copy the thumbnail of a model, as stored by `store_thumbnail`, or of an
+/// image as a PNG
+#[command]
+#[instrument(skip_all, err)]
+pub async fn copy_thumbnail(app: AppHandle, path: String) -> Result<(), String> {
+    let path = app.state::<PathScope>().check(&path)?;
+    let span = Span::current();
+    let png = tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let cache = app.state::<AssetCache>();
+            if image_thumbnail::is_image(&path) {
+                let max_bytes = app.state::<SettingsStore>().get().cache.max_bytes;
+                return match image_thumbnail::cached(&cache, &path, max_bytes)? {
+                    Some(cached) => std::fs::read(&cached)
+                        .map_err(|e| format!("Failed to read {}: {}", cached.display(), e)),
+                    None => image_thumbnail::thumbnail_png(&path, THUMBNAIL_SIZE),
+                };
+            }
+            AssetCache::source_key(&path, "")
+                .and_then(|key| cache.get(CacheKind::Thumbnail, &format!("{}.png", key)))
+                .ok_or_else(|| format!("No thumbnail stored for {}", path.display()))
+        })
+    })
+    .await
+    .map_err(|e| format!("Thumbnail failed: {}", e))??;
+    clipboard::copy_png(&png)
+}
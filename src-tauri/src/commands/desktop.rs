@@ -0,0 +1,82 @@
+use crate::commands::scope::pick_program;
+use crate::utils::desktop;
+use crate::utils::path_scope::PathScope;
+use crate::utils::settings::SettingsStore;
+use std::path::PathBuf;
+use tauri::{command, AppHandle, State};
+use tracing::instrument;
+
+/// Show a file or folder in Finder, Explorer or the desktop's file manager
+#[command]
+#[instrument(skip_all, err)]
+pub async fn reveal_in_file_manager(
+    scope: State<'_, PathScope>,
+    path: String,
+) -> Result<(), String> {
+    desktop::reveal(&existing(&scope, &path)?)
+}
+
+/// Open a file in the application the system associates with its type
+#[command]
+#[instrument(skip_all, err)]
+pub async fn open_with_default(scope: State<'_, PathScope>, path: String) -> Result<(), String> {
+    desktop::open(&existing(&scope, &path)?, None)
+}
+
+/// Open a file in a given application, such as Blender
+///
+/// `application` must be one the user picked with
+/// `pick_open_with_application`; anything else is refused, so the webview
+/// can't have an arbitrary program run.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn open_with(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    application: String,
+) -> Result<(), String> {
+    let application = application.trim();
+    if application.is_empty() {
+        return Err("No application given".to_string());
+    }
+    if !settings
+        .get()
+        .open_with_applications
+        .iter()
+        .any(|picked| picked == application)
+    {
+        return Err(format!(
+            "{} hasn't been picked as an application to open files with",
+            application
+        ));
+    }
+    desktop::open(&existing(&scope, &path)?, Some(application))
+}
+
+/// Let the user pick an application with a native dialog and add it to
+/// the ones `open_with` accepts
+///
+/// Returns its path, or `None` if the dialog was cancelled.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn pick_open_with_application(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+) -> Result<Option<String>, String> {
+    let Some(picked) = pick_program(&app, "Open with").await? else {
+        return Ok(None);
+    };
+    let application = picked.to_string_lossy().to_string();
+    settings.update(|s| {
+        if !s.open_with_applications.contains(&application) {
+            s.open_with_applications.push(application.clone());
+        }
+    })?;
+    Ok(Some(application))
+}
+
+fn existing(scope: &PathScope, path: &str) -> Result<PathBuf, String> {
+    let path = scope.check(path)?;
+    std::fs::canonicalize(&path).map_err(|e| format!("File not found: {}: {}", path.display(), e))
+}
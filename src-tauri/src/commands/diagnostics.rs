@@ -0,0 +1,391 @@
+use crate::utils::asset_cache::AssetCache;
+use crate::utils::blender;
+use crate::utils::bundle;
+use crate::utils::changes::{ChangeReport, ChangeSet};
+use crate::utils::compute;
+use crate::utils::diagnostics::{self, Check, CheckArea, CheckStatus, DiagnosticsReport};
+use crate::utils::external_tools::find_executable;
+use crate::utils::jobs::JobQueue;
+use crate::utils::library::LibraryIndex;
+use crate::utils::logging::{LogLevel, LogRecord, Logs};
+use crate::utils::metrics::{Metrics, PerformanceReport};
+use crate::utils::path_scope::PathScope;
+use crate::utils::rpc_server::RpcServer;
+use crate::utils::settings::{AppSettings, ProcessingBackend, SettingsStore};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle, Manager};
+use tracing::{instrument, Span};
+
+/// Records `get_recent_logs` returns unless told otherwise
+const DEFAULT_LOG_LIMIT: usize = 200;
+
+/// Result of `export_debug_bundle`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedDebugBundle {
+    pub out_path: String,
+    /// Archive paths of the files in the bundle
+    pub files: Vec<String>,
+    pub changes: ChangeReport,
+}
+
+/// Timing and heap usage of every instrumented command and pipeline stage
+///
+/// Pass `reset: true` to clear the collected metrics after reading them.
+#[command]
+pub async fn get_performance_report(reset: Option<bool>) -> Result<PerformanceReport, String> {
+    let metrics = Metrics::global();
+    let report = metrics.report();
+    if reset.unwrap_or(false) {
+        metrics.reset();
+    }
+    Ok(report)
+}
+
+/// The newest log records at `level` (default `info`) or more severe,
+/// oldest first, at most `limit` (default 200) of them
+#[command]
+pub async fn get_recent_logs(
+    level: Option<LogLevel>,
+    limit: Option<usize>,
+) -> Result<Vec<LogRecord>, String> {
+    Ok(Logs::global().recent(
+        level.unwrap_or(LogLevel::Info),
+        limit.unwrap_or(DEFAULT_LOG_LIMIT),
+    ))
+}
+
+/// Pack what a bug report needs into one `.tar.gz` file: the log files,
+/// the settings, the performance report, recent jobs and a description of
+/// the system
+///
+/// Without `out_path` the bundle is written to the app's log folder. The
+/// settings include the paths of storage roots and watch folders.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_debug_bundle(
+    app: AppHandle,
+    out_path: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<ExportedDebugBundle, String> {
+    let created_ms = now_ms();
+    let out_path = match out_path {
+        Some(path) => app.state::<PathScope>().check(&path)?,
+        None => app
+            .path()
+            .app_log_dir()
+            .map_err(|e| format!("Failed to find the log folder: {}", e))?
+            .join(format!("sweedle-debug-{}.tar.gz", created_ms)),
+    };
+    let settings = app.state::<SettingsStore>().get();
+    let mut changes = ChangeSet::new(&settings, dry_run)?;
+
+    let to_json = |value: serde_json::Value| {
+        serde_json::to_vec_pretty(&value)
+            .map_err(|e| format!("Failed to serialize debug bundle: {}", e))
+    };
+    let system = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "gpu_feature": cfg!(feature = "gpu"),
+        "pid": std::process::id(),
+        "created_ms": created_ms,
+    });
+    let mut files = vec![
+        ("system.json".to_string(), to_json(system)?),
+        ("settings.json".to_string(), to_json(json!(settings))?),
+        (
+            "performance.json".to_string(),
+            to_json(json!(Metrics::global().report()))?,
+        ),
+        (
+            "jobs.json".to_string(),
+            to_json(json!(app.state::<JobQueue>().jobs()))?,
+        ),
+    ];
+    let logs = Logs::global();
+    for path in logs.files() {
+        let contents =
+            fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string());
+        files.push((format!("logs/{}", name.unwrap_or_default()), contents));
+    }
+    if logs.files().is_empty() {
+        // Not writing to files, so what is still in memory goes instead
+        let recent = logs.recent(LogLevel::Trace, usize::MAX);
+        files.push(("logs/recent.json".to_string(), to_json(json!(recent))?));
+    }
+
+    let bytes = bundle::archive(&files, created_ms / 1000)?;
+    changes.write(&out_path, &bytes)?;
+    Ok(ExportedDebugBundle {
+        out_path: out_path.to_string_lossy().to_string(),
+        files: files.into_iter().map(|(name, _)| name).collect(),
+        changes: changes.finish(),
+    })
+}
+
+/// Check what commonly breaks an install, for the troubleshooting page
+///
+/// Covers the GPU processing and rendering run on, free space and write
+/// access at each storage root and the app's folders, the saved library
+/// index, the cache, and the external tools and local API other programs
+/// work through. Storage roots aren't written to in read-only mode.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn run_diagnostics(app: AppHandle) -> Result<DiagnosticsReport, String> {
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let settings = app.state::<SettingsStore>().get();
+            let mut checks = vec![gpu_check(&settings)];
+            checks.extend(folder_checks(&app, &settings)?);
+            checks.extend(index_checks(&app, &settings));
+            checks.push(cache_check(&app, &settings));
+            checks.extend(plugin_checks(&app, &settings));
+            Ok(DiagnosticsReport::new(now_ms(), checks))
+        })
+    })
+    .await
+    .map_err(|e| format!("Diagnostics failed: {}", e))?
+}
+
+fn gpu_check(settings: &AppSettings) -> Check {
+    if let Some(adapter) = compute::gpu_adapter_name() {
+        return Check::ok(CheckArea::Gpu, "GPU", adapter);
+    }
+    let message = if cfg!(feature = "gpu") {
+        "No GPU adapter could be initialized"
+    } else {
+        "This build has no GPU support"
+    };
+    let mut check = Check::warning(CheckArea::Gpu, "GPU", message)
+        .hint("Processing and renders use the CPU; updating the graphics driver may help");
+    if settings.processing_backend == ProcessingBackend::Gpu {
+        check.status = CheckStatus::Error;
+        check.hint = Some("The processing backend is set to GPU; set it to Auto or CPU".into());
+    }
+    check
+}
+
+/// Free space and write access at the storage roots and the app's folders
+fn folder_checks(app: &AppHandle, settings: &AppSettings) -> Result<Vec<Check>, String> {
+    let mut checks = Vec::new();
+    for root in &settings.storage_roots {
+        let name = format!("Storage root {}", root.name);
+        let path = Path::new(&root.path);
+        if !path.is_dir() {
+            checks.push(
+                Check::error(
+                    CheckArea::DiskSpace,
+                    &name,
+                    format!("Storage path is not a directory: {}", root.path),
+                )
+                .hint("Connect the drive or share it's on, or remove the root"),
+            );
+            continue;
+        }
+        checks.push(diagnostics::disk_space(&name, path));
+        checks.push(if settings.read_only {
+            Check::skipped(CheckArea::Permissions, &name, "Read-only mode is enabled")
+        } else {
+            diagnostics::write_access(&name, path)
+        });
+    }
+
+    let resolver = app.path();
+    let folder = |dir: tauri::Result<PathBuf>| {
+        dir.map_err(|e| format!("Failed to find the app folders: {}", e))
+    };
+    let data_dir = folder(resolver.app_data_dir())?;
+    checks.push(diagnostics::disk_space("App data folder", &data_dir));
+    for (name, dir) in [
+        ("App data folder", data_dir.clone()),
+        ("Settings folder", folder(resolver.app_config_dir())?),
+        ("Cache folder", folder(resolver.app_cache_dir())?),
+        ("Log folder", folder(resolver.app_log_dir())?),
+    ] {
+        // The app creates these as it needs them
+        if dir.is_dir() {
+            checks.push(diagnostics::write_access(name, &dir));
+        }
+    }
+    Ok(checks)
+}
+
+/// Whether the saved index reads and matches the roots and files on disk
+fn index_checks(app: &AppHandle, settings: &AppSettings) -> Vec<Check> {
+    let index = app.state::<LibraryIndex>();
+    let mut checks = Vec::new();
+    if let Some(file) = index.file().filter(|file| file.is_file()) {
+        let parsed = fs::read_to_string(file)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                serde_json::from_str::<serde_json::Value>(&json).map_err(|e| e.to_string())
+            });
+        if let Err(e) = parsed {
+            checks.push(
+                Check::error(
+                    CheckArea::Index,
+                    "Library index",
+                    format!("The saved index is damaged: {}", e),
+                )
+                .hint("Index the storage roots again to rebuild it"),
+            );
+        }
+    }
+
+    let reindex = "Index the storage root again";
+    for root in &settings.storage_roots {
+        let name = format!("Index of {}", root.name);
+        let Some(root_index) = index.get(&root.name) else {
+            checks.push(Check::warning(CheckArea::Index, name, "Not indexed yet").hint(reindex));
+            continue;
+        };
+        if let Some(error) = &root_index.error {
+            let message = format!("The last indexing failed: {}", error);
+            checks.push(Check::warning(CheckArea::Index, name, message).hint(reindex));
+            continue;
+        }
+        if root_index.path != root.path {
+            let message = format!(
+                "Indexed at {} but the root is now at {}",
+                root_index.path, root.path
+            );
+            checks.push(Check::warning(CheckArea::Index, name, message).hint(reindex));
+            continue;
+        }
+        if !Path::new(&root.path).is_dir() {
+            // Offline; the folder checks already say so
+            continue;
+        }
+        let missing = root_index
+            .assets
+            .iter()
+            .filter(|asset| {
+                [&asset.model_path, &asset.texture_path]
+                    .into_iter()
+                    .flatten()
+                    .any(|file| !Path::new(file).is_file())
+            })
+            .count();
+        let total = root_index.assets.len();
+        checks.push(if missing > 0 {
+            Check::warning(
+                CheckArea::Index,
+                name,
+                format!("{} of {} indexed assets are gone from disk", missing, total),
+            )
+            .hint(reindex)
+        } else {
+            Check::ok(CheckArea::Index, name, format!("{} assets", total))
+        });
+    }
+    checks
+}
+
+fn cache_check(app: &AppHandle, settings: &AppSettings) -> Check {
+    let cache = app.state::<AssetCache>();
+    let stats = cache.stats(settings.cache.max_bytes);
+    if stats.path.is_empty() {
+        return Check::skipped(CheckArea::Cache, "Cache", "No cache folder");
+    }
+    let entries: usize = stats.kinds.iter().map(|kind| kind.entry_count).sum();
+    let missing = cache.missing_entries();
+    let size = format!(
+        "{} entries, {} MB of {} MB",
+        entries,
+        stats.total_bytes >> 20,
+        stats.max_bytes >> 20
+    );
+    if missing > 0 {
+        Check::warning(
+            CheckArea::Cache,
+            "Cache",
+            format!("{}; {} are gone from disk", size, missing),
+        )
+        .hint("Clear the cache")
+    } else if stats.total_bytes > stats.max_bytes {
+        Check::warning(
+            CheckArea::Cache,
+            "Cache",
+            format!("{}, over its limit", size),
+        )
+        .hint("Clear the cache or raise its size limit")
+    } else {
+        Check::ok(CheckArea::Cache, "Cache", size)
+    }
+}
+
+/// Whether the external programs Sweedle runs can be found, and the state
+/// of the local API plugins in other tools connect to
+fn plugin_checks(app: &AppHandle, settings: &AppSettings) -> Vec<Check> {
+    let found = |name: &str, executable: &str, hint: &str| match find_executable(executable) {
+        Some(path) => Check::ok(CheckArea::Plugins, name, path.to_string_lossy()),
+        None => Check::warning(
+            CheckArea::Plugins,
+            name,
+            format!("{} wasn't found", executable),
+        )
+        .hint(hint),
+    };
+    let blender = blender::executable(&settings.blender_executable);
+    let mut checks = vec![
+        found(
+            "Blender",
+            &blender.to_string_lossy(),
+            "Install Blender or set its path in the settings; editing in Blender needs it",
+        ),
+        found(
+            "glTF Transform",
+            match settings.gltf_transform_executable.trim() {
+                "" => "gltf-transform",
+                configured => configured,
+            },
+            "Install @gltf-transform/cli or set its path; web viewer exports need it",
+        ),
+    ];
+
+    for tool in &settings.external_tools {
+        let name = format!("External tool {}", tool.id);
+        let mut check = found(&name, &tool.executable, "Fix the tool's executable path");
+        if check.status == CheckStatus::Warning {
+            check.status = CheckStatus::Error;
+        }
+        checks.push(check);
+    }
+    let upscaler = settings.texture_upscaler.trim();
+    if !upscaler.is_empty() && !settings.external_tools.iter().any(|t| t.id == upscaler) {
+        checks.push(
+            Check::error(
+                CheckArea::Plugins,
+                "Texture upscaler",
+                format!("No external tool with id {}", upscaler),
+            )
+            .hint("Add the tool or pick another upscaler in the settings"),
+        );
+    }
+
+    checks.push(match (app.state::<RpcServer>().url(), settings.local_api) {
+        (Some(url), _) => Check::ok(CheckArea::Plugins, "Local API", url),
+        (None, true) => Check::error(
+            CheckArea::Plugins,
+            "Local API",
+            "On in the settings but not running",
+        )
+        .hint("Turn the local API off and on again, and check the log for why it stopped"),
+        (None, false) => Check::skipped(CheckArea::Plugins, "Local API", "Off"),
+    });
+    checks
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,157 @@
+use crate::commands::scope::approve_program;
+use crate::utils::analysis_cache::{AnalysisCache, AnalysisUpdate};
+use crate::utils::changes;
+use crate::utils::external_tools::{self, ExternalTool, ToolOutput};
+use crate::utils::mesh_files::MeshFileFormat;
+use crate::utils::path_scope::PathScope;
+use crate::utils::settings::SettingsStore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use tauri::{command, AppHandle, Manager, State};
+use tracing::{instrument, Span};
+
+/// Result of `run_external_tool`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalToolRun {
+    pub tool_id: String,
+    pub asset_id: String,
+    #[serde(flatten)]
+    pub output: ToolOutput,
+    /// Files in the asset's folder the tool created or modified
+    pub changed: Vec<String>,
+    /// Fresh analysis of the asset and of glTF files the tool wrote
+    pub reanalyzed: Vec<AnalysisUpdate>,
+}
+
+/// Replace the registered external tools
+///
+/// Ids must be unique and argument templates may only use the known
+/// placeholders. A tool that is new, or whose executable or arguments
+/// changed, is only saved once the user approves its command line in a
+/// native dialog.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn set_external_tools(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    tools: Vec<ExternalTool>,
+) -> Result<Vec<ExternalTool>, String> {
+    let mut ids = HashSet::new();
+    let tools = tools
+        .into_iter()
+        .map(|mut tool| {
+            tool.id = tool.id.trim().to_string();
+            tool.executable = tool.executable.trim().to_string();
+            if tool.name.trim().is_empty() {
+                tool.name = tool.id.clone();
+            }
+            tool.validate()?;
+            if !ids.insert(tool.id.clone()) {
+                return Err(format!("External tool {} is listed twice", tool.id));
+            }
+            Ok(tool)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let saved = settings.get().external_tools;
+    for tool in &tools {
+        let unchanged = saved.iter().any(|old| {
+            old.id == tool.id
+                && old.executable == tool.executable
+                && old.arguments == tool.arguments
+        });
+        if unchanged {
+            continue;
+        }
+        let message = format!(
+            "Allow Sweedle to run {} on your models?\n\n{} {}",
+            tool.name,
+            tool.executable,
+            tool.arguments.join(" ")
+        );
+        if !approve_program(&app, message).await? {
+            return Err(format!("External tool {} was not approved", tool.id));
+        }
+    }
+
+    Ok(settings
+        .update(|s| s.external_tools = tools)?
+        .external_tools)
+}
+
+/// Run a registered tool on a model file and analyze what it left behind
+///
+/// The tool runs in the model's folder. Afterwards the model, and any glTF
+/// file the tool created or changed next to it, is analyzed again so the
+/// library shows the result. A tool that fails is reported, not an error.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn run_external_tool(
+    app: AppHandle,
+    tool_id: String,
+    asset_id: String,
+) -> Result<ExternalToolRun, String> {
+    let settings = app.state::<SettingsStore>().get();
+    // The tool can write anything, so it is never run in read-only mode
+    changes::ensure_writable(&settings)?;
+    let tool = settings
+        .external_tools
+        .iter()
+        .find(|tool| tool.id == tool_id)
+        .cloned()
+        .ok_or_else(|| format!("Unknown external tool: {}", tool_id))?;
+    let path = app.state::<PathScope>().check(&asset_id)?;
+    if !path.is_file() {
+        return Err(format!("File not found: {}", path.display()));
+    }
+
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+            let before = external_tools::snapshot(&dir);
+            let output = external_tools::run(&tool, &path)?;
+            let changed = external_tools::changed_files(&before, &external_tools::snapshot(&dir));
+            if !output.success() {
+                log::warn!(
+                    "External tool {} failed on {}: {}",
+                    tool.id,
+                    path.display(),
+                    output.stderr.lines().last().unwrap_or_default()
+                );
+            }
+
+            let cache = app.state::<AnalysisCache>();
+            let is_gltf = |file: &Path| {
+                MeshFileFormat::from_path(file).is_ok_and(|f| f == MeshFileFormat::Gltf)
+            };
+            let mut models = vec![path.clone()];
+            models.extend(changed.iter().filter(|f| **f != path).cloned());
+            let reanalyzed = models
+                .iter()
+                .filter(|model| is_gltf(model) && model.is_file())
+                .filter_map(|model| match cache.analyze(model) {
+                    Ok(update) => Some(update),
+                    Err(e) => {
+                        log::warn!("Failed to analyze {}: {}", model.display(), e);
+                        None
+                    }
+                })
+                .collect();
+
+            Ok(ExternalToolRun {
+                tool_id,
+                asset_id,
+                output,
+                changed: changed
+                    .iter()
+                    .map(|file| file.to_string_lossy().to_string())
+                    .collect(),
+                reanalyzed,
+            })
+        })
+    })
+    .await
+    .map_err(|e| format!("External tool failed: {}", e))?
+}
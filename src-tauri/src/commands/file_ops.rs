@@ -1,10 +1,22 @@
-use memmap2::Mmap;
+use crate::commands::jobs;
+use crate::utils::analysis_cache::AnalysisCache;
+use crate::utils::asset_cache::AssetCache;
+use crate::utils::changes::{ChangeReport, ChangeSet};
+use crate::utils::image_thumbnail;
+use crate::utils::media::{self, MediaFile};
+use crate::utils::path_scope::PathScope;
+use crate::utils::platform;
+use crate::utils::settings::SettingsStore;
+use crate::utils::storage_layout::{self, StorageLayout, Template};
+use crate::utils::usage_stats::UsageStats;
+use crate::utils::watcher::DirectoryWatchers;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
 use std::fs::{self, File};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
-use tauri::command;
-use walkdir::WalkDir;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tracing::{instrument, Span};
 
 /// Information about a file
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,50 +30,99 @@ pub struct FileInfo {
     pub is_directory: bool,
 }
 
+/// Paths that changed under a watched directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryChangedEvent {
+    pub root: String,
+    pub paths: Vec<String>,
+}
+
+/// A watched model that could not be re-analyzed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisFailedEvent {
+    pub path: String,
+    pub error: String,
+}
+
 /// Information about an asset in storage
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageAsset {
     pub id: String,
+    /// Folder holding the asset's model files
     pub path: String,
+    /// Preferred model file: GLB, then OBJ, then FBX
+    pub model_path: Option<String>,
+    /// Image file of an asset that is a texture or HDRI rather than a model
+    pub texture_path: Option<String>,
+    /// Category from a layout that sorts assets by category
+    pub category: Option<String>,
     pub has_glb: bool,
     pub has_obj: bool,
     pub has_fbx: bool,
     pub has_thumbnail: bool,
     pub glb_size: Option<u64>,
     pub thumbnail_path: Option<String>,
+    /// Videos and audio kept with the asset
+    #[serde(default)]
+    pub media: Vec<MediaFile>,
+}
+
+/// Result of `migrate_storage_layout`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageMigration {
+    pub from: StorageLayout,
+    pub to: StorageLayout,
+    pub asset_count: usize,
+    pub changes: ChangeReport,
 }
 
 /// Read file in chunks for streaming
 #[command]
+#[instrument(skip_all, err)]
 pub async fn read_file_chunked(
+    scope: State<'_, PathScope>,
     path: String,
     offset: Option<u64>,
     length: Option<u64>,
 ) -> Result<Vec<u8>, String> {
-    let path = Path::new(&path);
+    let path = scope.check(&path)?;
 
     if !path.exists() {
         return Err(format!("File not found: {}", path.display()));
     }
 
-    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
-    let mmap = unsafe { Mmap::map(&file) }.map_err(|e| format!("Failed to mmap file: {}", e))?;
+    let file = File::open(&path).map_err(|e| format!("Failed to open file: {}", e))?;
 
-    let start = offset.unwrap_or(0) as usize;
-    let len = length.unwrap_or(mmap.len() as u64) as usize;
-    let end = (start + len).min(mmap.len());
+    // Phones read just the chunk rather than the whole file
+    #[cfg(mobile)]
+    return platform::read_range(&file, offset.unwrap_or(0), length)
+        .map_err(|e| format!("Failed to read file: {}", e));
 
-    if start >= mmap.len() {
-        return Ok(vec![]);
-    }
+    #[cfg(not(mobile))]
+    {
+        let mmap = platform::FileBytes::from_file(&file)?;
+
+        let start = offset.unwrap_or(0) as usize;
+        let len = length.unwrap_or(mmap.len() as u64) as usize;
+        let end = (start + len).min(mmap.len());
+
+        if start >= mmap.len() {
+            return Ok(vec![]);
+        }
 
-    Ok(mmap[start..end].to_vec())
+        Ok(mmap[start..end].to_vec())
+    }
 }
 
 /// Get detailed information about a file
 #[command]
-pub async fn get_file_info(path: String) -> Result<FileInfo, String> {
-    let path_obj = Path::new(&path);
+#[instrument(skip_all, err)]
+pub async fn get_file_info(
+    scope: State<'_, PathScope>,
+    path: String,
+) -> Result<FileInfo, String> {
+    let resolved = scope.check(&path)?;
+    let path_obj = resolved.as_path();
 
     if !path_obj.exists() {
         return Err(format!("File not found: {}", path));
@@ -102,9 +163,18 @@ pub async fn get_file_info(path: String) -> Result<FileInfo, String> {
 }
 
 /// List all assets in the storage directory
+///
+/// Assets are found by their model and image files, following the storage
+/// layout from settings. Texture assets without a thumbnail of their own get
+/// one generated into the cache.
 #[command]
-pub async fn list_storage_assets(storage_path: String) -> Result<Vec<StorageAsset>, String> {
-    let path = Path::new(&storage_path);
+#[instrument(skip_all, err)]
+pub async fn list_storage_assets(
+    app: AppHandle,
+    storage_path: String,
+) -> Result<Vec<StorageAsset>, String> {
+    let resolved = app.state::<PathScope>().check(&storage_path)?;
+    let path = resolved.as_path();
 
     if !path.exists() {
         return Err(format!("Storage path not found: {}", storage_path));
@@ -114,57 +184,151 @@ pub async fn list_storage_assets(storage_path: String) -> Result<Vec<StorageAsse
         return Err(format!("Storage path is not a directory: {}", storage_path));
     }
 
-    let mut assets = Vec::new();
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let settings = app.state::<SettingsStore>().get();
+            let layout = settings.storage_layout.parse()?;
+            let cache = app.state::<AssetCache>();
+            let assets = storage_assets(&resolved, &layout, &cache, settings.cache.max_bytes)?;
+
+            // Each listing doubles as a sample of the library's size for the dashboard
+            let total_bytes = assets.iter().filter_map(|asset| asset.glb_size).sum();
+            app.state::<UsageStats>()
+                .record_library(&resolved, assets.len(), total_bytes);
+
+            Ok(assets)
+        })
+    })
+    .await
+    .map_err(|e| format!("Listing failed: {}", e))?
+}
 
-    for entry in WalkDir::new(path).min_depth(1).max_depth(1) {
-        let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
-
-        if entry.file_type().is_dir() {
-            let dir_name = entry.file_name().to_string_lossy().to_string();
-            let dir_path = entry.path();
-
-            // Check for various model files
-            let glb_path = dir_path.join(format!("{}.glb", dir_name));
-            let obj_path = dir_path.join(format!("{}.obj", dir_name));
-            let fbx_path = dir_path.join(format!("{}.fbx", dir_name));
-            let thumbnail_path = dir_path.join("thumbnail.png");
-
-            let has_glb = glb_path.exists();
-            let has_obj = obj_path.exists();
-            let has_fbx = fbx_path.exists();
-            let has_thumbnail = thumbnail_path.exists();
-
-            let glb_size = if has_glb {
-                fs::metadata(&glb_path).ok().map(|m| m.len())
-            } else {
-                None
-            };
-
-            assets.push(StorageAsset {
-                id: dir_name,
-                path: dir_path.to_string_lossy().to_string(),
-                has_glb,
-                has_obj,
-                has_fbx,
-                has_thumbnail,
-                glb_size,
-                thumbnail_path: if has_thumbnail {
-                    Some(thumbnail_path.to_string_lossy().to_string())
-                } else {
+/// Assets laid out under `path` as `layout` places them
+///
+/// Thumbnails of texture assets that lack one are made in `cache`.
+pub fn storage_assets(
+    path: &Path,
+    layout: &Template,
+    cache: &AssetCache,
+    max_cache_bytes: u64,
+) -> Result<Vec<StorageAsset>, String> {
+    let mut assets = Vec::new();
+    for asset in layout.scan(path)? {
+        let model = |ext: &str| {
+            asset.models.iter().find(|model| {
+                model
+                    .extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+            })
+        };
+        let glb_path = model("glb");
+        let texture_path = asset.images.first();
+        let stored_thumbnail =
+            Some(layout.thumbnail(&asset.dir, &asset.id)).filter(|thumbnail| thumbnail.exists());
+        let thumbnail_path = match (stored_thumbnail, texture_path) {
+            (None, Some(texture)) => image_thumbnail::cached(cache, texture, max_cache_bytes)
+                .unwrap_or_else(|e| {
+                    log::warn!("No thumbnail for {}: {}", texture.display(), e);
                     None
-                },
-            });
-        }
+                }),
+            (stored, _) => stored,
+        };
+        let media = media::sidecars(&asset.dir, &asset.id, layout.per_asset_folder())
+            .iter()
+            .filter_map(|path| {
+                media::probe(path)
+                    .map_err(|e| log::warn!("Skipping {}: {}", path.display(), e))
+                    .ok()
+            })
+            .collect();
+
+        assets.push(StorageAsset {
+            path: asset.dir.to_string_lossy().to_string(),
+            model_path: asset
+                .models
+                .first()
+                .map(|model| model.to_string_lossy().to_string()),
+            texture_path: texture_path.map(|texture| texture.to_string_lossy().to_string()),
+            category: asset.category.clone(),
+            has_glb: glb_path.is_some(),
+            has_obj: model("obj").is_some(),
+            has_fbx: model("fbx").is_some(),
+            has_thumbnail: thumbnail_path.is_some(),
+            glb_size: glb_path
+                .and_then(|glb| fs::metadata(glb).ok())
+                .map(|m| m.len()),
+            thumbnail_path: thumbnail_path.map(|thumbnail| thumbnail.to_string_lossy().to_string()),
+            media,
+            id: asset.id,
+        });
     }
-
     Ok(assets)
 }
 
+/// Reorganize a storage folder into another layout and make it the current one
+///
+/// Every asset is moved with its thumbnail and companion files. `categories`
+/// sets asset categories by id for layouts that use them; other assets keep
+/// the category they have, or go under `uncategorized`. Nothing moves if any
+/// file would land on another or on an existing file, and if a move fails
+/// partway the files already moved are moved back.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn migrate_storage_layout(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    storage_path: String,
+    layout: StorageLayout,
+    categories: Option<HashMap<String, String>>,
+    dry_run: Option<bool>,
+) -> Result<StorageMigration, String> {
+    let root = scope.check(&storage_path)?;
+    if !root.is_dir() {
+        return Err(format!("Storage path is not a directory: {}", storage_path));
+    }
+    let current = settings.get().storage_layout;
+    let from = current.parse()?;
+    let to = layout.parse()?;
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let plan = storage_layout::plan_migration(&root, &from, &to, &categories.unwrap_or_default())?;
+    let moves = plan
+        .moves
+        .iter()
+        .map(|(source, dest)| Ok((scope.check(source)?, scope.check(dest)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    changes.rename_all(&moves)?;
+    if !changes.is_dry_run() {
+        storage_layout::remove_emptied(&plan, &root);
+        settings.update(|settings| settings.storage_layout = layout.clone())?;
+    }
+
+    Ok(StorageMigration {
+        from: current,
+        to: layout,
+        asset_count: plan.asset_count,
+        changes: changes.finish(),
+    })
+}
+
 /// Watch a directory for changes
 /// Returns the current list of files in the directory
+///
+/// Changes are reported with `directory-changed` events. Changed models, and
+/// models whose external buffers changed, are re-analyzed incrementally and
+/// reported with `model-analysis-updated` (or `model-analysis-failed`); on
+/// mobile they are only re-analyzed when next asked about. Changed files
+/// matching a watch rule are queued for processing.
 #[command]
-pub async fn watch_directory(path: String) -> Result<Vec<FileInfo>, String> {
-    let path_obj = Path::new(&path);
+#[instrument(skip_all, err)]
+pub async fn watch_directory(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    path: String,
+) -> Result<Vec<FileInfo>, String> {
+    let resolved = scope.check(&path)?;
+    let path_obj = resolved.as_path();
 
     if !path_obj.exists() {
         return Err(format!("Directory not found: {}", path));
@@ -215,5 +379,82 @@ pub async fn watch_directory(path: String) -> Result<Vec<FileInfo>, String> {
         });
     }
 
+    watch(&app, path_obj)?;
+
     Ok(files)
 }
+
+/// Watch `path` with the handling `watch_directory` describes; returns
+/// `false` if it was already being watched
+pub fn watch(app: &AppHandle, path: &Path) -> Result<bool, String> {
+    let handle = app.clone();
+    app.state::<DirectoryWatchers>()
+        .watch(path, move |root, paths| handle_changes(&handle, root, paths))
+}
+
+/// Stop watching a directory
+#[command]
+#[instrument(skip_all, err)]
+pub async fn unwatch_directory(
+    watchers: State<'_, DirectoryWatchers>,
+    path: String,
+) -> Result<bool, String> {
+    Ok(watchers.unwatch(Path::new(&path)))
+}
+
+fn handle_changes(app: &AppHandle, root: &Path, paths: Vec<PathBuf>) {
+    emit(
+        app,
+        "directory-changed",
+        DirectoryChangedEvent {
+            root: root.to_string_lossy().to_string(),
+            paths: paths
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect(),
+        },
+    );
+
+    jobs::queue_rule_jobs(app, &paths);
+
+    let cache = app.state::<AnalysisCache>();
+    let mut models = BTreeSet::new();
+    for path in &paths {
+        if !path.exists() {
+            cache.invalidate(path);
+        } else if is_model(path) {
+            models.insert(path.clone());
+        }
+        models.extend(cache.dependents(path));
+    }
+    // The cache notices the change when the model is next analyzed
+    if cfg!(mobile) {
+        return;
+    }
+
+    for model in models {
+        match cache.analyze(&model) {
+            Ok(update) => emit(app, "model-analysis-updated", update),
+            Err(error) => emit(
+                app,
+                "model-analysis-failed",
+                AnalysisFailedEvent {
+                    path: model.to_string_lossy().to_string(),
+                    error,
+                },
+            ),
+        }
+    }
+}
+
+fn is_model(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("glb") || e.eq_ignore_ascii_case("gltf"))
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        log::warn!("Failed to emit {}: {}", event, e);
+    }
+}
@@ -0,0 +1,449 @@
+use crate::commands::file_ops;
+use crate::commands::maintenance;
+use crate::commands::mesh_upload::{describe_mesh, MeshHandle};
+use crate::commands::processing::{process_file, record_processing, ProcessedAsset};
+use crate::utils::changes::{ChangeReport, ChangeSet};
+use crate::utils::file_lock;
+use crate::utils::i18n;
+use crate::utils::jobs::{Job, JobQueue};
+use crate::utils::mesh_store::MeshStore;
+use crate::utils::notifications::WindowFocus;
+use crate::utils::path_scope::PathScope;
+use crate::utils::power::{self, PowerStatus, PowerThrottling, MAX_CONCURRENT_JOBS};
+use crate::utils::profiles;
+use crate::utils::quarantine::{self, ParseLimits};
+use crate::utils::settings::{AppSettings, SettingsStore};
+use crate::utils::watch_rules::WatchRule;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+use tracing::{instrument, Span};
+
+/// How often the power state is read
+const POWER_POLL: Duration = Duration::from_secs(30);
+
+/// An interrupted job started again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumedJob {
+    pub interrupted_id: u64,
+    /// Id of the new job; `None` if the same work was already queued
+    pub job_id: Option<u64>,
+    /// Partial output deleted before resuming
+    pub changes: ChangeReport,
+}
+
+/// Result of a watch rule job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchRuleOutcome {
+    pub folder: String,
+    pub processed: ProcessedAsset,
+    /// The result in the mesh store, for rules that import it
+    pub mesh: Option<MeshHandle>,
+}
+
+/// Background jobs, oldest first, including recently finished ones
+///
+/// Each state change is also reported with a `job-updated` event.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn list_jobs(jobs: State<'_, JobQueue>) -> Result<Vec<Job>, String> {
+    Ok(jobs.jobs())
+}
+
+/// Replace the watch folder rules and start watching their folders
+///
+/// Folders must be approved and profiles must exist in the settings.
+/// Watching continues for folders of rules that are removed, as it does for
+/// folders opened with `watch_directory`.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn set_watch_rules(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    rules: Vec<WatchRule>,
+) -> Result<Vec<WatchRule>, String> {
+    let profiles = settings.get().processing_profiles;
+    let rules = rules
+        .into_iter()
+        .map(|mut rule| {
+            let folder = scope.check(&rule.folder)?;
+            if !folder.is_dir() {
+                return Err(format!("Not a directory: {}", folder.display()));
+            }
+            rule.folder = folder.to_string_lossy().to_string();
+            rule.profile = profiles.find(&rule.profile)?.name.clone();
+            rule.extensions = rule
+                .extensions
+                .iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .filter(|e| !e.is_empty())
+                .collect();
+            Ok(rule)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let saved = settings.update(|s| s.watch_rules = rules)?.watch_rules;
+    for rule in saved.iter().filter(|rule| rule.enabled) {
+        file_ops::watch(&app, Path::new(&rule.folder))?;
+    }
+    Ok(saved)
+}
+
+/// Start watching the folders of enabled rules, at startup
+pub fn watch_rule_folders(app: &AppHandle) {
+    let scope = app.state::<PathScope>();
+    for rule in app.state::<SettingsStore>().get().watch_rules {
+        if !rule.enabled {
+            continue;
+        }
+        let watched = scope
+            .check(&rule.folder)
+            .and_then(|folder| file_ops::watch(app, &folder));
+        if let Err(e) = watched {
+            log::warn!("Not watching {} for its rule: {}", rule.folder, e);
+        }
+    }
+}
+
+/// The power state and how many background jobs it lets run at once
+///
+/// Changes found while polling are reported with `power-status-changed`.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn get_power_status(app: AppHandle) -> Result<PowerStatus, String> {
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || span.in_scope(|| apply_throttling(&app)))
+        .await
+        .map_err(|e| format!("Reading the power state failed: {}", e))
+}
+
+/// Replace when background jobs are throttled and apply it at once
+#[command]
+#[instrument(skip_all, err)]
+pub async fn set_power_throttling(
+    app: AppHandle,
+    throttling: PowerThrottling,
+) -> Result<PowerStatus, String> {
+    if !(1..=MAX_CONCURRENT_JOBS).contains(&throttling.max_jobs) {
+        return Err(format!(
+            "Jobs at once must be between 1 and {}",
+            MAX_CONCURRENT_JOBS
+        ));
+    }
+    app.state::<SettingsStore>()
+        .update(|s| s.power_throttling = throttling)?;
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || span.in_scope(|| apply_throttling(&app)))
+        .await
+        .map_err(|e| format!("Reading the power state failed: {}", e))
+}
+
+/// Start the thread that sets the job queue's concurrency from the power
+/// state and the throttling settings, at startup
+pub fn start_power_monitor(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || loop {
+        apply_throttling(&app);
+        thread::sleep(POWER_POLL);
+    });
+}
+
+fn apply_throttling(app: &AppHandle) -> PowerStatus {
+    let throttling = app.state::<SettingsStore>().get().power_throttling;
+    let status = throttling.status(power::read_state());
+    let queue = app.state::<JobQueue>();
+    if queue.concurrency() != status.job_limit {
+        log::info!(
+            "Running up to {} background jobs at once ({:?})",
+            status.job_limit,
+            status.throttled_by
+        );
+        queue.set_concurrency(status.job_limit);
+        if let Err(e) = app.emit("power-status-changed", &status) {
+            log::warn!("Failed to emit power-status-changed: {}", e);
+        }
+    }
+    status
+}
+
+/// Announce a finished job with a system notification if the window is in
+/// the background and the notification preferences want to hear of it
+pub fn notify_finished(app: &AppHandle, job: &Job) {
+    if !app.state::<WindowFocus>().in_background() {
+        return;
+    }
+    let preferences = app.state::<SettingsStore>().get().notification_preferences;
+    let Some((title, body)) = preferences.message(job) else {
+        return;
+    };
+    let (title, body) = (i18n::localize(&title), i18n::localize(&body));
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        log::warn!("Failed to show a notification for job {}: {}", job.id, e);
+    }
+}
+
+/// Queue a job for every rule matching each changed path
+pub fn queue_rule_jobs(app: &AppHandle, paths: &[PathBuf]) {
+    let rules = app.state::<SettingsStore>().get().watch_rules;
+    if rules.is_empty() {
+        return;
+    }
+    let jobs = app.state::<JobQueue>();
+    for path in paths.iter().filter(|path| path.is_file()) {
+        for rule in rules.iter().filter(|rule| rule.matches(path)) {
+            submit_rule_job(app, &jobs, rule.clone(), path.clone());
+        }
+    }
+}
+
+/// Interrupted jobs to offer resuming at startup, oldest first
+#[command]
+#[instrument(skip_all, err)]
+pub async fn list_interrupted_jobs(jobs: State<'_, JobQueue>) -> Result<Vec<Job>, String> {
+    Ok(jobs.interrupted())
+}
+
+/// Start interrupted jobs again
+///
+/// Temporary files an interrupted watch rule job had started writing are
+/// deleted before the job runs again; its finished outputs are left alone,
+/// since they are only moved into place once complete. The interrupted jobs
+/// are marked failed and point at their new ids.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn resume_jobs(app: AppHandle, ids: Vec<u64>) -> Result<Vec<ResumedJob>, String> {
+    let queue = app.state::<JobQueue>();
+    let interrupted = ids
+        .iter()
+        .map(|&id| {
+            queue
+                .interrupted()
+                .into_iter()
+                .find(|job| job.id == id)
+                .ok_or_else(|| format!("Job {} is not an interrupted job", id))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let span = Span::current();
+    let worker_app = app.clone();
+    let resumed = tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            interrupted
+                .iter()
+                .map(|job| resume_job(&worker_app, job))
+                .collect::<Result<Vec<_>, String>>()
+        })
+    })
+    .await
+    .map_err(|e| format!("Resuming jobs failed: {}", e))??;
+
+    for job in &resumed {
+        let error = match job.job_id {
+            Some(id) => format!("Interrupted; resumed as job {}", id),
+            None => "Interrupted; the same work is already queued".to_string(),
+        };
+        queue.settle_interrupted(job.interrupted_id, error)?;
+    }
+    Ok(resumed)
+}
+
+/// Give up on interrupted jobs, marking them failed
+#[command]
+#[instrument(skip_all, err)]
+pub async fn dismiss_jobs(jobs: State<'_, JobQueue>, ids: Vec<u64>) -> Result<Vec<Job>, String> {
+    ids.iter()
+        .map(|&id| jobs.settle_interrupted(id, "Interrupted; not resumed".to_string()))
+        .collect()
+}
+
+/// Where a watch rule job is resumed from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RuleResume {
+    rule: WatchRule,
+    path: PathBuf,
+    /// Process the job ran in, which names its temporary files
+    #[serde(default)]
+    pid: Option<u32>,
+}
+
+fn submit_rule_job(
+    app: &AppHandle,
+    jobs: &JobQueue,
+    rule: WatchRule,
+    path: PathBuf,
+) -> Option<u64> {
+    let label = path.to_string_lossy().to_string();
+    let resume = serde_json::to_value(RuleResume {
+        rule: rule.clone(),
+        path: path.clone(),
+        pid: Some(std::process::id()),
+    })
+    .unwrap_or_default();
+    let app = app.clone();
+    jobs.submit_resumable(
+        "watch_rule",
+        &label,
+        resume,
+        Box::new(move || {
+            let outcome = run_rule(&app, &rule, &path)?;
+            serde_json::to_value(outcome).map_err(|e| e.to_string())
+        }),
+    )
+}
+
+fn resume_job(app: &AppHandle, job: &Job) -> Result<ResumedJob, String> {
+    let resume = job
+        .resume
+        .clone()
+        .ok_or_else(|| format!("Job {} ({}) can't be resumed", job.id, job.kind))?;
+    let settings = app.state::<SettingsStore>().get();
+    let mut changes = ChangeSet::new(&settings, None)?;
+    let job_id = match job.kind.as_str() {
+        "watch_rule" => {
+            let RuleResume { rule, path, pid } = serde_json::from_value(resume)
+                .map_err(|e| format!("Can't resume job {}: {}", job.id, e))?;
+            let scope = app.state::<PathScope>();
+            let path = scope.check(&path)?;
+            if !path.is_file() {
+                return Err(format!(
+                    "Can't resume job {}: {} is gone",
+                    job.id,
+                    path.display()
+                ));
+            }
+            if let (Some(pid), Some(_)) = (pid, job.started_ms) {
+                discard_partial_output(&scope, &settings, &rule, &path, pid, &mut changes)?;
+            }
+            submit_rule_job(app, &app.state::<JobQueue>(), rule, path)
+        }
+        "maintenance" => {
+            let tasks = serde_json::from_value(resume)
+                .map_err(|e| format!("Can't resume job {}: {}", job.id, e))?;
+            maintenance::queue_maintenance(app, tasks)
+        }
+        kind => return Err(format!("Jobs of kind {} can't be resumed", kind)),
+    };
+    Ok(ResumedJob {
+        interrupted_id: job.id,
+        job_id,
+        changes: changes.finish(),
+    })
+}
+
+/// Delete the temporary files a rule job for `path`, started in process
+/// `pid`, was writing its model and resized textures through
+fn discard_partial_output(
+    scope: &PathScope,
+    settings: &AppSettings,
+    rule: &WatchRule,
+    path: &Path,
+    pid: u32,
+    changes: &mut ChangeSet,
+) -> Result<(), String> {
+    let profile = settings.processing_profiles.find(&rule.profile)?;
+    let out_dir = rule.output_dir();
+    let mut outputs = vec![profile.output_path(&out_dir, path, profile.format.extension())];
+    if profile.max_texture_size.is_some() {
+        outputs.extend(profiles::sibling_textures(path).iter().map(|texture| {
+            let extension = texture.extension().unwrap_or_default().to_string_lossy();
+            profile.output_path(&out_dir, texture, &extension)
+        }));
+    }
+    for output in outputs {
+        changes.delete(&scope.check(file_lock::temp_path_of(&output, pid))?)?;
+    }
+    Ok(())
+}
+
+/// Process a file that landed in a watch folder
+///
+/// The file is untrusted, so it is parsed in the sandbox before anything
+/// loads it in this process.
+fn run_rule(app: &AppHandle, rule: &WatchRule, path: &Path) -> Result<WatchRuleOutcome, String> {
+    let settings = app.state::<SettingsStore>().get();
+    let verdict = quarantine::parse_in_sandbox(path, &ParseLimits::default())?;
+    if !verdict.passed {
+        return Err(format!(
+            "{} was rejected: {}",
+            path.display(),
+            verdict.reasons.join("; ")
+        ));
+    }
+    let (processed, mesh) = process_file(
+        &app.state::<PathScope>(),
+        &settings,
+        &path.to_string_lossy(),
+        &rule.profile,
+        Some(rule.output_dir()),
+        None,
+    )?;
+    record_processing(app, &processed);
+    let mesh = if rule.import_result {
+        let store = app.state::<MeshStore>();
+        let handle = store.insert(mesh);
+        Some(describe_mesh(&store, handle)?)
+    } else {
+        None
+    };
+    Ok(WatchRuleOutcome {
+        folder: rule.folder.clone(),
+        processed,
+        mesh,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_discard_only_the_started_jobs_temp_files() {
+        let dir = std::env::temp_dir().join(format!("sweedle-jobs-{}", std::process::id()));
+        let folder = dir.join("watched");
+        fs::create_dir_all(&folder).unwrap();
+        let scope = PathScope::default();
+        let settings = AppSettings::default();
+        let profile = &settings.processing_profiles.0[0];
+        let rule = WatchRule {
+            folder: scope
+                .allow_directory(&folder)
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            profile: profile.name.clone(),
+            ..Default::default()
+        };
+        let source = Path::new(&rule.folder).join("scan.obj");
+        fs::write(&source, b"v 0 0 0\n").unwrap();
+        let output = profile.output_path(&rule.output_dir(), &source, profile.format.extension());
+        fs::create_dir_all(rule.output_dir()).unwrap();
+        let ours = file_lock::temp_path_of(&output, 42);
+        let theirs = file_lock::temp_path_of(&output, 7);
+        for file in [&output, &ours, &theirs] {
+            fs::write(file, b"not a model").unwrap();
+        }
+
+        let mut changes = ChangeSet::new(&settings, None).unwrap();
+        discard_partial_output(&scope, &settings, &rule, &source, 42, &mut changes).unwrap();
+        assert!(!ours.exists());
+        assert!(theirs.exists() && output.exists());
+        assert_eq!(changes.finish().changes.len(), 1);
+
+        let outside = WatchRule {
+            folder: dir.join("elsewhere").to_string_lossy().to_string(),
+            ..rule
+        };
+        let mut changes = ChangeSet::new(&settings, None).unwrap();
+        assert!(
+            discard_partial_output(&scope, &settings, &outside, &source, 7, &mut changes).is_err()
+        );
+        assert!(theirs.exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,1008 @@
+use crate::commands::file_ops::{self, StorageAsset};
+use crate::utils::analysis_cache::{AnalysisCache, ModelSource};
+use crate::utils::asset_cache::AssetCache;
+use crate::utils::asset_history::{AssetHistory, HistoryEntry, HistoryKind};
+use crate::utils::changes::{ChangeReport, ChangeSet};
+use crate::utils::curation::{AssetCuration, CurationStore};
+use crate::utils::gltf_metadata;
+use crate::utils::library::{LibraryIndex, RootIndex, StorageRoot};
+use crate::utils::licenses::{AssetLicense, LicenseKind, LicenseStore};
+use crate::utils::mesh_files::{import_meshes, MeshFileFormat};
+use crate::utils::path_scope::PathScope;
+use crate::utils::provenance::{self, Provenance};
+use crate::utils::rename_pattern::{RenamePattern, RenameValues};
+use crate::utils::settings::{AppSettings, SettingsStore};
+use crate::utils::smart_collections::{self, AssetFacts, SmartCollection};
+use crate::utils::storage_layout::{self, LaidOutAsset};
+use crate::utils::usage_stats::UsageStats;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle, Manager, State};
+use tracing::{instrument, Span};
+
+/// A storage root as the library sees it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryRoot {
+    pub name: String,
+    pub path: String,
+    /// Whether the last attempt to index the root succeeded
+    pub online: bool,
+    pub asset_count: usize,
+    pub total_bytes: u64,
+    /// When the listed assets were found; `None` if the root never indexed
+    pub indexed_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// An asset and the root it's stored in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryAsset {
+    pub root: String,
+    #[serde(flatten)]
+    pub asset: StorageAsset,
+    pub license: Option<AssetLicense>,
+    #[serde(flatten)]
+    pub curation: AssetCuration,
+}
+
+/// Every storage root with the assets of all of them merged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Library {
+    pub roots: Vec<LibraryRoot>,
+    /// Sorted by id; an id stored in several roots is listed once per root
+    pub assets: Vec<LibraryAsset>,
+}
+
+/// Order of the assets `query_library` returns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LibrarySort {
+    #[default]
+    Id,
+    /// Highest rated first, unrated last
+    Rating,
+    /// Most often exported first
+    MostExported,
+    /// Last exported first, never exported last
+    RecentlyExported,
+}
+
+/// What `query_library` keeps of the library; every field narrows it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LibraryFilter {
+    /// Only assets rated at least this many stars
+    pub min_rating: Option<u8>,
+    pub favorites_only: bool,
+    /// Only assets exported at least once
+    pub exported_only: bool,
+    /// Only assets in the root with this name
+    pub root: Option<String>,
+    /// Only assets whose id or category contains this, ignoring case
+    pub text: Option<String>,
+    pub sort: LibrarySort,
+    /// At most this many assets, after sorting
+    pub limit: Option<usize>,
+}
+
+impl LibraryFilter {
+    fn matches(&self, asset: &LibraryAsset) -> bool {
+        let curation = &asset.curation;
+        let text = self.text.as_deref().map(str::trim).unwrap_or_default();
+        let text = text.to_lowercase();
+        self.min_rating
+            .is_none_or(|min| curation.rating.is_some_and(|r| r >= min))
+            && (!self.favorites_only || curation.favorite)
+            && (!self.exported_only || curation.export_count > 0)
+            && self.root.as_ref().is_none_or(|root| asset.root == *root)
+            && (text.is_empty()
+                || asset.asset.id.to_lowercase().contains(&text)
+                || asset
+                    .asset
+                    .category
+                    .as_ref()
+                    .is_some_and(|c| c.to_lowercase().contains(&text)))
+    }
+
+    fn apply(&self, assets: Vec<LibraryAsset>) -> Vec<LibraryAsset> {
+        let mut assets: Vec<LibraryAsset> =
+            assets.into_iter().filter(|a| self.matches(a)).collect();
+        // Sorts are stable, so ties stay in id order
+        match self.sort {
+            LibrarySort::Id => {}
+            LibrarySort::Rating => assets.sort_by_key(|a| std::cmp::Reverse(a.curation.rating)),
+            LibrarySort::MostExported => {
+                assets.sort_by_key(|a| std::cmp::Reverse(a.curation.export_count))
+            }
+            LibrarySort::RecentlyExported => {
+                assets.sort_by_key(|a| std::cmp::Reverse(a.curation.last_exported_ms))
+            }
+        }
+        if let Some(limit) = self.limit {
+            assets.truncate(limit);
+        }
+        assets
+    }
+}
+
+/// Result of `evaluate_smart_collection`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluatedCollection {
+    pub collection: SmartCollection,
+    /// Sorted by id
+    pub assets: Vec<LibraryAsset>,
+    /// Ids of glTF models whose triangle count couldn't be read, left out
+    /// by the collection's triangle rules
+    pub unreadable: Vec<String>,
+}
+
+/// Result of `move_asset_between_roots`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovedAsset {
+    pub asset_id: String,
+    pub from_root: String,
+    pub to_root: String,
+    /// Where the asset's model files went
+    pub path: String,
+    pub changes: ChangeReport,
+}
+
+/// Result of `get_asset_history`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetHistoryReport {
+    pub asset_id: String,
+    /// Imports, processing runs, renames and moves, oldest first
+    pub entries: Vec<HistoryEntry>,
+    /// Where the recorded license says the asset was obtained
+    pub source_url: Option<String>,
+    /// Record embedded in the asset's glTF model by `stamp_provenance`
+    pub provenance: Option<Provenance>,
+}
+
+/// One asset given a new id by `bulk_rename`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetRename {
+    pub asset_id: String,
+    pub new_id: String,
+    pub root: String,
+    /// Folder the asset's model files are in afterwards
+    pub path: String,
+}
+
+/// Result of `bulk_rename`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkRename {
+    /// One entry per copy of each renamed asset
+    pub renames: Vec<AssetRename>,
+    /// Ids the pattern left as they were
+    pub unchanged: Vec<String>,
+    pub changes: ChangeReport,
+}
+
+/// An asset used by a project and what its license asks for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceEntry {
+    pub asset_id: String,
+    /// Root holding the asset; `None` if the library doesn't have it
+    pub root: Option<String>,
+    pub path: Option<String>,
+    pub license: Option<AssetLicense>,
+    /// Whether the license was read from the model file because none is
+    /// recorded for the asset
+    pub from_file: bool,
+    pub requires_attribution: bool,
+    /// Line for the credits file
+    pub attribution: Option<String>,
+}
+
+/// Result of `compliance_report`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceReport {
+    pub project_id: String,
+    pub entries: Vec<ComplianceEntry>,
+    /// Ids of assets with no known license
+    pub unlicensed: Vec<String>,
+    /// Text of a credits file, one attribution per licensed asset
+    pub credits: String,
+}
+
+/// Replace the registered storage roots
+///
+/// Names must be unique and folders approved. Indexes of removed roots are
+/// dropped, and new roots are indexed the next time the library is read.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn set_storage_roots(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    index: State<'_, LibraryIndex>,
+    roots: Vec<StorageRoot>,
+) -> Result<Vec<StorageRoot>, String> {
+    let mut names = HashSet::new();
+    let roots = roots
+        .into_iter()
+        .map(|mut root| {
+            root.name = root.name.trim().to_string();
+            if root.name.is_empty() {
+                return Err("Storage roots need a name".to_string());
+            }
+            if !names.insert(root.name.clone()) {
+                return Err(format!("Storage root {} is listed twice", root.name));
+            }
+            let path = scope.check(&root.path)?;
+            if !path.is_dir() {
+                return Err(format!("Not a directory: {}", path.display()));
+            }
+            root.path = path.to_string_lossy().to_string();
+            Ok(root)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let saved = settings.update(|s| s.storage_roots = roots)?.storage_roots;
+    index.retain(&saved)?;
+    Ok(saved)
+}
+
+/// The merged library from the saved root indexes
+///
+/// Roots that were never indexed are indexed first; use
+/// `index_storage_roots` to pick up changes in the others.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn get_library(app: AppHandle) -> Result<Library, String> {
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let index = app.state::<LibraryIndex>();
+            for root in app.state::<SettingsStore>().get().storage_roots {
+                if index.get(&root.name).is_none() {
+                    index_root(&app, &root)?;
+                }
+            }
+            Ok(library(&app))
+        })
+    })
+    .await
+    .map_err(|e| format!("Indexing failed: {}", e))?
+}
+
+/// Index storage roots again, all of them or those in `names`, and return
+/// the merged library
+///
+/// A root that can't be read keeps the assets it was last seen with and is
+/// reported offline.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn index_storage_roots(
+    app: AppHandle,
+    names: Option<Vec<String>>,
+) -> Result<Library, String> {
+    let roots = app.state::<SettingsStore>().get().storage_roots;
+    if let Some(names) = &names {
+        if let Some(unknown) = names.iter().find(|n| !roots.iter().any(|r| r.name == **n)) {
+            return Err(format!("Unknown storage root: {}", unknown));
+        }
+    }
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let selected = roots.iter().filter(|root| {
+                names
+                    .as_ref()
+                    .is_none_or(|names| names.contains(&root.name))
+            });
+            for root in selected {
+                index_root(&app, root)?;
+            }
+            Ok(library(&app))
+        })
+    })
+    .await
+    .map_err(|e| format!("Indexing failed: {}", e))?
+}
+
+/// Move an asset with its thumbnail and companion files to another root
+///
+/// The asset lands where the storage layout puts it in the new root,
+/// keeping its category. Nothing moves if any of its files would replace an
+/// existing one, or if one of them can't be moved. Both roots are indexed
+/// again afterwards.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn move_asset_between_roots(
+    app: AppHandle,
+    asset_id: String,
+    from_root: String,
+    to_root: String,
+    dry_run: Option<bool>,
+) -> Result<MovedAsset, String> {
+    let settings = app.state::<SettingsStore>().get();
+    let find = |name: &str| {
+        settings
+            .storage_roots
+            .iter()
+            .find(|root| root.name == name)
+            .ok_or_else(|| format!("Unknown storage root: {}", name))
+    };
+    let (from, to) = (find(&from_root)?, find(&to_root)?);
+    if from == to {
+        return Err(format!("{} is already in {}", asset_id, to_root));
+    }
+    let scope = app.state::<PathScope>();
+    let from_path = scope.check(&from.path)?;
+    let to_path = scope.check(&to.path)?;
+    let layout = settings.storage_layout.parse()?;
+    let mut changes = ChangeSet::new(&settings, dry_run)?;
+
+    let matching: Vec<_> = layout
+        .scan(&from_path)?
+        .into_iter()
+        .filter(|asset| asset.id == asset_id)
+        .collect();
+    let asset = match matching.as_slice() {
+        [asset] => asset,
+        [] => return Err(format!("No asset {} in {}", asset_id, from_root)),
+        _ => {
+            return Err(format!(
+                "Several assets are named {} in {}",
+                asset_id, from_root
+            ))
+        }
+    };
+    let plan = storage_layout::plan_moves(
+        std::slice::from_ref(asset),
+        &layout,
+        &to_path,
+        &layout,
+        &HashMap::new(),
+    )
+    .map_err(|e| format!("Can't move {}: {}", asset_id, e))?;
+    let moves = plan
+        .moves
+        .iter()
+        .map(|(source, dest)| Ok((scope.check(source)?, scope.check(dest)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    changes.rename_all(&moves)?;
+    let path = plan
+        .moves
+        .first()
+        .and_then(|(_, dest)| dest.parent())
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    if !changes.is_dry_run() {
+        storage_layout::remove_emptied(&plan, &from_path);
+        index_root(&app, from)?;
+        index_root(&app, to)?;
+        app.state::<AssetHistory>().record(
+            &asset_id,
+            HistoryEntry {
+                source: Some(from_root.clone()),
+                path: Some(path.clone()),
+                ..HistoryEntry::new(HistoryKind::Moved)
+            },
+        )?;
+    }
+    Ok(MovedAsset {
+        asset_id,
+        from_root,
+        to_root,
+        path,
+        changes: changes.finish(),
+    })
+}
+
+/// Give assets new ids from a pattern such as `{name}_{index:3}`
+///
+/// Each asset's models or images, thumbnail, companion files and media go
+/// where the storage layout puts the new id, in every root holding the
+/// asset; `{index}` counts in the order of `asset_ids`. Licenses, ratings
+/// and project entries follow the new ids, and the roots are indexed again.
+/// Nothing is renamed if two assets would get the same id, a new id is
+/// already taken, or any file would replace an existing one, and a rename
+/// that fails partway is undone; a dry run previews the renames.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn bulk_rename(
+    app: AppHandle,
+    asset_ids: Vec<String>,
+    pattern: String,
+    dry_run: Option<bool>,
+) -> Result<BulkRename, String> {
+    let pattern = RenamePattern::parse(&pattern)?;
+    let mut ids: Vec<String> = Vec::new();
+    for id in asset_ids {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if ids.is_empty() {
+        return Err("No assets to rename".to_string());
+    }
+    let settings = app.state::<SettingsStore>().get();
+    let layout = settings.storage_layout.parse()?;
+    let mut changes = ChangeSet::new(&settings, dry_run)?;
+
+    let span = Span::current();
+    let handle = app.clone();
+    let (plans, unchanged) = tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let app = handle;
+            let scope = app.state::<PathScope>();
+            let library = library(&app);
+            let mut copies: Vec<(StorageRoot, Vec<LaidOutAsset>)> = Vec::new();
+            for root in &settings.storage_roots {
+                let holds_any = library
+                    .assets
+                    .iter()
+                    .any(|a| a.root == root.name && ids.contains(&a.asset.id));
+                if holds_any {
+                    let laid_out = layout.scan(&scope.check(&root.path)?)?;
+                    copies.push((root.clone(), laid_out));
+                }
+            }
+
+            let analyses = app.state::<AnalysisCache>();
+            let mut new_ids: HashMap<String, String> = HashMap::new();
+            let mut unchanged = Vec::new();
+            for (i, id) in ids.iter().enumerate() {
+                let first = copies
+                    .iter()
+                    .flat_map(|(_, assets)| assets)
+                    .find(|asset| asset.id == *id)
+                    .ok_or_else(|| format!("No asset {} in the library", id))?;
+                let file = first.models.first().or(first.images.first());
+                let format = file
+                    .and_then(|f| f.extension())
+                    .map(|ext| ext.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                let polycount = match file {
+                    Some(model) if pattern.needs_polycount() && !first.models.is_empty() => {
+                        Some(triangle_count(&analyses, &settings, model)?)
+                    }
+                    _ => None,
+                };
+                let new_id = pattern.fill(&RenameValues {
+                    name: id,
+                    index: i + 1,
+                    format: &format,
+                    polycount,
+                    category: first.category.as_deref(),
+                })?;
+                if new_id == *id {
+                    unchanged.push(id.clone());
+                    continue;
+                }
+                if let Some((other, _)) = new_ids.iter().find(|(_, taken)| **taken == new_id) {
+                    return Err(format!(
+                        "{} and {} would both be named {}",
+                        other, id, new_id
+                    ));
+                }
+                let taken =
+                    library.assets.iter().any(|a| a.asset.id == new_id) && !ids.contains(&new_id);
+                if taken {
+                    return Err(format!(
+                        "{} would take the id of another asset, {}",
+                        id, new_id
+                    ));
+                }
+                new_ids.insert(id.clone(), new_id);
+            }
+
+            let mut plans = Vec::new();
+            for (root, assets) in copies {
+                let renamed: Vec<(LaidOutAsset, String)> = assets
+                    .into_iter()
+                    .filter_map(|asset| {
+                        let new_id = new_ids.get(&asset.id)?.clone();
+                        Some((asset, new_id))
+                    })
+                    .collect();
+                if renamed.is_empty() {
+                    continue;
+                }
+                let path = scope.check(&root.path)?;
+                let plan = storage_layout::plan_renames(&renamed, &layout, &path)
+                    .map_err(|e| format!("Can't rename assets in {}: {}", root.name, e))?;
+                plans.push((root, path, renamed, plan));
+            }
+            Ok::<_, String>((plans, unchanged))
+        })
+    })
+    .await
+    .map_err(|e| format!("Renaming failed: {}", e))??;
+
+    let scope = app.state::<PathScope>();
+    let moves = plans
+        .iter()
+        .flat_map(|(_, _, _, plan)| &plan.moves)
+        .map(|(source, dest)| Ok((scope.check(source)?, scope.check(dest)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    changes.rename_all(&moves)?;
+    let mut renames = Vec::new();
+    for (root, _, renamed, plan) in &plans {
+        for (asset, new_id) in renamed {
+            let first = asset.models.first().or(asset.images.first());
+            let path = plan
+                .moves
+                .iter()
+                .find(|(source, _)| Some(source) == first)
+                .and_then(|(_, dest)| dest.parent())
+                .map(|dir| dir.to_string_lossy().to_string())
+                .unwrap_or_default();
+            renames.push(AssetRename {
+                asset_id: asset.id.clone(),
+                new_id: new_id.clone(),
+                root: root.name.clone(),
+                path,
+            });
+        }
+    }
+
+    if !changes.is_dry_run() {
+        let licenses = app.state::<LicenseStore>();
+        let curation = app.state::<CurationStore>();
+        let mut moved: Vec<(&String, &String)> = Vec::new();
+        for (root, path, renamed, plan) in &plans {
+            storage_layout::remove_emptied(plan, path);
+            index_root(&app, root)?;
+            for (asset, new_id) in renamed {
+                if !moved.contains(&(&asset.id, new_id)) {
+                    moved.push((&asset.id, new_id));
+                }
+            }
+        }
+        let history = app.state::<AssetHistory>();
+        for (from, to) in moved {
+            licenses.rename_asset(from, to)?;
+            curation.rename_asset(from, to)?;
+            history.rename_asset(from, to)?;
+        }
+    }
+    Ok(BulkRename {
+        renames,
+        unchanged,
+        changes: changes.finish(),
+    })
+}
+
+/// Triangles in the model at `path`: from the analysis cache for glTF,
+/// otherwise by importing it
+fn triangle_count(
+    analyses: &AnalysisCache,
+    settings: &AppSettings,
+    path: &Path,
+) -> Result<u64, String> {
+    if MeshFileFormat::from_path(path)? == MeshFileFormat::Gltf {
+        return Ok(analyses.analyze(path)?.analysis.face_count as u64);
+    }
+    let imported = import_meshes(path, &settings.import_axes, None, settings.triangulation)?;
+    Ok(imported
+        .meshes
+        .iter()
+        .map(|mesh| mesh.indices.len() as u64 / 3)
+        .sum())
+}
+
+/// Where an asset came from and what was done to it
+///
+/// Combines the history recorded for the asset id with the source in its
+/// license and the provenance record in the model of its first copy in the
+/// saved indexes. Assets that left the library keep their history.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn get_asset_history(
+    app: AppHandle,
+    asset_id: String,
+) -> Result<AssetHistoryReport, String> {
+    let entries = app.state::<AssetHistory>().entries(&asset_id);
+    let asset = library(&app)
+        .assets
+        .into_iter()
+        .find(|a| a.asset.id == asset_id);
+    if asset.is_none() && entries.is_empty() {
+        return Err(format!("No asset {} in the library", asset_id));
+    }
+    let model = asset.and_then(|a| a.asset.model_path);
+    let provenance = match model {
+        Some(model) if MeshFileFormat::from_path(Path::new(&model)) == Ok(MeshFileFormat::Gltf) => {
+            let model = app.state::<PathScope>().check(&model)?;
+            ModelSource::open(&model)
+                .ok()
+                .and_then(|source| serde_json::from_slice(source.json()).ok())
+                .and_then(|document| provenance::extract(&document))
+        }
+        _ => None,
+    };
+    let license = app.state::<LicenseStore>().license(&asset_id);
+    Ok(AssetHistoryReport {
+        asset_id,
+        entries,
+        source_url: license.and_then(|l| l.source_url),
+        provenance,
+    })
+}
+
+/// Record the license of an asset, or forget it with `None`
+///
+/// Licenses are kept by asset id, so every copy of an asset shares one.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn set_asset_license(
+    licenses: State<'_, LicenseStore>,
+    asset_id: String,
+    license: Option<AssetLicense>,
+) -> Result<Option<AssetLicense>, String> {
+    if asset_id.trim().is_empty() {
+        return Err("An asset id is required".to_string());
+    }
+    licenses.set_license(&asset_id, license)
+}
+
+/// Replace the assets a project uses, by asset id
+#[command]
+#[instrument(skip_all, err)]
+pub async fn set_project_assets(
+    licenses: State<'_, LicenseStore>,
+    project_id: String,
+    asset_ids: Vec<String>,
+) -> Result<Vec<String>, String> {
+    licenses.set_project_assets(&project_id, asset_ids)
+}
+
+/// Rate an asset from 1 to 5 stars, or clear its rating with `None`
+///
+/// Like licenses, ratings are kept by asset id and shared by every copy.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn set_rating(
+    curation: State<'_, CurationStore>,
+    asset_id: String,
+    rating: Option<u8>,
+) -> Result<AssetCuration, String> {
+    curation.set_rating(&asset_id, rating)
+}
+
+/// Mark an asset as a favorite, or unmark it if it already is one
+#[command]
+#[instrument(skip_all, err)]
+pub async fn toggle_favorite(
+    curation: State<'_, CurationStore>,
+    asset_id: String,
+) -> Result<AssetCuration, String> {
+    curation.toggle_favorite(&asset_id)
+}
+
+/// The library narrowed and sorted by `filter`, with every root listed
+///
+/// The library is read from the saved indexes.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn query_library(app: AppHandle, filter: LibraryFilter) -> Result<Library, String> {
+    let Library { roots, assets } = library(&app);
+    Ok(Library {
+        roots,
+        assets: filter.apply(assets),
+    })
+}
+
+/// The smart collections saved in the settings
+#[command]
+#[instrument(skip_all, err)]
+pub async fn list_smart_collections(
+    settings: State<'_, SettingsStore>,
+) -> Result<Vec<SmartCollection>, String> {
+    Ok(settings.get().smart_collections)
+}
+
+/// Replace the saved smart collections
+///
+/// Ids must be unique, and every collection needs a name.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn set_smart_collections(
+    settings: State<'_, SettingsStore>,
+    collections: Vec<SmartCollection>,
+) -> Result<Vec<SmartCollection>, String> {
+    smart_collections::validate(&collections)?;
+    Ok(settings
+        .update(|s| s.smart_collections = collections)?
+        .smart_collections)
+}
+
+/// The library assets that match the rules of the smart collection `id`
+///
+/// The library is read from the saved indexes; triangle counts come from
+/// the analysis cache and file times from the files themselves, each only
+/// when a rule needs them.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn evaluate_smart_collection(
+    app: AppHandle,
+    id: String,
+) -> Result<EvaluatedCollection, String> {
+    let collection = app
+        .state::<SettingsStore>()
+        .get()
+        .smart_collections
+        .into_iter()
+        .find(|c| c.id == id)
+        .ok_or_else(|| format!("Unknown smart collection: {}", id))?;
+
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let analyses = app.state::<AnalysisCache>();
+            let now_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            let mut unreadable = Vec::new();
+            let assets = library(&app)
+                .assets
+                .into_iter()
+                .filter(|asset| {
+                    let facts = asset_facts(asset, &collection, &analyses, &mut unreadable);
+                    collection.rules.matches(&facts, now_ms)
+                })
+                .collect();
+            Ok(EvaluatedCollection {
+                collection,
+                assets,
+                unreadable,
+            })
+        })
+    })
+    .await
+    .map_err(|e| format!("Evaluating the collection failed: {}", e))?
+}
+
+/// Every asset in a project with its license and attribution text
+///
+/// Assets without a recorded license fall back to the license in their
+/// glTF file, if it names one. The library is read from the saved indexes.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn compliance_report(
+    app: AppHandle,
+    project_id: String,
+) -> Result<ComplianceReport, String> {
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let licenses = app.state::<LicenseStore>();
+            let library = library(&app);
+            let entries: Vec<ComplianceEntry> = licenses
+                .project_assets(&project_id)
+                .into_iter()
+                .map(|asset_id| {
+                    let found = library.assets.iter().find(|a| a.asset.id == asset_id);
+                    let recorded = found.and_then(|a| a.license.clone());
+                    let from_file = found
+                        .filter(|_| recorded.is_none())
+                        .and_then(|a| a.asset.model_path.as_deref())
+                        .and_then(|path| file_license(Path::new(path)));
+                    let license = recorded.or(from_file.clone());
+                    ComplianceEntry {
+                        root: found.map(|a| a.root.clone()),
+                        path: found.map(|a| a.asset.path.clone()),
+                        from_file: from_file.is_some(),
+                        requires_attribution: license
+                            .as_ref()
+                            .is_some_and(AssetLicense::requires_attribution),
+                        attribution: license.as_ref().map(|l| l.attribution(&asset_id)),
+                        license,
+                        asset_id,
+                    }
+                })
+                .collect();
+            let credits: String = entries
+                .iter()
+                .filter_map(|entry| entry.attribution.as_deref())
+                .map(|line| format!("{}\n", line))
+                .collect();
+            Ok(ComplianceReport {
+                unlicensed: entries
+                    .iter()
+                    .filter(|entry| entry.license.is_none())
+                    .map(|entry| entry.asset_id.clone())
+                    .collect(),
+                project_id,
+                entries,
+                credits,
+            })
+        })
+    })
+    .await
+    .map_err(|e| format!("Report failed: {}", e))?
+}
+
+/// The license a glTF file names in `asset.extras.license`, credited to
+/// its copyright holder
+fn file_license(path: &Path) -> Option<AssetLicense> {
+    if MeshFileFormat::from_path(path).ok()? != MeshFileFormat::Gltf {
+        return None;
+    }
+    let source = ModelSource::open(path).ok()?;
+    let metadata = gltf_metadata::read(source.json()).ok()?;
+    let terms = metadata.license?;
+    Some(AssetLicense {
+        kind: LicenseKind::from_text(&terms),
+        author: metadata.copyright,
+        source_url: None,
+        terms: Some(terms),
+    })
+}
+
+/// Scan a root and save its index, or record why it couldn't be read
+pub(crate) fn index_root(app: &AppHandle, root: &StorageRoot) -> Result<(), String> {
+    let index = app.state::<LibraryIndex>();
+    let scanned = app.state::<PathScope>().check(&root.path).and_then(|path| {
+        if !path.is_dir() {
+            return Err(format!("Storage root is not reachable: {}", root.path));
+        }
+        let settings = app.state::<SettingsStore>().get();
+        let layout = settings.storage_layout.parse()?;
+        let cache = app.state::<AssetCache>();
+        let assets = file_ops::storage_assets(&path, &layout, &cache, settings.cache.max_bytes)?;
+        Ok((path, assets))
+    });
+    match scanned {
+        Ok((path, assets)) => {
+            let fresh = RootIndex::new(root.path.clone(), assets);
+            app.state::<UsageStats>().record_library(
+                &path,
+                fresh.assets.len(),
+                fresh.total_bytes(),
+            );
+            index.set(&root.name, fresh)
+        }
+        Err(e) => index.set_error(&root.name, root.path.clone(), e),
+    }
+}
+
+pub(crate) fn library(app: &AppHandle) -> Library {
+    let index = app.state::<LibraryIndex>();
+    let licenses = app.state::<LicenseStore>();
+    let curation = app.state::<CurationStore>();
+    let mut roots = Vec::new();
+    let mut assets = Vec::new();
+    for root in app.state::<SettingsStore>().get().storage_roots {
+        let indexed = index.get(&root.name);
+        roots.push(LibraryRoot {
+            online: indexed.as_ref().is_some_and(|i| i.error.is_none()),
+            asset_count: indexed.as_ref().map_or(0, |i| i.assets.len()),
+            total_bytes: indexed.as_ref().map_or(0, RootIndex::total_bytes),
+            indexed_ms: indexed.as_ref().map(|i| i.indexed_ms).filter(|&ms| ms > 0),
+            error: indexed.as_ref().and_then(|i| i.error.clone()),
+            name: root.name.clone(),
+            path: root.path,
+        });
+        assets.extend(
+            indexed
+                .into_iter()
+                .flat_map(|i| i.assets)
+                .map(|asset| LibraryAsset {
+                    root: root.name.clone(),
+                    license: licenses.license(&asset.id),
+                    curation: curation.get(&asset.id),
+                    asset,
+                }),
+        );
+    }
+    assets.sort_by(|a, b| a.asset.id.cmp(&b.asset.id));
+    Library { roots, assets }
+}
+
+/// What the rules of `collection` need to know of `asset`, noting its id
+/// in `unreadable` if its triangles are needed and can't be counted
+fn asset_facts<'a>(
+    asset: &'a LibraryAsset,
+    collection: &SmartCollection,
+    analyses: &AnalysisCache,
+    unreadable: &mut Vec<String>,
+) -> AssetFacts<'a> {
+    let files = [&asset.asset.model_path, &asset.asset.texture_path];
+    let mut formats: Vec<String> = files
+        .into_iter()
+        .flatten()
+        .filter_map(|path| Path::new(path).extension())
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .collect();
+    for (has, ext) in [
+        (asset.asset.has_glb, "glb"),
+        (asset.asset.has_obj, "obj"),
+        (asset.asset.has_fbx, "fbx"),
+    ] {
+        if has && !formats.iter().any(|f| f == ext) {
+            formats.push(ext.to_string());
+        }
+    }
+
+    let mut facts = AssetFacts {
+        id: &asset.asset.id,
+        formats,
+        category: asset.asset.category.as_deref(),
+        rating: asset.curation.rating,
+        favorite: asset.curation.favorite,
+        licensed: asset.license.is_some(),
+        ..Default::default()
+    };
+    let rules = &collection.rules;
+    if let Some(model) = asset.asset.model_path.as_deref().map(Path::new) {
+        let is_gltf = matches!(MeshFileFormat::from_path(model), Ok(MeshFileFormat::Gltf));
+        if rules.needs_triangles() && is_gltf {
+            match analyses.analyze(model) {
+                Ok(update) => facts.triangles = Some(update.analysis.face_count as u64),
+                Err(e) => {
+                    log::warn!("Failed to count triangles of {}: {}", model.display(), e);
+                    unreadable.push(asset.asset.id.clone());
+                }
+            }
+        }
+    }
+    if rules.needs_modified() {
+        facts.modified_ms = files
+            .into_iter()
+            .flatten()
+            .next()
+            .and_then(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64);
+    }
+    facts
+}
+
+/// Library ids of `items`, given as ids or as the paths of their model or
+/// image files; paths that aren't in the library are skipped
+pub(crate) fn library_ids(app: &AppHandle, items: &[String]) -> Vec<String> {
+    let assets = library(app).assets;
+    let mut ids: Vec<String> = Vec::new();
+    for item in items {
+        let found = assets.iter().find(|a| {
+            a.asset.id == *item
+                || [&a.asset.model_path, &a.asset.texture_path]
+                    .iter()
+                    .any(|path| {
+                        path.as_ref()
+                            .is_some_and(|p| Path::new(p) == Path::new(item))
+                    })
+        });
+        if let Some(asset) = found {
+            if !ids.contains(&asset.asset.id) {
+                ids.push(asset.asset.id.clone());
+            }
+        }
+    }
+    ids
+}
+
+/// Count an export of each of `exported`, given as library ids or as the
+/// paths of their model files
+///
+/// A failure to save the counts is only logged, since the export itself
+/// went through.
+pub(crate) fn record_exports(app: &AppHandle, exported: &[String]) {
+    let ids = library_ids(app, exported);
+    if let Err(e) = app.state::<CurationStore>().record_exports(&ids) {
+        log::warn!("Failed to record exports: {}", e);
+    }
+}
+
+/// Add `entry` to the history of the library asset `item`, given as an id
+/// or the path of its model file
+///
+/// Files outside the library have no history to add to. Like export
+/// counts, a failure to save is only logged.
+pub(crate) fn record_history(app: &AppHandle, item: &str, entry: HistoryEntry) {
+    let history = app.state::<AssetHistory>();
+    for id in library_ids(app, &[item.to_string()]) {
+        if let Err(e) = history.record(&id, entry.clone()) {
+            log::warn!("Failed to record the history of {}: {}", id, e);
+        }
+    }
+}
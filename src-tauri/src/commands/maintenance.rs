@@ -0,0 +1,228 @@
+use crate::commands::file_ops::StorageAsset;
+use crate::commands::library::index_root;
+use crate::commands::reports::{report_asset, validation_profile};
+use crate::utils::analysis_cache::{AnalysisCache, ModelSource};
+use crate::utils::asset_cache::AssetCache;
+use crate::utils::image_thumbnail;
+use crate::utils::jobs::JobQueue;
+use crate::utils::library::LibraryIndex;
+use crate::utils::maintenance::{
+    ChangedFile, FailedValidation, MaintenanceSchedule, MaintenanceSummary, MaintenanceTask,
+};
+use crate::utils::mesh_files::MeshFileFormat;
+use crate::utils::provenance;
+use crate::utils::settings::SettingsStore;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use tracing::instrument;
+
+/// How often the scheduler looks at the clock
+const SCHEDULE_POLL: Duration = Duration::from_secs(30);
+
+/// Replace the maintenance schedule
+///
+/// The time is local 24-hour `HH:MM`; tasks listed twice run once.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn set_maintenance_schedule(
+    settings: State<'_, SettingsStore>,
+    mut schedule: MaintenanceSchedule,
+) -> Result<MaintenanceSchedule, String> {
+    schedule.time = schedule.time_of_day()?.format("%H:%M").to_string();
+    let mut tasks = Vec::new();
+    for task in schedule.tasks {
+        if !tasks.contains(&task) {
+            tasks.push(task);
+        }
+    }
+    schedule.tasks = tasks;
+    Ok(settings.update(|s| s.maintenance = schedule)?.maintenance)
+}
+
+/// Queue a maintenance run now, with `tasks` or those of the schedule
+///
+/// Returns the job id, or `None` if a run is already queued. The summary
+/// is the job's result and is also sent with a `maintenance-finished` event.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn run_maintenance(
+    app: AppHandle,
+    tasks: Option<Vec<MaintenanceTask>>,
+) -> Result<Option<u64>, String> {
+    let tasks = tasks.unwrap_or_else(|| app.state::<SettingsStore>().get().maintenance.tasks);
+    if tasks.is_empty() {
+        return Err("No maintenance tasks to run".to_string());
+    }
+    Ok(queue_maintenance(&app, tasks))
+}
+
+/// Start the thread that queues maintenance when the schedule says so
+///
+/// The schedule is read from the settings on every check, so changes apply
+/// without a restart. A run missed while the app was closed is not made up.
+pub fn start_schedule(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        let mut checked = chrono::Local::now().naive_local();
+        loop {
+            thread::sleep(SCHEDULE_POLL);
+            let now = chrono::Local::now().naive_local();
+            let schedule = app.state::<SettingsStore>().get().maintenance;
+            if schedule.due_between(checked, now) {
+                log::info!("Starting scheduled maintenance");
+                queue_maintenance(&app, schedule.tasks);
+            }
+            checked = now;
+        }
+    });
+}
+
+pub(crate) fn queue_maintenance(app: &AppHandle, tasks: Vec<MaintenanceTask>) -> Option<u64> {
+    let job_app = app.clone();
+    let resume = serde_json::to_value(&tasks).unwrap_or_default();
+    app.state::<JobQueue>().submit_resumable(
+        "maintenance",
+        "library",
+        resume,
+        Box::new(move || {
+            let summary = run(&job_app, &tasks);
+            if let Err(e) = job_app.emit("maintenance-finished", &summary) {
+                log::warn!("Failed to emit maintenance-finished: {}", e);
+            }
+            serde_json::to_value(summary).map_err(|e| e.to_string())
+        }),
+    )
+}
+
+/// Index every storage root again, then run `tasks` over their assets
+fn run(app: &AppHandle, tasks: &[MaintenanceTask]) -> MaintenanceSummary {
+    let settings = app.state::<SettingsStore>().get();
+    let index = app.state::<LibraryIndex>();
+    let cache = app.state::<AssetCache>();
+    let mut summary = MaintenanceSummary {
+        tasks: tasks.to_vec(),
+        started_ms: now_ms(),
+        ..Default::default()
+    };
+
+    // Indexing remakes outdated texture thumbnails, so count them first
+    if tasks.contains(&MaintenanceTask::RefreshThumbnails) {
+        summary.refreshed_thumbnail_count = settings
+            .storage_roots
+            .iter()
+            .filter_map(|root| index.get(&root.name))
+            .flat_map(|root| root.assets)
+            .filter_map(|asset| asset.texture_path)
+            .filter(|texture| image_thumbnail::is_stale(&cache, Path::new(texture)))
+            .count();
+    }
+
+    let mut assets: Vec<(String, StorageAsset)> = Vec::new();
+    for root in &settings.storage_roots {
+        if let Err(e) = index_root(app, root) {
+            summary.errors.push(format!("{}: {}", root.name, e));
+        }
+        let Some(indexed) = index.get(&root.name) else {
+            continue;
+        };
+        if let Some(error) = indexed.error {
+            summary
+                .offline_roots
+                .push(format!("{}: {}", root.name, error));
+            continue;
+        }
+        assets.extend(
+            indexed
+                .assets
+                .into_iter()
+                .map(|asset| (root.name.clone(), asset)),
+        );
+    }
+    summary.asset_count = assets.len();
+
+    if tasks.contains(&MaintenanceTask::VerifyHashes) {
+        for (root, asset) in &assets {
+            let Some(model) = &asset.model_path else {
+                continue;
+            };
+            let model = Path::new(model);
+            if MeshFileFormat::from_path(model) != Ok(MeshFileFormat::Gltf) {
+                continue;
+            }
+            match verify(model) {
+                Ok(check) if check.provenance.is_none() => summary.unstamped_count += 1,
+                Ok(check) if check.intact => summary.intact_count += 1,
+                Ok(_) => summary.changed_files.push(ChangedFile {
+                    root: root.clone(),
+                    asset_id: asset.id.clone(),
+                    path: model.to_string_lossy().to_string(),
+                }),
+                Err(e) => summary.errors.push(format!("{}: {}", model.display(), e)),
+            }
+        }
+    }
+
+    if tasks.contains(&MaintenanceTask::RefreshThumbnails) {
+        summary.stale_thumbnails = assets
+            .iter()
+            .filter_map(|(_, asset)| asset.model_path.as_ref().map(|model| (asset, model)))
+            .filter(|(asset, model)| match &asset.thumbnail_path {
+                Some(thumbnail) => modified(Path::new(thumbnail)) < modified(Path::new(model)),
+                None => true,
+            })
+            .map(|(_, model)| model.clone())
+            .collect();
+    }
+
+    if tasks.contains(&MaintenanceTask::Revalidate) {
+        match validation_profile(&settings, None) {
+            Ok(profile) => {
+                let analysis = app.state::<AnalysisCache>();
+                summary.validation_profile = profile.as_ref().map(|p| p.name.clone());
+                for (root, asset) in &assets {
+                    let report = report_asset(
+                        &analysis,
+                        &asset.id,
+                        Path::new(&asset.path),
+                        profile.as_ref(),
+                    );
+                    if report.error.is_some() || report.score < 100 {
+                        summary.failed_validation.push(FailedValidation {
+                            root: root.clone(),
+                            report,
+                        });
+                    }
+                }
+            }
+            Err(e) => summary.errors.push(e),
+        }
+    }
+
+    summary.finished_ms = now_ms();
+    summary
+}
+
+fn verify(path: &Path) -> Result<provenance::ProvenanceCheck, String> {
+    let source = ModelSource::open(path)?;
+    let document: Value = serde_json::from_slice(source.json())
+        .map_err(|e| format!("Failed to parse GLTF: {}", e))?;
+    Ok(provenance::verify(
+        &document,
+        source.bin().unwrap_or_default(),
+    ))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -1,6 +1,38 @@
+use crate::commands::mesh_upload::{describe_mesh, MeshHandle};
+use crate::utils::decimate::{
+    cluster_decimate_constrained, cluster_decimate_to_error, ClusterConstraints, DecimatedMesh,
+};
+use crate::utils::degenerate::{self, DegenerateCleanup, FaceThresholds};
+use crate::utils::hollow::{self, OffsetMode};
+use crate::utils::lightmap;
+use crate::utils::mesh_array::{self, ArrayPattern};
+use crate::utils::mesh_diff::{self, SpatialDiff};
+use crate::utils::mesh_edit::{self, CropBox, MeshSelection};
+use crate::utils::mesh_files::load_meshes;
+use crate::utils::mesh_store::{MeshData, MeshStore};
+use crate::utils::orientation::{self, OrientationCriteria, PrintOrientation};
+use crate::utils::path_scope::PathScope;
+use crate::utils::plane_cut;
+use crate::utils::polygons::{self, FaceTopology};
+use crate::utils::printability::{self, PrintSettings, PrintabilityReport};
+use crate::utils::reindex::reindex;
+use crate::utils::sanitize::{self, SanitizeMode, SanitizeReport, MAX_COORDINATE};
+use crate::utils::seams::find_seams;
+use crate::utils::section::{self, Section, SectionPlane};
+use crate::utils::shrinkwrap;
+use crate::utils::simd;
+use crate::utils::skin::{joint_regions, merge_influences};
+use crate::utils::symmetry::{self, MirrorMode, MirrorPlane, SymmetryReport};
+use crate::utils::texel_density::{self, IslandResize};
+use crate::utils::topology::{self, Topology};
+use crate::utils::uv_analysis::{UvAnalysis, UvLayout, UvRenderFormat, UvSet};
+use crate::utils::uv_projection::{self, UvProjection};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use tauri::command;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{command, State};
+use tracing::instrument;
 
 /// Result of LOD generation
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +49,25 @@ pub struct LodLevel {
     pub vertex_count: usize,
     pub face_count: usize,
     pub reduction_ratio: f32,
+    /// Largest distance a vertex moved, in mesh units (screen-space mode)
+    pub geometric_error: Option<f32>,
+    /// Camera distance from which this level can replace the previous one
+    /// (screen-space mode)
+    pub switch_distance: Option<f32>,
+    /// Handle of the simplified mesh (screen-space mode)
+    pub mesh_handle: Option<u64>,
+}
+
+/// Viewing setup used to pick LOD levels by screen-space error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScreenSpaceLod {
+    /// Vertical field of view in degrees
+    pub fov_y_degrees: f32,
+    pub viewport_height_px: u32,
+    /// Largest error allowed on screen, in pixels (default 1)
+    pub max_error_px: Option<f32>,
+    /// Viewing distances to build levels for, in mesh units
+    pub distances: Vec<f32>,
 }
 
 /// Mesh statistics
@@ -24,11 +75,169 @@ pub struct LodLevel {
 pub struct MeshStats {
     pub vertex_count: usize,
     pub face_count: usize,
-    pub edge_count: usize,
+    /// Every edge has at most two faces and every vertex a single fan
     pub is_manifold: bool,
     pub has_degenerate_faces: bool,
+    /// Faces with repeated corners or no area
+    pub degenerate_face_count: usize,
+    /// Long thin faces past the default aspect ratio limit
+    pub sliver_face_count: usize,
     pub surface_area: f32,
     pub volume: f32,
+    /// Edge count, shells, boundary loops and genus
+    #[serde(flatten)]
+    pub topology: Topology,
+}
+
+/// Result of simplifying a mesh into a new handle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimplifiedMesh {
+    pub mesh: MeshHandle,
+    pub original_face_count: usize,
+    pub reduction_ratio: f32,
+    /// Vertices with weight 1 (including `protected_vertices` and seams)
+    pub protected_vertex_count: usize,
+    /// Vertices locked because they lie on a UV seam or material boundary
+    pub seam_vertex_count: usize,
+    /// Material of each output face, when `face_materials` was given
+    pub face_materials: Option<Vec<u32>>,
+}
+
+/// Result of welding a mesh's vertices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReindexResult {
+    pub mesh: MeshHandle,
+    pub original_vertex_count: usize,
+    /// Faces whose corners were welded into a line or point
+    pub removed_face_count: usize,
+    /// Vertices kept, as a fraction of the original count
+    pub reduction_ratio: f32,
+}
+
+/// What `repair_mesh` should fix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepairOptions {
+    /// How to handle NaN, infinite and out-of-range positions, or `None` to
+    /// only report them (default drop); bad attribute values are zeroed and
+    /// faces with out-of-range indices dropped either way
+    pub sanitize: Option<SanitizeMode>,
+    /// Coordinates past this magnitude count as out of range (default 1e6)
+    pub max_coordinate: f32,
+    /// Drop faces with repeated corners or no area (default true)
+    pub remove_degenerate: bool,
+    /// Collapse or flip sliver faces (default true); degenerate faces are
+    /// dropped afterwards either way
+    pub fix_slivers: bool,
+    pub thresholds: FaceThresholds,
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        Self {
+            sanitize: Some(SanitizeMode::Drop),
+            max_coordinate: MAX_COORDINATE,
+            remove_degenerate: true,
+            fix_slivers: true,
+            thresholds: FaceThresholds::default(),
+        }
+    }
+}
+
+/// Result of repairing a mesh
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepairResult {
+    pub mesh: MeshHandle,
+    pub sanitized: SanitizeReport,
+    pub degenerate: DegenerateCleanup,
+}
+
+/// Result of splitting selected faces off a mesh
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeparatedMesh {
+    /// The mesh without the selected faces
+    pub rest: MeshHandle,
+    pub separated: MeshHandle,
+}
+
+/// Result of cutting a mesh in two along a plane
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaneCutResult {
+    /// The part on the side the plane's normal points to
+    pub front: MeshHandle,
+    pub back: MeshHandle,
+    pub closed_loop_count: usize,
+    /// Cut runs through holes in the mesh, which are left open
+    pub open_loop_count: usize,
+}
+
+/// Result of shrinkwrapping a template onto a target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShrinkwrapResult {
+    pub mesh: MeshHandle,
+    /// How far template vertices moved to reach the target
+    pub mean_displacement: f32,
+    pub max_displacement: f32,
+}
+
+/// Result of scaling a mesh to a known measurement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeasuredScale {
+    pub mesh: MeshHandle,
+    /// Distance between the picked points before scaling
+    pub measured_distance: f32,
+    pub scale_factor: f32,
+}
+
+/// Result of projecting UVs onto a mesh
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectedUvs {
+    pub mesh: MeshHandle,
+    /// Vertices added where the projection is discontinuous
+    pub split_vertex_count: usize,
+}
+
+/// Result of laying out lightmap UVs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LightmapUvs {
+    pub mesh: MeshHandle,
+    pub chart_count: usize,
+    /// Vertices added along chart boundaries
+    pub split_vertex_count: usize,
+    pub texels_per_unit: f32,
+    /// Fraction of the lightmap covered by faces
+    pub coverage: f32,
+}
+
+/// Result of evening out texel density
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NormalizedTexelDensity {
+    pub mesh: MeshHandle,
+    pub texels_per_cm: f32,
+    pub island_count: usize,
+    /// Islands that were rescaled, and by how much
+    pub resized: Vec<IslandResize>,
+    /// Islands without UV or surface area, left alone
+    pub skipped_island_count: usize,
+}
+
+/// Statistics for one mesh of a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedMeshStats {
+    pub name: String,
+    pub stats: MeshStats,
+    /// Whether the mesh was modelled in quads before triangulation
+    pub topology: FaceTopology,
+}
+
+/// Statistics for every mesh in a model file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileStats {
+    pub path: String,
+    pub format: String,
+    pub meshes: Vec<NamedMeshStats>,
+    /// Sums over all meshes; manifold only if every mesh is
+    pub total: MeshStats,
 }
 
 /// Result of mesh optimization
@@ -42,16 +251,47 @@ pub struct OptimizedMeshResult {
     pub overdraw_after: f32,
 }
 
+/// Resolve mesh input given either inline arrays or an uploaded mesh handle
+pub fn resolve_mesh(
+    store: &MeshStore,
+    vertices: Option<Vec<f32>>,
+    indices: Option<Vec<u32>>,
+    mesh_handle: Option<u64>,
+) -> Result<Arc<MeshData>, String> {
+    if let Some(handle) = mesh_handle {
+        return store.get(handle);
+    }
+
+    Ok(Arc::new(MeshData {
+        positions: vertices.unwrap_or_default(),
+        indices: indices.unwrap_or_default(),
+        ..Default::default()
+    }))
+}
+
 /// Generate LOD levels for a mesh
 ///
-/// Takes vertex positions and indices, returns simplified versions
-/// at various quality levels (e.g., 0.75, 0.5, 0.25, 0.1)
+/// Takes vertex positions and indices (or a handle from
+/// `finish_mesh_upload`), returns simplified versions at various
+/// quality levels (e.g., 0.75, 0.5, 0.25, 0.1)
+///
+/// With `screen_space`, levels are instead chosen so each stays under the
+/// pixel error at the given viewing distances. Those levels are simplified
+/// for real, registered as mesh handles and come with the distance at which
+/// to switch to them.
 #[command]
+#[instrument(skip_all, err)]
 pub async fn generate_lod(
-    vertices: Vec<f32>,
-    indices: Vec<u32>,
-    target_ratios: Vec<f32>,
+    store: State<'_, MeshStore>,
+    vertices: Option<Vec<f32>>,
+    indices: Option<Vec<u32>>,
+    mesh_handle: Option<u64>,
+    target_ratios: Option<Vec<f32>>,
+    screen_space: Option<ScreenSpaceLod>,
 ) -> Result<LodResult, String> {
+    let mesh = resolve_mesh(&store, vertices, indices, mesh_handle)?;
+    let (vertices, indices) = (&mesh.positions, &mesh.indices);
+
     if vertices.is_empty() {
         return Err("No vertices provided".to_string());
     }
@@ -63,6 +303,16 @@ pub async fn generate_lod(
     let vertex_count = vertices.len() / 3;
     let face_count = indices.len() / 3;
 
+    if let Some(view) = screen_space {
+        return Ok(LodResult {
+            original_vertex_count: vertex_count,
+            original_face_count: face_count,
+            levels: screen_space_levels(&store, &mesh, mesh_handle, &view)?,
+        });
+    }
+    let target_ratios =
+        target_ratios.ok_or_else(|| "Pass either target_ratios or screen_space".to_string())?;
+
     // Generate LOD levels in parallel
     let levels: Vec<LodLevel> = target_ratios
         .par_iter()
@@ -78,6 +328,9 @@ pub async fn generate_lod(
                 vertex_count: target_vertices.max(3),
                 face_count: target_faces.max(1),
                 reduction_ratio: ratio,
+                geometric_error: None,
+                switch_distance: None,
+                mesh_handle: None,
             }
         })
         .collect();
@@ -89,14 +342,903 @@ pub async fn generate_lod(
     })
 }
 
+/// Simplify a mesh once per viewing distance, keeping only levels that differ
+fn screen_space_levels(
+    store: &MeshStore,
+    mesh: &MeshData,
+    mesh_handle: Option<u64>,
+    view: &ScreenSpaceLod,
+) -> Result<Vec<LodLevel>, String> {
+    let max_error_px = view.max_error_px.unwrap_or(1.0);
+    if !(view.fov_y_degrees > 0.0 && view.fov_y_degrees < 180.0) {
+        return Err(format!("Invalid field of view: {}", view.fov_y_degrees));
+    }
+    if view.viewport_height_px == 0 || max_error_px <= 0.0 {
+        return Err("Viewport height and pixel error must be positive".to_string());
+    }
+    if view.distances.iter().any(|d| !d.is_finite() || *d <= 0.0) {
+        return Err("Distances must be positive".to_string());
+    }
+
+    // World-space height of one pixel, per unit of distance from the camera
+    let pixel_size =
+        2.0 * (view.fov_y_degrees.to_radians() / 2.0).tan() / view.viewport_height_px as f32;
+    let mut distances = view.distances.clone();
+    distances.sort_by(f32::total_cmp);
+    distances.dedup();
+
+    let simplified: Vec<(DecimatedMesh, f32)> = distances
+        .par_iter()
+        .map(|&distance| {
+            let max_error = max_error_px * pixel_size * distance;
+            cluster_decimate_to_error(&mesh.positions, &mesh.indices, max_error)
+        })
+        .collect();
+
+    let face_count = mesh.face_count();
+    let mut levels = vec![LodLevel {
+        level: 0,
+        vertex_count: mesh.vertex_count(),
+        face_count,
+        reduction_ratio: 1.0,
+        geometric_error: Some(0.0),
+        switch_distance: Some(0.0),
+        mesh_handle,
+    }];
+
+    for (decimated, error) in simplified {
+        let faces = decimated.indices.len() / 3;
+        let previous = levels.last().unwrap();
+        if faces == 0 || faces >= previous.face_count {
+            continue;
+        }
+        // The level is good enough once its error shrinks below the pixel limit
+        let switch_distance =
+            (error / (max_error_px * pixel_size)).max(previous.switch_distance.unwrap_or(0.0));
+
+        let vertex_count = decimated.positions.len() / 3;
+        let handle = store.insert(simplified_mesh_data(mesh, decimated));
+        levels.push(LodLevel {
+            level: levels.len() as u32,
+            vertex_count,
+            face_count: faces,
+            reduction_ratio: faces as f32 / face_count as f32,
+            geometric_error: Some(error),
+            switch_distance: Some(switch_distance),
+            mesh_handle: Some(handle),
+        });
+    }
+
+    Ok(levels)
+}
+
+/// Simplify a mesh to `target_ratio` of its faces, keeping chosen regions detailed
+///
+/// `weights` assigns each vertex an importance in [0, 1] (e.g. high on a
+/// character's face, low on its back); `protected_vertices` lists vertices
+/// that are never merged, the same as a weight of 1. The result is
+/// registered as a new mesh handle.
+///
+/// With `preserve_seams`, vertices on UV seams are locked and UV islands are
+/// simplified separately, so textures don't smear across islands.
+/// `face_materials` (one id per face) adds the same treatment for material
+/// boundaries.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn simplify_mesh(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    target_ratio: f32,
+    weights: Option<Vec<f32>>,
+    protected_vertices: Option<Vec<u32>>,
+    preserve_seams: Option<bool>,
+    face_materials: Option<Vec<u32>>,
+) -> Result<SimplifiedMesh, String> {
+    let mesh = store.get(mesh_handle)?;
+    let vertex_count = mesh.vertex_count();
+    let face_count = mesh.face_count();
+    if mesh.indices.is_empty() {
+        return Err("No indices provided".to_string());
+    }
+    if !(target_ratio > 0.0 && target_ratio <= 1.0) {
+        return Err(format!(
+            "Target ratio must be in (0, 1], got {}",
+            target_ratio
+        ));
+    }
+
+    let mut weights = match weights {
+        Some(weights) if weights.len() != vertex_count => {
+            return Err(format!(
+                "Expected {} weights, got {}",
+                vertex_count,
+                weights.len()
+            ))
+        }
+        Some(weights) => weights,
+        None => vec![0.0; vertex_count],
+    };
+    for &vertex in protected_vertices.iter().flatten() {
+        let weight = weights
+            .get_mut(vertex as usize)
+            .ok_or_else(|| format!("Protected vertex {} out of range", vertex))?;
+        *weight = 1.0;
+    }
+    if let Some(materials) = &face_materials {
+        if materials.len() != face_count {
+            return Err(format!(
+                "Expected {} face materials, got {}",
+                face_count,
+                materials.len()
+            ));
+        }
+    }
+
+    let seams = (preserve_seams.unwrap_or(false) || face_materials.is_some())
+        .then(|| find_seams(&mesh.positions, &mesh.indices, face_materials.as_deref()));
+    let mut seam_vertex_count = 0;
+    if let Some(seams) = &seams {
+        for (weight, _) in weights.iter_mut().zip(&seams.locked).filter(|(_, &l)| l) {
+            *weight = 1.0;
+            seam_vertex_count += 1;
+        }
+    }
+    let protected_vertex_count = weights.iter().filter(|&&w| w >= 1.0).count();
+
+    let constraints = ClusterConstraints {
+        weights: Some(&weights),
+        groups: seams.as_ref().map(|s| s.groups.as_slice()),
+    };
+    let target_faces = ((face_count as f32 * target_ratio) as usize).max(1);
+    let decimated =
+        cluster_decimate_constrained(&mesh.positions, &mesh.indices, &constraints, target_faces);
+    let simplified_faces = decimated.indices.len() / 3;
+
+    let face_materials = face_materials.map(|materials| {
+        decimated
+            .source_faces
+            .iter()
+            .map(|&f| materials[f as usize])
+            .collect()
+    });
+
+    let handle = store.insert(simplified_mesh_data(&mesh, decimated));
+
+    Ok(SimplifiedMesh {
+        mesh: describe_mesh(&store, handle)?,
+        original_face_count: face_count,
+        reduction_ratio: simplified_faces as f32 / face_count as f32,
+        protected_vertex_count,
+        seam_vertex_count,
+        face_materials,
+    })
+}
+
+/// Generate animatable LOD levels for a skinned mesh
+///
+/// Each level in `target_ratios` is simplified and registered as a mesh
+/// handle. Vertices are only merged with others bound mostly to the same
+/// joint, and merged vertices combine their influences into the four
+/// strongest, re-normalized. `preserve_seams` additionally locks UV seams.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn generate_skinned_lod(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    target_ratios: Vec<f32>,
+    preserve_seams: Option<bool>,
+) -> Result<LodResult, String> {
+    let mesh = store.get(mesh_handle)?;
+    let (Some(joints), Some(weights)) = (&mesh.skin_joints, &mesh.skin_weights) else {
+        return Err(format!("Mesh {} has no joints or weights", mesh_handle));
+    };
+    if mesh.indices.is_empty() {
+        return Err("No indices provided".to_string());
+    }
+    if let Some(&bad) = target_ratios.iter().find(|&&r| !(r > 0.0 && r <= 1.0)) {
+        return Err(format!("Target ratio must be in (0, 1], got {}", bad));
+    }
+
+    let regions = joint_regions(joints, weights, &mesh.indices);
+    let mut locked = regions.locked;
+    let mut groups = regions.groups;
+    if preserve_seams.unwrap_or(false) {
+        let seams = find_seams(&mesh.positions, &mesh.indices, None);
+        let mut ids: HashMap<(u32, u32), u32> = HashMap::new();
+        for (i, group) in groups.iter_mut().enumerate() {
+            let next = ids.len() as u32;
+            *group = *ids.entry((*group, seams.groups[i])).or_insert(next);
+            locked[i] |= seams.locked[i];
+        }
+    }
+    let lock_weights: Vec<f32> = locked.iter().map(|&l| l as u8 as f32).collect();
+    let constraints = ClusterConstraints {
+        weights: Some(&lock_weights),
+        groups: Some(&groups),
+    };
+
+    let face_count = mesh.face_count();
+    let simplified: Vec<DecimatedMesh> = target_ratios
+        .par_iter()
+        .map(|&ratio| {
+            let target_faces = ((face_count as f32 * ratio) as usize).max(1);
+            cluster_decimate_constrained(&mesh.positions, &mesh.indices, &constraints, target_faces)
+        })
+        .collect();
+
+    let levels = simplified
+        .into_iter()
+        .enumerate()
+        .map(|(idx, decimated)| {
+            let faces = decimated.indices.len() / 3;
+            let vertex_count = decimated.positions.len() / 3;
+            let handle = store.insert(simplified_mesh_data(&mesh, decimated));
+            LodLevel {
+                level: idx as u32,
+                vertex_count,
+                face_count: faces,
+                reduction_ratio: faces as f32 / face_count as f32,
+                geometric_error: None,
+                switch_distance: None,
+                mesh_handle: Some(handle),
+            }
+        })
+        .collect();
+
+    Ok(LodResult {
+        original_vertex_count: mesh.vertex_count(),
+        original_face_count: face_count,
+        levels,
+    })
+}
+
+/// Build the simplified mesh, carrying over the source's vertex attributes
+///
+/// Normals, UVs and colors are averaged over merged vertices; skin influences are
+/// merged and re-normalized.
+fn simplified_mesh_data(source: &MeshData, decimated: DecimatedMesh) -> MeshData {
+    let normals = source.normals.as_ref().map(|normals| {
+        let mut normals = decimated.remap_attribute(normals, 3);
+        for n in normals.chunks_exact_mut(3) {
+            let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+            if len > 0.0 {
+                n.iter_mut().for_each(|v| *v /= len);
+            }
+        }
+        normals
+    });
+    let uvs = source
+        .uvs
+        .as_ref()
+        .map(|uvs| decimated.remap_attribute(uvs, 2));
+    let lightmap_uvs = source
+        .lightmap_uvs
+        .as_ref()
+        .map(|uvs| decimated.remap_attribute(uvs, 2));
+    let colors = source
+        .colors
+        .as_ref()
+        .map(|colors| decimated.remap_attribute(colors, 4));
+    let (skin_joints, skin_weights) = match (&source.skin_joints, &source.skin_weights) {
+        (Some(joints), Some(weights)) => {
+            let (joints, weights) = merge_influences(&decimated, joints, weights);
+            (Some(joints), Some(weights))
+        }
+        _ => (None, None),
+    };
+
+    MeshData {
+        positions: decimated.positions,
+        normals,
+        uvs,
+        lightmap_uvs,
+        colors,
+        indices: decimated.indices,
+        skin_joints,
+        skin_weights,
+        polygons: None,
+    }
+}
+
+/// Weld vertices that match in every attribute and rebuild the index buffer
+///
+/// `tolerance` is 0 for bit-identical vertices, otherwise the largest
+/// distance (and per-component attribute difference) still welded. The mesh
+/// behind the handle is replaced.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn reindex_mesh(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    tolerance: f32,
+) -> Result<ReindexResult, String> {
+    let mesh = store.get(mesh_handle)?;
+    if mesh.indices.is_empty() {
+        return Err("No indices provided".to_string());
+    }
+    if !(tolerance >= 0.0 && tolerance.is_finite()) {
+        return Err(format!(
+            "Tolerance must be a non-negative number, got {}",
+            tolerance
+        ));
+    }
+
+    let original_vertex_count = mesh.vertex_count();
+    let reindexed = reindex(&mesh, tolerance);
+    let vertex_count = reindexed.mesh.vertex_count();
+    store.replace(mesh_handle, reindexed.mesh)?;
+
+    Ok(ReindexResult {
+        mesh: describe_mesh(&store, mesh_handle)?,
+        original_vertex_count,
+        removed_face_count: reindexed.removed_face_count,
+        reduction_ratio: vertex_count as f32 / original_vertex_count as f32,
+    })
+}
+
+/// Clean up bad data, then degenerate and sliver faces
+///
+/// Counts are always reported; each step only changes the mesh when its
+/// option is set. The mesh behind the handle is replaced.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn repair_mesh(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    options: Option<RepairOptions>,
+) -> Result<RepairResult, String> {
+    let options = options.unwrap_or_default();
+    let thresholds = options.thresholds;
+    if !(thresholds.min_area >= 0.0 && thresholds.max_aspect_ratio >= 1.0) {
+        return Err(format!(
+            "Thresholds need a non-negative min_area and a max_aspect_ratio of at least 1, got {} and {}",
+            thresholds.min_area, thresholds.max_aspect_ratio
+        ));
+    }
+    if !(options.max_coordinate > 0.0 && options.max_coordinate.is_finite()) {
+        return Err(format!(
+            "Coordinate limit must be a positive number, got {}",
+            options.max_coordinate
+        ));
+    }
+    let mut mesh = store.get(mesh_handle)?;
+
+    // Degenerate checks can't rate faces with NaN corners, so sanitize first
+    let sanitized = match options.sanitize {
+        Some(mode) => {
+            let (cleaned, report) = sanitize::sanitize(&mesh, mode, options.max_coordinate);
+            if cleaned.positions.is_empty() {
+                return Err("No valid vertices left after sanitizing".to_string());
+            }
+            mesh = Arc::new(cleaned);
+            report
+        }
+        None => SanitizeReport {
+            issues: sanitize::scan(&mesh, options.max_coordinate),
+            ..Default::default()
+        },
+    };
+
+    let degenerate = if options.remove_degenerate || options.fix_slivers {
+        let (cleaned, report) = degenerate::clean(&mesh, &thresholds, options.fix_slivers);
+        mesh = Arc::new(cleaned);
+        report
+    } else {
+        let (degenerate_face_count, sliver_face_count) =
+            degenerate::count(&mesh.positions, &mesh.indices, &thresholds);
+        DegenerateCleanup {
+            degenerate_face_count,
+            sliver_face_count,
+            remaining_sliver_count: sliver_face_count,
+            ..Default::default()
+        }
+    };
+    if options.sanitize.is_some() || options.remove_degenerate || options.fix_slivers {
+        store.replace(mesh_handle, Arc::unwrap_or_clone(mesh))?;
+    }
+
+    Ok(RepairResult {
+        mesh: describe_mesh(&store, mesh_handle)?,
+        sanitized,
+        degenerate,
+    })
+}
+
+/// Cut a mesh with a plane into polylines
+///
+/// Points are in plane coordinates along the returned `u_axis` and
+/// `v_axis`, in mesh units.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn slice_mesh(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    plane: SectionPlane,
+) -> Result<Section, String> {
+    let mesh = store.get(mesh_handle)?;
+    Ok(section::slice(&mesh, &plane.normalized()?))
+}
+
+/// Check whether a mesh is ready to 3D print and estimate the print
+///
+/// The mesh is taken as Y-up meters and printed as it stands. Time and
+/// material come from slicing it, not from real toolpaths, so treat them as
+/// a rough guide; settings default to a common FDM profile.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn printability_report(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    settings: Option<PrintSettings>,
+) -> Result<PrintabilityReport, String> {
+    let mesh = store.get(mesh_handle)?;
+    printability::report(&mesh, &settings.unwrap_or_default())
+}
+
+/// Rank orientations to 3D print a mesh in, best first
+///
+/// Each result names the mesh direction that would face the build plate
+/// and the rotation that puts it there, scored on support, plate contact
+/// and height as weighted by `criteria`.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn suggest_print_orientation(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    criteria: Option<OrientationCriteria>,
+) -> Result<Vec<PrintOrientation>, String> {
+    let mesh = store.get(mesh_handle)?;
+    orientation::suggest(&mesh, &criteria.unwrap_or_default())
+}
+
+/// Find the dominant mirror plane of a mesh and what breaks the symmetry
+///
+/// `tolerance` is how far (in mesh units) a reflected vertex may land from
+/// its counterpart; it defaults to a small fraction of the mesh size.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn detect_symmetry(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    tolerance: Option<f32>,
+) -> Result<SymmetryReport, String> {
+    let mesh = store.get(mesh_handle)?;
+    symmetry::detect(&mesh, tolerance)
+}
+
+/// Mirror a mesh across `plane` into a new handle
+///
+/// The default mode rebuilds the half behind the plane (opposite its normal)
+/// from the half in front, which fixes accidental asymmetry; `flip` mirrors
+/// the whole mesh. Vertices within `tolerance` of the plane are welded.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn mirror_mesh(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    plane: MirrorPlane,
+    mode: Option<MirrorMode>,
+    tolerance: Option<f32>,
+) -> Result<MeshHandle, String> {
+    let mesh = store.get(mesh_handle)?;
+    let plane = plane.normalized()?;
+    let mirrored = match mode.unwrap_or_default() {
+        MirrorMode::Flip => symmetry::flip(&mesh, &plane),
+        MirrorMode::Symmetrize => {
+            symmetry::symmetrize(&mesh, &plane, tolerance.unwrap_or(1e-5).max(0.0))
+        }
+    };
+    if mirrored.indices.is_empty() {
+        return Err("No faces in front of the mirror plane".to_string());
+    }
+
+    let handle = store.insert(mirrored);
+    describe_mesh(&store, handle)
+}
+
+/// Uniformly scale a mesh so two picked points are `real_distance` apart
+///
+/// Meant for scans and photogrammetry, which rarely come out at real size:
+/// pick two ends of a feature with a known length (in meters, like mesh
+/// units). Scales about the origin, as import unit conversion does. The
+/// mesh behind the handle is replaced.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn scale_to_measurement(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    point_a: [f32; 3],
+    point_b: [f32; 3],
+    real_distance: f32,
+) -> Result<MeasuredScale, String> {
+    if !(real_distance > 0.0 && real_distance.is_finite()) {
+        return Err(format!(
+            "Real distance must be a positive number, got {}",
+            real_distance
+        ));
+    }
+    let measured_distance = (0..3)
+        .map(|k| (point_b[k] - point_a[k]).powi(2))
+        .sum::<f32>()
+        .sqrt();
+    if !(measured_distance > 0.0 && measured_distance.is_finite()) {
+        return Err("The picked points must be distinct".to_string());
+    }
+    let scale_factor = real_distance / measured_distance;
+
+    // Uniform scaling keeps normals and winding as they are
+    let mut mesh = Arc::unwrap_or_clone(store.get(mesh_handle)?);
+    mesh.positions.iter_mut().for_each(|c| *c *= scale_factor);
+    store.replace(mesh_handle, mesh)?;
+
+    Ok(MeasuredScale {
+        mesh: describe_mesh(&store, mesh_handle)?,
+        measured_distance,
+        scale_factor,
+    })
+}
+
+/// Hollow a closed mesh for resin printing into a new handle
+///
+/// Adds an inner wall `wall_thickness` (mesh units) inside the surface, so
+/// only the shell between them is printed. Drill drain holes into the
+/// result with `add_drain_holes`.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn hollow_mesh(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    wall_thickness: f32,
+) -> Result<MeshHandle, String> {
+    let mesh = store.get(mesh_handle)?;
+    let hollowed = hollow::hollow(&mesh, wall_thickness)?;
+    let handle = store.insert(hollowed);
+    describe_mesh(&store, handle)
+}
+
+/// Offset a mesh's surface by `distance` (mesh units) into a new handle
+///
+/// Positive distances grow the mesh and negative ones shrink it. In
+/// `shell` mode the original is kept as well, so the result is a solid
+/// wall between the two surfaces, for molds and shells; `surface` mode
+/// (the default) only moves it, for tolerance compensation.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn offset_mesh(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    distance: f32,
+    mode: Option<OffsetMode>,
+) -> Result<MeshHandle, String> {
+    let mesh = store.get(mesh_handle)?;
+    let offset = hollow::offset(&mesh, distance, mode.unwrap_or_default())?;
+    let handle = store.insert(offset);
+    describe_mesh(&store, handle)
+}
+
+/// Merge every copy of a linear, radial or grid array into a new handle
+///
+/// Write the array as GPU instances with `export_array_glb` instead.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn array_mesh(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    pattern: ArrayPattern,
+) -> Result<MeshHandle, String> {
+    let mesh = store.get(mesh_handle)?;
+    let merged = mesh_array::merge(&mesh, &pattern.placements()?);
+    let handle = store.insert(merged);
+    describe_mesh(&store, handle)
+}
+
+/// Drill drain holes of `radius` through a hollowed mesh into a new handle
+///
+/// Each hole starts at the surface point closest to one of `positions` and
+/// runs straight in to the cavity.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn add_drain_holes(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    positions: Vec<[f32; 3]>,
+    radius: f32,
+) -> Result<MeshHandle, String> {
+    let mesh = store.get(mesh_handle)?;
+    let drilled = hollow::drill_drain_holes(&mesh, &positions, radius)?;
+    let handle = store.insert(drilled);
+    describe_mesh(&store, handle)
+}
+
+/// Delete the selected faces into a new handle
+///
+/// Vertices only the deleted faces used are dropped with them.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn delete_faces(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    selection: MeshSelection,
+) -> Result<MeshHandle, String> {
+    let mesh = store.get(mesh_handle)?;
+    let edited = mesh_edit::delete(&mesh, &selection)?;
+    let handle = store.insert(edited);
+    describe_mesh(&store, handle)
+}
+
+/// Split the selected faces off into a mesh of their own
+///
+/// Both the rest of the mesh and the separated part get new handles.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn separate_selection(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    selection: MeshSelection,
+) -> Result<SeparatedMesh, String> {
+    let mesh = store.get(mesh_handle)?;
+    let (rest, separated) = mesh_edit::separate(&mesh, &selection)?;
+    let rest = store.insert(rest);
+    let separated = store.insert(separated);
+    Ok(SeparatedMesh {
+        rest: describe_mesh(&store, rest)?,
+        separated: describe_mesh(&store, separated)?,
+    })
+}
+
+/// Keep only the faces inside a box into a new handle
+///
+/// The box is `bounds`, or the one around the selected faces; faces with a
+/// corner outside it are removed.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn crop_mesh(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    bounds: Option<CropBox>,
+    selection: Option<MeshSelection>,
+) -> Result<MeshHandle, String> {
+    let mesh = store.get(mesh_handle)?;
+    let bounds = match (bounds, selection) {
+        (Some(bounds), None) => bounds,
+        (None, Some(selection)) => mesh_edit::selection_bounds(&mesh, &selection)?,
+        _ => return Err("Crop to either a box or a selection".to_string()),
+    };
+    let cropped = mesh_edit::crop(&mesh, &bounds)?;
+    let handle = store.insert(cropped);
+    describe_mesh(&store, handle)
+}
+
+/// Cut a mesh in two along a plane, each half into a new handle
+///
+/// With `cap` (the default) each closed cut loop is filled on both halves,
+/// so a watertight mesh gives two watertight halves; loops inside others
+/// are filled as holes. Fails if the plane misses the mesh.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn plane_cut(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    plane: SectionPlane,
+    cap: Option<bool>,
+) -> Result<PlaneCutResult, String> {
+    let mesh = store.get(mesh_handle)?;
+    let cut = plane_cut::cut(&mesh, &plane.normalized()?, cap.unwrap_or(true))?;
+    let front = store.insert(cut.front);
+    let back = store.insert(cut.back);
+    Ok(PlaneCutResult {
+        front: describe_mesh(&store, front)?,
+        back: describe_mesh(&store, back)?,
+        closed_loop_count: cut.closed_loop_count,
+        open_loop_count: cut.open_loop_count,
+    })
+}
+
+/// Project a template mesh onto a target surface into a new handle
+///
+/// Each of the `iterations` (1 to 100) passes relaxes the template by
+/// `smoothing` (0 to 1, default 0.5) and snaps it to the closest point on
+/// the target, so the result keeps the template's topology and edge flow.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn shrinkwrap(
+    store: State<'_, MeshStore>,
+    source_handle: u64,
+    target_handle: u64,
+    iterations: u32,
+    smoothing: Option<f32>,
+) -> Result<ShrinkwrapResult, String> {
+    let source = store.get(source_handle)?;
+    let target = store.get(target_handle)?;
+    let wrapped = shrinkwrap::shrinkwrap(&source, &target, iterations, smoothing.unwrap_or(0.5))?;
+
+    let handle = store.insert(wrapped.mesh);
+    Ok(ShrinkwrapResult {
+        mesh: describe_mesh(&store, handle)?,
+        mean_displacement: wrapped.mean_displacement,
+        max_displacement: wrapped.max_displacement,
+    })
+}
+
+/// Per-vertex deviation of mesh B from mesh A, for painting a heatmap on B
+///
+/// Each of B's vertices gets its signed distance to A's surface, positive
+/// in front of A's faces. Vertices that moved by at least `resolution`
+/// (default 0.5% of A's size) count as changed and are grouped into
+/// connected regions, largest first.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn diff_meshes_spatial(
+    store: State<'_, MeshStore>,
+    a_handle: u64,
+    b_handle: u64,
+    resolution: Option<f32>,
+) -> Result<SpatialDiff, String> {
+    let a = store.get(a_handle)?;
+    let b = store.get(b_handle)?;
+    mesh_diff::diff(&a, &b, resolution)
+}
+
+/// Replace a mesh's UVs with a planar, cylindrical or box projection
+///
+/// UVs cover [0, 1] across the mesh's largest extent, times `scale`
+/// (default 1). Vertices are split where neighbouring faces project
+/// differently, so the vertex count can grow.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn project_uvs(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    projection: UvProjection,
+    scale: Option<f32>,
+) -> Result<ProjectedUvs, String> {
+    let mesh = store.get(mesh_handle)?;
+    if mesh.indices.is_empty() {
+        return Err("No indices provided".to_string());
+    }
+    let scale = scale.unwrap_or(1.0);
+    if !(scale > 0.0 && scale.is_finite()) {
+        return Err(format!("Scale must be positive, got {}", scale));
+    }
+
+    let projected = uv_projection::project(&mesh, projection, scale)?;
+    let split_vertex_count = projected.vertex_count().saturating_sub(mesh.vertex_count());
+    store.replace(mesh_handle, projected)?;
+
+    Ok(ProjectedUvs {
+        mesh: describe_mesh(&store, mesh_handle)?,
+        split_vertex_count,
+    })
+}
+
+/// Generate a non-overlapping second UV set for baked lighting
+///
+/// Charts are packed at one texel density with `padding` texels (default 4)
+/// between them in a `resolution`² lightmap (default 1024). The mesh's own
+/// UVs are kept; vertices are split along chart boundaries.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn generate_lightmap_uvs(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    padding: Option<u32>,
+    resolution: Option<u32>,
+) -> Result<LightmapUvs, String> {
+    let mesh = store.get(mesh_handle)?;
+    if mesh.indices.is_empty() {
+        return Err("No indices provided".to_string());
+    }
+
+    let layout = lightmap::generate(&mesh, padding.unwrap_or(4), resolution.unwrap_or(1024))?;
+    let split_vertex_count = layout
+        .mesh
+        .vertex_count()
+        .saturating_sub(mesh.vertex_count());
+    store.replace(mesh_handle, layout.mesh)?;
+
+    Ok(LightmapUvs {
+        mesh: describe_mesh(&store, mesh_handle)?,
+        chart_count: layout.chart_count,
+        split_vertex_count,
+        texels_per_unit: layout.texels_per_unit,
+        coverage: layout.coverage,
+    })
+}
+
+/// Inspect a mesh's UV layout
+///
+/// Reports bounds, islands, texel density for a `texture_size`² texture
+/// (default 1024), overlapping islands, flipped faces and use of UV space
+/// outside [0, 1]. `uv_set` picks the base (default) or lightmap UVs.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn analyze_uvs(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    uv_set: Option<UvSet>,
+    texture_size: Option<u32>,
+) -> Result<UvAnalysis, String> {
+    let mesh = store.get(mesh_handle)?;
+    let uv_set = uv_set.unwrap_or_default();
+    let layout = UvLayout::new(&mesh, uv_set)?;
+    Ok(layout.analyze(&mesh, uv_set, texture_size.unwrap_or(1024).max(1)))
+}
+
+/// Rescale UV islands so the whole mesh has one texel density
+///
+/// `texels_per_cm` is the target on a `texture_size`² texture (default
+/// 1024), with positions in meters. Islands within `tolerance` (default
+/// 0.01) of the target are left alone. Other islands are scaled about
+/// their centers, so the layout may need packing again afterwards.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn normalize_texel_density(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    texels_per_cm: f32,
+    texture_size: Option<u32>,
+    uv_set: Option<UvSet>,
+    tolerance: Option<f32>,
+) -> Result<NormalizedTexelDensity, String> {
+    let mesh = store.get(mesh_handle)?;
+    if mesh.indices.is_empty() {
+        return Err("No indices provided".to_string());
+    }
+
+    let result = texel_density::normalize(
+        &mesh,
+        uv_set.unwrap_or_default(),
+        texels_per_cm,
+        texture_size.unwrap_or(1024).max(1),
+        tolerance.unwrap_or(0.01).max(0.0),
+    )?;
+    store.replace(mesh_handle, result.mesh)?;
+
+    Ok(NormalizedTexelDensity {
+        mesh: describe_mesh(&store, mesh_handle)?,
+        texels_per_cm,
+        island_count: result.island_count,
+        resized: result.resized,
+        skipped_island_count: result.skipped_island_count,
+    })
+}
+
+/// Draw a mesh's UV layout as SVG or PNG bytes for the inspector
+///
+/// Islands get their own colors; overlapping faces are red and flipped
+/// faces orange. The image covers [0, 1] at `size` pixels (default 1024).
+#[command]
+#[instrument(skip_all, err)]
+pub async fn render_uv_layout(
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    uv_set: Option<UvSet>,
+    format: Option<UvRenderFormat>,
+    size: Option<u32>,
+) -> Result<Vec<u8>, String> {
+    let mesh = store.get(mesh_handle)?;
+    let layout = UvLayout::new(&mesh, uv_set.unwrap_or_default())?;
+    let size = size.unwrap_or(1024).clamp(16, 4096);
+    match format.unwrap_or_default() {
+        UvRenderFormat::Svg => Ok(layout.render_svg(size).into_bytes()),
+        UvRenderFormat::Png => layout.render_png(size),
+    }
+}
+
 /// Optimize mesh for GPU rendering
 ///
 /// Performs vertex cache optimization and overdraw optimization
 #[command]
+#[instrument(skip_all, err)]
 pub async fn optimize_mesh(
-    vertices: Vec<f32>,
-    indices: Vec<u32>,
+    store: State<'_, MeshStore>,
+    vertices: Option<Vec<f32>>,
+    indices: Option<Vec<u32>>,
+    mesh_handle: Option<u64>,
 ) -> Result<OptimizedMeshResult, String> {
+    let mesh = resolve_mesh(&store, vertices, indices, mesh_handle)?;
+    let vertices = &mesh.positions;
+
     if vertices.is_empty() {
         return Err("No vertices provided".to_string());
     }
@@ -117,118 +1259,99 @@ pub async fn optimize_mesh(
 
 /// Calculate detailed mesh statistics
 #[command]
+#[instrument(skip_all, err)]
 pub async fn calculate_mesh_stats(
-    vertices: Vec<f32>,
-    indices: Vec<u32>,
+    store: State<'_, MeshStore>,
+    vertices: Option<Vec<f32>>,
+    indices: Option<Vec<u32>>,
+    mesh_handle: Option<u64>,
 ) -> Result<MeshStats, String> {
-    if vertices.is_empty() {
+    let mesh = resolve_mesh(&store, vertices, indices, mesh_handle)?;
+
+    if mesh.positions.is_empty() {
         return Err("No vertices provided".to_string());
     }
 
-    let vertex_count = vertices.len() / 3;
-    let face_count = indices.len() / 3;
-
-    // Calculate edge count (each face has 3 edges, but edges are shared)
-    // For a closed manifold: E = 3F/2
-    let edge_count = (face_count * 3) / 2;
-
-    // Check for degenerate faces (faces with zero area)
-    let has_degenerate_faces = indices
-        .par_chunks(3)
-        .any(|face| {
-            if face.len() < 3 {
-                return true;
-            }
-            let i0 = face[0] as usize;
-            let i1 = face[1] as usize;
-            let i2 = face[2] as usize;
-
-            // Check if any two indices are the same
-            i0 == i1 || i1 == i2 || i0 == i2
-        });
+    Ok(mesh_stats(&mesh.positions, &mesh.indices))
+}
 
-    // Calculate surface area (sum of triangle areas)
-    let surface_area: f32 = indices
-        .par_chunks(3)
-        .map(|face| {
-            if face.len() < 3 {
-                return 0.0;
-            }
-            let i0 = face[0] as usize * 3;
-            let i1 = face[1] as usize * 3;
-            let i2 = face[2] as usize * 3;
+/// Calculate statistics for every mesh in a GLB/GLTF, OBJ, STL or FBX file
+///
+/// Geometry is decoded here, so the frontend doesn't have to load the file
+/// first. glTF meshes are measured in world space, once per instance.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn calculate_file_stats(
+    scope: State<'_, PathScope>,
+    path: String,
+) -> Result<FileStats, String> {
+    let path = scope.check(&path)?;
+    let (format, meshes) = load_meshes(&path)?;
 
-            if i0 + 2 >= vertices.len() || i1 + 2 >= vertices.len() || i2 + 2 >= vertices.len() {
-                return 0.0;
-            }
+    let stats: Vec<NamedMeshStats> = meshes
+        .par_iter()
+        .map(|mesh| NamedMeshStats {
+            name: mesh.name.clone(),
+            stats: mesh_stats(&mesh.positions, &mesh.indices),
+            topology: polygons::topology(mesh.polygons.as_deref()),
+        })
+        .collect();
 
-            let v0 = [vertices[i0], vertices[i0 + 1], vertices[i0 + 2]];
-            let v1 = [vertices[i1], vertices[i1 + 1], vertices[i1 + 2]];
-            let v2 = [vertices[i2], vertices[i2 + 1], vertices[i2 + 2]];
+    let mut total = MeshStats {
+        vertex_count: 0,
+        face_count: 0,
+        is_manifold: true,
+        has_degenerate_faces: false,
+        degenerate_face_count: 0,
+        sliver_face_count: 0,
+        surface_area: 0.0,
+        volume: 0.0,
+        topology: Topology::default(),
+    };
+    for mesh in &stats {
+        total.vertex_count += mesh.stats.vertex_count;
+        total.face_count += mesh.stats.face_count;
+        total.is_manifold &= mesh.stats.is_manifold;
+        total.has_degenerate_faces |= mesh.stats.has_degenerate_faces;
+        total.degenerate_face_count += mesh.stats.degenerate_face_count;
+        total.sliver_face_count += mesh.stats.sliver_face_count;
+        total.surface_area += mesh.stats.surface_area;
+        total.volume += mesh.stats.volume;
+        total.topology.merge(&mesh.stats.topology);
+    }
 
-            triangle_area(v0, v1, v2)
-        })
-        .sum();
-
-    // Estimate volume using signed volumes of tetrahedra
-    let volume: f32 = indices
-        .par_chunks(3)
-        .map(|face| {
-            if face.len() < 3 {
-                return 0.0;
-            }
-            let i0 = face[0] as usize * 3;
-            let i1 = face[1] as usize * 3;
-            let i2 = face[2] as usize * 3;
+    Ok(FileStats {
+        path: path.to_string_lossy().to_string(),
+        format: format.name().to_string(),
+        meshes: stats,
+        total,
+    })
+}
 
-            if i0 + 2 >= vertices.len() || i1 + 2 >= vertices.len() || i2 + 2 >= vertices.len() {
-                return 0.0;
-            }
+/// Statistics for a single triangle mesh
+pub fn mesh_stats(vertices: &[f32], indices: &[u32]) -> MeshStats {
+    let vertex_count = vertices.len() / 3;
+    let face_count = indices.len() / 3;
 
-            let v0 = [vertices[i0], vertices[i0 + 1], vertices[i0 + 2]];
-            let v1 = [vertices[i1], vertices[i1 + 1], vertices[i1 + 2]];
-            let v2 = [vertices[i2], vertices[i2 + 1], vertices[i2 + 2]];
+    // Degenerate faces (repeated corners or zero area) and slivers
+    let (degenerate_face_count, sliver_face_count) =
+        degenerate::count(vertices, indices, &FaceThresholds::default());
+    let has_degenerate_faces = degenerate_face_count > 0 || !indices.len().is_multiple_of(3);
 
-            signed_tetrahedron_volume(v0, v1, v2)
-        })
-        .sum::<f32>()
-        .abs();
+    // Surface area and volume (signed tetrahedra against the origin)
+    let surface_area = simd::surface_area(vertices, indices);
+    let volume = simd::signed_volume(vertices, indices).abs();
+    let topology = topology::analyze(vertices, indices);
 
-    Ok(MeshStats {
+    MeshStats {
         vertex_count,
         face_count,
-        edge_count,
-        is_manifold: !has_degenerate_faces, // Simplified check
+        is_manifold: topology.is_manifold(),
         has_degenerate_faces,
+        degenerate_face_count,
+        sliver_face_count,
         surface_area,
         volume,
-    })
-}
-
-/// Calculate the area of a triangle
-fn triangle_area(v0: [f32; 3], v1: [f32; 3], v2: [f32; 3]) -> f32 {
-    let e1 = [v1[0] - v0[0], v1[1] - v0[1], v1[2] - v0[2]];
-    let e2 = [v2[0] - v0[0], v2[1] - v0[1], v2[2] - v0[2]];
-
-    // Cross product
-    let cross = [
-        e1[1] * e2[2] - e1[2] * e2[1],
-        e1[2] * e2[0] - e1[0] * e2[2],
-        e1[0] * e2[1] - e1[1] * e2[0],
-    ];
-
-    // Area = |cross| / 2
-    (cross[0] * cross[0] + cross[1] * cross[1] + cross[2] * cross[2]).sqrt() / 2.0
-}
-
-/// Calculate signed volume of tetrahedron formed by triangle and origin
-fn signed_tetrahedron_volume(v0: [f32; 3], v1: [f32; 3], v2: [f32; 3]) -> f32 {
-    // V = (v0 . (v1 x v2)) / 6
-    let cross = [
-        v1[1] * v2[2] - v1[2] * v2[1],
-        v1[2] * v2[0] - v1[0] * v2[2],
-        v1[0] * v2[1] - v1[1] * v2[0],
-    ];
-
-    (v0[0] * cross[0] + v0[1] * cross[1] + v0[2] * cross[2]) / 6.0
+        topology,
+    }
 }
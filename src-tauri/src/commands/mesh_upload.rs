@@ -0,0 +1,113 @@
+use crate::utils::mesh_store::{MeshAttribute, MeshStore};
+use crate::utils::polygons::FaceTopology;
+use serde::{Deserialize, Serialize};
+use tauri::ipc::{InvokeBody, Request};
+use tauri::{command, State};
+use tracing::instrument;
+
+/// Handle to a mesh held by the backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeshHandle {
+    pub handle: u64,
+    pub vertex_count: usize,
+    pub face_count: usize,
+    pub has_normals: bool,
+    pub has_uvs: bool,
+    pub has_lightmap_uvs: bool,
+    pub has_vertex_colors: bool,
+    pub has_skin: bool,
+    /// Whether the mesh was authored in quads, as imported
+    pub topology: FaceTopology,
+    /// Source polygons, or triangles when there is no polygon information
+    pub polygon_count: usize,
+}
+
+/// Start a chunked mesh upload
+///
+/// `expected_bytes` is optional; when given, the upload is rejected if the
+/// chunks add up to anything else.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn begin_mesh_upload(
+    store: State<'_, MeshStore>,
+    expected_bytes: Option<u64>,
+) -> Result<u64, String> {
+    Ok(store.begin_upload(expected_bytes))
+}
+
+/// Append a raw binary chunk to an upload
+///
+/// The body is little-endian `f32` (positions, normals, uvs, lightmap_uvs, colors, weights) or
+/// `u32` (indices, joints, polygons) data. The target upload and attribute are passed in the
+/// `x-upload-id` and `x-attribute` headers so no JSON is involved.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn append_mesh_chunk(
+    store: State<'_, MeshStore>,
+    request: Request<'_>,
+) -> Result<u64, String> {
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| format!("Missing {} header", name))
+    };
+
+    let upload_id = header("x-upload-id")?
+        .parse::<u64>()
+        .map_err(|e| format!("Invalid upload id: {}", e))?;
+    let attribute = MeshAttribute::parse(header("x-attribute")?)?;
+
+    match request.body() {
+        InvokeBody::Raw(bytes) => store.append_chunk(upload_id, attribute, bytes),
+        InvokeBody::Json(_) => Err("Mesh chunks must be sent as raw binary".to_string()),
+    }
+}
+
+/// Validate a finished upload and register it as a mesh handle
+#[command]
+#[instrument(skip_all, err)]
+pub async fn finish_mesh_upload(
+    store: State<'_, MeshStore>,
+    upload_id: u64,
+) -> Result<MeshHandle, String> {
+    let handle = store.finish_upload(upload_id)?;
+    describe_mesh(&store, handle)
+}
+
+/// Abandon an upload and free its buffers
+#[command]
+#[instrument(skip_all, err)]
+pub async fn cancel_mesh_upload(
+    store: State<'_, MeshStore>,
+    upload_id: u64,
+) -> Result<bool, String> {
+    Ok(store.cancel_upload(upload_id))
+}
+
+/// Release a mesh handle
+#[command]
+#[instrument(skip_all, err)]
+pub async fn release_mesh(store: State<'_, MeshStore>, mesh_handle: u64) -> Result<bool, String> {
+    Ok(store.remove(mesh_handle))
+}
+
+pub fn describe_mesh(store: &MeshStore, handle: u64) -> Result<MeshHandle, String> {
+    let mesh = store.get(handle)?;
+    Ok(MeshHandle {
+        handle,
+        vertex_count: mesh.vertex_count(),
+        face_count: mesh.face_count(),
+        has_normals: mesh.normals.is_some(),
+        has_uvs: mesh.uvs.is_some(),
+        has_lightmap_uvs: mesh.lightmap_uvs.is_some(),
+        has_vertex_colors: mesh.colors.is_some(),
+        has_skin: mesh.skin_joints.is_some(),
+        topology: mesh.topology(),
+        polygon_count: mesh
+            .polygons
+            .as_ref()
+            .map_or(mesh.face_count(), |polygons| polygons.len()),
+    })
+}
@@ -1,3 +1,28 @@
+pub mod animation;
+pub mod api;
+pub mod ar_preview;
+pub mod blender;
+pub mod bundle;
+pub mod cache;
+pub mod clipboard;
+pub mod desktop;
+pub mod diagnostics;
+pub mod external_tools;
 pub mod file_ops;
+pub mod jobs;
+pub mod library;
+pub mod maintenance;
 pub mod mesh_ops;
+pub mod mesh_upload;
+pub mod model_export;
+pub mod model_import;
 pub mod model_loader;
+pub mod operations;
+pub mod processing;
+pub mod quarantine;
+pub mod reports;
+pub mod scope;
+pub mod settings;
+pub mod streaming;
+pub mod transport;
+pub mod usage;
@@ -0,0 +1,965 @@
+use crate::commands::library;
+use crate::utils::analysis_cache::ModelSource;
+use crate::utils::asset_cache::{AssetCache, CacheKind};
+use crate::utils::axis_conversion::AxisConversion;
+use crate::utils::changes::{ChangeReport, ChangeSet};
+use crate::utils::engine_export::{self, Engine, UNREAL_MANIFEST};
+use crate::utils::flatten;
+use crate::utils::glb_writer::{container, write_glb_preserving, write_instanced_glb};
+use crate::utils::gltf_extensions::Passthrough;
+use crate::utils::gltf_geometry::{load_gltf, read_primitives};
+use crate::utils::gltf_metadata::{self, AssetMetadata, RightsEdit};
+use crate::utils::gltf_subset;
+use crate::utils::instancing;
+use crate::utils::mesh_array::{self, ArrayPattern};
+use crate::utils::mesh_files::{import_meshes, MeshFileFormat, NamedMesh};
+use crate::utils::mesh_store::MeshStore;
+use crate::utils::obj_writer::write_obj;
+use crate::utils::path_scope::PathScope;
+use crate::utils::plate::{self, PlateLayout, PlateModel};
+use crate::utils::print_writer::{write_3mf, write_stl, PrintObject};
+use crate::utils::provenance::{self, Provenance, ProvenanceCheck, ProvenanceStamp};
+use crate::utils::scene_graph;
+use crate::utils::section::{self, SectionPlane};
+use crate::utils::section_writer::{write_dxf, write_svg, MM_PER_UNIT};
+use crate::utils::settings::{AppSettings, SettingsStore};
+use crate::utils::web_viewer::{self, MODEL_FILE, PAGE_FILE, POSTER_FILE};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle, Manager, State};
+use tracing::{instrument, Span};
+
+/// Result of `export_obj`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedObj {
+    pub out_path: String,
+    pub mesh_count: usize,
+    /// Faces written: polygons where they were preserved, triangles otherwise
+    pub face_count: usize,
+    pub changes: ChangeReport,
+}
+
+/// Write meshes to an OBJ file, one object per handle
+///
+/// With `preserve_polygons` (the default), meshes imported with quads or
+/// n-gons are written back as those polygons instead of triangles.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_obj(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    store: State<'_, MeshStore>,
+    mesh_handles: Vec<u64>,
+    out_path: String,
+    preserve_polygons: Option<bool>,
+    dry_run: Option<bool>,
+) -> Result<ExportedObj, String> {
+    let out_path = scope.check(&out_path)?;
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+    let preserve_polygons = preserve_polygons.unwrap_or(true);
+
+    let meshes = mesh_handles
+        .iter()
+        .map(|&handle| {
+            store
+                .get(handle)
+                .map(|mesh| (format!("mesh_{}", handle), mesh))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    if meshes.is_empty() {
+        return Err("No meshes to export".to_string());
+    }
+    let objects: Vec<(&str, _)> = meshes
+        .iter()
+        .map(|(name, mesh)| (name.as_str(), mesh.as_ref()))
+        .collect();
+    changes.write(&out_path, write_obj(&objects, preserve_polygons).as_bytes())?;
+
+    let face_count = meshes
+        .iter()
+        .map(|(_, mesh)| match (&mesh.polygons, preserve_polygons) {
+            (Some(polygons), true) => polygons.len(),
+            _ => mesh.face_count(),
+        })
+        .sum();
+    Ok(ExportedObj {
+        out_path: out_path.to_string_lossy().to_string(),
+        mesh_count: meshes.len(),
+        face_count,
+        changes: changes.finish(),
+    })
+}
+
+/// Result of `export_section`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedSection {
+    pub out_path: String,
+    pub polyline_count: usize,
+    pub closed_count: usize,
+    /// Drawing size in millimeters
+    pub width_mm: f32,
+    pub height_mm: f32,
+    pub changes: ChangeReport,
+}
+
+/// Write a mesh's cross-section with a plane to an SVG or DXF file
+///
+/// The format follows the extension of `out_path`. Drawings are in
+/// millimeters, taking mesh units to be meters as imports are.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_section(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    plane: SectionPlane,
+    out_path: String,
+    dry_run: Option<bool>,
+) -> Result<ExportedSection, String> {
+    let out_path = scope.check(&out_path)?;
+    let extension = out_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    let write: fn(&section::Section, f32) -> String = match extension.as_deref() {
+        Some("svg") => write_svg,
+        Some("dxf") => write_dxf,
+        _ => {
+            return Err(format!(
+                "Sections export to .svg or .dxf, not {}",
+                out_path.display()
+            ))
+        }
+    };
+
+    let mesh = store.get(mesh_handle)?;
+    let section = section::slice(&mesh, &plane.normalized()?);
+    let (lo, hi) = section.bounds().ok_or("The plane doesn't cross the mesh")?;
+
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+    changes.write(&out_path, write(&section, MM_PER_UNIT).as_bytes())?;
+    Ok(ExportedSection {
+        out_path: out_path.to_string_lossy().to_string(),
+        polyline_count: section.polylines.len(),
+        closed_count: section.polylines.iter().filter(|p| p.closed).count(),
+        width_mm: (hi[0] - lo[0]) * MM_PER_UNIT,
+        height_mm: (hi[1] - lo[1]) * MM_PER_UNIT,
+        changes: changes.finish(),
+    })
+}
+
+/// Result of `export_build_plate`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedPlate {
+    pub out_path: String,
+    pub item_count: usize,
+    pub triangle_count: usize,
+    /// Items reaching past the edge of the plate, which were still written
+    pub off_plate: Vec<String>,
+    pub changes: ChangeReport,
+}
+
+/// Load a model file into print space, converted as an import would be
+fn load_plate_model(
+    scope: &PathScope,
+    settings: &AppSettings,
+    asset_id: &str,
+) -> Result<PlateModel, String> {
+    let path = scope.check(asset_id)?;
+    let imported = import_meshes(&path, &settings.import_axes, None, settings.triangulation)?;
+    PlateModel::from_meshes(&imported.meshes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Arrange model files on a build plate of `plate_dimensions` millimeters
+///
+/// Models are packed `spacing` mm apart (default 5) without overlapping.
+/// The layout can be edited freely before handing it to
+/// `export_build_plate`; assets that don't fit are listed in `unplaced`.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn pack_build_plate(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    asset_ids: Vec<String>,
+    plate_dimensions: [f32; 2],
+    spacing: Option<f32>,
+) -> Result<PlateLayout, String> {
+    if asset_ids.is_empty() {
+        return Err("No models to arrange".to_string());
+    }
+    let settings = settings.get();
+    let models = asset_ids
+        .iter()
+        .map(|id| load_plate_model(&scope, &settings, id).map(|model| (id.clone(), model)))
+        .collect::<Result<Vec<_>, String>>()?;
+    plate::pack(&models, plate_dimensions, spacing.unwrap_or(5.0))
+}
+
+/// Write a build plate layout to one STL or 3MF file
+///
+/// The format follows the extension of `out_path`. Each model is placed as
+/// the layout says, in Z-up millimeters; 3MF keeps them as separate
+/// objects, STL merges them.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_build_plate(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    layout: PlateLayout,
+    out_path: String,
+    dry_run: Option<bool>,
+) -> Result<ExportedPlate, String> {
+    let out_path = scope.check(&out_path)?;
+    let extension = out_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    if !matches!(extension.as_deref(), Some("stl" | "3mf")) {
+        return Err(format!(
+            "Build plates export to .stl or .3mf, not {}",
+            out_path.display()
+        ));
+    }
+    if layout.items.is_empty() {
+        return Err("The layout has no items".to_string());
+    }
+
+    let settings = settings.get();
+    let mut changes = ChangeSet::new(&settings, dry_run)?;
+
+    let mut off_plate = Vec::new();
+    let mut placed = Vec::with_capacity(layout.items.len());
+    for item in &layout.items {
+        let model = load_plate_model(&scope, &settings, &item.asset_id)?;
+        if plate::off_plate(&model, item, layout.plate_mm) {
+            off_plate.push(item.asset_id.clone());
+        }
+        let name = std::path::Path::new(&item.asset_id)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| item.asset_id.clone());
+        placed.push((name, model.placed(item), model.indices));
+    }
+    let objects: Vec<PrintObject> = placed
+        .iter()
+        .map(|(name, positions, indices)| (name.as_str(), positions.as_slice(), indices.as_slice()))
+        .collect();
+    let bytes = match extension.as_deref() {
+        Some("3mf") => write_3mf(&objects)?,
+        _ => write_stl(&objects),
+    };
+    changes.write(&out_path, &bytes)?;
+    Ok(ExportedPlate {
+        out_path: out_path.to_string_lossy().to_string(),
+        item_count: objects.len(),
+        triangle_count: objects
+            .iter()
+            .map(|(_, _, indices)| indices.len() / 3)
+            .sum(),
+        off_plate,
+        changes: changes.finish(),
+    })
+}
+
+/// One model laid out by `export_for_engine`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineAsset {
+    pub asset_id: String,
+    pub folder: String,
+    /// The model and its companion files, without `.meta` stubs
+    pub files: Vec<String>,
+}
+
+/// Result of `export_for_engine`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineExport {
+    pub engine: Engine,
+    pub assets: Vec<EngineAsset>,
+    /// The Unreal import manifest, if one was written
+    pub manifest_path: Option<String>,
+    pub changes: ChangeReport,
+}
+
+/// Copy models into `out_dir` laid out for a Unity or Unreal project
+///
+/// Each model goes into a folder named after it, together with the images
+/// beside it that start with its name and its `.mtl` or `.bin` file. For
+/// Unity, `out_dir` should be inside the project's `Assets` folder and every
+/// file and folder gets a `.meta` stub with a GUID that stays the same when
+/// exporting again. For Unreal, `import_manifest.json` lists the files for
+/// the `ImportAssets` commandlet under `/Game/<out_dir name>`.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_for_engine(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    asset_ids: Vec<String>,
+    engine: Engine,
+    out_dir: String,
+    dry_run: Option<bool>,
+) -> Result<EngineExport, String> {
+    if asset_ids.is_empty() {
+        return Err("No models to export".to_string());
+    }
+    let out_dir = scope.check(&out_dir)?;
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let models = asset_ids
+        .iter()
+        .map(|id| scope.check(id))
+        .collect::<Result<Vec<_>, String>>()?;
+    let stems: Vec<String> = models
+        .iter()
+        .map(|path| {
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    let folders = engine_export::folder_names(&stems);
+
+    let mut assets = Vec::with_capacity(models.len());
+    let mut laid_out = Vec::with_capacity(models.len());
+    for ((asset_id, model), folder) in asset_ids.iter().zip(&models).zip(folders) {
+        if !model.is_file() {
+            return Err(format!("Not a file: {}", model.display()));
+        }
+        let dir = out_dir.join(&folder);
+        if engine == Engine::Unity {
+            let meta = engine_export::unity_meta(&dir, true);
+            changes.write(&meta_path(&dir), meta.as_bytes())?;
+        }
+
+        let mut files = Vec::new();
+        for source in std::iter::once(model.clone()).chain(engine_export::companions(model)) {
+            let source = scope.check(&source)?;
+            let dest = dir.join(source.file_name().unwrap_or_default());
+            let bytes = fs::read(&source)
+                .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+            changes.write(&dest, &bytes)?;
+            if engine == Engine::Unity {
+                let meta = engine_export::unity_meta(&dest, false);
+                changes.write(&meta_path(&dest), meta.as_bytes())?;
+            }
+            files.push(dest);
+        }
+        assets.push(EngineAsset {
+            asset_id: asset_id.clone(),
+            folder: dir.to_string_lossy().to_string(),
+            files: files
+                .iter()
+                .map(|file| file.to_string_lossy().to_string())
+                .collect(),
+        });
+        laid_out.push((folder, files));
+    }
+
+    let manifest_path = match engine {
+        Engine::Unity => None,
+        Engine::Unreal => {
+            let path = out_dir.join(UNREAL_MANIFEST);
+            let root = engine_export::unreal_content_root(&out_dir);
+            let manifest = engine_export::unreal_manifest(&root, &laid_out);
+            changes.write(&path, manifest.as_bytes())?;
+            Some(path.to_string_lossy().to_string())
+        }
+    };
+    if !changes.is_dry_run() {
+        library::record_exports(&app, &asset_ids);
+    }
+    Ok(EngineExport {
+        engine,
+        assets,
+        manifest_path,
+        changes: changes.finish(),
+    })
+}
+
+/// `<path>.meta`, beside the file or folder it describes
+fn meta_path(path: &Path) -> PathBuf {
+    let mut meta = path.as_os_str().to_owned();
+    meta.push(".meta");
+    meta.into()
+}
+
+/// Result of `export_instanced_glb`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstancedGlb {
+    pub out_path: String,
+    /// Meshes written once for several placements
+    pub instanced_mesh_count: usize,
+    pub instance_count: usize,
+    /// Meshes written in world space, once per placement
+    pub baked_mesh_count: usize,
+    pub vertex_count: usize,
+    /// Vertices a flattened export would have written
+    pub flattened_vertex_count: usize,
+    pub extensions: Vec<String>,
+    pub changes: ChangeReport,
+}
+
+/// Write a glTF scene to GLB with repeated meshes as GPU instances
+///
+/// Meshes with identical geometry placed at least `min_instances` times
+/// (default 2) are written once with `EXT_mesh_gpu_instancing`; the rest are
+/// baked into world space as `convert_to_glb` does. Extension data, extras,
+/// the copyright, lights and cameras are carried over the same way, and
+/// `rights` changes the copyright and license written.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_instanced_glb(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    out_path: String,
+    min_instances: Option<usize>,
+    rights: Option<RightsEdit>,
+    dry_run: Option<bool>,
+) -> Result<InstancedGlb, String> {
+    let path = scope.check(&path)?;
+    let out_path = scope.check(&out_path)?;
+    if MeshFileFormat::from_path(&path)? != MeshFileFormat::Gltf {
+        return Err(format!(
+            "Instancing needs a glTF scene, not {}",
+            path.display()
+        ));
+    }
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let loaded = load_gltf(&path)?;
+    let mesh_name = |index: usize| {
+        loaded
+            .document
+            .meshes()
+            .nth(index)
+            .and_then(|m| m.name().map(str::to_string))
+            .unwrap_or_else(|| format!("mesh {}", index))
+    };
+    let found = instancing::find_instances(
+        &read_primitives(&loaded),
+        mesh_name,
+        min_instances.unwrap_or(2),
+    );
+    if found.instanced.is_empty() && found.baked.is_empty() {
+        return Err(format!("No triangle meshes found in {}", path.display()));
+    }
+
+    let source = ModelSource::open(&path)?;
+    let mut passthrough = Passthrough::read(source.json())?;
+    let graph = scene_graph::parse(source.json())?;
+    passthrough.place_lights_and_cameras(&graph, &AxisConversion::default());
+    passthrough.set_rights(&rights.unwrap_or_default())?;
+    let (bytes, extensions) = write_instanced_glb(&found, &passthrough)?;
+    changes.write(&out_path, &bytes)?;
+
+    let vertices = |mesh: &NamedMesh| mesh.positions.len() / 3;
+    let baked_vertices: usize = found.baked.iter().map(vertices).sum();
+    Ok(InstancedGlb {
+        out_path: out_path.to_string_lossy().to_string(),
+        instanced_mesh_count: found.instanced.len(),
+        instance_count: found.instanced.iter().map(|m| m.instances.len()).sum(),
+        baked_mesh_count: found.baked.len(),
+        vertex_count: baked_vertices
+            + found
+                .instanced
+                .iter()
+                .map(|m| vertices(&m.mesh))
+                .sum::<usize>(),
+        flattened_vertex_count: baked_vertices
+            + found
+                .instanced
+                .iter()
+                .map(|m| vertices(&m.mesh) * m.instances.len())
+                .sum::<usize>(),
+        extensions,
+        changes: changes.finish(),
+    })
+}
+
+/// Write an array of a mesh to GLB as GPU instances
+///
+/// The mesh is written once with a placement per copy of `pattern` in
+/// `EXT_mesh_gpu_instancing`, positions and faces only, as
+/// `export_instanced_glb` writes them. Merge the array into one mesh with
+/// `array_mesh` instead for targets without the extension.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_array_glb(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    pattern: ArrayPattern,
+    out_path: String,
+    dry_run: Option<bool>,
+) -> Result<InstancedGlb, String> {
+    let out_path = scope.check(&out_path)?;
+    let mesh = store.get(mesh_handle)?;
+    if mesh.indices.is_empty() {
+        return Err("The mesh has no faces to array".to_string());
+    }
+    let placements = pattern.placements()?;
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let name = format!("mesh_{}", mesh_handle);
+    let found = mesh_array::instance(&mesh, &name, &placements);
+    let (bytes, extensions) = write_instanced_glb(&found, &Passthrough::default())?;
+    changes.write(&out_path, &bytes)?;
+    Ok(InstancedGlb {
+        out_path: out_path.to_string_lossy().to_string(),
+        instanced_mesh_count: 1,
+        instance_count: placements.len(),
+        baked_mesh_count: 0,
+        vertex_count: mesh.vertex_count(),
+        flattened_vertex_count: mesh.vertex_count() * placements.len(),
+        extensions,
+        changes: changes.finish(),
+    })
+}
+
+/// Result of `export_flattened_glb`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlattenedGlb {
+    pub out_path: String,
+    /// Meshes written, each under its own top-level node
+    pub mesh_count: usize,
+    pub vertex_count: usize,
+    pub face_count: usize,
+    pub extensions: Vec<String>,
+    pub changes: ChangeReport,
+}
+
+/// Write a model to GLB with no hierarchy: every transform baked into the
+/// vertices and all geometry merged into as few meshes as possible
+///
+/// glTF scenes get one mesh per material, or a single mesh with a
+/// primitive per material with `merge_by_material` false; materials,
+/// textures, normals, UVs and vertex colors are kept. Other formats don't
+/// keep materials and are always merged into one mesh of positions,
+/// converted as `import_model` would. `rights` sets the copyright and
+/// license written.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_flattened_glb(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    out_path: String,
+    merge_by_material: Option<bool>,
+    rights: Option<RightsEdit>,
+    dry_run: Option<bool>,
+) -> Result<FlattenedGlb, String> {
+    let path = scope.check(&path)?;
+    let out_path = scope.check(&out_path)?;
+    let settings = settings.get();
+    let mut changes = ChangeSet::new(&settings, dry_run)?;
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "scene".to_string());
+    let rights = rights.unwrap_or_default();
+
+    let (bytes, extensions, mesh_count, vertex_count, face_count) =
+        if MeshFileFormat::from_path(&path)? == MeshFileFormat::Gltf {
+            let mut flat = flatten::flatten(&path, merge_by_material.unwrap_or(true), &stem)?;
+            let source = ModelSource::open(&path)?;
+            let mut passthrough = Passthrough::read(source.json())?;
+            let graph = scene_graph::parse(source.json())?;
+            passthrough.place_lights_and_cameras(&graph, &AxisConversion::default());
+            passthrough.set_rights(&rights)?;
+            let extensions = passthrough.apply(&mut flat.document.json);
+            let bytes = flat.document.to_glb()?;
+            (
+                bytes,
+                extensions,
+                flat.mesh_count,
+                flat.vertex_count,
+                flat.face_count,
+            )
+        } else {
+            let imported =
+                import_meshes(&path, &settings.import_axes, None, settings.triangulation)?;
+            let mut merged = NamedMesh {
+                name: stem,
+                ..Default::default()
+            };
+            for mesh in &imported.meshes {
+                merged.append(&mesh.positions, &mesh.indices);
+            }
+            if merged.indices.is_empty() {
+                return Err(format!("No triangle meshes found in {}", path.display()));
+            }
+            let mut passthrough = Passthrough::default();
+            passthrough.set_rights(&rights)?;
+            let (bytes, extensions) =
+                write_glb_preserving(std::slice::from_ref(&merged), &passthrough)?;
+            let counts = (merged.positions.len() / 3, merged.indices.len() / 3);
+            (bytes, extensions, 1, counts.0, counts.1)
+        };
+    changes.write(&out_path, &bytes)?;
+
+    Ok(FlattenedGlb {
+        out_path: out_path.to_string_lossy().to_string(),
+        mesh_count,
+        vertex_count,
+        face_count,
+        extensions,
+        changes: changes.finish(),
+    })
+}
+
+/// Result of `extract_node`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractedNode {
+    pub out_path: String,
+    /// The extracted node and its descendants
+    pub node_count: usize,
+    pub mesh_count: usize,
+    pub material_count: usize,
+    pub texture_count: usize,
+    /// Parts of the source that were left out, such as skins and animations
+    pub dropped: Vec<String>,
+    pub changes: ChangeReport,
+}
+
+/// Write one node of a glTF scene and everything below it to a new GLB
+///
+/// `node` is a `/`-separated path of names from a root (`Level/Props/Crate`)
+/// or the name of a node that occurs once. Meshes, materials, textures,
+/// cameras and lights under the node come along; the node keeps its world
+/// transform. `rights` changes the copyright and license written.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn extract_node(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    node: String,
+    out_path: String,
+    rights: Option<RightsEdit>,
+    dry_run: Option<bool>,
+) -> Result<ExtractedNode, String> {
+    let path = scope.check(&path)?;
+    let out_path = scope.check(&out_path)?;
+    if MeshFileFormat::from_path(&path)? != MeshFileFormat::Gltf {
+        return Err(format!(
+            "Nodes can only be extracted from glTF, not {}",
+            path.display()
+        ));
+    }
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let source = ModelSource::open(&path)?;
+    let raw: serde_json::Value = serde_json::from_slice(source.json())
+        .map_err(|e| format!("Failed to parse GLTF: {}", e))?;
+    let graph = scene_graph::parse(source.json())?;
+    let root = gltf_subset::find_node(&graph, &node)?;
+    let loaded = load_gltf(&path)?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    let mut subset = gltf_subset::extract(&raw, &loaded, &graph, root, base)?;
+    if let Some(asset) = subset
+        .document
+        .get_mut("asset")
+        .and_then(Value::as_object_mut)
+    {
+        rights.unwrap_or_default().apply(asset)?;
+    }
+    changes.write(&out_path, &container(&subset.document, &subset.bin)?)?;
+
+    Ok(ExtractedNode {
+        out_path: out_path.to_string_lossy().to_string(),
+        node_count: subset.node_count,
+        mesh_count: subset.mesh_count,
+        material_count: subset.material_count,
+        texture_count: subset.texture_count,
+        dropped: subset.dropped,
+        changes: changes.finish(),
+    })
+}
+
+/// Result of `set_asset_rights`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditedRights {
+    pub path: String,
+    /// The file's metadata after the edit
+    pub metadata: AssetMetadata,
+    pub changes: ChangeReport,
+}
+
+/// Change the copyright and license recorded in a glTF or GLB file
+///
+/// Only the JSON is rewritten; buffers and everything else are kept.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn set_asset_rights(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    rights: RightsEdit,
+    dry_run: Option<bool>,
+) -> Result<EditedRights, String> {
+    let path = scope.check(&path)?;
+    if MeshFileFormat::from_path(&path)? != MeshFileFormat::Gltf {
+        return Err(format!(
+            "Only glTF files record a copyright, not {}",
+            path.display()
+        ));
+    }
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let metadata = rewrite_json(&path, &mut changes, |document, _| {
+        let asset = document
+            .get_mut("asset")
+            .and_then(Value::as_object_mut)
+            .ok_or_else(|| format!("{} has no asset object", path.display()))?;
+        rights.apply(asset)?;
+        Ok(gltf_metadata::of(document))
+    })?;
+
+    Ok(EditedRights {
+        path: path.to_string_lossy().to_string(),
+        metadata,
+        changes: changes.finish(),
+    })
+}
+
+/// Result of `stamp_provenance`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StampedProvenance {
+    pub path: String,
+    /// The record as embedded
+    pub provenance: Provenance,
+    pub changes: ChangeReport,
+}
+
+/// Embed a provenance record in an exported glTF or GLB file, or add to
+/// the one it has
+///
+/// `import_path` is the file the asset was imported from; its hash is
+/// recorded unless the stamp gives one. Run this last: any later change to
+/// the file shows up in `verify_provenance`.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn stamp_provenance(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    mut stamp: ProvenanceStamp,
+    import_path: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<StampedProvenance, String> {
+    let path = scope.check(&path)?;
+    if MeshFileFormat::from_path(&path)? != MeshFileFormat::Gltf {
+        return Err(format!(
+            "Provenance can only be embedded in glTF, not {}",
+            path.display()
+        ));
+    }
+    if let (None, Some(import_path)) = (&stamp.import_hash, import_path) {
+        stamp.import_hash = Some(provenance::file_hash(&scope.check(&import_path)?)?);
+    }
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let provenance = rewrite_json(&path, &mut changes, |document, bin| {
+        provenance::embed(document, bin, stamp)
+    })?;
+
+    Ok(StampedProvenance {
+        path: path.to_string_lossy().to_string(),
+        provenance,
+        changes: changes.finish(),
+    })
+}
+
+/// The provenance record of a glTF or GLB file and whether the file has
+/// changed since it was embedded
+#[command]
+#[instrument(skip_all, err)]
+pub async fn verify_provenance(
+    scope: State<'_, PathScope>,
+    path: String,
+) -> Result<ProvenanceCheck, String> {
+    let path = scope.check(&path)?;
+    if MeshFileFormat::from_path(&path)? != MeshFileFormat::Gltf {
+        return Err(format!(
+            "Only glTF files carry provenance, not {}",
+            path.display()
+        ));
+    }
+    let source = ModelSource::open(&path)?;
+    let document: Value = serde_json::from_slice(source.json())
+        .map_err(|e| format!("Failed to parse GLTF: {}", e))?;
+    Ok(provenance::verify(
+        &document,
+        source.bin().unwrap_or_default(),
+    ))
+}
+
+/// Result of `export_web_viewer`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebViewerExport {
+    pub out_dir: String,
+    pub page_path: String,
+    pub model_path: String,
+    pub poster_path: String,
+    pub source_bytes: u64,
+    /// Size of the compressed GLB
+    pub model_bytes: u64,
+    pub changes: ChangeReport,
+}
+
+/// Write a folder that shows a model on the web: a Draco and KTX2
+/// compressed GLB, a poster image and an `index.html` with a viewer
+///
+/// The poster is `poster_png` if given, otherwise the thumbnail stored for
+/// the model. glTF models keep their materials and textures; OBJ and FBX
+/// models are converted as `convert_to_glb` does first. Compression runs the
+/// `gltf-transform` tool from the settings.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_web_viewer(
+    app: AppHandle,
+    asset_id: String,
+    out_dir: String,
+    poster_png: Option<Vec<u8>>,
+    dry_run: Option<bool>,
+) -> Result<WebViewerExport, String> {
+    let scope = app.state::<PathScope>();
+    let path = scope.check(&asset_id)?;
+    if !path.is_file() {
+        return Err(format!("File not found: {}", path.display()));
+    }
+    let format = MeshFileFormat::from_path(&path)?;
+    let out_dir = scope.check(&out_dir)?;
+    let settings = app.state::<SettingsStore>().get();
+    let mut changes = ChangeSet::new(&settings, dry_run)?;
+
+    let poster = match poster_png {
+        Some(png) => {
+            if image::guess_format(&png).ok() != Some(image::ImageFormat::Png) {
+                return Err("Posters must be PNG images".to_string());
+            }
+            png
+        }
+        None => AssetCache::source_key(&path, "")
+            .and_then(|key| {
+                app.state::<AssetCache>()
+                    .get(CacheKind::Thumbnail, &format!("{}.png", key))
+            })
+            .ok_or_else(|| {
+                format!(
+                    "No thumbnail stored for {}; render one or pass a poster",
+                    path.display()
+                )
+            })?,
+    };
+
+    let span = Span::current();
+    let source = path.clone();
+    let model = tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let work_dir = std::env::temp_dir().join(format!(
+                "sweedle-web-{}-{}",
+                std::process::id(),
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos())
+                    .unwrap_or(0)
+            ));
+            fs::create_dir_all(&work_dir)
+                .map_err(|e| format!("Failed to create {}: {}", work_dir.display(), e))?;
+            let optimized = compress_for_web(&settings, &source, format, &work_dir);
+            if let Err(e) = fs::remove_dir_all(&work_dir) {
+                log::warn!("Failed to remove {}: {}", work_dir.display(), e);
+            }
+            optimized
+        })
+    })
+    .await
+    .map_err(|e| format!("Compression failed: {}", e))??;
+
+    let (page_path, model_path, poster_path) = (
+        out_dir.join(PAGE_FILE),
+        out_dir.join(MODEL_FILE),
+        out_dir.join(POSTER_FILE),
+    );
+    let title = path.file_stem().unwrap_or_default().to_string_lossy();
+    changes.write(&model_path, &model)?;
+    changes.write(&poster_path, &poster)?;
+    changes.write(&page_path, web_viewer::page(&title).as_bytes())?;
+    if !changes.is_dry_run() {
+        library::record_exports(&app, &[asset_id]);
+    }
+
+    Ok(WebViewerExport {
+        out_dir: out_dir.to_string_lossy().to_string(),
+        page_path: page_path.to_string_lossy().to_string(),
+        model_path: model_path.to_string_lossy().to_string(),
+        poster_path: poster_path.to_string_lossy().to_string(),
+        source_bytes: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+        model_bytes: model.len() as u64,
+        changes: changes.finish(),
+    })
+}
+
+/// The model at `path` as a compressed GLB, with intermediate files in
+/// `work_dir`
+fn compress_for_web(
+    settings: &AppSettings,
+    path: &Path,
+    format: MeshFileFormat,
+    work_dir: &Path,
+) -> Result<Vec<u8>, String> {
+    let input = if format == MeshFileFormat::Gltf {
+        path.to_path_buf()
+    } else {
+        let mut imported =
+            import_meshes(path, &settings.import_axes, None, settings.triangulation)?;
+        imported.meshes.retain(|mesh| !mesh.indices.is_empty());
+        if imported.meshes.is_empty() {
+            return Err(format!("No triangle meshes found in {}", path.display()));
+        }
+        let (bytes, _) = write_glb_preserving(&imported.meshes, &Passthrough::default())?;
+        let converted = work_dir.join("source.glb");
+        fs::write(&converted, bytes)
+            .map_err(|e| format!("Failed to write {}: {}", converted.display(), e))?;
+        converted
+    };
+    let output = work_dir.join(MODEL_FILE);
+    web_viewer::optimize(&settings.gltf_transform_executable, &input, &output)?;
+    fs::read(&output).map_err(|e| format!("Failed to read {}: {}", output.display(), e))
+}
+
+/// Edit the JSON of a glTF or GLB file in place, keeping its binary chunk
+///
+/// `edit` gets the parsed document and the binary chunk.
+fn rewrite_json<T>(
+    path: &Path,
+    changes: &mut ChangeSet,
+    edit: impl FnOnce(&mut Value, &[u8]) -> Result<T, String>,
+) -> Result<T, String> {
+    let source = ModelSource::open(path)?;
+    let mut document: Value = serde_json::from_slice(source.json())
+        .map_err(|e| format!("Failed to parse GLTF: {}", e))?;
+    let bin = source.bin().unwrap_or_default();
+    let edited = edit(&mut document, bin)?;
+    let is_glb = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("glb"));
+    let bytes = if is_glb {
+        container(&document, bin)?
+    } else {
+        serde_json::to_vec_pretty(&document)
+            .map_err(|e| format!("Failed to serialize glTF: {}", e))?
+    };
+    // The source maps the file, which has to be released before replacing it
+    drop(source);
+    changes.write(path, &bytes)?;
+    Ok(edited)
+}
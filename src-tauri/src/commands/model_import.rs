@@ -0,0 +1,399 @@
+use crate::commands::mesh_upload::{describe_mesh, MeshHandle};
+use crate::utils::analysis_cache::ModelSource;
+use crate::utils::asset_history::{AssetHistory, HistoryEntry, HistoryKind};
+use crate::utils::axis_conversion::AxisConversion;
+use crate::utils::changes::{ChangeReport, ChangeSet};
+use crate::utils::dependencies::{self, Dependency};
+use crate::utils::engine_export::folder_names;
+use crate::utils::glb_writer::write_glb_preserving;
+use crate::utils::gltf_extensions::Passthrough;
+use crate::utils::mesh_files::{import_meshes, MeshFileFormat};
+use crate::utils::mesh_store::{MeshData, MeshStore};
+use crate::utils::path_scope::PathScope;
+use crate::utils::provenance;
+use crate::utils::relink::{self, Fix, Relink, RelinkStatus};
+use crate::utils::scene_graph;
+use crate::utils::settings::SettingsStore;
+use crate::utils::storage_layout::{AssetLocation, Date};
+use crate::utils::triangulate::{TriangulationOptions, TriangulationReport};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use tauri::{command, AppHandle, Manager, State};
+use tracing::instrument;
+
+/// One mesh of an imported model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedMesh {
+    pub name: String,
+    pub mesh: MeshHandle,
+}
+
+/// Result of `import_model`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedModel {
+    pub format: String,
+    /// Conversion applied to reach Y-up meters
+    pub conversion: AxisConversion,
+    pub meshes: Vec<ImportedMesh>,
+    /// Non-planar and badly split polygons
+    pub triangulation: TriangulationReport,
+}
+
+/// Result of `convert_to_glb`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConvertedModel {
+    pub out_path: String,
+    pub format: String,
+    pub conversion: AxisConversion,
+    pub mesh_count: usize,
+    pub vertex_count: usize,
+    pub face_count: usize,
+    pub triangulation: TriangulationReport,
+    /// Extensions carried over from a glTF source
+    pub extensions: Vec<String>,
+    pub changes: ChangeReport,
+}
+
+/// Result of `resolve_dependencies`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedDependencies {
+    pub path: String,
+    pub dependencies: Vec<Dependency>,
+    pub missing_count: usize,
+    /// Where the model was copied, when a storage folder was given
+    pub stored: Option<StoredAsset>,
+}
+
+/// A model copied into the storage layout with its dependencies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredAsset {
+    pub id: String,
+    /// Where the model was copied
+    pub asset_path: String,
+    pub changes: ChangeReport,
+}
+
+/// Result of `relink_dependencies`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelinkReport {
+    pub path: String,
+    /// One entry per dependency that was missing
+    pub relinks: Vec<Relink>,
+    /// Dependencies still missing afterwards
+    pub missing_count: usize,
+    pub changes: ChangeReport,
+}
+
+/// Import every mesh of a GLB/GLTF, OBJ, STL or binary FBX file
+///
+/// Node transforms are baked in and the geometry is converted to Y-up
+/// meters; `conversion` overrides the file's declared axes and the
+/// per-format default from settings. `triangulation` likewise overrides how
+/// polygons are split.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn import_model(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    store: State<'_, MeshStore>,
+    path: String,
+    conversion: Option<AxisConversion>,
+    triangulation: Option<TriangulationOptions>,
+) -> Result<ImportedModel, String> {
+    let path = scope.check(&path)?;
+    let settings = settings.get();
+    let imported = import_meshes(
+        &path,
+        &settings.import_axes,
+        conversion,
+        triangulation.unwrap_or(settings.triangulation),
+    )?;
+
+    let meshes = imported
+        .meshes
+        .into_iter()
+        .filter(|mesh| !mesh.indices.is_empty())
+        .map(|mesh| {
+            let data = MeshData {
+                positions: mesh.positions,
+                indices: mesh.indices,
+                polygons: mesh.polygons,
+                ..Default::default()
+            };
+            data.validate()?;
+            let handle = store.insert(data);
+            Ok(ImportedMesh {
+                name: mesh.name,
+                mesh: describe_mesh(&store, handle)?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(ImportedModel {
+        format: imported.format.name().to_string(),
+        conversion: imported.conversion,
+        meshes,
+        triangulation: imported.triangulation,
+    })
+}
+
+/// Convert a model file to GLB with the same conversion `import_model` applies
+///
+/// Extension data and extras of a glTF source are kept where they don't
+/// depend on the geometry: on the root, the asset and meshes by name.
+/// Its lights and cameras are kept at their world positions unless
+/// `keep_lights_and_cameras` is false.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn convert_to_glb(
+    app: AppHandle,
+    path: String,
+    out_path: String,
+    conversion: Option<AxisConversion>,
+    triangulation: Option<TriangulationOptions>,
+    keep_lights_and_cameras: Option<bool>,
+    dry_run: Option<bool>,
+) -> Result<ConvertedModel, String> {
+    let scope = app.state::<PathScope>();
+    let path = scope.check(&path)?;
+    let out_path = scope.check(&out_path)?;
+    let settings = app.state::<SettingsStore>().get();
+    let mut changes = ChangeSet::new(&settings, dry_run)?;
+
+    let mut imported = import_meshes(
+        &path,
+        &settings.import_axes,
+        conversion,
+        triangulation.unwrap_or(settings.triangulation),
+    )?;
+    imported.meshes.retain(|mesh| !mesh.indices.is_empty());
+    if imported.meshes.is_empty() {
+        return Err(format!("No triangle meshes found in {}", path.display()));
+    }
+    let passthrough = match imported.format {
+        MeshFileFormat::Gltf => {
+            let source = ModelSource::open(&path)?;
+            let mut passthrough = Passthrough::read(source.json())?;
+            if keep_lights_and_cameras.unwrap_or(true) {
+                let graph = scene_graph::parse(source.json())?;
+                passthrough.place_lights_and_cameras(&graph, &imported.conversion);
+            } else {
+                passthrough.strip_lights_and_cameras();
+            }
+            passthrough
+        }
+        _ => Passthrough::default(),
+    };
+    let (bytes, extensions) = write_glb_preserving(&imported.meshes, &passthrough)?;
+    changes.write(&out_path, &bytes)?;
+
+    Ok(ConvertedModel {
+        out_path: out_path.to_string_lossy().to_string(),
+        format: imported.format.name().to_string(),
+        conversion: imported.conversion,
+        mesh_count: imported.meshes.len(),
+        vertex_count: imported.meshes.iter().map(|m| m.positions.len() / 3).sum(),
+        face_count: imported.meshes.iter().map(|m| m.indices.len() / 3).sum(),
+        triangulation: imported.triangulation,
+        extensions,
+        changes: changes.finish(),
+    })
+}
+
+/// List every file a GLTF, OBJ or FBX model refers to, flagging missing ones
+///
+/// With `storage_path` the model and its dependencies are also copied into
+/// the storage folder: the model where the storage layout puts it, and
+/// dependencies at their paths relative to the model. `asset_id` defaults
+/// to the model's file name. Missing dependencies and ones outside the
+/// model's folder aren't copied. The copy is recorded in the asset's
+/// history with the model's hash.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn resolve_dependencies(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    history: State<'_, AssetHistory>,
+    path: String,
+    storage_path: Option<String>,
+    asset_id: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<ResolvedDependencies, String> {
+    let path = scope.check(&path)?;
+    if !path.is_file() {
+        return Err(format!("Not a file: {}", path.display()));
+    }
+    let found = dependencies::resolve(&path)?;
+
+    let stored = match storage_path {
+        Some(storage_path) => {
+            let storage = scope.check(&storage_path)?;
+            let id = match asset_id {
+                Some(id) => id,
+                None => {
+                    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                    folder_names(&[stem]).remove(0)
+                }
+            };
+            if id.is_empty() || id.contains(['/', '\\']) || id == ".." {
+                return Err(format!("Invalid asset id: {}", id));
+            }
+            let settings = settings.get();
+            let mut changes = ChangeSet::new(&settings, dry_run)?;
+            let dest = storage.join(
+                settings.storage_layout.parse()?.fill(&AssetLocation {
+                    id: id.clone(),
+                    ext: path
+                        .extension()
+                        .map(|e| e.to_string_lossy().to_lowercase())
+                        .unwrap_or_default(),
+                    category: None,
+                    date: Some(Date::from_system_time(SystemTime::now())),
+                }),
+            );
+            for (source, dest) in dependencies::copied_files(&path, &found, &dest) {
+                let source = scope.check(&source)?;
+                let bytes = fs::read(&source)
+                    .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+                changes.write(&dest, &bytes)?;
+            }
+            if !changes.is_dry_run() {
+                history.record(
+                    &id,
+                    HistoryEntry {
+                        source: Some(path.to_string_lossy().to_string()),
+                        path: Some(dest.to_string_lossy().to_string()),
+                        hash: Some(provenance::file_hash(&path)?),
+                        ..HistoryEntry::new(HistoryKind::Imported)
+                    },
+                )?;
+            }
+            Some(StoredAsset {
+                asset_path: dest.to_string_lossy().to_string(),
+                id,
+                changes: changes.finish(),
+            })
+        }
+        None => None,
+    };
+
+    Ok(ResolvedDependencies {
+        path: path.to_string_lossy().to_string(),
+        missing_count: found.iter().filter(|d| !d.exists).count(),
+        dependencies: found,
+        stored,
+    })
+}
+
+/// Look for a model's missing dependencies under `search_dirs` and point the
+/// model at what's found
+///
+/// Files are matched by name, and copies with the same contents count as one;
+/// names matching files that differ are reported as ambiguous and left alone.
+/// glTF, OBJ and MTL references are rewritten, while FBX textures and glTF
+/// buffers from outside the model's folder are copied to where they're
+/// expected. Textures of a relinked material library are checked on the next
+/// run.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn relink_dependencies(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    search_dirs: Vec<String>,
+    dry_run: Option<bool>,
+) -> Result<RelinkReport, String> {
+    let path = scope.check(&path)?;
+    if !path.is_file() {
+        return Err(format!("Not a file: {}", path.display()));
+    }
+    let search_dirs = search_dirs
+        .iter()
+        .map(|dir| scope.check(dir))
+        .collect::<Result<Vec<PathBuf>, String>>()?;
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let missing: Vec<Dependency> = dependencies::resolve(&path)?
+        .into_iter()
+        .filter(|dependency| !dependency.exists)
+        .collect();
+    let names: HashSet<String> = missing
+        .iter()
+        .filter_map(|dependency| relink::wanted_name(&dependency.reference))
+        .collect();
+    let candidates = relink::find_candidates(&search_dirs, &names);
+
+    let mut relinks = Vec::with_capacity(missing.len());
+    let mut rewrites: BTreeMap<String, HashMap<String, String>> = BTreeMap::new();
+    for dependency in missing {
+        let mut relink = Relink {
+            kind: dependency.kind,
+            reference: dependency.reference.clone(),
+            referenced_by: dependency.referenced_by.clone(),
+            status: RelinkStatus::NotFound,
+            found: None,
+            new_reference: None,
+            candidates: Vec::new(),
+        };
+        let matches = relink::wanted_name(&dependency.reference)
+            .and_then(|name| candidates.get(&name))
+            .map(|paths| relink::choose(paths));
+        match matches {
+            None => {}
+            Some(Err(distinct)) => {
+                if !distinct.is_empty() {
+                    relink.status = RelinkStatus::Ambiguous;
+                }
+                relink.candidates = distinct
+                    .iter()
+                    .map(|path| path.to_string_lossy().to_string())
+                    .collect();
+            }
+            Some(Ok(found)) => {
+                let new_reference = match relink::fix(&dependency, &found) {
+                    Fix::Rewrite { new_reference } => {
+                        relink.status = RelinkStatus::Relinked;
+                        Some(new_reference)
+                    }
+                    Fix::Copy { to, new_reference } => {
+                        let bytes = fs::read(&found)
+                            .map_err(|e| format!("Failed to read {}: {}", found.display(), e))?;
+                        changes.write(&scope.check(&to)?, &bytes)?;
+                        relink.status = RelinkStatus::Copied;
+                        new_reference
+                    }
+                };
+                if let Some(new_reference) = &new_reference {
+                    rewrites
+                        .entry(dependency.referenced_by.clone())
+                        .or_default()
+                        .insert(dependency.reference.clone(), new_reference.clone());
+                }
+                relink.found = Some(found.to_string_lossy().to_string());
+                relink.new_reference = new_reference;
+            }
+        }
+        relinks.push(relink);
+    }
+
+    for (referrer, replacements) in &rewrites {
+        let referrer = scope.check(referrer)?;
+        let bytes = relink::rewritten(&referrer, replacements)?;
+        changes.write(&referrer, &bytes)?;
+    }
+
+    Ok(RelinkReport {
+        path: path.to_string_lossy().to_string(),
+        missing_count: relinks
+            .iter()
+            .filter(|relink| {
+                !matches!(relink.status, RelinkStatus::Relinked | RelinkStatus::Copied)
+            })
+            .count(),
+        relinks,
+        changes: changes.finish(),
+    })
+}
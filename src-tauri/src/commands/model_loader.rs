@@ -1,10 +1,16 @@
-use gltf::Gltf;
-use memmap2::Mmap;
+use crate::utils::analysis_cache::{AnalysisCache, ModelSource};
+use crate::utils::gltf_extensions::{self, GltfExtension};
+use crate::utils::gltf_metadata::AssetMetadata;
+use crate::utils::path_scope::PathScope;
+use crate::utils::platform::FileBytes;
+use crate::utils::sanitize::DataIssues;
+use crate::utils::scene_graph::{self, SceneGraph};
+use crate::utils::usage_stats::UsageStats;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::path::Path;
-use tauri::command;
+use std::collections::HashMap;
+use tauri::{command, State};
+use tracing::instrument;
 
 /// Result of analyzing a 3D model
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,9 +22,21 @@ pub struct ModelAnalysis {
     pub has_textures: bool,
     pub has_normals: bool,
     pub has_uvs: bool,
+    /// A second UV set (`TEXCOORD_1`), usually for lightmaps
+    pub has_lightmap_uvs: bool,
+    pub has_vertex_colors: bool,
     pub file_size_bytes: u64,
     pub bounding_box: BoundingBox,
     pub center: [f32; 3],
+    /// NaN, infinite or out-of-range positions and indices
+    #[serde(default)]
+    pub data_issues: DataIssues,
+    /// Extensions the file uses or requires, and which the app understands
+    #[serde(default)]
+    pub extensions: Vec<GltfExtension>,
+    /// Generator, copyright, license and top-level extras
+    #[serde(default)]
+    pub metadata: AssetMetadata,
 }
 
 /// Axis-aligned bounding box
@@ -37,9 +55,9 @@ impl BoundingBox {
     }
 
     pub fn expand(&mut self, point: [f32; 3]) {
-        for i in 0..3 {
-            self.min[i] = self.min[i].min(point[i]);
-            self.max[i] = self.max[i].max(point[i]);
+        for (i, &value) in point.iter().enumerate() {
+            self.min[i] = self.min[i].min(value);
+            self.max[i] = self.max[i].max(value);
         }
     }
 
@@ -57,25 +75,29 @@ impl BoundingBox {
 }
 
 /// Analyze a GLB/GLTF model and return detailed information
+///
+/// Results are cached; re-analyzing a changed file only re-reads its JSON
+/// and the buffers whose contents changed.
 #[command]
-pub async fn analyze_model(path: String) -> Result<ModelAnalysis, String> {
-    let path = Path::new(&path);
-
-    if !path.exists() {
-        return Err(format!("File not found: {}", path.display()));
-    }
-
-    let file_size_bytes = std::fs::metadata(path)
-        .map_err(|e| format!("Failed to get file metadata: {}", e))?
-        .len();
-
-    // Memory-map the file for efficient access
-    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
-    let mmap = unsafe { Mmap::map(&file) }.map_err(|e| format!("Failed to mmap file: {}", e))?;
-
-    // Parse GLTF
-    let gltf = Gltf::from_slice(&mmap).map_err(|e| format!("Failed to parse GLTF: {}", e))?;
+#[instrument(skip_all, err)]
+pub async fn analyze_model(
+    scope: State<'_, PathScope>,
+    cache: State<'_, AnalysisCache>,
+    path: String,
+) -> Result<ModelAnalysis, String> {
+    let path = scope.check(&path)?;
+    cache.analyze(&path).map(|update| update.analysis)
+}
 
+/// Summarize a parsed glTF document
+///
+/// Bounds come from accessor min/max; `decoded_bounds` supplies bounds for
+/// position accessors that omit them, by accessor index.
+pub fn analyze_document(
+    gltf: &gltf::Document,
+    file_size_bytes: u64,
+    decoded_bounds: &HashMap<usize, BoundingBox>,
+) -> ModelAnalysis {
     // Collect mesh statistics in parallel
     let mesh_stats: Vec<MeshStats> = gltf
         .meshes()
@@ -88,9 +110,12 @@ pub async fn analyze_model(path: String) -> Result<ModelAnalysis, String> {
                 if let Some(accessor) = primitive.get(&gltf::Semantic::Positions) {
                     stats.vertex_count += accessor.count();
 
-                    // We can't read the actual buffer data without gltf::import,
-                    // but we can estimate bounds from accessor min/max if available
-                    if let Some(min) = accessor.min() {
+                    // Use accessor min/max if available; accessors without them
+                    // have bounds decoded from their buffer by the analysis cache
+                    if let Some(bounds) = decoded_bounds.get(&accessor.index()) {
+                        stats.bounds.expand(bounds.min);
+                        stats.bounds.expand(bounds.max);
+                    } else if let Some(min) = accessor.min() {
                         if let Some(max) = accessor.max() {
                             let min_vals: Vec<f32> = min.as_array().unwrap()
                                 .iter()
@@ -124,6 +149,15 @@ pub async fn analyze_model(path: String) -> Result<ModelAnalysis, String> {
                 if primitive.get(&gltf::Semantic::TexCoords(0)).is_some() {
                     stats.has_uvs = true;
                 }
+
+                if primitive.get(&gltf::Semantic::TexCoords(1)).is_some() {
+                    stats.has_lightmap_uvs = true;
+                }
+
+                // Check for vertex colors
+                if primitive.get(&gltf::Semantic::Colors(0)).is_some() {
+                    stats.has_vertex_colors = true;
+                }
             }
             stats
         })
@@ -134,6 +168,8 @@ pub async fn analyze_model(path: String) -> Result<ModelAnalysis, String> {
     let mut total_faces = 0;
     let mut has_normals = false;
     let mut has_uvs = false;
+    let mut has_lightmap_uvs = false;
+    let mut has_vertex_colors = false;
     let mut bounding_box = BoundingBox::new();
 
     for stats in &mesh_stats {
@@ -141,6 +177,8 @@ pub async fn analyze_model(path: String) -> Result<ModelAnalysis, String> {
         total_faces += stats.face_count;
         has_normals |= stats.has_normals;
         has_uvs |= stats.has_uvs;
+        has_lightmap_uvs |= stats.has_lightmap_uvs;
+        has_vertex_colors |= stats.has_vertex_colors;
         if stats.bounds.is_valid() {
             bounding_box.expand(stats.bounds.min);
             bounding_box.expand(stats.bounds.max);
@@ -166,7 +204,7 @@ pub async fn analyze_model(path: String) -> Result<ModelAnalysis, String> {
 
     let center = bounding_box.center();
 
-    Ok(ModelAnalysis {
+    ModelAnalysis {
         vertex_count: total_vertices,
         face_count: total_faces,
         mesh_count: gltf.meshes().count(),
@@ -174,40 +212,73 @@ pub async fn analyze_model(path: String) -> Result<ModelAnalysis, String> {
         has_textures,
         has_normals,
         has_uvs,
+        has_lightmap_uvs,
+        has_vertex_colors,
         file_size_bytes,
         bounding_box,
         center,
-    })
+        data_issues: DataIssues::default(),
+        extensions: gltf_extensions::report(gltf.extensions_used(), gltf.extensions_required()),
+        metadata: AssetMetadata::default(),
+    }
 }
 
 /// Load raw model data as bytes (for streaming to frontend)
 #[command]
-pub async fn load_model_data(path: String) -> Result<Vec<u8>, String> {
-    let path = Path::new(&path);
+#[instrument(skip_all, err)]
+pub async fn load_model_data(
+    scope: State<'_, PathScope>,
+    usage: State<'_, UsageStats>,
+    path: String,
+) -> Result<Vec<u8>, String> {
+    let path = scope.check(&path)?;
 
     if !path.exists() {
         return Err(format!("File not found: {}", path.display()));
     }
 
-    // Memory-map for efficient loading
-    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
-    let mmap = unsafe { Mmap::map(&file) }.map_err(|e| format!("Failed to mmap file: {}", e))?;
+    // Memory-mapped on desktop for efficient loading
+    let bytes = FileBytes::open(&path)?;
+    usage.record_open(&path);
 
-    Ok(mmap.to_vec())
+    Ok(bytes.into_vec())
 }
 
 /// Get just the bounding box of a model (fast operation)
 #[command]
-pub async fn get_model_bounds(path: String) -> Result<BoundingBox, String> {
-    let analysis = analyze_model(path).await?;
+#[instrument(skip_all, err)]
+pub async fn get_model_bounds(
+    scope: State<'_, PathScope>,
+    cache: State<'_, AnalysisCache>,
+    path: String,
+) -> Result<BoundingBox, String> {
+    let analysis = analyze_model(scope, cache, path).await?;
     Ok(analysis.bounding_box)
 }
 
+/// Node hierarchy of a GLB/GLTF model with its lights and cameras
+///
+/// Transforms are as stored in the file, before any import conversion.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn get_scene_graph(
+    scope: State<'_, PathScope>,
+    path: String,
+) -> Result<SceneGraph, String> {
+    let path = scope.check(&path)?;
+    if !path.exists() {
+        return Err(format!("File not found: {}", path.display()));
+    }
+    scene_graph::parse(ModelSource::open(&path)?.json())
+}
+
 #[derive(Default)]
 struct MeshStats {
     vertex_count: usize,
     face_count: usize,
     has_normals: bool,
     has_uvs: bool,
+    has_lightmap_uvs: bool,
+    has_vertex_colors: bool,
     bounds: BoundingBox,
 }
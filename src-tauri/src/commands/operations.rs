@@ -0,0 +1,104 @@
+use crate::utils::changes::ensure_writable;
+use crate::utils::oplog::{InterruptedOperation, OperationLog, PlannedItem, ResumePlan};
+use crate::utils::path_scope::PathScope;
+use crate::utils::settings::SettingsStore;
+use serde::{Deserialize, Serialize};
+use tauri::{command, State};
+use tracing::instrument;
+
+/// Progress of one item, as reported by whoever runs the operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ItemProgressUpdate {
+    /// About to write output, optionally to a temp file that recovery removes
+    Started {
+        temp_path: Option<String>,
+    },
+    /// Output written and moved into place
+    Completed,
+    Failed {
+        error: String,
+    },
+}
+
+/// Log the start of a batch or destructive operation
+///
+/// Refused in read-only mode, since the operation is about to write files.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn begin_operation(
+    log: State<'_, OperationLog>,
+    settings: State<'_, SettingsStore>,
+    kind: String,
+    items: Vec<PlannedItem>,
+) -> Result<u64, String> {
+    ensure_writable(&settings.get())?;
+    log.begin(&kind, items)
+}
+
+/// Log progress of one item; the entry is on disk before this returns
+///
+/// Recovery deletes the temp file of an item that started, so it must be
+/// inside an approved folder and files must be writable.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn record_operation_item(
+    log: State<'_, OperationLog>,
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    op_id: u64,
+    index: usize,
+    update: ItemProgressUpdate,
+) -> Result<(), String> {
+    match update {
+        ItemProgressUpdate::Started { temp_path } => {
+            let temp_path = match temp_path {
+                Some(path) => {
+                    ensure_writable(&settings.get())?;
+                    Some(scope.check(&path)?.to_string_lossy().to_string())
+                }
+                None => None,
+            };
+            log.item_started(op_id, index, temp_path)
+        }
+        ItemProgressUpdate::Completed => log.item_completed(op_id, index),
+        ItemProgressUpdate::Failed { error } => log.item_failed(op_id, index, error),
+    }
+}
+
+/// Log that an operation ran to the end
+#[command]
+#[instrument(skip_all, err)]
+pub async fn finish_operation(log: State<'_, OperationLog>, op_id: u64) -> Result<(), String> {
+    log.finish(op_id)
+}
+
+/// Operations cut off by a crash or forced exit, recovered at startup
+///
+/// Partial outputs have already been cleaned up when this is called.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn get_interrupted_operations(
+    log: State<'_, OperationLog>,
+) -> Result<Vec<InterruptedOperation>, String> {
+    Ok(log.interrupted())
+}
+
+/// Resume an interrupted operation, returning the items left to run
+#[command]
+#[instrument(skip_all, err)]
+pub async fn resume_operation(
+    log: State<'_, OperationLog>,
+    settings: State<'_, SettingsStore>,
+    op_id: u64,
+) -> Result<ResumePlan, String> {
+    ensure_writable(&settings.get())?;
+    log.resume(op_id)
+}
+
+/// Drop an interrupted operation without resuming it
+#[command]
+#[instrument(skip_all, err)]
+pub async fn discard_operation(log: State<'_, OperationLog>, op_id: u64) -> Result<(), String> {
+    log.discard(op_id)
+}
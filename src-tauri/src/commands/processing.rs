@@ -0,0 +1,1142 @@
+use crate::commands::library;
+use crate::commands::mesh_upload::{describe_mesh, MeshHandle};
+use crate::utils::analysis_cache::AnalysisCache;
+use crate::utils::asset_cache::{AssetCache, CacheKind};
+use crate::utils::asset_history::{HistoryEntry, HistoryKind};
+use crate::utils::changes::{ChangeReport, ChangeSet};
+use crate::utils::channel_pack::{self, ImageChannel, PackSpec};
+use crate::utils::compute::{
+    self, check_voxel_resolution, AoParams, VoxelGrid, DEFAULT_AO_SAMPLES,
+    DEFAULT_VOXEL_RESOLUTION,
+};
+use crate::utils::estimate::{CostEstimator, EstimatedOperation, MeshSize, OperationEstimate};
+use crate::utils::glb_writer::write_textured_glb;
+use crate::utils::gltf_geometry::decode_uri;
+use crate::utils::mesh_files::{import_meshes, load_meshes, MeshFileFormat};
+use crate::utils::mesh_store::{MeshData, MeshStore};
+use crate::utils::normal_maps::{self, NormalMapOptions};
+use crate::utils::path_scope::PathScope;
+use crate::utils::profiles;
+use crate::utils::provenance::ProcessingStep;
+use crate::utils::relink;
+use crate::utils::scan_cleanup::{self, ScanPreset, StageReport};
+use crate::utils::settings::{AppSettings, ProcessingBackend, SettingsStore};
+use crate::utils::texture_bake;
+use crate::utils::texture_enhance;
+use crate::utils::vertex_colors::{self, Texture};
+use crate::utils::volume_writer::write_sparse_volume;
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::{command, AppHandle, Manager, State};
+use tracing::{instrument, Span};
+
+/// Compute backends available on this machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComputeBackendInfo {
+    pub gpu_supported: bool,
+    pub gpu_adapter: Option<String>,
+    pub active: String,
+}
+
+/// Result of recomputing a mesh's normals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NormalsResult {
+    pub vertex_count: usize,
+    pub backend: String,
+    pub elapsed_ms: f64,
+}
+
+/// Per-vertex mean curvature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurvatureResult {
+    pub values: Vec<f32>,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub backend: String,
+    pub elapsed_ms: f64,
+}
+
+/// Occupancy grid for a mesh
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoxelizationResult {
+    pub resolution: [u32; 3],
+    pub origin: [f32; 3],
+    pub voxel_size: f32,
+    pub occupied_count: usize,
+    /// Bitset of occupied voxels, x fastest
+    pub bits: Vec<u32>,
+    pub backend: String,
+    pub elapsed_ms: f64,
+}
+
+/// Result of `export_volume`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedVolume {
+    pub out_path: String,
+    pub resolution: [u32; 3],
+    pub voxel_size: f32,
+    pub occupied_count: usize,
+    /// 8×8×8 bricks with occupied voxels, the only ones written
+    pub brick_count: usize,
+    pub changes: ChangeReport,
+}
+
+/// Per-vertex ambient occlusion (1 = fully unoccluded)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AoResult {
+    pub values: Vec<f32>,
+    pub backend: String,
+    pub elapsed_ms: f64,
+}
+
+/// Textures written by `reproject_textures`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReprojectedTextures {
+    /// One per input texture, in the same order
+    pub out_paths: Vec<String>,
+    /// Fraction of the texture inside the target's UV islands
+    pub coverage: f32,
+    pub changes: ChangeReport,
+}
+
+/// Result of `pack_texture_channels`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackedTexture {
+    pub out_path: String,
+    pub width: u32,
+    pub height: u32,
+    /// Material textures pointed at the packed texture
+    pub repointed_count: usize,
+    pub changes: ChangeReport,
+}
+
+/// A channel to write as its own map with `unpack_texture_channels`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnpackedChannel {
+    pub channel: ImageChannel,
+    pub out_path: String,
+}
+
+/// Result of `unpack_texture_channels`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnpackedTexture {
+    /// One per requested channel, in the same order
+    pub out_paths: Vec<String>,
+    pub changes: ChangeReport,
+}
+
+/// Result of `bake_vertex_colors_to_texture`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BakedColorTexture {
+    /// The unwrapped mesh, with UVs instead of vertex colors
+    pub mesh: MeshHandle,
+    pub out_path: String,
+    pub chart_count: usize,
+    /// Fraction of the texture inside a UV chart
+    pub coverage: f32,
+    pub changes: ChangeReport,
+}
+
+/// Result of `process_asset`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessedAsset {
+    pub asset_id: String,
+    pub profile: String,
+    pub out_path: String,
+    /// Scaled-down copies of the model's textures
+    pub texture_paths: Vec<String>,
+    pub original_face_count: usize,
+    pub face_count: usize,
+    /// What each cleanup stage did, empty if the profile has no cleanup
+    pub cleanup: Vec<StageReport>,
+    pub changes: ChangeReport,
+}
+
+/// A cleaned scan and what each stage did to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanCleanup {
+    pub mesh: MeshHandle,
+    pub stages: Vec<StageReport>,
+}
+
+/// Report which compute backends can be used
+#[command]
+#[instrument(skip_all, err)]
+pub async fn get_compute_backends(
+    settings: State<'_, SettingsStore>,
+) -> Result<ComputeBackendInfo, String> {
+    let preference = settings.get().processing_backend;
+    let (gpu_adapter, active) = in_current_span(move || {
+        (
+            compute::gpu_adapter_name(),
+            compute::select_backend(preference).primary.name(),
+        )
+    })
+    .await
+    .map_err(|e| format!("Backend probe failed: {}", e))?;
+
+    Ok(ComputeBackendInfo {
+        gpu_supported: cfg!(feature = "gpu"),
+        gpu_adapter,
+        active: active.to_string(),
+    })
+}
+
+/// Recompute smooth vertex normals and store them on the mesh
+#[command]
+#[instrument(skip_all, err)]
+pub async fn generate_normals(
+    store: State<'_, MeshStore>,
+    settings: State<'_, SettingsStore>,
+    mesh_handle: u64,
+) -> Result<NormalsResult, String> {
+    let mesh = indexed_mesh(&store, mesh_handle)?;
+    let preference = settings.get().processing_backend;
+    let started = Instant::now();
+
+    let source = mesh.clone();
+    let (normals, backend) = in_current_span(move || {
+        compute::select_backend(preference)
+            .run(|backend| backend.vertex_normals(&source.positions, &source.indices))
+    })
+    .await
+    .map_err(|e| format!("Normal generation failed: {}", e))??;
+
+    store.replace(
+        mesh_handle,
+        MeshData {
+            normals: Some(normals),
+            ..(*mesh).clone()
+        },
+    )?;
+
+    Ok(NormalsResult {
+        vertex_count: mesh.vertex_count(),
+        backend: backend.to_string(),
+        elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+    })
+}
+
+/// Estimate signed mean curvature per vertex
+///
+/// Uses the mesh's normals when present, otherwise generates them first.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn compute_curvature(
+    store: State<'_, MeshStore>,
+    settings: State<'_, SettingsStore>,
+    mesh_handle: u64,
+) -> Result<CurvatureResult, String> {
+    let mesh = indexed_mesh(&store, mesh_handle)?;
+    let preference = settings.get().processing_backend;
+    let started = Instant::now();
+
+    let (values, backend) = in_current_span(move || {
+        compute::select_backend(preference).run(|backend| {
+            let normals = match &mesh.normals {
+                Some(normals) => normals.clone(),
+                None => backend.vertex_normals(&mesh.positions, &mesh.indices)?,
+            };
+            backend.mean_curvature(&mesh.positions, &normals, &mesh.indices)
+        })
+    })
+    .await
+    .map_err(|e| format!("Curvature computation failed: {}", e))??;
+
+    let min = values.iter().copied().fold(f32::MAX, f32::min);
+    let max = values.iter().copied().fold(f32::MIN, f32::max);
+    let mean = values.iter().sum::<f32>() / values.len().max(1) as f32;
+
+    Ok(CurvatureResult {
+        values,
+        min,
+        max,
+        mean,
+        backend: backend.to_string(),
+        elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+    })
+}
+
+/// Voxelize a mesh surface, optionally filling closed interiors
+///
+/// `resolution` is the voxel count along the longest axis (default 128).
+#[command]
+#[instrument(skip_all, err)]
+pub async fn voxelize_mesh(
+    store: State<'_, MeshStore>,
+    settings: State<'_, SettingsStore>,
+    mesh_handle: u64,
+    resolution: Option<u32>,
+    fill_interior: Option<bool>,
+) -> Result<VoxelizationResult, String> {
+    let mesh = indexed_mesh(&store, mesh_handle)?;
+    let preference = settings.get().processing_backend;
+    let started = Instant::now();
+    let (grid, backend) = voxel_grid(mesh, preference, resolution, fill_interior).await?;
+
+    Ok(VoxelizationResult {
+        resolution: grid.resolution,
+        origin: grid.origin,
+        voxel_size: grid.voxel_size,
+        occupied_count: grid.occupied_count(),
+        bits: grid.bits,
+        backend: backend.to_string(),
+        elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+    })
+}
+
+/// Voxelize a mesh and write it as a sparse volume for VFX tools
+///
+/// Takes the same `resolution` and `fill_interior` as `voxelize_mesh`
+/// (filling defaults to on here, since volumes are usually solid), with the
+/// same limit of [`compute::MAX_VOXEL_RESOLUTION`]. The file format is described in
+/// [`crate::utils::volume_writer`].
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_volume(
+    app: AppHandle,
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    out_path: String,
+    resolution: Option<u32>,
+    fill_interior: Option<bool>,
+    dry_run: Option<bool>,
+) -> Result<ExportedVolume, String> {
+    if let Some(resolution) = resolution {
+        check_voxel_resolution(resolution)?;
+    }
+    let out_path = app.state::<PathScope>().check(&out_path)?;
+    let settings = app.state::<SettingsStore>().get();
+    let mut changes = ChangeSet::new(&settings, dry_run)?;
+    let mesh = indexed_mesh(&store, mesh_handle)?;
+    let fill_interior = Some(fill_interior.unwrap_or(true));
+    let (grid, _) =
+        voxel_grid(mesh, settings.processing_backend, resolution, fill_interior).await?;
+
+    let (bytes, brick_count) = write_sparse_volume(&grid);
+    changes.write(&out_path, &bytes)?;
+    Ok(ExportedVolume {
+        out_path: out_path.to_string_lossy().to_string(),
+        resolution: grid.resolution,
+        voxel_size: grid.voxel_size,
+        occupied_count: grid.occupied_count(),
+        brick_count,
+        changes: changes.finish(),
+    })
+}
+
+/// Voxelize on the preferred backend off the async runtime
+async fn voxel_grid(
+    mesh: Arc<MeshData>,
+    preference: ProcessingBackend,
+    resolution: Option<u32>,
+    fill_interior: Option<bool>,
+) -> Result<(VoxelGrid, &'static str), String> {
+    let resolution = resolution.unwrap_or(DEFAULT_VOXEL_RESOLUTION);
+    in_current_span(move || {
+        compute::select_backend(preference).run(|backend| {
+            let mut grid = VoxelGrid::fitting(&mesh.positions, resolution)?;
+            backend.voxelize(&mesh.positions, &mesh.indices, &mut grid)?;
+            if fill_interior.unwrap_or(false) {
+                grid.fill_interior();
+            }
+            Ok(grid)
+        })
+    })
+    .await
+    .map_err(|e| format!("Voxelization failed: {}", e))?
+}
+
+/// Bake per-vertex ambient occlusion by tracing rays through a voxel grid
+#[command]
+#[instrument(skip_all, err)]
+pub async fn bake_vertex_ao(
+    store: State<'_, MeshStore>,
+    settings: State<'_, SettingsStore>,
+    mesh_handle: u64,
+    samples: Option<u32>,
+    resolution: Option<u32>,
+) -> Result<AoResult, String> {
+    let mesh = indexed_mesh(&store, mesh_handle)?;
+    let preference = settings.get().processing_backend;
+    let resolution = resolution.unwrap_or(DEFAULT_VOXEL_RESOLUTION);
+    let params = AoParams {
+        samples: samples.unwrap_or(DEFAULT_AO_SAMPLES),
+        max_steps: resolution,
+    };
+    let started = Instant::now();
+
+    let (values, backend) = in_current_span(move || {
+        compute::select_backend(preference).run(|backend| {
+            let normals = match &mesh.normals {
+                Some(normals) => normals.clone(),
+                None => backend.vertex_normals(&mesh.positions, &mesh.indices)?,
+            };
+            let mut grid = VoxelGrid::fitting(&mesh.positions, resolution)?;
+            backend.voxelize(&mesh.positions, &mesh.indices, &mut grid)?;
+            grid.fill_interior();
+            backend.ambient_occlusion(&mesh.positions, &normals, &grid, params)
+        })
+    })
+    .await
+    .map_err(|e| format!("AO baking failed: {}", e))??;
+
+    Ok(AoResult {
+        values,
+        backend: backend.to_string(),
+        elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+    })
+}
+
+/// Sample a texture at each vertex's UV into the mesh's vertex colors
+///
+/// A fallback for viewing textured meshes where textures aren't available,
+/// such as simplified previews. The mesh is updated in place and needs UVs;
+/// detail finer than the vertex spacing is lost.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn bake_texture_to_vertex_colors(
+    scope: State<'_, PathScope>,
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    texture_path: String,
+) -> Result<MeshHandle, String> {
+    let texture_path = scope.check(&texture_path)?;
+    let mesh = store.get(mesh_handle)?;
+    if mesh.uvs.is_none() {
+        return Err("Mesh has no UVs to sample the texture with".to_string());
+    }
+
+    let source = Arc::clone(&mesh);
+    let colors = in_current_span(move || {
+        let image =
+            image::open(&texture_path).map_err(|e| format!("Failed to load texture: {}", e))?;
+        let texture = Texture::from_image(&image)?;
+        Ok::<_, String>(vertex_colors::bake(
+            source.uvs.as_deref().unwrap_or_default(),
+            &texture,
+        ))
+    })
+    .await
+    .map_err(|e| format!("Texture baking failed: {}", e))??;
+
+    store.replace(
+        mesh_handle,
+        MeshData {
+            colors: Some(colors),
+            ..(*mesh).clone()
+        },
+    )?;
+    describe_mesh(&store, mesh_handle)
+}
+
+/// Bake a mesh's textures onto another mesh of the same shape
+///
+/// For a voxel remesh or retopology of a textured scan: each texture, laid
+/// out on the source's UVs, is resampled onto the target's UVs at
+/// `resolution`² (default 2048) and written beside the original as
+/// `<name>_reprojected.png`. The target needs UVs of its own.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn reproject_textures(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    store: State<'_, MeshStore>,
+    source_handle: u64,
+    target_handle: u64,
+    texture_paths: Vec<String>,
+    resolution: Option<u32>,
+) -> Result<ReprojectedTextures, String> {
+    if texture_paths.is_empty() {
+        return Err("No textures to reproject".to_string());
+    }
+    let texture_paths = texture_paths
+        .iter()
+        .map(|path| scope.check(path))
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut changes = ChangeSet::new(&settings.get(), None)?;
+    let source = store.get(source_handle)?;
+    let target = store.get(target_handle)?;
+    let resolution = resolution.unwrap_or(2048);
+
+    let paths = texture_paths.clone();
+    let (images, coverage) = in_current_span(move || {
+        let textures = paths
+            .iter()
+            .map(|path| {
+                let image = image::open(path)
+                    .map_err(|e| format!("Failed to load {}: {}", path.display(), e))?;
+                Texture::from_image(&image)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let (images, coverage) = texture_bake::reproject(&source, &target, &textures, resolution)?;
+        let images = images
+            .into_iter()
+            .map(|image| {
+                let mut out = Cursor::new(Vec::new());
+                image
+                    .write_to(&mut out, ImageFormat::Png)
+                    .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+                Ok(out.into_inner())
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok::<_, String>((images, coverage))
+    })
+    .await
+    .map_err(|e| format!("Texture reprojection failed: {}", e))??;
+
+    let mut out_paths = Vec::new();
+    for (path, png) in texture_paths.iter().zip(images) {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let out_path = path.with_file_name(format!("{}_reprojected.png", stem));
+        changes.write(&out_path, &png)?;
+        out_paths.push(out_path.to_string_lossy().to_string());
+    }
+    Ok(ReprojectedTextures {
+        out_paths,
+        coverage,
+        changes: changes.finish(),
+    })
+}
+
+/// Pack channels of grayscale maps into one texture, such as an ORM map
+///
+/// See [`PackSpec`] for the layout. With a `model_path`, the glTF's
+/// materials are pointed at the packed texture by a relative URI.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn pack_texture_channels(
+    app: AppHandle,
+    spec: PackSpec,
+    dry_run: Option<bool>,
+) -> Result<PackedTexture, String> {
+    let scope = app.state::<PathScope>();
+    let out_path = scope.check(&spec.out_path)?;
+    let sources: Vec<Option<(PathBuf, ImageChannel, bool)>> = spec
+        .sources()
+        .iter()
+        .map(|source| {
+            source
+                .map(|s| Ok::<_, String>((scope.check(&s.path)?, s.channel, s.invert)))
+                .transpose()
+        })
+        .collect::<Result<_, _>>()?;
+    let model_path = spec
+        .model_path
+        .as_ref()
+        .map(|p| scope.check(p))
+        .transpose()?;
+    if let Some(model) = &model_path {
+        if !model
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("gltf"))
+        {
+            return Err(format!(
+                "Materials can only be repointed in .gltf files, not {}",
+                model.display()
+            ));
+        }
+    }
+    let mut changes = ChangeSet::new(&app.state::<SettingsStore>().get(), dry_run)?;
+
+    let (fill, paths, out) = (spec.fill, sources.clone(), out_path.clone());
+    let (bytes, (width, height)) = in_current_span(move || {
+        let mut loaded: HashMap<PathBuf, image::RgbaImage> = HashMap::new();
+        for (path, _, _) in paths.iter().flatten() {
+            if !loaded.contains_key(path) {
+                let image = image::open(path)
+                    .map_err(|e| format!("Failed to load {}: {}", path.display(), e))?;
+                loaded.insert(path.clone(), image.to_rgba8());
+            }
+        }
+        let images = [0, 1, 2, 3].map(|c| {
+            paths[c]
+                .as_ref()
+                .map(|(path, channel, invert)| (&loaded[path], *channel, *invert))
+        });
+        let packed = channel_pack::pack(images, fill)?;
+        let size = packed.dimensions();
+        Ok::<_, String>((channel_pack::encode(packed.into(), &out)?, size))
+    })
+    .await
+    .map_err(|e| format!("Channel packing failed: {}", e))??;
+    changes.write(&out_path, &bytes)?;
+
+    let mut repointed_count = 0;
+    if let Some(model) = &model_path {
+        let dir = model.parent().unwrap_or(Path::new(""));
+        let json =
+            fs::read(model).map_err(|e| format!("Failed to read {}: {}", model.display(), e))?;
+        let mut document: Value = serde_json::from_slice(&json)
+            .map_err(|e| format!("Failed to parse glTF JSON: {}", e))?;
+        let packed_uri = relink::relative_path(dir, &out_path)
+            .map(|relative| relink::encode_uri(&relative))
+            .ok_or_else(|| {
+                format!(
+                    "{} can't be referred to from {}",
+                    out_path.display(),
+                    model.display()
+                )
+            })?;
+        let source_paths =
+            [0, 1, 2, 3].map(|c| sources[c].as_ref().map(|(path, _, _)| path.clone()));
+        repointed_count =
+            channel_pack::repoint_materials(&mut document, &source_paths, &packed_uri, |uri| {
+                (!uri.starts_with("data:")).then(|| {
+                    let path = dir.join(decode_uri(uri));
+                    fs::canonicalize(&path).unwrap_or(path)
+                })
+            });
+        if repointed_count > 0 {
+            let text = serde_json::to_vec_pretty(&document).map_err(|e| e.to_string())?;
+            changes.write(model, &text)?;
+        }
+    }
+
+    Ok(PackedTexture {
+        out_path: out_path.to_string_lossy().to_string(),
+        width,
+        height,
+        repointed_count,
+        changes: changes.finish(),
+    })
+}
+
+/// Split a texture into one grayscale map per listed channel
+#[command]
+#[instrument(skip_all, err)]
+pub async fn unpack_texture_channels(
+    app: AppHandle,
+    path: String,
+    outputs: Vec<UnpackedChannel>,
+    dry_run: Option<bool>,
+) -> Result<UnpackedTexture, String> {
+    if outputs.is_empty() {
+        return Err("No channels to unpack".to_string());
+    }
+    let scope = app.state::<PathScope>();
+    let path = scope.check(&path)?;
+    let out_paths = outputs
+        .iter()
+        .map(|output| scope.check(&output.out_path))
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut changes = ChangeSet::new(&app.state::<SettingsStore>().get(), dry_run)?;
+
+    let (channels, paths) = (
+        outputs.iter().map(|o| o.channel).collect::<Vec<_>>(),
+        out_paths.clone(),
+    );
+    let encoded = in_current_span(move || {
+        let image = image::open(&path)
+            .map_err(|e| format!("Failed to load {}: {}", path.display(), e))?
+            .to_rgba8();
+        channels
+            .iter()
+            .zip(&paths)
+            .map(|(&channel, out)| {
+                channel_pack::encode(channel_pack::unpack(&image, channel).into(), out)
+            })
+            .collect::<Result<Vec<_>, String>>()
+    })
+    .await
+    .map_err(|e| format!("Channel unpacking failed: {}", e))??;
+    for (out, bytes) in out_paths.iter().zip(encoded) {
+        changes.write(out, &bytes)?;
+    }
+
+    Ok(UnpackedTexture {
+        out_paths: out_paths
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect(),
+        changes: changes.finish(),
+    })
+}
+
+/// Result of `enhance_texture`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnhancedTexture {
+    pub out_path: String,
+    pub width: u32,
+    pub height: u32,
+    /// External tool that did the upscaling; `None` for the built-in filters
+    pub upscaler: Option<String>,
+    /// Whether an earlier result for the same file and options was reused
+    pub cached: bool,
+    pub changes: ChangeReport,
+}
+
+/// Denoise a texture and upscale it `scale` times (1 to 8)
+///
+/// `denoise` from 0 (the default) to 1 sets how strongly noise is smoothed.
+/// Upscaling uses the external tool chosen as `texture_upscaler` in the
+/// settings, or Lanczos resampling with sharpening. The result goes to
+/// `out_path`, by default `<stem>_enhanced.png` next to the texture, and is
+/// cached per file version and options.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn enhance_texture(
+    app: AppHandle,
+    path: String,
+    scale: u32,
+    denoise: Option<f32>,
+    out_path: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<EnhancedTexture, String> {
+    let scope = app.state::<PathScope>();
+    let path = scope.check(&path)?;
+    let out_path = match out_path {
+        Some(out_path) => scope.check(&out_path)?,
+        None => {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!("{}_enhanced.png", stem))
+        }
+    };
+    let settings = app.state::<SettingsStore>().get();
+    let mut changes = ChangeSet::new(&settings, dry_run)?;
+    let denoise = denoise.unwrap_or(0.0);
+    let upscaler = match settings.texture_upscaler.trim() {
+        id if !id.is_empty() && scale > 1 => Some(
+            settings
+                .external_tools
+                .iter()
+                .find(|tool| tool.id == id)
+                .cloned()
+                .ok_or_else(|| format!("Unknown texture upscaler: {}", id))?,
+        ),
+        _ => None,
+    };
+    let upscaler_id = upscaler.as_ref().map(|tool| tool.id.clone());
+
+    let (source, out, tool_id) = (path.clone(), out_path.clone(), upscaler_id.clone());
+    let max_bytes = settings.cache.max_bytes;
+    let (bytes, width, height, cached) = in_current_span(move || {
+        let cache = app.state::<AssetCache>();
+        let variant = format!(
+            "enhance-{}-{}-{}",
+            scale,
+            denoise,
+            tool_id.as_deref().unwrap_or("")
+        );
+        let name = AssetCache::source_key(&source, &variant).map(|key| format!("{}.png", key));
+        let hit = name
+            .as_deref()
+            .and_then(|n| cache.get(CacheKind::Texture, n))
+            .and_then(|png| image::load_from_memory(&png).ok());
+        let (image, cached) = match hit {
+            Some(image) => (image, true),
+            None => {
+                let image = image::open(&source)
+                    .map_err(|e| format!("Failed to load {}: {}", source.display(), e))?
+                    .to_rgba8();
+                texture_enhance::check(image.width(), image.height(), scale, denoise)?;
+                let image = texture_enhance::denoise(&image, denoise);
+                let image = match &upscaler {
+                    Some(tool) => texture_enhance::upscale_with_tool(tool, &image, scale)?,
+                    None => texture_enhance::upscale(&image, scale),
+                };
+                let image = image::DynamicImage::ImageRgba8(image);
+                if let Some(name) = &name {
+                    let mut png = Cursor::new(Vec::new());
+                    let stored = image
+                        .write_to(&mut png, ImageFormat::Png)
+                        .map_err(|e| e.to_string())
+                        .and_then(|_| {
+                            cache.put(CacheKind::Texture, name, &png.into_inner(), max_bytes)
+                        });
+                    if let Err(e) = stored {
+                        log::warn!("Failed to cache enhanced {}: {}", source.display(), e);
+                    }
+                }
+                (image, false)
+            }
+        };
+        let (width, height) = (image.width(), image.height());
+        Ok::<_, String>((channel_pack::encode(image, &out)?, width, height, cached))
+    })
+    .await
+    .map_err(|e| format!("Texture enhancement failed: {}", e))??;
+    changes.write(&out_path, &bytes)?;
+
+    Ok(EnhancedTexture {
+        out_path: out_path.to_string_lossy().to_string(),
+        width,
+        height,
+        upscaler: upscaler_id,
+        cached,
+        changes: changes.finish(),
+    })
+}
+
+/// Result of `convert_normal_map`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConvertedNormalMap {
+    pub out_path: String,
+    /// Fraction of the map inside the mesh's UV islands, when converting
+    /// between spaces
+    pub coverage: Option<f32>,
+    pub changes: ChangeReport,
+}
+
+/// Convert a normal map between the DirectX and OpenGL green conventions
+/// and between object and tangent space
+///
+/// Space conversions need the mesh the map was made for, with its UVs, as
+/// `mesh_handle`. The result goes to `out_path`, by default
+/// `<stem>_converted.png` next to the map.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn convert_normal_map(
+    app: AppHandle,
+    path: String,
+    options: NormalMapOptions,
+    mesh_handle: Option<u64>,
+    out_path: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<ConvertedNormalMap, String> {
+    let scope = app.state::<PathScope>();
+    let path = scope.check(&path)?;
+    let out_path = match out_path {
+        Some(out_path) => scope.check(&out_path)?,
+        None => {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!("{}_converted.png", stem))
+        }
+    };
+    let mut changes = ChangeSet::new(&app.state::<SettingsStore>().get(), dry_run)?;
+    let mesh = match mesh_handle {
+        Some(handle) if options.needs_mesh() => Some(app.state::<MeshStore>().get(handle)?),
+        _ => None,
+    };
+
+    let (source, out) = (path.clone(), out_path.clone());
+    let (bytes, coverage) = in_current_span(move || {
+        let image = image::open(&source)
+            .map_err(|e| format!("Failed to load {}: {}", source.display(), e))?
+            .to_rgba8();
+        let (image, coverage) = normal_maps::convert(&image, options, mesh.as_deref())?;
+        Ok::<_, String>((channel_pack::encode(image.into(), &out)?, coverage))
+    })
+    .await
+    .map_err(|e| format!("Normal map conversion failed: {}", e))??;
+    changes.write(&out_path, &bytes)?;
+
+    Ok(ConvertedNormalMap {
+        out_path: out_path.to_string_lossy().to_string(),
+        coverage,
+        changes: changes.finish(),
+    })
+}
+
+/// Turn a vertex-colored mesh into a textured GLB
+///
+/// For colored scans without UVs: the mesh is unwrapped into charts, its
+/// colors are baked into a `resolution`² texture (default 2048) and the
+/// result is written to `out_path` as one GLB with the PNG embedded. The
+/// unwrapped mesh is also added to the store.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn bake_vertex_colors_to_texture(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    out_path: String,
+    resolution: Option<u32>,
+    dry_run: Option<bool>,
+) -> Result<BakedColorTexture, String> {
+    let out_path = scope.check(&out_path)?;
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+    let mesh = store.get(mesh_handle)?;
+    let resolution = resolution.unwrap_or(2048);
+    let name = out_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let (baked, glb) = in_current_span(move || {
+        let baked = texture_bake::bake_vertex_colors(&mesh, resolution)?;
+        let mut png = Cursor::new(Vec::new());
+        baked
+            .texture
+            .write_to(&mut png, ImageFormat::Png)
+            .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+        let glb = write_textured_glb(&name, &baked.mesh, png.get_ref())?;
+        Ok::<_, String>((baked, glb))
+    })
+    .await
+    .map_err(|e| format!("Color baking failed: {}", e))??;
+
+    changes.write(&out_path, &glb)?;
+    let handle = store.insert(baked.mesh);
+    Ok(BakedColorTexture {
+        mesh: describe_mesh(&store, handle)?,
+        out_path: out_path.to_string_lossy().to_string(),
+        chart_count: baked.chart_count,
+        coverage: baked.coverage,
+        changes: changes.finish(),
+    })
+}
+
+/// Clean up a photogrammetry or 3D scan file in one go
+///
+/// Removes floaters, fills small holes, decimates, smooths, recenters on
+/// the ground and regenerates normals, as hard as `preset` says (default
+/// standard). `fit_size` rescales the largest dimension to that many
+/// meters. All meshes in the file are merged into one new mesh.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn cleanup_scan(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    store: State<'_, MeshStore>,
+    path: String,
+    preset: Option<ScanPreset>,
+    fit_size: Option<f32>,
+) -> Result<ScanCleanup, String> {
+    let path = scope.check(&path)?;
+    let settings = settings.get();
+    let cleanup_settings = scan_cleanup::CleanupSettings {
+        fit_size,
+        ..preset.unwrap_or_default().settings()
+    };
+
+    let cleaned = in_current_span(move || {
+        let imported = import_meshes(&path, &settings.import_axes, None, settings.triangulation)?;
+        let mut scan = MeshData::default();
+        for mesh in &imported.meshes {
+            let base = scan.vertex_count() as u32;
+            scan.positions.extend_from_slice(&mesh.positions);
+            scan.indices.extend(mesh.indices.iter().map(|i| i + base));
+        }
+        scan_cleanup::cleanup(&scan, &cleanup_settings)
+    })
+    .await
+    .map_err(|e| format!("Scan cleanup failed: {}", e))??;
+
+    let handle = store.insert(cleaned.mesh);
+    Ok(ScanCleanup {
+        mesh: describe_mesh(&store, handle)?,
+        stages: cleaned.stages,
+    })
+}
+
+/// Run a model file through a processing profile from the settings
+///
+/// The profile's cleanup, simplification and export steps run in order and
+/// the result is written to `out_dir` (default the model's folder) as
+/// `<name>_<profile>.<ext>`. With a texture size limit, images beside the
+/// model that start with its name are scaled to fit and written the same way.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn process_asset(
+    app: AppHandle,
+    asset_id: String,
+    profile: String,
+    out_dir: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<ProcessedAsset, String> {
+    in_current_span(move || {
+        let scope = app.state::<PathScope>();
+        let out_dir = out_dir.map(|dir| scope.check(&dir)).transpose()?;
+        let settings = app.state::<SettingsStore>().get();
+        let (processed, _) =
+            process_file(&scope, &settings, &asset_id, &profile, out_dir, dry_run)?;
+        record_processing(&app, &processed);
+        Ok(processed)
+    })
+    .await
+    .map_err(|e| format!("Processing failed: {}", e))?
+}
+
+/// Add a processing run to the history of the library asset it read from,
+/// unless it was a dry run
+pub(crate) fn record_processing(app: &AppHandle, processed: &ProcessedAsset) {
+    if processed.changes.dry_run {
+        return;
+    }
+    let mut entry = HistoryEntry {
+        source: Some(processed.asset_id.clone()),
+        path: Some(processed.out_path.clone()),
+        ..HistoryEntry::new(HistoryKind::Processed)
+    };
+    let step = |operation: String, details: String| ProcessingStep {
+        operation,
+        details: Some(details).filter(|d| !d.is_empty()),
+        at_ms: entry.at_ms,
+    };
+    let mut steps = vec![step(
+        "process_asset".to_string(),
+        format!(
+            "Profile {}: {} to {} faces",
+            processed.profile, processed.original_face_count, processed.face_count
+        ),
+    )];
+    for stage in processed.cleanup.iter().filter(|stage| !stage.skipped) {
+        let name = serde_json::to_value(stage.stage)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        steps.push(step(name, stage.detail.clone()));
+    }
+    entry.steps = steps;
+    library::record_history(app, &processed.asset_id, entry);
+}
+
+/// `process_asset` on the calling thread, also returning the processed mesh
+pub fn process_file(
+    scope: &PathScope,
+    settings: &AppSettings,
+    asset_id: &str,
+    profile: &str,
+    out_dir: Option<PathBuf>,
+    dry_run: Option<bool>,
+) -> Result<(ProcessedAsset, MeshData), String> {
+    let path = scope.check(asset_id)?;
+    let out_dir =
+        out_dir.unwrap_or_else(|| path.parent().map(Path::to_path_buf).unwrap_or_default());
+    let profile = settings.processing_profiles.find(profile)?;
+    let mut changes = ChangeSet::new(settings, dry_run)?;
+
+    let named = |source: &Path, extension: &str| profile.output_path(&out_dir, source, extension);
+    let out_path = named(&path, profile.format.extension());
+    let textures = match profile.max_texture_size {
+        Some(max) => profiles::sibling_textures(&path)
+            .into_iter()
+            .map(|texture| {
+                scope.check(&texture).map(|texture| {
+                    let extension = texture.extension().unwrap_or_default().to_string_lossy();
+                    let out = named(&texture, &extension);
+                    (texture, out, max)
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+        None => Vec::new(),
+    };
+
+    let imported = import_meshes(&path, &settings.import_axes, None, settings.triangulation)?;
+    let mut mesh = MeshData::default();
+    for part in &imported.meshes {
+        let base = mesh.vertex_count() as u32;
+        mesh.positions.extend_from_slice(&part.positions);
+        mesh.indices.extend(part.indices.iter().map(|i| i + base));
+    }
+    let processed = profiles::process(mesh, profile)?;
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    changes.write(
+        &out_path,
+        &profiles::encode(&name, &processed.mesh, profile.format)?,
+    )?;
+
+    let mut texture_paths = Vec::new();
+    for (texture, out, max) in textures {
+        let image = image::open(&texture)
+            .map_err(|e| format!("Failed to load {}: {}", texture.display(), e))?;
+        let image = if image.width() > max || image.height() > max {
+            image.resize(max, max, image::imageops::FilterType::Lanczos3)
+        } else {
+            image
+        };
+        let format = ImageFormat::from_path(&out)
+            .map_err(|e| format!("Unsupported texture {}: {}", out.display(), e))?;
+        let mut bytes = Cursor::new(Vec::new());
+        image
+            .write_to(&mut bytes, format)
+            .map_err(|e| format!("Failed to encode {}: {}", out.display(), e))?;
+        changes.write(&out, bytes.get_ref())?;
+        texture_paths.push(out.to_string_lossy().to_string());
+    }
+
+    let summary = ProcessedAsset {
+        asset_id: asset_id.to_string(),
+        profile: profile.name.clone(),
+        out_path: out_path.to_string_lossy().to_string(),
+        texture_paths,
+        original_face_count: processed.original_face_count,
+        face_count: processed.mesh.face_count(),
+        cleanup: processed.cleanup,
+        changes: changes.finish(),
+    };
+    Ok((summary, processed.mesh))
+}
+
+/// Estimate how long an operation will take and how much memory it needs
+///
+/// Pass either a mesh handle or a model path. The active backend is
+/// benchmarked on a small mesh the first time, which takes about a second.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn estimate_operation(
+    app: AppHandle,
+    store: State<'_, MeshStore>,
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    mesh_handle: Option<u64>,
+    path: Option<String>,
+    operation: EstimatedOperation,
+) -> Result<OperationEstimate, String> {
+    let mesh = match (mesh_handle, path) {
+        (Some(handle), _) => {
+            let mesh = store.get(handle)?;
+            MeshSize {
+                vertex_count: mesh.vertex_count(),
+                face_count: mesh.face_count(),
+                has_normals: mesh.normals.is_some(),
+            }
+        }
+        (None, Some(path)) => {
+            let path = scope.check(&path)?;
+            if MeshFileFormat::from_path(&path)? == MeshFileFormat::Gltf {
+                let analysis = app.state::<AnalysisCache>().analyze(&path)?.analysis;
+                MeshSize {
+                    vertex_count: analysis.vertex_count,
+                    face_count: analysis.face_count,
+                    has_normals: analysis.has_normals,
+                }
+            } else {
+                let (_, meshes) = load_meshes(&path)?;
+                MeshSize {
+                    vertex_count: meshes.iter().map(|m| m.positions.len() / 3).sum(),
+                    face_count: meshes.iter().map(|m| m.indices.len() / 3).sum(),
+                    has_normals: false,
+                }
+            }
+        }
+        (None, None) => return Err("Pass either mesh_handle or path".to_string()),
+    };
+    let preference = settings.get().processing_backend;
+
+    in_current_span(move || {
+        let estimator = app.state::<CostEstimator>();
+        compute::select_backend(preference)
+            .run(|backend| estimator.estimate(backend, operation, mesh))
+            .map(|(estimate, _)| estimate)
+    })
+    .await
+    .map_err(|e| format!("Estimation failed: {}", e))?
+}
+
+fn indexed_mesh(store: &MeshStore, mesh_handle: u64) -> Result<Arc<MeshData>, String> {
+    let mesh = store.get(mesh_handle)?;
+    if mesh.indices.is_empty() {
+        return Err("No indices provided".to_string());
+    }
+    Ok(mesh)
+}
+
+/// Run blocking work on the thread pool inside the calling command's span
+async fn in_current_span<R: Send + 'static>(
+    work: impl FnOnce() -> R + Send + 'static,
+) -> Result<R, tauri::Error> {
+    let span = Span::current();
+    tauri::async_runtime::spawn_blocking(move || span.in_scope(work)).await
+}
@@ -0,0 +1,104 @@
+use crate::utils::changes::{ChangeReport, ChangeSet};
+use crate::utils::path_scope::PathScope;
+use crate::utils::quarantine::{self, ParseLimits, QuarantineVerdict};
+use crate::utils::settings::SettingsStore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{command, AppHandle, Manager, State};
+use tracing::instrument;
+
+/// Result of promoting a quarantined asset into the library
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromotionResult {
+    pub verdict: QuarantineVerdict,
+    /// Where the file was (or would be) moved; `None` if it was rejected
+    pub destination: Option<String>,
+    pub changes: ChangeReport,
+}
+
+/// Folder downloads and imports should land in before they are checked
+#[command]
+#[instrument(skip_all, err)]
+pub async fn get_quarantine_dir(app: AppHandle) -> Result<String, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?
+        .join("quarantine");
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create quarantine directory: {}", e))?;
+    Ok(dir.to_string_lossy().to_string())
+}
+
+/// Parse an untrusted file in a separate, resource-limited process
+///
+/// `limits` can only tighten the defaults.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn inspect_asset(
+    scope: State<'_, PathScope>,
+    path: String,
+    limits: Option<ParseLimits>,
+) -> Result<QuarantineVerdict, String> {
+    let path = scope.check(&path)?;
+    let limits = limits.unwrap_or_default().clamped();
+    tauri::async_runtime::spawn_blocking(move || quarantine::parse_in_sandbox(&path, &limits))
+        .await
+        .map_err(|e| format!("Inspection failed: {}", e))?
+}
+
+/// Move a file into `destination_dir` only if it passes sandboxed parsing
+///
+/// Rejected files stay where they are; the verdict explains why. As for
+/// [`inspect_asset`], `limits` can only tighten the defaults.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn promote_asset(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    destination_dir: String,
+    limits: Option<ParseLimits>,
+    dry_run: Option<bool>,
+) -> Result<PromotionResult, String> {
+    let path = scope.check(&path)?;
+    let destination_dir = scope.check(&destination_dir)?;
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Not a file: {}", path.display()))?;
+    let destination: PathBuf = destination_dir.join(file_name);
+    if destination.exists() {
+        return Err(format!("{} already exists", destination.display()));
+    }
+
+    let limits = limits.unwrap_or_default().clamped();
+    let source = path.clone();
+    let verdict = tauri::async_runtime::spawn_blocking(move || {
+        quarantine::parse_in_sandbox(&source, &limits)
+    })
+    .await
+    .map_err(|e| format!("Inspection failed: {}", e))??;
+
+    if !verdict.passed {
+        return Ok(PromotionResult {
+            verdict,
+            destination: None,
+            changes: changes.finish(),
+        });
+    }
+
+    if !changes.is_dry_run() {
+        fs::create_dir_all(&destination_dir)
+            .map_err(|e| format!("Failed to create {}: {}", destination_dir.display(), e))?;
+    }
+    changes.rename(&path, &destination)?;
+
+    Ok(PromotionResult {
+        verdict,
+        destination: Some(destination.to_string_lossy().to_string()),
+        changes: changes.finish(),
+    })
+}
@@ -0,0 +1,310 @@
+use crate::utils::analysis_cache::AnalysisCache;
+use crate::utils::changes::{ChangeReport, ChangeSet};
+use crate::utils::color_space::{self, ColorSpaceIssue};
+use crate::utils::gltf_document::GltfDocument;
+use crate::utils::gltf_geometry::{load_gltf, read_primitives};
+use crate::utils::path_scope::PathScope;
+use crate::utils::report::{self, AssetReport, ReportFormat, ValidationResult};
+use crate::utils::settings::{AppSettings, SettingsStore};
+use crate::utils::validation_profiles::{self, largest_texture, ListedProfile, ValidationProfile};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{command, AppHandle, Manager, State};
+use tracing::{instrument, Span};
+
+/// Model formats looked for next to an asset
+const MODEL_FORMATS: [&str; 5] = ["glb", "gltf", "obj", "fbx", "stl"];
+
+/// Outcome of `export_report`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedReport {
+    pub out_path: String,
+    pub format: ReportFormat,
+    pub asset_count: usize,
+    /// Assets whose model could not be analyzed
+    pub failed_count: usize,
+    pub changes: ChangeReport,
+}
+
+/// The built-in validation profiles with the ones from the settings
+#[command]
+#[instrument(skip_all, err)]
+pub async fn list_validation_profiles(
+    settings: State<'_, SettingsStore>,
+) -> Result<Vec<ListedProfile>, String> {
+    Ok(validation_profiles::list(
+        &settings.get().validation_profiles,
+    ))
+}
+
+/// The validation profile called `name`, or the one chosen in the settings
+pub(crate) fn validation_profile(
+    settings: &AppSettings,
+    name: Option<&str>,
+) -> Result<Option<ValidationProfile>, String> {
+    let name = name.unwrap_or(&settings.validation_profile).trim();
+    if name.is_empty() {
+        return Ok(None);
+    }
+    validation_profiles::find(&settings.validation_profiles, name).map(Some)
+}
+
+/// Write per-asset analysis and validation results to a CSV or JSON file
+///
+/// Each id is an asset folder (as returned by `list_storage_assets`) or a
+/// model file. Assets that fail to analyze are still listed with their error.
+/// Assets are checked against the validation profile named `profile`, by
+/// default the one chosen in the settings; an empty name checks none.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_report(
+    app: AppHandle,
+    asset_ids: Vec<String>,
+    format: ReportFormat,
+    out_path: String,
+    profile: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<ExportedReport, String> {
+    let scope = app.state::<PathScope>();
+    let settings = app.state::<SettingsStore>().get();
+    let out_path = scope.check(&out_path)?;
+    let assets = asset_ids
+        .iter()
+        .map(|id| scope.check(id).map(|path| (id.clone(), path)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let profile = validation_profile(&settings, profile.as_deref())?;
+    let mut changes = ChangeSet::new(&settings, dry_run)?;
+
+    let span = Span::current();
+    let app = app.clone();
+    let reports = tauri::async_runtime::spawn_blocking(move || {
+        span.in_scope(|| {
+            let cache = app.state::<AnalysisCache>();
+            assets
+                .iter()
+                .map(|(id, path)| report_asset(&cache, id, path, profile.as_ref()))
+                .collect::<Vec<_>>()
+        })
+    })
+    .await
+    .map_err(|e| format!("Report failed: {}", e))?;
+
+    let generated_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let contents = report::render(&reports, format, generated_ms)?;
+    changes.write(&out_path, contents.as_bytes())?;
+
+    Ok(ExportedReport {
+        out_path: out_path.to_string_lossy().to_string(),
+        format,
+        asset_count: reports.len(),
+        failed_count: reports.iter().filter(|r| r.error.is_some()).count(),
+        changes: changes.finish(),
+    })
+}
+
+pub(crate) fn report_asset(
+    cache: &AnalysisCache,
+    id: &str,
+    path: &Path,
+    profile: Option<&ValidationProfile>,
+) -> AssetReport {
+    let (model, formats) = locate_model(path);
+    let mut report = AssetReport {
+        id: id.to_string(),
+        model_path: model
+            .as_ref()
+            .map(|model| model.to_string_lossy().to_string()),
+        formats,
+        ..Default::default()
+    };
+
+    let Some(model) = model else {
+        report.error = Some(format!("No GLB/GLTF model found in {}", path.display()));
+        return report;
+    };
+
+    match cache.analyze(&model) {
+        Ok(update) => {
+            let analysis = update.analysis;
+            report.file_size_bytes = analysis.file_size_bytes;
+            report.vertex_count = analysis.vertex_count;
+            report.face_count = analysis.face_count;
+            report.mesh_count = analysis.mesh_count;
+            report.material_count = analysis.material_count;
+            report.has_textures = analysis.has_textures;
+            report.bounds_min = Some(analysis.bounding_box.min);
+            report.bounds_max = Some(analysis.bounding_box.max);
+            report.validation = ValidationResult {
+                parses: true,
+                has_geometry: analysis.vertex_count > 0,
+                has_normals: analysis.has_normals,
+                has_uvs: analysis.has_uvs,
+                no_degenerate_faces: false,
+                profile_violations: None,
+                color_space_issues: None,
+            };
+        }
+        Err(e) => {
+            report.error = Some(e);
+            return report;
+        }
+    }
+
+    // Degenerate faces need the decoded indices
+    match load_gltf(&model) {
+        Ok(loaded) => {
+            report.validation.no_degenerate_faces = read_primitives(&loaded).iter().all(|p| {
+                p.indices
+                    .chunks_exact(3)
+                    .all(|f| f[0] != f[1] && f[1] != f[2] && f[0] != f[2])
+            });
+            report.max_texture_size =
+                largest_texture(&loaded, model.parent().unwrap_or(Path::new("")));
+        }
+        Err(e) => {
+            report.validation.parses = false;
+            report.error = Some(e);
+        }
+    }
+
+    if report.has_textures && report.validation.parses {
+        report.validation.color_space_issues = GltfDocument::open(&model).ok().map(|document| {
+            audit(&document)
+                .iter()
+                .map(ColorSpaceIssue::describe)
+                .collect()
+        });
+    }
+
+    if let Some(profile) = profile {
+        report.profile = Some(profile.name.clone());
+        report.validation.profile_violations = Some(profile.violations(&report));
+    }
+    report.score = report.validation.score();
+    report
+}
+
+/// Find the model to analyze and which formats the asset comes in
+///
+/// For a folder, models named after the folder win (`crate/crate.glb`),
+/// otherwise the first one found. For a file, siblings sharing its stem count
+/// as other formats of the same asset.
+fn locate_model(path: &Path) -> (Option<PathBuf>, Vec<String>) {
+    let candidates: Vec<PathBuf> = if path.is_dir() {
+        let name = path
+            .file_name()
+            .map(|n| n.to_os_string())
+            .unwrap_or_default();
+        let mut files: Vec<PathBuf> = fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|e| e.path()).collect())
+            .unwrap_or_default();
+        files.retain(|f| f.is_file() && model_format(f).is_some());
+        files.sort_by_key(|f| (f.file_stem() != Some(name.as_os_str()), f.clone()));
+        files
+    } else {
+        let mut files = vec![path.to_path_buf()];
+        if let (Some(parent), Some(stem)) = (path.parent(), path.file_stem()) {
+            for format in MODEL_FORMATS {
+                let sibling = parent.join(stem).with_extension(format);
+                if sibling != path && sibling.is_file() {
+                    files.push(sibling);
+                }
+            }
+        }
+        files
+    };
+
+    let mut formats: Vec<String> = candidates
+        .iter()
+        .filter_map(|f| model_format(f))
+        .map(str::to_string)
+        .collect();
+    formats.sort_by_key(|f| MODEL_FORMATS.iter().position(|m| m == f));
+    formats.dedup();
+
+    let model = candidates
+        .into_iter()
+        .find(|f| matches!(model_format(f), Some("glb" | "gltf")));
+    (model, formats)
+}
+
+fn model_format(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    MODEL_FORMATS.into_iter().find(|&format| format == ext)
+}
+
+/// Textures of a glTF or GLB whose encoding doesn't match the color space
+/// of the material slots using them
+#[command]
+#[instrument(skip_all, err)]
+pub async fn audit_color_spaces(
+    scope: State<'_, PathScope>,
+    path: String,
+) -> Result<Vec<ColorSpaceIssue>, String> {
+    let path = scope.check(&path)?;
+    Ok(audit(&GltfDocument::open(&path)?))
+}
+
+/// Result of `fix_color_spaces`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixedColorSpaces {
+    pub out_path: String,
+    /// Everything found, including issues that can't be fixed
+    pub issues: Vec<ColorSpaceIssue>,
+    pub fixed_count: usize,
+    pub changes: ChangeReport,
+}
+
+/// Fix the texture color space issues `audit_color_spaces` finds: PNGs get
+/// the color chunks their slots call for and gamma-encoded normal maps are
+/// decoded to linear; the file is written as GLB
+///
+/// Images shared by color and data slots are left alone, since no
+/// encoding suits both.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn fix_color_spaces(
+    scope: State<'_, PathScope>,
+    settings: State<'_, SettingsStore>,
+    path: String,
+    out_path: String,
+    dry_run: Option<bool>,
+) -> Result<FixedColorSpaces, String> {
+    let path = scope.check(&path)?;
+    let out_path = scope.check(&out_path)?;
+    let mut changes = ChangeSet::new(&settings.get(), dry_run)?;
+
+    let mut document = GltfDocument::open(&path)?;
+    let issues = audit(&document);
+    let mut fixed_count = 0;
+    for issue in issues.iter().filter(|issue| issue.fixable) {
+        // An earlier fix of the same image is built on, not discarded
+        let Some(bytes) = document.image_bytes(issue.image) else {
+            continue;
+        };
+        if let Some(fixed) = color_space::fix(bytes, issue.kind)? {
+            document.replace_image(issue.image, &fixed);
+            fixed_count += 1;
+        }
+    }
+    document.compact();
+    changes.write(&out_path, &document.to_glb()?)?;
+
+    Ok(FixedColorSpaces {
+        out_path: out_path.to_string_lossy().to_string(),
+        issues,
+        fixed_count,
+        changes: changes.finish(),
+    })
+}
+
+fn audit(document: &GltfDocument) -> Vec<ColorSpaceIssue> {
+    color_space::audit(&document.json, |image| {
+        document.image_bytes(image).map(<[u8]>::to_vec)
+    })
+}
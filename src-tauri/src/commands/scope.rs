@@ -0,0 +1,160 @@
+use crate::utils::path_scope::{self, PathScope, ScopeEntry};
+use std::path::PathBuf;
+use tauri::{command, AppHandle, Manager, State};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tracing::instrument;
+
+/// Ask the user to approve access to a path outside the current scope
+///
+/// Shows a native confirmation dialog; folders the user approves are
+/// remembered, single files only for this session. Returns `true` right
+/// away if the path is already allowed.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn request_path_access(
+    app: AppHandle,
+    scope: State<'_, PathScope>,
+    path: String,
+    reason: Option<String>,
+) -> Result<bool, String> {
+    let resolved = path_scope::resolve(&PathBuf::from(&path))?;
+    if scope.is_allowed(&resolved) {
+        return Ok(true);
+    }
+
+    let is_directory = resolved.is_dir();
+    let mut message = format!(
+        "Allow Sweedle to access this {}?\n\n{}",
+        if is_directory { "folder" } else { "file" },
+        resolved.display()
+    );
+    if let Some(reason) = reason {
+        message.push_str(&format!("\n\n{}", reason));
+    }
+
+    let handle = app.clone();
+    let approved = tauri::async_runtime::spawn_blocking(move || {
+        handle
+            .dialog()
+            .message(message)
+            .title("File access")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                "Allow".to_string(),
+                "Deny".to_string(),
+            ))
+            .blocking_show()
+    })
+    .await
+    .map_err(|e| format!("Access dialog failed: {}", e))?;
+
+    if approved {
+        let scope = app.state::<PathScope>();
+        if is_directory {
+            scope.allow_directory(&resolved)?;
+        } else {
+            scope.allow_file(&resolved)?;
+        }
+    }
+    Ok(approved)
+}
+
+/// Let the user pick a file or folder with a native dialog and approve it
+///
+/// Returns the chosen path, or `None` if the dialog was cancelled.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn pick_path_access(
+    app: AppHandle,
+    directory: bool,
+    title: Option<String>,
+) -> Result<Option<String>, String> {
+    let handle = app.clone();
+    let picked = tauri::async_runtime::spawn_blocking(move || {
+        let mut dialog = handle.dialog().file();
+        if let Some(title) = title {
+            dialog = dialog.set_title(title);
+        }
+        if directory {
+            dialog.blocking_pick_folder()
+        } else {
+            dialog.blocking_pick_file()
+        }
+    })
+    .await
+    .map_err(|e| format!("File dialog failed: {}", e))?;
+
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+    let path = picked
+        .into_path()
+        .map_err(|e| format!("Unsupported path: {}", e))?;
+
+    let scope = app.state::<PathScope>();
+    let resolved = if directory {
+        scope.allow_directory(&path)?
+    } else {
+        scope.allow_file(&path)?
+    };
+    Ok(Some(resolved.to_string_lossy().to_string()))
+}
+
+/// List every approved root
+#[command]
+#[instrument(skip_all, err)]
+pub async fn get_path_scope(scope: State<'_, PathScope>) -> Result<Vec<ScopeEntry>, String> {
+    Ok(scope.entries())
+}
+
+/// Withdraw access to a previously approved folder or file
+#[command]
+#[instrument(skip_all, err)]
+pub async fn revoke_path_access(scope: State<'_, PathScope>, path: String) -> Result<bool, String> {
+    scope.revoke(&PathBuf::from(path))
+}
+
+/// Let the user pick a program with a native dialog
+///
+/// For settings that name an executable to run: only a path chosen here is
+/// saved, never one sent by the webview. Returns `None` if the dialog was
+/// cancelled.
+pub(crate) async fn pick_program(app: &AppHandle, title: &str) -> Result<Option<PathBuf>, String> {
+    let handle = app.clone();
+    let title = title.to_string();
+    let picked = tauri::async_runtime::spawn_blocking(move || {
+        handle.dialog().file().set_title(title).blocking_pick_file()
+    })
+    .await
+    .map_err(|e| format!("File dialog failed: {}", e))?;
+
+    picked
+        .map(|picked| {
+            picked
+                .into_path()
+                .map_err(|e| format!("Unsupported path: {}", e))
+        })
+        .transpose()
+}
+
+/// Ask the user with a native dialog to approve a program the webview
+/// asked to save, such as an external tool's command line
+///
+/// Like `request_path_access`, the approval can't come from the webview.
+pub(crate) async fn approve_program(app: &AppHandle, message: String) -> Result<bool, String> {
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        handle
+            .dialog()
+            .message(message)
+            .title("Run a program")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                "Allow".to_string(),
+                "Deny".to_string(),
+            ))
+            .blocking_show()
+    })
+    .await
+    .map_err(|e| format!("Approval dialog failed: {}", e))
+}
@@ -0,0 +1,90 @@
+use crate::commands::scope::pick_program;
+use crate::utils::i18n;
+use crate::utils::settings::{AppSettings, SettingsStore};
+use tauri::{command, AppHandle, State};
+use tracing::instrument;
+
+/// Get the current application settings
+#[command]
+#[instrument(skip_all, err)]
+pub async fn get_settings(store: State<'_, SettingsStore>) -> Result<AppSettings, String> {
+    Ok(store.get())
+}
+
+/// Replace the application settings and persist them
+///
+/// Watch rules, storage folders and layout, smart collections, external
+/// tools, the applications files are opened with, the Blender and
+/// gltf-transform executables, the maintenance schedule, power throttling
+/// and the local API are changed with their own commands; the values sent
+/// for them are ignored.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn update_settings(
+    store: State<'_, SettingsStore>,
+    settings: AppSettings,
+) -> Result<AppSettings, String> {
+    store.update(|current| *current = std::mem::take(current).with_edits(settings))
+}
+
+/// Let the user pick the Blender executable with a native dialog
+///
+/// `clear` goes back to looking in the usual install location and on the
+/// `PATH`. Returns the saved path, or `None` if the dialog was cancelled.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn pick_blender_executable(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    clear: Option<bool>,
+) -> Result<Option<String>, String> {
+    pick_executable(&app, &store, "Blender executable", clear, |s| {
+        &mut s.blender_executable
+    })
+    .await
+}
+
+/// Let the user pick the `gltf-transform` executable with a native dialog
+///
+/// `clear` goes back to looking on the `PATH`. Returns the saved path, or
+/// `None` if the dialog was cancelled.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn pick_gltf_transform_executable(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    clear: Option<bool>,
+) -> Result<Option<String>, String> {
+    pick_executable(&app, &store, "gltf-transform executable", clear, |s| {
+        &mut s.gltf_transform_executable
+    })
+    .await
+}
+
+/// Save a program's path picked with a native dialog, or an empty one for
+/// `clear`, into the setting `field` selects
+async fn pick_executable(
+    app: &AppHandle,
+    store: &SettingsStore,
+    title: &str,
+    clear: Option<bool>,
+    field: fn(&mut AppSettings) -> &mut String,
+) -> Result<Option<String>, String> {
+    let executable = if clear.unwrap_or(false) {
+        String::new()
+    } else {
+        match pick_program(app, title).await? {
+            Some(picked) => picked.to_string_lossy().to_string(),
+            None => return Ok(None),
+        }
+    };
+    let mut saved = store.update(|s| *field(s) = executable)?;
+    Ok(Some(std::mem::take(field(&mut saved))))
+}
+
+/// `message`, such as a command's error, in the language of the settings
+#[command]
+#[instrument(skip_all, err)]
+pub async fn localize_message(message: String) -> Result<String, String> {
+    Ok(i18n::localize(&message))
+}
@@ -0,0 +1,588 @@
+use crate::commands::model_loader::BoundingBox;
+use crate::utils::analysis_cache::AnalysisCache;
+use crate::utils::asset_cache::{AssetCache, CacheKind};
+use crate::utils::decimate::{cluster_decimate, DecimatedMesh};
+use crate::utils::gltf_geometry::{load_gltf, read_primitives, PrimitiveGeometry};
+use crate::utils::path_scope::PathScope;
+use crate::utils::preview_cache;
+use crate::utils::settings::SettingsStore;
+use crate::utils::usage_stats::UsageStats;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use tauri::{command, AppHandle, Emitter, Manager};
+use tracing::{info_span, instrument, Span};
+
+const DEFAULT_PREVIEW_FACES: usize = 50_000;
+const DEFAULT_CHUNK_FACES: usize = 65_536;
+
+static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Options controlling a progressive stream
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamOptions {
+    /// Face budget for the decimated preview
+    pub preview_faces: Option<usize>,
+    /// Maximum faces per full-resolution chunk
+    pub chunk_faces: Option<usize>,
+    /// Camera position used to rank chunks by on-screen importance
+    pub camera_position: Option<[f32; 3]>,
+}
+
+/// First event of a stream: enough to frame the camera
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamBoundsEvent {
+    pub stream_id: u64,
+    pub bounding_box: BoundingBox,
+    pub center: [f32; 3],
+    pub vertex_count: usize,
+    pub face_count: usize,
+}
+
+/// Decimated stand-in for the whole model, in world space
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamPreviewEvent {
+    pub stream_id: u64,
+    pub positions: Vec<f32>,
+    pub indices: Vec<u32>,
+    pub face_count: usize,
+    pub from_cache: bool,
+}
+
+/// A piece of full-resolution geometry
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamChunkEvent {
+    pub stream_id: u64,
+    pub chunk_index: usize,
+    pub chunk_count: usize,
+    pub mesh_index: usize,
+    pub primitive_index: usize,
+    pub node_index: Option<usize>,
+    pub material_index: Option<usize>,
+    pub transform: [[f32; 4]; 4],
+    pub importance: f32,
+    pub positions: Vec<f32>,
+    pub normals: Option<Vec<f32>>,
+    pub uvs: Option<Vec<f32>>,
+    pub lightmap_uvs: Option<Vec<f32>>,
+    pub colors: Option<Vec<f32>>,
+    pub indices: Vec<u32>,
+}
+
+/// Summary returned once every chunk has been emitted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamSummary {
+    pub stream_id: u64,
+    pub chunk_count: usize,
+    pub vertex_count: usize,
+    pub face_count: usize,
+    pub preview_face_count: usize,
+    pub preview_from_cache: bool,
+    pub elapsed_ms: u64,
+}
+
+/// Stream a model to the viewer in order of increasing detail
+///
+/// Emits `model-stream-bounds` as soon as the glTF JSON is parsed, then a
+/// decimated `model-stream-preview` (kept in the asset cache per geometry,
+/// so it outlives changes to textures and materials, and sent before the
+/// buffers are decoded when the file was streamed before), then one
+/// `model-stream-chunk` per piece of full-resolution geometry, largest
+/// on-screen contribution first, and finally `model-stream-complete`.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn stream_model_progressive(
+    app: AppHandle,
+    path: String,
+    options: Option<StreamOptions>,
+) -> Result<StreamSummary, String> {
+    let options = options.unwrap_or_default();
+    let stream_id = NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed);
+    let started = Instant::now();
+
+    let path = app.state::<PathScope>().check(&path)?;
+
+    // Bounds come from the cached analysis, usually straight from accessor min/max
+    let analysis = app.state::<AnalysisCache>().analyze(&path)?.analysis;
+    app.state::<UsageStats>().record_open(&path);
+    emit(
+        &app,
+        "model-stream-bounds",
+        StreamBoundsEvent {
+            stream_id,
+            bounding_box: analysis.bounding_box.clone(),
+            center: analysis.center,
+            vertex_count: analysis.vertex_count,
+            face_count: analysis.face_count,
+        },
+    );
+
+    let span = Span::current();
+    tokio::task::spawn_blocking(move || {
+        span.in_scope(|| stream_blocking(&app, stream_id, &path, &options, started))
+    })
+    .await
+    .map_err(|e| format!("Streaming task failed: {}", e))?
+}
+
+fn stream_blocking(
+    app: &AppHandle,
+    stream_id: u64,
+    path: &Path,
+    options: &StreamOptions,
+    started: Instant,
+) -> Result<StreamSummary, String> {
+    let preview_faces = options
+        .preview_faces
+        .unwrap_or(DEFAULT_PREVIEW_FACES)
+        .max(1);
+    let chunk_faces = options.chunk_faces.unwrap_or(DEFAULT_CHUNK_FACES).max(1);
+
+    let cache = app.state::<AssetCache>();
+    let max_bytes = app.state::<SettingsStore>().get().cache.max_bytes;
+    let (primitives, preview_face_count, preview_from_cache) = decode_with_preview(
+        &cache,
+        max_bytes,
+        path,
+        preview_faces,
+        || {
+            info_span!("decode").in_scope(|| {
+                let loaded = load_gltf(path)?;
+                Ok(read_primitives(&loaded))
+            })
+        },
+        |preview, from_cache| emit_preview(app, stream_id, preview, from_cache),
+    )?;
+
+    let mut chunks: Vec<(f32, PrimitiveGeometry)> = primitives
+        .into_iter()
+        .flat_map(|primitive| split_primitive(primitive, chunk_faces))
+        .map(|chunk| (importance(&chunk, options.camera_position), chunk))
+        .collect();
+    chunks.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let chunk_count = chunks.len();
+    let mut vertex_count = 0;
+    let mut face_count = 0;
+    let _emitting = info_span!("emit_chunks").entered();
+
+    for (chunk_index, (importance, chunk)) in chunks.into_iter().enumerate() {
+        vertex_count += chunk.vertex_count();
+        face_count += chunk.face_count();
+
+        emit(
+            app,
+            "model-stream-chunk",
+            StreamChunkEvent {
+                stream_id,
+                chunk_index,
+                chunk_count,
+                mesh_index: chunk.mesh_index,
+                primitive_index: chunk.primitive_index,
+                node_index: chunk.node_index,
+                material_index: chunk.material_index,
+                transform: chunk.transform,
+                importance,
+                positions: chunk.positions,
+                normals: chunk.normals,
+                uvs: chunk.uvs,
+                lightmap_uvs: chunk.lightmap_uvs,
+                colors: chunk.colors,
+                indices: chunk.indices,
+            },
+        );
+    }
+
+    let summary = StreamSummary {
+        stream_id,
+        chunk_count,
+        vertex_count,
+        face_count,
+        preview_face_count,
+        preview_from_cache,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    };
+
+    emit(app, "model-stream-complete", summary.clone());
+
+    Ok(summary)
+}
+
+/// Decode a model's primitives, handing its preview to `send_preview`
+/// as early as possible
+///
+/// Previews are cached per geometry hash, with a small entry per file
+/// version recording the hash of its geometry. When that is known, a
+/// cached preview goes out before `decode` runs; otherwise the geometry is
+/// hashed once decoded, so a file whose textures changed still finds the
+/// preview made for its geometry. Returns the primitives, the preview's
+/// face count and whether it came from the cache.
+fn decode_with_preview(
+    cache: &AssetCache,
+    max_bytes: u64,
+    path: &Path,
+    preview_faces: usize,
+    decode: impl FnOnce() -> Result<Vec<PrimitiveGeometry>, String>,
+    mut send_preview: impl FnMut(DecimatedMesh, bool),
+) -> Result<(Vec<PrimitiveGeometry>, usize, bool), String> {
+    let preview_name = |hash: u64| format!("{:016x}-{}.preview", hash, preview_faces);
+    let hash_name = AssetCache::source_key(path, "geometry").map(|key| key + ".geometry");
+    let known_hash = hash_name
+        .as_ref()
+        .and_then(|name| cache.get(CacheKind::Preview, name))
+        .and_then(|bytes| Some(u64::from_le_bytes(bytes.try_into().ok()?)));
+
+    let early = info_span!("read_cached_preview").in_scope(|| {
+        let bytes = cache.get(CacheKind::Preview, &preview_name(known_hash?))?;
+        preview_cache::decode(&bytes)
+    });
+    if let Some(preview) = early {
+        let face_count = preview.indices.len() / 3;
+        send_preview(preview, true);
+        return Ok((decode()?, face_count, true));
+    }
+
+    let primitives = decode()?;
+    let (positions, indices) = merge_primitives(&primitives);
+    let hash = preview_cache::geometry_hash(&positions, &indices);
+    if let Some(name) = hash_name.filter(|_| known_hash != Some(hash)) {
+        if let Err(e) = cache.put(CacheKind::Preview, &name, &hash.to_le_bytes(), max_bytes) {
+            log::warn!(
+                "Failed to cache geometry hash for {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+
+    let cached = info_span!("read_cached_preview").in_scope(|| {
+        let bytes = cache.get(CacheKind::Preview, &preview_name(hash))?;
+        preview_cache::decode(&bytes)
+    });
+    let from_cache = cached.is_some();
+    let preview = match cached {
+        Some(preview) => preview,
+        None => {
+            let preview = info_span!("build_preview")
+                .in_scope(|| cluster_decimate(&positions, &indices, preview_faces));
+            let bytes = preview_cache::encode(&preview);
+            if let Err(e) = cache.put(CacheKind::Preview, &preview_name(hash), &bytes, max_bytes) {
+                log::warn!("Failed to cache preview for {}: {}", path.display(), e);
+            }
+            preview
+        }
+    };
+    drop((positions, indices));
+    let face_count = preview.indices.len() / 3;
+    send_preview(preview, from_cache);
+    Ok((primitives, face_count, from_cache))
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        log::warn!("Failed to emit {}: {}", event, e);
+    }
+}
+
+fn emit_preview(app: &AppHandle, stream_id: u64, preview: DecimatedMesh, from_cache: bool) {
+    emit(
+        app,
+        "model-stream-preview",
+        StreamPreviewEvent {
+            stream_id,
+            face_count: preview.indices.len() / 3,
+            positions: preview.positions,
+            indices: preview.indices,
+            from_cache,
+        },
+    );
+}
+
+/// Merge all primitives into one mesh in world space
+///
+/// Faces that reference vertices their primitive doesn't have are dropped,
+/// as [`cluster_decimate`] does, and primitives that would take the vertex
+/// count past `u32::MAX` are left out.
+fn merge_primitives(primitives: &[PrimitiveGeometry]) -> (Vec<f32>, Vec<u32>) {
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+
+    for primitive in primitives {
+        let base = (positions.len() / 3) as u32;
+        let count = primitive.vertex_count() as u32;
+        if primitive.vertex_count() > u32::MAX as usize || base.checked_add(count).is_none() {
+            log::warn!(
+                "Leaving primitive {} of mesh {} out of the preview: too many vertices",
+                primitive.primitive_index,
+                primitive.mesh_index
+            );
+            continue;
+        }
+        positions.extend(primitive.world_positions());
+        indices.extend(
+            faces_in_range(&primitive.indices, primitive.vertex_count())
+                .flatten()
+                .map(|&i| base + i),
+        );
+    }
+
+    (positions, indices)
+}
+
+/// Split a primitive into pieces of at most `max_faces` triangles
+///
+/// Faces with an index past the primitive's vertices are dropped.
+fn split_primitive(primitive: PrimitiveGeometry, max_faces: usize) -> Vec<PrimitiveGeometry> {
+    let vertex_count = primitive.vertex_count();
+    if primitive.face_count() <= max_faces
+        && faces_in_range(&primitive.indices, vertex_count).count() == primitive.face_count()
+    {
+        return vec![primitive];
+    }
+
+    // One remap table for every chunk, reset after each through `order`
+    let mut remap = vec![u32::MAX; vertex_count];
+    let mut order = Vec::new();
+    let mut chunks = Vec::new();
+    let mut faces = faces_in_range(&primitive.indices, vertex_count).peekable();
+
+    while faces.peek().is_some() {
+        let mut indices = Vec::new();
+        for &i in faces.by_ref().take(max_faces).flatten() {
+            let i = i as usize;
+            if remap[i] == u32::MAX {
+                remap[i] = order.len() as u32;
+                order.push(i);
+            }
+            indices.push(remap[i]);
+        }
+
+        chunks.push(PrimitiveGeometry {
+            positions: gather(&primitive.positions, &order, 3),
+            normals: primitive.normals.as_ref().map(|n| gather(n, &order, 3)),
+            uvs: primitive.uvs.as_ref().map(|uv| gather(uv, &order, 2)),
+            lightmap_uvs: primitive
+                .lightmap_uvs
+                .as_ref()
+                .map(|uv| gather(uv, &order, 2)),
+            colors: primitive.colors.as_ref().map(|c| gather(c, &order, 4)),
+            indices,
+            ..primitive.without_geometry()
+        });
+
+        for &i in &order {
+            remap[i] = u32::MAX;
+        }
+        order.clear();
+    }
+
+    chunks
+}
+
+/// Triangles of `indices` whose corners are all below `vertex_count`
+fn faces_in_range(indices: &[u32], vertex_count: usize) -> impl Iterator<Item = &[u32]> {
+    indices
+        .chunks_exact(3)
+        .filter(move |face| face.iter().all(|&i| (i as usize) < vertex_count))
+}
+
+fn gather(values: &[f32], order: &[usize], width: usize) -> Vec<f32> {
+    order
+        .iter()
+        .flat_map(|&i| values[i * width..(i + 1) * width].iter().copied())
+        .collect()
+}
+
+/// Rough projected size: bounding radius over distance to the camera
+fn importance(chunk: &PrimitiveGeometry, camera: Option<[f32; 3]>) -> f32 {
+    let world = chunk.world_positions();
+    let mut bounds = BoundingBox::new();
+    for p in world.chunks_exact(3) {
+        bounds.expand([p[0], p[1], p[2]]);
+    }
+    if !bounds.is_valid() {
+        return 0.0;
+    }
+
+    let center = bounds.center();
+    let radius = (0..3)
+        .map(|i| (bounds.max[i] - bounds.min[i]).powi(2))
+        .sum::<f32>()
+        .sqrt()
+        / 2.0;
+
+    match camera {
+        Some(eye) => {
+            let distance = (0..3)
+                .map(|i| (center[i] - eye[i]).powi(2))
+                .sum::<f32>()
+                .sqrt();
+            radius / (distance - radius).max(radius * 0.01).max(f32::EPSILON)
+        }
+        None => radius,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A strip of `quads` unit quads along x, two triangles each
+    fn strip(quads: u32) -> PrimitiveGeometry {
+        let mut positions = Vec::new();
+        for x in 0..=quads {
+            positions.extend([x as f32, 0.0, 0.0, x as f32, 1.0, 0.0]);
+        }
+        let mut indices = Vec::new();
+        for q in 0..quads {
+            let i = q * 2;
+            indices.extend([i, i + 2, i + 1, i + 1, i + 2, i + 3]);
+        }
+        PrimitiveGeometry {
+            mesh_index: 0,
+            primitive_index: 0,
+            node_index: None,
+            material_index: None,
+            transform: nalgebra::Matrix4::identity().into(),
+            uvs: Some(
+                positions
+                    .chunks_exact(3)
+                    .flat_map(|p| [p[0], p[1]])
+                    .collect(),
+            ),
+            positions,
+            normals: None,
+            lightmap_uvs: None,
+            colors: None,
+            indices,
+        }
+    }
+
+    /// An `n` x `n` grid of quads raised by `height`
+    fn grid(n: u32, height: f32) -> PrimitiveGeometry {
+        let mut positions = Vec::new();
+        for y in 0..=n {
+            for x in 0..=n {
+                positions.extend([x as f32, y as f32, ((x * y) % 5) as f32 * 0.1 + height]);
+            }
+        }
+        let mut indices = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * (n + 1) + x;
+                indices.extend([i, i + 1, i + n + 1, i + 1, i + n + 2, i + n + 1]);
+            }
+        }
+        PrimitiveGeometry {
+            positions,
+            indices,
+            uvs: None,
+            ..strip(0)
+        }
+    }
+
+    /// World positions of each corner of each face
+    fn corners(primitive: &PrimitiveGeometry) -> Vec<[f32; 3]> {
+        primitive
+            .indices
+            .iter()
+            .map(|&i| {
+                let p = &primitive.positions[i as usize * 3..i as usize * 3 + 3];
+                [p[0], p[1], p[2]]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_primitive_chunks() {
+        let primitive = strip(5);
+        assert_eq!(split_primitive(primitive.clone(), 10).len(), 1);
+
+        // 10 faces in chunks of 4: 4, 4 and 2, with shared vertices copied
+        // into every chunk that uses them
+        let chunks = split_primitive(primitive.clone(), 4);
+        assert_eq!(
+            chunks.iter().map(|c| c.face_count()).collect::<Vec<_>>(),
+            [4, 4, 2]
+        );
+        assert_eq!(
+            chunks.iter().map(|c| c.vertex_count()).collect::<Vec<_>>(),
+            [6, 6, 4]
+        );
+        for chunk in &chunks {
+            assert_eq!(chunk.uvs.as_ref().unwrap().len(), chunk.vertex_count() * 2);
+            assert!(chunk
+                .indices
+                .iter()
+                .all(|&i| (i as usize) < chunk.vertex_count()));
+        }
+        let rejoined: Vec<_> = chunks.iter().flat_map(corners).collect();
+        assert_eq!(rejoined, corners(&primitive));
+    }
+
+    #[test]
+    fn test_split_and_merge_drop_out_of_range_faces() {
+        let mut primitive = strip(2);
+        primitive.indices.extend([0, 1, 99]);
+
+        let chunks = split_primitive(primitive.clone(), 1);
+        assert_eq!(chunks.len(), 4);
+        let whole = split_primitive(primitive.clone(), 10);
+        assert_eq!(whole.len(), 1);
+        assert_eq!(whole[0].face_count(), 4);
+
+        primitive.indices.push(u32::MAX);
+        let (positions, indices) = merge_primitives(&[primitive.clone(), primitive]);
+        assert_eq!(positions.len(), 2 * 6 * 3);
+        assert_eq!(indices.len(), 2 * 4 * 3);
+        assert!(indices.iter().all(|&i| (i as usize) < positions.len() / 3));
+    }
+
+    #[test]
+    fn test_cached_preview_goes_out_before_decode() {
+        let dir = std::env::temp_dir().join(format!("sweedle-stream-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cache = AssetCache::new(dir.join("cache"));
+        let model = dir.join("scan.glb");
+        let events = std::cell::RefCell::new(Vec::new());
+        let stream = |geometry: PrimitiveGeometry| {
+            events.borrow_mut().clear();
+            let (primitives, faces, from_cache) = decode_with_preview(
+                &cache,
+                u64::MAX,
+                &model,
+                60,
+                || {
+                    events.borrow_mut().push("decode");
+                    Ok(vec![geometry])
+                },
+                |preview, from_cache| {
+                    assert!(!preview.indices.is_empty());
+                    events
+                        .borrow_mut()
+                        .push(if from_cache { "cached" } else { "built" });
+                },
+            )
+            .unwrap();
+            assert_eq!(primitives.len(), 1);
+            assert!(faces > 0);
+            (events.borrow().clone(), from_cache)
+        };
+
+        std::fs::write(&model, b"first").unwrap();
+        assert_eq!(stream(grid(20, 0.0)), (vec!["decode", "built"], false));
+        assert_eq!(stream(grid(20, 0.0)), (vec!["cached", "decode"], true));
+
+        // A new version of the file with the same geometry, like a texture
+        // update, finds the preview once it is decoded
+        std::fs::write(&model, b"second version").unwrap();
+        assert_eq!(stream(grid(20, 0.0)), (vec!["decode", "cached"], true));
+        assert_eq!(stream(grid(20, 0.0)), (vec!["cached", "decode"], true));
+
+        std::fs::write(&model, b"third").unwrap();
+        assert_eq!(stream(grid(20, 1.0)), (vec!["decode", "built"], false));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,391 @@
+use crate::commands::mesh_upload::{describe_mesh, MeshHandle};
+use crate::utils::mesh_store::{MeshData, MeshStore};
+use crate::utils::platform::FileBytes;
+use crate::utils::primitives::{
+    average_cache_miss_ratio, optimize_vertex_cache, optimize_vertex_fetch, stripify,
+    PrimitiveLayout,
+};
+#[cfg(not(mobile))]
+use memmap2::MmapMut;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+#[cfg(mobile)]
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tauri::{command, AppHandle, Manager, State};
+use tracing::instrument;
+
+/// Sections are aligned so the webview can view them as typed arrays in place
+const SECTION_ALIGNMENT: u64 = 16;
+const HANDOFF_DIR: &str = "sweedle-handoff";
+
+static NEXT_HANDOFF_ID: AtomicU64 = AtomicU64::new(1);
+
+/// How mesh buffers move between backend and webview
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeshTransport {
+    /// Memory-mapped temp file read/written directly by both sides
+    SharedFile,
+    /// Raw binary IPC messages (chunked upload)
+    RawIpc,
+}
+
+/// Result of transport negotiation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransportCapabilities {
+    pub platform: String,
+    pub preferred: MeshTransport,
+    pub available: Vec<MeshTransport>,
+    pub handoff_dir: Option<String>,
+}
+
+/// Byte range of one attribute within a handoff file
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BufferRange {
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// Location of each attribute within a handoff file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BufferLayout {
+    pub positions: BufferRange,
+    pub normals: Option<BufferRange>,
+    pub uvs: Option<BufferRange>,
+    #[serde(default)]
+    pub lightmap_uvs: Option<BufferRange>,
+    #[serde(default)]
+    pub colors: Option<BufferRange>,
+    pub indices: BufferRange,
+}
+
+/// How `export_mesh_buffer` lays out the index buffer
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BufferExportOptions {
+    pub primitive: PrimitiveLayout,
+    /// Separate strips with `0xFFFFFFFF` instead of degenerate triangles
+    pub primitive_restart: bool,
+}
+
+/// A mesh written to a shared handoff file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeshBufferHandoff {
+    pub path: String,
+    pub byte_length: u64,
+    pub layout: BufferLayout,
+    pub vertex_count: usize,
+    pub face_count: usize,
+    pub primitive: PrimitiveLayout,
+    /// Indices of a plain triangle list
+    pub original_index_count: usize,
+    pub index_count: usize,
+    /// Fraction of indices saved over the triangle list
+    pub index_savings: f32,
+    /// Vertices shaded per triangle before and after (optimized layout)
+    pub acmr_before: Option<f32>,
+    pub acmr_after: Option<f32>,
+}
+
+/// Report which mesh transports this platform supports
+///
+/// Desktop builds prefer the shared file handoff; mobile sandboxes don't
+/// give the webview reliable access to temp files, so they stay on IPC.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn negotiate_mesh_transport(app: AppHandle) -> Result<TransportCapabilities, String> {
+    let handoff_dir = handoff_dir(&app).ok();
+
+    let mut available = vec![MeshTransport::RawIpc];
+    if cfg!(desktop) && handoff_dir.is_some() {
+        available.insert(0, MeshTransport::SharedFile);
+    }
+
+    Ok(TransportCapabilities {
+        platform: std::env::consts::OS.to_string(),
+        preferred: available[0],
+        available,
+        handoff_dir: handoff_dir.map(|dir| dir.to_string_lossy().to_string()),
+    })
+}
+
+/// Write a mesh into a memory-mapped handoff file for the webview to read
+///
+/// `options.primitive` selects the index layout: the stored triangle list,
+/// a cache-optimized list with vertices in fetch order, or triangle strips
+/// for targets that are short on index bandwidth.
+#[command]
+#[instrument(skip_all, err)]
+pub async fn export_mesh_buffer(
+    app: AppHandle,
+    store: State<'_, MeshStore>,
+    mesh_handle: u64,
+    options: Option<BufferExportOptions>,
+) -> Result<MeshBufferHandoff, String> {
+    let options = options.unwrap_or_default();
+    let stored = store.get(mesh_handle)?;
+    let original_index_count = stored.indices.len();
+    let (mut acmr_before, mut acmr_after) = (None, None);
+    let mesh = match options.primitive {
+        PrimitiveLayout::Triangles => stored,
+        PrimitiveLayout::Optimized => {
+            let reordered = optimize_vertex_cache(&stored.indices, stored.vertex_count());
+            acmr_before = Some(average_cache_miss_ratio(&stored.indices));
+            acmr_after = Some(average_cache_miss_ratio(&reordered));
+            let (order, indices) = optimize_vertex_fetch(&reordered, stored.vertex_count());
+            Arc::new(stored.with_vertices(&order, indices))
+        }
+        PrimitiveLayout::TriangleStrip => {
+            let indices = stripify(&stored.indices, options.primitive_restart);
+            Arc::new(MeshData {
+                indices,
+                polygons: None,
+                ..(*stored).clone()
+            })
+        }
+    };
+
+    let dir = handoff_dir(&app)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create handoff directory: {}", e))?;
+
+    let id = NEXT_HANDOFF_ID.fetch_add(1, Ordering::Relaxed);
+    let path = dir.join(format!("mesh-{}-{}.bin", std::process::id(), id));
+
+    let mut cursor = 0u64;
+    let mut place = |length: u64| {
+        let range = BufferRange {
+            offset: cursor,
+            length,
+        };
+        cursor = align(cursor + length);
+        range
+    };
+
+    let layout = BufferLayout {
+        positions: place(mesh.positions.len() as u64 * 4),
+        normals: mesh.normals.as_ref().map(|n| place(n.len() as u64 * 4)),
+        uvs: mesh.uvs.as_ref().map(|uv| place(uv.len() as u64 * 4)),
+        lightmap_uvs: mesh
+            .lightmap_uvs
+            .as_ref()
+            .map(|uv| place(uv.len() as u64 * 4)),
+        colors: mesh.colors.as_ref().map(|c| place(c.len() as u64 * 4)),
+        indices: place(mesh.indices.len() as u64 * 4),
+    };
+    let byte_length = cursor.max(1);
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to create handoff file: {}", e))?;
+    file.set_len(byte_length)
+        .map_err(|e| format!("Failed to size handoff file: {}", e))?;
+
+    // Phones fill the file from memory rather than mapping it
+    #[cfg(not(mobile))]
+    let mut buffer = unsafe { MmapMut::map_mut(&file) }
+        .map_err(|e| format!("Failed to mmap handoff file: {}", e))?;
+    #[cfg(mobile)]
+    let mut buffer = vec![0u8; byte_length as usize];
+
+    write_words(
+        &mut buffer,
+        layout.positions,
+        mesh.positions.iter().map(|v| v.to_le_bytes()),
+    );
+    if let (Some(range), Some(normals)) = (layout.normals, &mesh.normals) {
+        write_words(&mut buffer, range, normals.iter().map(|v| v.to_le_bytes()));
+    }
+    if let (Some(range), Some(uvs)) = (layout.uvs, &mesh.uvs) {
+        write_words(&mut buffer, range, uvs.iter().map(|v| v.to_le_bytes()));
+    }
+    if let (Some(range), Some(uvs)) = (layout.lightmap_uvs, &mesh.lightmap_uvs) {
+        write_words(&mut buffer, range, uvs.iter().map(|v| v.to_le_bytes()));
+    }
+    if let (Some(range), Some(colors)) = (layout.colors, &mesh.colors) {
+        write_words(&mut buffer, range, colors.iter().map(|v| v.to_le_bytes()));
+    }
+    write_words(
+        &mut buffer,
+        layout.indices,
+        mesh.indices.iter().map(|i| i.to_le_bytes()),
+    );
+
+    #[cfg(not(mobile))]
+    buffer
+        .flush()
+        .map_err(|e| format!("Failed to flush handoff file: {}", e))?;
+    #[cfg(mobile)]
+    (&file)
+        .write_all(&buffer)
+        .map_err(|e| format!("Failed to write handoff file: {}", e))?;
+
+    Ok(MeshBufferHandoff {
+        path: path.to_string_lossy().to_string(),
+        byte_length,
+        layout,
+        vertex_count: mesh.vertex_count(),
+        face_count: original_index_count / 3,
+        primitive: options.primitive,
+        original_index_count,
+        index_count: mesh.indices.len(),
+        index_savings: 1.0 - mesh.indices.len() as f32 / original_index_count.max(1) as f32,
+        acmr_before,
+        acmr_after,
+    })
+}
+
+/// Register a mesh from a handoff file written by the webview
+#[command]
+#[instrument(skip_all, err)]
+pub async fn import_mesh_buffer(
+    app: AppHandle,
+    store: State<'_, MeshStore>,
+    path: String,
+    layout: BufferLayout,
+) -> Result<MeshHandle, String> {
+    let path = checked_handoff_path(&app, &path)?;
+
+    let file = File::open(&path).map_err(|e| format!("Failed to open handoff file: {}", e))?;
+    let bytes = FileBytes::from_file(&file)?;
+
+    let mesh = MeshData {
+        positions: read_f32(&bytes, layout.positions)?,
+        normals: layout.normals.map(|r| read_f32(&bytes, r)).transpose()?,
+        uvs: layout.uvs.map(|r| read_f32(&bytes, r)).transpose()?,
+        lightmap_uvs: layout
+            .lightmap_uvs
+            .map(|r| read_f32(&bytes, r))
+            .transpose()?,
+        colors: layout.colors.map(|r| read_f32(&bytes, r)).transpose()?,
+        indices: read_u32(&bytes, layout.indices)?,
+        ..Default::default()
+    };
+    mesh.validate()?;
+
+    let handle = store.insert(mesh);
+    describe_mesh(&store, handle)
+}
+
+/// Delete a handoff file once the other side is done with it
+#[command]
+#[instrument(skip_all, err)]
+pub async fn release_mesh_buffer(app: AppHandle, path: String) -> Result<bool, String> {
+    let path = checked_handoff_path(&app, &path)?;
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(format!("Failed to remove handoff file: {}", e)),
+    }
+}
+
+fn handoff_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .temp_dir()
+        .map(|dir| dir.join(HANDOFF_DIR))
+        .map_err(|e| format!("Failed to resolve temp directory: {}", e))
+}
+
+/// Only files inside the handoff directory may be read or deleted
+fn checked_handoff_path(app: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    contained_path(&handoff_dir(app)?, path)
+}
+
+/// `path` with symlinks resolved, if that lies inside `dir`
+fn contained_path(dir: &Path, path: &str) -> Result<PathBuf, String> {
+    let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let path =
+        fs::canonicalize(Path::new(path)).map_err(|e| format!("Handoff file not found: {}", e))?;
+
+    if !path.starts_with(&dir) {
+        return Err(format!("Not a handoff file: {}", path.display()));
+    }
+    Ok(path)
+}
+
+fn align(offset: u64) -> u64 {
+    offset.div_ceil(SECTION_ALIGNMENT) * SECTION_ALIGNMENT
+}
+
+fn write_words(bytes: &mut [u8], range: BufferRange, words: impl Iterator<Item = [u8; 4]>) {
+    let start = range.offset as usize;
+    let section = &mut bytes[start..start + range.length as usize];
+    for (slot, word) in section.chunks_exact_mut(4).zip(words) {
+        slot.copy_from_slice(&word);
+    }
+}
+
+fn section(bytes: &[u8], range: BufferRange) -> Result<&[u8], String> {
+    let start = range.offset as usize;
+    let end = start
+        .checked_add(range.length as usize)
+        .filter(|&end| end <= bytes.len())
+        .ok_or_else(|| {
+            format!(
+                "Buffer range {}+{} exceeds handoff file of {} bytes",
+                range.offset,
+                range.length,
+                bytes.len()
+            )
+        })?;
+    if !range.length.is_multiple_of(4) {
+        return Err(format!(
+            "Buffer range of {} bytes is misaligned",
+            range.length
+        ));
+    }
+    Ok(&bytes[start..end])
+}
+
+fn read_f32(bytes: &[u8], range: BufferRange) -> Result<Vec<f32>, String> {
+    Ok(section(bytes, range)?
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+fn read_u32(bytes: &[u8], range: BufferRange) -> Result<Vec<u32>, String> {
+    Ok(section(bytes, range)?
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handoff_paths_stay_in_their_folder() {
+        let root = std::env::temp_dir().join(format!("sweedle-handoff-{}", std::process::id()));
+        let dir = root.join(HANDOFF_DIR);
+        fs::create_dir_all(&dir).unwrap();
+        let inside = dir.join("mesh.bin");
+        let outside = root.join("secret.bin");
+        fs::write(&inside, b"mesh").unwrap();
+        fs::write(&outside, b"secret").unwrap();
+        let path = |p: &Path| p.to_string_lossy().to_string();
+
+        assert_eq!(
+            contained_path(&dir, &path(&inside)).unwrap(),
+            fs::canonicalize(&inside).unwrap()
+        );
+        assert!(contained_path(&dir, &path(&outside)).is_err());
+        assert!(contained_path(&dir, &path(&dir.join("../secret.bin"))).is_err());
+        assert!(contained_path(&dir, &path(&dir.join("missing.bin"))).is_err());
+
+        #[cfg(unix)]
+        {
+            let link = dir.join("link.bin");
+            std::os::unix::fs::symlink(&outside, &link).unwrap();
+            assert!(contained_path(&dir, &path(&link)).is_err());
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,20 @@
+use crate::utils::usage_stats::{UsageReport, UsageStats};
+use tauri::{command, State};
+
+/// Locally collected usage statistics for the dashboard
+///
+/// `top` limits how many of the most opened assets are returned (default 20).
+#[command]
+pub async fn get_usage_stats(
+    usage: State<'_, UsageStats>,
+    top: Option<usize>,
+) -> Result<UsageReport, String> {
+    usage.flush()?;
+    Ok(usage.report(top.unwrap_or(20)))
+}
+
+/// Delete all collected usage statistics
+#[command]
+pub async fn clear_usage_stats(usage: State<'_, UsageStats>) -> Result<(), String> {
+    usage.clear()
+}
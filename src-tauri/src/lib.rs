@@ -1,31 +1,356 @@
 mod commands;
-mod utils;
+pub mod utils;
 
-use commands::{file_ops, mesh_ops, model_loader};
+use commands::{
+    animation, api, ar_preview, blender, bundle, cache, clipboard, desktop, diagnostics,
+    external_tools, file_ops, jobs, library, maintenance, mesh_ops, mesh_upload, model_export,
+    model_import, model_loader, operations, processing, quarantine, reports, scope, settings,
+    streaming, transport, usage,
+};
+use tauri::{DragDropEvent, Emitter, Manager, RunEvent, WebviewWindowBuilder, WindowEvent};
+use utils::analysis_cache::AnalysisCache;
+use utils::ar_preview::ArPreviewServer;
+use utils::asset_cache::AssetCache;
+use utils::asset_history::AssetHistory;
+use utils::curation::CurationStore;
+use utils::estimate::CostEstimator;
+use utils::jobs::JobQueue;
+use utils::library::LibraryIndex;
+use utils::licenses::LicenseStore;
+use utils::logging::Logs;
+use utils::mesh_store::MeshStore;
+use utils::metrics::{Metrics, TrackingAllocator};
+use utils::notifications::WindowFocus;
+use utils::oplog::OperationLog;
+use utils::path_scope::PathScope;
+use utils::rpc_server::{HeadlessOptions, RpcServer};
+use utils::settings::SettingsStore;
+use utils::usage_stats::UsageStats;
+use utils::watcher::DirectoryWatchers;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    env_logger::init();
+    // Sandboxed parse workers are this same executable with a flag
+    if let Some(code) = utils::quarantine::worker_main() {
+        std::process::exit(code);
+    }
+
+    // `--headless` serves JSON-RPC instead of opening a window; there is no
+    // command line on mobile
+    let headless = HeadlessOptions::from_args(std::env::args()).filter(|_| !cfg!(mobile));
+    let serve_rpc = match headless {
+        Some(Ok(options)) => Some(options),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    let serving = serve_rpc.is_some();
+
+    utils::logging::init();
+    utils::platform::limit_threads();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .manage(MeshStore::default())
+        .manage(AnalysisCache::default())
+        .manage(DirectoryWatchers::default())
+        .manage(CostEstimator::default())
+        .manage(ArPreviewServer::default())
+        .manage(WindowFocus::default())
+        .manage(RpcServer::default())
+        .setup(move |app| {
+            // Logs are kept in memory until the log folder is known
+            if let Err(e) = Logs::global().open_dir(&app.path().app_log_dir()?) {
+                log::warn!("Logging to stderr only: {}", e);
+            }
+            let path = app.path().app_config_dir()?.join("settings.json");
+            app.manage(SettingsStore::load(path));
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(OperationLog::open(data_dir.join("operations.log")));
+            app.manage(UsageStats::load(data_dir.join("usage_stats.json")));
+            app.manage(LibraryIndex::load(data_dir.join("library_index.json")));
+            app.manage(LicenseStore::load(data_dir.join("licenses.json")));
+            app.manage(CurationStore::load(data_dir.join("curation.json")));
+            app.manage(AssetHistory::load(data_dir.join("asset_history.json")));
+            app.manage(JobQueue::load(data_dir.join("jobs.json")));
+            app.manage(AssetCache::new(app.path().app_cache_dir()?));
+            let handle = app.handle().clone();
+            Metrics::global().observe_operations(move |name, elapsed, failed| {
+                handle
+                    .state::<UsageStats>()
+                    .record_operation(name, elapsed, failed);
+            });
+
+            let config_dir = app.path().app_config_dir()?;
+            let app_dirs = vec![
+                data_dir,
+                config_dir.clone(),
+                app.path().app_cache_dir()?,
+                app.path().temp_dir()?.join("sweedle-handoff"),
+            ];
+            app.manage(PathScope::load(config_dir.join("path_scope.json"), app_dirs));
+
+            let handle = app.handle().clone();
+            app.state::<JobQueue>().observe(move |job| {
+                if let Err(e) = handle.emit("job-updated", job) {
+                    log::warn!("Failed to emit job-updated: {}", e);
+                }
+                jobs::notify_finished(&handle, job);
+            });
+            jobs::start_power_monitor(app.handle());
+            // Phones don't process or check the library unless asked to
+            if !cfg!(mobile) {
+                jobs::watch_rule_folders(app.handle());
+                maintenance::start_schedule(app.handle());
+            }
+
+            match serve_rpc {
+                Some(options) => api::start_headless(app.handle(), options)?,
+                None => {
+                    api::start_local_api(app.handle());
+                    for window in &app.config().app.windows {
+                        WebviewWindowBuilder::from_config(app.handle(), window)?.build()?;
+                    }
+                }
+            }
+            Ok(())
+        })
+        .on_window_event(|window, event| match event {
+            // Dropping files onto the window counts as opening them
+            WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) => {
+                let scope = window.state::<PathScope>();
+                for path in paths {
+                    let approved = if path.is_dir() {
+                        scope.allow_directory(path)
+                    } else {
+                        scope.allow_file(path)
+                    };
+                    if let Err(e) = approved {
+                        log::warn!("Failed to approve dropped path: {}", e);
+                    }
+                }
+            }
+            WindowEvent::Focused(focused) => {
+                window.state::<WindowFocus>().set_focused(*focused);
+            }
+            WindowEvent::Destroyed => {
+                if let Err(e) = window.state::<UsageStats>().flush() {
+                    log::warn!("{}", e);
+                }
+            }
+            _ => {}
+        })
         .invoke_handler(tauri::generate_handler![
             // Model loading commands
             model_loader::analyze_model,
             model_loader::load_model_data,
             model_loader::get_model_bounds,
+            model_loader::get_scene_graph,
+            // Progressive streaming
+            streaming::stream_model_progressive,
             // Mesh operations
             mesh_ops::generate_lod,
+            mesh_ops::simplify_mesh,
+            mesh_ops::generate_skinned_lod,
+            mesh_ops::reindex_mesh,
+            mesh_ops::repair_mesh,
+            mesh_ops::slice_mesh,
+            mesh_ops::printability_report,
+            mesh_ops::suggest_print_orientation,
+            mesh_ops::detect_symmetry,
+            mesh_ops::mirror_mesh,
+            mesh_ops::scale_to_measurement,
+            mesh_ops::hollow_mesh,
+            mesh_ops::offset_mesh,
+            mesh_ops::array_mesh,
+            mesh_ops::add_drain_holes,
+            mesh_ops::delete_faces,
+            mesh_ops::separate_selection,
+            mesh_ops::crop_mesh,
+            mesh_ops::plane_cut,
+            mesh_ops::shrinkwrap,
+            mesh_ops::diff_meshes_spatial,
+            mesh_ops::project_uvs,
+            mesh_ops::generate_lightmap_uvs,
+            mesh_ops::analyze_uvs,
+            mesh_ops::render_uv_layout,
+            mesh_ops::normalize_texel_density,
             mesh_ops::optimize_mesh,
             mesh_ops::calculate_mesh_stats,
+            mesh_ops::calculate_file_stats,
+            // Chunked mesh upload
+            mesh_upload::begin_mesh_upload,
+            mesh_upload::append_mesh_chunk,
+            mesh_upload::finish_mesh_upload,
+            mesh_upload::cancel_mesh_upload,
+            mesh_upload::release_mesh,
+            // Model import with axis and unit conversion
+            model_import::import_model,
+            model_import::convert_to_glb,
+            model_import::resolve_dependencies,
+            model_import::relink_dependencies,
+            model_export::export_obj,
+            model_export::export_section,
+            model_export::pack_build_plate,
+            model_export::export_build_plate,
+            model_export::export_for_engine,
+            model_export::export_instanced_glb,
+            model_export::export_array_glb,
+            model_export::export_flattened_glb,
+            model_export::extract_node,
+            model_export::set_asset_rights,
+            model_export::stamp_provenance,
+            model_export::verify_provenance,
+            model_export::export_web_viewer,
+            // Animation clip editing
+            animation::list_animations,
+            animation::trim_animation,
+            animation::split_animation,
+            animation::merge_animations,
+            animation::bake_pose,
+            animation::analyze_rest_pose,
+            animation::convert_rest_pose,
+            animation::analyze_weights,
+            animation::limit_influences,
+            animation::prune_unused_bones,
+            api::get_local_api,
+            api::set_local_api,
+            // LAN previews for viewing models in AR on a phone
+            ar_preview::start_ar_preview,
+            ar_preview::stop_ar_preview,
+            // Shared-memory mesh transport
+            transport::negotiate_mesh_transport,
+            transport::export_mesh_buffer,
+            transport::import_mesh_buffer,
+            transport::release_mesh_buffer,
+            // Mesh processing
+            processing::get_compute_backends,
+            processing::generate_normals,
+            processing::compute_curvature,
+            processing::voxelize_mesh,
+            processing::export_volume,
+            processing::bake_vertex_ao,
+            processing::bake_texture_to_vertex_colors,
+            processing::reproject_textures,
+            processing::bake_vertex_colors_to_texture,
+            processing::pack_texture_channels,
+            processing::unpack_texture_channels,
+            processing::enhance_texture,
+            processing::convert_normal_map,
+            processing::cleanup_scan,
+            processing::process_asset,
+            processing::estimate_operation,
+            // Quarantine for untrusted assets
+            quarantine::get_quarantine_dir,
+            quarantine::inspect_asset,
+            quarantine::promote_asset,
+            // Path access
+            scope::request_path_access,
+            scope::pick_path_access,
+            scope::get_path_scope,
+            scope::revoke_path_access,
+            // Settings
+            settings::get_settings,
+            settings::update_settings,
+            settings::pick_blender_executable,
+            settings::pick_gltf_transform_executable,
+            settings::localize_message,
+            // Operation log and crash recovery
+            operations::begin_operation,
+            operations::record_operation_item,
+            operations::finish_operation,
+            operations::get_interrupted_operations,
+            operations::resume_operation,
+            operations::discard_operation,
+            // Diagnostics
+            diagnostics::get_performance_report,
+            diagnostics::get_recent_logs,
+            diagnostics::export_debug_bundle,
+            diagnostics::run_diagnostics,
+            // Review reports
+            reports::export_report,
+            reports::list_validation_profiles,
+            reports::audit_color_spaces,
+            reports::fix_color_spaces,
+            // Local usage statistics
+            usage::get_usage_stats,
+            usage::clear_usage_stats,
             // File operations
             file_ops::read_file_chunked,
             file_ops::get_file_info,
             file_ops::list_storage_assets,
+            file_ops::migrate_storage_layout,
             file_ops::watch_directory,
+            file_ops::unwatch_directory,
+            // Storage roots and the merged library
+            library::set_storage_roots,
+            library::get_library,
+            library::index_storage_roots,
+            library::move_asset_between_roots,
+            library::bulk_rename,
+            // Asset licenses and project credits
+            library::set_asset_license,
+            library::set_project_assets,
+            library::compliance_report,
+            // Import and processing history
+            library::get_asset_history,
+            // Ratings, favorites and export counts
+            library::set_rating,
+            library::toggle_favorite,
+            library::query_library,
+            // Smart collections
+            library::list_smart_collections,
+            library::set_smart_collections,
+            library::evaluate_smart_collection,
+            // Asset bundles for sharing
+            bundle::export_bundle,
+            bundle::import_bundle,
+            // Thumbnail, preview and texture cache
+            cache::get_cache_stats,
+            cache::clear_cache,
+            cache::store_thumbnail,
+            cache::get_thumbnail,
+            cache::transcode_texture,
+            cache::render_material_previews,
+            // Clipboard
+            clipboard::copy_asset_path,
+            clipboard::copy_asset_summary,
+            clipboard::copy_thumbnail,
+            // File manager and other applications
+            desktop::reveal_in_file_manager,
+            desktop::open_with_default,
+            desktop::open_with,
+            desktop::pick_open_with_application,
+            // External tools
+            external_tools::set_external_tools,
+            external_tools::run_external_tool,
+            // Round-trip editing in Blender
+            blender::edit_in_blender,
+            // Background jobs, resuming them and watch folder rules
+            jobs::list_jobs,
+            jobs::set_watch_rules,
+            jobs::list_interrupted_jobs,
+            jobs::resume_jobs,
+            jobs::dismiss_jobs,
+            jobs::get_power_status,
+            jobs::set_power_throttling,
+            // Scheduled library maintenance
+            maintenance::set_maintenance_schedule,
+            maintenance::run_maintenance,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(move |_, event| match event {
+            // Without windows, the last one closing mustn't end a headless run
+            RunEvent::ExitRequested { code: None, api, .. } if serving => api.prevent_exit(),
+            _ => {}
+        });
 }
@@ -0,0 +1,649 @@
+use crate::commands::model_loader::{analyze_document, BoundingBox, ModelAnalysis};
+use crate::utils::gltf_geometry::external_buffer_path;
+use crate::utils::gltf_metadata;
+use crate::utils::platform::FileBytes;
+use crate::utils::sanitize::{self, DataIssues, MAX_COORDINATE};
+use gltf::buffer::Source;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tracing::info_span;
+use xxhash_rust::xxh3::xxh3_64;
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+/// How an analysis result was produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalysisMode {
+    /// File and its buffers are unchanged since the cached analysis
+    Cached,
+    /// JSON re-read, buffers re-validated by hash
+    Incremental,
+    /// No previous analysis to build on
+    Full,
+}
+
+/// Content hash of one glTF buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufferFingerprint {
+    pub byte_length: u64,
+    pub hash: u64,
+}
+
+/// Result of analyzing a model through the cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisUpdate {
+    pub path: String,
+    pub analysis: ModelAnalysis,
+    pub mode: AnalysisMode,
+    pub json_changed: bool,
+    /// Buffers whose contents changed (every buffer on a full analysis)
+    pub changed_buffers: Vec<usize>,
+    pub reused_buffers: Vec<usize>,
+    /// Position accessors without min/max that had to be decoded
+    pub decoded_accessors: usize,
+    /// Problems found while validating changed buffers
+    pub issues: Vec<String>,
+}
+
+/// Size and modification time, used to skip files that haven't changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+#[derive(Clone)]
+struct CachedModel {
+    stamp: FileStamp,
+    /// External buffer files and their stamps at analysis time
+    dependencies: Vec<(PathBuf, Option<FileStamp>)>,
+    json_hash: u64,
+    /// `None` for buffers embedded as data URIs (covered by the JSON hash)
+    buffers: Vec<Option<BufferFingerprint>>,
+    /// Decoded accessor bounds keyed by accessor fingerprint
+    decoded_bounds: HashMap<u64, BoundingBox>,
+    /// Bad data per primitive, keyed by its accessors' fingerprints
+    data_scans: HashMap<u64, DataIssues>,
+    analysis: ModelAnalysis,
+}
+
+/// Cached model analyses keyed by canonical path, managed as Tauri state
+#[derive(Default)]
+pub struct AnalysisCache {
+    entries: Mutex<HashMap<PathBuf, CachedModel>>,
+}
+
+impl AnalysisCache {
+    /// Analyze a model, reusing whatever the previous analysis can vouch for
+    ///
+    /// Only the JSON is parsed; buffers are hashed and compared with the
+    /// cached fingerprints, and geometry is decoded only where its data
+    /// changed: to find bounds for position accessors that lack min/max,
+    /// and to scan positions and indices for NaN or out-of-range values.
+    pub fn analyze(&self, path: &Path) -> Result<AnalysisUpdate, String> {
+        if !path.exists() {
+            return Err(format!("File not found: {}", path.display()));
+        }
+        let path = fs::canonicalize(path).map_err(|e| format!("Failed to resolve path: {}", e))?;
+        let stamp = FileStamp::of(&path)
+            .ok_or_else(|| format!("Failed to get file metadata: {}", path.display()))?;
+        let previous = self.entries.lock().unwrap().get(&path).cloned();
+
+        if let Some(previous) = &previous {
+            let dependencies_unchanged = previous
+                .dependencies
+                .iter()
+                .all(|(dependency, stamp)| FileStamp::of(dependency) == *stamp);
+            if previous.stamp == stamp && dependencies_unchanged {
+                return Ok(AnalysisUpdate {
+                    path: path.to_string_lossy().to_string(),
+                    analysis: previous.analysis.clone(),
+                    mode: AnalysisMode::Cached,
+                    json_changed: false,
+                    changed_buffers: Vec::new(),
+                    reused_buffers: (0..previous.buffers.len()).collect(),
+                    decoded_accessors: 0,
+                    issues: Vec::new(),
+                });
+            }
+        }
+
+        let source = ModelSource::open(&path)?;
+        let json_hash = xxh3_64(source.json());
+        let document = info_span!("parse_json").in_scope(|| {
+            let root = gltf::json::Root::from_slice(source.json())
+                .map_err(|e| format!("Failed to parse GLTF: {}", e))?;
+            validate_document(root)
+        })?;
+        let json_changed = previous.as_ref().is_none_or(|p| p.json_hash != json_hash);
+
+        // Open external buffers; embedded data URIs are covered by the JSON hash
+        let base = path.parent().unwrap_or(Path::new(""));
+        let mut dependencies = Vec::new();
+        let mut issues = Vec::new();
+        let external: Vec<Option<FileBytes>> = document
+            .buffers()
+            .map(|buffer| match buffer.source() {
+                Source::Uri(uri) if !uri.starts_with("data:") => {
+                    let file_path = match external_buffer_path(base, uri) {
+                        Ok(file_path) => file_path,
+                        Err(e) => {
+                            issues.push(e);
+                            return None;
+                        }
+                    };
+                    dependencies.push((file_path.clone(), FileStamp::of(&file_path)));
+                    File::open(&file_path)
+                        .ok()
+                        .and_then(|file| FileBytes::from_file(&file).ok())
+                }
+                _ => None,
+            })
+            .collect();
+        let buffer_data: Vec<Option<&[u8]>> = document
+            .buffers()
+            .map(|buffer| match buffer.source() {
+                Source::Bin => source.bin(),
+                Source::Uri(_) => external[buffer.index()].as_deref(),
+            })
+            .collect();
+
+        let fingerprints: Vec<Option<BufferFingerprint>> =
+            info_span!("hash_buffers").in_scope(|| {
+                buffer_data
+                    .par_iter()
+                    .map(|data| {
+                        data.map(|bytes| BufferFingerprint {
+                            byte_length: bytes.len() as u64,
+                            hash: xxh3_64(bytes),
+                        })
+                    })
+                    .collect()
+            });
+
+        let (changed_buffers, reused_buffers): (Vec<usize>, Vec<usize>) = (0..fingerprints.len())
+            .partition(|&i| match &previous {
+                Some(previous) => {
+                    previous.buffers.get(i) != Some(&fingerprints[i])
+                        || (fingerprints[i].is_none() && json_changed)
+                }
+                None => true,
+            });
+
+        // A JSON change can move views around, so every buffer is re-checked
+        let to_validate: HashSet<usize> = if json_changed {
+            (0..fingerprints.len()).collect()
+        } else {
+            changed_buffers.iter().copied().collect()
+        };
+        issues.extend(
+            info_span!("validate_buffers")
+                .in_scope(|| validate_buffers(&document, &buffer_data, &to_validate)),
+        );
+
+        let cached_bounds = previous
+            .as_ref()
+            .map(|p| &p.decoded_bounds)
+            .cloned()
+            .unwrap_or_default();
+        let mut decoded_bounds = HashMap::new();
+        let mut bounds_by_accessor = HashMap::new();
+        let mut decoded_accessors = 0;
+
+        for accessor in position_accessors_without_bounds(&document) {
+            let key = accessor_key(document.as_json(), accessor.index(), &fingerprints);
+            let bounds = match key.and_then(|key| cached_bounds.get(&key)) {
+                Some(bounds) => Some(bounds.clone()),
+                None => {
+                    let decoded = info_span!("decode_bounds")
+                        .in_scope(|| decode_bounds(&accessor, &buffer_data));
+                    decoded_accessors += decoded.is_some() as usize;
+                    decoded
+                }
+            };
+            if let Some(bounds) = bounds {
+                if let Some(key) = key {
+                    decoded_bounds.insert(key, bounds.clone());
+                }
+                bounds_by_accessor.insert(accessor.index(), bounds);
+            }
+        }
+
+        let cached_scans = previous
+            .as_ref()
+            .map(|p| &p.data_scans)
+            .cloned()
+            .unwrap_or_default();
+        let primitives: Vec<_> = document
+            .meshes()
+            .flat_map(|mesh| mesh.primitives().map(move |p| (mesh.index(), p)))
+            .collect();
+        let scans: Vec<_> = info_span!("scan_geometry").in_scope(|| {
+            primitives
+                .par_iter()
+                .map(|(mesh, primitive)| {
+                    let key = primitive_key(document.as_json(), primitive, &fingerprints);
+                    let scan = key
+                        .and_then(|key| cached_scans.get(&key).copied())
+                        .or_else(|| scan_primitive(primitive, &buffer_data));
+                    (*mesh, primitive.index(), key, scan)
+                })
+                .collect()
+        });
+
+        let mut analysis = analyze_document(&document, stamp.size, &bounds_by_accessor);
+        analysis.metadata = gltf_metadata::read(source.json())?;
+        let mut data_scans = HashMap::new();
+        for (mesh, primitive, key, scan) in scans {
+            let Some(scan) = scan else {
+                continue;
+            };
+            if let Some(key) = key {
+                data_scans.insert(key, scan);
+            }
+            if !scan.is_empty() {
+                issues.push(format!(
+                    "Mesh {} primitive {} has {}",
+                    mesh,
+                    primitive,
+                    scan.summary()
+                ));
+            }
+            analysis.data_issues.merge(&scan);
+        }
+        for issue in &issues {
+            log::warn!("{}: {}", path.display(), issue);
+        }
+
+        self.entries.lock().unwrap().insert(
+            path.clone(),
+            CachedModel {
+                stamp,
+                dependencies,
+                json_hash,
+                buffers: fingerprints,
+                decoded_bounds,
+                data_scans,
+                analysis: analysis.clone(),
+            },
+        );
+
+        Ok(AnalysisUpdate {
+            path: path.to_string_lossy().to_string(),
+            analysis,
+            mode: if previous.is_some() {
+                AnalysisMode::Incremental
+            } else {
+                AnalysisMode::Full
+            },
+            json_changed,
+            changed_buffers,
+            reused_buffers,
+            decoded_accessors,
+            issues,
+        })
+    }
+
+    /// Forget the analysis for a path, e.g. after it was deleted
+    pub fn invalidate(&self, path: &Path) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        entries.remove(&key).is_some()
+    }
+
+    /// Cached models that load external buffers from `path`
+    pub fn dependents(&self, path: &Path) -> Vec<PathBuf> {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, model)| {
+                model.dependencies.iter().any(|(dependency, _)| {
+                    fs::canonicalize(dependency).ok().as_ref() == Some(&path)
+                })
+            })
+            .map(|(model_path, _)| model_path.clone())
+            .collect()
+    }
+}
+
+/// A model file opened with its JSON and BIN chunks located
+pub(crate) struct ModelSource {
+    mmap: FileBytes,
+    json: Range<usize>,
+    bin: Option<Range<usize>>,
+}
+
+impl ModelSource {
+    /// Open the file and locate its chunks; on desktop the file is mapped,
+    /// so the binary data isn't read
+    pub(crate) fn open(path: &Path) -> Result<Self, String> {
+        let mmap = FileBytes::open(path)?;
+
+        if !mmap.starts_with(GLB_MAGIC) {
+            let json = 0..mmap.len();
+            return Ok(Self {
+                mmap,
+                json,
+                bin: None,
+            });
+        }
+
+        let read_u32 = |offset: usize| {
+            mmap.get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+
+        let (json_length, json_type) = read_u32(12)
+            .zip(read_u32(16))
+            .ok_or("GLB header is truncated")?;
+        if json_type != CHUNK_JSON {
+            return Err("GLB does not start with a JSON chunk".to_string());
+        }
+        let json = 20..20 + json_length as usize;
+        if json.end > mmap.len() {
+            return Err("GLB JSON chunk is truncated".to_string());
+        }
+
+        let bin = match read_u32(json.end).zip(read_u32(json.end + 4)) {
+            Some((length, CHUNK_BIN)) => {
+                let start = json.end + 8;
+                Some(start..(start + length as usize).min(mmap.len()))
+            }
+            _ => None,
+        };
+
+        Ok(Self { mmap, json, bin })
+    }
+
+    pub(crate) fn json(&self) -> &[u8] {
+        &self.mmap[self.json.clone()]
+    }
+
+    pub(crate) fn bin(&self) -> Option<&[u8]> {
+        self.bin.clone().map(|range| &self.mmap[range])
+    }
+}
+
+/// Validate like `gltf::Document::from_json`, except that position accessors
+/// may omit min/max; their bounds are decoded instead
+pub(crate) fn validate_document(root: gltf::json::Root) -> Result<gltf::Document, String> {
+    use gltf::json::validation::{Error, Validate};
+
+    let mut errors = Vec::new();
+    root.validate(&root, gltf::json::Path::new, &mut |path, error| {
+        let path = path();
+        let missing_bounds = error == Error::Missing
+            && (path.as_str().ends_with("[\"POSITION\"].min")
+                || path.as_str().ends_with("[\"POSITION\"].max"));
+        if !missing_bounds {
+            errors.push((path, error));
+        }
+    });
+
+    if errors.is_empty() {
+        Ok(gltf::Document::from_json_without_validation(root))
+    } else {
+        Err(format!(
+            "Failed to parse GLTF: {}",
+            gltf::Error::Validation(errors)
+        ))
+    }
+}
+
+/// Check declared buffer and view sizes against the actual data
+fn validate_buffers(
+    document: &gltf::Document,
+    buffer_data: &[Option<&[u8]>],
+    buffers: &HashSet<usize>,
+) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    for buffer in document.buffers().filter(|b| buffers.contains(&b.index())) {
+        let embedded = matches!(buffer.source(), Source::Uri(uri) if uri.starts_with("data:"));
+        match buffer_data[buffer.index()] {
+            Some(bytes) if bytes.len() < buffer.length() => issues.push(format!(
+                "Buffer {} is {} bytes but declares {}",
+                buffer.index(),
+                bytes.len(),
+                buffer.length()
+            )),
+            None if !embedded => {
+                issues.push(format!("Buffer {} could not be read", buffer.index()))
+            }
+            _ => {}
+        }
+    }
+
+    for view in document.views() {
+        let buffer = view.buffer().index();
+        if !buffers.contains(&buffer) {
+            continue;
+        }
+        let Some(bytes) = buffer_data[buffer] else {
+            continue;
+        };
+        let end = view.offset() + view.length();
+        if end > bytes.len() {
+            issues.push(format!(
+                "Buffer view {} ends at byte {} past the end of buffer {} ({} bytes)",
+                view.index(),
+                end,
+                buffer,
+                bytes.len()
+            ));
+        }
+    }
+
+    issues
+}
+
+fn position_accessors_without_bounds(document: &gltf::Document) -> Vec<gltf::Accessor<'_>> {
+    let mut seen = HashSet::new();
+    document
+        .meshes()
+        .flat_map(|mesh| mesh.primitives().collect::<Vec<_>>())
+        .filter_map(|primitive| primitive.get(&gltf::Semantic::Positions))
+        .filter(|accessor| accessor.min().is_none() || accessor.max().is_none())
+        .filter(|accessor| seen.insert(accessor.index()))
+        .collect()
+}
+
+/// Fingerprint of an accessor's definition and the data it reads
+///
+/// `None` if any referenced buffer can't be fingerprinted.
+fn accessor_key(
+    root: &gltf::json::Root,
+    index: usize,
+    fingerprints: &[Option<BufferFingerprint>],
+) -> Option<u64> {
+    let accessor = root.accessors.get(index)?;
+    let mut bytes = serde_json::to_vec(accessor).ok()?;
+
+    let sparse_views = accessor
+        .sparse
+        .iter()
+        .flat_map(|s| [s.indices.buffer_view, s.values.buffer_view]);
+    for view_index in accessor.buffer_view.into_iter().chain(sparse_views) {
+        let view = root.buffer_views.get(view_index.value())?;
+        bytes.extend(serde_json::to_vec(view).ok()?);
+        let fingerprint = fingerprints.get(view.buffer.value()).copied().flatten()?;
+        bytes.extend(fingerprint.hash.to_le_bytes());
+    }
+
+    Some(xxh3_64(&bytes))
+}
+
+fn decode_bounds(accessor: &gltf::Accessor, buffer_data: &[Option<&[u8]>]) -> Option<BoundingBox> {
+    let positions = gltf::accessor::Iter::<[f32; 3]>::new(accessor.clone(), |buffer| {
+        buffer_data.get(buffer.index()).copied().flatten()
+    })?;
+
+    let mut bounds = BoundingBox::new();
+    for position in positions {
+        bounds.expand(position);
+    }
+    bounds.is_valid().then_some(bounds)
+}
+
+/// Fingerprint of a primitive's position and index accessors
+fn primitive_key(
+    root: &gltf::json::Root,
+    primitive: &gltf::Primitive,
+    fingerprints: &[Option<BufferFingerprint>],
+) -> Option<u64> {
+    let positions = primitive.get(&gltf::Semantic::Positions)?;
+    let mut bytes = accessor_key(root, positions.index(), fingerprints)?
+        .to_le_bytes()
+        .to_vec();
+    if let Some(indices) = primitive.indices() {
+        bytes.extend(accessor_key(root, indices.index(), fingerprints)?.to_le_bytes());
+    }
+    Some(xxh3_64(&bytes))
+}
+
+/// NaN, infinite and absurd positions, and indices past the vertex count
+fn scan_primitive(
+    primitive: &gltf::Primitive,
+    buffer_data: &[Option<&[u8]>],
+) -> Option<DataIssues> {
+    let reader = primitive.reader(|buffer| buffer_data.get(buffer.index()).copied().flatten());
+    let positions = reader.read_positions()?;
+    let vertex_count = positions.len();
+    let (non_finite_vertex_count, absurd_vertex_count) =
+        sanitize::scan_positions(positions, MAX_COORDINATE);
+    let out_of_range_index_count = reader.read_indices().map_or(0, |indices| {
+        sanitize::scan_indices(indices.into_u32(), vertex_count)
+    });
+    Some(DataIssues {
+        non_finite_vertex_count,
+        absurd_vertex_count,
+        out_of_range_index_count,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_glb(path: &Path, material_name: &str, z: f32) {
+        let positions: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, z]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let json = format!(
+            r#"{{"asset":{{"version":"2.0"}},
+            "buffers":[{{"byteLength":36}}],
+            "bufferViews":[{{"buffer":0,"byteLength":36}}],
+            "accessors":[{{"bufferView":0,"componentType":5126,"count":3,"type":"VEC3"}}],
+            "materials":[{{"name":"{}"}}],
+            "meshes":[{{"primitives":[{{"attributes":{{"POSITION":0}},"material":0}}]}}]}}"#,
+            material_name
+        );
+        let mut json = json.into_bytes();
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+
+        let mut glb = Vec::new();
+        glb.extend(GLB_MAGIC);
+        glb.extend(2u32.to_le_bytes());
+        glb.extend(((12 + 8 + json.len() + 8 + positions.len()) as u32).to_le_bytes());
+        glb.extend((json.len() as u32).to_le_bytes());
+        glb.extend(CHUNK_JSON.to_le_bytes());
+        glb.extend(&json);
+        glb.extend((positions.len() as u32).to_le_bytes());
+        glb.extend(CHUNK_BIN.to_le_bytes());
+        glb.extend(&positions);
+        fs::write(path, glb).unwrap();
+    }
+
+    #[test]
+    fn test_incremental_reuses_unchanged_buffers() {
+        let dir = std::env::temp_dir().join(format!("sweedle-analysis-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.glb");
+        let cache = AnalysisCache::default();
+
+        write_glb(&path, "a", 0.0);
+        let full = cache.analyze(&path).unwrap();
+        assert_eq!(full.mode, AnalysisMode::Full);
+        assert_eq!(full.decoded_accessors, 1);
+        assert_eq!(full.analysis.bounding_box.max, [1.0, 2.0, 0.0]);
+        assert_eq!(cache.analyze(&path).unwrap().mode, AnalysisMode::Cached);
+
+        // Material edit: JSON changes, geometry is reused
+        write_glb(&path, "renamed", 0.0);
+        let edited = cache.analyze(&path).unwrap();
+        assert!(edited.json_changed);
+        assert_eq!(edited.reused_buffers, vec![0]);
+        assert_eq!(edited.decoded_accessors, 0);
+
+        // Geometry edit: the buffer is re-hashed and decoded
+        write_glb(&path, "renamed", 3.0);
+        let moved = cache.analyze(&path).unwrap();
+        assert!(!moved.json_changed);
+        assert_eq!(moved.changed_buffers, vec![0]);
+        assert_eq!(moved.analysis.bounding_box.max, [1.0, 2.0, 3.0]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_percent_encoded_buffer_uris_are_fingerprinted() {
+        let dir = std::env::temp_dir().join(format!("sweedle-analysis-uri-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.gltf");
+        let write_buffer = |z: f32| {
+            let positions: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, z]
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect();
+            fs::write(dir.join("my buffer.bin"), positions).unwrap();
+        };
+        fs::write(
+            &path,
+            r#"{"asset":{"version":"2.0"},
+            "buffers":[{"byteLength":36,"uri":"my%20buffer.bin"}],
+            "bufferViews":[{"buffer":0,"byteLength":36}],
+            "accessors":[{"bufferView":0,"componentType":5126,"count":3,"type":"VEC3"}],
+            "meshes":[{"primitives":[{"attributes":{"POSITION":0}}]}]}"#,
+        )
+        .unwrap();
+        let cache = AnalysisCache::default();
+
+        write_buffer(0.0);
+        let full = cache.analyze(&path).unwrap();
+        assert!(full.issues.is_empty(), "{:?}", full.issues);
+        assert_eq!(full.analysis.bounding_box.max, [1.0, 2.0, 0.0]);
+
+        // Only the buffer changes, so the fingerprint is what notices
+        write_buffer(3.0);
+        let moved = cache.analyze(&path).unwrap();
+        assert_eq!(moved.changed_buffers, vec![0]);
+        assert_eq!(moved.analysis.bounding_box.max, [1.0, 2.0, 3.0]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,515 @@
+//! glTF animation clips: reading, sampling, trimming and splitting
+//!
+//! Clips are decoded into tracks of keyframe times and values, edited, and
+//! written back as fresh accessors. Trimming keeps the keys inside the range
+//! and adds keys sampled at its ends, shifted so the clip starts at zero;
+//! cubic spline tracks get the curve's tangent at the new ends, so the
+//! motion inside the range is unchanged.
+
+use crate::utils::gltf_document::{items_mut, GltfDocument};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Interpolation {
+    #[serde(rename = "LINEAR")]
+    Linear,
+    #[serde(rename = "STEP")]
+    Step,
+    #[serde(rename = "CUBICSPLINE")]
+    CubicSpline,
+}
+
+/// Keyframes of one animated property of one node
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub node: usize,
+    /// `translation`, `rotation`, `scale` or `weights`
+    pub path: String,
+    pub interpolation: Interpolation,
+    pub times: Vec<f32>,
+    /// `width` numbers per key, or per in-tangent, value and out-tangent
+    /// for cubic splines
+    pub values: Vec<f32>,
+    pub width: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clip {
+    pub name: Option<String>,
+    pub tracks: Vec<Track>,
+    pub extras: Option<Value>,
+}
+
+/// A named point in a clip, where `split` starts a new one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationMarker {
+    pub name: String,
+    pub time: f32,
+}
+
+/// What `list` reports about each clip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipInfo {
+    pub index: usize,
+    pub name: Option<String>,
+    pub start: f32,
+    pub end: f32,
+    pub track_count: usize,
+    /// Markers from the clip's `extras.markers`
+    pub markers: Vec<AnimationMarker>,
+}
+
+impl Track {
+    pub fn start(&self) -> f32 {
+        self.times.first().copied().unwrap_or(0.0)
+    }
+
+    pub fn end(&self) -> f32 {
+        self.times.last().copied().unwrap_or(0.0)
+    }
+
+    /// Values per key: three blocks for cubic splines
+    fn stride(&self) -> usize {
+        match self.interpolation {
+            Interpolation::CubicSpline => 3 * self.width,
+            _ => self.width,
+        }
+    }
+
+    /// The value at `time`, held at the first and last keys outside them
+    pub fn sample(&self, time: f32) -> Vec<f32> {
+        self.sample_with_slope(time).0
+    }
+
+    /// The value at `time` and, for cubic splines, its rate of change
+    fn sample_with_slope(&self, time: f32) -> (Vec<f32>, Vec<f32>) {
+        let (w, stride) = (self.width, self.stride());
+        let value_at = |key: usize| {
+            let start = key * stride + if stride > w { w } else { 0 };
+            self.values[start..start + w].to_vec()
+        };
+        let flat = vec![0.0; w];
+        if self.times.is_empty() {
+            return (flat.clone(), flat);
+        }
+        let last = self.times.len() - 1;
+        if time <= self.times[0] {
+            return (value_at(0), flat);
+        }
+        if time >= self.times[last] {
+            return (value_at(last), flat);
+        }
+        let next = self.times.partition_point(|&t| t <= time);
+        let key = next - 1;
+        let (t0, t1) = (self.times[key], self.times[next]);
+        let span = (t1 - t0).max(f32::EPSILON);
+        let s = (time - t0) / span;
+        let (a, b) = (value_at(key), value_at(next));
+
+        match self.interpolation {
+            Interpolation::Step => (a, flat),
+            Interpolation::Linear if self.path == "rotation" => (slerp(&a, &b, s), flat),
+            Interpolation::Linear => (
+                a.iter().zip(&b).map(|(a, b)| a + (b - a) * s).collect(),
+                flat,
+            ),
+            Interpolation::CubicSpline => {
+                let out_tangent = &self.values[key * stride + 2 * w..key * stride + 3 * w];
+                let in_tangent = &self.values[next * stride..next * stride + w];
+                let (s2, s3) = (s * s, s * s * s);
+                let value = (0..w)
+                    .map(|i| {
+                        (2.0 * s3 - 3.0 * s2 + 1.0) * a[i]
+                            + (s3 - 2.0 * s2 + s) * span * out_tangent[i]
+                            + (-2.0 * s3 + 3.0 * s2) * b[i]
+                            + (s3 - s2) * span * in_tangent[i]
+                    })
+                    .collect();
+                let slope = (0..w)
+                    .map(|i| {
+                        ((6.0 * s2 - 6.0 * s) * a[i]
+                            + (3.0 * s2 - 4.0 * s + 1.0) * span * out_tangent[i]
+                            + (-6.0 * s2 + 6.0 * s) * b[i]
+                            + (3.0 * s2 - 2.0 * s) * span * in_tangent[i])
+                            / span
+                    })
+                    .collect();
+                (value, slope)
+            }
+        }
+    }
+
+    /// The keys from `start` to `end`, with sampled keys at both ends, on
+    /// a timeline starting at zero
+    pub fn trim(&self, start: f32, end: f32) -> Track {
+        let stride = self.stride();
+        let mut times = Vec::new();
+        let mut values = Vec::new();
+        let push_sampled = |times: &mut Vec<f32>, values: &mut Vec<f32>, time: f32| {
+            let (value, slope) = self.sample_with_slope(time);
+            times.push(time - start);
+            if self.interpolation == Interpolation::CubicSpline {
+                values.extend(&slope);
+                values.extend(&value);
+                values.extend(&slope);
+            } else {
+                values.extend(&value);
+            }
+        };
+
+        if !self.times.contains(&start) {
+            push_sampled(&mut times, &mut values, start);
+        }
+        for (key, &time) in self.times.iter().enumerate() {
+            if time >= start && time <= end {
+                times.push(time - start);
+                values.extend(&self.values[key * stride..(key + 1) * stride]);
+            }
+        }
+        if end > start && !self.times.contains(&end) {
+            push_sampled(&mut times, &mut values, end);
+        }
+        Track {
+            times,
+            values,
+            ..self.clone()
+        }
+    }
+}
+
+impl Clip {
+    pub fn start(&self) -> f32 {
+        self.tracks
+            .iter()
+            .map(Track::start)
+            .fold(f32::MAX, f32::min)
+            .min(self.end())
+    }
+
+    pub fn end(&self) -> f32 {
+        self.tracks.iter().map(Track::end).fold(0.0, f32::max)
+    }
+
+    /// The clip between `start` and `end` seconds, starting at zero
+    pub fn trim(&self, start: f32, end: f32) -> Result<Clip, String> {
+        if !(start.is_finite() && end.is_finite()) || end < start {
+            return Err(format!("Invalid time range {} to {}", start, end));
+        }
+        Ok(Clip {
+            name: self.name.clone(),
+            tracks: self.tracks.iter().map(|t| t.trim(start, end)).collect(),
+            extras: None,
+        })
+    }
+
+    /// Markers recorded in the clip's `extras.markers` as `{name, time}`
+    pub fn markers(&self) -> Vec<AnimationMarker> {
+        let markers = self.extras.as_ref().and_then(|e| e.get("markers")).cloned();
+        let mut markers: Vec<AnimationMarker> = markers
+            .and_then(|m| serde_json::from_value(m).ok())
+            .unwrap_or_default();
+        markers.sort_by(|a, b| a.time.total_cmp(&b.time));
+        markers
+    }
+
+    /// One clip per marker, running to the next marker or the end, named
+    /// after its marker
+    pub fn split(&self, markers: &[AnimationMarker]) -> Result<Vec<Clip>, String> {
+        let mut markers = markers.to_vec();
+        markers.sort_by(|a, b| a.time.total_cmp(&b.time));
+        if markers.is_empty() {
+            return Err("No markers to split the animation at".to_string());
+        }
+        let end = self.end();
+        markers
+            .iter()
+            .enumerate()
+            .map(|(i, marker)| {
+                let until = markers.get(i + 1).map_or(end, |next| next.time);
+                if marker.time >= until {
+                    return Err(format!(
+                        "Marker {} has no frames before the next",
+                        marker.name
+                    ));
+                }
+                let mut clip = self.trim(marker.time, until)?;
+                clip.name = Some(marker.name.clone());
+                Ok(clip)
+            })
+            .collect()
+    }
+
+    fn info(&self, index: usize) -> ClipInfo {
+        ClipInfo {
+            index,
+            name: self.name.clone(),
+            start: self.start(),
+            end: self.end(),
+            track_count: self.tracks.len(),
+            markers: self.markers(),
+        }
+    }
+}
+
+/// Every clip in the document
+pub fn read_clips(document: &GltfDocument) -> Result<Vec<Clip>, String> {
+    let animations = document.json["animations"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    animations
+        .iter()
+        .enumerate()
+        .map(|(index, animation)| {
+            let samplers = animation["samplers"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+            let mut tracks = Vec::new();
+            for channel in animation["channels"].as_array().into_iter().flatten() {
+                let Some(node) = channel["target"]["node"].as_u64() else {
+                    continue;
+                };
+                let path = channel["target"]["path"].as_str().unwrap_or_default();
+                let sampler = channel["sampler"]
+                    .as_u64()
+                    .and_then(|s| samplers.get(s as usize))
+                    .ok_or_else(|| {
+                        format!("Animation {} has a channel without a sampler", index)
+                    })?;
+                let interpolation = serde_json::from_value(sampler["interpolation"].clone())
+                    .unwrap_or(Interpolation::Linear);
+                let accessor = |key: &str| {
+                    sampler[key]
+                        .as_u64()
+                        .map(|a| a as usize)
+                        .ok_or_else(|| format!("Animation {} has a sampler without {}", index, key))
+                };
+                let times = document.read_floats(accessor("input")?)?.values;
+                let output = document.read_floats(accessor("output")?)?;
+                let blocks = if interpolation == Interpolation::CubicSpline {
+                    3
+                } else {
+                    1
+                };
+                let keys = times.len().max(1);
+                let width = output.values.len() / keys / blocks;
+                if width == 0 || width * keys * blocks != output.values.len() {
+                    return Err(format!(
+                        "Animation {} has a sampler with mismatched keys",
+                        index
+                    ));
+                }
+                tracks.push(Track {
+                    node: node as usize,
+                    path: path.to_string(),
+                    interpolation,
+                    times,
+                    values: output.values,
+                    width,
+                });
+            }
+            Ok(Clip {
+                name: animation["name"].as_str().map(str::to_string),
+                tracks,
+                extras: animation.get("extras").cloned(),
+            })
+        })
+        .collect()
+}
+
+/// Replace the document's animations with `clips` and drop the data the
+/// old ones left behind
+pub fn write_clips(document: &mut GltfDocument, clips: &[Clip]) {
+    if let Some(object) = document.json.as_object_mut() {
+        object.remove("animations");
+    }
+    document.compact();
+
+    let mut animations = Vec::with_capacity(clips.len());
+    for clip in clips {
+        let mut samplers = Vec::new();
+        let mut channels = Vec::new();
+        for track in clip.tracks.iter().filter(|t| !t.times.is_empty()) {
+            let input = document.push_floats(&track.times, "SCALAR", true);
+            let kind = match track.width {
+                3 => "VEC3",
+                4 if track.path != "weights" => "VEC4",
+                _ => "SCALAR",
+            };
+            let output = document.push_floats(&track.values, kind, false);
+            channels.push(json!({
+                "sampler": samplers.len(),
+                "target": { "node": track.node, "path": track.path },
+            }));
+            samplers.push(json!({
+                "input": input,
+                "output": output,
+                "interpolation": track.interpolation,
+            }));
+        }
+        let mut animation = json!({ "channels": channels, "samplers": samplers });
+        if let Some(name) = &clip.name {
+            animation["name"] = json!(name);
+        }
+        if let Some(extras) = &clip.extras {
+            animation["extras"] = extras.clone();
+        }
+        animations.push(animation);
+    }
+    if !animations.is_empty() {
+        document.json["animations"] = Value::Array(animations);
+    }
+}
+
+/// Summaries of the clips, in document order
+pub fn list(clips: &[Clip]) -> Vec<ClipInfo> {
+    clips
+        .iter()
+        .enumerate()
+        .map(|(i, clip)| clip.info(i))
+        .collect()
+}
+
+/// Retarget `clip` from a document with nodes named `from` to one with
+/// nodes named `to`, by name; tracks on nodes with no match are returned
+/// as dropped node names
+pub fn retarget(
+    clip: &Clip,
+    from: &[Option<String>],
+    to: &[Option<String>],
+) -> (Clip, Vec<String>) {
+    let mut dropped = Vec::new();
+    let tracks = clip
+        .tracks
+        .iter()
+        .filter_map(|track| {
+            let name = from.get(track.node).cloned().flatten();
+            let target = name
+                .as_ref()
+                .and_then(|name| to.iter().position(|n| n.as_ref() == Some(name)));
+            if target.is_none() {
+                let name = name.unwrap_or_else(|| format!("node {}", track.node));
+                if !dropped.contains(&name) {
+                    dropped.push(name);
+                }
+            }
+            Some(Track {
+                node: target?,
+                ..track.clone()
+            })
+        })
+        .collect();
+    (
+        Clip {
+            tracks,
+            ..clip.clone()
+        },
+        dropped,
+    )
+}
+
+/// Names of the document's nodes, by index
+pub fn node_names(document: &mut GltfDocument) -> Vec<Option<String>> {
+    items_mut(&mut document.json, "nodes")
+        .map(|node| node["name"].as_str().map(str::to_string))
+        .collect()
+}
+
+/// Spherical interpolation between unit quaternions, the short way round
+fn slerp(a: &[f32], b: &[f32], s: f32) -> Vec<f32> {
+    let mut dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let b: Vec<f32> = if dot < 0.0 {
+        dot = -dot;
+        b.iter().map(|v| -v).collect()
+    } else {
+        b.to_vec()
+    };
+    let (wa, wb) = if dot > 0.9995 {
+        (1.0 - s, s)
+    } else {
+        let theta = dot.clamp(-1.0, 1.0).acos();
+        let sin = theta.sin();
+        (((1.0 - s) * theta).sin() / sin, (s * theta).sin() / sin)
+    };
+    let mixed: Vec<f32> = a.iter().zip(&b).map(|(a, b)| a * wa + b * wb).collect();
+    let length = mixed
+        .iter()
+        .map(|v| v * v)
+        .sum::<f32>()
+        .sqrt()
+        .max(f32::EPSILON);
+    mixed.iter().map(|v| v / length).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document_with_clip() -> GltfDocument {
+        let mut document = GltfDocument {
+            json: json!({ "asset": { "version": "2.0" }, "nodes": [{ "name": "Hips" }] }),
+            bin: Vec::new(),
+        };
+        let clip = Clip {
+            name: Some("Walk".to_string()),
+            tracks: vec![
+                Track {
+                    node: 0,
+                    path: "translation".to_string(),
+                    interpolation: Interpolation::Linear,
+                    times: vec![0.0, 1.0, 2.0],
+                    values: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 3.0, 0.0, 0.0],
+                    width: 3,
+                },
+                Track {
+                    node: 0,
+                    path: "scale".to_string(),
+                    interpolation: Interpolation::CubicSpline,
+                    // Tangent 1 per second at both keys: a straight line
+                    times: vec![0.0, 2.0],
+                    values: vec![
+                        1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, //
+                        1.0, 1.0, 1.0, 3.0, 3.0, 3.0, 1.0, 1.0, 1.0,
+                    ],
+                    width: 3,
+                },
+            ],
+            extras: Some(json!({ "markers": [
+                { "name": "Loop", "time": 0.5 },
+                { "name": "Stop", "time": 1.5 },
+            ] })),
+        };
+        write_clips(&mut document, &[clip]);
+        document
+    }
+
+    #[test]
+    fn test_clips_trim_split_and_round_trip() {
+        let document = document_with_clip();
+        let clips = read_clips(&document).unwrap();
+        assert_eq!(clips.len(), 1);
+        let clip = &clips[0];
+        assert_eq!(clip.end(), 2.0);
+        assert_eq!(clip.tracks[0].sample(1.5), vec![2.0, 0.0, 0.0]);
+        let scale = clip.tracks[1].sample(0.5)[0];
+        assert!((scale - 1.5).abs() < 1e-5, "{}", scale);
+
+        let trimmed = clip.trim(0.5, 1.5).unwrap();
+        let translation = &trimmed.tracks[0];
+        assert_eq!(translation.times, vec![0.0, 0.5, 1.0]);
+        assert_eq!(translation.sample(0.0), vec![0.5, 0.0, 0.0]);
+        assert_eq!(translation.sample(1.0), vec![2.0, 0.0, 0.0]);
+        let scale = &trimmed.tracks[1];
+        assert_eq!(scale.times, vec![0.0, 1.0]);
+        assert!((scale.sample(0.5)[1] - 2.0).abs() < 1e-5);
+
+        let parts = clip.split(&clip.markers()).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name.as_deref(), Some("Loop"));
+        assert_eq!(parts[0].end(), 1.0);
+        assert_eq!(parts[1].end(), 0.5);
+        assert!(clip.split(&[]).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! The versioned description of the RPC API
+//!
+//! Tools integrating with Sweedle, such as Blender or Unreal plugins, call
+//! `api.describe` to learn the methods of the API version they talk to:
+//! each method's parameters as a JSON Schema and the name of what it
+//! returns, as named in the frontend's command bindings. Within a version
+//! methods only gain optional parameters; anything else waits for the next
+//! version, so a plugin written against `/v1/rpc` keeps working.
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// Version of the API served under `/v<N>/rpc`
+pub const API_VERSION: u32 = 1;
+
+/// One parameter of a method, taken by name
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: &'static str,
+    pub schema: Value,
+    pub required: bool,
+}
+
+/// What a method takes and returns
+#[derive(Debug, Clone)]
+pub struct MethodSpec {
+    pub name: &'static str,
+    pub summary: &'static str,
+    pub params: Vec<Param>,
+    /// Name of the type returned
+    pub result: &'static str,
+}
+
+impl MethodSpec {
+    pub fn new(name: &'static str, summary: &'static str, result: &'static str) -> Self {
+        Self {
+            name,
+            summary,
+            params: Vec::new(),
+            result,
+        }
+    }
+
+    pub fn param(mut self, name: &'static str, schema: Value) -> Self {
+        self.params.push(Param {
+            name,
+            schema,
+            required: true,
+        });
+        self
+    }
+
+    pub fn optional(mut self, name: &'static str, schema: Value) -> Self {
+        self.params.push(Param {
+            name,
+            schema,
+            required: false,
+        });
+        self
+    }
+
+    /// Fail on parameters the method doesn't take or required ones missing,
+    /// so a misspelled name isn't silently ignored
+    pub fn check(&self, params: &Map<String, Value>) -> Result<(), String> {
+        if let Some(unknown) = params
+            .keys()
+            .find(|key| !self.params.iter().any(|p| p.name == key.as_str()))
+        {
+            return Err(format!("{} takes no parameter {}", self.name, unknown));
+        }
+        match self
+            .params
+            .iter()
+            .find(|p| p.required && params.get(p.name).is_none_or(Value::is_null))
+        {
+            Some(missing) => Err(format!(
+                "{} needs the parameter {}",
+                self.name, missing.name
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let properties: Map<String, Value> = self
+            .params
+            .iter()
+            .map(|p| (p.name.to_string(), p.schema.clone()))
+            .collect();
+        let required: Vec<&str> = self
+            .params
+            .iter()
+            .filter(|p| p.required)
+            .map(|p| p.name)
+            .collect();
+        json!({
+            "name": self.name,
+            "summary": self.summary,
+            "params": {
+                "type": "object",
+                "properties": properties,
+                "required": required,
+                "additionalProperties": false,
+            },
+            "result": { "title": self.result },
+        })
+    }
+}
+
+/// The document `api.describe` answers with
+pub fn describe(methods: &[MethodSpec]) -> Value {
+    json!({
+        "api_version": API_VERSION,
+        "methods": methods.iter().map(MethodSpec::to_json).collect::<Vec<_>>(),
+    })
+}
+
+pub fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+pub fn boolean() -> Value {
+    json!({ "type": "boolean" })
+}
+
+pub fn strings() -> Value {
+    json!({ "type": "array", "items": string() })
+}
+
+/// One of the values of a unit enum, as it's serialized
+pub fn choice<T: Serialize>(values: &[T]) -> Value {
+    let values: Vec<Value> = values
+        .iter()
+        .filter_map(|value| serde_json::to_value(value).ok())
+        .collect();
+    json!({ "enum": values })
+}
+
+/// An object of the type named `title`
+pub fn object(title: &str) -> Value {
+    json!({ "type": "object", "title": title })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::engine_export::Engine;
+
+    #[test]
+    fn test_describe_and_check_params() {
+        let spec = MethodSpec::new("export_for_engine", "Copy models", "EngineExport")
+            .param("asset_ids", strings())
+            .param("engine", choice(&[Engine::Unity, Engine::Unreal]))
+            .optional("dry_run", boolean());
+        let document = describe(std::slice::from_ref(&spec));
+        assert_eq!(document["api_version"], json!(API_VERSION));
+        let method = &document["methods"][0];
+        assert_eq!(method["params"]["required"], json!(["asset_ids", "engine"]));
+        assert_eq!(
+            method["params"]["properties"]["engine"]["enum"],
+            json!(["unity", "unreal"])
+        );
+        assert_eq!(method["result"]["title"], json!("EngineExport"));
+
+        let params = |value: Value| value.as_object().cloned().unwrap();
+        assert!(spec
+            .check(&params(json!({ "asset_ids": [], "engine": "unity" })))
+            .is_ok());
+        let missing = spec.check(&params(json!({ "asset_ids": [], "engine": null })));
+        assert!(missing.unwrap_err().contains("engine"));
+        let misspelled = spec.check(&params(json!({
+            "asset_ids": [],
+            "engine": "unity",
+            "dryrun": true,
+        })));
+        assert!(misspelled.unwrap_err().contains("dryrun"));
+    }
+}
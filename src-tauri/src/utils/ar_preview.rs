@@ -0,0 +1,326 @@
+//! Serving one model over the local network for viewing on a phone
+//!
+//! The server answers only under a random token, so other devices on the
+//! network can't guess the address, and only with the page and the model's
+//! GLB and USDZ files. The page uses `<model-viewer>`, which opens Scene
+//! Viewer on Android, Quick Look on iOS (from the USDZ) or WebXR.
+
+use crate::utils::web_viewer::escape;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+/// Largest request head read before giving up on a client
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// The model files served for an asset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreviewFiles {
+    pub title: String,
+    pub glb: Option<PathBuf>,
+    pub usdz: Option<PathBuf>,
+}
+
+impl PreviewFiles {
+    /// A `.glb` or `.usdz` model, with the other format if a file of the
+    /// same name sits beside it
+    pub fn for_model(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if extension != "glb" && extension != "usdz" {
+            return Err(format!(
+                "AR previews need a GLB or USDZ model, not {}",
+                path.display()
+            ));
+        }
+        let sibling = |extension: &str| {
+            let sibling = path.with_extension(extension);
+            sibling.is_file().then_some(sibling)
+        };
+        Ok(Self {
+            title: path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            glb: sibling("glb"),
+            usdz: sibling("usdz"),
+        })
+    }
+}
+
+/// Where a running preview can be reached
+#[derive(Debug, Clone)]
+pub struct PreviewAddress {
+    pub address: SocketAddr,
+    pub url: String,
+}
+
+struct Running {
+    url: String,
+    stop: oneshot::Sender<()>,
+}
+
+/// The preview server, managed as Tauri state; at most one runs at a time
+#[derive(Default)]
+pub struct ArPreviewServer {
+    running: Arc<Mutex<Option<Running>>>,
+}
+
+impl ArPreviewServer {
+    /// Serve `files` on the LAN until `stop` or until `expires` has passed,
+    /// replacing any preview already running
+    ///
+    /// `on_stop` is called with the URL when the server shuts down.
+    pub async fn start(
+        &self,
+        files: PreviewFiles,
+        expires: Duration,
+        on_stop: impl FnOnce(String) + Send + 'static,
+    ) -> Result<PreviewAddress, String> {
+        self.stop();
+        let ip = lan_address()?;
+        let listener = TcpListener::bind(SocketAddr::new(ip, 0))
+            .await
+            .map_err(|e| format!("Failed to listen on {}: {}", ip, e))?;
+        let address = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to read the preview address: {}", e))?;
+        let token = token()?;
+        let url = format!("http://{}/{}/", address, token);
+
+        let (stop, stopped) = oneshot::channel();
+        *self.running.lock().unwrap() = Some(Running {
+            url: url.clone(),
+            stop,
+        });
+        let (served_url, running) = (url.clone(), self.running.clone());
+        tokio::spawn(async move {
+            serve(listener, &token, files, expires, stopped).await;
+            let mut running = running.lock().unwrap();
+            if running.as_ref().is_some_and(|r| r.url == served_url) {
+                *running = None;
+            }
+            drop(running);
+            on_stop(served_url);
+        });
+        Ok(PreviewAddress { address, url })
+    }
+
+    /// Stop the running preview; `false` if none was running
+    pub fn stop(&self) -> bool {
+        match self.running.lock().unwrap().take() {
+            Some(running) => {
+                let _ = running.stop.send(());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn url(&self) -> Option<String> {
+        self.running.lock().unwrap().as_ref().map(|r| r.url.clone())
+    }
+}
+
+async fn serve(
+    listener: TcpListener,
+    token: &str,
+    files: PreviewFiles,
+    expires: Duration,
+    mut stopped: oneshot::Receiver<()>,
+) {
+    let deadline = tokio::time::sleep(expires);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            _ = &mut stopped => break,
+            _ = &mut deadline => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let (token, files) = (token.to_string(), files.clone());
+                    tokio::spawn(async move {
+                        if let Err(e) = respond(stream, &token, &files).await {
+                            log::debug!("AR preview request failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => log::warn!("AR preview stopped accepting: {}", e),
+            },
+        }
+    }
+}
+
+async fn respond(mut stream: TcpStream, token: &str, files: &PreviewFiles) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < MAX_REQUEST_BYTES {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    let request_line = String::from_utf8_lossy(&head)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    let (status, content_type, body): (&str, &str, Vec<u8>) =
+        match route(&request_line, token, files) {
+            Route::Page => (
+                "200 OK",
+                "text/html; charset=utf-8",
+                page(files).into_bytes(),
+            ),
+            Route::File(path, content_type) => match tokio::fs::read(&path).await {
+                Ok(bytes) => ("200 OK", content_type, bytes),
+                Err(_) => ("404 Not Found", "text/plain", b"Not found".to_vec()),
+            },
+            Route::NotFound => ("404 Not Found", "text/plain", b"Not found".to_vec()),
+        };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    if !request_line.starts_with("HEAD ") {
+        stream.write_all(&body).await?;
+    }
+    stream.shutdown().await
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Route {
+    Page,
+    File(PathBuf, &'static str),
+    NotFound,
+}
+
+/// What a request line asks for; anything outside `/<token>/` is not found
+fn route(request_line: &str, token: &str, files: &PreviewFiles) -> Route {
+    let mut parts = request_line.split(' ');
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    if method != "GET" && method != "HEAD" {
+        return Route::NotFound;
+    }
+    let path = target.split('?').next().unwrap_or("");
+    let Some(rest) = path
+        .strip_prefix('/')
+        .and_then(|p| p.strip_prefix(token))
+        .and_then(|p| p.strip_prefix('/'))
+    else {
+        return Route::NotFound;
+    };
+    match (rest, &files.glb, &files.usdz) {
+        ("" | "index.html", _, _) => Route::Page,
+        ("model.glb", Some(glb), _) => Route::File(glb.clone(), "model/gltf-binary"),
+        ("model.usdz", _, Some(usdz)) => Route::File(usdz.clone(), "model/vnd.usdz+zip"),
+        _ => Route::NotFound,
+    }
+}
+
+/// The page a phone opens from the QR code
+fn page(files: &PreviewFiles) -> String {
+    let title = escape(&files.title);
+    let usdz = if files.usdz.is_some() {
+        " ios-src=\"model.usdz\""
+    } else {
+        ""
+    };
+    let viewer = if files.glb.is_some() {
+        format!(
+            "<model-viewer src=\"model.glb\"{usdz} alt=\"{title}\" ar \
+             ar-modes=\"webxr scene-viewer quick-look\" camera-controls \
+             touch-action=\"pan-y\" shadow-intensity=\"1\"></model-viewer>"
+        )
+    } else {
+        // Quick Look opens USDZ links marked rel="ar" on iOS
+        format!("<a rel=\"ar\" href=\"model.usdz\"><button>View {title} in AR</button></a>")
+    };
+    format!(
+        "<!doctype html>\n<html lang=\"en\"><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>{title}</title><script type=\"module\" \
+         src=\"https://ajax.googleapis.com/ajax/libs/model-viewer/3.5.0/model-viewer.min.js\">\
+         </script><style>html,body{{margin:0;height:100%;background:#1e1e22;color:#e8e8ec;\
+         font-family:system-ui,sans-serif}}model-viewer{{width:100%;height:100%}}\
+         a{{display:block;padding:40px;text-align:center}}</style></head>\
+         <body>{viewer}</body></html>\n"
+    )
+}
+
+/// The address this machine has on the network a default route leads to
+///
+/// Connecting a UDP socket picks the outgoing interface without sending
+/// anything.
+pub fn lan_address() -> Result<IpAddr, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .map_err(|e| format!("Failed to find the LAN address: {}", e))?;
+    socket
+        .connect("192.0.2.1:80")
+        .and_then(|_| socket.local_addr())
+        .map(|address| address.ip())
+        .ok()
+        .filter(|ip| !ip.is_loopback() && !ip.is_unspecified())
+        .ok_or_else(|| "Not connected to a network".to_string())
+}
+
+/// 128 bits from the operating system's secure random source, as hex
+pub fn token() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate a token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes_only_answer_under_token() {
+        let files = PreviewFiles {
+            title: "Rock <A>".to_string(),
+            glb: Some(PathBuf::from("/lib/rock.glb")),
+            usdz: None,
+        };
+        let secret = "abc123";
+        assert_eq!(route("GET /abc123/ HTTP/1.1", secret, &files), Route::Page);
+        assert_eq!(
+            route("HEAD /abc123/model.glb?v=2 HTTP/1.1", secret, &files),
+            Route::File(PathBuf::from("/lib/rock.glb"), "model/gltf-binary")
+        );
+        for request in [
+            "GET /abc123/model.usdz HTTP/1.1",
+            "GET /model.glb HTTP/1.1",
+            "GET /abc1234/ HTTP/1.1",
+            "GET /abc123/../rock.glb HTTP/1.1",
+            "POST /abc123/ HTTP/1.1",
+        ] {
+            assert_eq!(
+                route(request, secret, &files),
+                Route::NotFound,
+                "{}",
+                request
+            );
+        }
+
+        let html = page(&files);
+        assert!(html.contains("src=\"model.glb\""));
+        assert!(!html.contains("ios-src"));
+        assert!(html.contains("Rock &lt;A&gt;"));
+        let secret = token().unwrap();
+        assert_eq!(secret.len(), 32);
+        assert_ne!(secret, token().unwrap());
+    }
+}
@@ -0,0 +1,361 @@
+//! Managed cache of generated files
+//!
+//! Thumbnails, decimated previews and transcoded textures are cheap to throw
+//! away but slow enough to rebuild that doing it on every view shows. They
+//! share one folder with a size limit; when a write goes over it, the
+//! entries used least recently are evicted. Reading an entry bumps its
+//! modification time, so the order of use survives restarts.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::SystemTime;
+
+/// Size limit used until one is configured: 2 GiB
+pub const DEFAULT_MAX_BYTES: u64 = 2 << 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheKind {
+    /// Rendered model thumbnails
+    Thumbnail,
+    /// Decimated stand-ins sent before full-resolution geometry
+    Preview,
+    /// Textures converted to a format and size the viewer can display
+    Texture,
+}
+
+impl CacheKind {
+    pub const ALL: [CacheKind; 3] = [CacheKind::Thumbnail, CacheKind::Preview, CacheKind::Texture];
+
+    fn dir_name(self) -> &'static str {
+        match self {
+            CacheKind::Thumbnail => "thumbnails",
+            CacheKind::Preview => "previews",
+            CacheKind::Texture => "textures",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheSettings {
+    /// Total size the cache is trimmed to after each write
+    pub max_bytes: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheKindStats {
+    pub kind: CacheKind,
+    pub entry_count: usize,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// Folder holding the cache; empty if there is none
+    pub path: String,
+    pub max_bytes: u64,
+    pub total_bytes: u64,
+    pub kinds: Vec<CacheKindStats>,
+}
+
+struct Entry {
+    kind: CacheKind,
+    size: u64,
+    used: SystemTime,
+}
+
+/// The cache folder and what it holds, managed as Tauri state
+///
+/// Entries are found by scanning the folder the first time they're needed.
+/// The default cache has no folder and stores nothing.
+#[derive(Default)]
+pub struct AssetCache {
+    root: Option<PathBuf>,
+    entries: OnceLock<Mutex<HashMap<PathBuf, Entry>>>,
+}
+
+impl AssetCache {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root: Some(root),
+            entries: OnceLock::new(),
+        }
+    }
+
+    /// Entry name for something generated from `source`, changing whenever
+    /// the file does; `variant` tells apart entries made with other options
+    pub fn source_key(source: &Path, variant: &str) -> Option<String> {
+        let metadata = fs::metadata(source).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+
+        let canonical = fs::canonicalize(source).unwrap_or_else(|_| source.to_path_buf());
+        let key = format!(
+            "{}|{}|{}|{}",
+            canonical.display(),
+            metadata.len(),
+            modified,
+            variant
+        );
+        Some(format!("{:016x}", fnv1a(key.as_bytes())))
+    }
+
+    /// Contents of an entry, marking it as just used
+    pub fn get(&self, kind: CacheKind, name: &str) -> Option<Vec<u8>> {
+        let path = self.root.as_ref()?.join(kind.dir_name()).join(name);
+        let bytes = fs::read(&path).ok()?;
+        self.touch(&path);
+        Some(bytes)
+    }
+
+    /// Where an entry is stored, for handing to the viewer, marking it as
+    /// just used
+    pub fn locate(&self, kind: CacheKind, name: &str) -> Option<PathBuf> {
+        let path = self.root.as_ref()?.join(kind.dir_name()).join(name);
+        if !path.is_file() {
+            return None;
+        }
+        self.touch(&path);
+        Some(path)
+    }
+
+    /// Store an entry, then evict the least recently used ones until the
+    /// cache fits in `max_bytes`
+    ///
+    /// An entry larger than the whole cache is not stored.
+    pub fn put(
+        &self,
+        kind: CacheKind,
+        name: &str,
+        bytes: &[u8],
+        max_bytes: u64,
+    ) -> Result<(), String> {
+        let Some(root) = &self.root else {
+            return Ok(());
+        };
+        if bytes.len() as u64 > max_bytes {
+            return Ok(());
+        }
+        let dir = root.join(kind.dir_name());
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let path = dir.join(name);
+
+        // Write to a temp file first so a crash never leaves a torn entry
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, bytes).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+        fs::rename(&tmp, &path)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        let mut entries = self.entries();
+        entries.insert(
+            path,
+            Entry {
+                kind,
+                size: bytes.len() as u64,
+                used: SystemTime::now(),
+            },
+        );
+        evict(&mut entries, max_bytes);
+        Ok(())
+    }
+
+    pub fn stats(&self, max_bytes: u64) -> CacheStats {
+        let entries = self.entries();
+        let kinds: Vec<CacheKindStats> = CacheKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let of_kind = entries.values().filter(|entry| entry.kind == kind);
+                CacheKindStats {
+                    kind,
+                    entry_count: of_kind.clone().count(),
+                    total_bytes: of_kind.map(|entry| entry.size).sum(),
+                }
+            })
+            .collect();
+        CacheStats {
+            path: self
+                .root
+                .as_ref()
+                .map(|root| root.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            max_bytes,
+            total_bytes: kinds.iter().map(|k| k.total_bytes).sum(),
+            kinds,
+        }
+    }
+
+    /// Entries known to the cache whose files have gone, such as ones
+    /// removed by hand while the app ran
+    pub fn missing_entries(&self) -> usize {
+        self.entries().keys().filter(|path| !path.is_file()).count()
+    }
+
+    /// Remove every entry of `kind`, or every entry when `None`
+    pub fn clear(&self, kind: Option<CacheKind>) -> Result<(), String> {
+        let mut entries = self.entries();
+        let mut failed = None;
+        entries.retain(|path, entry| {
+            if kind.is_some_and(|kind| kind != entry.kind) {
+                return true;
+            }
+            match fs::remove_file(path) {
+                Ok(()) => false,
+                Err(e) => {
+                    failed = Some(format!("Failed to remove {}: {}", path.display(), e));
+                    true
+                }
+            }
+        });
+        failed.map_or(Ok(()), Err)
+    }
+
+    fn touch(&self, path: &Path) {
+        let now = SystemTime::now();
+        let touched = fs::File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(now));
+        if let Err(e) = touched {
+            log::warn!("Failed to mark {} as used: {}", path.display(), e);
+        }
+        if let Some(entry) = self.entries().get_mut(path) {
+            entry.used = now;
+        }
+    }
+
+    /// Known entries, scanning the folder on first use
+    fn entries(&self) -> MutexGuard<'_, HashMap<PathBuf, Entry>> {
+        self.entries
+            .get_or_init(|| Mutex::new(self.root.as_deref().map(scan).unwrap_or_default()))
+            .lock()
+            .unwrap()
+    }
+}
+
+/// Every entry in the cache folder, leaving out unfinished writes
+fn scan(root: &Path) -> HashMap<PathBuf, Entry> {
+    let mut entries = HashMap::new();
+    for kind in CacheKind::ALL {
+        let Ok(dir) = fs::read_dir(root.join(kind.dir_name())) else {
+            continue;
+        };
+        for file in dir.flatten() {
+            let path = file.path();
+            let Ok(metadata) = file.metadata() else {
+                continue;
+            };
+            if !metadata.is_file() || path.extension().is_some_and(|e| e == "tmp") {
+                continue;
+            }
+            let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            entries.insert(
+                path,
+                Entry {
+                    kind,
+                    size: metadata.len(),
+                    used,
+                },
+            );
+        }
+    }
+    entries
+}
+
+/// Remove the least recently used entries until the rest fit in `max_bytes`
+fn evict(entries: &mut HashMap<PathBuf, Entry>, max_bytes: u64) {
+    let mut total: u64 = entries.values().map(|entry| entry.size).sum();
+    if total <= max_bytes {
+        return;
+    }
+    let mut by_use: Vec<(SystemTime, PathBuf)> = entries
+        .iter()
+        .map(|(path, entry)| (entry.used, path.clone()))
+        .collect();
+    by_use.sort();
+    for (_, path) in by_use {
+        if total <= max_bytes {
+            break;
+        }
+        if let Err(e) = fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to evict {}: {}", path.display(), e);
+                continue;
+            }
+        }
+        if let Some(entry) = entries.remove(&path) {
+            total -= entry.size;
+        }
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325u64, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let root = std::env::temp_dir().join(format!("sweedle-cache-{}", std::process::id()));
+        let cache = AssetCache::new(root.clone());
+        cache
+            .put(CacheKind::Thumbnail, "a.png", &[0; 40], 100)
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        cache
+            .put(CacheKind::Preview, "b.bin", &[0; 40], 100)
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        // Reading the older entry makes the other one the next to go
+        assert_eq!(cache.get(CacheKind::Thumbnail, "a.png").unwrap().len(), 40);
+        std::thread::sleep(Duration::from_millis(10));
+        cache
+            .put(CacheKind::Texture, "c.png", &[0; 40], 100)
+            .unwrap();
+
+        assert!(cache.get(CacheKind::Preview, "b.bin").is_none());
+        assert!(cache.get(CacheKind::Thumbnail, "a.png").is_some());
+        cache
+            .put(CacheKind::Texture, "huge.png", &[0; 101], 100)
+            .unwrap();
+        assert!(cache.get(CacheKind::Texture, "huge.png").is_none());
+
+        // A fresh cache finds the same entries on disk
+        let reopened = AssetCache::new(root.clone());
+        let stats = reopened.stats(100);
+        assert_eq!(stats.total_bytes, 80);
+        reopened.clear(Some(CacheKind::Texture)).unwrap();
+        let stats = reopened.stats(100);
+        assert_eq!(
+            stats
+                .kinds
+                .iter()
+                .map(|k| k.entry_count)
+                .collect::<Vec<_>>(),
+            vec![1, 0, 0]
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
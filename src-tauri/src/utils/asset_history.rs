@@ -0,0 +1,207 @@
+//! Where each library asset came from and what was done to it
+//!
+//! Imports, processing runs, renames and moves between roots are appended
+//! per asset id, like licenses kept beside the root indexes. An import
+//! records the file it came from and that file's hash, so a copy found
+//! later can be matched with it; the record embedded by `stamp_provenance`
+//! travels with exported files instead.
+
+use crate::utils::file_lock::{self, FileLock};
+use crate::utils::provenance::ProcessingStep;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Most entries kept per asset; the oldest import is always kept
+const MAX_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryKind {
+    /// Copied into a storage folder from a file or bundle
+    Imported,
+    /// Run through a processing profile
+    Processed,
+    /// Given a new id
+    Renamed,
+    /// Moved to another storage root
+    Moved,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub kind: HistoryKind,
+    pub at_ms: u64,
+    /// File or URL the asset was imported from, the file a processing run
+    /// read, or the id or root it had before a rename or move
+    pub source: Option<String>,
+    /// Where the asset's files went
+    pub path: Option<String>,
+    /// Hash of the source file as imported
+    pub hash: Option<String>,
+    /// What was done to the asset
+    #[serde(default)]
+    pub steps: Vec<ProcessingStep>,
+}
+
+impl HistoryEntry {
+    pub fn new(kind: HistoryKind) -> Self {
+        Self {
+            kind,
+            at_ms: now_ms(),
+            source: None,
+            path: None,
+            hash: None,
+            steps: Vec::new(),
+        }
+    }
+}
+
+/// Asset histories, saved to a JSON file and managed as Tauri state
+#[derive(Default)]
+pub struct AssetHistory {
+    path: Option<PathBuf>,
+    /// Oldest first, by asset id
+    entries: Mutex<BTreeMap<String, Vec<HistoryEntry>>>,
+}
+
+impl AssetHistory {
+    /// Load histories from `path`, starting empty if missing or invalid
+    pub fn load(path: PathBuf) -> Self {
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid asset history {}: {}", path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            path: Some(path),
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Oldest first, empty for an asset with no recorded history
+    pub fn entries(&self, asset_id: &str) -> Vec<HistoryEntry> {
+        let entries = self.entries.lock().unwrap();
+        entries.get(asset_id).cloned().unwrap_or_default()
+    }
+
+    pub fn record(&self, asset_id: &str, entry: HistoryEntry) -> Result<(), String> {
+        let mut entries = self.entries.lock().unwrap();
+        let _lock = self.reload(&mut entries)?;
+        let history = entries.entry(asset_id.to_string()).or_default();
+        history.push(entry);
+        if history.len() > MAX_ENTRIES {
+            let keep_import = history[0].kind == HistoryKind::Imported;
+            history.remove(usize::from(keep_import));
+        }
+        self.save(&entries)
+    }
+
+    /// Carry the history of `from` over to the id `to`, ending with the
+    /// rename
+    pub fn rename_asset(&self, from: &str, to: &str) -> Result<(), String> {
+        let mut entries = self.entries.lock().unwrap();
+        let _lock = self.reload(&mut entries)?;
+        let mut history = entries.remove(from).unwrap_or_default();
+        if let Some(existing) = entries.remove(to) {
+            history.extend(existing);
+        }
+        history.sort_by_key(|entry| entry.at_ms);
+        history.push(HistoryEntry {
+            source: Some(from.to_string()),
+            ..HistoryEntry::new(HistoryKind::Renamed)
+        });
+        entries.insert(to.to_string(), history);
+        self.save(&entries)
+    }
+
+    /// Lock the file and pick up what other instances saved to it
+    fn reload(
+        &self,
+        entries: &mut BTreeMap<String, Vec<HistoryEntry>>,
+    ) -> Result<Option<FileLock>, String> {
+        self.path
+            .as_deref()
+            .map(|path| file_lock::lock_and_reload(path, entries, "asset history"))
+            .transpose()
+    }
+
+    fn save(&self, entries: &BTreeMap<String, Vec<HistoryEntry>>) -> Result<(), String> {
+        match &self.path {
+            Some(path) => file_lock::write_json(path, entries, "asset history"),
+            None => Ok(()),
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_follows_renames_and_survives_reload() {
+        let dir = std::env::temp_dir().join(format!("sweedle-history-{}", std::process::id()));
+        let path = dir.join("asset_history.json");
+        let history = AssetHistory::load(path.clone());
+        history
+            .record(
+                "rock",
+                HistoryEntry {
+                    source: Some("/downloads/rock.glb".to_string()),
+                    hash: Some("abc".to_string()),
+                    ..HistoryEntry::new(HistoryKind::Imported)
+                },
+            )
+            .unwrap();
+        history
+            .record(
+                "rock",
+                HistoryEntry {
+                    steps: vec![ProcessingStep {
+                        operation: "process_asset".to_string(),
+                        details: Some("mobile".to_string()),
+                        at_ms: 0,
+                    }],
+                    ..HistoryEntry::new(HistoryKind::Processed)
+                },
+            )
+            .unwrap();
+        history.rename_asset("rock", "stone").unwrap();
+
+        let reloaded = AssetHistory::load(path);
+        assert!(reloaded.entries("rock").is_empty());
+        let kinds: Vec<HistoryKind> = reloaded.entries("stone").iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                HistoryKind::Imported,
+                HistoryKind::Processed,
+                HistoryKind::Renamed
+            ]
+        );
+        assert_eq!(reloaded.entries("stone")[2].source.as_deref(), Some("rock"));
+
+        // A long history drops its oldest steps but not the import
+        for _ in 0..MAX_ENTRIES {
+            reloaded
+                .record("stone", HistoryEntry::new(HistoryKind::Processed))
+                .unwrap();
+        }
+        let entries = reloaded.entries("stone");
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries[0].hash.as_deref(), Some("abc"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+//! Up-axis and unit conversion for imported geometry
+//!
+//! glTF is Y-up in meters. OBJ has no convention at all, STL is usually
+//! Z-up millimeters from CAD tools and FBX is Z-up centimeters out of Max
+//! and Blender's default exporter. Imports are converted into glTF space so
+//! everything lands next to generated assets at the same size and facing.
+
+use serde::{Deserialize, Serialize};
+
+/// Axis that points up in the source file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpAxis {
+    #[default]
+    Y,
+    Z,
+}
+
+/// How to bring a source file into Y-up meters
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AxisConversion {
+    pub up_axis: UpAxis,
+    /// Meters per source unit (0.01 for centimeters)
+    pub unit_scale: f32,
+}
+
+impl Default for AxisConversion {
+    /// Already Y-up meters: no conversion
+    fn default() -> Self {
+        Self {
+            up_axis: UpAxis::Y,
+            unit_scale: 1.0,
+        }
+    }
+}
+
+impl AxisConversion {
+    pub fn new(up_axis: UpAxis, unit_scale: f32) -> Self {
+        Self {
+            up_axis,
+            unit_scale,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.unit_scale > 0.0 && self.unit_scale.is_finite()) {
+            return Err(format!("Invalid unit scale: {}", self.unit_scale));
+        }
+        Ok(())
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.up_axis == UpAxis::Y && self.unit_scale == 1.0
+    }
+
+    /// Convert one source-space point
+    ///
+    /// Z-up is rotated -90° about X, so source -Y (the usual Z-up forward)
+    /// becomes glTF +Z. It's a rotation, so triangle winding is kept.
+    pub fn convert(&self, p: [f32; 3]) -> [f32; 3] {
+        let [x, y, z] = p.map(|c| c * self.unit_scale);
+        match self.up_axis {
+            UpAxis::Y => [x, y, z],
+            UpAxis::Z => [x, z, -y],
+        }
+    }
+
+    /// Convert a flat buffer of XYZ positions in place
+    pub fn apply(&self, positions: &mut [f32]) {
+        if self.is_identity() {
+            return;
+        }
+        for p in positions.chunks_exact_mut(3) {
+            p.copy_from_slice(&self.convert([p[0], p[1], p[2]]));
+        }
+    }
+}
+
+/// Conversion applied to each format when a file doesn't say otherwise
+///
+/// FBX files that declare their axes and units in `GlobalSettings` use
+/// those instead; glTF is never converted.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportAxes {
+    pub obj: AxisConversion,
+    pub stl: AxisConversion,
+    pub fbx: AxisConversion,
+}
+
+impl Default for ImportAxes {
+    fn default() -> Self {
+        Self {
+            obj: AxisConversion::new(UpAxis::Y, 1.0),
+            stl: AxisConversion::new(UpAxis::Z, 0.001),
+            fbx: AxisConversion::new(UpAxis::Z, 0.01),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_z_up_centimeters_to_y_up_meters() {
+        let fbx = ImportAxes::default().fbx;
+        // 2 m up and 1 m forward (-Y) in a Z-up centimeter file
+        assert_eq!(fbx.convert([0.0, -100.0, 200.0]), [0.0, 2.0, 1.0]);
+
+        let mut positions = vec![1.0, 2.0, 3.0];
+        AxisConversion::default().apply(&mut positions);
+        assert_eq!(positions, vec![1.0, 2.0, 3.0]);
+        assert!(AxisConversion::new(UpAxis::Y, 0.0).validate().is_err());
+    }
+}
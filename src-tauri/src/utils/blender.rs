@@ -0,0 +1,105 @@
+//! Round-trip editing in Blender
+//!
+//! The model and the files it refers to are copied to a session folder and
+//! Blender is started with a bundled script that imports the copy and
+//! exports `result.glb` whenever the scene is saved. Each new result is
+//! stored next to the original as its next version, `<name>_v2.glb` and so
+//! on; the original is never touched.
+
+use std::path::{Path, PathBuf};
+
+/// Script Blender runs on start, written into each session folder
+pub const EDIT_SCRIPT: &str = include_str!("blender_edit.py");
+/// File the script exports on every save
+pub const RESULT_FILE: &str = "result.glb";
+
+/// The Blender executable: the configured one, otherwise the usual install
+/// location, otherwise whatever `blender` is on the `PATH`
+pub fn executable(configured: &str) -> PathBuf {
+    let configured = configured.trim();
+    if !configured.is_empty() {
+        return PathBuf::from(configured);
+    }
+    if cfg!(target_os = "macos") {
+        let bundled = Path::new("/Applications/Blender.app/Contents/MacOS/Blender");
+        if bundled.is_file() {
+            return bundled.to_path_buf();
+        }
+    }
+    if cfg!(windows) {
+        // Versions install side by side; the newest sorts last
+        let foundation = Path::new("C:\\Program Files\\Blender Foundation");
+        let mut installs: Vec<PathBuf> = std::fs::read_dir(foundation)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path().join("blender.exe"))
+            .filter(|exe| exe.is_file())
+            .collect();
+        installs.sort();
+        if let Some(newest) = installs.pop() {
+            return newest;
+        }
+    }
+    PathBuf::from("blender")
+}
+
+/// Where the next version of `model` goes: `<name>_v<N>.glb` in its
+/// folder, with `N` one past the highest version there
+///
+/// A model that is already a version (`rock_v3.glb`) counts from its base
+/// name, so editing a version never yields `rock_v3_v2.glb`.
+pub fn next_version_path(model: &Path) -> PathBuf {
+    let dir = model.parent().unwrap_or(Path::new(""));
+    let stem = model
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let base = version_of(&stem).map_or(stem.as_str(), |(base, _)| base);
+
+    let highest = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let stem = path.file_stem()?.to_string_lossy().to_string();
+            let (name, version) = version_of(&stem)?;
+            (name == base).then_some(version)
+        })
+        .max()
+        .unwrap_or(1);
+    dir.join(format!("{}_v{}.glb", base, highest + 1))
+}
+
+/// `("rock", 3)` for `rock_v3`
+fn version_of(stem: &str) -> Option<(&str, u32)> {
+    let (base, version) = stem.rsplit_once("_v")?;
+    let version = version.parse().ok()?;
+    (!base.is_empty()).then_some((base, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_versions_count_up_from_base_name() {
+        let dir = std::env::temp_dir().join(format!("sweedle-versions-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let model = dir.join("rock.fbx");
+        fs::write(&model, b"").unwrap();
+        assert_eq!(next_version_path(&model), dir.join("rock_v2.glb"));
+
+        fs::write(dir.join("rock_v2.glb"), b"").unwrap();
+        fs::write(dir.join("rock_v7.glb"), b"").unwrap();
+        fs::write(dir.join("rocky_v9.glb"), b"").unwrap();
+        assert_eq!(next_version_path(&model), dir.join("rock_v8.glb"));
+        assert_eq!(
+            next_version_path(&dir.join("rock_v2.glb")),
+            dir.join("rock_v8.glb")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,47 @@
+# Opened by Sweedle's "Edit in Blender": imports the model, saves the scene
+# next to it so Ctrl+S works straight away, and exports a GLB for Sweedle
+# every time the scene is saved.
+#
+# blender --python sweedle_edit.py -- <model> <result.glb>
+
+import os
+import sys
+
+import bpy
+from bpy.app.handlers import persistent
+
+model, result = sys.argv[sys.argv.index("--") + 1 :][:2]
+
+
+def import_model(path):
+    extension = os.path.splitext(path)[1].lower()
+    if extension in (".glb", ".gltf"):
+        bpy.ops.import_scene.gltf(filepath=path)
+    elif extension == ".fbx":
+        bpy.ops.import_scene.fbx(filepath=path)
+    elif extension == ".obj":
+        if hasattr(bpy.ops.wm, "obj_import"):
+            bpy.ops.wm.obj_import(filepath=path)
+        else:
+            bpy.ops.import_scene.obj(filepath=path)
+    elif extension == ".stl":
+        if hasattr(bpy.ops.wm, "stl_import"):
+            bpy.ops.wm.stl_import(filepath=path)
+        else:
+            bpy.ops.import_mesh.stl(filepath=path)
+    else:
+        raise RuntimeError("Sweedle can't open " + path + " in Blender")
+
+
+@persistent
+def export_result(_scene, _depsgraph=None):
+    # Written under another name first so Sweedle never reads half a file
+    partial = result + ".partial.glb"
+    bpy.ops.export_scene.gltf(filepath=partial, export_format="GLB")
+    os.replace(partial, result)
+
+
+bpy.ops.wm.read_factory_settings(use_empty=True)
+import_model(model)
+bpy.ops.wm.save_as_mainfile(filepath=os.path.splitext(result)[0] + ".blend")
+bpy.app.handlers.save_post.append(export_result)
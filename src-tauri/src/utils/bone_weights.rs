@@ -0,0 +1,458 @@
+//! Skin weight statistics and clean-up
+//!
+//! Engines cap how many bones move a vertex (four is common, some allow
+//! eight) and expect the weights to add up to one. The report counts
+//! influences per vertex, weight sums that are off and joints no vertex
+//! uses. Limiting keeps each vertex's heaviest influences and renormalizes;
+//! pruning drops unused joints from the skins, keeping the ones between
+//! used joints and the root so the bone hierarchy stays whole. Pruned
+//! joints stay in the scene as plain nodes, so animations still apply.
+
+use crate::utils::gltf_document::{items_mut, GltfDocument};
+use crate::utils::pose::skin_weights;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// How far a vertex's weights may add up from one before it's reported
+const WEIGHT_SUM_TOLERANCE: f32 = 1e-3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoneUsage {
+    /// Node of the joint
+    pub joint: usize,
+    pub name: Option<String>,
+    /// Vertices it moves
+    pub vertex_count: usize,
+    pub max_weight: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WeightReport {
+    pub skin_count: usize,
+    pub skinned_vertex_count: usize,
+    pub max_influences: usize,
+    /// Vertices by number of influences: entry `n` counts vertices with `n`
+    pub influence_counts: Vec<usize>,
+    /// Weighted vertices whose weights don't add up to one
+    pub unnormalized_vertex_count: usize,
+    /// Skinned vertices with no weight at all
+    pub unweighted_vertex_count: usize,
+    /// Every joint of every skin, by node
+    pub bones: Vec<BoneUsage>,
+    /// Names of the joints no vertex uses
+    pub unused_bones: Vec<String>,
+}
+
+/// Influence statistics of every skinned mesh
+pub fn analyze(document: &GltfDocument) -> Result<WeightReport, String> {
+    let skins = document.json["skins"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let meshes = document.json["meshes"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let mut report = WeightReport {
+        skin_count: skins.len(),
+        ..Default::default()
+    };
+    let mut usage: BTreeMap<usize, BoneUsage> = BTreeMap::new();
+    for skin in &skins {
+        for joint in joints(skin) {
+            usage.entry(joint).or_insert_with(|| BoneUsage {
+                joint,
+                name: node_name(document, joint),
+                vertex_count: 0,
+                max_weight: 0.0,
+            });
+        }
+    }
+
+    for (mesh, skin) in skinned_meshes(document)? {
+        let joints = joints(&skins[skin]);
+        for primitive in meshes[mesh]["primitives"].as_array().into_iter().flatten() {
+            for influence in influences(document, primitive)? {
+                report.skinned_vertex_count += 1;
+                if report.influence_counts.len() <= influence.len() {
+                    report.influence_counts.resize(influence.len() + 1, 0);
+                }
+                report.influence_counts[influence.len()] += 1;
+                report.max_influences = report.max_influences.max(influence.len());
+                let sum: f32 = influence.iter().map(|(_, w)| w).sum();
+                if influence.is_empty() {
+                    report.unweighted_vertex_count += 1;
+                } else if (sum - 1.0).abs() > WEIGHT_SUM_TOLERANCE {
+                    report.unnormalized_vertex_count += 1;
+                }
+                for &(k, weight) in &influence {
+                    if let Some(bone) = joints.get(k).and_then(|j| usage.get_mut(j)) {
+                        bone.vertex_count += 1;
+                        bone.max_weight = bone.max_weight.max(weight);
+                    }
+                }
+            }
+        }
+    }
+
+    report.bones = usage.into_values().collect();
+    report.unused_bones = report
+        .bones
+        .iter()
+        .filter(|bone| bone.vertex_count == 0)
+        .map(|bone| {
+            bone.name
+                .clone()
+                .unwrap_or_else(|| format!("node {}", bone.joint))
+        })
+        .collect();
+    Ok(report)
+}
+
+/// Keep at most `max_influences` weights per vertex, the heaviest, and
+/// make every vertex's weights add up to one; returns the vertices changed
+pub fn limit_influences(
+    document: &mut GltfDocument,
+    max_influences: usize,
+) -> Result<usize, String> {
+    if max_influences == 0 {
+        return Err("Vertices need at least one influence".to_string());
+    }
+    let mut changed = 0;
+    edit_primitives(document, |document, _, primitive| {
+        let mut influences = influences(document, primitive)?;
+        let mut edited = false;
+        for influence in &mut influences {
+            let before = influence.clone();
+            influence.sort_by(|a, b| b.1.total_cmp(&a.1));
+            influence.truncate(max_influences);
+            let sum: f32 = influence.iter().map(|(_, w)| w).sum();
+            if sum > 0.0 {
+                for (_, weight) in influence.iter_mut() {
+                    *weight /= sum;
+                }
+            }
+            let moved = before.len() != influence.len()
+                || influence.iter().any(|&(k, w)| {
+                    !before
+                        .iter()
+                        .any(|&(j, v)| j == k && (v - w).abs() <= WEIGHT_SUM_TOLERANCE)
+                });
+            if moved {
+                changed += 1;
+                edited = true;
+            }
+        }
+        if edited {
+            write_influences(document, primitive, &influences);
+        }
+        Ok(())
+    })?;
+    document.compact();
+    Ok(changed)
+}
+
+/// Drop the joints no vertex uses from every skin, unless a used joint
+/// hangs below them; returns the names of the joints dropped
+pub fn prune_unused_bones(document: &mut GltfDocument) -> Result<Vec<String>, String> {
+    let report = analyze(document)?;
+    let used: Vec<usize> = report
+        .bones
+        .iter()
+        .filter(|bone| bone.vertex_count > 0)
+        .map(|bone| bone.joint)
+        .collect();
+    let parents = parents(document);
+    let mut keep = vec![false; parents.len()];
+    for &joint in &used {
+        let mut node = Some(joint);
+        let mut steps = 0;
+        while let Some(n) = node.filter(|_| steps <= parents.len()) {
+            if let Some(flag) = keep.get_mut(n) {
+                *flag = true;
+            }
+            node = parents.get(n).copied().flatten();
+            steps += 1;
+        }
+    }
+
+    // New position of each old joint slot, per skin
+    let skins = document.json["skins"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let mut remaps: Vec<Vec<Option<usize>>> = Vec::with_capacity(skins.len());
+    let mut pruned = Vec::new();
+    for (index, skin) in skins.iter().enumerate() {
+        let joints = joints(skin);
+        let is_kept = |joint: usize| keep.get(joint).copied().unwrap_or(false);
+        if !joints.iter().any(|&joint| is_kept(joint)) {
+            // A skin needs a joint; one no vertex uses is left alone
+            remaps.push((0..joints.len()).map(Some).collect());
+            continue;
+        }
+        let mut remap = Vec::with_capacity(joints.len());
+        let mut kept = Vec::new();
+        for &joint in &joints {
+            if is_kept(joint) {
+                remap.push(Some(kept.len()));
+                kept.push(joint);
+            } else {
+                remap.push(None);
+                let name = node_name(document, joint).unwrap_or_else(|| format!("node {}", joint));
+                if !pruned.contains(&name) {
+                    pruned.push(name);
+                }
+            }
+        }
+        if kept.len() < joints.len() {
+            let inverse_binds = match skin["inverseBindMatrices"].as_u64() {
+                Some(accessor) => {
+                    let matrices = document.read_floats(accessor as usize)?.values;
+                    let kept: Vec<f32> = remap
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, slot)| slot.is_some())
+                        .flat_map(|(k, _)| matrices.get(k * 16..(k + 1) * 16).unwrap_or(&[]))
+                        .copied()
+                        .collect();
+                    Some(document.push_floats(&kept, "MAT4", false))
+                }
+                None => None,
+            };
+            if let Some(skin) = items_mut(&mut document.json, "skins").nth(index) {
+                skin["joints"] = json!(kept);
+                if let Some(accessor) = inverse_binds {
+                    skin["inverseBindMatrices"] = json!(accessor);
+                }
+            }
+        }
+        remaps.push(remap);
+    }
+    if pruned.is_empty() {
+        return Ok(pruned);
+    }
+
+    edit_primitives(document, |document, skin, primitive| {
+        let remap = &remaps[skin];
+        if remap.iter().enumerate().all(|(k, slot)| *slot == Some(k)) {
+            return Ok(());
+        }
+        let influences: Vec<Vec<(usize, f32)>> = influences(document, primitive)?
+            .into_iter()
+            .map(|influence| {
+                influence
+                    .into_iter()
+                    .filter_map(|(k, w)| Some((remap.get(k).copied().flatten()?, w)))
+                    .collect()
+            })
+            .collect();
+        write_influences(document, primitive, &influences);
+        Ok(())
+    })?;
+    document.compact();
+    Ok(pruned)
+}
+
+/// Call `edit` with the skin of each skinned mesh's primitives, once per
+/// mesh
+fn edit_primitives(
+    document: &mut GltfDocument,
+    mut edit: impl FnMut(&mut GltfDocument, usize, &mut Value) -> Result<(), String>,
+) -> Result<(), String> {
+    let skinned = skinned_meshes(document)?;
+    let Some(mut meshes) = document.json.get_mut("meshes").map(Value::take) else {
+        return Ok(());
+    };
+    let mut result = Ok(());
+    'meshes: for (mesh, skin) in skinned {
+        let Some(mesh) = meshes.get_mut(mesh) else {
+            continue;
+        };
+        for primitive in items_mut(mesh, "primitives") {
+            result = edit(document, skin, primitive);
+            if result.is_err() {
+                break 'meshes;
+            }
+        }
+    }
+    document.json["meshes"] = meshes;
+    result
+}
+
+/// Each skinned mesh with its skin, once; a mesh bound to two different
+/// skins can't be edited for both
+fn skinned_meshes(document: &GltfDocument) -> Result<Vec<(usize, usize)>, String> {
+    let mut skinned: BTreeMap<usize, usize> = BTreeMap::new();
+    for node in document.json["nodes"].as_array().into_iter().flatten() {
+        let (Some(mesh), Some(skin)) = (node["mesh"].as_u64(), node["skin"].as_u64()) else {
+            continue;
+        };
+        let (mesh, skin) = (mesh as usize, skin as usize);
+        if document.json["skins"].get(skin).is_none() || document.json["meshes"].get(mesh).is_none()
+        {
+            return Err(format!(
+                "Node uses a missing mesh {} or skin {}",
+                mesh, skin
+            ));
+        }
+        if *skinned.entry(mesh).or_insert(skin) != skin {
+            return Err(format!("Mesh {} is bound to more than one skin", mesh));
+        }
+    }
+    Ok(skinned.into_iter().collect())
+}
+
+/// Joint slot and weight pairs of each vertex of a primitive
+fn influences(
+    document: &GltfDocument,
+    primitive: &Value,
+) -> Result<Vec<Vec<(usize, f32)>>, String> {
+    let count = primitive["attributes"]["POSITION"]
+        .as_u64()
+        .and_then(|a| document.json["accessors"][a as usize]["count"].as_u64())
+        .unwrap_or(0) as usize;
+    skin_weights(document, primitive, count)
+}
+
+/// Replace a primitive's `JOINTS_n` and `WEIGHTS_n` sets with as few sets
+/// of four as hold `influences`
+fn write_influences(
+    document: &mut GltfDocument,
+    primitive: &mut Value,
+    influences: &[Vec<(usize, f32)>],
+) {
+    let most = influences.iter().map(Vec::len).max().unwrap_or(0);
+    let sets = most.div_ceil(4).max(1);
+    let Some(attributes) = primitive
+        .get_mut("attributes")
+        .and_then(Value::as_object_mut)
+    else {
+        return;
+    };
+    attributes.retain(|name, _| !name.starts_with("JOINTS_") && !name.starts_with("WEIGHTS_"));
+    for set in 0..sets {
+        let mut joints = Vec::with_capacity(influences.len() * 4);
+        let mut weights = Vec::with_capacity(influences.len() * 4);
+        for influence in influences {
+            for slot in set * 4..set * 4 + 4 {
+                let (joint, weight) = influence.get(slot).copied().unwrap_or((0, 0.0));
+                joints.push(joint as u32);
+                weights.push(weight);
+            }
+        }
+        let joints = document.push_ints(&joints, "VEC4");
+        let weights = document.push_floats(&weights, "VEC4", false);
+        attributes.insert(format!("JOINTS_{}", set), json!(joints));
+        attributes.insert(format!("WEIGHTS_{}", set), json!(weights));
+    }
+}
+
+fn joints(skin: &Value) -> Vec<usize> {
+    skin["joints"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|j| j.as_u64().map(|j| j as usize))
+        .collect()
+}
+
+fn node_name(document: &GltfDocument, node: usize) -> Option<String> {
+    document.json["nodes"][node]["name"]
+        .as_str()
+        .map(str::to_string)
+}
+
+fn parents(document: &GltfDocument) -> Vec<Option<usize>> {
+    let nodes = document.json["nodes"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let mut parents = vec![None; nodes.len()];
+    for (index, node) in nodes.iter().enumerate() {
+        for child in node["children"].as_array().into_iter().flatten() {
+            if let Some(slot) = child.as_u64().and_then(|c| parents.get_mut(c as usize)) {
+                *slot = Some(index);
+            }
+        }
+    }
+    parents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Root > Arm > Hand and Root > Helper, with joint slots Root, Helper,
+    /// Arm, Hand; one vertex on three bones, one on Hand, one unweighted
+    fn rigged() -> GltfDocument {
+        let mut document = GltfDocument {
+            json: json!({ "asset": { "version": "2.0" } }),
+            bin: Vec::new(),
+        };
+        let position = document.push_floats(&[0.0; 9], "VEC3", true);
+        let joints = document.push_ints(&[2, 3, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0], "VEC4");
+        let weights = [0.5, 0.3, 0.2, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        let weights = document.push_floats(&weights, "VEC4", false);
+        let inverse_binds: Vec<f32> = (0..4).flat_map(|k| [k as f32; 16]).collect();
+        let inverse_binds = document.push_floats(&inverse_binds, "MAT4", false);
+        document.json["nodes"] = json!([
+            { "name": "Root", "children": [1, 3] },
+            { "name": "Arm", "children": [2] },
+            { "name": "Hand" },
+            { "name": "Helper" },
+            { "name": "Body", "mesh": 0, "skin": 0 },
+        ]);
+        document.json["meshes"] = json!([{ "primitives": [{
+            "attributes": { "POSITION": position, "JOINTS_0": joints, "WEIGHTS_0": weights },
+        }] }]);
+        document.json["skins"] =
+            json!([{ "joints": [0, 3, 1, 2], "inverseBindMatrices": inverse_binds }]);
+        document
+    }
+
+    fn assert_influences(found: &[(usize, f32)], expected: &[(usize, f32)]) {
+        assert_eq!(found.len(), expected.len(), "{:?}", found);
+        for (a, b) in found.iter().zip(expected) {
+            assert!(a.0 == b.0 && (a.1 - b.1).abs() < 1e-6, "{:?}", found);
+        }
+    }
+
+    #[test]
+    fn test_report_limit_and_prune() {
+        let mut document = rigged();
+        let report = analyze(&document).unwrap();
+        assert_eq!(report.skinned_vertex_count, 3);
+        assert_eq!(report.max_influences, 3);
+        assert_eq!(report.influence_counts, vec![1, 1, 0, 1]);
+        assert_eq!(report.unweighted_vertex_count, 1);
+        assert_eq!(report.unnormalized_vertex_count, 0);
+        assert_eq!(report.unused_bones, vec!["Helper"]);
+
+        assert_eq!(limit_influences(&mut document, 2).unwrap(), 1);
+        let report = analyze(&document).unwrap();
+        assert_eq!(report.max_influences, 2);
+        assert_eq!(report.unused_bones, vec!["Root", "Helper"]);
+        let primitive = document.json["meshes"][0]["primitives"][0].clone();
+        let limited = influences(&document, &primitive).unwrap();
+        assert_influences(&limited[0], &[(2, 0.625), (3, 0.375)]);
+
+        // Root stays as the parent of used joints
+        assert_eq!(prune_unused_bones(&mut document).unwrap(), vec!["Helper"]);
+        assert_eq!(document.json["skins"][0]["joints"], json!([0, 1, 2]));
+        let inverse_binds = document.json["skins"][0]["inverseBindMatrices"]
+            .as_u64()
+            .unwrap();
+        let inverse_binds = document.read_floats(inverse_binds as usize).unwrap().values;
+        assert_eq!(
+            (inverse_binds.len(), inverse_binds[16], inverse_binds[32]),
+            (48, 2.0, 3.0)
+        );
+        let primitive = document.json["meshes"][0]["primitives"][0].clone();
+        let pruned = influences(&document, &primitive).unwrap();
+        assert_influences(&pruned[0], &[(1, 0.625), (2, 0.375)]);
+        assert_influences(&pruned[1], &[(2, 1.0)]);
+        assert!(prune_unused_bones(&mut document).unwrap().is_empty());
+    }
+}
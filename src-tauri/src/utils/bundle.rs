@@ -0,0 +1,272 @@
+//! `.sweedlepack` asset bundles
+//!
+//! A bundle is a gzipped tar archive, so it can also be opened with any
+//! archive tool. `manifest.json` at the top lists the assets with their
+//! categories and licenses, and each asset's files are under
+//! `assets/<id>/`, laid out as one folder per asset whatever layout they
+//! came from.
+
+use crate::utils::licenses::AssetLicense;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+pub const EXTENSION: &str = "sweedlepack";
+/// Archive path of the manifest
+pub const MANIFEST: &str = "manifest.json";
+/// Folder holding the assets inside a bundle
+pub const ASSETS_DIR: &str = "assets";
+const FORMAT: &str = "sweedlepack";
+const VERSION: u32 = 1;
+const BLOCK: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format: String,
+    pub version: u32,
+    pub created_ms: u64,
+    pub assets: Vec<BundledAsset>,
+}
+
+impl BundleManifest {
+    pub fn new(created_ms: u64, assets: Vec<BundledAsset>) -> Self {
+        Self {
+            format: FORMAT.to_string(),
+            version: VERSION,
+            created_ms,
+            assets,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundledAsset {
+    pub id: String,
+    pub category: Option<String>,
+    pub license: Option<AssetLicense>,
+    /// Archive paths of the asset's files
+    pub files: Vec<String>,
+}
+
+/// A bundle of `manifest` with each file read from disk and stored at its
+/// archive path
+pub fn write(manifest: &BundleManifest, files: &[(PathBuf, String)]) -> Result<Vec<u8>, String> {
+    let mut archive = GzEncoder::new(Vec::new(), Compression::default());
+    let json = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize bundle manifest: {}", e))?;
+    let fail = |e: io::Error| format!("Failed to write bundle: {}", e);
+    append(&mut archive, MANIFEST, &json, manifest.created_ms / 1000).map_err(fail)?;
+    for (source, name) in files {
+        let contents =
+            fs::read(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        append(&mut archive, name, &contents, manifest.created_ms / 1000).map_err(fail)?;
+    }
+    archive.write_all(&[0; 2 * BLOCK]).map_err(fail)?;
+    archive.finish().map_err(fail)
+}
+
+/// A plain gzipped tar archive of `files`, each stored at its archive path
+pub fn archive(files: &[(String, Vec<u8>)], mtime: u64) -> Result<Vec<u8>, String> {
+    let mut archive = GzEncoder::new(Vec::new(), Compression::default());
+    let fail = |e: io::Error| format!("Failed to write archive: {}", e);
+    for (name, contents) in files {
+        append(&mut archive, name, contents, mtime).map_err(fail)?;
+    }
+    archive.write_all(&[0; 2 * BLOCK]).map_err(fail)?;
+    archive.finish().map_err(fail)
+}
+
+/// Unpack a bundle into `dir` and return its manifest
+///
+/// Entries that would land outside `dir`, and anything but regular files
+/// and folders, are refused.
+pub fn unpack(bundle: &Path, dir: &Path) -> Result<BundleManifest, String> {
+    let file = fs::File::open(bundle)
+        .map_err(|e| format!("Failed to open {}: {}", bundle.display(), e))?;
+    let mut archive = GzDecoder::new(io::BufReader::new(file));
+    let fail = |e: io::Error| format!("Failed to read {}: {}", bundle.display(), e);
+    let mut manifest = None;
+    let mut header = [0u8; BLOCK];
+    loop {
+        archive.read_exact(&mut header).map_err(fail)?;
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let (name, size, kind) = parse_header(&header)?;
+        // The declared size is only trusted as far as the data goes
+        let mut contents = Vec::new();
+        (&mut archive)
+            .take(size as u64)
+            .read_to_end(&mut contents)
+            .map_err(fail)?;
+        if contents.len() != size {
+            return Err(format!("Bundle entry {} is truncated", name));
+        }
+        let padding = size.next_multiple_of(BLOCK) - size;
+        io::copy(&mut (&mut archive).take(padding as u64), &mut io::sink()).map_err(fail)?;
+
+        match kind {
+            b'5' => continue,
+            b'0' | 0 => {}
+            _ => return Err(format!("Bundle entry {} is not a regular file", name)),
+        }
+        if name == MANIFEST {
+            manifest = Some(
+                serde_json::from_slice::<BundleManifest>(&contents)
+                    .map_err(|e| format!("Invalid bundle manifest: {}", e))?,
+            );
+            continue;
+        }
+        let dest = dir.join(safe_path(&name)?);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(&dest, contents)
+            .map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+    }
+
+    let manifest = manifest.ok_or_else(|| format!("{} has no manifest", bundle.display()))?;
+    if manifest.format != FORMAT || manifest.version > VERSION {
+        return Err(format!(
+            "{} is not a version {} Sweedle bundle",
+            bundle.display(),
+            VERSION
+        ));
+    }
+    Ok(manifest)
+}
+
+/// Write a ustar entry for a regular file
+fn append(archive: &mut impl Write, name: &str, contents: &[u8], mtime: u64) -> io::Result<()> {
+    let mut header = [0u8; BLOCK];
+    let (prefix, name) = split_name(name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Path too long: {}", name),
+        )
+    })?;
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], contents.len() as u64);
+    octal(&mut header[136..148], mtime);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+    // The checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+    header[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+
+    archive.write_all(&header)?;
+    archive.write_all(contents)?;
+    let padding = contents.len().next_multiple_of(BLOCK) - contents.len();
+    archive.write_all(&vec![0; padding])
+}
+
+/// Split a path into ustar's 155-byte prefix and 100-byte name at a `/`
+fn split_name(path: &str) -> Option<(&str, &str)> {
+    if path.len() <= 100 {
+        return Some(("", path));
+    }
+    path.match_indices('/')
+        .map(|(i, _)| (&path[..i], &path[i + 1..]))
+        .find(|(prefix, name)| prefix.len() <= 155 && name.len() <= 100 && !name.is_empty())
+}
+
+/// Name, size and type flag of an entry
+fn parse_header(header: &[u8; BLOCK]) -> Result<(String, usize, u8), String> {
+    let text = |field: &[u8]| {
+        let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+        String::from_utf8_lossy(&field[..end]).to_string()
+    };
+    let stored: u32 = u32::from_str_radix(text(&header[148..156]).trim(), 8)
+        .map_err(|_| "Bundle is damaged: bad header checksum".to_string())?;
+    let checksum: u32 = header
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| {
+            if (148..156).contains(&i) {
+                b' ' as u32
+            } else {
+                b as u32
+            }
+        })
+        .sum();
+    if stored != checksum {
+        return Err("Bundle is damaged: header checksum mismatch".to_string());
+    }
+    let size = usize::from_str_radix(text(&header[124..136]).trim(), 8)
+        .map_err(|_| "Bundle is damaged: bad entry size".to_string())?;
+    let (name, prefix) = (text(&header[..100]), text(&header[345..500]));
+    let name = if prefix.is_empty() {
+        name
+    } else {
+        format!("{}/{}", prefix, name)
+    };
+    Ok((name, size, header[156]))
+}
+
+/// An archive path as a relative path that can't climb out of its folder
+fn safe_path(name: &str) -> Result<PathBuf, String> {
+    let path = Path::new(name);
+    if name.contains('\\')
+        || path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(format!("Bundle entry {} points outside the bundle", name));
+    }
+    Ok(path.to_path_buf())
+}
+
+fn octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}\0", value, width = field.len() - 1);
+    field.copy_from_slice(digits.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let dir = std::env::temp_dir().join(format!("sweedle-bundle-{}", std::process::id()));
+        let source = dir.join("source");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("rock.glb"), b"glTF").unwrap();
+        fs::write(source.join("albedo.png"), vec![7u8; 1000]).unwrap();
+        let deep = format!("{}/{}/albedo.png", ASSETS_DIR, "d".repeat(120));
+        let manifest = BundleManifest::new(
+            1_700_000_000_000,
+            vec![BundledAsset {
+                id: "rock".to_string(),
+                category: Some("props".to_string()),
+                license: None,
+                files: vec!["assets/rock/rock.glb".to_string(), deep.clone()],
+            }],
+        );
+        let files = vec![
+            (source.join("rock.glb"), "assets/rock/rock.glb".to_string()),
+            (source.join("albedo.png"), deep.clone()),
+        ];
+        let bundle = dir.join("set.sweedlepack");
+        fs::write(&bundle, write(&manifest, &files).unwrap()).unwrap();
+
+        let out = dir.join("out");
+        assert_eq!(unpack(&bundle, &out).unwrap(), manifest);
+        assert_eq!(fs::read(out.join("assets/rock/rock.glb")).unwrap(), b"glTF");
+        assert_eq!(fs::read(out.join(&deep)).unwrap().len(), 1000);
+
+        assert!(safe_path("../escape.glb").is_err());
+        assert!(safe_path("/etc/passwd").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
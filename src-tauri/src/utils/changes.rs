@@ -0,0 +1,325 @@
+//! Planned filesystem changes for mutating commands
+//!
+//! Commands that convert, repair, prune or delete files route their writes
+//! through a [`ChangeSet`]. In dry-run mode every change is recorded but
+//! nothing touches the disk, so the report shows exactly what a real run
+//! would do. The global read-only setting refuses real runs outright.
+//!
+//! Before a real run replaces, moves or deletes a file it locks the file
+//! against other app instances, and it refuses to replace a file changed
+//! since the command started by anything other than the command itself, so
+//! two instances exporting to the same place don't overwrite each other.
+
+use crate::utils::file_lock::{self, FileLock};
+use crate::utils::settings::AppSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Create,
+    Overwrite,
+    Delete,
+    Rename,
+}
+
+/// One change a command made, or would make in a dry run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChange {
+    pub action: ChangeAction,
+    pub path: String,
+    /// Destination of a rename
+    pub to: Option<String>,
+    /// Size written, or size of the file being deleted
+    pub bytes: Option<u64>,
+}
+
+/// Everything a mutating command changed or would change
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChangeReport {
+    pub dry_run: bool,
+    pub changes: Vec<FileChange>,
+}
+
+/// Fail if read-only mode is on
+pub fn ensure_writable(settings: &AppSettings) -> Result<(), String> {
+    if settings.read_only {
+        return Err(
+            "Read-only mode is enabled; run with dry_run to preview the changes".to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Records file changes and applies them unless this is a dry run
+pub struct ChangeSet {
+    dry_run: bool,
+    changes: Vec<FileChange>,
+    started: SystemTime,
+    /// Files this change set wrote or moved into place
+    written: HashSet<PathBuf>,
+}
+
+impl ChangeSet {
+    /// Start a change set for a command called with `dry_run`
+    ///
+    /// Fails if read-only mode is on and this would be a real run.
+    pub fn new(settings: &AppSettings, dry_run: Option<bool>) -> Result<Self, String> {
+        let dry_run = dry_run.unwrap_or(false);
+        if !dry_run {
+            ensure_writable(settings)?;
+        }
+        Ok(Self {
+            dry_run,
+            changes: Vec::new(),
+            started: SystemTime::now(),
+            written: HashSet::new(),
+        })
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Write `contents` to `path` via a temp file, creating parent folders
+    pub fn write(&mut self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        let action = if path.exists() {
+            ChangeAction::Overwrite
+        } else {
+            ChangeAction::Create
+        };
+
+        if !self.dry_run {
+            let _lock = FileLock::acquire(path)?;
+            if action == ChangeAction::Overwrite {
+                self.ensure_replaceable(path)?;
+            }
+            let tmp = file_lock::temp_path(path);
+            fs::write(&tmp, contents)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            fs::rename(&tmp, path)
+                .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+            self.written.insert(path.to_path_buf());
+        }
+
+        self.record(action, path, None, Some(contents.len() as u64));
+        Ok(())
+    }
+
+    /// Delete a file; returns `false` if it didn't exist
+    pub fn delete(&mut self, path: &Path) -> Result<bool, String> {
+        let Ok(metadata) = fs::metadata(path) else {
+            return Ok(false);
+        };
+
+        if !self.dry_run {
+            let _lock = FileLock::acquire(path)?;
+            file_lock::ensure_not_in_use(path)?;
+            fs::remove_file(path)
+                .map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+        }
+
+        self.record(ChangeAction::Delete, path, None, Some(metadata.len()));
+        Ok(true)
+    }
+
+    pub fn rename(&mut self, from: &Path, to: &Path) -> Result<(), String> {
+        if !from.exists() {
+            return Err(format!("File not found: {}", from.display()));
+        }
+
+        if !self.dry_run {
+            let _from = FileLock::acquire(from)?;
+            // A rename that only changes case is one file on Windows and macOS
+            let same_file = from
+                .to_string_lossy()
+                .eq_ignore_ascii_case(&to.to_string_lossy());
+            let _to = if same_file {
+                None
+            } else {
+                Some(FileLock::acquire(to)?)
+            };
+            file_lock::ensure_not_in_use(from)?;
+            if to.exists() {
+                self.ensure_replaceable(to)?;
+            }
+            move_file(from, to).map_err(|e| {
+                format!(
+                    "Failed to move {} to {}: {}",
+                    from.display(),
+                    to.display(),
+                    e
+                )
+            })?;
+            self.written.insert(to.to_path_buf());
+        }
+
+        let bytes = fs::metadata(if self.dry_run { from } else { to })
+            .ok()
+            .map(|m| m.len());
+        self.record(ChangeAction::Rename, from, Some(to), bytes);
+        Ok(())
+    }
+
+    /// Rename each `(from, to)` pair in order, or none of them
+    ///
+    /// If one fails, the files already moved are moved back before the
+    /// error is returned, and their renames are dropped from the report.
+    pub fn rename_all(&mut self, moves: &[(PathBuf, PathBuf)]) -> Result<(), String> {
+        let recorded = self.changes.len();
+        for (done, (from, to)) in moves.iter().enumerate() {
+            let Err(e) = self.rename(from, to) else {
+                continue;
+            };
+            if self.dry_run {
+                return Err(e);
+            }
+            let mut stuck = Vec::new();
+            for (from, to) in moves[..done].iter().rev() {
+                let moved_back = FileLock::acquire(to)
+                    .and_then(|_lock| move_file(to, from).map_err(|e| e.to_string()));
+                if let Err(e) = moved_back {
+                    stuck.push(format!("{} ({})", to.display(), e));
+                }
+                self.written.remove(to);
+            }
+            self.changes.truncate(recorded);
+            return Err(if stuck.is_empty() {
+                format!("{}; the files already moved were moved back", e)
+            } else {
+                format!("{}; these could not be moved back: {}", e, stuck.join(", "))
+            });
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> ChangeReport {
+        ChangeReport {
+            dry_run: self.dry_run,
+            changes: self.changes,
+        }
+    }
+
+    /// Fail if `path` is open elsewhere, or was changed since the change set
+    /// started by something other than it, such as another instance
+    /// exporting to the same place
+    fn ensure_replaceable(&self, path: &Path) -> Result<(), String> {
+        file_lock::ensure_not_in_use(path)?;
+        let modified = fs::metadata(path).and_then(|m| m.modified());
+        if !self.written.contains(path) && modified.is_ok_and(|m| m > self.started) {
+            return Err(format!(
+                "{} was changed by another program or Sweedle instance while this ran; \
+                 run it again to replace it",
+                path.display()
+            ));
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, action: ChangeAction, path: &Path, to: Option<&Path>, bytes: Option<u64>) {
+        self.changes.push(FileChange {
+            action,
+            path: path.to_string_lossy().to_string(),
+            to: to.map(|p| p.to_string_lossy().to_string()),
+            bytes,
+        });
+    }
+}
+
+/// Move a file, copying it when the destination is on another volume
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to).and_then(|_| fs::remove_file(from))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dry_run_reports_without_writing() {
+        let dir = std::env::temp_dir().join(format!("sweedle-changes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let existing = dir.join("model.glb");
+        let created = dir.join("model_fixed.glb");
+        fs::write(&existing, b"original").unwrap();
+
+        let read_only = AppSettings {
+            read_only: true,
+            ..Default::default()
+        };
+        assert!(ChangeSet::new(&read_only, None).is_err());
+
+        let mut changes = ChangeSet::new(&read_only, Some(true)).unwrap();
+        changes.write(&created, b"repaired").unwrap();
+        changes.write(&existing, b"overwritten").unwrap();
+        assert!(changes.delete(&existing).unwrap());
+        assert!(!changes.delete(&dir.join("missing.glb")).unwrap());
+
+        let report = changes.finish();
+        let actions: Vec<ChangeAction> = report.changes.iter().map(|c| c.action).collect();
+        assert_eq!(
+            actions,
+            [
+                ChangeAction::Create,
+                ChangeAction::Overwrite,
+                ChangeAction::Delete
+            ]
+        );
+        assert!(!created.exists());
+        assert_eq!(fs::read(&existing).unwrap(), b"original");
+
+        let mut changes = ChangeSet::new(&AppSettings::default(), None).unwrap();
+        changes.write(&created, b"repaired").unwrap();
+        changes.delete(&existing).unwrap();
+        assert!(!changes.finish().dry_run);
+        assert_eq!(fs::read(&created).unwrap(), b"repaired");
+        assert!(!existing.exists());
+
+        // A file another instance wrote while this ran isn't overwritten
+        let mut changes = ChangeSet::new(&AppSettings::default(), None).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(&existing, b"theirs").unwrap();
+        assert!(changes.write(&existing, b"ours").is_err());
+        assert_eq!(fs::read(&existing).unwrap(), b"theirs");
+        changes.write(&created, b"ours").unwrap();
+        changes.write(&created, b"ours again").unwrap();
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_rename_all_moves_back_on_failure() {
+        let dir = std::env::temp_dir().join(format!("sweedle-rename-all-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a.glb"), dir.join("b.png"));
+        fs::write(&a, b"model").unwrap();
+        fs::write(&b, b"thumbnail").unwrap();
+        let moves = [
+            (a.clone(), dir.join("moved/a.glb")),
+            (b.clone(), dir.join("moved/b.png")),
+            (dir.join("missing.bin"), dir.join("moved/missing.bin")),
+        ];
+
+        let mut changes = ChangeSet::new(&AppSettings::default(), None).unwrap();
+        let error = changes.rename_all(&moves).unwrap_err();
+        assert!(error.contains("moved back"), "{}", error);
+        assert_eq!(fs::read(&a).unwrap(), b"model");
+        assert_eq!(fs::read(&b).unwrap(), b"thumbnail");
+        assert!(!dir.join("moved/a.glb").exists());
+        assert!(changes.finish().changes.is_empty());
+
+        let mut changes = ChangeSet::new(&AppSettings::default(), None).unwrap();
+        changes.rename_all(&moves[..2]).unwrap();
+        assert_eq!(changes.finish().changes.len(), 2);
+        assert!(dir.join("moved/b.png").exists() && !b.exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
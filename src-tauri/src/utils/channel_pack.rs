@@ -0,0 +1,298 @@
+//! Packing grayscale maps into the channels of one texture, and back
+//!
+//! Game engines and glTF want occlusion, roughness and metallic in the red,
+//! green and blue channels of one "ORM" texture, while texturing tools often
+//! export them as separate grayscale maps. Packing takes any channel of up
+//! to four images, scaled to the largest of them, and can point a glTF's
+//! materials at the result; unpacking splits a texture into one grayscale
+//! map per channel.
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GrayImage, ImageFormat, Luma, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageChannel {
+    /// Grayscale images read the same in red, green and blue
+    #[default]
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+impl ImageChannel {
+    fn index(self) -> usize {
+        match self {
+            Self::Red => 0,
+            Self::Green => 1,
+            Self::Blue => 2,
+            Self::Alpha => 3,
+        }
+    }
+}
+
+/// One channel of an image file feeding one channel of a packed texture
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelSource {
+    pub path: String,
+    #[serde(default)]
+    pub channel: ImageChannel,
+    /// Use one minus the value, e.g. roughness from a gloss map
+    #[serde(default)]
+    pub invert: bool,
+}
+
+/// What `pack_texture_channels` writes
+///
+/// Channels without a source are set to `fill` (0 to 1, white by default).
+/// The output's extension picks its format; JPEG can't hold an alpha source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackSpec {
+    pub out_path: String,
+    #[serde(default)]
+    pub red: Option<ChannelSource>,
+    #[serde(default)]
+    pub green: Option<ChannelSource>,
+    #[serde(default)]
+    pub blue: Option<ChannelSource>,
+    #[serde(default)]
+    pub alpha: Option<ChannelSource>,
+    #[serde(default = "white")]
+    pub fill: [f32; 4],
+    /// A `.gltf` whose materials should use the packed texture
+    ///
+    /// Occlusion textures made from the red source and metallic-roughness
+    /// textures made from the green or blue source are repointed, as the
+    /// glTF ORM layout expects.
+    #[serde(default)]
+    pub model_path: Option<String>,
+}
+
+fn white() -> [f32; 4] {
+    [1.0; 4]
+}
+
+impl PackSpec {
+    /// Sources by output channel
+    pub fn sources(&self) -> [Option<&ChannelSource>; 4] {
+        [&self.red, &self.green, &self.blue, &self.alpha].map(Option::as_ref)
+    }
+}
+
+/// Each source channel of `images` in the matching channel of one texture
+///
+/// Images smaller than the largest are scaled up to it.
+pub fn pack(
+    images: [Option<(&RgbaImage, ImageChannel, bool)>; 4],
+    fill: [f32; 4],
+) -> Result<RgbaImage, String> {
+    let (width, height) = images
+        .iter()
+        .flatten()
+        .map(|(image, _, _)| image.dimensions())
+        .max_by_key(|&(w, h)| w as u64 * h as u64)
+        .ok_or("Nothing to pack; give at least one channel a source")?;
+    let fill = fill.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+    let mut packed = RgbaImage::from_pixel(width, height, Rgba(fill));
+
+    for (out, source) in images.iter().enumerate() {
+        let Some((image, channel, invert)) = source else {
+            continue;
+        };
+        let scaled;
+        let image = if image.dimensions() == (width, height) {
+            *image
+        } else {
+            scaled = imageops::resize(*image, width, height, FilterType::Triangle);
+            &scaled
+        };
+        for (to, from) in packed.pixels_mut().zip(image.pixels()) {
+            let value = from.0[channel.index()];
+            to.0[out] = if *invert { 255 - value } else { value };
+        }
+    }
+    Ok(packed)
+}
+
+/// One channel of `image` as a grayscale map
+pub fn unpack(image: &RgbaImage, channel: ImageChannel) -> GrayImage {
+    GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        Luma([image.get_pixel(x, y).0[channel.index()]])
+    })
+}
+
+/// `image` encoded in the format `path`'s extension names
+pub fn encode(image: DynamicImage, path: &Path) -> Result<Vec<u8>, String> {
+    let format = ImageFormat::from_path(path).map_err(|_| {
+        format!(
+            "{} is not an image format that can be written",
+            path.display()
+        )
+    })?;
+    let image = match (format, &image) {
+        (ImageFormat::Jpeg, DynamicImage::ImageRgba8(_)) => {
+            DynamicImage::ImageRgb8(image.to_rgb8())
+        }
+        _ => image,
+    };
+    let mut out = Cursor::new(Vec::new());
+    image
+        .write_to(&mut out, format)
+        .map_err(|e| format!("Failed to encode {}: {}", path.display(), e))?;
+    Ok(out.into_inner())
+}
+
+/// Point the materials of a glTF document whose textures come from the
+/// packed `sources` at a new texture showing `packed_uri`
+///
+/// `resolve` turns an image URI into the file it names, for comparing with
+/// the sources. New textures keep the sampler of the ones they replace;
+/// the old ones stay in the document. Returns the number of material
+/// textures repointed.
+pub fn repoint_materials(
+    document: &mut Value,
+    sources: &[Option<PathBuf>; 4],
+    packed_uri: &str,
+    resolve: impl Fn(&str) -> Option<PathBuf>,
+) -> usize {
+    let image_of_texture = |document: &Value, texture: &Value| -> Option<PathBuf> {
+        let texture = &document["textures"][texture.as_u64()? as usize];
+        let image = &document["images"][texture["source"].as_u64()? as usize];
+        resolve(image["uri"].as_str()?)
+    };
+    let from = |path: &Option<PathBuf>, channels: &[usize]| {
+        path.as_ref()
+            .is_some_and(|path| channels.iter().any(|&c| sources[c].as_ref() == Some(path)))
+    };
+
+    // Which texture slots to repoint, found before anything is added
+    let mut slots: Vec<(usize, &str, u64)> = Vec::new();
+    for (m, material) in document["materials"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        let occlusion = &material["occlusionTexture"]["index"];
+        if from(&image_of_texture(document, occlusion), &[0]) {
+            slots.push((m, "occlusion", occlusion.as_u64().unwrap_or_default()));
+        }
+        let orm = &material["pbrMetallicRoughness"]["metallicRoughnessTexture"]["index"];
+        if from(&image_of_texture(document, orm), &[1, 2]) {
+            slots.push((m, "metallic_roughness", orm.as_u64().unwrap_or_default()));
+        }
+    }
+    if slots.is_empty() {
+        return 0;
+    }
+
+    let push = |document: &mut Value, key: &str, item: Value| -> usize {
+        if !document[key].is_array() {
+            document[key] = json!([]);
+        }
+        let items = document[key].as_array_mut().expect("just made an array");
+        items.push(item);
+        items.len() - 1
+    };
+    let image = push(document, "images", json!({ "uri": packed_uri }));
+    let mut replacements: HashMap<u64, usize> = HashMap::new();
+    for &(m, slot, old) in &slots {
+        let texture = match replacements.get(&old) {
+            Some(&texture) => texture,
+            None => {
+                let mut texture = json!({ "source": image });
+                if let Some(sampler) = document["textures"][old as usize].get("sampler").cloned() {
+                    texture["sampler"] = sampler;
+                }
+                let texture = push(document, "textures", texture);
+                replacements.insert(old, texture);
+                texture
+            }
+        };
+        let material = &mut document["materials"][m];
+        let info = match slot {
+            "occlusion" => &mut material["occlusionTexture"],
+            _ => &mut material["pbrMetallicRoughness"]["metallicRoughnessTexture"],
+        };
+        info["index"] = json!(texture);
+    }
+    slots.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_scales_inverts_and_repoints_materials() {
+        let gray = |size: u32, value: u8| {
+            RgbaImage::from_pixel(size, size, Rgba([value, value, value, 255]))
+        };
+        let ao = gray(4, 200);
+        let gloss = gray(2, 30);
+        let metal = gray(4, 255);
+        let packed = pack(
+            [
+                Some((&ao, ImageChannel::Red, false)),
+                Some((&gloss, ImageChannel::Green, true)),
+                Some((&metal, ImageChannel::Blue, false)),
+                None,
+            ],
+            [1.0, 1.0, 1.0, 0.5],
+        )
+        .unwrap();
+        assert_eq!(packed.dimensions(), (4, 4));
+        assert_eq!(packed.get_pixel(3, 3).0, [200, 225, 255, 128]);
+        assert_eq!(
+            unpack(&packed, ImageChannel::Green).get_pixel(0, 0).0,
+            [225]
+        );
+        assert!(pack([None, None, None, None], [1.0; 4]).is_err());
+
+        let mut document = json!({
+            "images": [{ "uri": "ao.png" }, { "uri": "mr.png" }, { "uri": "albedo.png" }],
+            "textures": [{ "source": 0, "sampler": 0 }, { "source": 1 }, { "source": 2 }],
+            "materials": [
+                {
+                    "occlusionTexture": { "index": 0, "strength": 0.5 },
+                    "pbrMetallicRoughness": {
+                        "baseColorTexture": { "index": 2 },
+                        "metallicRoughnessTexture": { "index": 1 }
+                    }
+                },
+                { "occlusionTexture": { "index": 0 } }
+            ]
+        });
+        let sources = [
+            Some(PathBuf::from("/t/ao.png")),
+            Some(PathBuf::from("/t/mr.png")),
+            Some(PathBuf::from("/t/mr.png")),
+            None,
+        ];
+        let count = repoint_materials(&mut document, &sources, "orm.png", |uri| {
+            Some(Path::new("/t").join(uri))
+        });
+        assert_eq!(count, 3);
+        assert_eq!(document["images"][3]["uri"], "orm.png");
+        // Occlusion keeps its sampler and strength; both materials share it
+        assert_eq!(
+            document["textures"][3],
+            json!({ "source": 3, "sampler": 0 })
+        );
+        assert_eq!(document["materials"][0]["occlusionTexture"]["index"], 3);
+        assert_eq!(
+            document["materials"][0]["occlusionTexture"]["strength"],
+            0.5
+        );
+        assert_eq!(document["materials"][1]["occlusionTexture"]["index"], 3);
+        let material = &document["materials"][0]["pbrMetallicRoughness"];
+        assert_eq!(material["metallicRoughnessTexture"]["index"], 4);
+        assert_eq!(material["baseColorTexture"]["index"], 2);
+    }
+}
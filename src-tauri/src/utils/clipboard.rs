@@ -0,0 +1,122 @@
+//! Copying to the OS clipboard
+//!
+//! Rather than linking a clipboard library, this goes through the tools
+//! each platform ships: `pbcopy` and `osascript` on macOS, PowerShell on
+//! Windows, and `wl-copy` or `xclip` on Linux, whichever the desktop has.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Put text on the clipboard
+pub fn copy_text(text: &str) -> Result<(), String> {
+    if cfg!(target_os = "macos") {
+        pipe("pbcopy", &[], text.as_bytes())
+    } else if cfg!(windows) {
+        pipe(
+            "powershell",
+            &[
+                "-NoProfile",
+                "-Command",
+                "[Console]::InputEncoding = [Text.Encoding]::UTF8; \
+                 Set-Clipboard -Value ([Console]::In.ReadToEnd())",
+            ],
+            text.as_bytes(),
+        )
+    } else if is_wayland() {
+        pipe("wl-copy", &[], text.as_bytes())
+    } else {
+        pipe("xclip", &["-selection", "clipboard"], text.as_bytes())
+    }
+}
+
+/// Put a PNG image on the clipboard
+pub fn copy_png(png: &[u8]) -> Result<(), String> {
+    if !(cfg!(target_os = "macos") || cfg!(windows)) {
+        return if is_wayland() {
+            pipe("wl-copy", &["--type", "image/png"], png)
+        } else {
+            pipe(
+                "xclip",
+                &["-selection", "clipboard", "-t", "image/png"],
+                png,
+            )
+        };
+    }
+
+    // Neither macOS nor Windows reads images from standard input
+    let path = std::env::temp_dir().join(format!("sweedle-clipboard-{}.png", std::process::id()));
+    std::fs::write(&path, png).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    let copied = if cfg!(target_os = "macos") {
+        let script = format!(
+            "set the clipboard to (read (POSIX file \"{}\") as «class PNGf»)",
+            escaped(&path, '"', "\\\"")
+        );
+        run(Command::new("osascript").args(["-e", &script]))
+    } else {
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms, System.Drawing; \
+             $image = [System.Drawing.Image]::FromFile('{}'); \
+             [System.Windows.Forms.Clipboard]::SetImage($image); $image.Dispose()",
+            escaped(&path, '\'', "''")
+        );
+        run(Command::new("powershell").args(["-NoProfile", "-STA", "-Command", &script]))
+    };
+    let _ = std::fs::remove_file(&path);
+    copied
+}
+
+fn is_wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+fn escaped(path: &Path, quote: char, replacement: &str) -> String {
+    path.to_string_lossy().replace(quote, replacement)
+}
+
+/// Run `program` with `input` on its standard input
+///
+/// Its output isn't read: `xclip` and `wl-copy` leave a process behind to
+/// serve the clipboard, which would hold the pipes open.
+fn pipe(program: &str, args: &[&str], input: &[u8]) -> Result<(), String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start {} for the clipboard: {}", program, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input)
+            .map_err(|e| format!("Failed to write to {}: {}", program, e))?;
+    }
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} couldn't copy to the clipboard ({})",
+            program, status
+        ))
+    }
+}
+
+fn run(command: &mut Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} couldn't copy to the clipboard: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
@@ -0,0 +1,430 @@
+//! Checking that textures are encoded for how materials use them
+//!
+//! glTF decides a texture's color space by the slot it's used in: base
+//! color and emissive are sRGB, normal, occlusion and metallic-roughness
+//! maps are linear data. Problems come from the files themselves: PNGs
+//! tagged with the other color space, which engines that honor the tag
+//! import wrongly, normal maps saved with sRGB gamma applied, which come
+//! out bent toward X and Y, and one image used in slots of both kinds.
+//! Tags are fixed by rewriting the PNG's color chunks, gamma-encoded normal
+//! maps by decoding their pixels back to linear.
+
+use image::{ImageFormat, ImageReader};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Cursor;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+/// PNG chunks that say how colors are encoded
+const COLOR_CHUNKS: [&[u8; 4]; 4] = [b"sRGB", b"iCCP", b"gAMA", b"cHRM"];
+/// Mean red and green, 0 to 1, between which a normal map most likely has
+/// sRGB gamma applied: flat normals read 0.5 linear and 0.735 encoded
+const ENCODED_NORMAL_MEAN: (f32, f32) = (0.65, 0.8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorSpace {
+    Srgb,
+    Linear,
+}
+
+/// Material texture slots and the color space glTF reads them in, by path
+/// below the material
+const SLOTS: [(&[&str], &str, ColorSpace); 10] = [
+    (
+        &["pbrMetallicRoughness", "baseColorTexture"],
+        "base color",
+        ColorSpace::Srgb,
+    ),
+    (&["emissiveTexture"], "emissive", ColorSpace::Srgb),
+    (
+        &[
+            "extensions",
+            "KHR_materials_specular",
+            "specularColorTexture",
+        ],
+        "specular color",
+        ColorSpace::Srgb,
+    ),
+    (
+        &["extensions", "KHR_materials_sheen", "sheenColorTexture"],
+        "sheen color",
+        ColorSpace::Srgb,
+    ),
+    (&["normalTexture"], "normal", ColorSpace::Linear),
+    (&["occlusionTexture"], "occlusion", ColorSpace::Linear),
+    (
+        &["pbrMetallicRoughness", "metallicRoughnessTexture"],
+        "metallic-roughness",
+        ColorSpace::Linear,
+    ),
+    (
+        &[
+            "extensions",
+            "KHR_materials_clearcoat",
+            "clearcoatNormalTexture",
+        ],
+        "clearcoat normal",
+        ColorSpace::Linear,
+    ),
+    (
+        &[
+            "extensions",
+            "KHR_materials_clearcoat",
+            "clearcoatRoughnessTexture",
+        ],
+        "clearcoat roughness",
+        ColorSpace::Linear,
+    ),
+    (
+        &[
+            "extensions",
+            "KHR_materials_transmission",
+            "transmissionTexture",
+        ],
+        "transmission",
+        ColorSpace::Linear,
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorSpaceIssueKind {
+    /// Used in both sRGB and linear slots, so one of them reads it wrongly
+    SharedAcrossColorSpaces,
+    /// A PNG tagged sRGB used only as data
+    SrgbTaggedData,
+    /// A PNG tagged linear used only as color
+    LinearTaggedColor,
+    /// A normal map whose values look gamma encoded
+    SrgbEncodedNormals,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorSpaceIssue {
+    pub image: usize,
+    /// The image's name or URI
+    pub name: String,
+    pub kind: ColorSpaceIssueKind,
+    /// Slots the image is used in, such as `base color`
+    pub slots: Vec<String>,
+    /// Whether `fix` can repair it
+    pub fixable: bool,
+}
+
+impl ColorSpaceIssue {
+    /// A one-line description for reports
+    pub fn describe(&self) -> String {
+        let problem = match self.kind {
+            ColorSpaceIssueKind::SharedAcrossColorSpaces => "used as both color and data",
+            ColorSpaceIssueKind::SrgbTaggedData => "tagged sRGB but used as data",
+            ColorSpaceIssueKind::LinearTaggedColor => "tagged linear but used as color",
+            ColorSpaceIssueKind::SrgbEncodedNormals => "normal map looks sRGB encoded",
+        };
+        format!("{}: {} ({})", self.name, problem, self.slots.join(", "))
+    }
+}
+
+/// Color space problems of the images a glTF document's materials use
+///
+/// `image_bytes` gives an image's encoded file by index; images it can't
+/// give are left out.
+pub fn audit(json: &Value, image_bytes: impl Fn(usize) -> Option<Vec<u8>>) -> Vec<ColorSpaceIssue> {
+    // Slots each image is used in, in image order
+    let mut usage: Vec<Vec<(&str, ColorSpace)>> = vec![Vec::new(); count(json, "images")];
+    for material in json["materials"].as_array().into_iter().flatten() {
+        for (path, slot, space) in SLOTS {
+            let info = path.iter().fold(material, |value, key| &value[key]);
+            let image = info["index"]
+                .as_u64()
+                .and_then(|texture| json["textures"][texture as usize]["source"].as_u64());
+            if let Some(used) = image.and_then(|image| usage.get_mut(image as usize)) {
+                if !used.contains(&(slot, space)) {
+                    used.push((slot, space));
+                }
+            }
+        }
+    }
+
+    let mut issues = Vec::new();
+    for (image, used) in usage.iter().enumerate() {
+        if used.is_empty() {
+            continue;
+        }
+        let entry = &json["images"][image];
+        let name = entry["name"]
+            .as_str()
+            .or(entry["uri"]
+                .as_str()
+                .filter(|uri| !uri.starts_with("data:")))
+            .map_or_else(|| format!("image {}", image), str::to_string);
+        let issue = |kind, fixable| ColorSpaceIssue {
+            image,
+            name: name.clone(),
+            kind,
+            slots: used.iter().map(|(slot, _)| slot.to_string()).collect(),
+            fixable,
+        };
+        let as_color = used.iter().any(|&(_, space)| space == ColorSpace::Srgb);
+        let as_data = used.iter().any(|&(_, space)| space == ColorSpace::Linear);
+        if as_color && as_data {
+            issues.push(issue(ColorSpaceIssueKind::SharedAcrossColorSpaces, false));
+            continue;
+        }
+        let Some(bytes) = image_bytes(image) else {
+            continue;
+        };
+        match (png_color_space(&bytes), as_color) {
+            (Some(ColorSpace::Srgb), false) => {
+                issues.push(issue(ColorSpaceIssueKind::SrgbTaggedData, true))
+            }
+            (Some(ColorSpace::Linear), true) => {
+                issues.push(issue(ColorSpaceIssueKind::LinearTaggedColor, true))
+            }
+            _ => {}
+        }
+        let normal = used.iter().any(|(slot, _)| slot.ends_with("normal"));
+        if normal && looks_gamma_encoded(&bytes) {
+            issues.push(issue(ColorSpaceIssueKind::SrgbEncodedNormals, true));
+        }
+    }
+    issues
+}
+
+/// The image file with `issue` repaired, `None` if it can't be
+pub fn fix(bytes: &[u8], kind: ColorSpaceIssueKind) -> Result<Option<Vec<u8>>, String> {
+    match kind {
+        ColorSpaceIssueKind::SharedAcrossColorSpaces => Ok(None),
+        ColorSpaceIssueKind::SrgbTaggedData => retag_png(bytes, None).map(Some),
+        ColorSpaceIssueKind::LinearTaggedColor => {
+            retag_png(bytes, Some(ColorSpace::Srgb)).map(Some)
+        }
+        ColorSpaceIssueKind::SrgbEncodedNormals => {
+            let reader = ImageReader::new(Cursor::new(bytes))
+                .with_guessed_format()
+                .map_err(|e| format!("Failed to read image: {}", e))?;
+            let format = reader.format().unwrap_or(ImageFormat::Png);
+            let mut image = reader
+                .decode()
+                .map_err(|e| format!("Failed to decode image: {}", e))?
+                .to_rgba8();
+            for pixel in image.pixels_mut() {
+                for channel in &mut pixel.0[..3] {
+                    *channel = (to_linear(*channel as f32 / 255.0) * 255.0).round() as u8;
+                }
+            }
+            let image = match format {
+                ImageFormat::Jpeg => {
+                    image::DynamicImage::ImageRgb8(image::DynamicImage::ImageRgba8(image).to_rgb8())
+                }
+                _ => image::DynamicImage::ImageRgba8(image),
+            };
+            let mut out = Cursor::new(Vec::new());
+            image
+                .write_to(&mut out, format)
+                .map_err(|e| format!("Failed to encode image: {}", e))?;
+            Ok(Some(out.into_inner()))
+        }
+    }
+}
+
+/// The color space a PNG's chunks declare, if any
+///
+/// An `sRGB` chunk or an ICC profile take precedence over `gAMA`, as the
+/// PNG specification asks. Profiles count as sRGB unless named linear.
+pub fn png_color_space(bytes: &[u8]) -> Option<ColorSpace> {
+    let mut gamma = None;
+    for (kind, data) in png_chunks(bytes)? {
+        match &kind {
+            b"sRGB" => return Some(ColorSpace::Srgb),
+            b"iCCP" => {
+                let name = data.split(|&b| b == 0).next().unwrap_or_default();
+                let linear = String::from_utf8_lossy(name)
+                    .to_lowercase()
+                    .contains("linear");
+                return Some(if linear {
+                    ColorSpace::Linear
+                } else {
+                    ColorSpace::Srgb
+                });
+            }
+            b"gAMA" if data.len() == 4 => {
+                // Stored as 100000 times the encoding gamma
+                gamma = match u32::from_be_bytes([data[0], data[1], data[2], data[3]]) {
+                    95_000..=105_000 => Some(ColorSpace::Linear),
+                    43_000..=48_000 => Some(ColorSpace::Srgb),
+                    _ => None,
+                };
+            }
+            b"IDAT" => break,
+            _ => {}
+        }
+    }
+    gamma
+}
+
+/// A PNG with its color chunks replaced by an `sRGB` chunk for `Srgb`, a
+/// linear `gAMA` for `Linear`, or nothing
+fn retag_png(bytes: &[u8], tag: Option<ColorSpace>) -> Result<Vec<u8>, String> {
+    let chunks = png_chunks(bytes).ok_or("Only PNG color space tags can be rewritten")?;
+    let mut out = PNG_SIGNATURE.to_vec();
+    for (kind, data) in chunks {
+        if COLOR_CHUNKS.contains(&&kind) {
+            continue;
+        }
+        write_chunk(&mut out, &kind, data);
+        if &kind == b"IHDR" {
+            match tag {
+                Some(ColorSpace::Srgb) => write_chunk(&mut out, b"sRGB", &[0]),
+                Some(ColorSpace::Linear) => {
+                    write_chunk(&mut out, b"gAMA", &100_000u32.to_be_bytes())
+                }
+                None => {}
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Whether a normal map's mean red and green are where sRGB encoding
+/// moves flat normals
+fn looks_gamma_encoded(bytes: &[u8]) -> bool {
+    let Ok(image) = image::load_from_memory(bytes) else {
+        return false;
+    };
+    let image = image.to_rgb8();
+    // A few thousand pixels across the image are plenty for a mean
+    let step = (image.len() / 3 / 4096).max(1);
+    let (mut sum, mut count) = ([0.0f32; 2], 0);
+    for pixel in image.pixels().step_by(step) {
+        sum[0] += pixel.0[0] as f32 / 255.0;
+        sum[1] += pixel.0[1] as f32 / 255.0;
+        count += 1;
+    }
+    let (lo, hi) = ENCODED_NORMAL_MEAN;
+    count > 0 && sum.iter().all(|&s| (lo..hi).contains(&(s / count as f32)))
+}
+
+/// Chunks of a PNG file as type and data, `None` if it isn't one
+fn png_chunks(bytes: &[u8]) -> Option<Vec<([u8; 4], &[u8])>> {
+    let mut rest = bytes.strip_prefix(&PNG_SIGNATURE)?;
+    let mut chunks = Vec::new();
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let kind = [rest[4], rest[5], rest[6], rest[7]];
+        let data = rest.get(8..8 + length)?;
+        chunks.push((kind, data));
+        rest = rest.get(12 + length..)?;
+        if &kind == b"IEND" {
+            break;
+        }
+    }
+    Some(chunks)
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    out.extend_from_slice(&crc32(kind.iter().chain(data)).to_be_bytes());
+}
+
+/// The CRC-32 each PNG chunk ends with
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn to_linear(encoded: f32) -> f32 {
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn count(json: &Value, key: &str) -> usize {
+    json[key].as_array().map_or(0, Vec::len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use serde_json::json;
+
+    fn png(color: [u8; 3], tag: Option<ColorSpace>) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        RgbImage::from_pixel(4, 4, Rgb(color))
+            .write_to(&mut out, ImageFormat::Png)
+            .unwrap();
+        retag_png(&out.into_inner(), tag).unwrap()
+    }
+
+    #[test]
+    fn test_mismatched_tags_and_encoded_normals_are_found_and_fixed() {
+        let document = json!({
+            "images": [
+                { "uri": "albedo.png" },
+                { "uri": "normal.png" },
+                { "uri": "orm.png" },
+                { "uri": "shared.png" }
+            ],
+            "textures": [{ "source": 0 }, { "source": 1 }, { "source": 2 }, { "source": 3 }],
+            "materials": [{
+                "pbrMetallicRoughness": {
+                    "baseColorTexture": { "index": 0 },
+                    "metallicRoughnessTexture": { "index": 2 }
+                },
+                "normalTexture": { "index": 1 },
+                "emissiveTexture": { "index": 3 },
+                "occlusionTexture": { "index": 3 }
+            }]
+        });
+        let files = [
+            png([200, 100, 50], Some(ColorSpace::Linear)),
+            // A flat normal map saved with sRGB gamma applied
+            png([188, 188, 255], None),
+            png([255, 128, 0], Some(ColorSpace::Srgb)),
+            png([0, 0, 0], None),
+        ];
+        let issues = audit(&document, |i| files.get(i).cloned());
+        let kinds: Vec<(usize, ColorSpaceIssueKind)> = issues
+            .iter()
+            .map(|issue| (issue.image, issue.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (0, ColorSpaceIssueKind::LinearTaggedColor),
+                (1, ColorSpaceIssueKind::SrgbEncodedNormals),
+                (2, ColorSpaceIssueKind::SrgbTaggedData),
+                (3, ColorSpaceIssueKind::SharedAcrossColorSpaces),
+            ]
+        );
+        assert_eq!(
+            issues[3].describe(),
+            "shared.png: used as both color and data (emissive, occlusion)"
+        );
+
+        let fixed = fix(&files[0], issues[0].kind).unwrap().unwrap();
+        assert_eq!(png_color_space(&fixed), Some(ColorSpace::Srgb));
+        let untagged = fix(&files[2], issues[2].kind).unwrap().unwrap();
+        assert_eq!(png_color_space(&untagged), None);
+        assert!(image::load_from_memory(&untagged).is_ok());
+        let normals = fix(&files[1], issues[1].kind).unwrap().unwrap();
+        let pixel = image::load_from_memory(&normals).unwrap().to_rgb8();
+        assert_eq!(pixel.get_pixel(0, 0).0, [128, 128, 255]);
+        assert_eq!(fix(&files[3], issues[3].kind).unwrap(), None);
+    }
+}
@@ -0,0 +1,332 @@
+use super::{sphere_directions, Adjacency, AoParams, ComputeBackend, VoxelGrid, FALLBACK_NORMAL};
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Multi-threaded CPU implementation, always available
+pub struct CpuBackend;
+
+impl ComputeBackend for CpuBackend {
+    fn name(&self) -> &'static str {
+        "cpu"
+    }
+
+    fn vertex_normals(&self, positions: &[f32], indices: &[u32]) -> Result<Vec<f32>, String> {
+        let vertex_count = positions.len() / 3;
+        let faces = Adjacency::vertex_faces(vertex_count, indices);
+
+        Ok((0..vertex_count)
+            .into_par_iter()
+            .flat_map_iter(|v| {
+                let mut sum = [0.0f32; 3];
+                for &face in faces.of(v) {
+                    let f = face as usize * 3;
+                    let n = face_cross(
+                        point(positions, indices[f]),
+                        point(positions, indices[f + 1]),
+                        point(positions, indices[f + 2]),
+                    );
+                    for axis in 0..3 {
+                        sum[axis] += n[axis];
+                    }
+                }
+                normalize_or(sum, FALLBACK_NORMAL)
+            })
+            .collect())
+    }
+
+    fn mean_curvature(
+        &self,
+        positions: &[f32],
+        normals: &[f32],
+        indices: &[u32],
+    ) -> Result<Vec<f32>, String> {
+        let vertex_count = positions.len() / 3;
+        let neighbors = Adjacency::vertex_neighbors(vertex_count, indices);
+
+        Ok((0..vertex_count)
+            .into_par_iter()
+            .map(|v| {
+                let p = point(positions, v as u32);
+                let ring = neighbors.of(v);
+                if ring.is_empty() {
+                    return 0.0;
+                }
+
+                let mut centroid = [0.0f32; 3];
+                let mut mean_sq = 0.0f32;
+                for &q in ring {
+                    let q = point(positions, q);
+                    let d = sub(q, p);
+                    for axis in 0..3 {
+                        centroid[axis] += q[axis];
+                    }
+                    mean_sq += dot(d, d);
+                }
+                let count = ring.len() as f32;
+                centroid = centroid.map(|c| c / count);
+                mean_sq /= count;
+                if mean_sq <= 0.0 {
+                    return 0.0;
+                }
+
+                // The umbrella vector points inward by ~d^2 / 2r on a sphere of radius r
+                let n = point(normals, v as u32);
+                -2.0 * dot(n, sub(centroid, p)) / mean_sq
+            })
+            .collect())
+    }
+
+    fn voxelize(
+        &self,
+        positions: &[f32],
+        indices: &[u32],
+        grid: &mut VoxelGrid,
+    ) -> Result<(), String> {
+        let bits: Vec<AtomicU32> = grid.bits.iter().map(|&w| AtomicU32::new(w)).collect();
+        let grid_ref = &*grid;
+
+        indices.par_chunks_exact(3).for_each(|tri| {
+            let a = point(positions, tri[0]);
+            let b = point(positions, tri[1]);
+            let c = point(positions, tri[2]);
+            for_each_overlapped_voxel(grid_ref, a, b, c, |index| {
+                bits[index / 32].fetch_or(1 << (index % 32), Ordering::Relaxed);
+            });
+        });
+
+        grid.bits = bits.into_iter().map(AtomicU32::into_inner).collect();
+        Ok(())
+    }
+
+    fn ambient_occlusion(
+        &self,
+        positions: &[f32],
+        normals: &[f32],
+        grid: &VoxelGrid,
+        params: AoParams,
+    ) -> Result<Vec<f32>, String> {
+        let directions = sphere_directions(params.samples);
+        let vertex_count = positions.len() / 3;
+
+        Ok((0..vertex_count)
+            .into_par_iter()
+            .map(|v| {
+                let n = point(normals, v as u32);
+                let p = point(positions, v as u32);
+                let origin = [0, 1, 2].map(|i| p[i] + n[i] * grid.voxel_size * 1.5);
+
+                let mut open = 0.0f32;
+                let mut total = 0.0f32;
+                for &d in &directions {
+                    let mut cos = dot(d, n);
+                    let d = if cos < 0.0 { d.map(|c| -c) } else { d };
+                    cos = cos.abs();
+
+                    total += cos;
+                    if !ray_hits(grid, origin, d, params.max_steps) {
+                        open += cos;
+                    }
+                }
+
+                if total > 0.0 {
+                    open / total
+                } else {
+                    1.0
+                }
+            })
+            .collect())
+    }
+}
+
+/// March a ray through the grid in half-voxel steps
+pub fn ray_hits(grid: &VoxelGrid, origin: [f32; 3], direction: [f32; 3], max_steps: u32) -> bool {
+    let step = grid.voxel_size * 0.5;
+    for i in 1..=max_steps * 2 {
+        let t = step * i as f32;
+        let mut cell = [0u32; 3];
+        for axis in 0..3 {
+            let coord = (origin[axis] + direction[axis] * t - grid.origin[axis]) / grid.voxel_size;
+            if coord < 0.0 || coord >= grid.resolution[axis] as f32 {
+                return false;
+            }
+            cell[axis] = coord as u32;
+        }
+        if grid.get(grid.index(cell[0], cell[1], cell[2])) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Visit voxels whose bounding sphere touches the triangle
+pub fn for_each_overlapped_voxel(
+    grid: &VoxelGrid,
+    a: [f32; 3],
+    b: [f32; 3],
+    c: [f32; 3],
+    mut visit: impl FnMut(usize),
+) {
+    let size = grid.voxel_size;
+    let reach = size * 0.866_025_4;
+
+    let mut lo = [0u32; 3];
+    let mut hi = [0u32; 3];
+    for axis in 0..3 {
+        let min = a[axis].min(b[axis]).min(c[axis]) - reach;
+        let max = a[axis].max(b[axis]).max(c[axis]) + reach;
+        let limit = grid.resolution[axis] as f32 - 1.0;
+        lo[axis] = ((min - grid.origin[axis]) / size).floor().clamp(0.0, limit) as u32;
+        hi[axis] = ((max - grid.origin[axis]) / size).floor().clamp(0.0, limit) as u32;
+    }
+
+    for z in lo[2]..=hi[2] {
+        for y in lo[1]..=hi[1] {
+            for x in lo[0]..=hi[0] {
+                let center = [
+                    grid.origin[0] + (x as f32 + 0.5) * size,
+                    grid.origin[1] + (y as f32 + 0.5) * size,
+                    grid.origin[2] + (z as f32 + 0.5) * size,
+                ];
+                let closest = closest_point_on_triangle(center, a, b, c);
+                let d = sub(center, closest);
+                if dot(d, d) <= reach * reach {
+                    visit(grid.index(x, y, z));
+                }
+            }
+        }
+    }
+}
+
+/// Closest point on triangle `abc` to `p` (Ericson, Real-Time Collision Detection 5.1.5)
+pub fn closest_point_on_triangle(p: [f32; 3], a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    let ab = sub(b, a);
+    let ac = sub(c, a);
+    let ap = sub(p, a);
+    let d1 = dot(ab, ap);
+    let d2 = dot(ac, ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = sub(p, b);
+    let d3 = dot(ab, bp);
+    let d4 = dot(ac, bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let v = d1 / (d1 - d3);
+        return add_scaled(a, ab, v);
+    }
+
+    let cp = sub(p, c);
+    let d5 = dot(ab, cp);
+    let d6 = dot(ac, cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let w = d2 / (d2 - d6);
+        return add_scaled(a, ac, w);
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return add_scaled(b, sub(c, b), w);
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    let v = vb * denom;
+    let w = vc * denom;
+    add_scaled(add_scaled(a, ab, v), ac, w)
+}
+
+fn point(values: &[f32], index: u32) -> [f32; 3] {
+    let i = index as usize * 3;
+    [values[i], values[i + 1], values[i + 2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn add_scaled(a: [f32; 3], d: [f32; 3], t: f32) -> [f32; 3] {
+    [a[0] + d[0] * t, a[1] + d[1] * t, a[2] + d[2] * t]
+}
+
+fn face_cross(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    let e1 = sub(b, a);
+    let e2 = sub(c, a);
+    [
+        e1[1] * e2[2] - e1[2] * e2[1],
+        e1[2] * e2[0] - e1[0] * e2[2],
+        e1[0] * e2[1] - e1[1] * e2[0],
+    ]
+}
+
+fn normalize_or(v: [f32; 3], fallback: [f32; 3]) -> [f32; 3] {
+    let len = dot(v, v).sqrt();
+    if len > 1e-20 {
+        v.map(|c| c / len)
+    } else {
+        fallback
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::compute::MAX_VOXEL_RESOLUTION;
+
+    /// Unit cube centered at the origin with outward winding
+    fn cube() -> (Vec<f32>, Vec<u32>) {
+        let positions = vec![
+            -0.5, -0.5, -0.5, 0.5, -0.5, -0.5, 0.5, 0.5, -0.5, -0.5, 0.5, -0.5, -0.5, -0.5, 0.5,
+            0.5, -0.5, 0.5, 0.5, 0.5, 0.5, -0.5, 0.5, 0.5,
+        ];
+        let indices = vec![
+            0, 2, 1, 0, 3, 2, 4, 5, 6, 4, 6, 7, 0, 1, 5, 0, 5, 4, 2, 3, 7, 2, 7, 6, 1, 2, 6, 1, 6,
+            5, 0, 4, 7, 0, 7, 3,
+        ];
+        (positions, indices)
+    }
+
+    #[test]
+    fn test_cube_normals_point_outward() {
+        let (positions, indices) = cube();
+        let normals = CpuBackend.vertex_normals(&positions, &indices).unwrap();
+
+        for (p, n) in positions.chunks_exact(3).zip(normals.chunks_exact(3)) {
+            let outward = p[0] * n[0] + p[1] * n[1] + p[2] * n[2];
+            assert!(outward > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_filled_cube_is_solid() {
+        let (positions, indices) = cube();
+        assert!(VoxelGrid::fitting(&positions, MAX_VOXEL_RESOLUTION + 1).is_err());
+        let mut grid = VoxelGrid::fitting(&positions, 16).unwrap();
+        CpuBackend
+            .voxelize(&positions, &indices, &mut grid)
+            .unwrap();
+
+        let surface = grid.occupied_count();
+        grid.fill_interior();
+        assert!(surface > 0);
+        assert!(grid.occupied_count() > surface);
+
+        let [rx, ry, rz] = grid.resolution;
+        assert!(grid.get(grid.index(rx / 2, ry / 2, rz / 2)));
+        assert!(!grid.get(grid.index(0, 0, 0)));
+    }
+}
@@ -0,0 +1,361 @@
+use super::{sphere_directions, Adjacency, AoParams, ComputeBackend, VoxelGrid};
+use std::sync::{mpsc, Arc, OnceLock};
+use wgpu::util::DeviceExt;
+
+const WORKGROUP_SIZE: u32 = 64;
+const MAX_GROUPS_PER_DIM: u32 = 65_535;
+
+const COMMON_WGSL: &str = include_str!("shaders/common.wgsl");
+const NORMALS_WGSL: &str = include_str!("shaders/normals.wgsl");
+const CURVATURE_WGSL: &str = include_str!("shaders/curvature.wgsl");
+const VOXELIZE_WGSL: &str = include_str!("shaders/voxelize.wgsl");
+const AO_WGSL: &str = include_str!("shaders/ao.wgsl");
+
+static SHARED: OnceLock<Option<Arc<GpuBackend>>> = OnceLock::new();
+
+/// wgpu compute implementation
+///
+/// Adjacency is built on the CPU; kernels only gather, so no float atomics
+/// are needed and results match the CPU backend.
+pub struct GpuBackend {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    adapter_name: String,
+    max_binding_size: u64,
+}
+
+impl GpuBackend {
+    /// Process-wide GPU context, created on first use
+    pub fn shared() -> Option<Arc<GpuBackend>> {
+        SHARED
+            .get_or_init(|| match Self::new() {
+                Ok(gpu) => {
+                    log::info!("GPU compute available on {}", gpu.adapter_name);
+                    Some(Arc::new(gpu))
+                }
+                Err(e) => {
+                    log::info!("GPU compute unavailable: {}", e);
+                    None
+                }
+            })
+            .clone()
+    }
+
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    fn new() -> Result<Self, String> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .map_err(|e| format!("No GPU adapter: {}", e))?;
+
+        let limits = adapter.limits();
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("sweedle-compute"),
+            required_limits: limits.clone(),
+            ..Default::default()
+        }))
+        .map_err(|e| format!("Failed to open GPU device: {}", e))?;
+
+        Ok(Self {
+            device,
+            queue,
+            adapter_name: adapter.get_info().name,
+            max_binding_size: limits.max_storage_buffer_binding_size as u64,
+        })
+    }
+
+    fn storage<T: bytemuck::Pod>(&self, label: &str, data: &[T]) -> Result<wgpu::Buffer, String> {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        if bytes.len() as u64 > self.max_binding_size {
+            return Err(format!(
+                "{} buffer of {} bytes exceeds the GPU binding limit of {}",
+                label,
+                bytes.len(),
+                self.max_binding_size
+            ));
+        }
+
+        // Zero-sized bindings are invalid
+        let padding = [0u8; 4];
+        let contents = if bytes.is_empty() {
+            &padding[..]
+        } else {
+            bytes
+        };
+
+        Ok(self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            }))
+    }
+
+    fn output(&self, label: &str, size: u64) -> Result<wgpu::Buffer, String> {
+        if size > self.max_binding_size {
+            return Err(format!(
+                "{} buffer of {} bytes exceeds the GPU binding limit of {}",
+                label, size, self.max_binding_size
+            ));
+        }
+        Ok(self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: size.max(4),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        }))
+    }
+
+    /// Run a kernel over `items` invocations and read back `result`
+    ///
+    /// Binding 0 is the uniform block; `buffers` fill bindings 1.. in order.
+    fn run(
+        &self,
+        kernel: &str,
+        params: &[u32],
+        buffers: &[&wgpu::Buffer],
+        result: &wgpu::Buffer,
+        items: u32,
+    ) -> Result<Vec<u8>, String> {
+        let scope = self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let module = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(format!("{}\n{}", COMMON_WGSL, kernel).into()),
+            });
+        let pipeline = self
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: None,
+                module: &module,
+                entry_point: Some("main"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+        let uniform = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("params"),
+                contents: bytemuck::cast_slice(params),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: uniform.as_entire_binding(),
+        }];
+        for (i, buffer) in buffers.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: i as u32 + 1,
+                resource: buffer.as_entire_binding(),
+            });
+        }
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: result.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let groups = items.div_ceil(WORKGROUP_SIZE).max(1);
+        let groups_x = groups.min(MAX_GROUPS_PER_DIM);
+        let groups_y = groups.div_ceil(groups_x);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(groups_x, groups_y, 1);
+        }
+        encoder.copy_buffer_to_buffer(result, 0, &staging, 0, result.size());
+        self.queue.submit([encoder.finish()]);
+
+        if let Some(error) = pollster::block_on(scope.pop()) {
+            return Err(format!("GPU validation error: {}", error));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        staging.map_async(wgpu::MapMode::Read, .., move |status| {
+            let _ = sender.send(status);
+        });
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|e| format!("GPU poll failed: {}", e))?;
+        receiver
+            .recv()
+            .map_err(|e| format!("GPU readback cancelled: {}", e))?
+            .map_err(|e| format!("GPU readback failed: {}", e))?;
+
+        let bytes = staging
+            .slice(..)
+            .get_mapped_range()
+            .map_err(|e| format!("GPU readback failed: {}", e))?
+            .to_vec();
+        staging.unmap();
+        Ok(bytes)
+    }
+}
+
+impl ComputeBackend for GpuBackend {
+    fn name(&self) -> &'static str {
+        "gpu"
+    }
+
+    fn vertex_normals(&self, positions: &[f32], indices: &[u32]) -> Result<Vec<f32>, String> {
+        let vertex_count = (positions.len() / 3) as u32;
+        let faces = Adjacency::vertex_faces(vertex_count as usize, indices);
+
+        let output = self.output("normals", positions.len() as u64 * 4)?;
+        let bytes = self.run(
+            NORMALS_WGSL,
+            &[vertex_count, 0, 0, 0],
+            &[
+                &self.storage("positions", positions)?,
+                &self.storage("indices", indices)?,
+                &self.storage("offsets", &faces.offsets)?,
+                &self.storage("faces", &faces.items)?,
+                &output,
+            ],
+            &output,
+            vertex_count,
+        )?;
+
+        Ok(floats(&bytes, positions.len()))
+    }
+
+    fn mean_curvature(
+        &self,
+        positions: &[f32],
+        normals: &[f32],
+        indices: &[u32],
+    ) -> Result<Vec<f32>, String> {
+        let vertex_count = (positions.len() / 3) as u32;
+        let neighbors = Adjacency::vertex_neighbors(vertex_count as usize, indices);
+
+        let output = self.output("curvature", vertex_count as u64 * 4)?;
+        let bytes = self.run(
+            CURVATURE_WGSL,
+            &[vertex_count, 0, 0, 0],
+            &[
+                &self.storage("positions", positions)?,
+                &self.storage("normals", normals)?,
+                &self.storage("offsets", &neighbors.offsets)?,
+                &self.storage("neighbors", &neighbors.items)?,
+                &output,
+            ],
+            &output,
+            vertex_count,
+        )?;
+
+        Ok(floats(&bytes, vertex_count as usize))
+    }
+
+    fn voxelize(
+        &self,
+        positions: &[f32],
+        indices: &[u32],
+        grid: &mut VoxelGrid,
+    ) -> Result<(), String> {
+        let face_count = (indices.len() / 3) as u32;
+        let [rx, ry, rz] = grid.resolution;
+        let [ox, oy, oz] = grid.origin;
+
+        let bits = self.storage("voxels", &grid.bits)?;
+        let bytes = self.run(
+            VOXELIZE_WGSL,
+            &[
+                rx,
+                ry,
+                rz,
+                0,
+                ox.to_bits(),
+                oy.to_bits(),
+                oz.to_bits(),
+                grid.voxel_size.to_bits(),
+                face_count,
+                0,
+                0,
+                0,
+            ],
+            &[
+                &self.storage("positions", positions)?,
+                &self.storage("indices", indices)?,
+                &bits,
+            ],
+            &bits,
+            face_count,
+        )?;
+
+        grid.bits = bytemuck::cast_slice::<u8, u32>(&bytes)[..grid.bits.len()].to_vec();
+        Ok(())
+    }
+
+    fn ambient_occlusion(
+        &self,
+        positions: &[f32],
+        normals: &[f32],
+        grid: &VoxelGrid,
+        params: AoParams,
+    ) -> Result<Vec<f32>, String> {
+        let vertex_count = (positions.len() / 3) as u32;
+        let directions: Vec<f32> = sphere_directions(params.samples)
+            .into_iter()
+            .flatten()
+            .collect();
+        let [rx, ry, rz] = grid.resolution;
+        let [ox, oy, oz] = grid.origin;
+
+        let output = self.output("ao", vertex_count as u64 * 4)?;
+        let bytes = self.run(
+            AO_WGSL,
+            &[
+                rx,
+                ry,
+                rz,
+                0,
+                ox.to_bits(),
+                oy.to_bits(),
+                oz.to_bits(),
+                grid.voxel_size.to_bits(),
+                vertex_count,
+                (directions.len() / 3) as u32,
+                params.max_steps,
+                0,
+            ],
+            &[
+                &self.storage("positions", positions)?,
+                &self.storage("normals", normals)?,
+                &self.storage("directions", &directions)?,
+                &self.storage("voxels", &grid.bits)?,
+                &output,
+            ],
+            &output,
+            vertex_count,
+        )?;
+
+        Ok(floats(&bytes, vertex_count as usize))
+    }
+}
+
+fn floats(bytes: &[u8], count: usize) -> Vec<f32> {
+    bytemuck::cast_slice::<u8, f32>(bytes)[..count].to_vec()
+}
@@ -0,0 +1,361 @@
+//! Heavy per-vertex and volumetric mesh processing
+//!
+//! Every operation has a CPU implementation (rayon) and, with the `gpu`
+//! feature, a wgpu compute implementation with the same semantics. Callers
+//! pick a backend through [`select_backend`] and fall back to the CPU if the
+//! GPU path returns an error.
+
+pub mod cpu;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+
+use crate::utils::settings::ProcessingBackend;
+use std::sync::Arc;
+
+/// Normal used for vertices with no non-degenerate incident faces
+pub const FALLBACK_NORMAL: [f32; 3] = [0.0, 1.0, 0.0];
+/// Voxel count along the longest axis when a command doesn't specify one
+pub const DEFAULT_VOXEL_RESOLUTION: u32 = 128;
+/// Largest voxel count along the longest axis accepted
+pub const MAX_VOXEL_RESOLUTION: u32 = 1024;
+/// Rays per vertex when baking ambient occlusion
+pub const DEFAULT_AO_SAMPLES: u32 = 64;
+
+/// Refuse voxel resolutions whose grids wouldn't fit in memory
+pub fn check_voxel_resolution(resolution: u32) -> Result<(), String> {
+    if resolution > MAX_VOXEL_RESOLUTION {
+        return Err(format!(
+            "Voxel resolution must be at most {}, got {}",
+            MAX_VOXEL_RESOLUTION, resolution
+        ));
+    }
+    Ok(())
+}
+
+/// Dense occupancy grid stored as a bitset, x fastest
+#[derive(Debug, Clone)]
+pub struct VoxelGrid {
+    pub resolution: [u32; 3],
+    pub origin: [f32; 3],
+    pub voxel_size: f32,
+    pub bits: Vec<u32>,
+}
+
+impl VoxelGrid {
+    /// Empty grid covering the mesh with one voxel of padding on each side
+    ///
+    /// `resolution` is the voxel count along the longest axis, at most
+    /// [`MAX_VOXEL_RESOLUTION`].
+    pub fn fitting(positions: &[f32], resolution: u32) -> Result<Self, String> {
+        check_voxel_resolution(resolution)?;
+        let resolution = resolution.max(4);
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for p in positions.chunks_exact(3) {
+            for (axis, &value) in p.iter().enumerate() {
+                min[axis] = min[axis].min(value);
+                max[axis] = max[axis].max(value);
+            }
+        }
+        if min[0] > max[0] {
+            min = [0.0; 3];
+            max = [0.0; 3];
+        }
+
+        let extent = (0..3).map(|i| max[i] - min[i]).fold(0.0f32, f32::max);
+        let voxel_size = if extent > 0.0 {
+            extent / (resolution - 2) as f32
+        } else {
+            1.0
+        };
+
+        let dims = [0, 1, 2].map(|i| (((max[i] - min[i]) / voxel_size).ceil() as u32 + 2).max(3));
+        let origin = [0, 1, 2].map(|i| min[i] - voxel_size);
+        let words = (dims[0] as usize * dims[1] as usize * dims[2] as usize).div_ceil(32);
+
+        Ok(Self {
+            resolution: dims,
+            origin,
+            voxel_size,
+            bits: vec![0; words],
+        })
+    }
+
+    pub fn voxel_count(&self) -> usize {
+        self.resolution.iter().map(|&r| r as usize).product()
+    }
+
+    pub fn index(&self, x: u32, y: u32, z: u32) -> usize {
+        let [rx, ry, _] = self.resolution;
+        (z as usize * ry as usize + y as usize) * rx as usize + x as usize
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        self.bits[index / 32] & (1 << (index % 32)) != 0
+    }
+
+    pub fn set(&mut self, index: usize) {
+        self.bits[index / 32] |= 1 << (index % 32);
+    }
+
+    pub fn occupied_count(&self) -> usize {
+        self.bits.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Mark every voxel not reachable from the grid border as occupied
+    pub fn fill_interior(&mut self) {
+        let [rx, ry, rz] = self.resolution;
+        let mut outside = vec![false; self.voxel_count()];
+        let mut stack = Vec::new();
+
+        for z in 0..rz {
+            for y in 0..ry {
+                for x in 0..rx {
+                    let on_border =
+                        x == 0 || y == 0 || z == 0 || x == rx - 1 || y == ry - 1 || z == rz - 1;
+                    let index = self.index(x, y, z);
+                    if on_border && !self.get(index) && !outside[index] {
+                        outside[index] = true;
+                        stack.push((x, y, z));
+                    }
+                }
+            }
+        }
+
+        while let Some((x, y, z)) = stack.pop() {
+            let neighbors = [
+                (x.wrapping_sub(1), y, z),
+                (x + 1, y, z),
+                (x, y.wrapping_sub(1), z),
+                (x, y + 1, z),
+                (x, y, z.wrapping_sub(1)),
+                (x, y, z + 1),
+            ];
+            for (nx, ny, nz) in neighbors {
+                if nx >= rx || ny >= ry || nz >= rz {
+                    continue;
+                }
+                let index = self.index(nx, ny, nz);
+                if !self.get(index) && !outside[index] {
+                    outside[index] = true;
+                    stack.push((nx, ny, nz));
+                }
+            }
+        }
+
+        for (index, &is_outside) in outside.iter().enumerate() {
+            if !is_outside {
+                self.set(index);
+            }
+        }
+    }
+}
+
+/// Compressed per-vertex adjacency lists
+#[derive(Debug, Clone, Default)]
+pub struct Adjacency {
+    pub offsets: Vec<u32>,
+    pub items: Vec<u32>,
+}
+
+impl Adjacency {
+    pub fn of(&self, vertex: usize) -> &[u32] {
+        &self.items[self.offsets[vertex] as usize..self.offsets[vertex + 1] as usize]
+    }
+
+    /// Faces incident to each vertex
+    pub fn vertex_faces(vertex_count: usize, indices: &[u32]) -> Self {
+        let mut lists = vec![Vec::new(); vertex_count];
+        for (face, tri) in indices.chunks_exact(3).enumerate() {
+            for &v in tri {
+                if let Some(list) = lists.get_mut(v as usize) {
+                    list.push(face as u32);
+                }
+            }
+        }
+        Self::from_lists(lists)
+    }
+
+    /// Unique edge-connected neighbors of each vertex
+    pub fn vertex_neighbors(vertex_count: usize, indices: &[u32]) -> Self {
+        let mut lists = vec![Vec::new(); vertex_count];
+        for tri in indices.chunks_exact(3) {
+            for (a, b) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+                if (a as usize) < vertex_count && (b as usize) < vertex_count && a != b {
+                    lists[a as usize].push(b);
+                    lists[b as usize].push(a);
+                }
+            }
+        }
+        for list in &mut lists {
+            list.sort_unstable();
+            list.dedup();
+        }
+        Self::from_lists(lists)
+    }
+
+    fn from_lists(lists: Vec<Vec<u32>>) -> Self {
+        let mut offsets = Vec::with_capacity(lists.len() + 1);
+        let mut items = Vec::new();
+        offsets.push(0);
+        for list in lists {
+            items.extend(list);
+            offsets.push(items.len() as u32);
+        }
+        Self { offsets, items }
+    }
+}
+
+/// Evenly spread unit directions on the sphere (Fibonacci lattice)
+pub fn sphere_directions(samples: u32) -> Vec<[f32; 3]> {
+    let samples = samples.max(1);
+    let golden = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+    (0..samples)
+        .map(|i| {
+            let y = 1.0 - 2.0 * (i as f32 + 0.5) / samples as f32;
+            let r = (1.0 - y * y).max(0.0).sqrt();
+            let theta = golden * i as f32;
+            [r * theta.cos(), y, r * theta.sin()]
+        })
+        .collect()
+}
+
+/// Parameters for voxel-traced ambient occlusion
+#[derive(Debug, Clone, Copy)]
+pub struct AoParams {
+    pub samples: u32,
+    /// Ray length in voxels
+    pub max_steps: u32,
+}
+
+/// A mesh processing implementation
+pub trait ComputeBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Area-weighted, normalized vertex normals
+    fn vertex_normals(&self, positions: &[f32], indices: &[u32]) -> Result<Vec<f32>, String>;
+
+    /// Signed mean curvature estimate per vertex (positive on convex regions)
+    fn mean_curvature(
+        &self,
+        positions: &[f32],
+        normals: &[f32],
+        indices: &[u32],
+    ) -> Result<Vec<f32>, String>;
+
+    /// Conservative surface voxelization into `grid`
+    fn voxelize(
+        &self,
+        positions: &[f32],
+        indices: &[u32],
+        grid: &mut VoxelGrid,
+    ) -> Result<(), String>;
+
+    /// Per-vertex accessibility in [0, 1], traced through an occupancy grid
+    fn ambient_occlusion(
+        &self,
+        positions: &[f32],
+        normals: &[f32],
+        grid: &VoxelGrid,
+        params: AoParams,
+    ) -> Result<Vec<f32>, String>;
+}
+
+/// Backends resolved for a processing preference
+pub struct BackendSelection {
+    pub primary: Arc<dyn ComputeBackend>,
+    pub fallback: Option<Arc<dyn ComputeBackend>>,
+}
+
+impl BackendSelection {
+    /// Run an operation, retrying on the CPU if the GPU path fails
+    pub fn run<T>(
+        &self,
+        op: impl Fn(&dyn ComputeBackend) -> Result<T, String>,
+    ) -> Result<(T, &'static str), String> {
+        match attempt(self.primary.as_ref(), &op) {
+            Ok(value) => Ok((value, self.primary.name())),
+            Err(e) => match &self.fallback {
+                Some(fallback) => {
+                    log::warn!(
+                        "{} backend failed ({}), falling back to {}",
+                        self.primary.name(),
+                        e,
+                        fallback.name()
+                    );
+                    attempt(fallback.as_ref(), &op).map(|value| (value, fallback.name()))
+                }
+                None => Err(e),
+            },
+        }
+    }
+}
+
+/// Run `op` on one backend inside a span named after it
+fn attempt<T>(
+    backend: &dyn ComputeBackend,
+    op: &impl Fn(&dyn ComputeBackend) -> Result<T, String>,
+) -> Result<T, String> {
+    let span = match backend.name() {
+        "gpu" => tracing::info_span!("gpu"),
+        _ => tracing::info_span!("cpu"),
+    };
+    span.in_scope(|| {
+        let result = op(backend);
+        if let Err(e) = &result {
+            tracing::error!(error = %e, "backend failed");
+        }
+        result
+    })
+}
+
+/// Resolve the configured backend, falling back to the CPU when no GPU exists
+pub fn select_backend(preference: ProcessingBackend) -> BackendSelection {
+    let cpu: Arc<dyn ComputeBackend> = Arc::new(cpu::CpuBackend);
+
+    if preference == ProcessingBackend::Cpu {
+        return BackendSelection {
+            primary: cpu,
+            fallback: None,
+        };
+    }
+
+    match gpu_backend() {
+        Some(gpu) => BackendSelection {
+            primary: gpu,
+            fallback: Some(cpu),
+        },
+        None => {
+            if preference == ProcessingBackend::Gpu {
+                log::warn!("GPU processing requested but no GPU backend is available");
+            }
+            BackendSelection {
+                primary: cpu,
+                fallback: None,
+            }
+        }
+    }
+}
+
+/// Name of the GPU adapter, if one could be initialized
+pub fn gpu_adapter_name() -> Option<String> {
+    #[cfg(feature = "gpu")]
+    {
+        gpu::GpuBackend::shared().map(|gpu| gpu.adapter_name().to_string())
+    }
+    #[cfg(not(feature = "gpu"))]
+    {
+        None
+    }
+}
+
+fn gpu_backend() -> Option<Arc<dyn ComputeBackend>> {
+    #[cfg(feature = "gpu")]
+    {
+        gpu::GpuBackend::shared().map(|gpu| gpu as Arc<dyn ComputeBackend>)
+    }
+    #[cfg(not(feature = "gpu"))]
+    {
+        None
+    }
+}
@@ -0,0 +1,78 @@
+struct Params {
+    resolution: vec4<u32>,
+    // xyz = grid origin, w = voxel size
+    origin: vec4<f32>,
+    vertex_count: u32,
+    sample_count: u32,
+    max_steps: u32,
+    _pad0: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> positions: array<f32>;
+@group(0) @binding(2) var<storage, read> normals: array<f32>;
+@group(0) @binding(3) var<storage, read> directions: array<f32>;
+@group(0) @binding(4) var<storage, read> bits: array<u32>;
+@group(0) @binding(5) var<storage, read_write> ao: array<f32>;
+
+fn position(i: u32) -> vec3<f32> {
+    return vec3<f32>(positions[i * 3u], positions[i * 3u + 1u], positions[i * 3u + 2u]);
+}
+
+fn normal(i: u32) -> vec3<f32> {
+    return vec3<f32>(normals[i * 3u], normals[i * 3u + 1u], normals[i * 3u + 2u]);
+}
+
+fn direction(i: u32) -> vec3<f32> {
+    return vec3<f32>(directions[i * 3u], directions[i * 3u + 1u], directions[i * 3u + 2u]);
+}
+
+fn ray_hits(origin: vec3<f32>, dir: vec3<f32>) -> bool {
+    let size = params.origin.w;
+    let step = size * 0.5;
+    let dims = vec3<f32>(params.resolution.xyz);
+    for (var i = 1u; i <= params.max_steps * 2u; i++) {
+        let coord = (origin + dir * (step * f32(i)) - params.origin.xyz) / size;
+        if (any(coord < vec3<f32>(0.0)) || any(coord >= dims)) {
+            return false;
+        }
+        let cell = vec3<u32>(coord);
+        let index = (cell.z * params.resolution.y + cell.y) * params.resolution.x + cell.x;
+        if ((bits[index / 32u] & (1u << (index % 32u))) != 0u) {
+            return true;
+        }
+    }
+    return false;
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>, @builtin(num_workgroups) nwg: vec3<u32>) {
+    let v = flat_index(gid, nwg);
+    if (v >= params.vertex_count) {
+        return;
+    }
+
+    let n = normal(v);
+    let origin = position(v) + n * (params.origin.w * 1.5);
+
+    var open = 0.0;
+    var total = 0.0;
+    for (var s = 0u; s < params.sample_count; s++) {
+        var d = direction(s);
+        let cos_theta = dot(d, n);
+        if (cos_theta < 0.0) {
+            d = -d;
+        }
+        let weight = abs(cos_theta);
+        total += weight;
+        if (!ray_hits(origin, d)) {
+            open += weight;
+        }
+    }
+
+    if (total > 0.0) {
+        ao[v] = open / total;
+    } else {
+        ao[v] = 1.0;
+    }
+}
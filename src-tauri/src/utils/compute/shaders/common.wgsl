@@ -0,0 +1,8 @@
+// Shared helpers prepended to every compute kernel
+
+const WORKGROUP_SIZE: u32 = 64u;
+
+// Flattened invocation index for 2D dispatches of 1D work
+fn flat_index(gid: vec3<u32>, nwg: vec3<u32>) -> u32 {
+    return gid.y * nwg.x * WORKGROUP_SIZE + gid.x;
+}
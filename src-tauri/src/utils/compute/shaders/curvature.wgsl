@@ -0,0 +1,56 @@
+struct Params {
+    vertex_count: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> positions: array<f32>;
+@group(0) @binding(2) var<storage, read> normals: array<f32>;
+@group(0) @binding(3) var<storage, read> offsets: array<u32>;
+@group(0) @binding(4) var<storage, read> neighbors: array<u32>;
+@group(0) @binding(5) var<storage, read_write> curvature: array<f32>;
+
+fn position(i: u32) -> vec3<f32> {
+    return vec3<f32>(positions[i * 3u], positions[i * 3u + 1u], positions[i * 3u + 2u]);
+}
+
+fn normal(i: u32) -> vec3<f32> {
+    return vec3<f32>(normals[i * 3u], normals[i * 3u + 1u], normals[i * 3u + 2u]);
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>, @builtin(num_workgroups) nwg: vec3<u32>) {
+    let v = flat_index(gid, nwg);
+    if (v >= params.vertex_count) {
+        return;
+    }
+
+    let start = offsets[v];
+    let end = offsets[v + 1u];
+    if (end == start) {
+        curvature[v] = 0.0;
+        return;
+    }
+
+    let p = position(v);
+    var centroid = vec3<f32>(0.0);
+    var mean_sq = 0.0;
+    for (var k = start; k < end; k++) {
+        let q = position(neighbors[k]);
+        centroid += q;
+        mean_sq += dot(q - p, q - p);
+    }
+    let count = f32(end - start);
+    centroid /= count;
+    mean_sq /= count;
+
+    if (mean_sq <= 0.0) {
+        curvature[v] = 0.0;
+        return;
+    }
+
+    let n = normal(v);
+    curvature[v] = -2.0 * dot(n, centroid - p) / mean_sq;
+}
@@ -0,0 +1,44 @@
+struct Params {
+    vertex_count: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> positions: array<f32>;
+@group(0) @binding(2) var<storage, read> indices: array<u32>;
+@group(0) @binding(3) var<storage, read> offsets: array<u32>;
+@group(0) @binding(4) var<storage, read> faces: array<u32>;
+@group(0) @binding(5) var<storage, read_write> normals: array<f32>;
+
+fn position(i: u32) -> vec3<f32> {
+    return vec3<f32>(positions[i * 3u], positions[i * 3u + 1u], positions[i * 3u + 2u]);
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>, @builtin(num_workgroups) nwg: vec3<u32>) {
+    let v = flat_index(gid, nwg);
+    if (v >= params.vertex_count) {
+        return;
+    }
+
+    var sum = vec3<f32>(0.0);
+    for (var k = offsets[v]; k < offsets[v + 1u]; k++) {
+        let f = faces[k] * 3u;
+        let a = position(indices[f]);
+        let b = position(indices[f + 1u]);
+        let c = position(indices[f + 2u]);
+        sum += cross(b - a, c - a);
+    }
+
+    var n = vec3<f32>(0.0, 1.0, 0.0);
+    let len = length(sum);
+    if (len > 1e-20) {
+        n = sum / len;
+    }
+
+    normals[v * 3u] = n.x;
+    normals[v * 3u + 1u] = n.y;
+    normals[v * 3u + 2u] = n.z;
+}
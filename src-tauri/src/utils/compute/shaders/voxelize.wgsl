@@ -0,0 +1,95 @@
+struct Params {
+    resolution: vec4<u32>,
+    // xyz = grid origin, w = voxel size
+    origin: vec4<f32>,
+    face_count: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> positions: array<f32>;
+@group(0) @binding(2) var<storage, read> indices: array<u32>;
+@group(0) @binding(3) var<storage, read_write> bits: array<atomic<u32>>;
+
+fn position(i: u32) -> vec3<f32> {
+    return vec3<f32>(positions[i * 3u], positions[i * 3u + 1u], positions[i * 3u + 2u]);
+}
+
+// Ericson, Real-Time Collision Detection 5.1.5
+fn closest_point(p: vec3<f32>, a: vec3<f32>, b: vec3<f32>, c: vec3<f32>) -> vec3<f32> {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = dot(ab, ap);
+    let d2 = dot(ac, ap);
+    if (d1 <= 0.0 && d2 <= 0.0) {
+        return a;
+    }
+
+    let bp = p - b;
+    let d3 = dot(ab, bp);
+    let d4 = dot(ac, bp);
+    if (d3 >= 0.0 && d4 <= d3) {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if (vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0) {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = p - c;
+    let d5 = dot(ab, cp);
+    let d6 = dot(ac, cp);
+    if (d6 >= 0.0 && d5 <= d6) {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if (vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0) {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if (va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0) {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    return a + ab * (vb * denom) + ac * (vc * denom);
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>, @builtin(num_workgroups) nwg: vec3<u32>) {
+    let f = flat_index(gid, nwg);
+    if (f >= params.face_count) {
+        return;
+    }
+
+    let a = position(indices[f * 3u]);
+    let b = position(indices[f * 3u + 1u]);
+    let c = position(indices[f * 3u + 2u]);
+
+    let size = params.origin.w;
+    let origin = params.origin.xyz;
+    let reach = size * 0.8660254;
+    let limit = vec3<f32>(params.resolution.xyz) - vec3<f32>(1.0);
+
+    let lo = vec3<u32>(clamp(floor((min(min(a, b), c) - vec3<f32>(reach) - origin) / size), vec3<f32>(0.0), limit));
+    let hi = vec3<u32>(clamp(floor((max(max(a, b), c) + vec3<f32>(reach) - origin) / size), vec3<f32>(0.0), limit));
+
+    for (var z = lo.z; z <= hi.z; z++) {
+        for (var y = lo.y; y <= hi.y; y++) {
+            for (var x = lo.x; x <= hi.x; x++) {
+                let center = origin + (vec3<f32>(f32(x), f32(y), f32(z)) + vec3<f32>(0.5)) * size;
+                let d = center - closest_point(center, a, b, c);
+                if (dot(d, d) <= reach * reach) {
+                    let index = (z * params.resolution.y + y) * params.resolution.x + x;
+                    atomicOr(&bits[index / 32u], 1u << (index % 32u));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Ratings, favorites and export counts of library assets
+//!
+//! Like licenses, these are kept per asset id beside the root indexes, so
+//! re-indexing doesn't lose them and they follow an asset moved to another
+//! root. The library merges them into its assets and filters on them.
+
+use crate::utils::file_lock::{self, FileLock};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Highest star rating
+pub const MAX_RATING: u8 = 5;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssetCuration {
+    /// Stars from 1 to 5; `None` when unrated
+    pub rating: Option<u8>,
+    pub favorite: bool,
+    /// Times the asset went out in an export, bundle or web viewer
+    pub export_count: u64,
+    pub last_exported_ms: Option<u64>,
+}
+
+impl AssetCuration {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Curation of every asset that has any, saved to a JSON file and managed
+/// as Tauri state
+#[derive(Default)]
+pub struct CurationStore {
+    path: Option<PathBuf>,
+    /// By asset id
+    assets: Mutex<BTreeMap<String, AssetCuration>>,
+}
+
+impl CurationStore {
+    /// Load curation from `path`, starting empty if missing or invalid
+    pub fn load(path: PathBuf) -> Self {
+        let assets = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid curation file {}: {}", path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            path: Some(path),
+            assets: Mutex::new(assets),
+        }
+    }
+
+    pub fn get(&self, asset_id: &str) -> AssetCuration {
+        let assets = self.assets.lock().unwrap();
+        assets.get(asset_id).cloned().unwrap_or_default()
+    }
+
+    /// Rate an asset from 1 to 5 stars, or clear its rating with `None`
+    pub fn set_rating(&self, asset_id: &str, rating: Option<u8>) -> Result<AssetCuration, String> {
+        if let Some(rating) = rating.filter(|r| !(1..=MAX_RATING).contains(r)) {
+            return Err(format!(
+                "Ratings go from 1 to {} stars, not {}",
+                MAX_RATING, rating
+            ));
+        }
+        self.update(asset_id, |curation| curation.rating = rating)
+    }
+
+    /// Mark an asset as a favorite if it isn't one, and unmark it if it is
+    pub fn toggle_favorite(&self, asset_id: &str) -> Result<AssetCuration, String> {
+        self.update(asset_id, |curation| curation.favorite = !curation.favorite)
+    }
+
+    /// Count one export of each of `asset_ids`
+    pub fn record_exports(&self, asset_ids: &[String]) -> Result<(), String> {
+        if asset_ids.is_empty() {
+            return Ok(());
+        }
+        let now = now_ms();
+        let mut assets = self.assets.lock().unwrap();
+        let _lock = self.reload(&mut assets)?;
+        for id in asset_ids {
+            let curation = assets.entry(id.clone()).or_default();
+            curation.export_count += 1;
+            curation.last_exported_ms = Some(now);
+        }
+        self.save(&assets)
+    }
+
+    /// Carry the curation of `from` over to the id `to`
+    pub fn rename_asset(&self, from: &str, to: &str) -> Result<(), String> {
+        let mut assets = self.assets.lock().unwrap();
+        let _lock = self.reload(&mut assets)?;
+        if let Some(curation) = assets.remove(from) {
+            assets.insert(to.to_string(), curation);
+        }
+        self.save(&assets)
+    }
+
+    fn update(
+        &self,
+        asset_id: &str,
+        change: impl FnOnce(&mut AssetCuration),
+    ) -> Result<AssetCuration, String> {
+        if asset_id.trim().is_empty() {
+            return Err("An asset id is required".to_string());
+        }
+        let mut assets = self.assets.lock().unwrap();
+        let _lock = self.reload(&mut assets)?;
+        let mut curation = assets.get(asset_id).cloned().unwrap_or_default();
+        change(&mut curation);
+        if curation.is_empty() {
+            assets.remove(asset_id);
+        } else {
+            assets.insert(asset_id.to_string(), curation.clone());
+        }
+        self.save(&assets)?;
+        Ok(curation)
+    }
+
+    /// Lock the file and pick up what other instances saved to it
+    fn reload(
+        &self,
+        assets: &mut BTreeMap<String, AssetCuration>,
+    ) -> Result<Option<FileLock>, String> {
+        self.path
+            .as_deref()
+            .map(|path| file_lock::lock_and_reload(path, assets, "curation file"))
+            .transpose()
+    }
+
+    fn save(&self, assets: &BTreeMap<String, AssetCuration>) -> Result<(), String> {
+        match &self.path {
+            Some(path) => file_lock::write_json(path, assets, "curation file"),
+            None => Ok(()),
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ratings_favorites_and_exports_survive_reload() {
+        let dir = std::env::temp_dir().join(format!("sweedle-curation-{}", std::process::id()));
+        let path = dir.join("curation.json");
+        let store = CurationStore::load(path.clone());
+        assert_eq!(store.set_rating("rock", Some(4)).unwrap().rating, Some(4));
+        assert!(store.set_rating("rock", Some(6)).is_err());
+        assert!(store.set_rating("rock", Some(0)).is_err());
+        assert!(store.toggle_favorite("tree").unwrap().favorite);
+        let ids = ["rock".to_string(), "tree".to_string()];
+        store.record_exports(&ids).unwrap();
+        store.record_exports(&ids[..1]).unwrap();
+
+        let reloaded = CurationStore::load(path);
+        let rock = reloaded.get("rock");
+        assert_eq!(
+            (rock.rating, rock.favorite, rock.export_count),
+            (Some(4), false, 2)
+        );
+        assert!(rock.last_exported_ms.is_some());
+        assert_eq!(reloaded.get("tree").export_count, 1);
+        assert_eq!(reloaded.get("bush"), AssetCuration::default());
+
+        // Clearing everything forgets the asset
+        reloaded.set_rating("rock", None).unwrap();
+        assert!(!reloaded.toggle_favorite("tree").unwrap().favorite);
+        assert_eq!(reloaded.assets.lock().unwrap().len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
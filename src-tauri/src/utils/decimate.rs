@@ -0,0 +1,379 @@
+use std::collections::{HashMap, HashSet};
+
+/// Weights are quantized into this many cell sizes; the top band is kept as is
+const WEIGHT_BANDS: u32 = 8;
+
+/// Result of a vertex clustering pass
+#[derive(Debug, Clone, Default)]
+pub struct DecimatedMesh {
+    pub positions: Vec<f32>,
+    pub indices: Vec<u32>,
+    /// Output vertex of each input vertex, `u32::MAX` if it was dropped
+    pub vertex_map: Vec<u32>,
+    /// Input face each output face came from
+    pub source_faces: Vec<u32>,
+}
+
+impl DecimatedMesh {
+    fn unchanged(positions: &[f32], indices: &[u32]) -> Self {
+        Self {
+            positions: positions.to_vec(),
+            indices: indices.to_vec(),
+            vertex_map: (0..(positions.len() / 3) as u32).collect(),
+            source_faces: (0..(indices.len() / 3) as u32).collect(),
+        }
+    }
+
+    /// Average a per-vertex attribute of the input over each output vertex
+    ///
+    /// `values` holds `width` floats per input vertex.
+    pub fn remap_attribute(&self, values: &[f32], width: usize) -> Vec<f32> {
+        let vertex_count = self.positions.len() / 3;
+        let mut sums = vec![0.0f64; vertex_count * width];
+        let mut counts = vec![0u32; vertex_count];
+
+        for (i, &out) in self.vertex_map.iter().enumerate() {
+            if out == u32::MAX {
+                continue;
+            }
+            let out = out as usize;
+            for (k, value) in values[i * width..(i + 1) * width].iter().enumerate() {
+                sums[out * width + k] += *value as f64;
+            }
+            counts[out] += 1;
+        }
+
+        sums.iter()
+            .enumerate()
+            .map(|(k, &sum)| (sum / counts[k / width].max(1) as f64) as f32)
+            .collect()
+    }
+}
+
+/// Restrictions on which vertices a clustering pass may merge
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClusterConstraints<'a> {
+    /// Importance per vertex in [0, 1]; see [`cluster_decimate_weighted`]
+    pub weights: Option<&'a [f32]>,
+    /// Group per vertex; vertices in different groups are never merged
+    pub groups: Option<&'a [u32]>,
+}
+
+/// Decimate a mesh by snapping vertices to a uniform grid
+///
+/// Vertex clustering is far cheaper than edge-collapse simplification and
+/// never fails on messy input, which makes it a good fit for throwaway
+/// previews. The grid is refined until the face count lands near the target.
+pub fn cluster_decimate(positions: &[f32], indices: &[u32], target_faces: usize) -> DecimatedMesh {
+    decimate_to_target(
+        positions,
+        indices,
+        &ClusterConstraints::default(),
+        target_faces,
+    )
+}
+
+/// Decimate a mesh while preserving detail where `weights` is high
+///
+/// `weights` holds one importance value per vertex in [0, 1]. Vertices at 0
+/// are clustered as coarsely as the face target requires, higher weights
+/// use proportionally finer cells, and vertices at 1 are never merged, so
+/// triangles whose corners are all at 1 survive unchanged.
+pub fn cluster_decimate_weighted(
+    positions: &[f32],
+    indices: &[u32],
+    weights: &[f32],
+    target_faces: usize,
+) -> DecimatedMesh {
+    let constraints = ClusterConstraints {
+        weights: Some(weights),
+        groups: None,
+    };
+    decimate_to_target(positions, indices, &constraints, target_faces)
+}
+
+/// Decimate a mesh under arbitrary [`ClusterConstraints`]
+pub fn cluster_decimate_constrained(
+    positions: &[f32],
+    indices: &[u32],
+    constraints: &ClusterConstraints,
+    target_faces: usize,
+) -> DecimatedMesh {
+    decimate_to_target(positions, indices, constraints, target_faces)
+}
+
+fn decimate_to_target(
+    positions: &[f32],
+    indices: &[u32],
+    constraints: &ClusterConstraints,
+    target_faces: usize,
+) -> DecimatedMesh {
+    let face_count = indices.len() / 3;
+    if face_count <= target_faces || positions.len() < 9 {
+        return DecimatedMesh::unchanged(positions, indices);
+    }
+
+    let (min, max) = bounds(positions);
+    let extent = (0..3).map(|i| max[i] - min[i]).fold(0.0f32, f32::max);
+    if extent <= 0.0 {
+        return DecimatedMesh::default();
+    }
+
+    // A closed surface clustered on an n^3 grid yields roughly 4n^2 faces
+    let mut resolution = ((target_faces as f32 / 4.0).sqrt()).clamp(2.0, 4096.0);
+    let (mut best, _) = cluster_pass(positions, indices, constraints, min, extent / resolution);
+
+    for _ in 0..4 {
+        let produced = best.indices.len() / 3;
+        if produced == 0 {
+            break;
+        }
+        let ratio = target_faces as f32 / produced as f32;
+        if (0.8..=1.2).contains(&ratio) {
+            break;
+        }
+
+        resolution = (resolution * ratio.sqrt()).clamp(2.0, 4096.0);
+        let (candidate, _) =
+            cluster_pass(positions, indices, constraints, min, extent / resolution);
+        let candidate_faces = candidate.indices.len() / 3;
+
+        if candidate_faces.abs_diff(target_faces) < produced.abs_diff(target_faces) {
+            best = candidate;
+        }
+    }
+
+    best
+}
+
+/// Decimate a mesh so that no vertex moves further than `max_error`
+///
+/// Returns the mesh and the largest distance a vertex actually moved, which
+/// is usually well below the bound.
+pub fn cluster_decimate_to_error(
+    positions: &[f32],
+    indices: &[u32],
+    max_error: f32,
+) -> (DecimatedMesh, f32) {
+    let keep = || (DecimatedMesh::unchanged(positions, indices), 0.0);
+    if max_error <= 0.0 || positions.len() < 9 {
+        return keep();
+    }
+
+    let (min, max) = bounds(positions);
+    let extent = (0..3).map(|i| max[i] - min[i]).fold(0.0f32, f32::max);
+    if extent <= 0.0 {
+        return keep();
+    }
+    // A vertex is at most one cell diagonal away from its cluster's centroid;
+    // at least two cells per axis keep something recognizable
+    let cell = (max_error / 3f32.sqrt()).clamp(extent / 4096.0, extent / 2.0);
+    let constraints = ClusterConstraints::default();
+    cluster_pass(positions, indices, &constraints, min, cell)
+}
+
+/// Cluster vertices on a grid of `cell`-sized cubes
+///
+/// With `weights`, each weight band clusters on its own grid with a cell
+/// shrunk by the band's weight, and vertices in the top band stay unique.
+/// With `groups`, each group clusters separately. Also returns the largest distance between a vertex and its cluster's
+/// centroid.
+fn cluster_pass(
+    positions: &[f32],
+    indices: &[u32],
+    constraints: &ClusterConstraints,
+    origin: [f32; 3],
+    cell: f32,
+) -> (DecimatedMesh, f32) {
+    let vertex_count = positions.len() / 3;
+
+    let mut cells: HashMap<(u32, u32, i32, i32, i32), u32> = HashMap::new();
+    let mut sums: Vec<[f64; 4]> = Vec::new();
+    let mut remap = vec![0u32; vertex_count];
+
+    for (i, slot) in remap.iter_mut().enumerate() {
+        let p = [positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]];
+        let group = constraints
+            .groups
+            .and_then(|g| g.get(i))
+            .copied()
+            .unwrap_or(0);
+        let band = constraints
+            .weights
+            .and_then(|w| w.get(i))
+            .map(|&w| (w.clamp(0.0, 1.0) * WEIGHT_BANDS as f32).round() as u32)
+            .unwrap_or(0);
+        let key = if band == WEIGHT_BANDS {
+            (group, band, i as i32, 0, 0)
+        } else {
+            let inv_cell = WEIGHT_BANDS as f32 / ((WEIGHT_BANDS - band) as f32 * cell);
+            (
+                group,
+                band,
+                ((p[0] - origin[0]) * inv_cell) as i32,
+                ((p[1] - origin[1]) * inv_cell) as i32,
+                ((p[2] - origin[2]) * inv_cell) as i32,
+            )
+        };
+
+        let cluster = *cells.entry(key).or_insert_with(|| {
+            sums.push([0.0; 4]);
+            (sums.len() - 1) as u32
+        });
+
+        let sum = &mut sums[cluster as usize];
+        sum[0] += p[0] as f64;
+        sum[1] += p[1] as f64;
+        sum[2] += p[2] as f64;
+        sum[3] += 1.0;
+        *slot = cluster;
+    }
+
+    let mut max_error_sq = 0.0f64;
+    for (i, &cluster) in remap.iter().enumerate() {
+        let sum = sums[cluster as usize];
+        let d: f64 = (0..3)
+            .map(|axis| (positions[i * 3 + axis] as f64 - sum[axis] / sum[3]).powi(2))
+            .sum();
+        max_error_sq = max_error_sq.max(d);
+    }
+
+    let mut seen = HashSet::new();
+    let mut out_indices = Vec::new();
+    let mut source_faces = Vec::new();
+
+    for (f, face) in indices.chunks_exact(3).enumerate() {
+        let (a, b, c) = (face[0] as usize, face[1] as usize, face[2] as usize);
+        if a >= vertex_count || b >= vertex_count || c >= vertex_count {
+            continue;
+        }
+
+        let tri = [remap[a], remap[b], remap[c]];
+        if tri[0] == tri[1] || tri[1] == tri[2] || tri[0] == tri[2] {
+            continue;
+        }
+
+        let mut key = tri;
+        key.sort_unstable();
+        if seen.insert(key) {
+            out_indices.extend_from_slice(&tri);
+            source_faces.push(f as u32);
+        }
+    }
+
+    // Drop clusters no surviving triangle references
+    let mut compact = vec![u32::MAX; sums.len()];
+    let mut out_positions = Vec::new();
+    for index in out_indices.iter_mut() {
+        let cluster = *index as usize;
+        if compact[cluster] == u32::MAX {
+            let sum = sums[cluster];
+            compact[cluster] = (out_positions.len() / 3) as u32;
+            out_positions.push((sum[0] / sum[3]) as f32);
+            out_positions.push((sum[1] / sum[3]) as f32);
+            out_positions.push((sum[2] / sum[3]) as f32);
+        }
+        *index = compact[cluster];
+    }
+
+    let mesh = DecimatedMesh {
+        positions: out_positions,
+        indices: out_indices,
+        vertex_map: remap.iter().map(|&c| compact[c as usize]).collect(),
+        source_faces,
+    };
+    (mesh, max_error_sq.sqrt() as f32)
+}
+
+fn bounds(positions: &[f32]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in positions.chunks_exact(3) {
+        for (axis, &value) in p.iter().enumerate() {
+            min[axis] = min[axis].min(value);
+            max[axis] = max[axis].max(value);
+        }
+    }
+    (min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Subdivided unit quad in the XY plane
+    fn grid(n: usize) -> (Vec<f32>, Vec<u32>) {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for y in 0..=n {
+            for x in 0..=n {
+                positions.extend_from_slice(&[x as f32 / n as f32, y as f32 / n as f32, 0.0]);
+            }
+        }
+        let row = (n + 1) as u32;
+        for y in 0..n as u32 {
+            for x in 0..n as u32 {
+                let i = y * row + x;
+                indices.extend_from_slice(&[i, i + 1, i + row, i + 1, i + row + 1, i + row]);
+            }
+        }
+        (positions, indices)
+    }
+
+    #[test]
+    fn test_cluster_decimate_reduces_faces() {
+        let (positions, indices) = grid(64);
+        let result = cluster_decimate(&positions, &indices, 500);
+
+        let faces = result.indices.len() / 3;
+        assert!(faces > 0 && faces < indices.len() / 3);
+        assert!(result
+            .indices
+            .iter()
+            .all(|&i| (i as usize) < result.positions.len() / 3));
+    }
+
+    #[test]
+    fn test_cluster_decimate_to_error_respects_bound() {
+        let (positions, indices) = grid(64);
+
+        let (coarse, coarse_error) = cluster_decimate_to_error(&positions, &indices, 0.2);
+        let (fine, fine_error) = cluster_decimate_to_error(&positions, &indices, 0.05);
+        assert!(coarse_error <= 0.2 && fine_error <= 0.05);
+        assert!(coarse.indices.len() < fine.indices.len());
+        assert!(fine.indices.len() < indices.len());
+
+        let (kept, error) = cluster_decimate_to_error(&positions, &indices, 0.0);
+        assert_eq!(kept.indices, indices);
+        assert_eq!(error, 0.0);
+    }
+
+    #[test]
+    fn test_weighted_decimation_preserves_protected_region() {
+        let (positions, indices) = grid(64);
+        let weights: Vec<f32> = positions
+            .chunks_exact(3)
+            .map(|p| if p[0] < 0.25 { 1.0 } else { 0.0 })
+            .collect();
+        let protected_faces = |positions: &[f32], indices: &[u32]| {
+            indices
+                .chunks_exact(3)
+                .filter(|f| f.iter().all(|&i| positions[i as usize * 3] < 0.25))
+                .count()
+        };
+
+        let result = cluster_decimate_weighted(&positions, &indices, &weights, 2000);
+        let before = protected_faces(&positions, &indices);
+        assert!(result.indices.len() / 3 < indices.len() / 3);
+        assert_eq!(protected_faces(&result.positions, &result.indices), before);
+    }
+
+    #[test]
+    fn test_cluster_decimate_keeps_small_meshes() {
+        let (positions, indices) = grid(2);
+        let result = cluster_decimate(&positions, &indices, 100);
+
+        assert_eq!(result.indices, indices);
+        assert_eq!(result.positions, positions);
+    }
+}
@@ -0,0 +1,385 @@
+//! Zero-area and sliver triangle detection and cleanup
+//!
+//! Repeated indices are only one way a face collapses; scanners and boolean
+//! operations mostly leave faces whose corners are distinct but collinear,
+//! and long thin slivers that shade badly and break decimation. Faces are
+//! rated by area and by aspect ratio (longest edge over shortest altitude,
+//! scaled so an equilateral triangle is 1).
+//!
+//! Cleanup keeps the surface closed: needle slivers have their short edge
+//! collapsed, cap slivers (one corner near the opposite edge) have that
+//! edge flipped, and faces left with no area are dropped.
+
+use crate::utils::mesh_store::MeshData;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Faces below this area relative to their longest edge squared are flat
+const ZERO_AREA_RATIO: f32 = 1e-6;
+/// Slivers whose shortest edge is under this fraction of the longest are
+/// needles, fixed by collapsing that edge; the rest are caps
+const NEEDLE_EDGE_RATIO: f32 = 0.25;
+const MAX_FIX_PASSES: usize = 8;
+
+/// Limits past which a face counts as degenerate or a sliver
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FaceThresholds {
+    /// Faces with less area, in mesh units², are degenerate (default 0)
+    pub min_area: f32,
+    /// Faces with a larger aspect ratio are slivers (default 20)
+    pub max_aspect_ratio: f32,
+}
+
+impl Default for FaceThresholds {
+    fn default() -> Self {
+        Self {
+            min_area: 0.0,
+            max_aspect_ratio: 20.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaceQuality {
+    Good,
+    /// Repeated corners, or no area to speak of
+    Degenerate,
+    Sliver,
+}
+
+/// What `clean` found and changed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DegenerateCleanup {
+    pub degenerate_face_count: usize,
+    pub sliver_face_count: usize,
+    pub collapsed_edge_count: usize,
+    pub flipped_edge_count: usize,
+    pub removed_face_count: usize,
+    /// Slivers no collapse or flip could fix
+    pub remaining_sliver_count: usize,
+}
+
+/// Rate one face against `thresholds`
+pub fn classify(positions: &[f32], face: [u32; 3], thresholds: &FaceThresholds) -> FaceQuality {
+    if face[0] == face[1] || face[1] == face[2] || face[0] == face[2] {
+        return FaceQuality::Degenerate;
+    }
+    let Some(shape) = Shape::of(positions, face) else {
+        return FaceQuality::Degenerate;
+    };
+    if shape.area <= ZERO_AREA_RATIO * shape.longest * shape.longest
+        || shape.area < thresholds.min_area
+    {
+        FaceQuality::Degenerate
+    } else if shape.aspect_ratio() > thresholds.max_aspect_ratio {
+        FaceQuality::Sliver
+    } else {
+        FaceQuality::Good
+    }
+}
+
+/// Degenerate and sliver face counts
+pub fn count(positions: &[f32], indices: &[u32], thresholds: &FaceThresholds) -> (usize, usize) {
+    indices
+        .chunks_exact(3)
+        .fold((0, 0), |(degenerate, slivers), f| {
+            match classify(positions, [f[0], f[1], f[2]], thresholds) {
+                FaceQuality::Degenerate => (degenerate + 1, slivers),
+                FaceQuality::Sliver => (degenerate, slivers + 1),
+                FaceQuality::Good => (degenerate, slivers),
+            }
+        })
+}
+
+/// Remove degenerate faces and, with `fix_slivers`, repair slivers
+///
+/// Collapsing moves both ends of the short edge, and every vertex sharing
+/// their positions, to its midpoint, so seams stay closed. Polygon
+/// information is dropped when any face changes.
+pub fn clean(
+    mesh: &MeshData,
+    thresholds: &FaceThresholds,
+    fix_slivers: bool,
+) -> (MeshData, DegenerateCleanup) {
+    let (degenerate_face_count, sliver_face_count) =
+        count(&mesh.positions, &mesh.indices, thresholds);
+    let mut report = DegenerateCleanup {
+        degenerate_face_count,
+        sliver_face_count,
+        ..Default::default()
+    };
+    let mut out = mesh.clone();
+
+    if fix_slivers && sliver_face_count > 0 {
+        // Vertices at the same position move together
+        let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+        let mut position_ids: Vec<u32> = out
+            .positions
+            .chunks_exact(3)
+            .map(|p| {
+                let next = welded.len() as u32;
+                *welded
+                    .entry([p[0], p[1], p[2]].map(f32::to_bits))
+                    .or_insert(next)
+            })
+            .collect();
+        let mut members: Vec<Vec<usize>> = vec![Vec::new(); welded.len()];
+        for (v, &id) in position_ids.iter().enumerate() {
+            members[id as usize].push(v);
+        }
+
+        for _ in 0..MAX_FIX_PASSES {
+            let fixed = fix_pass(
+                &mut out,
+                thresholds,
+                &mut position_ids,
+                &mut members,
+                &mut report,
+            );
+            if fixed == 0 {
+                break;
+            }
+        }
+    }
+
+    let before = out.face_count();
+    let mut kept = Vec::with_capacity(out.indices.len());
+    for f in out.indices.chunks_exact(3) {
+        let face = [f[0], f[1], f[2]];
+        if classify(&out.positions, face, thresholds) != FaceQuality::Degenerate {
+            kept.extend_from_slice(&face);
+        }
+    }
+    out.indices = kept;
+    report.removed_face_count = before - out.face_count();
+    report.remaining_sliver_count = count(&out.positions, &out.indices, thresholds).1;
+    if report.removed_face_count > 0 || report.flipped_edge_count > 0 {
+        out.polygons = None;
+    }
+    (out, report)
+}
+
+/// Fix slivers that don't share a vertex with one already fixed this pass
+fn fix_pass(
+    mesh: &mut MeshData,
+    thresholds: &FaceThresholds,
+    position_ids: &mut [u32],
+    members: &mut [Vec<usize>],
+    report: &mut DegenerateCleanup,
+) -> usize {
+    let mut edge_face: HashMap<(u32, u32), usize> = HashMap::new();
+    for (f, face) in mesh.indices.chunks_exact(3).enumerate() {
+        for k in 0..3 {
+            edge_face.insert((face[k], face[(k + 1) % 3]), f);
+        }
+    }
+
+    let mut touched = vec![false; members.len()];
+    let mut fixed = 0;
+    for f in 0..mesh.face_count() {
+        let face = [0, 1, 2].map(|k| mesh.indices[f * 3 + k]);
+        if classify(&mesh.positions, face, thresholds) != FaceQuality::Sliver {
+            continue;
+        }
+        let ids = face.map(|v| position_ids[v as usize] as usize);
+        if ids.iter().any(|&id| touched[id]) {
+            continue;
+        }
+        let Some(shape) = Shape::of(&mesh.positions, face) else {
+            continue;
+        };
+
+        if shape.shortest < NEEDLE_EDGE_RATIO * shape.longest {
+            let k = shape.shortest_edge;
+            let (a, b) = (face[k] as usize, face[(k + 1) % 3] as usize);
+            let mid: [f32; 3] = std::array::from_fn(|axis| {
+                (mesh.positions[a * 3 + axis] + mesh.positions[b * 3 + axis]) / 2.0
+            });
+            // Both ends now share a position, so they move as one from here
+            let (keep, merged) = (position_ids[a] as usize, position_ids[b] as usize);
+            let moved = std::mem::take(&mut members[merged]);
+            for &v in &moved {
+                position_ids[v] = keep as u32;
+            }
+            members[keep].extend(moved);
+            for &v in &members[keep] {
+                mesh.positions[v * 3..v * 3 + 3].copy_from_slice(&mid);
+            }
+            touched[keep] = true;
+            touched[merged] = true;
+            report.collapsed_edge_count += 1;
+            fixed += 1;
+        } else {
+            // Flip the longest edge with the face across it
+            let k = shape.longest_edge;
+            let (a, b, c) = (face[k], face[(k + 1) % 3], face[(k + 2) % 3]);
+            let Some(&g) = edge_face.get(&(b, a)) else {
+                continue;
+            };
+            let other = [0, 1, 2].map(|j| mesh.indices[g * 3 + j]);
+            let Some(&d) = other.iter().find(|&&v| v != a && v != b) else {
+                continue;
+            };
+            let d_id = position_ids[d as usize] as usize;
+            if touched[d_id] {
+                continue;
+            }
+
+            let (new_f, new_g) = ([a, d, c], [d, b, c]);
+            if !improves(&mesh.positions, [face, other], [new_f, new_g]) {
+                continue;
+            }
+            mesh.indices[f * 3..f * 3 + 3].copy_from_slice(&new_f);
+            mesh.indices[g * 3..g * 3 + 3].copy_from_slice(&new_g);
+            for id in ids.into_iter().chain([d_id]) {
+                touched[id] = true;
+            }
+            report.flipped_edge_count += 1;
+            fixed += 1;
+        }
+    }
+    fixed
+}
+
+/// Whether the flipped pair faces the same way and is better shaped
+fn improves(positions: &[f32], old: [[u32; 3]; 2], new: [[u32; 3]; 2]) -> bool {
+    let normal = |face: [u32; 3]| {
+        let [a, b, c] = face.map(|i| point(positions, i as usize));
+        cross(sub(b, a), sub(c, a))
+    };
+    let reference = add(normal(old[0]), normal(old[1]));
+    if new.iter().any(|&face| dot(normal(face), reference) <= 0.0) {
+        return false;
+    }
+
+    let worst = |pair: [[u32; 3]; 2]| {
+        pair.iter()
+            .map(|&face| Shape::of(positions, face).map_or(f32::MAX, |s| s.aspect_ratio()))
+            .fold(0.0f32, f32::max)
+    };
+    worst(new) < worst(old)
+}
+
+/// Area and edge lengths of a face
+struct Shape {
+    area: f32,
+    longest: f32,
+    shortest: f32,
+    /// Edge `k` runs from corner `k` to corner `k + 1`
+    longest_edge: usize,
+    shortest_edge: usize,
+}
+
+impl Shape {
+    fn of(positions: &[f32], face: [u32; 3]) -> Option<Self> {
+        let vertex_count = positions.len() / 3;
+        if face.iter().any(|&i| i as usize >= vertex_count) {
+            return None;
+        }
+        let p = face.map(|i| point(positions, i as usize));
+        let lengths = [0, 1, 2].map(|k| {
+            let d = sub(p[(k + 1) % 3], p[k]);
+            dot(d, d).sqrt()
+        });
+        let c = cross(sub(p[1], p[0]), sub(p[2], p[0]));
+        let area = dot(c, c).sqrt() / 2.0;
+        if !area.is_finite() || lengths.iter().any(|l| !l.is_finite()) {
+            return None;
+        }
+
+        let longest_edge = (0..3).max_by(|&a, &b| lengths[a].total_cmp(&lengths[b]))?;
+        let shortest_edge = (0..3).min_by(|&a, &b| lengths[a].total_cmp(&lengths[b]))?;
+        Some(Self {
+            area,
+            longest: lengths[longest_edge],
+            shortest: lengths[shortest_edge],
+            longest_edge,
+            shortest_edge,
+        })
+    }
+
+    /// Longest edge over shortest altitude, 1 for an equilateral triangle
+    fn aspect_ratio(&self) -> f32 {
+        if self.area <= 0.0 {
+            return f32::MAX;
+        }
+        self.longest * self.longest * 3f32.sqrt() / (4.0 * self.area)
+    }
+}
+
+fn point(positions: &[f32], i: usize) -> [f32; 3] {
+    [positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_collapses_needles_and_flips_caps() {
+        // A square split along a diagonal with a corner nudged onto it (a
+        // cap), plus two needles sharing a short edge and a collinear face
+        let positions = vec![
+            0.0, 0.0, 0.0, // 0
+            1.0, 0.0, 0.0, // 1
+            1.0, 1.0, 0.0, // 2
+            0.49, 0.51, 0.0, // 3, just off the 0-2 diagonal
+            2.0, 0.0, 0.0, // 4
+            2.0, 0.001, 0.0, // 5, a needle's short edge with 4
+            3.0, 0.0, 0.0, // 6
+            4.0, 0.0, 0.0, // 7
+            5.0, 0.0, 0.0, // 8, collinear with 6 and 7
+        ];
+        let mesh = MeshData {
+            positions,
+            indices: vec![
+                0, 1, 2, // good
+                0, 2, 3, // cap across 0-2
+                1, 4, 2, // good
+                4, 6, 5, // needle along 4-5
+                6, 8, 7, // no area
+                2, 4, 5, // another needle on the same short edge
+            ],
+            ..Default::default()
+        };
+        let thresholds = FaceThresholds::default();
+        assert_eq!(count(&mesh.positions, &mesh.indices, &thresholds), (1, 3));
+
+        let (cleaned, report) = clean(&mesh, &thresholds, true);
+        assert_eq!(report.degenerate_face_count, 1);
+        assert_eq!(report.sliver_face_count, 3);
+        assert_eq!(report.flipped_edge_count, 1);
+        assert_eq!(report.collapsed_edge_count, 1);
+        assert_eq!(report.remaining_sliver_count, 0);
+        // The collinear face and both needles are gone
+        assert_eq!(report.removed_face_count, 3);
+        assert_eq!(cleaned.face_count(), 3);
+        assert!(cleaned.validate().is_ok());
+
+        // Without fixing, only the collinear face goes
+        let (_, report) = clean(&mesh, &thresholds, false);
+        assert_eq!(report.removed_face_count, 1);
+        assert_eq!(report.remaining_sliver_count, 3);
+    }
+}
@@ -0,0 +1,322 @@
+//! Files a model refers to
+//!
+//! glTF files point at external buffers and images by URI, OBJ files at
+//! material libraries that in turn point at textures, and FBX files at
+//! texture images. Copying or moving only the model leaves those behind, so
+//! references are followed through every file they lead to and each target
+//! is checked on disk.
+
+use crate::utils::analysis_cache::ModelSource;
+use crate::utils::fbx;
+use crate::utils::gltf_geometry::decode_uri;
+use crate::utils::mesh_files::MeshFileFormat;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// MTL statements naming a texture besides the `map_*` family
+const MTL_TEXTURE_KEYS: [&str; 5] = ["bump", "disp", "decal", "refl", "norm"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyKind {
+    /// External glTF buffer
+    Buffer,
+    /// glTF image
+    Image,
+    /// OBJ material library
+    MaterialLibrary,
+    /// Texture named by a material library or an FBX file
+    Texture,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dependency {
+    pub kind: DependencyKind,
+    /// The reference as written in the file that uses it
+    pub reference: String,
+    /// File the reference appears in
+    pub referenced_by: String,
+    /// Where the reference points
+    pub path: String,
+    /// `path` relative to the model's folder, if it lies inside it
+    pub relative_path: Option<String>,
+    pub exists: bool,
+    pub size_bytes: Option<u64>,
+}
+
+/// Every file `model` depends on, directly or through its material
+/// libraries, in the order they're referenced
+pub fn resolve(model: &Path) -> Result<Vec<Dependency>, String> {
+    let dir = normalize(model.parent().unwrap_or(Path::new("")));
+    let mut dependencies = Vec::new();
+    let mut seen = HashSet::from([normalize(model)]);
+    let mut pending = vec![model.to_path_buf()];
+    while let Some(file) = pending.pop() {
+        let base = file.parent().unwrap_or(Path::new(""));
+        let mut found = Vec::new();
+        for (kind, reference) in references(&file)? {
+            let path = target(base, &reference);
+            if !seen.insert(path.clone()) {
+                continue;
+            }
+            let metadata = fs::metadata(&path).ok().filter(|m| m.is_file());
+            if kind == DependencyKind::MaterialLibrary && metadata.is_some() {
+                found.push(path.clone());
+            }
+            dependencies.push(Dependency {
+                kind,
+                reference,
+                referenced_by: file.to_string_lossy().to_string(),
+                relative_path: path
+                    .strip_prefix(&dir)
+                    .ok()
+                    .filter(|relative| !relative.starts_with(".."))
+                    .map(|relative| relative.to_string_lossy().replace('\\', "/")),
+                path: path.to_string_lossy().to_string(),
+                exists: metadata.is_some(),
+                size_bytes: metadata.map(|m| m.len()),
+            });
+        }
+        // Keep material libraries in the order the model lists them
+        pending.extend(found.into_iter().rev());
+    }
+    Ok(dependencies)
+}
+
+/// Where `model` and its dependencies go when the model is copied to
+/// `dest`: each existing dependency inside the model's folder keeps its path
+/// relative to the model so references keep working
+pub fn copied_files(
+    model: &Path,
+    dependencies: &[Dependency],
+    dest: &Path,
+) -> Vec<(PathBuf, PathBuf)> {
+    let dir = dest.parent().unwrap_or(Path::new(""));
+    let mut files = vec![(model.to_path_buf(), dest.to_path_buf())];
+    files.extend(dependencies.iter().filter(|d| d.exists).filter_map(|d| {
+        let relative = d.relative_path.as_ref()?;
+        Some((PathBuf::from(&d.path), dir.join(relative)))
+    }));
+    files
+}
+
+/// References in one file, by its extension
+fn references(file: &Path) -> Result<Vec<(DependencyKind, String)>, String> {
+    let read = || fs::read(file).map_err(|e| format!("Failed to read {}: {}", file.display(), e));
+    let is_mtl = file
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("mtl"));
+    if is_mtl {
+        return Ok(mtl_references(&String::from_utf8_lossy(&read()?)));
+    }
+    Ok(match MeshFileFormat::from_path(file)? {
+        MeshFileFormat::Gltf => gltf_references(ModelSource::open(file)?.json())?,
+        MeshFileFormat::Obj => obj_references(&String::from_utf8_lossy(&read()?)),
+        MeshFileFormat::Fbx => fbx::texture_files(&read()?)?
+            .into_iter()
+            .map(|name| (DependencyKind::Texture, name))
+            .collect(),
+        MeshFileFormat::Stl => Vec::new(),
+    })
+}
+
+/// Buffer and image URIs of a glTF JSON document, leaving out data URIs and
+/// the GLB binary chunk
+pub fn gltf_references(json: &[u8]) -> Result<Vec<(DependencyKind, String)>, String> {
+    let document: Value =
+        serde_json::from_slice(json).map_err(|e| format!("Failed to parse glTF JSON: {}", e))?;
+    let uris = |key: &str, kind: DependencyKind| {
+        document[key]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item["uri"].as_str())
+            .filter(|uri| !uri.starts_with("data:"))
+            .map(move |uri| (kind, uri.to_string()))
+            .collect::<Vec<_>>()
+    };
+    let mut references = uris("buffers", DependencyKind::Buffer);
+    references.extend(uris("images", DependencyKind::Image));
+    Ok(references)
+}
+
+/// Material libraries named by `mtllib` statements
+///
+/// The format separates several libraries with spaces, but exporters also
+/// write single names containing spaces; the line is only split when every
+/// part ends in `.mtl`.
+pub fn obj_references(text: &str) -> Vec<(DependencyKind, String)> {
+    let mut references = Vec::new();
+    for line in text.lines() {
+        let Some(rest) = line.trim().strip_prefix("mtllib") else {
+            continue;
+        };
+        if !rest.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let rest = rest.trim();
+        let parts: Vec<&str> = rest.split_whitespace().collect();
+        let separate = parts
+            .iter()
+            .all(|part| part.to_lowercase().ends_with(".mtl"));
+        if separate {
+            references.extend(
+                parts
+                    .into_iter()
+                    .map(|part| (DependencyKind::MaterialLibrary, part.to_string())),
+            );
+        } else if !rest.is_empty() {
+            references.push((DependencyKind::MaterialLibrary, rest.to_string()));
+        }
+    }
+    references
+}
+
+/// Texture files named by a material library, without their options
+pub fn mtl_references(text: &str) -> Vec<(DependencyKind, String)> {
+    let mut references: Vec<(DependencyKind, String)> = Vec::new();
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace().peekable();
+        let Some(key) = tokens.next() else {
+            continue;
+        };
+        let key = key.to_lowercase();
+        if !key.starts_with("map_") && !MTL_TEXTURE_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        let mut name = Vec::new();
+        while let Some(token) = tokens.next() {
+            if name.is_empty() && is_option(token) {
+                while tokens.peek().is_some_and(|arg| is_option_argument(arg)) {
+                    tokens.next();
+                }
+            } else {
+                name.push(token);
+            }
+        }
+        let name = name.join(" ");
+        if !name.is_empty() && !references.iter().any(|(_, seen)| *seen == name) {
+            references.push((DependencyKind::Texture, name));
+        }
+    }
+    references
+}
+
+/// An MTL texture option such as `-bm` or `-clamp`
+fn is_option(token: &str) -> bool {
+    token.len() > 1 && token.starts_with('-') && token.parse::<f32>().is_err()
+}
+
+/// A value following an MTL texture option: a number, `on`/`off`, an
+/// `-imfchan` channel or a `-type` name
+fn is_option_argument(token: &str) -> bool {
+    token.parse::<f32>().is_ok()
+        || ["on", "off", "sphere"].contains(&token)
+        || token.starts_with("cube_")
+        || (token.len() == 1 && "rgbmlz".contains(token))
+}
+
+/// The file a reference points at from `base`
+///
+/// Windows separators are accepted since Windows exporters write them, and
+/// absolute references, including ones with a drive letter, are kept as is.
+fn target(base: &Path, reference: &str) -> PathBuf {
+    let reference = decode_uri(reference).replace('\\', "/");
+    let has_drive = reference.as_bytes().get(1) == Some(&b':');
+    let path = Path::new(&reference);
+    if path.is_absolute() || has_drive {
+        return normalize(path);
+    }
+    normalize(&base.join(path))
+}
+
+/// Fold `.` and `..` components without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_obj_closure_and_copied_files() {
+        assert_eq!(
+            mtl_references(
+                "map_Kd -o 0.5 0.5 -clamp on tex/bark albedo.png\nbump -bm 2 n.png\nKd 1 1 1"
+            ),
+            vec![
+                (DependencyKind::Texture, "tex/bark albedo.png".to_string()),
+                (DependencyKind::Texture, "n.png".to_string()),
+            ]
+        );
+        let libraries = |text: &str| -> Vec<String> {
+            obj_references(text)
+                .into_iter()
+                .map(|(_, name)| name)
+                .collect()
+        };
+        assert_eq!(libraries("mtllib a.mtl b.mtl"), vec!["a.mtl", "b.mtl"]);
+        assert_eq!(libraries("mtllib my tree.mtl"), vec!["my tree.mtl"]);
+
+        let dir = std::env::temp_dir().join(format!("sweedle-deps-{}", std::process::id()));
+        fs::create_dir_all(dir.join("textures")).unwrap();
+        fs::write(dir.join("tree.obj"), "mtllib tree.mtl\nv 0 0 0\n").unwrap();
+        fs::write(
+            dir.join("tree.mtl"),
+            "newmtl bark\nmap_Kd textures\\bark.png\nmap_Bump missing.png\nmap_Ks ../shared.png\n",
+        )
+        .unwrap();
+        fs::write(dir.join("textures/bark.png"), b"png").unwrap();
+
+        let model = dir.join("tree.obj");
+        let dependencies = resolve(&model).unwrap();
+        let summary: Vec<(DependencyKind, Option<&str>, bool)> = dependencies
+            .iter()
+            .map(|d| (d.kind, d.relative_path.as_deref(), d.exists))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (DependencyKind::MaterialLibrary, Some("tree.mtl"), true),
+                (DependencyKind::Texture, Some("textures/bark.png"), true),
+                (DependencyKind::Texture, Some("missing.png"), false),
+                (DependencyKind::Texture, None, false),
+            ]
+        );
+        assert_eq!(dependencies[1].size_bytes, Some(3));
+        assert!(dependencies[1].referenced_by.ends_with("tree.mtl"));
+
+        let files = copied_files(
+            &model,
+            &dependencies,
+            Path::new("/storage/tree_1/tree_1.obj"),
+        );
+        let dests: Vec<PathBuf> = files.into_iter().map(|(_, dest)| dest).collect();
+        assert_eq!(
+            dests,
+            vec![
+                PathBuf::from("/storage/tree_1/tree_1.obj"),
+                PathBuf::from("/storage/tree_1/tree.mtl"),
+                PathBuf::from("/storage/tree_1/textures/bark.png"),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
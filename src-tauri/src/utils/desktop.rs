@@ -0,0 +1,125 @@
+//! Showing files in the system file manager and opening them in other apps
+//!
+//! Each platform has its own launcher: `open` on macOS, Explorer on Windows,
+//! and on Linux the file manager's D-Bus interface with `xdg-open` as the
+//! fallback. The webview's own opener can't select a file in its folder or
+//! pick the application.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Open the folder holding `path` with `path` selected
+///
+/// File managers on Linux that don't implement `org.freedesktop.FileManager1`
+/// open the folder without selecting anything.
+pub fn reveal(path: &Path) -> Result<(), String> {
+    if cfg!(target_os = "macos") {
+        run(Command::new("open").arg("-R").arg(path))
+    } else if cfg!(windows) {
+        // Explorer exits with 1 even when it succeeds, so only a failure to
+        // start it is an error
+        let mut select = std::ffi::OsString::from("/select,");
+        select.push(path);
+        spawn(Command::new("explorer").arg(select))
+    } else {
+        let shown = run(Command::new("dbus-send").args([
+            "--session",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+            &format!("array:string:{}", file_uri(path)),
+            "string:",
+        ]));
+        if shown.is_ok() {
+            return shown;
+        }
+        let folder = if path.is_dir() {
+            path
+        } else {
+            path.parent().unwrap_or(path)
+        };
+        run(Command::new("xdg-open").arg(folder))
+    }
+}
+
+/// Open `path` in `application`, or in the application the system
+/// associates with its type
+///
+/// `application` is an executable, or on macOS also an application name
+/// such as `Blender`.
+pub fn open(path: &Path, application: Option<&str>) -> Result<(), String> {
+    match application {
+        Some(app) if cfg!(target_os = "macos") && !Path::new(app).is_file() => {
+            run(Command::new("open").arg("-a").arg(app).arg(path))
+        }
+        Some(app) => spawn(Command::new(app).arg(path)),
+        None if cfg!(target_os = "macos") => run(Command::new("open").arg(path)),
+        None if cfg!(windows) => spawn(Command::new("explorer").arg(path)),
+        None => run(Command::new("xdg-open").arg(path)),
+    }
+}
+
+/// `file://` URI of an absolute path, percent-encoding all but unreserved
+/// characters and separators
+pub fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().replace('\\', "/").bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Run a launcher that exits once it has handed off the file
+fn run(command: &mut Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Start a program that keeps running, reaping it when it exits
+fn spawn(command: &mut Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", program, e))?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_uri_encodes_reserved_characters() {
+        assert_eq!(
+            file_uri(Path::new("/assets/Rock #2/mossy rock.glb")),
+            "file:///assets/Rock%20%232/mossy%20rock.glb"
+        );
+        assert_eq!(
+            file_uri(Path::new("/lib/caf\u{e9}.png")),
+            "file:///lib/caf%C3%A9.png"
+        );
+    }
+}
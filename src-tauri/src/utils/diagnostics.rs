@@ -0,0 +1,247 @@
+//! Self-checks for the troubleshooting page
+//!
+//! Each [`Check`] looks at one thing that commonly breaks an install, such
+//! as a full disk under a storage root or a Blender that can't be found,
+//! and says what to do about it. The report's status is the worst of its
+//! checks, so the page can flag it before the user reads the list.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Free space under which a folder gets a warning
+pub const LOW_SPACE_BYTES: u64 = 1 << 30;
+/// Free space under which writes are likely to fail
+pub const CRITICAL_SPACE_BYTES: u64 = 100 << 20;
+
+/// Outcome of a check, from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    /// Not checked, such as write access in read-only mode
+    Skipped,
+    Warning,
+    Error,
+}
+
+/// What a check is about, for grouping them on the page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckArea {
+    Gpu,
+    DiskSpace,
+    Permissions,
+    Index,
+    Cache,
+    Plugins,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Check {
+    pub area: CheckArea,
+    /// What was checked, such as `Storage root Archive`
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+    /// What to do about a warning or error
+    pub hint: Option<String>,
+}
+
+impl Check {
+    pub fn ok(area: CheckArea, name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            area,
+            name: name.into(),
+            status: CheckStatus::Ok,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn skipped(area: CheckArea, name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Skipped,
+            ..Self::ok(area, name, message)
+        }
+    }
+
+    pub fn warning(area: CheckArea, name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Warning,
+            ..Self::ok(area, name, message)
+        }
+    }
+
+    pub fn error(area: CheckArea, name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Error,
+            ..Self::ok(area, name, message)
+        }
+    }
+
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+/// Result of `run_diagnostics`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    pub created_ms: u64,
+    /// Worst status of the checks
+    pub status: CheckStatus,
+    pub checks: Vec<Check>,
+}
+
+impl DiagnosticsReport {
+    pub fn new(created_ms: u64, checks: Vec<Check>) -> Self {
+        Self {
+            created_ms,
+            status: checks
+                .iter()
+                .map(|check| check.status)
+                .max()
+                .unwrap_or(CheckStatus::Ok),
+            checks,
+        }
+    }
+}
+
+/// How much free space the folder at `path` has
+pub fn disk_space(name: &str, path: &Path) -> Check {
+    let free = match free_space(path) {
+        Ok(free) => free,
+        Err(e) => return Check::warning(CheckArea::DiskSpace, name, e),
+    };
+    let message = format!("{} MB free", free >> 20);
+    let hint = "Free up space on the drive or move the folder to a larger one";
+    if free < CRITICAL_SPACE_BYTES {
+        Check::error(CheckArea::DiskSpace, name, message).hint(hint)
+    } else if free < LOW_SPACE_BYTES {
+        Check::warning(CheckArea::DiskSpace, name, message).hint(hint)
+    } else {
+        Check::ok(CheckArea::DiskSpace, name, message)
+    }
+}
+
+/// Whether a file can be created and removed in the folder at `path`
+pub fn write_access(name: &str, path: &Path) -> Check {
+    match check_writable(path) {
+        Ok(()) => Check::ok(CheckArea::Permissions, name, "Writable"),
+        Err(e) => Check::error(CheckArea::Permissions, name, e)
+            .hint("Check the folder's permissions and that its drive isn't read-only"),
+    }
+}
+
+/// Create, write and remove a probe file in `dir`
+pub fn check_writable(dir: &Path) -> Result<(), String> {
+    let probe = dir.join(format!(".sweedle-write-check-{}", std::process::id()));
+    let written = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .and_then(|mut file| file.write_all(b"sweedle"));
+    let removed = fs::remove_file(&probe);
+    written
+        .and(removed)
+        .map_err(|e| format!("Failed to write to {}: {}", dir.display(), e))
+}
+
+/// Bytes available to this user on the volume holding `path`
+#[cfg(unix)]
+pub fn free_space(path: &Path) -> Result<u64, String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| format!("Invalid path: {}", path.display()))?;
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: the path is NUL-terminated and `stats` is only read once
+    // statvfs has filled it in
+    if unsafe { libc::statvfs(c_path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return Err(space_error(path, io::Error::last_os_error()));
+    }
+    let stats = unsafe { stats.assume_init() };
+    // The field types differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    let available = stats.f_bavail as u64 * stats.f_frsize as u64;
+    Ok(available)
+}
+
+/// Bytes available to this user on the volume holding `path`
+#[cfg(windows)]
+pub fn free_space(path: &Path) -> Result<u64, String> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            available: *mut u64,
+            total: *mut u64,
+            free: *mut u64,
+        ) -> i32;
+    }
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    let mut available = 0u64;
+    // SAFETY: the path is NUL-terminated and the totals we don't want may
+    // be null
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(space_error(path, io::Error::last_os_error()));
+    }
+    Ok(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn free_space(path: &Path) -> Result<u64, String> {
+    Err(space_error(
+        path,
+        io::Error::new(io::ErrorKind::Unsupported, "not supported here"),
+    ))
+}
+
+fn space_error(path: &Path, e: io::Error) -> String {
+    format!("Failed to read the free space of {}: {}", path.display(), e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probes_and_worst_status() {
+        let dir = std::env::temp_dir();
+        assert!(check_writable(&dir).is_ok());
+        assert!(!dir
+            .join(format!(".sweedle-write-check-{}", std::process::id()))
+            .exists());
+        assert!(check_writable(&dir.join("sweedle-no-such-folder")).is_err());
+        #[cfg(unix)]
+        assert!(free_space(&dir).unwrap() > 0);
+
+        let report = DiagnosticsReport::new(
+            0,
+            vec![
+                Check::ok(CheckArea::Gpu, "GPU", "Found"),
+                Check::warning(CheckArea::Plugins, "Blender", "Not found"),
+                Check::skipped(CheckArea::Permissions, "Root", "Read-only mode"),
+            ],
+        );
+        assert_eq!(report.status, CheckStatus::Warning);
+        assert_eq!(
+            DiagnosticsReport::new(0, Vec::new()).status,
+            CheckStatus::Ok
+        );
+    }
+}
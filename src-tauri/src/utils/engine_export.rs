@@ -0,0 +1,188 @@
+//! Layout and metadata for dropping models into a game engine project
+//!
+//! Each model is copied with its companion files (textures, OBJ materials,
+//! glTF buffers) into a folder of its own, so relative references between
+//! them keep working. For Unity every file and folder gets a `.meta` stub
+//! whose GUID is derived from its path, so exporting again to the same place
+//! keeps the GUIDs that scenes and prefabs refer to. For Unreal a manifest in
+//! the format of the `ImportAssets` commandlet lists what to import where.
+
+use crate::utils::profiles::sibling_textures;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use xxhash_rust::xxh3::xxh3_128;
+
+/// Name of the Unreal import manifest written to the output folder
+pub const UNREAL_MANIFEST: &str = "import_manifest.json";
+/// Extensions of files that travel with a model besides its textures
+const COMPANION_EXTENSIONS: [&str; 2] = ["mtl", "bin"];
+const MODEL_EXTENSIONS: [&str; 5] = ["glb", "gltf", "obj", "stl", "fbx"];
+const TEXTURE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Engine {
+    Unity,
+    Unreal,
+}
+
+/// Files beside `model` that belong with it: images whose names start with
+/// the model's, and its material library or buffers
+pub fn companions(model: &Path) -> Vec<PathBuf> {
+    let mut files = sibling_textures(model);
+    for extension in COMPANION_EXTENSIONS {
+        let path = model.with_extension(extension);
+        if path.is_file() && path != model {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// A folder name for each model stem, with anything but letters, digits,
+/// `-` and `_` replaced and repeats numbered (`rock`, `rock_2`)
+pub fn folder_names<S: AsRef<str>>(stems: &[S]) -> Vec<String> {
+    let mut used = HashSet::new();
+    stems
+        .iter()
+        .map(|stem| {
+            let base: String = stem
+                .as_ref()
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            let base = if base.is_empty() {
+                "model".to_string()
+            } else {
+                base
+            };
+            let mut name = base.clone();
+            let mut n = 1;
+            while !used.insert(name.to_lowercase()) {
+                n += 1;
+                name = format!("{}_{}", base, n);
+            }
+            name
+        })
+        .collect()
+}
+
+/// A Unity `.meta` file for the file or folder at `path`
+///
+/// Only the GUID and the importer type are set; Unity fills in the
+/// importer's defaults the first time it imports the asset.
+pub fn unity_meta(path: &Path, is_folder: bool) -> String {
+    let guid = format!(
+        "{:032x}",
+        xxh3_128(path.to_string_lossy().replace('\\', "/").as_bytes())
+    );
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let importer = if is_folder {
+        "DefaultImporter"
+    } else if MODEL_EXTENSIONS.contains(&extension.as_str()) {
+        "ModelImporter"
+    } else if TEXTURE_EXTENSIONS.contains(&extension.as_str()) {
+        "TextureImporter"
+    } else {
+        "DefaultImporter"
+    };
+    let folder = if is_folder { "folderAsset: yes\n" } else { "" };
+    format!(
+        "fileFormatVersion: 2\nguid: {}\n{}{}:\n  externalObjects: {{}}\n  userData: \n  assetBundleName: \n  assetBundleVariant: \n",
+        guid, folder, importer
+    )
+}
+
+/// An `ImportAssets` commandlet manifest importing each folder's files under
+/// `content_root` (such as `/Game/Props`)
+pub fn unreal_manifest(content_root: &str, folders: &[(String, Vec<PathBuf>)]) -> String {
+    let groups: Vec<serde_json::Value> = folders
+        .iter()
+        .map(|(name, files)| {
+            let filenames: Vec<String> = files
+                .iter()
+                .filter(|file| {
+                    let extension = file
+                        .extension()
+                        .map(|e| e.to_string_lossy().to_lowercase())
+                        .unwrap_or_default();
+                    MODEL_EXTENSIONS.contains(&extension.as_str())
+                        || TEXTURE_EXTENSIONS.contains(&extension.as_str())
+                })
+                .map(|file| file.to_string_lossy().replace('\\', "/"))
+                .collect();
+            json!({
+                "GroupName": name,
+                "Filenames": filenames,
+                "DestinationPath": format!("{}/{}", content_root.trim_end_matches('/'), name),
+                "bReplaceExisting": true,
+                "bSkipReadOnly": true,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&json!({ "ImportGroups": groups })).unwrap_or_default()
+}
+
+/// The Unreal content path for an output folder: `/Game/<folder name>`
+pub fn unreal_content_root(out_dir: &Path) -> String {
+    let name = out_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = folder_names(&[name]).remove(0);
+    format!("/Game/{}", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_names_and_metadata() {
+        assert_eq!(
+            folder_names(&["rock", "Rock", "old tree.v2", ""]),
+            vec!["rock", "Rock_2", "old_tree_v2", "model"]
+        );
+
+        let model = unity_meta(Path::new("/Project/Assets/Props/rock/rock.fbx"), false);
+        let again = unity_meta(Path::new("/Project/Assets/Props/rock/rock.fbx"), false);
+        let texture = unity_meta(Path::new("/Project/Assets/Props/rock/rock.png"), false);
+        let folder = unity_meta(Path::new("/Project/Assets/Props/rock"), true);
+        assert_eq!(model, again);
+        assert!(model.contains("ModelImporter:"));
+        assert!(texture.contains("TextureImporter:"));
+        assert!(folder.contains("folderAsset: yes\nDefaultImporter:"));
+        let guid = |meta: &str| meta.lines().nth(1).unwrap().to_string();
+        assert_ne!(guid(&model), guid(&texture));
+        assert_eq!(guid(&model).len(), "guid: ".len() + 32);
+
+        let root = unreal_content_root(Path::new("/exports/Sci-Fi props"));
+        assert_eq!(root, "/Game/Sci-Fi_props");
+        let manifest = unreal_manifest(
+            &root,
+            &[(
+                "rock".to_string(),
+                vec![
+                    PathBuf::from("/exports/rock/rock.obj"),
+                    PathBuf::from("/exports/rock/rock.mtl"),
+                    PathBuf::from("/exports/rock/rock.png"),
+                ],
+            )],
+        );
+        let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+        let group = &manifest["ImportGroups"][0];
+        assert_eq!(group["DestinationPath"], "/Game/Sci-Fi_props/rock");
+        assert_eq!(group["Filenames"].as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,335 @@
+//! Time and memory estimates for heavy mesh operations
+//!
+//! Each backend is benchmarked once per session on a small built-in mesh.
+//! The measured per-face, per-voxel and per-ray-step costs are then scaled
+//! to the real mesh size, so the UI can warn before starting a job that
+//! would run for minutes. Estimates are rough by design: time is usually
+//! within a small factor, memory is an upper bound that assumes a cubic
+//! voxel grid.
+
+use crate::utils::compute::{check_voxel_resolution, AoParams, ComputeBackend, VoxelGrid};
+use crate::utils::compute::{DEFAULT_AO_SAMPLES, DEFAULT_VOXEL_RESOLUTION};
+use crate::utils::decimate::cluster_decimate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Estimates past this are flagged as long-running
+const LONG_RUNNING_MS: f64 = 60_000.0;
+/// Latitude/longitude segments of the calibration sphere (~8k faces)
+const CALIBRATION_SEGMENTS: u32 = 64;
+const CALIBRATION_LOW_RES: u32 = 16;
+const CALIBRATION_HIGH_RES: u32 = 48;
+const CALIBRATION_AO_SAMPLES: u32 = 16;
+
+/// An operation to estimate, with the parameters its command would use
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EstimatedOperation {
+    /// LOD generation / decimation
+    Simplify,
+    Normals,
+    Curvature,
+    Voxelize {
+        resolution: Option<u32>,
+        fill_interior: Option<bool>,
+    },
+    AoBake {
+        samples: Option<u32>,
+        resolution: Option<u32>,
+    },
+}
+
+/// Size of the mesh an estimate is for
+#[derive(Debug, Clone, Copy)]
+pub struct MeshSize {
+    pub vertex_count: usize,
+    pub face_count: usize,
+    pub has_normals: bool,
+}
+
+/// Expected cost of an operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationEstimate {
+    pub operation: EstimatedOperation,
+    pub backend: String,
+    pub vertex_count: usize,
+    pub face_count: usize,
+    pub estimated_ms: f64,
+    /// Extra memory the operation allocates on top of the mesh itself
+    pub estimated_peak_bytes: u64,
+    pub long_running: bool,
+}
+
+/// Measured costs of one backend, in nanoseconds per unit of work
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Calibration {
+    pub normals_ns_per_face: f64,
+    pub curvature_ns_per_face: f64,
+    pub decimate_ns_per_face: f64,
+    pub voxelize_ns_per_face: f64,
+    /// Surface voxels grow with the square of the resolution
+    pub voxelize_ns_per_surface_voxel: f64,
+    pub fill_ns_per_voxel: f64,
+    pub ao_ns_per_ray_step: f64,
+}
+
+impl Calibration {
+    /// Benchmark `backend` on the built-in calibration mesh
+    pub fn measure(backend: &dyn ComputeBackend) -> Result<Self, String> {
+        let (positions, indices) = calibration_sphere(CALIBRATION_SEGMENTS);
+        let faces = (indices.len() / 3) as f64;
+        let vertices = (positions.len() / 3) as f64;
+
+        let (normals_ns, normals) = time(|| backend.vertex_normals(&positions, &indices));
+        let normals = normals?;
+        let (curvature_ns, curvature) =
+            time(|| backend.mean_curvature(&positions, &normals, &indices));
+        curvature?;
+        let (decimate_ns, _) = time(|| cluster_decimate(&positions, &indices, indices.len() / 12));
+
+        let voxelize = |resolution| {
+            let mut grid = VoxelGrid::fitting(&positions, resolution)?;
+            let (ns, result) = time(|| backend.voxelize(&positions, &indices, &mut grid));
+            result.map(|()| (ns, grid))
+        };
+        let (low_ns, _) = voxelize(CALIBRATION_LOW_RES)?;
+        let (high_ns, mut grid) = voxelize(CALIBRATION_HIGH_RES)?;
+        let surface_voxels = (CALIBRATION_HIGH_RES.pow(2) - CALIBRATION_LOW_RES.pow(2)) as f64;
+        let per_surface_voxel = ((high_ns - low_ns) / surface_voxels).max(0.0);
+        let per_face =
+            ((low_ns - per_surface_voxel * CALIBRATION_LOW_RES.pow(2) as f64) / faces).max(0.0);
+
+        let (fill_ns, _) = time(|| grid.fill_interior());
+        let params = AoParams {
+            samples: CALIBRATION_AO_SAMPLES,
+            max_steps: CALIBRATION_HIGH_RES,
+        };
+        let (ao_ns, ao) = time(|| backend.ambient_occlusion(&positions, &normals, &grid, params));
+        ao?;
+
+        Ok(Self {
+            normals_ns_per_face: normals_ns / faces,
+            curvature_ns_per_face: curvature_ns / faces,
+            decimate_ns_per_face: decimate_ns / faces,
+            voxelize_ns_per_face: per_face,
+            voxelize_ns_per_surface_voxel: per_surface_voxel,
+            fill_ns_per_voxel: fill_ns / grid.voxel_count() as f64,
+            ao_ns_per_ray_step: ao_ns
+                / (vertices * CALIBRATION_AO_SAMPLES as f64 * CALIBRATION_HIGH_RES as f64),
+        })
+    }
+
+    /// Expected time (ms) and extra memory (bytes) of `operation` on `mesh`
+    pub fn estimate(&self, operation: EstimatedOperation, mesh: MeshSize) -> (f64, u64) {
+        let faces = mesh.face_count as f64;
+        let vertices = mesh.vertex_count as u64;
+
+        // Normals are generated first whenever an operation needs them
+        let (normals_ns, normals_bytes) = if mesh.has_normals {
+            (0.0, 0)
+        } else {
+            (self.normals_ns_per_face * faces, vertices * 12)
+        };
+        let voxelize = |resolution: u32, fill: bool| {
+            let r = resolution as f64;
+            let mut ns =
+                self.voxelize_ns_per_face * faces + self.voxelize_ns_per_surface_voxel * r * r;
+            let voxels = (resolution as u64).pow(3);
+            // Bitset grid, plus one byte per voxel for the flood fill
+            let mut bytes = voxels / 8;
+            if fill {
+                ns += self.fill_ns_per_voxel * r * r * r;
+                bytes += voxels;
+            }
+            (ns, bytes)
+        };
+
+        let (ns, bytes) = match operation {
+            EstimatedOperation::Simplify => (
+                self.decimate_ns_per_face * faces,
+                // Cluster map per vertex, plus a copy of the output mesh
+                vertices * 40 + mesh.face_count as u64 * 12,
+            ),
+            EstimatedOperation::Normals => (self.normals_ns_per_face * faces, vertices * 24),
+            EstimatedOperation::Curvature => (
+                normals_ns + self.curvature_ns_per_face * faces,
+                // Neighbor lists hold two entries per face corner
+                normals_bytes + mesh.face_count as u64 * 24 + vertices * 4,
+            ),
+            EstimatedOperation::Voxelize {
+                resolution,
+                fill_interior,
+            } => {
+                let resolution = resolution.unwrap_or(DEFAULT_VOXEL_RESOLUTION);
+                let (ns, bytes) = voxelize(resolution, fill_interior.unwrap_or(false));
+                // The bitset is returned to the frontend as JSON numbers
+                let json = (resolution as u64).pow(3) / 32 * 11;
+                (ns, bytes + json)
+            }
+            EstimatedOperation::AoBake {
+                samples,
+                resolution,
+            } => {
+                let resolution = resolution.unwrap_or(DEFAULT_VOXEL_RESOLUTION);
+                let samples = samples.unwrap_or(DEFAULT_AO_SAMPLES) as f64;
+                let (voxel_ns, voxel_bytes) = voxelize(resolution, true);
+                let trace_ns = self.ao_ns_per_ray_step
+                    * mesh.vertex_count as f64
+                    * samples
+                    * resolution as f64;
+                (
+                    normals_ns + voxel_ns + trace_ns,
+                    normals_bytes + voxel_bytes + vertices * 4,
+                )
+            }
+        };
+        (ns / 1_000_000.0, bytes)
+    }
+}
+
+/// Calibrations per backend name, measured lazily and managed as Tauri state
+#[derive(Default)]
+pub struct CostEstimator {
+    calibrations: Mutex<HashMap<&'static str, Calibration>>,
+}
+
+impl CostEstimator {
+    /// Calibration for `backend`, benchmarking it on first use
+    pub fn calibration(&self, backend: &dyn ComputeBackend) -> Result<Calibration, String> {
+        if let Some(calibration) = self.calibrations.lock().unwrap().get(backend.name()) {
+            return Ok(*calibration);
+        }
+        let calibration = Calibration::measure(backend)?;
+        self.calibrations
+            .lock()
+            .unwrap()
+            .insert(backend.name(), calibration);
+        Ok(calibration)
+    }
+
+    pub fn estimate(
+        &self,
+        backend: &dyn ComputeBackend,
+        operation: EstimatedOperation,
+        mesh: MeshSize,
+    ) -> Result<OperationEstimate, String> {
+        if let EstimatedOperation::Voxelize {
+            resolution: Some(resolution),
+            ..
+        }
+        | EstimatedOperation::AoBake {
+            resolution: Some(resolution),
+            ..
+        } = operation
+        {
+            check_voxel_resolution(resolution)?;
+        }
+        let (estimated_ms, estimated_peak_bytes) =
+            self.calibration(backend)?.estimate(operation, mesh);
+        Ok(OperationEstimate {
+            operation,
+            backend: backend.name().to_string(),
+            vertex_count: mesh.vertex_count,
+            face_count: mesh.face_count,
+            estimated_ms,
+            estimated_peak_bytes,
+            long_running: estimated_ms > LONG_RUNNING_MS,
+        })
+    }
+}
+
+/// Fastest of three runs, after a warm-up run
+fn time<T>(mut f: impl FnMut() -> T) -> (f64, T) {
+    let mut result = f();
+    let mut best = f64::MAX;
+    for _ in 0..3 {
+        let started = Instant::now();
+        result = f();
+        best = best.min(started.elapsed().as_secs_f64() * 1e9);
+    }
+    (best, result)
+}
+
+/// Closed UV sphere with `segments` latitude and longitude divisions
+fn calibration_sphere(segments: u32) -> (Vec<f32>, Vec<u32>) {
+    let mut positions = Vec::new();
+    for lat in 0..=segments {
+        let theta = lat as f32 / segments as f32 * PI;
+        for lon in 0..segments {
+            let phi = lon as f32 / segments as f32 * 2.0 * PI;
+            positions.extend([
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            ]);
+        }
+    }
+
+    let mut indices = Vec::new();
+    for lat in 0..segments {
+        for lon in 0..segments {
+            let a = lat * segments + lon;
+            let b = lat * segments + (lon + 1) % segments;
+            let (c, d) = (a + segments, b + segments);
+            indices.extend([a, c, b, b, c, d]);
+        }
+    }
+    (positions, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates_scale_with_mesh_and_resolution() {
+        let calibration = Calibration {
+            normals_ns_per_face: 10.0,
+            curvature_ns_per_face: 20.0,
+            decimate_ns_per_face: 30.0,
+            voxelize_ns_per_face: 5.0,
+            voxelize_ns_per_surface_voxel: 2.0,
+            fill_ns_per_voxel: 1.0,
+            ao_ns_per_ray_step: 0.5,
+        };
+        let mesh = |faces: usize, has_normals| MeshSize {
+            vertex_count: faces / 2,
+            face_count: faces,
+            has_normals,
+        };
+
+        let (small, _) = calibration.estimate(EstimatedOperation::Simplify, mesh(1_000, false));
+        let (large, _) = calibration.estimate(EstimatedOperation::Simplify, mesh(1_000_000, false));
+        assert!((large / small - 1000.0).abs() < 1e-6);
+
+        // Missing normals are generated first
+        let (with, _) = calibration.estimate(EstimatedOperation::Curvature, mesh(1_000, true));
+        let (without, _) = calibration.estimate(EstimatedOperation::Curvature, mesh(1_000, false));
+        assert!((without - with - 0.01).abs() < 1e-9);
+
+        let ao = |resolution| {
+            calibration.estimate(
+                EstimatedOperation::AoBake {
+                    samples: Some(64),
+                    resolution: Some(resolution),
+                },
+                mesh(100_000, true),
+            )
+        };
+        let (low_ms, low_bytes) = ao(64);
+        let (high_ms, high_bytes) = ao(256);
+        assert!(high_ms > low_ms * 4.0);
+        assert!(high_bytes > low_bytes * 32);
+    }
+
+    #[test]
+    fn test_calibration_sphere_dimensions() {
+        let (positions, indices) = calibration_sphere(8);
+        assert_eq!(positions.len() / 3, 9 * 8);
+        assert_eq!(indices.len() / 3, 8 * 8 * 2);
+        assert!(indices.iter().all(|&i| (i as usize) < positions.len() / 3));
+    }
+}
@@ -0,0 +1,277 @@
+//! External command-line tools run on assets
+//!
+//! A tool is an executable and a list of argument templates, such as
+//! Blender with `--background --python fix.py -- {path}` or `gltf-transform
+//! optimize {path} {dir}/{stem}_opt.glb`. Arguments are passed to the tool
+//! as they are, never through a shell, so paths with spaces need no quoting.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Output kept from each of a tool's streams; the end is what explains a
+/// failure
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExternalTool {
+    pub id: String,
+    pub name: String,
+    pub executable: String,
+    /// Argument templates; `{path}` is the asset's file, `{dir}` its folder,
+    /// `{stem}` its name without extension and `{ext}` its extension
+    pub arguments: Vec<String>,
+    /// The tool is stopped if it runs longer
+    pub timeout_secs: u64,
+}
+
+impl Default for ExternalTool {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            name: String::new(),
+            executable: String::new(),
+            arguments: Vec::new(),
+            timeout_secs: 600,
+        }
+    }
+}
+
+impl ExternalTool {
+    /// The tool's arguments for the asset at `path`
+    pub fn arguments_for(&self, path: &Path) -> Result<Vec<String>, String> {
+        let text = |value: Option<&std::ffi::OsStr>| {
+            value
+                .map(|v| v.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        let values = [
+            ("path", path.to_string_lossy().to_string()),
+            ("dir", text(path.parent().map(Path::as_os_str))),
+            ("stem", text(path.file_stem())),
+            ("ext", text(path.extension())),
+        ];
+        self.arguments
+            .iter()
+            .map(|template| expand(template, &values))
+            .collect()
+    }
+
+    /// Check a tool before it's saved; unknown placeholders are errors
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.trim().is_empty() {
+            return Err("External tools need an id".to_string());
+        }
+        if self.executable.trim().is_empty() {
+            return Err(format!("External tool {} has no executable", self.id));
+        }
+        self.arguments_for(Path::new("/asset.glb"))
+            .map(|_| ())
+            .map_err(|e| format!("External tool {}: {}", self.id, e))
+    }
+}
+
+/// How a tool run ended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolOutput {
+    /// `None` if the tool was stopped at its timeout or by a signal
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+    pub elapsed_ms: u64,
+}
+
+impl ToolOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Run `tool` on the asset at `path`, waiting until it exits or times out
+pub fn run(tool: &ExternalTool, path: &Path) -> Result<ToolOutput, String> {
+    let started = Instant::now();
+    let mut child = Command::new(&tool.executable)
+        .args(tool.arguments_for(path)?)
+        .current_dir(path.parent().unwrap_or(Path::new(".")))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", tool.executable, e))?;
+    let stdout = child.stdout.take().map(read_tail);
+    let stderr = child.stderr.take().map(read_tail);
+
+    let deadline = started + Duration::from_secs(tool.timeout_secs.max(1));
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(e) => return Err(format!("Failed to wait for {}: {}", tool.executable, e)),
+        }
+    };
+    let text = |handle: Option<thread::JoinHandle<Vec<u8>>>| {
+        let bytes = handle.and_then(|h| h.join().ok()).unwrap_or_default();
+        String::from_utf8_lossy(&bytes).to_string()
+    };
+
+    Ok(ToolOutput {
+        exit_code: status.and_then(|s| s.code()),
+        timed_out: status.is_none(),
+        stdout: text(stdout),
+        stderr: text(stderr),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Where `executable` would be run from: the path itself if it names a
+/// file, otherwise the first match on the `PATH`
+///
+/// On Windows a bare name is also tried with each extension in `PATHEXT`.
+pub fn find_executable(executable: &str) -> Option<PathBuf> {
+    let executable = Path::new(executable.trim());
+    if executable.as_os_str().is_empty() {
+        return None;
+    }
+    if executable.components().count() > 1 || executable.is_absolute() {
+        return executable.is_file().then(|| executable.to_path_buf());
+    }
+    let extensions: Vec<String> = match std::env::var("PATHEXT") {
+        Ok(extensions) if cfg!(windows) && executable.extension().is_none() => extensions
+            .split(';')
+            .map(|ext| ext.trim_start_matches('.').to_string())
+            .collect(),
+        _ => vec![String::new()],
+    };
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| {
+        extensions.iter().find_map(|ext| {
+            let mut name = executable.as_os_str().to_os_string();
+            if !ext.is_empty() {
+                name.push(format!(".{}", ext));
+            }
+            let candidate = dir.join(name);
+            candidate.is_file().then_some(candidate)
+        })
+    })
+}
+
+/// Size and modification time of the files in a folder, to tell what a
+/// tool changed
+pub fn snapshot(dir: &Path) -> BTreeMap<PathBuf, (u64, SystemTime)> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().ok()?;
+            metadata
+                .is_file()
+                .then(|| (entry.path(), (metadata.len(), modified)))
+        })
+        .collect()
+}
+
+/// Files in `after` that are new or differ from `before`
+pub fn changed_files(
+    before: &BTreeMap<PathBuf, (u64, SystemTime)>,
+    after: &BTreeMap<PathBuf, (u64, SystemTime)>,
+) -> Vec<PathBuf> {
+    after
+        .iter()
+        .filter(|(path, stamp)| before.get(*path) != Some(stamp))
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+fn expand(template: &str, values: &[(&str, String)]) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| format!("Unclosed placeholder in {}", template))?;
+        let name = &rest[start + 1..end];
+        let (_, value) = values
+            .iter()
+            .find(|(key, _)| *key == name)
+            .ok_or_else(|| format!("Unknown placeholder {{{}}} in {}", name, template))?;
+        out.push_str(value);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Everything a pipe produces, keeping only the last `MAX_OUTPUT_BYTES`
+fn read_tail(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut kept = Vec::new();
+        let mut buffer = [0u8; 8192];
+        while let Ok(read) = pipe.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+            kept.extend_from_slice(&buffer[..read]);
+            if kept.len() > 2 * MAX_OUTPUT_BYTES {
+                kept.drain(..kept.len() - MAX_OUTPUT_BYTES);
+            }
+        }
+        if kept.len() > MAX_OUTPUT_BYTES {
+            kept.drain(..kept.len() - MAX_OUTPUT_BYTES);
+        }
+        kept
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arguments_expand_placeholders() {
+        let tool = ExternalTool {
+            id: "optimize".to_string(),
+            executable: "gltf-transform".to_string(),
+            arguments: vec![
+                "optimize".to_string(),
+                "{path}".to_string(),
+                "{dir}/{stem}_opt.{ext}".to_string(),
+            ],
+            ..Default::default()
+        };
+        let arguments = tool
+            .arguments_for(Path::new("/scans/old rock.glb"))
+            .unwrap();
+        assert_eq!(
+            arguments,
+            vec!["optimize", "/scans/old rock.glb", "/scans/old rock_opt.glb"]
+        );
+        assert!(tool.validate().is_ok());
+
+        let typo = ExternalTool {
+            arguments: vec!["{file}".to_string()],
+            ..tool.clone()
+        };
+        assert!(typo.validate().unwrap_err().contains("{file}"));
+        let unclosed = ExternalTool {
+            arguments: vec!["{path".to_string()],
+            ..tool
+        };
+        assert!(unclosed.validate().is_err());
+    }
+}
@@ -0,0 +1,753 @@
+//! Geometry from binary FBX files
+//!
+//! Only what import needs is read: each mesh's vertices and polygons, the
+//! local transforms of the models they hang off (baked into the vertices, so
+//! transforms come out frozen), and the axis and unit declarations in
+//! `GlobalSettings`. Pivots and rotation orders other than XYZ are ignored,
+//! as are ASCII files.
+
+use crate::utils::axis_conversion::{AxisConversion, UpAxis};
+use crate::utils::mesh_files::NamedMesh;
+use crate::utils::triangulate::Triangulator;
+use flate2::read::ZlibDecoder;
+use nalgebra::{Matrix4, Point3, Rotation3, Vector3};
+use std::collections::HashMap;
+use std::io::Read;
+
+const MAGIC: &[u8] = b"Kaydara FBX Binary  \0";
+
+/// Node records switched to 64-bit offsets in FBX 7.5
+const WIDE_VERSION: u32 = 7500;
+
+/// Model parents followed before giving up on a cyclic hierarchy
+const MAX_DEPTH: usize = 64;
+
+/// Meshes of an FBX file in its own axes and units
+#[derive(Debug, Clone, Default)]
+pub struct FbxScene {
+    pub meshes: Vec<NamedMesh>,
+    /// Conversion to Y-up meters declared by the file, if it can be expressed
+    pub axes: Option<AxisConversion>,
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Ints(Vec<i64>),
+    Floats(Vec<f64>),
+    /// Binary data, of which only the length is kept
+    Raw(usize),
+}
+
+impl Property {
+    fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(v) => Some(*v),
+            Self::Float(v) => Some(*v as i64),
+            _ => None,
+        }
+    }
+
+    fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Int(v) => Some(*v as f64),
+            Self::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    name: String,
+    properties: Vec<Property>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn child(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Node> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    fn id(&self) -> Option<i64> {
+        self.properties.first().and_then(Property::as_int)
+    }
+
+    /// Object name without the `\0\x01Class` suffix binary files add
+    fn object_name(&self) -> String {
+        let name = self
+            .properties
+            .get(1)
+            .and_then(Property::as_str)
+            .unwrap_or("");
+        name.split("\0\u{1}").next().unwrap_or("").to_string()
+    }
+
+    /// Values of a `Properties70` entry, after its name, type, label and flags
+    fn property70(&self, name: &str) -> Option<&[Property]> {
+        self.child("Properties70")?
+            .children_named("P")
+            .find(|p| p.properties.first().and_then(Property::as_str) == Some(name))
+            .map(|p| p.properties.get(4..).unwrap_or(&[]))
+    }
+
+    fn vector70(&self, name: &str) -> Option<Vector3<f64>> {
+        let values = self.property70(name)?;
+        let v: Vec<f64> = values.iter().filter_map(Property::as_float).collect();
+        (v.len() >= 3).then(|| Vector3::new(v[0], v[1], v[2]))
+    }
+
+    fn scalar70(&self, name: &str) -> Option<f64> {
+        self.property70(name)?.first()?.as_float()
+    }
+}
+
+/// Parse a binary FBX file, splitting polygons with `triangulator`
+pub fn parse_fbx(bytes: &[u8], triangulator: &mut Triangulator) -> Result<FbxScene, String> {
+    let root = read_root(bytes)?;
+    Ok(FbxScene {
+        meshes: scene_meshes(&root, triangulator)?,
+        axes: declared_axes(&root),
+    })
+}
+
+/// Image files referenced by the textures of a binary FBX file, as written
+///
+/// The path relative to the FBX file is preferred over the absolute one
+/// recorded on the artist's machine. Images embedded in the file are left
+/// out.
+pub fn texture_files(bytes: &[u8]) -> Result<Vec<String>, String> {
+    let root = read_root(bytes)?;
+    let Some(objects) = root.child("Objects") else {
+        return Ok(Vec::new());
+    };
+    let filename = |node: &Node, absolute: &str| {
+        [node.child("RelativeFilename"), node.child(absolute)]
+            .into_iter()
+            .flatten()
+            .filter_map(|child| child.properties.first()?.as_str())
+            .find(|name| !name.is_empty())
+            .map(str::to_string)
+    };
+    let embedded: Vec<String> = objects
+        .children_named("Video")
+        .filter(|video| {
+            video
+                .child("Content")
+                .and_then(|content| content.properties.first())
+                .is_some_and(|content| matches!(content, Property::Raw(len) if *len > 0))
+        })
+        .filter_map(|video| filename(video, "Filename"))
+        .collect();
+
+    let mut files: Vec<String> = Vec::new();
+    let referenced = objects
+        .children_named("Texture")
+        .filter_map(|texture| filename(texture, "FileName"))
+        .chain(
+            objects
+                .children_named("Video")
+                .filter_map(|video| filename(video, "Filename")),
+        );
+    for file in referenced {
+        if !embedded.contains(&file) && !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+fn read_root(bytes: &[u8]) -> Result<Node, String> {
+    if !bytes.starts_with(MAGIC) {
+        if bytes.trim_ascii_start().starts_with(b";") {
+            return Err("ASCII FBX files are not supported; re-export as binary".to_string());
+        }
+        return Err("Invalid FBX file: missing binary header".to_string());
+    }
+    let mut reader = Reader {
+        bytes,
+        pos: MAGIC.len() + 2,
+        wide: false,
+    };
+    let version = u32::from_le_bytes(reader.array::<4>()?);
+    reader.wide = version >= WIDE_VERSION;
+
+    let mut root = Node::default();
+    while reader.pos + reader.null_record_len() <= bytes.len() {
+        match reader.node()? {
+            Some(node) => root.children.push(node),
+            None => break,
+        }
+    }
+    Ok(root)
+}
+
+/// Meshes with their model transforms baked in, one per model instance
+fn scene_meshes(root: &Node, triangulator: &mut Triangulator) -> Result<Vec<NamedMesh>, String> {
+    let Some(objects) = root.child("Objects") else {
+        return Ok(Vec::new());
+    };
+    let models: HashMap<i64, &Node> = objects
+        .children_named("Model")
+        .filter_map(|m| m.id().map(|id| (id, m)))
+        .collect();
+
+    // Object-object connections: child id -> parent ids
+    let mut parents: HashMap<i64, Vec<i64>> = HashMap::new();
+    if let Some(connections) = root.child("Connections") {
+        for c in connections.children_named("C") {
+            let p = &c.properties;
+            if p.first().and_then(Property::as_str) != Some("OO") {
+                continue;
+            }
+            if let (Some(child), Some(parent)) = (
+                p.get(1).and_then(Property::as_int),
+                p.get(2).and_then(Property::as_int),
+            ) {
+                parents.entry(child).or_default().push(parent);
+            }
+        }
+    }
+
+    let mut meshes = Vec::new();
+    for geometry in objects.children_named("Geometry") {
+        if geometry.properties.get(2).and_then(Property::as_str) != Some("Mesh") {
+            continue;
+        }
+        let Geometry {
+            positions,
+            indices,
+            polygons,
+        } = geometry_triangles(geometry, triangulator)?;
+        if indices.is_empty() {
+            continue;
+        }
+
+        let owners: Vec<&Node> = geometry
+            .id()
+            .and_then(|id| parents.get(&id))
+            .into_iter()
+            .flatten()
+            .filter_map(|id| models.get(id).copied())
+            .collect();
+        if owners.is_empty() {
+            meshes.push(NamedMesh {
+                name: geometry.object_name(),
+                positions: positions.iter().map(|&c| c as f32).collect(),
+                indices,
+                polygons: Some(polygons),
+            });
+            continue;
+        }
+
+        for model in owners {
+            let matrix = world_matrix(model, &models, &parents) * geometric_matrix(model);
+            meshes.push(NamedMesh {
+                name: model.object_name(),
+                positions: positions
+                    .chunks_exact(3)
+                    .flat_map(|p| {
+                        let w = matrix.transform_point(&Point3::new(p[0], p[1], p[2]));
+                        [w.x as f32, w.y as f32, w.z as f32]
+                    })
+                    .collect(),
+                indices: indices.clone(),
+                polygons: Some(polygons.clone()),
+            });
+        }
+    }
+    Ok(meshes)
+}
+
+/// Triangulated contents of a `Geometry` node, in geometry space
+#[derive(Default)]
+struct Geometry {
+    positions: Vec<f64>,
+    indices: Vec<u32>,
+    polygons: Vec<u32>,
+}
+
+/// Vertices and triangulated polygons of a `Geometry` node
+///
+/// The last corner of each polygon is stored bitwise negated. Polygons with
+/// fewer than three corners have no area and are skipped.
+fn geometry_triangles(
+    geometry: &Node,
+    triangulator: &mut Triangulator,
+) -> Result<Geometry, String> {
+    let positions = match geometry
+        .child("Vertices")
+        .and_then(|v| v.properties.first())
+    {
+        Some(Property::Floats(values)) => values.clone(),
+        _ => return Ok(Geometry::default()),
+    };
+    let corners = match geometry
+        .child("PolygonVertexIndex")
+        .and_then(|v| v.properties.first())
+    {
+        Some(Property::Ints(values)) => values,
+        _ => return Ok(Geometry::default()),
+    };
+
+    let vertex_count = (positions.len() / 3) as i64;
+    let name = geometry.object_name();
+    let flat: Vec<f32> = positions.iter().map(|&c| c as f32).collect();
+    let mut indices = Vec::with_capacity(corners.len());
+    let mut polygons = Vec::new();
+    let mut polygon: Vec<u32> = Vec::new();
+    for &corner in corners {
+        let (index, last) = if corner < 0 {
+            (!corner, true)
+        } else {
+            (corner, false)
+        };
+        if index >= vertex_count {
+            return Err(format!(
+                "FBX geometry '{}' references vertex {} of {}",
+                name, index, vertex_count
+            ));
+        }
+        polygon.push(index as u32);
+        if last {
+            if polygon.len() >= 3 {
+                triangulator.polygon(&name, polygons.len(), &flat, &polygon, &mut indices);
+                polygons.push(polygon.len() as u32);
+            }
+            polygon.clear();
+        }
+    }
+    Ok(Geometry {
+        positions,
+        indices,
+        polygons,
+    })
+}
+
+/// A model's transform to scene space, through its parent models
+fn world_matrix(
+    model: &Node,
+    models: &HashMap<i64, &Node>,
+    parents: &HashMap<i64, Vec<i64>>,
+) -> Matrix4<f64> {
+    let mut matrix = local_matrix(model);
+    let mut current = model;
+    for _ in 0..MAX_DEPTH {
+        let parent = current
+            .id()
+            .and_then(|id| parents.get(&id))
+            .and_then(|ids| ids.iter().find_map(|id| models.get(id)));
+        let Some(&parent) = parent else {
+            break;
+        };
+        matrix = local_matrix(parent) * matrix;
+        current = parent;
+    }
+    matrix
+}
+
+/// `T * PreRotation * R * S`, with XYZ Euler angles in degrees
+fn local_matrix(model: &Node) -> Matrix4<f64> {
+    let translation = model
+        .vector70("Lcl Translation")
+        .unwrap_or_else(Vector3::zeros);
+    let pre_rotation = model.vector70("PreRotation").unwrap_or_else(Vector3::zeros);
+    let rotation = model
+        .vector70("Lcl Rotation")
+        .unwrap_or_else(Vector3::zeros);
+    let scaling = model
+        .vector70("Lcl Scaling")
+        .unwrap_or_else(|| Vector3::repeat(1.0));
+    Matrix4::new_translation(&translation)
+        * euler_xyz(pre_rotation)
+        * euler_xyz(rotation)
+        * Matrix4::new_nonuniform_scaling(&scaling)
+}
+
+/// Offset applied to a model's geometry but not to its children
+fn geometric_matrix(model: &Node) -> Matrix4<f64> {
+    let translation = model
+        .vector70("GeometricTranslation")
+        .unwrap_or_else(Vector3::zeros);
+    let rotation = model
+        .vector70("GeometricRotation")
+        .unwrap_or_else(Vector3::zeros);
+    let scaling = model
+        .vector70("GeometricScaling")
+        .unwrap_or_else(|| Vector3::repeat(1.0));
+    Matrix4::new_translation(&translation)
+        * euler_xyz(rotation)
+        * Matrix4::new_nonuniform_scaling(&scaling)
+}
+
+fn euler_xyz(degrees: Vector3<f64>) -> Matrix4<f64> {
+    let r = degrees.map(f64::to_radians);
+    Rotation3::from_euler_angles(r.x, r.y, r.z).to_homogeneous()
+}
+
+/// Up axis and unit scale from `GlobalSettings`
+///
+/// Files whose up axis is X or points down can't be described by an
+/// [`AxisConversion`] and fall back to the configured default.
+fn declared_axes(root: &Node) -> Option<AxisConversion> {
+    let settings = root.child("GlobalSettings")?;
+    let up_axis = match (
+        settings.scalar70("UpAxis").map(|v| v as i64),
+        settings
+            .scalar70("UpAxisSign")
+            .map(|v| v as i64)
+            .unwrap_or(1),
+    ) {
+        (Some(1), 1) => UpAxis::Y,
+        (Some(2), 1) => UpAxis::Z,
+        (None, _) => return None,
+        (axis, sign) => {
+            log::warn!(
+                "Ignoring unsupported FBX up axis {:?} (sign {})",
+                axis,
+                sign
+            );
+            return None;
+        }
+    };
+    // UnitScaleFactor is centimeters per file unit
+    let unit_scale = settings.scalar70("UnitScaleFactor").unwrap_or(1.0) * 0.01;
+    let conversion = AxisConversion::new(up_axis, unit_scale as f32);
+    conversion.validate().ok().map(|_| conversion)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    wide: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| format!("FBX file is truncated at byte {}", self.pos))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.array::<4>().map(u32::from_le_bytes)
+    }
+
+    /// An offset or count that is 64-bit from FBX 7.5 on
+    fn word(&mut self) -> Result<u64, String> {
+        if self.wide {
+            self.array::<8>().map(u64::from_le_bytes)
+        } else {
+            self.u32().map(u64::from)
+        }
+    }
+
+    fn null_record_len(&self) -> usize {
+        if self.wide {
+            25
+        } else {
+            13
+        }
+    }
+
+    /// Read one node record, or `None` at the null record ending a list
+    fn node(&mut self) -> Result<Option<Node>, String> {
+        let end = self.word()? as usize;
+        let property_count = self.word()?;
+        let _property_bytes = self.word()?;
+        let name_len = self.take(1)?[0] as usize;
+        if end == 0 {
+            return Ok(None);
+        }
+        if end > self.bytes.len() || end < self.pos {
+            return Err(format!("Invalid FBX node end offset {}", end));
+        }
+
+        let name = String::from_utf8_lossy(self.take(name_len)?).into_owned();
+        let properties = (0..property_count)
+            .map(|_| self.property())
+            .collect::<Result<Vec<_>, String>>()?;
+        let mut children = Vec::new();
+        while self.pos < end {
+            match self.node()? {
+                Some(child) => children.push(child),
+                None => break,
+            }
+        }
+        self.pos = end;
+        Ok(Some(Node {
+            name,
+            properties,
+            children,
+        }))
+    }
+
+    fn property(&mut self) -> Result<Property, String> {
+        let code = self.take(1)?[0];
+        Ok(match code {
+            b'C' => Property::Int(self.take(1)?[0] as i64),
+            b'Y' => Property::Int(i16::from_le_bytes(self.array()?) as i64),
+            b'I' => Property::Int(i32::from_le_bytes(self.array()?) as i64),
+            b'L' => Property::Int(i64::from_le_bytes(self.array()?)),
+            b'F' => Property::Float(f32::from_le_bytes(self.array()?) as f64),
+            b'D' => Property::Float(f64::from_le_bytes(self.array()?)),
+            b'S' => {
+                let len = self.u32()? as usize;
+                Property::Str(String::from_utf8_lossy(self.take(len)?).into_owned())
+            }
+            b'R' => {
+                let len = self.u32()? as usize;
+                self.take(len)?;
+                Property::Raw(len)
+            }
+            b'f' => Property::Floats(
+                self.array_property(4)?
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+                    .collect(),
+            ),
+            b'd' => Property::Floats(
+                self.array_property(8)?
+                    .chunks_exact(8)
+                    .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+                    .collect(),
+            ),
+            b'i' => Property::Ints(
+                self.array_property(4)?
+                    .chunks_exact(4)
+                    .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as i64)
+                    .collect(),
+            ),
+            b'l' => Property::Ints(
+                self.array_property(8)?
+                    .chunks_exact(8)
+                    .map(|b| i64::from_le_bytes(b.try_into().unwrap()))
+                    .collect(),
+            ),
+            b'b' => Property::Ints(self.array_property(1)?.iter().map(|&b| b as i64).collect()),
+            other => {
+                return Err(format!(
+                    "Unknown FBX property type '{}' at byte {}",
+                    other as char,
+                    self.pos - 1
+                ))
+            }
+        })
+    }
+
+    /// Raw contents of an array property, inflated if zlib-compressed
+    fn array_property(&mut self, element_size: usize) -> Result<Vec<u8>, String> {
+        let count = self.u32()? as usize;
+        let encoding = self.u32()?;
+        let stored_len = self.u32()? as usize;
+        let stored = self.take(stored_len)?;
+        let expected = count * element_size;
+
+        let data = match encoding {
+            0 => stored.to_vec(),
+            1 => {
+                let mut data = Vec::with_capacity(expected);
+                ZlibDecoder::new(stored)
+                    .take(expected as u64)
+                    .read_to_end(&mut data)
+                    .map_err(|e| format!("Failed to inflate FBX array: {}", e))?;
+                data
+            }
+            other => return Err(format!("Unknown FBX array encoding {}", other)),
+        };
+        if data.len() != expected {
+            return Err(format!(
+                "FBX array holds {} bytes, expected {}",
+                data.len(),
+                expected
+            ));
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    struct N(&'static str, Vec<Vec<u8>>, Vec<N>);
+
+    fn s(value: &str) -> Vec<u8> {
+        let mut out = vec![b'S'];
+        out.extend((value.len() as u32).to_le_bytes());
+        out.extend(value.as_bytes());
+        out
+    }
+
+    fn l(value: i64) -> Vec<u8> {
+        let mut out = vec![b'L'];
+        out.extend(value.to_le_bytes());
+        out
+    }
+
+    fn d(value: f64) -> Vec<u8> {
+        let mut out = vec![b'D'];
+        out.extend(value.to_le_bytes());
+        out
+    }
+
+    fn compressed_doubles(values: &[f64]) -> Vec<u8> {
+        let raw: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw).unwrap();
+        let packed = encoder.finish().unwrap();
+        let mut out = vec![b'd'];
+        out.extend((values.len() as u32).to_le_bytes());
+        out.extend(1u32.to_le_bytes());
+        out.extend((packed.len() as u32).to_le_bytes());
+        out.extend(packed);
+        out
+    }
+
+    fn ints(values: &[i32]) -> Vec<u8> {
+        let mut out = vec![b'i'];
+        out.extend((values.len() as u32).to_le_bytes());
+        out.extend(0u32.to_le_bytes());
+        out.extend(((values.len() * 4) as u32).to_le_bytes());
+        out.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+        out
+    }
+
+    fn p(name: &str, values: Vec<Vec<u8>>) -> N {
+        let mut props = vec![s(name), s(""), s(""), s("")];
+        props.extend(values);
+        N("P", props, vec![])
+    }
+
+    fn write(node: &N, out: &mut Vec<u8>) {
+        let start = out.len();
+        let props: Vec<u8> = node.1.concat();
+        out.extend([0u8; 4]);
+        out.extend((node.1.len() as u32).to_le_bytes());
+        out.extend((props.len() as u32).to_le_bytes());
+        out.push(node.0.len() as u8);
+        out.extend(node.0.as_bytes());
+        out.extend(props);
+        if !node.2.is_empty() {
+            for child in &node.2 {
+                write(child, out);
+            }
+            out.extend([0u8; 13]);
+        }
+        let end = (out.len() as u32).to_le_bytes();
+        out[start..start + 4].copy_from_slice(&end);
+    }
+
+    #[test]
+    fn test_parse_fbx_bakes_model_transforms() {
+        let nodes = [
+            N(
+                "GlobalSettings",
+                vec![],
+                vec![N(
+                    "Properties70",
+                    vec![],
+                    vec![
+                        p("UpAxis", vec![vec![b'I', 2, 0, 0, 0]]),
+                        p("UnitScaleFactor", vec![d(1.0)]),
+                    ],
+                )],
+            ),
+            N(
+                "Objects",
+                vec![],
+                vec![
+                    N(
+                        "Geometry",
+                        vec![l(10), s("Quad\0\u{1}Geometry"), s("Mesh")],
+                        vec![
+                            N(
+                                "Vertices",
+                                vec![compressed_doubles(&[
+                                    0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0,
+                                ])],
+                                vec![],
+                            ),
+                            N("PolygonVertexIndex", vec![ints(&[0, 1, 2, !3])], vec![]),
+                        ],
+                    ),
+                    N(
+                        "Model",
+                        vec![l(20), s("Child\0\u{1}Model"), s("Mesh")],
+                        vec![N(
+                            "Properties70",
+                            vec![],
+                            vec![p("Lcl Scaling", vec![d(2.0), d(2.0), d(2.0)])],
+                        )],
+                    ),
+                    N(
+                        "Model",
+                        vec![l(30), s("Parent\0\u{1}Model"), s("Null")],
+                        vec![N(
+                            "Properties70",
+                            vec![],
+                            vec![p("Lcl Translation", vec![d(0.0), d(0.0), d(5.0)])],
+                        )],
+                    ),
+                ],
+            ),
+            N(
+                "Connections",
+                vec![],
+                vec![
+                    N("C", vec![s("OO"), l(10), l(20)], vec![]),
+                    N("C", vec![s("OO"), l(20), l(30)], vec![]),
+                    N("C", vec![s("OO"), l(30), l(0)], vec![]),
+                ],
+            ),
+        ];
+
+        let mut file = MAGIC.to_vec();
+        file.extend([0x1A, 0x00]);
+        file.extend(7400u32.to_le_bytes());
+        for node in &nodes {
+            write(node, &mut file);
+        }
+        file.extend([0u8; 13]);
+
+        let mut triangulator = Triangulator::new(Default::default());
+        let scene = parse_fbx(&file, &mut triangulator).unwrap();
+        assert_eq!(scene.axes, Some(AxisConversion::new(UpAxis::Z, 0.01)));
+        assert_eq!(scene.meshes.len(), 1);
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.name, "Child");
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.polygons, Some(vec![4]));
+        // Scaled by the child, then moved by the parent
+        assert_eq!(&mesh.positions[6..9], &[2.0, 2.0, 5.0]);
+
+        assert!(parse_fbx(b"; FBX 7.4.0 project file", &mut triangulator).is_err());
+    }
+}
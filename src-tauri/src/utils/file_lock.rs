@@ -0,0 +1,287 @@
+//! Coordination between app instances that share files
+//!
+//! Two instances may run against the same storage, such as two workstations
+//! on a shared drive, and may share a data folder. A file being written gets
+//! a `.sweedle-lock` file beside it naming the instance writing it. The lock
+//! file is created atomically, so only one instance holds it; the others
+//! wait a few seconds and then fail with an error naming the holder. Data
+//! files are updated by re-reading them under the lock and applying the
+//! change on top, so what another instance saved is merged, not overwritten.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const LOCK_SUFFIX: &str = ".sweedle-lock";
+/// How long to wait for another instance to finish with a file
+const LOCK_WAIT: Duration = Duration::from_secs(5);
+const LOCK_POLL: Duration = Duration::from_millis(50);
+/// Locks older than this were left behind by an instance that died
+const STALE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// The instance holding a lock
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub host: String,
+    pub pid: u32,
+    pub acquired_ms: u64,
+}
+
+impl LockHolder {
+    fn current() -> Self {
+        Self {
+            host: host_name().to_string(),
+            pid: std::process::id(),
+            acquired_ms: now_ms(),
+        }
+    }
+
+    /// Whether the instance that took the lock is gone
+    fn is_stale(&self) -> bool {
+        now_ms().saturating_sub(self.acquired_ms) > STALE_AFTER.as_millis() as u64
+            || (self.host == host_name() && process_running(self.pid) == Some(false))
+    }
+}
+
+/// A lock on a file, released when dropped
+#[derive(Debug)]
+pub struct FileLock {
+    lock_path: PathBuf,
+}
+
+impl FileLock {
+    /// Lock `path`, waiting a few seconds if another instance holds it
+    pub fn acquire(path: &Path) -> Result<Self, String> {
+        Self::acquire_within(path, LOCK_WAIT)
+    }
+
+    fn acquire_within(path: &Path, wait: Duration) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let lock_path = lock_path(path);
+        let deadline = Instant::now() + wait;
+        loop {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&lock_path)
+            {
+                Ok(mut file) => {
+                    let lock = Self { lock_path };
+                    let holder = serde_json::to_vec(&LockHolder::current())
+                        .map_err(|e| format!("Failed to serialize lock: {}", e))?;
+                    file.write_all(&holder)
+                        .map_err(|e| format!("Failed to lock {}: {}", path.display(), e))?;
+                    return Ok(lock);
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(format!("Failed to lock {}: {}", path.display(), e)),
+            }
+
+            let holder = read_holder(&lock_path);
+            if is_stale(&lock_path, holder.as_ref()) && fs::remove_file(&lock_path).is_ok() {
+                log::warn!("Removed a stale lock on {}", path.display());
+                continue;
+            }
+            if Instant::now() >= deadline {
+                let by = match holder {
+                    Some(holder) => format!(
+                        "another Sweedle instance ({}, process {})",
+                        holder.host, holder.pid
+                    ),
+                    None => "another Sweedle instance".to_string(),
+                };
+                return Err(format!(
+                    "{} is being written by {}; try again when it's done",
+                    path.display(),
+                    by
+                ));
+            }
+            thread::sleep(LOCK_POLL);
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.lock_path) {
+            log::warn!("Failed to remove {}: {}", self.lock_path.display(), e);
+        }
+    }
+}
+
+/// Lock a data file and bring `state` up to date with it, so a change
+/// applied to `state` next keeps what other instances saved
+///
+/// `state` is left as it is when the file is missing or invalid.
+pub fn lock_and_reload<T: DeserializeOwned>(
+    path: &Path,
+    state: &mut T,
+    what: &str,
+) -> Result<FileLock, String> {
+    let lock = FileLock::acquire(path)?;
+    if let Ok(contents) = fs::read_to_string(path) {
+        match serde_json::from_str(&contents) {
+            Ok(saved) => *state = saved,
+            Err(e) => log::warn!("Ignoring invalid {} {}: {}", what, path.display(), e),
+        }
+    }
+    Ok(lock)
+}
+
+/// Write a data file via a temp file, so no reader sees it half written
+pub fn write_json<T: Serialize>(path: &Path, value: &T, what: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let contents =
+        serde_json::to_string(value).map_err(|e| format!("Failed to serialize {}: {}", what, e))?;
+    let tmp = temp_path(path);
+    fs::write(&tmp, contents)
+        .and_then(|_| fs::rename(&tmp, path))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Temp file to write `path` through, unique to this process so instances
+/// writing the same file don't write into each other's temp file
+pub fn temp_path(path: &Path) -> PathBuf {
+    temp_path_of(path, std::process::id())
+}
+
+/// Temp file process `pid` writes `path` through
+pub fn temp_path_of(path: &Path, pid: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.tmp", pid));
+    path.with_file_name(name)
+}
+
+/// Fail if another program has `path` open in a way that keeps it from
+/// being replaced or deleted, as Windows does for a file open in a DCC tool
+#[cfg(windows)]
+pub fn ensure_not_in_use(path: &Path) -> Result<(), String> {
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    match OpenOptions::new().append(true).open(path) {
+        Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => Err(format!(
+            "{} is open in another program; close it and try again",
+            path.display()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Fail if another program has `path` open in a way that keeps it from
+/// being replaced or deleted; other systems don't keep open files from that
+#[cfg(not(windows))]
+pub fn ensure_not_in_use(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(LOCK_SUFFIX);
+    path.with_file_name(name)
+}
+
+fn read_holder(lock_path: &Path) -> Option<LockHolder> {
+    let contents = fs::read(lock_path).ok()?;
+    serde_json::from_slice(&contents).ok()
+}
+
+/// A lock without a readable holder is still being written, unless it has
+/// been that way too long to be
+fn is_stale(lock_path: &Path, holder: Option<&LockHolder>) -> bool {
+    match holder {
+        Some(holder) => holder.is_stale(),
+        None => fs::metadata(lock_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > STALE_AFTER),
+    }
+}
+
+/// Whether process `pid` is running on this machine, where that can be told
+fn process_running(pid: u32) -> Option<bool> {
+    if cfg!(target_os = "linux") {
+        Some(Path::new("/proc").join(pid.to_string()).exists())
+    } else {
+        None
+    }
+}
+
+fn host_name() -> &'static str {
+    static HOST: OnceLock<String> = OnceLock::new();
+    HOST.get_or_init(|| {
+        std::env::var("COMPUTERNAME")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .ok()
+            .or_else(|| fs::read_to_string("/etc/hostname").ok())
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "unknown host".to_string())
+    })
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_locks_exclude_other_instances_and_reload_merges() {
+        let dir = std::env::temp_dir().join(format!("sweedle-lock-{}", std::process::id()));
+        let path = dir.join("curation.json");
+
+        let lock = FileLock::acquire(&path).unwrap();
+        let error = FileLock::acquire_within(&path, Duration::ZERO).unwrap_err();
+        assert!(error.contains("being written by another Sweedle instance"));
+        drop(lock);
+        assert!(!lock_path(&path).exists());
+
+        // A lock left by an instance that is long gone doesn't block
+        let abandoned = LockHolder {
+            host: "workstation-2".to_string(),
+            pid: 1,
+            acquired_ms: 0,
+        };
+        fs::write(lock_path(&path), serde_json::to_vec(&abandoned).unwrap()).unwrap();
+        drop(FileLock::acquire_within(&path, Duration::ZERO).unwrap());
+        let live = LockHolder {
+            acquired_ms: now_ms(),
+            ..abandoned
+        };
+        fs::write(lock_path(&path), serde_json::to_vec(&live).unwrap()).unwrap();
+        let error = FileLock::acquire_within(&path, Duration::ZERO).unwrap_err();
+        assert!(error.contains("workstation-2, process 1"));
+        fs::remove_file(lock_path(&path)).unwrap();
+
+        // Each instance applies its change on top of what the other saved
+        let mut first: BTreeMap<String, u32> = BTreeMap::new();
+        let mut second = first.clone();
+        for (state, id) in [(&mut first, "rock"), (&mut second, "tree")] {
+            let _lock = lock_and_reload(&path, state, "curation").unwrap();
+            state.insert(id.to_string(), 1);
+            write_json(&path, state, "curation").unwrap();
+        }
+        assert_eq!(second.len(), 2);
+        drop(lock_and_reload(&path, &mut first, "curation").unwrap());
+        assert_eq!(first, second);
+        assert!(!temp_path(&path).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,323 @@
+//! Flattening a glTF scene into world-space meshes that keep their materials
+//!
+//! Every primitive's node transform is baked into its positions and
+//! normals, and primitives sharing a material are merged into one. The
+//! document keeps its materials, textures, samplers and images, which
+//! [`GltfDocument::open`] has embedded; its nodes, meshes, skins and
+//! animations are replaced by one node per merged mesh. Extensions are cut
+//! down to those the materials and textures still use.
+
+use crate::utils::gltf_document::GltfDocument;
+use crate::utils::gltf_geometry::{load_gltf, read_primitives, PrimitiveGeometry};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Vertex attributes carried over besides positions, with their glTF type
+const ATTRIBUTES: [(&str, &str); 4] = [
+    ("NORMAL", "VEC3"),
+    ("TEXCOORD_0", "VEC2"),
+    ("TEXCOORD_1", "VEC2"),
+    ("COLOR_0", "VEC4"),
+];
+
+/// Triangles of one material, merged across the scene in world space
+#[derive(Debug, Clone, Default)]
+struct FlatMesh {
+    name: String,
+    material: Option<usize>,
+    positions: Vec<f32>,
+    indices: Vec<u32>,
+    /// Values of each of [`ATTRIBUTES`], kept only if every merged
+    /// primitive had it
+    attributes: [Option<Vec<f32>>; 4],
+}
+
+impl FlatMesh {
+    fn append(&mut self, primitive: &PrimitiveGeometry) {
+        let first = self.positions.is_empty();
+        let attributes = [
+            primitive.world_normals(),
+            primitive.uvs.clone(),
+            primitive.lightmap_uvs.clone(),
+            primitive.colors.clone(),
+        ];
+        for (kept, values) in self.attributes.iter_mut().zip(attributes) {
+            *kept = match (kept.take(), values) {
+                (Some(mut kept), Some(values)) => {
+                    kept.extend(values);
+                    Some(kept)
+                }
+                (None, Some(values)) if first => Some(values),
+                _ => None,
+            };
+        }
+
+        let base = (self.positions.len() / 3) as u32;
+        self.positions.extend(primitive.world_positions());
+        let mirrored = primitive.is_mirrored();
+        for triangle in primitive.indices.chunks_exact(3) {
+            let triangle = if mirrored {
+                [triangle[0], triangle[2], triangle[1]]
+            } else {
+                [triangle[0], triangle[1], triangle[2]]
+            };
+            self.indices.extend(triangle.map(|i| i + base));
+        }
+    }
+
+    fn vertex_count(&self) -> usize {
+        self.positions.len() / 3
+    }
+}
+
+/// A flattened document and what it holds
+pub struct Flattened {
+    pub document: GltfDocument,
+    pub mesh_count: usize,
+    pub vertex_count: usize,
+    pub face_count: usize,
+}
+
+/// Flatten the glTF file at `path`
+///
+/// With `merge_by_material`, every material gets a mesh and node of its
+/// own; otherwise there is one mesh called `name`, with a primitive per
+/// material.
+pub fn flatten(path: &Path, merge_by_material: bool, name: &str) -> Result<Flattened, String> {
+    let mut document = GltfDocument::open(path)?;
+    let loaded = load_gltf(path)?;
+    let mut by_material: BTreeMap<Option<usize>, FlatMesh> = BTreeMap::new();
+    for primitive in read_primitives(&loaded) {
+        by_material
+            .entry(primitive.material_index)
+            .or_insert_with(|| FlatMesh {
+                name: match primitive.material_index {
+                    Some(index) => document.json["materials"][index]["name"]
+                        .as_str()
+                        .map_or_else(|| format!("material {}", index), str::to_string),
+                    None => "default".to_string(),
+                },
+                material: primitive.material_index,
+                ..Default::default()
+            })
+            .append(&primitive);
+    }
+    let meshes: Vec<FlatMesh> = by_material
+        .into_values()
+        .filter(|mesh| !mesh.indices.is_empty())
+        .collect();
+    if meshes.is_empty() {
+        return Err(format!("No triangle meshes found in {}", path.display()));
+    }
+
+    let mut primitives = Vec::new();
+    for mesh in &meshes {
+        let mut attributes = serde_json::Map::new();
+        attributes.insert(
+            "POSITION".to_string(),
+            json!(document.push_floats(&mesh.positions, "VEC3", true)),
+        );
+        for ((semantic, kind), values) in ATTRIBUTES.iter().zip(&mesh.attributes) {
+            if let Some(values) = values {
+                let accessor = document.push_floats(values, kind, false);
+                attributes.insert(semantic.to_string(), json!(accessor));
+            }
+        }
+        let mut primitive = json!({
+            "attributes": attributes,
+            "indices": document.push_indices(&mesh.indices),
+        });
+        if let Some(material) = mesh.material {
+            primitive["material"] = json!(material);
+        }
+        primitives.push((mesh.name.clone(), primitive));
+    }
+
+    let gltf_meshes: Vec<Value> = if merge_by_material {
+        primitives
+            .into_iter()
+            .map(|(name, primitive)| json!({ "name": name, "primitives": [primitive] }))
+            .collect()
+    } else {
+        let primitives: Vec<Value> = primitives.into_iter().map(|(_, p)| p).collect();
+        vec![json!({ "name": name, "primitives": primitives })]
+    };
+    let nodes: Vec<Value> = gltf_meshes
+        .iter()
+        .enumerate()
+        .map(|(i, mesh)| json!({ "name": mesh["name"], "mesh": i }))
+        .collect();
+    let mesh_count = gltf_meshes.len();
+
+    let json = &mut document.json;
+    json["scenes"] = json!([{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }]);
+    json["scene"] = json!(0);
+    json["nodes"] = Value::Array(nodes);
+    json["meshes"] = Value::Array(gltf_meshes);
+    if let Some(object) = json.as_object_mut() {
+        for key in ["skins", "animations", "cameras"] {
+            object.remove(key);
+        }
+    }
+    keep_material_extensions(json);
+    document.compact();
+
+    Ok(Flattened {
+        document,
+        mesh_count,
+        vertex_count: meshes.iter().map(FlatMesh::vertex_count).sum(),
+        face_count: meshes.iter().map(|m| m.indices.len() / 3).sum(),
+    })
+}
+
+/// Limit `extensionsUsed` and `extensionsRequired` to extensions found on
+/// materials, textures, images and samplers
+fn keep_material_extensions(json: &mut Value) {
+    let mut used = BTreeSet::new();
+    for key in ["materials", "textures", "images", "samplers"] {
+        collect_extensions(&json[key], &mut used);
+    }
+    for key in ["extensionsUsed", "extensionsRequired"] {
+        let kept: Vec<Value> = json[key]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|name| name.as_str().is_some_and(|name| used.contains(name)))
+            .cloned()
+            .collect();
+        match json.as_object_mut() {
+            Some(object) if kept.is_empty() => {
+                object.remove(key);
+            }
+            _ => json[key] = Value::Array(kept),
+        }
+    }
+}
+
+/// Names of every extension used anywhere under `value`
+fn collect_extensions(value: &Value, names: &mut BTreeSet<String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, child) in object {
+                if key == "extensions" {
+                    if let Some(extensions) = child.as_object() {
+                        names.extend(extensions.keys().cloned());
+                    }
+                }
+                collect_extensions(child, names);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_extensions(item, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::glb_writer::container;
+
+    /// Two materials over three nodes, one of them moved and mirrored
+    fn write_scene(path: &Path) {
+        let floats = |values: &[f32]| values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let positions: Vec<u8> = floats(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        let normals: Vec<u8> = floats(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+        let uvs: Vec<u8> = floats(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+        let indices: Vec<u8> = [0u32, 1, 2].iter().flat_map(|i| i.to_le_bytes()).collect();
+        let bin = [positions, normals, uvs, indices].concat();
+        let json = json!({
+            "asset": { "version": "2.0" },
+            "extensionsUsed": ["KHR_materials_unlit", "KHR_lights_punctual"],
+            "buffers": [{ "byteLength": bin.len() }],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 72, "byteLength": 24 },
+                { "buffer": 0, "byteOffset": 96, "byteLength": 12 },
+            ],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                  "min": [0, 0, 0], "max": [1, 1, 0] },
+                { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" },
+                { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2" },
+                { "bufferView": 3, "componentType": 5125, "count": 3, "type": "SCALAR" },
+            ],
+            "materials": [
+                { "name": "stone", "extensions": { "KHR_materials_unlit": {} } },
+                { "name": "moss" },
+            ],
+            "meshes": [
+                { "primitives": [{ "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+                                   "indices": 3, "material": 0 }] },
+                { "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 3,
+                                   "material": 1 }] },
+            ],
+            "nodes": [
+                { "mesh": 0 },
+                { "mesh": 0, "translation": [5, 0, 0], "scale": [-1, 1, 1] },
+                { "mesh": 1, "children": [] },
+            ],
+            "scenes": [{ "nodes": [0, 1, 2] }],
+        });
+        std::fs::write(path, container(&json, &bin).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_flatten_keeps_materials_and_attributes() {
+        let dir = std::env::temp_dir().join(format!("sweedle-flatten-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("scene.glb");
+        write_scene(&path);
+
+        let flat = flatten(&path, true, "scene").unwrap();
+        assert_eq!(
+            (flat.mesh_count, flat.vertex_count, flat.face_count),
+            (2, 9, 3)
+        );
+        let document = &flat.document;
+        assert_eq!(document.count("materials"), 2);
+        assert_eq!(
+            document.json["extensionsUsed"],
+            json!(["KHR_materials_unlit"])
+        );
+        let stone = &document.json["meshes"][0];
+        assert_eq!(stone["name"], json!("stone"));
+        let primitive = &stone["primitives"][0];
+        assert_eq!(primitive["material"], json!(0));
+        let attributes = primitive["attributes"].as_object().unwrap();
+        assert!(attributes.contains_key("NORMAL") && attributes.contains_key("TEXCOORD_0"));
+
+        let read = |semantic: &str| {
+            let accessor = primitive["attributes"][semantic].as_u64().unwrap() as usize;
+            document.read_floats(accessor).unwrap().values
+        };
+        // The mirrored copy sits at x = 5 facing the other way, wound the
+        // other way round so it still faces outwards
+        let positions = read("POSITION");
+        assert_eq!(&positions[9..12], &[5.0, 0.0, 0.0]);
+        assert_eq!(&positions[12..15], &[4.0, 0.0, 0.0]);
+        assert_eq!(&read("NORMAL")[9..12], &[0.0, 0.0, 1.0]);
+        let indices = primitive["indices"].as_u64().unwrap() as usize;
+        assert_eq!(
+            document.read_ints(indices).unwrap().values,
+            vec![0, 1, 2, 3, 5, 4]
+        );
+        let moss = &document.json["meshes"][1]["primitives"][0];
+        assert_eq!(moss["material"], json!(1));
+        assert!(moss["attributes"].get("NORMAL").is_none());
+
+        let single = flatten(&path, false, "scene").unwrap();
+        assert_eq!(single.mesh_count, 1);
+        let mesh = &single.document.json["meshes"][0];
+        assert_eq!(mesh["name"], json!("scene"));
+        assert_eq!(mesh["primitives"].as_array().unwrap().len(), 2);
+        assert!(single.document.to_glb().is_ok());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
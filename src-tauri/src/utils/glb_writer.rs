@@ -0,0 +1,438 @@
+//! Minimal GLB output for converted geometry
+//!
+//! Each mesh becomes one triangle primitive with positions and 32-bit
+//! indices under its own node. Nodes carry no transform: imports bake them
+//! into the vertices, so the file is exactly what the importer saw.
+//! A single textured mesh can also be written with its PNG base color
+//! texture embedded, for handing to engines as one file.
+//! Converted glTF files can keep the source's extension data and extras,
+//! and repeated meshes can be written once as GPU instances.
+
+use crate::utils::gltf_extensions::Passthrough;
+use crate::utils::instancing::{Instancing, INSTANCING_EXTENSION};
+use crate::utils::mesh_files::NamedMesh;
+use crate::utils::mesh_store::MeshData;
+use crate::utils::simd;
+use serde_json::{json, Value};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Encode meshes as a binary glTF file
+pub fn write_glb(meshes: &[NamedMesh]) -> Result<Vec<u8>, String> {
+    let (document, bin) = geometry_document(meshes.iter())?;
+    container(&document, &bin)
+}
+
+/// `write_glb` with the extension data and extras of the source file;
+/// also returns the extensions written
+pub fn write_glb_preserving(
+    meshes: &[NamedMesh],
+    passthrough: &Passthrough,
+) -> Result<(Vec<u8>, Vec<String>), String> {
+    let (mut document, bin) = geometry_document(meshes.iter())?;
+    let extensions = passthrough.apply(&mut document);
+    Ok((container(&document, &bin)?, extensions))
+}
+
+/// `write_glb_preserving` with each instanced mesh written once under a
+/// node listing its placements in `EXT_mesh_gpu_instancing`
+///
+/// The extension is marked required, since a viewer without it would show
+/// a single copy.
+pub fn write_instanced_glb(
+    instancing: &Instancing,
+    passthrough: &Passthrough,
+) -> Result<(Vec<u8>, Vec<String>), String> {
+    let baked = instancing.baked.iter().filter(|m| !m.indices.is_empty());
+    let instanced: Vec<_> = instancing
+        .instanced
+        .iter()
+        .filter(|m| !m.mesh.indices.is_empty() && !m.instances.is_empty())
+        .collect();
+    let first_instanced = baked.clone().count();
+    let (mut document, mut bin) =
+        geometry_document(baked.chain(instanced.iter().map(|m| &m.mesh)))?;
+
+    for (k, mesh) in instanced.iter().enumerate() {
+        let mut attributes = serde_json::Map::new();
+        let columns: [(&str, &str, Vec<f32>); 3] = [
+            (
+                "TRANSLATION",
+                "VEC3",
+                mesh.instances.iter().flat_map(|t| t.translation).collect(),
+            ),
+            (
+                "ROTATION",
+                "VEC4",
+                mesh.instances.iter().flat_map(|t| t.rotation).collect(),
+            ),
+            (
+                "SCALE",
+                "VEC3",
+                mesh.instances.iter().flat_map(|t| t.scale).collect(),
+            ),
+        ];
+        for (semantic, kind, values) in columns {
+            let view = document["bufferViews"].as_array().map_or(0, Vec::len);
+            let accessor = document["accessors"].as_array().map_or(0, Vec::len);
+            if let Some(views) = document["bufferViews"].as_array_mut() {
+                views.push(json!({
+                    "buffer": 0,
+                    "byteOffset": bin.len(),
+                    "byteLength": values.len() * 4,
+                }));
+            }
+            if let Some(accessors) = document["accessors"].as_array_mut() {
+                accessors.push(json!({
+                    "bufferView": view,
+                    "componentType": FLOAT,
+                    "count": mesh.instances.len(),
+                    "type": kind,
+                }));
+            }
+            bin.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+            attributes.insert(semantic.to_string(), json!(accessor));
+        }
+        document["nodes"][first_instanced + k]["extensions"] =
+            json!({ INSTANCING_EXTENSION: { "attributes": attributes } });
+    }
+    document["buffers"][0]["byteLength"] = json!(bin.len());
+
+    let mut extensions = passthrough.apply(&mut document);
+    if !instanced.is_empty() {
+        for key in ["extensionsUsed", "extensionsRequired"] {
+            let mut names: Vec<Value> = document[key].as_array().cloned().unwrap_or_default();
+            names.push(json!(INSTANCING_EXTENSION));
+            document[key] = Value::Array(names);
+        }
+        extensions.push(INSTANCING_EXTENSION.to_string());
+    }
+    Ok((container(&document, &bin)?, extensions))
+}
+
+/// The JSON and binary chunk for `write_glb`
+fn geometry_document<'a>(
+    meshes: impl Iterator<Item = &'a NamedMesh>,
+) -> Result<(Value, Vec<u8>), String> {
+    let mut bin: Vec<u8> = Vec::new();
+    let mut views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut gltf_meshes = Vec::new();
+    let mut nodes = Vec::new();
+
+    for mesh in meshes.filter(|m| !m.indices.is_empty()) {
+        let vertex_count = mesh.positions.len() / 3;
+        if let Some(&i) = mesh.indices.iter().find(|&&i| i as usize >= vertex_count) {
+            return Err(format!(
+                "Mesh '{}' references vertex {} of {}",
+                mesh.name, i, vertex_count
+            ));
+        }
+
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for p in mesh.positions.chunks_exact(3) {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(p[axis]);
+                max[axis] = max[axis].max(p[axis]);
+            }
+        }
+
+        let positions_view = views.len();
+        views.push(json!({
+            "buffer": 0,
+            "byteOffset": bin.len(),
+            "byteLength": vertex_count * 12,
+            "target": ARRAY_BUFFER,
+        }));
+        bin.extend(
+            mesh.positions[..vertex_count * 3]
+                .iter()
+                .flat_map(|v| v.to_le_bytes()),
+        );
+        let indices_view = views.len();
+        views.push(json!({
+            "buffer": 0,
+            "byteOffset": bin.len(),
+            "byteLength": mesh.indices.len() * 4,
+            "target": ELEMENT_ARRAY_BUFFER,
+        }));
+        bin.extend(mesh.indices.iter().flat_map(|i| i.to_le_bytes()));
+
+        accessors.push(json!({
+            "bufferView": positions_view,
+            "componentType": FLOAT,
+            "count": vertex_count,
+            "type": "VEC3",
+            "min": min,
+            "max": max,
+        }));
+        accessors.push(json!({
+            "bufferView": indices_view,
+            "componentType": UNSIGNED_INT,
+            "count": mesh.indices.len(),
+            "type": "SCALAR",
+        }));
+        nodes.push(json!({ "name": mesh.name, "mesh": gltf_meshes.len() }));
+        gltf_meshes.push(json!({
+            "name": mesh.name,
+            "primitives": [{
+                "attributes": { "POSITION": accessors.len() - 2 },
+                "indices": accessors.len() - 1,
+            }],
+        }));
+    }
+
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "Sweedle" },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+        "meshes": gltf_meshes,
+        "accessors": accessors,
+        "bufferViews": views,
+        "buffers": [{ "byteLength": bin.len() }],
+    });
+    Ok((document, bin))
+}
+
+/// Encode one mesh with UVs as a binary glTF file textured with `png`
+///
+/// Normals are written when the mesh has them. The material is a plain
+/// rough, non-metallic surface showing the texture.
+pub fn write_textured_glb(name: &str, mesh: &MeshData, png: &[u8]) -> Result<Vec<u8>, String> {
+    mesh.validate()?;
+    let uvs = mesh
+        .uvs
+        .as_deref()
+        .ok_or("Mesh has no UVs for the texture")?;
+    let vertex_count = mesh.vertex_count();
+    let (min, max) = simd::bounds(&mesh.positions).ok_or("Mesh has no vertices")?;
+
+    let mut bin: Vec<u8> = Vec::new();
+    let mut views = Vec::new();
+    let mut view = |bytes: &[u8], target: Option<u32>| {
+        while !bin.len().is_multiple_of(4) {
+            bin.push(0);
+        }
+        let mut view = json!({ "buffer": 0, "byteOffset": bin.len(), "byteLength": bytes.len() });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        bin.extend_from_slice(bytes);
+        views.push(view);
+        views.len() - 1
+    };
+    let floats =
+        |values: &[f32]| -> Vec<u8> { values.iter().flat_map(|v| v.to_le_bytes()).collect() };
+
+    let mut accessors = vec![
+        json!({
+            "bufferView": view(&floats(&mesh.positions), Some(ARRAY_BUFFER)),
+            "componentType": FLOAT,
+            "count": vertex_count,
+            "type": "VEC3",
+            "min": min,
+            "max": max,
+        }),
+        json!({
+            "bufferView": view(&floats(uvs), Some(ARRAY_BUFFER)),
+            "componentType": FLOAT,
+            "count": vertex_count,
+            "type": "VEC2",
+        }),
+        json!({
+            "bufferView": view(
+                &mesh.indices.iter().flat_map(|i| i.to_le_bytes()).collect::<Vec<_>>(),
+                Some(ELEMENT_ARRAY_BUFFER),
+            ),
+            "componentType": UNSIGNED_INT,
+            "count": mesh.indices.len(),
+            "type": "SCALAR",
+        }),
+    ];
+    let mut attributes = json!({ "POSITION": 0, "TEXCOORD_0": 1 });
+    if let Some(normals) = &mesh.normals {
+        attributes["NORMAL"] = json!(accessors.len());
+        accessors.push(json!({
+            "bufferView": view(&floats(normals), Some(ARRAY_BUFFER)),
+            "componentType": FLOAT,
+            "count": vertex_count,
+            "type": "VEC3",
+        }));
+    }
+    let image_view = view(png, None);
+
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "Sweedle" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "name": name, "mesh": 0 }],
+        "meshes": [{
+            "name": name,
+            "primitives": [{ "attributes": attributes, "indices": 2, "material": 0 }],
+        }],
+        "materials": [{
+            "name": name,
+            "pbrMetallicRoughness": {
+                "baseColorTexture": { "index": 0 },
+                "metallicFactor": 0.0,
+                "roughnessFactor": 1.0,
+            },
+        }],
+        "textures": [{ "source": 0, "sampler": 0 }],
+        "samplers": [{}],
+        "images": [{ "bufferView": image_view, "mimeType": "image/png" }],
+        "accessors": accessors,
+        "bufferViews": views,
+        "buffers": [{ "byteLength": bin.len() }],
+    });
+    container(&document, &bin)
+}
+
+/// GLB header, JSON chunk and binary chunk around a glTF document
+pub fn container(document: &Value, bin: &[u8]) -> Result<Vec<u8>, String> {
+    let mut json =
+        serde_json::to_vec(document).map_err(|e| format!("Failed to serialize glTF: {}", e))?;
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+
+    // The binary chunk is padded to 4 bytes too; the buffer can be shorter
+    let padding = bin.len().next_multiple_of(4) - bin.len();
+    let total = 12 + 8 + json.len() + 8 + bin.len() + padding;
+    let total = u32::try_from(total).map_err(|_| "GLB would exceed 4 GiB".to_string())?;
+    let mut glb = Vec::with_capacity(total as usize);
+    glb.extend(GLB_MAGIC);
+    glb.extend(2u32.to_le_bytes());
+    glb.extend(total.to_le_bytes());
+    glb.extend((json.len() as u32).to_le_bytes());
+    glb.extend(CHUNK_JSON.to_le_bytes());
+    glb.extend(&json);
+    glb.extend(((bin.len() + padding) as u32).to_le_bytes());
+    glb.extend(CHUNK_BIN.to_le_bytes());
+    glb.extend(bin);
+    glb.resize(total as usize, 0);
+    Ok(glb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_written_glb_reads_back() {
+        let meshes = [NamedMesh {
+            name: "tri".to_string(),
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0],
+            indices: vec![0, 1, 2],
+            polygons: None,
+        }];
+        let glb = write_glb(&meshes).unwrap();
+
+        let (document, buffers, _) = gltf::import_slice(&glb).unwrap();
+        let primitive = document
+            .meshes()
+            .next()
+            .unwrap()
+            .primitives()
+            .next()
+            .unwrap();
+        let reader = primitive.reader(|b| Some(&buffers[b.index()]));
+        let positions: Vec<[f32; 3]> = reader.read_positions().unwrap().collect();
+        assert_eq!(positions[2], [0.0, 2.0, 0.0]);
+        assert_eq!(primitive.bounding_box().max, [1.0, 2.0, 0.0]);
+        let indices: Vec<u32> = reader.read_indices().unwrap().into_u32().collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_textured_glb_reads_back() {
+        let mesh = MeshData {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            uvs: Some(vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0]),
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::from_pixel(3, 3, image::Rgba([200, 10, 10, 255]))
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let glb = write_textured_glb("scan", &mesh, png.get_ref()).unwrap();
+        assert!(glb.len().is_multiple_of(4));
+
+        let (document, buffers, images) = gltf::import_slice(&glb).unwrap();
+        let primitive = document
+            .meshes()
+            .next()
+            .unwrap()
+            .primitives()
+            .next()
+            .unwrap();
+        let texture = primitive
+            .material()
+            .pbr_metallic_roughness()
+            .base_color_texture()
+            .unwrap();
+        assert_eq!(texture.tex_coord(), 0);
+        assert_eq!((images[0].width, images[0].height), (3, 3));
+        assert_eq!(&images[0].pixels[..4], &[200, 10, 10, 255]);
+        let reader = primitive.reader(|b| Some(&buffers[b.index()]));
+        let uvs: Vec<[f32; 2]> = reader.read_tex_coords(0).unwrap().into_f32().collect();
+        assert_eq!(uvs[1], [1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_instanced_glb_lists_placements() {
+        use crate::utils::instancing::{InstancedMesh, Trs};
+
+        let tri = NamedMesh {
+            name: "tree".to_string(),
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0],
+            indices: vec![0, 1, 2],
+            polygons: None,
+        };
+        let trs = |x: f32| Trs {
+            translation: [x, 0.0, 0.0],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: [1.0; 3],
+        };
+        let instancing = Instancing {
+            instanced: vec![InstancedMesh {
+                mesh: tri.clone(),
+                instances: vec![trs(1.0), trs(2.0), trs(3.0)],
+            }],
+            baked: vec![NamedMesh {
+                name: "ground".to_string(),
+                ..tri
+            }],
+        };
+        let (glb, extensions) = write_instanced_glb(&instancing, &Passthrough::default()).unwrap();
+        assert_eq!(extensions, vec![INSTANCING_EXTENSION]);
+
+        let json_length = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+        let document: Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+        assert_eq!(
+            document["extensionsRequired"],
+            json!([INSTANCING_EXTENSION])
+        );
+        assert!(document["nodes"][0].get("extensions").is_none());
+        let attributes = &document["nodes"][1]["extensions"][INSTANCING_EXTENSION]["attributes"];
+        let translations =
+            &document["accessors"][attributes["TRANSLATION"].as_u64().unwrap() as usize];
+        assert_eq!(translations["count"], 3);
+        assert_eq!(translations["type"], "VEC3");
+
+        // The last accessor's data ends at the end of the buffer
+        let scales = &document["accessors"][attributes["SCALE"].as_u64().unwrap() as usize];
+        let view = &document["bufferViews"][scales["bufferView"].as_u64().unwrap() as usize];
+        let end = view["byteOffset"].as_u64().unwrap() + view["byteLength"].as_u64().unwrap();
+        assert_eq!(json!(end), document["buffers"][0]["byteLength"]);
+    }
+}
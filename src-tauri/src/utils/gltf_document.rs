@@ -0,0 +1,623 @@
+//! A glTF document held as JSON and one binary chunk, for edits that
+//! rewrite accessor data
+//!
+//! Opening a file merges all its buffers into one and embeds external
+//! images, so the result is always written as a self-contained GLB. Edits
+//! append new accessors; `compact` then drops the ones nothing refers to any
+//! more and the bytes behind them. Accessors are looked for in mesh
+//! primitives and morph targets, skins, animation samplers and
+//! `EXT_mesh_gpu_instancing`; buffer views wherever a `bufferView` appears.
+
+use crate::utils::analysis_cache::ModelSource;
+use crate::utils::glb_writer::container;
+use crate::utils::gltf_geometry::{external_buffer_path, load_gltf};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+const FLOAT: u64 = 5126;
+const UNSIGNED_BYTE: u64 = 5121;
+const UNSIGNED_SHORT: u64 = 5123;
+const UNSIGNED_INT: u64 = 5125;
+const INSTANCING_EXTENSION: &str = "EXT_mesh_gpu_instancing";
+/// Most elements an accessor without a buffer view may declare; it has no
+/// data to bound its count, only zeros and sparse values
+const MAX_UNVIEWED_ELEMENTS: usize = 1 << 24;
+
+#[derive(Debug, Clone, Default)]
+pub struct GltfDocument {
+    pub json: Value,
+    pub bin: Vec<u8>,
+}
+
+/// Elements of an accessor, `width` numbers each
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessorData<T> {
+    pub values: Vec<T>,
+    pub width: usize,
+}
+
+impl GltfDocument {
+    /// Load a glTF or GLB file with its buffers merged into one
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let source = ModelSource::open(path)?;
+        let mut json: Value = serde_json::from_slice(source.json())
+            .map_err(|e| format!("Failed to parse GLTF: {}", e))?;
+        drop(source);
+        let required = json["extensionsRequired"].as_array().cloned();
+        if required
+            .iter()
+            .flatten()
+            .any(|name| name.as_str() == Some("EXT_meshopt_compression"))
+        {
+            return Err(format!(
+                "{} is compressed with meshopt, which can't be edited",
+                path.display()
+            ));
+        }
+
+        let loaded = load_gltf(path)?;
+        let mut bin = Vec::new();
+        let starts: Vec<usize> = loaded
+            .buffers
+            .iter()
+            .map(|data| append_aligned(&mut bin, &data.0))
+            .collect();
+        for view in items_mut(&mut json, "bufferViews") {
+            let buffer = view["buffer"].as_u64().unwrap_or(0) as usize;
+            let start = *starts
+                .get(buffer)
+                .ok_or_else(|| format!("Buffer {} is missing", buffer))?;
+            let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+            view["buffer"] = json!(0);
+            view["byteOffset"] = json!(start + offset);
+        }
+        if let Some(object) = json.as_object_mut() {
+            object.remove("buffers");
+        }
+
+        let mut document = Self { json, bin };
+        document.embed_images(path.parent().unwrap_or(Path::new("")))?;
+        document.sync_buffer();
+        Ok(document)
+    }
+
+    /// The document as a GLB file
+    pub fn to_glb(&self) -> Result<Vec<u8>, String> {
+        container(&self.json, &self.bin)
+    }
+
+    pub fn count(&self, kind: &str) -> usize {
+        self.json[kind].as_array().map_or(0, Vec::len)
+    }
+
+    /// The encoded file of an image stored in the binary chunk
+    pub fn image_bytes(&self, image: usize) -> Option<&[u8]> {
+        let view = self.json["images"].get(image)?["bufferView"].as_u64()?;
+        self.view(view as usize).ok().map(|(bytes, _)| bytes)
+    }
+
+    /// Store `bytes` as an image's file; `compact` drops the old one
+    pub fn replace_image(&mut self, image: usize, bytes: &[u8]) {
+        let offset = append_aligned(&mut self.bin, bytes);
+        let view = push(
+            &mut self.json["bufferViews"],
+            json!({ "buffer": 0, "byteOffset": offset, "byteLength": bytes.len() }),
+        );
+        self.json["images"][image]["bufferView"] = json!(view);
+        self.sync_buffer();
+    }
+
+    /// An accessor's elements as floats, normalized integers scaled to
+    /// their range
+    pub fn read_floats(&self, accessor: usize) -> Result<AccessorData<f32>, String> {
+        self.read(accessor, |bytes, component_type, normalized| {
+            let (value, range) = match component_type {
+                5120 => (bytes[0] as i8 as f32, 127.0),
+                UNSIGNED_BYTE => (bytes[0] as f32, 255.0),
+                5122 => (i16::from_le_bytes([bytes[0], bytes[1]]) as f32, 32767.0),
+                UNSIGNED_SHORT => (u16::from_le_bytes([bytes[0], bytes[1]]) as f32, 65535.0),
+                5125 => (
+                    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32,
+                    1.0,
+                ),
+                _ => return f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            };
+            if normalized {
+                (value / range).max(-1.0)
+            } else {
+                value
+            }
+        })
+    }
+
+    /// An accessor's elements as unsigned integers, such as indices or
+    /// joints
+    pub fn read_ints(&self, accessor: usize) -> Result<AccessorData<u32>, String> {
+        self.read(accessor, |bytes, component_type, _| match component_type {
+            UNSIGNED_BYTE | 5120 => bytes[0] as u32,
+            UNSIGNED_SHORT | 5122 => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+            FLOAT => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u32,
+            _ => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        })
+    }
+
+    /// Add a float accessor of `kind` (`SCALAR`, `VEC3`, `MAT4`, ...) and
+    /// return its index; `bounds` records the per-component minimum and
+    /// maximum, which positions and animation times require
+    pub fn push_floats(&mut self, values: &[f32], kind: &str, bounds: bool) -> usize {
+        let width = width_of(kind);
+        let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut accessor = json!({
+            "componentType": FLOAT,
+            "count": values.len() / width.max(1),
+            "type": kind,
+        });
+        if bounds && !values.is_empty() {
+            let mut min = vec![f32::MAX; width];
+            let mut max = vec![f32::MIN; width];
+            for element in values.chunks_exact(width) {
+                for (i, &v) in element.iter().enumerate() {
+                    min[i] = min[i].min(v);
+                    max[i] = max[i].max(v);
+                }
+            }
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+        self.push_accessor(accessor, &bytes)
+    }
+
+    /// Add an unsigned byte or short accessor of `kind`, whichever holds the
+    /// largest value, and return its index
+    pub fn push_ints(&mut self, values: &[u32], kind: &str) -> usize {
+        let small = values.iter().all(|&v| v <= u8::MAX as u32);
+        let (component_type, bytes): (u64, Vec<u8>) = if small {
+            (UNSIGNED_BYTE, values.iter().map(|&v| v as u8).collect())
+        } else {
+            (
+                UNSIGNED_SHORT,
+                values
+                    .iter()
+                    .flat_map(|&v| (v.min(u16::MAX as u32) as u16).to_le_bytes())
+                    .collect(),
+            )
+        };
+        let accessor = json!({
+            "componentType": component_type,
+            "count": values.len() / width_of(kind).max(1),
+            "type": kind,
+        });
+        self.push_accessor(accessor, &bytes)
+    }
+
+    /// Add a 32-bit scalar accessor of triangle indices and return its index
+    pub fn push_indices(&mut self, indices: &[u32]) -> usize {
+        let bytes: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let accessor = json!({
+            "componentType": UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        });
+        self.push_accessor(accessor, &bytes)
+    }
+
+    /// Drop accessors nothing refers to, then buffer views, and rebuild the
+    /// binary chunk from what's left
+    pub fn compact(&mut self) {
+        let mut used = BTreeSet::new();
+        visit_accessor_refs(&mut self.json, &mut |index| {
+            used.insert(*index);
+        });
+        let remap = renumber(self.count("accessors"), &used);
+        visit_accessor_refs(&mut self.json, &mut |index| {
+            if let Some(&new) = remap.get(*index).and_then(Option::as_ref) {
+                *index = new;
+            }
+        });
+        retain_indexed(self.json.get_mut("accessors"), &remap);
+
+        let mut used = BTreeSet::new();
+        visit_view_refs(&mut self.json, &mut |index| {
+            used.insert(*index);
+        });
+        let remap = renumber(self.count("bufferViews"), &used);
+        visit_view_refs(&mut self.json, &mut |index| {
+            if let Some(&new) = remap.get(*index).and_then(Option::as_ref) {
+                *index = new;
+            }
+        });
+        retain_indexed(self.json.get_mut("bufferViews"), &remap);
+
+        let mut bin = Vec::new();
+        for view in items_mut(&mut self.json, "bufferViews") {
+            let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+            let length = view["byteLength"].as_u64().unwrap_or(0) as usize;
+            let bytes = self.bin.get(offset..offset + length).unwrap_or_default();
+            view["byteOffset"] = json!(append_aligned(&mut bin, bytes));
+        }
+        self.bin = bin;
+        self.sync_buffer();
+    }
+
+    fn read<T: Copy + Default>(
+        &self,
+        accessor: usize,
+        convert: impl Fn(&[u8], u64, bool) -> T,
+    ) -> Result<AccessorData<T>, String> {
+        let info = self.json["accessors"]
+            .get(accessor)
+            .ok_or_else(|| format!("Accessor {} is missing", accessor))?;
+        let width = width_of(info["type"].as_str().unwrap_or("SCALAR"));
+        let component_type = info["componentType"].as_u64().unwrap_or(FLOAT);
+        let size = component_size(component_type);
+        let count = info["count"].as_u64().unwrap_or(0) as usize;
+        let normalized = info["normalized"].as_bool().unwrap_or(false);
+        let short = || format!("Accessor {} runs past its view", accessor);
+
+        let view = match info["bufferView"].as_u64() {
+            Some(view) => {
+                let (bytes, stride) = self.view(view as usize)?;
+                let stride = stride.unwrap_or(width * size);
+                let offset = info["byteOffset"].as_u64().unwrap_or(0) as usize;
+                span(offset, count, stride, width * size)
+                    .filter(|&end| end <= bytes.len())
+                    .ok_or_else(short)?;
+                Some((bytes, stride, offset))
+            }
+            None if count > MAX_UNVIEWED_ELEMENTS => {
+                return Err(format!(
+                    "Accessor {} declares {} elements without data (limit {})",
+                    accessor, count, MAX_UNVIEWED_ELEMENTS
+                ));
+            }
+            None => None,
+        };
+
+        let len = count
+            .checked_mul(width)
+            .ok_or_else(|| format!("Accessor {} is too large", accessor))?;
+        let mut values = vec![T::default(); len];
+        if let Some((bytes, stride, offset)) = view {
+            for element in 0..count {
+                for component in 0..width {
+                    let at = offset + element * stride + component * size;
+                    let raw = bytes.get(at..at + size).ok_or_else(short)?;
+                    values[element * width + component] = convert(raw, component_type, normalized);
+                }
+            }
+        }
+
+        if let Some(sparse) = info.get("sparse") {
+            let sparse_count = sparse["count"].as_u64().unwrap_or(0) as usize;
+            let (index_bytes, _) =
+                self.view(sparse["indices"]["bufferView"].as_u64().unwrap_or(0) as usize)?;
+            let index_offset = sparse["indices"]["byteOffset"].as_u64().unwrap_or(0) as usize;
+            let index_type = sparse["indices"]["componentType"].as_u64().unwrap_or(5125);
+            let index_size = component_size(index_type);
+            let (value_bytes, _) =
+                self.view(sparse["values"]["bufferView"].as_u64().unwrap_or(0) as usize)?;
+            let value_offset = sparse["values"]["byteOffset"].as_u64().unwrap_or(0) as usize;
+            span(index_offset, sparse_count, index_size, index_size)
+                .filter(|&end| end <= index_bytes.len())
+                .ok_or_else(|| format!("Sparse indices of accessor {} are short", accessor))?;
+            span(value_offset, sparse_count, width * size, width * size)
+                .filter(|&end| end <= value_bytes.len())
+                .ok_or_else(|| format!("Sparse values of accessor {} are short", accessor))?;
+            for i in 0..sparse_count {
+                let at = index_offset + i * index_size;
+                let raw = index_bytes
+                    .get(at..at + index_size)
+                    .ok_or_else(|| format!("Sparse indices of accessor {} are short", accessor))?;
+                let target = match index_size {
+                    1 => raw[0] as usize,
+                    2 => u16::from_le_bytes([raw[0], raw[1]]) as usize,
+                    _ => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as usize,
+                };
+                for component in 0..width {
+                    let at = value_offset + (i * width + component) * size;
+                    let raw = value_bytes.get(at..at + size).ok_or_else(|| {
+                        format!("Sparse values of accessor {} are short", accessor)
+                    })?;
+                    if let Some(value) = values.get_mut(target * width + component) {
+                        *value = convert(raw, component_type, normalized);
+                    }
+                }
+            }
+        }
+        Ok(AccessorData { values, width })
+    }
+
+    /// Bytes of a buffer view and its stride
+    fn view(&self, index: usize) -> Result<(&[u8], Option<usize>), String> {
+        let view = self.json["bufferViews"]
+            .get(index)
+            .ok_or_else(|| format!("Buffer view {} is missing", index))?;
+        let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let length = view["byteLength"].as_u64().unwrap_or(0) as usize;
+        let bytes = offset
+            .checked_add(length)
+            .and_then(|end| self.bin.get(offset..end))
+            .ok_or_else(|| format!("Buffer view {} runs past the buffer", index))?;
+        Ok((bytes, view["byteStride"].as_u64().map(|s| s as usize)))
+    }
+
+    fn push_accessor(&mut self, mut accessor: Value, bytes: &[u8]) -> usize {
+        let offset = append_aligned(&mut self.bin, bytes);
+        let view = push(
+            &mut self.json["bufferViews"],
+            json!({ "buffer": 0, "byteOffset": offset, "byteLength": bytes.len() }),
+        );
+        accessor["bufferView"] = json!(view);
+        self.sync_buffer();
+        push(&mut self.json["accessors"], accessor)
+    }
+
+    /// Move external images into the binary chunk
+    fn embed_images(&mut self, base: &Path) -> Result<(), String> {
+        for i in 0..self.count("images") {
+            let Some(uri) = self.json["images"][i]["uri"]
+                .as_str()
+                .filter(|uri| !uri.starts_with("data:"))
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            let path = external_buffer_path(base, &uri)?;
+            let bytes = fs::read(&path)
+                .map_err(|e| format!("Failed to read image {}: {}", path.display(), e))?;
+            let offset = append_aligned(&mut self.bin, &bytes);
+            let view = push(
+                &mut self.json["bufferViews"],
+                json!({ "buffer": 0, "byteOffset": offset, "byteLength": bytes.len() }),
+            );
+            let image = &mut self.json["images"][i];
+            if image.get("mimeType").is_none() {
+                let lower = uri.to_lowercase();
+                image["mimeType"] = json!(if lower.ends_with(".jpg") || lower.ends_with(".jpeg") {
+                    "image/jpeg"
+                } else if lower.ends_with(".ktx2") {
+                    "image/ktx2"
+                } else if lower.ends_with(".webp") {
+                    "image/webp"
+                } else {
+                    "image/png"
+                });
+            }
+            image["bufferView"] = json!(view);
+            if let Some(object) = image.as_object_mut() {
+                object.remove("uri");
+            }
+        }
+        Ok(())
+    }
+
+    /// Point the single buffer at the binary chunk, or drop it when empty
+    fn sync_buffer(&mut self) {
+        if let Some(object) = self.json.as_object_mut() {
+            if self.bin.is_empty() {
+                object.remove("buffers");
+            } else {
+                object.insert(
+                    "buffers".to_string(),
+                    json!([{ "byteLength": self.bin.len() }]),
+                );
+            }
+        }
+    }
+}
+
+pub fn width_of(kind: &str) -> usize {
+    match kind {
+        "VEC2" => 2,
+        "VEC3" => 3,
+        "VEC4" | "MAT2" => 4,
+        "MAT3" => 9,
+        "MAT4" => 16,
+        _ => 1,
+    }
+}
+
+fn component_size(component_type: u64) -> usize {
+    match component_type {
+        5120 | UNSIGNED_BYTE => 1,
+        5122 | UNSIGNED_SHORT => 2,
+        _ => 4,
+    }
+}
+
+/// Call `f` with every accessor index the document refers to
+fn visit_accessor_refs(json: &mut Value, f: &mut impl FnMut(&mut usize)) {
+    let mut patch = |value: &mut Value| {
+        if let Some(mut index) = value.as_u64().map(|i| i as usize) {
+            f(&mut index);
+            *value = json!(index);
+        }
+    };
+    fn patch_map(map: Option<&mut Value>, patch: &mut dyn FnMut(&mut Value)) {
+        let values = map.and_then(Value::as_object_mut).into_iter().flatten();
+        for (_, value) in values {
+            patch(value);
+        }
+    }
+    for mesh in items_mut(json, "meshes") {
+        for primitive in items_mut(mesh, "primitives") {
+            patch_map(primitive.get_mut("attributes"), &mut patch);
+            for target in items_mut(primitive, "targets") {
+                patch_map(Some(target), &mut patch);
+            }
+            if let Some(indices) = primitive.get_mut("indices") {
+                patch(indices);
+            }
+        }
+    }
+    for skin in items_mut(json, "skins") {
+        if let Some(matrices) = skin.get_mut("inverseBindMatrices") {
+            patch(matrices);
+        }
+    }
+    for animation in items_mut(json, "animations") {
+        for sampler in items_mut(animation, "samplers") {
+            for key in ["input", "output"] {
+                if let Some(accessor) = sampler.get_mut(key) {
+                    patch(accessor);
+                }
+            }
+        }
+    }
+    for node in items_mut(json, "nodes") {
+        let pointer = format!("/extensions/{}/attributes", INSTANCING_EXTENSION);
+        patch_map(node.pointer_mut(&pointer), &mut patch);
+    }
+}
+
+/// Call `f` with every `bufferView` index anywhere in the document
+fn visit_view_refs(value: &mut Value, f: &mut impl FnMut(&mut usize)) {
+    match value {
+        Value::Object(object) => {
+            for (key, child) in object.iter_mut() {
+                match child.as_u64() {
+                    Some(index) if key == "bufferView" => {
+                        let mut index = index as usize;
+                        f(&mut index);
+                        *child = json!(index);
+                    }
+                    _ => visit_view_refs(child, f),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for child in values {
+                visit_view_refs(child, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// New index of each of `count` objects, `None` for those not in `used`
+fn renumber(count: usize, used: &BTreeSet<usize>) -> Vec<Option<usize>> {
+    let mut next = 0;
+    (0..count)
+        .map(|i| {
+            used.contains(&i).then(|| {
+                next += 1;
+                next - 1
+            })
+        })
+        .collect()
+}
+
+fn retain_indexed(values: Option<&mut Value>, remap: &[Option<usize>]) {
+    if let Some(array) = values.and_then(Value::as_array_mut) {
+        let mut i = 0;
+        array.retain(|_| {
+            i += 1;
+            remap.get(i - 1).is_some_and(Option::is_some)
+        });
+    }
+}
+
+/// Elements of the array under `key`, none if it's missing
+pub fn items_mut<'a>(value: &'a mut Value, key: &str) -> impl Iterator<Item = &'a mut Value> {
+    value
+        .get_mut(key)
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+}
+
+/// Append `value` to the array at `array`, creating it, and return its index
+fn push(array: &mut Value, value: Value) -> usize {
+    if !array.is_array() {
+        *array = json!([]);
+    }
+    let values = array.as_array_mut().expect("just made an array");
+    values.push(value);
+    values.len() - 1
+}
+
+/// End of `count` elements of `element` bytes, `stride` apart from
+/// `offset`, or `None` if it overflows
+fn span(offset: usize, count: usize, stride: usize, element: usize) -> Option<usize> {
+    match count.checked_sub(1) {
+        None => Some(offset),
+        Some(last) => last
+            .checked_mul(stride)?
+            .checked_add(element)?
+            .checked_add(offset),
+    }
+}
+
+/// Append `bytes` at a 4-byte boundary and return where they start
+fn append_aligned(bin: &mut Vec<u8>, bytes: &[u8]) -> usize {
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+    let offset = bin.len();
+    bin.extend_from_slice(bytes);
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_drops_unreferenced_accessors() {
+        let mut document = GltfDocument {
+            json: json!({ "asset": { "version": "2.0" } }),
+            bin: Vec::new(),
+        };
+        let stale = document.push_floats(&[9.0; 6], "VEC3", false);
+        let positions = document.push_floats(&[0.0, 0.0, 0.0, 1.0, 2.0, 3.0], "VEC3", true);
+        let joints = document.push_ints(&[0, 1, 2, 300], "VEC4");
+        document.json["meshes"] = json!([{ "primitives": [{
+            "attributes": { "POSITION": positions, "JOINTS_0": joints }
+        }] }]);
+        assert_eq!(
+            document.json["accessors"][positions]["max"],
+            json!([1.0, 2.0, 3.0])
+        );
+
+        document.compact();
+        assert_eq!(stale, 0);
+        assert_eq!(document.count("accessors"), 2);
+        assert_eq!(document.count("bufferViews"), 2);
+        let attributes = &document.json["meshes"][0]["primitives"][0]["attributes"];
+        assert_eq!(attributes["POSITION"], json!(0));
+        let read = document.read_floats(0).unwrap();
+        assert_eq!(read.width, 3);
+        assert_eq!(read.values, vec![0.0, 0.0, 0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(document.read_ints(1).unwrap().values, vec![0, 1, 2, 300]);
+        assert_eq!(document.bin.len(), 24 + 8);
+        assert_eq!(document.json["buffers"][0]["byteLength"], json!(32));
+    }
+
+    #[test]
+    fn test_read_checks_declared_counts_before_allocating() {
+        let mut document = GltfDocument {
+            json: json!({ "asset": { "version": "2.0" } }),
+            bin: Vec::new(),
+        };
+        let positions = document.push_floats(&[0.0; 6], "VEC3", false);
+        assert_eq!(document.read_floats(positions).unwrap().values.len(), 6);
+
+        let accessor = &mut document.json["accessors"][positions];
+        accessor["count"] = json!(3);
+        assert!(document.read_floats(positions).is_err());
+        document.json["accessors"][positions]["count"] = json!(u64::MAX);
+        assert!(document.read_floats(positions).is_err());
+        document.json["accessors"][positions]["count"] = json!(1);
+        document.json["accessors"][positions]["byteOffset"] = json!(u64::MAX - 4);
+        assert!(document.read_floats(positions).is_err());
+
+        document.json["accessors"] = json!([
+            { "componentType": FLOAT, "type": "VEC3", "count": 2 },
+            { "componentType": FLOAT, "type": "VEC3", "count": u32::MAX },
+        ]);
+        assert_eq!(document.read_floats(0).unwrap().values, vec![0.0; 6]);
+        assert!(document.read_floats(1).is_err());
+    }
+}
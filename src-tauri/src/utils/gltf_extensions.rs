@@ -0,0 +1,296 @@
+//! glTF extension reporting and pass-through
+//!
+//! Files list the extensions they use in `extensionsUsed`, and those a
+//! viewer must support to show them correctly in `extensionsRequired`.
+//! Analysis reports both along with whether the app understands each one.
+//!
+//! Exports rebuild the geometry from scratch, so extension data and extras
+//! would otherwise be lost. [`Passthrough`] keeps what can be carried over
+//! safely: everything at the root, the asset's copyright and everything on
+//! it, and what sits on meshes, matched by name. Data on nodes, primitives and accessors refers to
+//! indices that don't survive the rewrite and is dropped, except for lights
+//! and cameras, which get nodes of their own at their world transforms.
+
+use crate::utils::axis_conversion::AxisConversion;
+use crate::utils::gltf_metadata::RightsEdit;
+use crate::utils::scene_graph::{self, SceneGraph, LIGHTS_EXTENSION};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+
+/// Extensions the app decodes, or that only affect shading and so don't
+/// change what it reads from the geometry
+const UNDERSTOOD: [&str; 14] = [
+    "KHR_lights_punctual",
+    "KHR_materials_anisotropy",
+    "KHR_materials_clearcoat",
+    "KHR_materials_emissive_strength",
+    "KHR_materials_ior",
+    "KHR_materials_iridescence",
+    "KHR_materials_pbrSpecularGlossiness",
+    "KHR_materials_sheen",
+    "KHR_materials_specular",
+    "KHR_materials_transmission",
+    "KHR_materials_unlit",
+    "KHR_materials_variants",
+    "KHR_materials_volume",
+    "KHR_texture_transform",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GltfExtension {
+    pub name: String,
+    /// Listed in `extensionsRequired`
+    pub required: bool,
+    /// Decoded by the app, or safe to ignore when reading geometry
+    pub understood: bool,
+}
+
+/// Every extension a document uses or requires, sorted by name
+pub fn report<'a>(
+    used: impl IntoIterator<Item = &'a str>,
+    required: impl IntoIterator<Item = &'a str>,
+) -> Vec<GltfExtension> {
+    let required: BTreeSet<&str> = required.into_iter().collect();
+    let names: BTreeSet<&str> = used.into_iter().chain(required.iter().copied()).collect();
+    names
+        .into_iter()
+        .map(|name| GltfExtension {
+            name: name.to_string(),
+            required: required.contains(name),
+            understood: UNDERSTOOD.contains(&name),
+        })
+        .collect()
+}
+
+/// Extension data and extras copied from a source document into an export
+#[derive(Debug, Clone, Default)]
+pub struct Passthrough {
+    root: Map<String, Value>,
+    asset: Map<String, Value>,
+    /// `extensions` and `extras` of each named mesh
+    meshes: Vec<(String, Map<String, Value>)>,
+    required: BTreeSet<String>,
+    cameras: Vec<Value>,
+    /// Nodes placing lights and cameras, added by `place_lights_and_cameras`
+    nodes: Vec<Value>,
+}
+
+impl Passthrough {
+    /// Collect what can be carried over from a glTF JSON document
+    pub fn read(json: &[u8]) -> Result<Self, String> {
+        let document: Value = serde_json::from_slice(json)
+            .map_err(|e| format!("Failed to parse glTF JSON: {}", e))?;
+        let meshes = document["meshes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|mesh| {
+                let name = mesh["name"].as_str()?;
+                let data = carried(mesh);
+                (!data.is_empty()).then(|| (name.to_string(), data))
+            })
+            .collect();
+        let mut asset = carried(&document["asset"]);
+        if let Some(copyright) = document["asset"].get("copyright") {
+            asset.insert("copyright".to_string(), copyright.clone());
+        }
+        Ok(Self {
+            root: carried(&document),
+            asset,
+            meshes,
+            required: document["extensionsRequired"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|name| name.as_str().map(str::to_string))
+                .collect(),
+            cameras: document["cameras"].as_array().cloned().unwrap_or_default(),
+            nodes: Vec::new(),
+        })
+    }
+
+    /// Add a node for each light and camera of `graph` at its world
+    /// transform, converted like the geometry
+    pub fn place_lights_and_cameras(&mut self, graph: &SceneGraph, conversion: &AxisConversion) {
+        self.nodes = graph
+            .nodes
+            .iter()
+            .filter(|node| node.light.is_some() || node.camera.is_some())
+            .map(|node| {
+                let matrix = scene_graph::convert_transform(conversion, node.world_transform);
+                let mut placed = json!({ "matrix": matrix.concat() });
+                if let Some(name) = &node.name {
+                    placed["name"] = json!(name);
+                }
+                if let Some(camera) = node.camera {
+                    placed["camera"] = json!(camera);
+                }
+                if let Some(light) = node.light {
+                    placed["extensions"] = json!({ LIGHTS_EXTENSION: { "light": light } });
+                }
+                placed
+            })
+            .collect();
+    }
+
+    /// Change the copyright and license the export is written with
+    pub fn set_rights(&mut self, rights: &RightsEdit) -> Result<(), String> {
+        rights.apply(&mut self.asset)
+    }
+
+    /// Leave out lights and cameras
+    pub fn strip_lights_and_cameras(&mut self) {
+        if let Some(extensions) = self
+            .root
+            .get_mut("extensions")
+            .and_then(Value::as_object_mut)
+        {
+            extensions.remove(LIGHTS_EXTENSION);
+            if extensions.is_empty() {
+                self.root.remove("extensions");
+            }
+        }
+        self.cameras.clear();
+        self.nodes.clear();
+    }
+
+    /// Add the carried data to an exported document and list the extensions
+    /// it now contains, along with any it already listed; returns their names
+    pub fn apply(&self, document: &mut Value) -> Vec<String> {
+        merge(document, &self.root);
+        merge(&mut document["asset"], &self.asset);
+        if let Some(meshes) = document["meshes"].as_array_mut() {
+            for mesh in meshes {
+                let data = self
+                    .meshes
+                    .iter()
+                    .find(|(name, _)| mesh["name"].as_str() == Some(name.as_str()));
+                if let Some((_, data)) = data {
+                    merge(mesh, data);
+                }
+            }
+        }
+        if !self.nodes.is_empty() {
+            let first = document["nodes"].as_array().map_or(0, Vec::len);
+            if let Some(nodes) = document["nodes"].as_array_mut() {
+                nodes.extend(self.nodes.iter().cloned());
+            }
+            if let Some(roots) = document["scenes"][0]["nodes"].as_array_mut() {
+                roots.extend((first..first + self.nodes.len()).map(|i| json!(i)));
+            }
+            if self.nodes.iter().any(|node| node.get("camera").is_some()) {
+                document["cameras"] = Value::Array(self.cameras.clone());
+            }
+        }
+
+        let listed = |document: &Value, key: &str| -> Vec<String> {
+            document[key]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|name| name.as_str().map(str::to_string))
+                .collect()
+        };
+        let already_required = listed(document, "extensionsRequired");
+        let names: BTreeSet<String> = std::iter::once(&self.root)
+            .chain(self.meshes.iter().map(|(_, data)| data))
+            .filter_map(|data| data.get("extensions")?.as_object())
+            .chain(
+                self.nodes
+                    .iter()
+                    .filter_map(|node| node["extensions"].as_object()),
+            )
+            .flat_map(|extensions| extensions.keys().cloned())
+            .chain(listed(document, "extensionsUsed"))
+            .collect();
+        if !names.is_empty() {
+            document["extensionsUsed"] = names.iter().cloned().collect();
+            let mut required: BTreeSet<String> =
+                names.intersection(&self.required).cloned().collect();
+            required.extend(already_required);
+            if !required.is_empty() {
+                document["extensionsRequired"] = required.into_iter().collect();
+            }
+        }
+        names.into_iter().collect()
+    }
+}
+
+/// The `extensions` and `extras` of a JSON object
+fn carried(object: &Value) -> Map<String, Value> {
+    ["extensions", "extras"]
+        .into_iter()
+        .filter_map(|key| Some((key.to_string(), object.get(key)?.clone())))
+        .collect()
+}
+
+/// Set each carried `extensions` and `extras` entry on `object`
+fn merge(object: &mut Value, data: &Map<String, Value>) {
+    let Some(object) = object.as_object_mut() else {
+        return;
+    };
+    for (key, value) in data {
+        object.insert(key.clone(), value.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_vendor_data_survives_rewrite() {
+        let extensions = report(
+            ["KHR_materials_unlit", "ACME_wind"],
+            ["ACME_wind", "KHR_draco_mesh_compression"],
+        );
+        let names: Vec<(&str, bool, bool)> = extensions
+            .iter()
+            .map(|e| (e.name.as_str(), e.required, e.understood))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("ACME_wind", true, false),
+                ("KHR_draco_mesh_compression", true, false),
+                ("KHR_materials_unlit", false, true),
+            ]
+        );
+
+        let source = json!({
+            "asset": {
+                "version": "2.0",
+                "copyright": "Forest Co",
+                "extras": { "author": "scan rig 3" }
+            },
+            "extensionsUsed": ["ACME_wind", "KHR_draco_mesh_compression"],
+            "extensionsRequired": ["ACME_wind", "KHR_draco_mesh_compression"],
+            "extensions": { "ACME_wind": { "speed": 4 } },
+            "extras": { "project": "forest" },
+            "meshes": [
+                { "name": "tree", "extras": { "lod": 0 }, "primitives": [] },
+                { "name": "rock", "primitives": [] }
+            ]
+        });
+        let passthrough = Passthrough::read(source.to_string().as_bytes()).unwrap();
+        let mut exported = json!({
+            "asset": { "version": "2.0", "generator": "Sweedle" },
+            "meshes": [{ "name": "rock" }, { "name": "tree" }]
+        });
+        let names = passthrough.apply(&mut exported);
+
+        assert_eq!(names, vec!["ACME_wind"]);
+        assert_eq!(exported["extensionsUsed"], json!(["ACME_wind"]));
+        // Compression data lives on primitives, which aren't carried over
+        assert_eq!(exported["extensionsRequired"], json!(["ACME_wind"]));
+        assert_eq!(exported["extensions"]["ACME_wind"]["speed"], 4);
+        assert_eq!(exported["extras"]["project"], "forest");
+        assert_eq!(exported["asset"]["extras"]["author"], "scan rig 3");
+        assert_eq!(exported["asset"]["generator"], "Sweedle");
+        assert_eq!(exported["asset"]["copyright"], "Forest Co");
+        assert_eq!(exported["meshes"][1]["extras"]["lod"], 0);
+        assert!(exported["meshes"][0].get("extras").is_none());
+    }
+}
@@ -0,0 +1,280 @@
+use crate::utils::platform::FileBytes;
+use gltf::mesh::Mode;
+use gltf::Gltf;
+use nalgebra::{Matrix3, Matrix4, Point3, Vector3};
+use std::path::{Component, Path, PathBuf};
+
+/// Geometry of a single primitive instance, decoded into flat arrays
+#[derive(Debug, Clone)]
+pub struct PrimitiveGeometry {
+    pub mesh_index: usize,
+    pub primitive_index: usize,
+    pub node_index: Option<usize>,
+    pub material_index: Option<usize>,
+    /// Column-major world transform of the owning node
+    pub transform: [[f32; 4]; 4],
+    pub positions: Vec<f32>,
+    pub normals: Option<Vec<f32>>,
+    pub uvs: Option<Vec<f32>>,
+    /// Second UV set (glTF `TEXCOORD_1`), usually for lightmaps
+    pub lightmap_uvs: Option<Vec<f32>>,
+    /// Linear RGBA per vertex (glTF `COLOR_0`)
+    pub colors: Option<Vec<f32>>,
+    pub indices: Vec<u32>,
+}
+
+impl PrimitiveGeometry {
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len() / 3
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Copy of the descriptive fields with empty geometry
+    pub fn without_geometry(&self) -> PrimitiveGeometry {
+        PrimitiveGeometry {
+            mesh_index: self.mesh_index,
+            primitive_index: self.primitive_index,
+            node_index: self.node_index,
+            material_index: self.material_index,
+            transform: self.transform,
+            positions: Vec::new(),
+            normals: None,
+            uvs: None,
+            lightmap_uvs: None,
+            colors: None,
+            indices: Vec::new(),
+        }
+    }
+
+    /// Positions with the node transform applied
+    pub fn world_positions(&self) -> Vec<f32> {
+        let matrix = Matrix4::from(self.transform);
+        if matrix == Matrix4::identity() {
+            return self.positions.clone();
+        }
+
+        self.positions
+            .chunks_exact(3)
+            .flat_map(|p| {
+                let w = matrix.transform_point(&Point3::new(p[0], p[1], p[2]));
+                [w.x, w.y, w.z]
+            })
+            .collect()
+    }
+
+    /// Normals with the node transform applied, still of unit length
+    pub fn world_normals(&self) -> Option<Vec<f32>> {
+        let normals = self.normals.as_ref()?;
+        let matrix = Matrix4::from(self.transform);
+        if matrix == Matrix4::identity() {
+            return Some(normals.clone());
+        }
+
+        let linear: Matrix3<f32> = matrix.fixed_view::<3, 3>(0, 0).into();
+        let normal_matrix = linear.try_inverse()?.transpose();
+        Some(
+            normals
+                .chunks_exact(3)
+                .flat_map(|n| {
+                    let w = (normal_matrix * Vector3::new(n[0], n[1], n[2]))
+                        .try_normalize(f32::EPSILON)
+                        .unwrap_or_default();
+                    [w.x, w.y, w.z]
+                })
+                .collect(),
+        )
+    }
+
+    /// Whether the node transform mirrors the geometry, turning its
+    /// triangles inside out
+    pub fn is_mirrored(&self) -> bool {
+        let matrix = Matrix4::from(self.transform);
+        matrix.fixed_view::<3, 3>(0, 0).determinant() < 0.0
+    }
+}
+
+/// A parsed glTF document with all of its buffers resolved
+pub struct LoadedGltf {
+    pub document: gltf::Document,
+    pub buffers: Vec<gltf::buffer::Data>,
+}
+
+/// Parse a GLB/GLTF file and load its binary chunk and external buffers
+pub fn load_gltf(path: &Path) -> Result<LoadedGltf, String> {
+    if !path.exists() {
+        return Err(format!("File not found: {}", path.display()));
+    }
+
+    let bytes = FileBytes::open(path)?;
+
+    let Gltf { document, blob } =
+        Gltf::from_slice(&bytes).map_err(|e| format!("Failed to parse GLTF: {}", e))?;
+
+    let base = path.parent().unwrap_or(Path::new(""));
+    for buffer in document.buffers() {
+        if let gltf::buffer::Source::Uri(uri) = buffer.source() {
+            if !uri.starts_with("data:") {
+                external_buffer_path(base, uri)?;
+            }
+        }
+    }
+
+    let buffers = gltf::import_buffers(&document, path.parent(), blob)
+        .map_err(|e| format!("Failed to load GLTF buffers: {}", e))?;
+
+    Ok(LoadedGltf { document, buffers })
+}
+
+/// Resolve an external buffer URI relative to the model's folder
+///
+/// Buffers must live in that folder or below it; URIs that climb out of it
+/// or point elsewhere on disk are refused.
+pub fn external_buffer_path(base: &Path, uri: &str) -> Result<PathBuf, String> {
+    let decoded = decode_uri(uri);
+    let relative = Path::new(&decoded);
+    let escapes = relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if escapes {
+        return Err(format!(
+            "External buffer {} is outside the model's folder",
+            uri
+        ));
+    }
+    Ok(base.join(relative))
+}
+
+/// Percent-decode a relative URI reference
+pub(crate) fn decode_uri(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Decode every triangle primitive reachable from the scene graph
+///
+/// Primitives are returned once per node instance with that node's world
+/// transform. Documents without scenes fall back to listing each mesh once
+/// with an identity transform.
+pub fn read_primitives(loaded: &LoadedGltf) -> Vec<PrimitiveGeometry> {
+    let mut primitives = Vec::new();
+
+    let scene = loaded
+        .document
+        .default_scene()
+        .or_else(|| loaded.document.scenes().next());
+
+    match scene {
+        Some(scene) => {
+            for node in scene.nodes() {
+                collect_node(loaded, &node, Matrix4::identity(), &mut primitives);
+            }
+        }
+        None => {
+            for mesh in loaded.document.meshes() {
+                read_mesh(loaded, &mesh, None, Matrix4::identity(), &mut primitives);
+            }
+        }
+    }
+
+    primitives
+}
+
+fn collect_node(
+    loaded: &LoadedGltf,
+    node: &gltf::Node,
+    parent: Matrix4<f32>,
+    out: &mut Vec<PrimitiveGeometry>,
+) {
+    let world = parent * Matrix4::from(node.transform().matrix());
+
+    if let Some(mesh) = node.mesh() {
+        read_mesh(loaded, &mesh, Some(node.index()), world, out);
+    }
+
+    for child in node.children() {
+        collect_node(loaded, &child, world, out);
+    }
+}
+
+fn read_mesh(
+    loaded: &LoadedGltf,
+    mesh: &gltf::Mesh,
+    node_index: Option<usize>,
+    world: Matrix4<f32>,
+    out: &mut Vec<PrimitiveGeometry>,
+) {
+    for primitive in mesh.primitives() {
+        if primitive.mode() != Mode::Triangles {
+            log::debug!(
+                "Skipping non-triangle primitive {} of mesh {}",
+                primitive.index(),
+                mesh.index()
+            );
+            continue;
+        }
+
+        let reader =
+            primitive.reader(|buffer| loaded.buffers.get(buffer.index()).map(|d| &d.0[..]));
+
+        let positions: Vec<f32> = match reader.read_positions() {
+            Some(iter) => iter.flatten().collect(),
+            None => continue,
+        };
+
+        let normals = reader
+            .read_normals()
+            .map(|iter| iter.flatten().collect::<Vec<f32>>());
+
+        let uvs = reader
+            .read_tex_coords(0)
+            .map(|coords| coords.into_f32().flatten().collect::<Vec<f32>>());
+
+        let lightmap_uvs = reader
+            .read_tex_coords(1)
+            .map(|coords| coords.into_f32().flatten().collect::<Vec<f32>>());
+
+        let colors = reader
+            .read_colors(0)
+            .map(|colors| colors.into_rgba_f32().flatten().collect::<Vec<f32>>());
+
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..(positions.len() / 3) as u32).collect(),
+        };
+
+        out.push(PrimitiveGeometry {
+            mesh_index: mesh.index(),
+            primitive_index: primitive.index(),
+            node_index,
+            material_index: primitive.material().index(),
+            transform: world.into(),
+            positions,
+            normals,
+            uvs,
+            lightmap_uvs,
+            colors,
+            indices,
+        });
+    }
+}
@@ -0,0 +1,119 @@
+//! Provenance and license fields of glTF files
+//!
+//! glTF records the tool that wrote a file in `asset.generator` and a
+//! copyright notice in `asset.copyright`. It has no license field; files
+//! from Sketchfab and other stores carry one in `asset.extras.license`, so
+//! that's where it's read from and written to. Application data in the
+//! top-level `extras` is reported as is.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssetMetadata {
+    pub generator: Option<String>,
+    pub copyright: Option<String>,
+    /// `asset.extras.license`
+    pub license: Option<String>,
+    /// The document's top-level `extras`
+    pub extras: Option<Value>,
+}
+
+/// Metadata of a glTF JSON document
+pub fn read(json: &[u8]) -> Result<AssetMetadata, String> {
+    let document: Value =
+        serde_json::from_slice(json).map_err(|e| format!("Failed to parse glTF JSON: {}", e))?;
+    Ok(of(&document))
+}
+
+/// Metadata of a parsed glTF document
+pub fn of(document: &Value) -> AssetMetadata {
+    let asset = &document["asset"];
+    let text = |value: &Value| value.as_str().map(str::to_string);
+    AssetMetadata {
+        generator: text(&asset["generator"]),
+        copyright: text(&asset["copyright"]),
+        license: text(&asset["extras"]["license"]),
+        extras: document.get("extras").cloned(),
+    }
+}
+
+/// Copyright and license to write into an exported or edited file
+///
+/// `None` leaves a field as it is and an empty string removes it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RightsEdit {
+    pub copyright: Option<String>,
+    pub license: Option<String>,
+}
+
+impl RightsEdit {
+    /// Apply the edit to a document's `asset` object
+    pub fn apply(&self, asset: &mut Map<String, Value>) -> Result<(), String> {
+        if let Some(copyright) = &self.copyright {
+            set(asset, "copyright", copyright);
+        }
+        let Some(license) = &self.license else {
+            return Ok(());
+        };
+        let extras = asset
+            .entry("extras")
+            .or_insert_with(|| Value::Object(Map::new()));
+        let Some(fields) = extras.as_object_mut() else {
+            return Err("Can't add a license: asset.extras is not an object".to_string());
+        };
+        set(fields, "license", license);
+        if fields.is_empty() {
+            asset.remove("extras");
+        }
+        Ok(())
+    }
+}
+
+fn set(object: &mut Map<String, Value>, key: &str, value: &str) {
+    let value = value.trim();
+    if value.is_empty() {
+        object.remove(key);
+    } else {
+        object.insert(key.to_string(), Value::String(value.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reads_and_edits_rights() {
+        let document = json!({
+            "asset": {
+                "version": "2.0",
+                "generator": "Blender",
+                "extras": { "author": "kit", "license": "CC-BY-4.0" }
+            },
+            "extras": { "scan_id": 7 }
+        });
+        let metadata = read(document.to_string().as_bytes()).unwrap();
+        assert_eq!(metadata.generator.as_deref(), Some("Blender"));
+        assert_eq!(metadata.copyright, None);
+        assert_eq!(metadata.license.as_deref(), Some("CC-BY-4.0"));
+        assert_eq!(metadata.extras, Some(json!({ "scan_id": 7 })));
+
+        let mut asset = document["asset"].as_object().unwrap().clone();
+        let edit = RightsEdit {
+            copyright: Some(" 2026 Example Studio ".to_string()),
+            license: Some(String::new()),
+        };
+        edit.apply(&mut asset).unwrap();
+        assert_eq!(asset["copyright"], "2026 Example Studio");
+        assert_eq!(asset["extras"], json!({ "author": "kit" }));
+
+        // Removing the only extra leaves no empty object behind
+        let mut bare = Map::new();
+        edit.apply(&mut bare).unwrap();
+        assert!(!bare.contains_key("extras"));
+        let mut odd = json!({ "extras": "notes" }).as_object().unwrap().clone();
+        assert!(edit.apply(&mut odd).is_err());
+    }
+}
@@ -0,0 +1,510 @@
+//! Pulling one node subtree out of a glTF scene
+//!
+//! Unlike the geometry exports, this works on the glTF JSON itself, so the
+//! subtree keeps everything it refers to: meshes with all their attributes,
+//! materials, textures, samplers, images, cameras and lights. Referenced
+//! objects are renumbered in order of first use and only the bytes of the
+//! buffer views they need are copied into the new binary chunk; external
+//! images are embedded. Skins and animations refer to the rest of the scene
+//! and are left out. Compressed primitives (Draco, meshopt) aren't supported.
+
+use crate::utils::gltf_geometry::{external_buffer_path, LoadedGltf};
+use crate::utils::scene_graph::{SceneGraph, LIGHTS_EXTENSION};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// A self-contained glTF document for one subtree, ready for a GLB
+pub struct Subset {
+    pub document: Value,
+    pub bin: Vec<u8>,
+    pub node_count: usize,
+    pub mesh_count: usize,
+    pub material_count: usize,
+    pub texture_count: usize,
+    /// What the source had that couldn't come along
+    pub dropped: Vec<String>,
+}
+
+/// Indices of one kind of object in the source, renumbered by first use
+#[derive(Default)]
+struct Remap {
+    new: HashMap<usize, usize>,
+    order: Vec<usize>,
+}
+
+impl Remap {
+    fn add(&mut self, old: usize) -> usize {
+        *self.new.entry(old).or_insert_with(|| {
+            self.order.push(old);
+            self.order.len() - 1
+        })
+    }
+
+    /// Replace the index in `value` with its new number
+    fn patch(&mut self, value: &mut Value) {
+        if let Some(old) = value.as_u64() {
+            *value = json!(self.add(old as usize));
+        }
+    }
+}
+
+/// The node `query` names: a `/`-separated path of node names from a root,
+/// or the name of a single node anywhere in the scene
+pub fn find_node(graph: &SceneGraph, query: &str) -> Result<usize, String> {
+    let query = query.trim().trim_matches('/');
+    let named = |index: &usize, name: &str| graph.nodes[*index].name.as_deref() == Some(name);
+
+    if query.contains('/') {
+        let mut candidates = graph.roots.clone();
+        let mut found = None;
+        for part in query.split('/') {
+            let next = candidates
+                .iter()
+                .find(|index| named(index, part))
+                .copied()
+                .ok_or_else(|| format!("No node at '{}': '{}' not found", query, part))?;
+            candidates = graph.nodes[next].children.clone();
+            found = Some(next);
+        }
+        return found.ok_or_else(|| format!("No node at '{}'", query));
+    }
+
+    let matches: Vec<usize> = (0..graph.nodes.len())
+        .filter(|index| named(index, query))
+        .collect();
+    match matches.as_slice() {
+        [index] => Ok(*index),
+        [] => Err(format!("No node named '{}'", query)),
+        _ => Err(format!(
+            "{} nodes are named '{}'; give its path from the root instead",
+            matches.len(),
+            query
+        )),
+    }
+}
+
+/// Copy node `root` and its descendants with everything they refer to
+///
+/// The subtree root keeps its world transform, so the prop sits where it
+/// did in the scene. `base` is the source's folder, for external images.
+pub fn extract(
+    raw: &Value,
+    loaded: &LoadedGltf,
+    graph: &SceneGraph,
+    root: usize,
+    base: &Path,
+) -> Result<Subset, String> {
+    let source = |kind: &str, index: usize| -> Result<Value, String> {
+        raw[kind]
+            .get(index)
+            .cloned()
+            .ok_or_else(|| format!("{} {} is missing", kind, index))
+    };
+    let mut dropped = Vec::new();
+
+    let mut nodes = Remap::default();
+    nodes.add(root);
+    let mut i = 0;
+    while let Some(&old) = nodes.order.get(i) {
+        for &child in &graph.nodes[old].children {
+            nodes.add(child);
+        }
+        i += 1;
+    }
+
+    let (mut meshes, mut cameras, mut lights) =
+        (Remap::default(), Remap::default(), Remap::default());
+    let mut out_nodes = Vec::with_capacity(nodes.order.len());
+    for old in nodes.order.clone() {
+        let mut node = source("nodes", old)?;
+        if let Some(children) = node.get_mut("children").and_then(Value::as_array_mut) {
+            for child in children {
+                nodes.patch(child);
+            }
+        }
+        if node.get("mesh").is_some() {
+            meshes.patch(&mut node["mesh"]);
+        }
+        if node.get("camera").is_some() {
+            cameras.patch(&mut node["camera"]);
+        }
+        if let Some(light) = node.pointer_mut(&format!("/extensions/{}/light", LIGHTS_EXTENSION)) {
+            lights.patch(light);
+        }
+        if let Some(object) = node.as_object_mut() {
+            if object.remove("skin").is_some() {
+                dropped.push(format!("skin of node {}", old));
+            }
+            if old == root {
+                for key in ["translation", "rotation", "scale"] {
+                    object.remove(key);
+                }
+                object.insert(
+                    "matrix".to_string(),
+                    json!(graph.nodes[root].world_transform.concat()),
+                );
+            }
+        }
+        out_nodes.push(node);
+    }
+
+    let (mut accessors, mut materials) = (Remap::default(), Remap::default());
+    let mut out_meshes = Vec::with_capacity(meshes.order.len());
+    for old in meshes.order.clone() {
+        let mut mesh = source("meshes", old)?;
+        let primitives = mesh.get_mut("primitives").and_then(Value::as_array_mut);
+        for primitive in primitives.into_iter().flatten() {
+            let targets = primitive.get_mut("targets").and_then(Value::as_array_mut);
+            for target in targets.into_iter().flatten() {
+                patch_attributes(target, &mut accessors);
+            }
+            if let Some(attributes) = primitive.get_mut("attributes") {
+                patch_attributes(attributes, &mut accessors);
+            }
+            if primitive.get("indices").is_some() {
+                accessors.patch(&mut primitive["indices"]);
+            }
+            if primitive.get("material").is_some() {
+                materials.patch(&mut primitive["material"]);
+            }
+            let extensions = primitive
+                .get_mut("extensions")
+                .and_then(Value::as_object_mut);
+            for (name, extension) in extensions.into_iter().flatten() {
+                if extension.get("bufferView").is_some() {
+                    return Err(format!("Mesh {} is compressed with {}", old, name));
+                }
+                let mappings = extension.get_mut("mappings").and_then(Value::as_array_mut);
+                for mapping in mappings.into_iter().flatten() {
+                    if mapping.get("material").is_some() {
+                        materials.patch(&mut mapping["material"]);
+                    }
+                }
+            }
+        }
+        out_meshes.push(mesh);
+    }
+
+    let mut textures = Remap::default();
+    let out_materials = materials
+        .order
+        .clone()
+        .into_iter()
+        .map(|old| {
+            let mut material = source("materials", old)?;
+            patch_textures(&mut material, &mut textures);
+            Ok(material)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let (mut images, mut samplers) = (Remap::default(), Remap::default());
+    let mut out_textures = Vec::with_capacity(textures.order.len());
+    for old in textures.order.clone() {
+        let mut texture = source("textures", old)?;
+        if texture.get("source").is_some() {
+            images.patch(&mut texture["source"]);
+        }
+        if texture.get("sampler").is_some() {
+            samplers.patch(&mut texture["sampler"]);
+        }
+        // Image formats like KTX2 and WebP name their source in an extension
+        let extensions = texture.get_mut("extensions").and_then(Value::as_object_mut);
+        for (_, extension) in extensions.into_iter().flatten() {
+            if extension.get("source").is_some() {
+                images.patch(&mut extension["source"]);
+            }
+        }
+        out_textures.push(texture);
+    }
+
+    let mut views = Remap::default();
+    let mut embedded = Vec::new();
+    let mut out_images = Vec::with_capacity(images.order.len());
+    for old in images.order.clone() {
+        let mut image = source("images", old)?;
+        if image.get("bufferView").is_some() {
+            views.patch(&mut image["bufferView"]);
+        } else if let Some(uri) = image["uri"]
+            .as_str()
+            .filter(|uri| !uri.starts_with("data:"))
+        {
+            let path = external_buffer_path(base, uri)?;
+            let bytes = fs::read(&path)
+                .map_err(|e| format!("Failed to read image {}: {}", path.display(), e))?;
+            if image.get("mimeType").is_none() {
+                let extension = path
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                let mime = match extension.as_str() {
+                    "jpg" | "jpeg" => "image/jpeg",
+                    "ktx2" => "image/ktx2",
+                    "webp" => "image/webp",
+                    _ => "image/png",
+                };
+                image["mimeType"] = json!(mime);
+            }
+            if let Some(object) = image.as_object_mut() {
+                object.remove("uri");
+            }
+            embedded.push((out_images.len(), bytes));
+        }
+        out_images.push(image);
+    }
+
+    let mut out_accessors = Vec::with_capacity(accessors.order.len());
+    for old in accessors.order.clone() {
+        let mut accessor = source("accessors", old)?;
+        if accessor.get("bufferView").is_some() {
+            views.patch(&mut accessor["bufferView"]);
+        }
+        for part in ["indices", "values"] {
+            if let Some(view) = accessor.pointer_mut(&format!("/sparse/{}/bufferView", part)) {
+                views.patch(view);
+            }
+        }
+        out_accessors.push(accessor);
+    }
+
+    let mut bin = Vec::new();
+    let mut out_views = Vec::with_capacity(views.order.len() + embedded.len());
+    for old in views.order.clone() {
+        let mut view = source("bufferViews", old)?;
+        let buffer = view["buffer"].as_u64().unwrap_or(0) as usize;
+        let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let length = view["byteLength"].as_u64().unwrap_or(0) as usize;
+        let bytes = loaded
+            .buffers
+            .get(buffer)
+            .and_then(|data| data.0.get(offset..offset + length))
+            .ok_or_else(|| format!("bufferViews {} runs past its buffer", old))?;
+        view["buffer"] = json!(0);
+        view["byteOffset"] = json!(append_aligned(&mut bin, bytes));
+        out_views.push(view);
+    }
+    for (image, bytes) in embedded {
+        out_images[image]["bufferView"] = json!(out_views.len());
+        let offset = append_aligned(&mut bin, &bytes);
+        out_views.push(json!({ "buffer": 0, "byteOffset": offset, "byteLength": bytes.len() }));
+    }
+
+    let out_cameras = cameras
+        .order
+        .iter()
+        .map(|&old| source("cameras", old))
+        .collect::<Result<Vec<_>, String>>()?;
+    let out_samplers = samplers
+        .order
+        .iter()
+        .map(|&old| source("samplers", old))
+        .collect::<Result<Vec<_>, String>>()?;
+    let out_lights = lights
+        .order
+        .iter()
+        .map(|&old| {
+            raw["extensions"][LIGHTS_EXTENSION]["lights"]
+                .get(old)
+                .cloned()
+                .ok_or_else(|| format!("light {} is missing", old))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    if let Some(animations) = raw["animations"].as_array().filter(|a| !a.is_empty()) {
+        dropped.push(format!("{} animations", animations.len()));
+    }
+
+    let mut document = Map::new();
+    let mut asset = raw["asset"].clone();
+    asset["generator"] = json!("Sweedle");
+    document.insert("asset".to_string(), asset);
+    document.insert("scene".to_string(), json!(0));
+    document.insert("scenes".to_string(), json!([{ "nodes": [0] }]));
+    for (key, values) in [
+        ("nodes", out_nodes),
+        ("meshes", out_meshes),
+        ("materials", out_materials),
+        ("textures", out_textures),
+        ("images", out_images),
+        ("samplers", out_samplers),
+        ("cameras", out_cameras),
+        ("accessors", out_accessors),
+        ("bufferViews", out_views),
+    ] {
+        if !values.is_empty() {
+            document.insert(key.to_string(), Value::Array(values));
+        }
+    }
+    if !bin.is_empty() {
+        document.insert("buffers".to_string(), json!([{ "byteLength": bin.len() }]));
+    }
+
+    // Extensions the source uses are kept listed, except lights when none
+    // came along
+    let mut extensions = raw["extensions"].clone();
+    if let Some(object) = extensions.as_object_mut() {
+        if out_lights.is_empty() {
+            object.remove(LIGHTS_EXTENSION);
+        } else {
+            object.insert(
+                LIGHTS_EXTENSION.to_string(),
+                json!({ "lights": out_lights }),
+            );
+        }
+        if !object.is_empty() {
+            document.insert("extensions".to_string(), extensions.clone());
+        }
+    }
+    for key in ["extensionsUsed", "extensionsRequired"] {
+        let names: Vec<Value> = raw[key]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|name| name.as_str() != Some(LIGHTS_EXTENSION) || !out_lights.is_empty())
+            .cloned()
+            .collect();
+        if !names.is_empty() {
+            document.insert(key.to_string(), Value::Array(names));
+        }
+    }
+
+    Ok(Subset {
+        node_count: nodes.order.len(),
+        mesh_count: meshes.order.len(),
+        material_count: materials.order.len(),
+        texture_count: textures.order.len(),
+        document: Value::Object(document),
+        bin,
+        dropped,
+    })
+}
+
+/// Renumber the accessors of a primitive's attributes or morph target
+fn patch_attributes(attributes: &mut Value, accessors: &mut Remap) {
+    for (_, accessor) in attributes.as_object_mut().into_iter().flatten() {
+        accessors.patch(accessor);
+    }
+}
+
+/// Renumber the texture of every `...Texture` object in a material,
+/// including those in material extensions
+fn patch_textures(value: &mut Value, textures: &mut Remap) {
+    match value {
+        Value::Object(object) => {
+            for (key, child) in object.iter_mut() {
+                if key.ends_with("Texture") && child.get("index").is_some() {
+                    textures.patch(&mut child["index"]);
+                }
+                patch_textures(child, textures);
+            }
+        }
+        Value::Array(values) => {
+            for child in values {
+                patch_textures(child, textures);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Append `bytes` at a 4-byte boundary and return where they start
+fn append_aligned(bin: &mut Vec<u8>, bytes: &[u8]) -> usize {
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+    let offset = bin.len();
+    bin.extend_from_slice(bytes);
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::glb_writer::container;
+    use crate::utils::gltf_geometry::load_gltf;
+    use crate::utils::scene_graph;
+
+    #[test]
+    fn test_subtree_keeps_what_it_uses() {
+        // A triangle, its indices and a 1x1 PNG, each in its own view
+        let mut bin: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        bin.extend([0u16, 1, 2, 0].iter().flat_map(|i| i.to_le_bytes()));
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::new(1, 1)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let png = png.into_inner();
+        bin.extend(&png);
+        let view = |offset: usize, length: usize| json!({ "buffer": 0, "byteOffset": offset, "byteLength": length });
+        let document = json!({
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [
+                { "name": "level", "children": [1, 2] },
+                { "name": "ground", "mesh": 0 },
+                { "name": "crate", "translation": [5, 0, 0], "children": [3] },
+                { "name": "lid", "mesh": 1 }
+            ],
+            "meshes": [
+                { "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1 }] },
+                { "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 1 }] }
+            ],
+            "materials": [
+                { "name": "grass" },
+                { "name": "wood", "pbrMetallicRoughness": { "baseColorTexture": { "index": 0 } } }
+            ],
+            "textures": [{ "source": 0 }],
+            "images": [{ "bufferView": 2, "mimeType": "image/png" }],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                  "min": [0, 0, 0], "max": [1, 1, 0] },
+                { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+            ],
+            "bufferViews": [view(0, 36), view(36, 6), view(44, png.len())],
+            "buffers": [{ "byteLength": bin.len() }],
+            "animations": [{ "channels": [], "samplers": [] }]
+        });
+        let dir = std::env::temp_dir().join(format!("sweedle_subset_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("level.glb");
+        fs::write(&path, container(&document, &bin).unwrap()).unwrap();
+
+        let loaded = load_gltf(&path).unwrap();
+        let graph = scene_graph::parse(document.to_string().as_bytes()).unwrap();
+        assert_eq!(find_node(&graph, "level/crate/lid").unwrap(), 3);
+        assert!(find_node(&graph, "level/lid").is_err());
+        let root = find_node(&graph, "crate").unwrap();
+        let subset = extract(&document, &loaded, &graph, root, &dir).unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(subset.node_count, 2);
+        assert_eq!(subset.mesh_count, 1);
+        assert_eq!(subset.material_count, 1);
+        assert_eq!(subset.texture_count, 1);
+        assert_eq!(subset.dropped, vec!["1 animations"]);
+        let out = &subset.document;
+        assert_eq!(out["nodes"][0]["children"], json!([1]));
+        assert_eq!(out["nodes"][1]["mesh"], 0);
+        assert_eq!(out["meshes"][0]["primitives"][0]["material"], 0);
+        assert_eq!(out["materials"][0]["name"], "wood");
+        assert_eq!(out["nodes"][0]["matrix"][12], 5.0);
+
+        let glb = container(&subset.document, &subset.bin).unwrap();
+        let (document, buffers, images) = gltf::import_slice(&glb).unwrap();
+        assert_eq!(images.len(), 1);
+        let primitive = document
+            .meshes()
+            .next()
+            .unwrap()
+            .primitives()
+            .next()
+            .unwrap();
+        let reader = primitive.reader(|b| Some(&buffers[b.index()]));
+        assert_eq!(reader.read_positions().unwrap().count(), 3);
+    }
+}
@@ -0,0 +1,608 @@
+//! Hollowing and drain holes for resin printing
+//!
+//! A hollowed mesh keeps its surface and gains an inner wall offset inward
+//! by the wall thickness and wound the other way, so the solid is the gap
+//! between the two and the cavity inside costs no resin. A drain hole cuts
+//! the faces around a line through both walls and joins the two openings
+//! with a tube, letting uncured resin run out and air in. Vertices are
+//! welded by exact position, as in the topology checks.
+//!
+//! The same offset makes shells and clearances for molds and fitted parts,
+//! moving the surface out as well as in.
+
+use crate::utils::compute::cpu::closest_point_on_triangle;
+use crate::utils::mesh_store::MeshData;
+use crate::utils::simd;
+use crate::utils::topology;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+/// What `offset` makes of the mesh
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OffsetMode {
+    /// Only the moved surface
+    #[default]
+    Surface,
+    /// The moved surface and the original, with the solid between them
+    Shell,
+}
+
+/// Smallest cosine between a face and its corner's normal used to size the
+/// offset, so a sharp corner moves at most three times the wall thickness
+const MIN_CORNER_COSINE: f32 = 1.0 / 3.0;
+
+/// Add an inner wall `wall_thickness` inside a closed mesh
+///
+/// Each corner moves along its angle-weighted normal, far enough that the
+/// faces around it move the full thickness; walls thinner than twice the
+/// thickness fold through each other, which is reported as an error.
+pub fn hollow(mesh: &MeshData, wall_thickness: f32) -> Result<MeshData, String> {
+    if !(wall_thickness > 0.0 && wall_thickness.is_finite()) {
+        return Err(format!(
+            "Wall thickness must be a positive number, got {}",
+            wall_thickness
+        ));
+    }
+    let topology = topology::analyze(&mesh.positions, &mesh.indices);
+    if topology.boundary_loop_count > 0 || !topology.is_manifold() {
+        return Err("Only closed, manifold meshes can be hollowed; repair it first".to_string());
+    }
+    let (lo, hi) = simd::bounds(&mesh.positions).ok_or("Mesh has no vertices")?;
+    let thinnest = (0..3).map(|k| hi[k] - lo[k]).fold(f32::MAX, f32::min);
+    if wall_thickness * 2.0 >= thinnest {
+        return Err(format!(
+            "Wall thickness {} leaves no room inside a mesh {} thick",
+            wall_thickness, thinnest
+        ));
+    }
+    // Inward is against the normals of an outward-wound mesh
+    let volume = simd::signed_volume(&mesh.positions, &mesh.indices);
+    let inner = offset_positions(mesh, -wall_thickness * volume.signum());
+    let hollowed = shell(mesh, inner, true);
+
+    // A folded inner wall adds volume back instead of taking it away
+    let remaining = simd::signed_volume(&hollowed.positions, &hollowed.indices);
+    if !(remaining * volume > 0.0 && remaining.abs() < volume.abs()) {
+        return Err(format!(
+            "Wall thickness {} is too thick for the thinner parts of this mesh",
+            wall_thickness
+        ));
+    }
+    Ok(hollowed)
+}
+
+/// Move a mesh's surface `distance` along its normals, outward if positive
+///
+/// Closed meshes move out of the solid whichever way they're wound, open
+/// ones along their winding. Corners move as the inner wall's do when
+/// hollowing. A shell needs a closed, manifold mesh; an inward offset
+/// deeper than half the mesh's thinnest part folds and is an error.
+pub fn offset(mesh: &MeshData, distance: f32, mode: OffsetMode) -> Result<MeshData, String> {
+    if !(distance != 0.0 && distance.is_finite()) {
+        return Err(format!(
+            "Offset distance must be a non-zero number, got {}",
+            distance
+        ));
+    }
+    let topology = topology::analyze(&mesh.positions, &mesh.indices);
+    let closed = topology.boundary_loop_count == 0 && topology.is_manifold();
+    if mode == OffsetMode::Shell && !closed {
+        return Err("Only closed, manifold meshes can be shelled; repair it first".to_string());
+    }
+    if mesh.positions.is_empty() {
+        return Err("Mesh has no vertices".to_string());
+    }
+
+    let volume = simd::signed_volume(&mesh.positions, &mesh.indices);
+    let along = if closed {
+        distance * volume.signum()
+    } else {
+        distance
+    };
+    let moved = offset_positions(mesh, along);
+    if closed && distance < 0.0 {
+        // As when hollowing, a folded surface doesn't shrink the solid
+        let remaining = simd::signed_volume(&moved, &mesh.indices);
+        if !(remaining * volume > 0.0 && remaining.abs() < volume.abs()) {
+            return Err(format!(
+                "Offset {} is deeper than the thinner parts of this mesh",
+                distance
+            ));
+        }
+    }
+
+    Ok(match mode {
+        OffsetMode::Surface => MeshData {
+            positions: moved,
+            ..mesh.clone()
+        },
+        OffsetMode::Shell => shell(mesh, moved, distance < 0.0),
+    })
+}
+
+/// Drill a hole of `radius` at each of `points` through a hollowed mesh's
+/// wall into its cavity
+///
+/// Each hole starts on the surface closest to its point and runs against
+/// the surface normal. Openings follow the existing edges, taking every face
+/// whose center is within `radius` of the hole's line, so a coarse mesh
+/// gets angular holes; subdivide it first for round ones.
+pub fn drill_drain_holes(
+    mesh: &MeshData,
+    points: &[[f32; 3]],
+    radius: f32,
+) -> Result<MeshData, String> {
+    if !(radius > 0.0 && radius.is_finite()) {
+        return Err(format!("Radius must be a positive number, got {}", radius));
+    }
+    if points.is_empty() {
+        return Err("No drain hole positions given".to_string());
+    }
+    let mut drilled = mesh.clone();
+    for (i, &point) in points.iter().enumerate() {
+        drilled =
+            drill(&drilled, point, radius).map_err(|e| format!("Drain hole {}: {}", i + 1, e))?;
+    }
+    Ok(drilled)
+}
+
+fn drill(mesh: &MeshData, point: [f32; 3], radius: f32) -> Result<MeshData, String> {
+    let orientation = simd::signed_volume(&mesh.positions, &mesh.indices).signum();
+    let corner = |i: u32| {
+        let b = i as usize * 3;
+        [
+            mesh.positions[b],
+            mesh.positions[b + 1],
+            mesh.positions[b + 2],
+        ]
+    };
+    let triangles: Vec<[[f32; 3]; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [corner(f[0]), corner(f[1]), corner(f[2])])
+        .collect();
+    let outward = |f: usize| face_normal(triangles[f]).map(|n| scale(n, orientation));
+
+    // Enter at the closest surface point and head straight in
+    let (_, entry_face, entry) = triangles
+        .par_iter()
+        .enumerate()
+        .map(|(f, &[a, b, c])| {
+            let q = closest_point_on_triangle(point, a, b, c);
+            (length(sub(q, point)), f, q)
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .ok_or("Mesh has no faces")?;
+    let direction = scale(
+        outward(entry_face).ok_or("Surface at the hole has no area")?,
+        -1.0,
+    );
+    let (lo, hi) = simd::bounds(&mesh.positions).ok_or("Mesh has no vertices")?;
+    let epsilon = length(sub(hi, lo)) * 1e-5;
+    let mut hits: Vec<(f32, usize)> = triangles
+        .par_iter()
+        .enumerate()
+        .filter(|&(f, _)| f != entry_face)
+        .filter_map(|(f, t)| Some((ray_hit(entry, direction, t)?, f)))
+        .filter(|&(t, _)| t > epsilon)
+        .collect();
+    hits.sort_by(|a, b| a.0.total_cmp(&b.0));
+    // The first face leaves the wall; a cavity has more solid past it,
+    // where the far side of a solid has none
+    let &(depth, exit_face) = hits.first().ok_or("Nothing behind the surface there")?;
+    let cavity = hits
+        .iter()
+        .any(|&(t, f)| t > depth + epsilon && outward(f).is_some_and(|n| dot(n, direction) < 0.0));
+    if !cavity {
+        return Err(format!(
+            "No cavity behind the surface at {:?}; hollow the mesh first",
+            entry
+        ));
+    }
+
+    let (u_axis, v_axis) = perpendicular(direction);
+    let around = |p: [f32; 3]| {
+        let w = sub(p, entry);
+        let along = dot(w, direction);
+        let radial = sub(w, scale(direction, along));
+        (along, radial)
+    };
+    let mut removed = vec![0u8; triangles.len()];
+    for (f, t) in triangles.iter().enumerate() {
+        let centroid = scale(add(add(t[0], t[1]), t[2]), 1.0 / 3.0);
+        let (along, radial) = around(centroid);
+        if length(radial) >= radius {
+            continue;
+        }
+        if (-radius..depth / 2.0).contains(&along) {
+            removed[f] = 1;
+        } else if (depth / 2.0..depth + radius).contains(&along) {
+            removed[f] = 2;
+        }
+    }
+    removed[entry_face] = 1;
+    removed[exit_face] = 2;
+
+    let (ids, points) = weld(&mesh.positions);
+    let mut representative = vec![0usize; points.len()];
+    for (v, &id) in ids.iter().enumerate().rev() {
+        representative[id as usize] = v;
+    }
+    let welded_faces: Vec<[u32; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [f[0], f[1], f[2]].map(|i| ids[i as usize]))
+        .collect();
+
+    // Both openings as rings of tube vertices sorted by angle around the hole
+    let mut tube: Vec<(usize, [f32; 3])> = Vec::new();
+    let mut rings: Vec<Vec<(f32, u32)>> = Vec::new();
+    for wall in [1, 2] {
+        let faces: Vec<[u32; 3]> = (0..welded_faces.len())
+            .filter(|&f| removed[f] == wall)
+            .map(|f| welded_faces[f])
+            .collect();
+        let mut ring: Vec<(f32, u32)> = opening(&faces)?
+            .into_iter()
+            .map(|id| {
+                let (_, radial) = around(points[id as usize]);
+                let angle = dot(radial, v_axis).atan2(dot(radial, u_axis));
+                let normal = scale(radial, -1.0 / length(radial).max(f32::MIN_POSITIVE));
+                tube.push((representative[id as usize], normal));
+                (angle, (tube.len() - 1) as u32)
+            })
+            .collect();
+        ring.sort_by(|a, b| a.0.total_cmp(&b.0));
+        rings.push(ring);
+    }
+
+    // Zip the rings together, always stepping along the one whose next
+    // vertex comes first around the hole
+    let (outer, inner) = (&rings[0], &rings[1]);
+    let angle_at = |ring: &[(f32, u32)], i: usize| {
+        let wrapped = ring[i % ring.len()].0;
+        if i < ring.len() {
+            wrapped
+        } else {
+            wrapped + TAU
+        }
+    };
+    let mut tube_faces: Vec<[u32; 3]> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < outer.len() || j < inner.len() {
+        let step_outer = j == inner.len()
+            || (i < outer.len() && angle_at(outer, i + 1) < angle_at(inner, j + 1));
+        let a = outer[i % outer.len()].1;
+        let b = inner[j % inner.len()].1;
+        let mut face = if step_outer {
+            i += 1;
+            [a, outer[i % outer.len()].1, b]
+        } else {
+            j += 1;
+            [a, inner[j % inner.len()].1, b]
+        };
+        // The tube's faces look into the hole
+        let positions = face.map(|t| corner(tube[t as usize].0 as u32));
+        let centroid = scale(
+            add(add(positions[0], positions[1]), positions[2]),
+            1.0 / 3.0,
+        );
+        let normal = cross(
+            sub(positions[1], positions[0]),
+            sub(positions[2], positions[0]),
+        );
+        if dot(normal, around(centroid).1) * orientation > 0.0 {
+            face.swap(1, 2);
+        }
+        tube_faces.push(face);
+    }
+
+    // Keep the vertices that are still used, then the tube's
+    let mut remap = vec![u32::MAX; mesh.vertex_count()];
+    let mut order = Vec::new();
+    let mut indices = Vec::new();
+    for (face, _) in mesh
+        .indices
+        .chunks_exact(3)
+        .zip(&removed)
+        .filter(|(_, &wall)| wall == 0)
+    {
+        for &v in face {
+            if remap[v as usize] == u32::MAX {
+                remap[v as usize] = order.len() as u32;
+                order.push(v as usize);
+            }
+            indices.push(remap[v as usize]);
+        }
+    }
+    let kept = order.len();
+    order.extend(tube.iter().map(|&(source, _)| source));
+    indices.extend(tube_faces.iter().flatten().map(|&t| kept as u32 + t));
+
+    let mut drilled = mesh.with_vertices(&order, indices);
+    if let Some(normals) = &mut drilled.normals {
+        for (t, &(_, normal)) in tube.iter().enumerate() {
+            normals[(kept + t) * 3..(kept + t + 1) * 3].copy_from_slice(&normal);
+        }
+    }
+    Ok(drilled)
+}
+
+/// Welded vertices around the edge of a patch of faces, which has to be a
+/// single loop
+fn opening(faces: &[[u32; 3]]) -> Result<Vec<u32>, String> {
+    let mut edges: HashMap<(u32, u32), u32> = HashMap::new();
+    for face in faces {
+        for k in 0..3 {
+            let (a, b) = (face[k], face[(k + 1) % 3]);
+            *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+    }
+    let mut neighbours: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (&(a, b), _) in edges.iter().filter(|(_, &count)| count == 1) {
+        neighbours.entry(a).or_default().push(b);
+        neighbours.entry(b).or_default().push(a);
+    }
+
+    let uneven = || "The hole cuts an uneven patch of faces; try another radius or position";
+    if neighbours.values().any(|n| n.len() != 2) {
+        return Err(uneven().to_string());
+    }
+    let &start = neighbours.keys().min().ok_or_else(uneven)?;
+    let mut ring = vec![start];
+    let (mut previous, mut at) = (start, neighbours[&start][0]);
+    while at != start {
+        ring.push(at);
+        let next = neighbours[&at]
+            .iter()
+            .copied()
+            .find(|&n| n != previous)
+            .ok_or_else(uneven)?;
+        (previous, at) = (at, next);
+    }
+    if ring.len() != neighbours.len() {
+        return Err(uneven().to_string());
+    }
+    Ok(ring)
+}
+
+/// Distance along `direction` to where a ray from `origin` meets a triangle
+fn ray_hit(origin: [f32; 3], direction: [f32; 3], [a, b, c]: &[[f32; 3]; 3]) -> Option<f32> {
+    let e1 = sub(*b, *a);
+    let e2 = sub(*c, *a);
+    let p = cross(direction, e2);
+    let det = dot(e1, p);
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    let s = sub(origin, *a);
+    let u = dot(s, p) / det;
+    let q = cross(s, e1);
+    let v = dot(direction, q) / det;
+    if u < 0.0 || v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    Some(dot(e2, q) / det)
+}
+
+/// Positions of `mesh` with each corner moved `distance` along its
+/// angle-weighted normal, far enough that the faces around it move the
+/// full distance
+fn offset_positions(mesh: &MeshData, distance: f32) -> Vec<f32> {
+    let (ids, points) = weld(&mesh.positions);
+    let mut normals = vec![[0.0f32; 3]; points.len()];
+    let welded_faces: Vec<[u32; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [f[0], f[1], f[2]].map(|i| ids[i as usize]))
+        .collect();
+    for face in &welded_faces {
+        let corners = face.map(|id| points[id as usize]);
+        let Some(n) = face_normal(corners) else {
+            continue;
+        };
+        for k in 0..3 {
+            let e1 = sub(corners[(k + 1) % 3], corners[k]);
+            let e2 = sub(corners[(k + 2) % 3], corners[k]);
+            let angle = (dot(e1, e2) / (length(e1) * length(e2)))
+                .clamp(-1.0, 1.0)
+                .acos();
+            let normal = &mut normals[face[k] as usize];
+            *normal = add(*normal, scale(n, angle));
+        }
+    }
+    let normals: Vec<[f32; 3]> = normals
+        .into_iter()
+        .map(|n| scale(n, 1.0 / length(n).max(f32::MIN_POSITIVE)))
+        .collect();
+    let mut cosines = vec![1.0f32; points.len()];
+    for face in &welded_faces {
+        let Some(n) = face_normal(face.map(|id| points[id as usize])) else {
+            continue;
+        };
+        for &id in face {
+            let cosine = &mut cosines[id as usize];
+            *cosine = cosine.min(dot(n, normals[id as usize]));
+        }
+    }
+
+    let mut moved = mesh.positions.clone();
+    for (v, &id) in ids.iter().enumerate() {
+        let id = id as usize;
+        let offset = scale(normals[id], distance / cosines[id].max(MIN_CORNER_COSINE));
+        for (k, d) in offset.iter().enumerate() {
+            moved[v * 3 + k] += d;
+        }
+    }
+    moved
+}
+
+/// `mesh` and a copy at `moved`, with whichever is inside wound the other
+/// way so the solid is the gap between them
+fn shell(mesh: &MeshData, moved: Vec<f32>, moved_inside: bool) -> MeshData {
+    let n = mesh.vertex_count();
+    let order: Vec<usize> = (0..n).chain(0..n).collect();
+    let faces = |base: u32, reversed: bool| {
+        mesh.indices.chunks_exact(3).flat_map(move |f| {
+            let f = if reversed {
+                [f[0], f[2], f[1]]
+            } else {
+                [f[0], f[1], f[2]]
+            };
+            f.map(|i| i + base)
+        })
+    };
+    let indices = faces(0, !moved_inside)
+        .chain(faces(n as u32, moved_inside))
+        .collect();
+    let mut shelled = mesh.with_vertices(&order, indices);
+    shelled.positions[n * 3..].copy_from_slice(&moved);
+    if let Some(normals) = &mut shelled.normals {
+        let inner = if moved_inside {
+            &mut normals[n * 3..]
+        } else {
+            &mut normals[..n * 3]
+        };
+        inner.iter_mut().for_each(|c| *c = -*c);
+    }
+    shelled
+}
+
+fn weld(positions: &[f32]) -> (Vec<u32>, Vec<[f32; 3]>) {
+    let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+    let mut points: Vec<[f32; 3]> = Vec::new();
+    let ids = positions
+        .chunks_exact(3)
+        .map(|p| {
+            let next = welded.len() as u32;
+            *welded
+                .entry([p[0], p[1], p[2]].map(f32::to_bits))
+                .or_insert_with(|| {
+                    points.push([p[0], p[1], p[2]]);
+                    next
+                })
+        })
+        .collect();
+    (ids, points)
+}
+
+/// Unit normal following the winding, `None` for a face with no area
+fn face_normal([a, b, c]: [[f32; 3]; 3]) -> Option<[f32; 3]> {
+    let n = cross(sub(b, a), sub(c, a));
+    let len = length(n);
+    (len > 0.0).then(|| scale(n, 1.0 / len))
+}
+
+/// Unit axes perpendicular to a unit direction and each other
+fn perpendicular(d: [f32; 3]) -> ([f32; 3], [f32; 3]) {
+    let helper = if d[0].abs() > 0.9 {
+        [0.0, 1.0, 0.0]
+    } else {
+        [1.0, 0.0, 0.0]
+    };
+    let u = cross(d, helper);
+    let u = scale(u, 1.0 / length(u));
+    (u, cross(d, u))
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    a.map(|c| c * s)
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube() -> MeshData {
+        MeshData {
+            positions: (0..8)
+                .flat_map(|i| [i & 1, (i >> 1) & 1, (i >> 2) & 1].map(|c| c as f32))
+                .collect(),
+            indices: vec![
+                0, 2, 1, 1, 2, 3, 4, 5, 6, 5, 7, 6, 0, 1, 4, 1, 5, 4, 2, 6, 3, 3, 6, 7, 0, 4, 2, 2,
+                4, 6, 1, 3, 5, 3, 7, 5,
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_hollow_and_drill_cube() {
+        let cube = cube();
+
+        // Corners move diagonally, so the inner wall is a cube 0.8 across
+        let hollowed = hollow(&cube, 0.1).unwrap();
+        assert_eq!(hollowed.face_count(), 24);
+        let volume = simd::signed_volume(&hollowed.positions, &hollowed.indices).abs();
+        assert!((volume - (1.0 - 0.8f32.powi(3))).abs() < 1e-4);
+        assert!(hollow(&cube, 0.5).is_err());
+
+        // Holes through the top and bottom join both walls into a torus
+        let holes = [[0.7, 1.2, 0.4], [0.3, -0.2, 0.6]];
+        let drilled = drill_drain_holes(&hollowed, &holes, 0.05).unwrap();
+        let topology = topology::analyze(&drilled.positions, &drilled.indices);
+        assert_eq!(topology.shell_count, 1);
+        assert_eq!(topology.boundary_loop_count, 0);
+        assert!(topology.is_manifold());
+        assert_eq!(topology.shells[0].genus, Some(1));
+
+        assert!(drill_drain_holes(&cube, &[[0.7, 1.2, 0.4]], 0.05).is_err());
+    }
+
+    #[test]
+    fn test_offset_moves_out_of_the_solid_either_winding() {
+        let cube = cube();
+        let mut reversed = cube.clone();
+        reversed
+            .indices
+            .chunks_exact_mut(3)
+            .for_each(|f| f.swap(1, 2));
+
+        for mesh in [&cube, &reversed] {
+            let grown = offset(mesh, 0.1, OffsetMode::Surface).unwrap();
+            let volume = simd::signed_volume(&grown.positions, &grown.indices).abs();
+            assert!((volume - 1.2f32.powi(3)).abs() < 1e-4, "{}", volume);
+        }
+
+        // An outward shell is the gap between the original and a larger cube
+        let shelled = offset(&cube, 0.1, OffsetMode::Shell).unwrap();
+        assert_eq!(shelled.face_count(), 24);
+        let topology = topology::analyze(&shelled.positions, &shelled.indices);
+        assert_eq!(topology.shell_count, 2);
+        let volume = simd::signed_volume(&shelled.positions, &shelled.indices).abs();
+        assert!((volume - (1.2f32.powi(3) - 1.0)).abs() < 1e-4, "{}", volume);
+
+        let shrunk = offset(&cube, -0.1, OffsetMode::Surface).unwrap();
+        let volume = simd::signed_volume(&shrunk.positions, &shrunk.indices).abs();
+        assert!((volume - 0.8f32.powi(3)).abs() < 1e-4, "{}", volume);
+        assert!(offset(&cube, -0.6, OffsetMode::Surface).is_err());
+        assert!(offset(&cube, 0.0, OffsetMode::Surface).is_err());
+    }
+}
@@ -0,0 +1,492 @@
+//! Messages shown to the user in the language picked in the settings
+//!
+//! The Rust side builds its messages in English with `format!`. The catalog
+//! pairs those English templates with their translations, and `localize`
+//! matches a finished message against them and renders it in the current
+//! locale; the arguments are translated too when they are catalog messages
+//! themselves, as the underlying error at the end of a message often is.
+//! Messages missing from the catalog stay in English, so a new message needs
+//! no translation to ship.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Language of the messages and of number formatting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+    Es,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 4] = [Locale::En, Locale::De, Locale::Fr, Locale::Es];
+
+    /// `1234567` as `1,234,567`, `1.234.567` or `1 234 567`
+    pub fn integer(self, value: u64) -> String {
+        let separator = match self {
+            Locale::En => ',',
+            Locale::De | Locale::Es => '.',
+            // Narrow no-break space, as French typography groups digits
+            Locale::Fr => '\u{202f}',
+        };
+        let digits = value.to_string();
+        let mut out = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push(separator);
+            }
+            out.push(digit);
+        }
+        out
+    }
+
+    /// `value` rounded to `places` decimals, with the locale's decimal mark
+    pub fn decimal(self, value: f64, places: usize) -> String {
+        let out = format!("{:.*}", places, value);
+        match self {
+            Locale::En => out,
+            Locale::De | Locale::Fr | Locale::Es => out.replace('.', ","),
+        }
+    }
+
+    /// `bytes` in binary units, such as `3.0 MB`
+    pub fn file_size(self, bytes: u64) -> String {
+        const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+        let byte = match self {
+            Locale::Fr => "o",
+            Locale::En | Locale::De | Locale::Es => "B",
+        };
+        if bytes < 1024 {
+            return format!("{} {}", bytes, byte);
+        }
+        let mut size = bytes as f64 / 1024.0;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        let unit = match self {
+            Locale::Fr => UNITS[unit].replace('B', "o"),
+            Locale::En | Locale::De | Locale::Es => UNITS[unit].to_string(),
+        };
+        format!("{} {}", self.decimal(size, 1), unit)
+    }
+
+    fn column(self) -> usize {
+        self as usize
+    }
+}
+
+static LOCALE: AtomicU8 = AtomicU8::new(Locale::En as u8);
+
+/// The locale messages are shown in, as last set from the settings
+pub fn locale() -> Locale {
+    Locale::ALL[LOCALE.load(Ordering::Relaxed) as usize]
+}
+
+pub fn set_locale(locale: Locale) {
+    LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+/// `message` in the current locale
+pub fn localize(message: &str) -> String {
+    localize_in(locale(), message)
+}
+
+/// `message` in `locale`, or unchanged if the catalog doesn't know it
+pub fn localize_in(locale: Locale, message: &str) -> String {
+    if locale == Locale::En {
+        return message.to_string();
+    }
+    translate(locale, message).unwrap_or_else(|| message.to_string())
+}
+
+fn translate(locale: Locale, message: &str) -> Option<String> {
+    CATALOG.iter().find_map(|entry| {
+        let args = match_template(entry[0], message)?;
+        let mut out = entry[locale.column()].to_string();
+        for (i, arg) in args.iter().enumerate() {
+            let arg = translate(locale, arg).unwrap_or_else(|| arg.to_string());
+            out = out.replace(&format!("{{{}}}", i), &arg);
+        }
+        Some(out)
+    })
+}
+
+/// The arguments that fill the `{}` placeholders of `template` to give
+/// `message`; each but the last is as short as it can be
+fn match_template<'a>(template: &str, message: &'a str) -> Option<Vec<&'a str>> {
+    let mut literals = template.split("{}");
+    let first = literals.next()?;
+    let mut rest = message.strip_prefix(first)?;
+    let literals: Vec<&str> = literals.collect();
+    let Some((last, middle)) = literals.split_last() else {
+        return (rest.is_empty()).then(Vec::new);
+    };
+    let mut args = Vec::new();
+    for literal in middle {
+        let end = rest.find(literal)?;
+        args.push(&rest[..end]);
+        rest = &rest[end + literal.len()..];
+    }
+    args.push(rest.strip_suffix(last)?);
+    args.iter().all(|arg| !arg.is_empty()).then_some(args)
+}
+
+/// English, German, French and Spanish; the English is the `format!`
+/// template as written in the code, the translations number its arguments,
+/// as their order may differ. More specific templates come first, since the
+/// first that matches wins.
+const CATALOG: &[[&str; 4]] = &[
+    // Files
+    [
+        "Failed to open file: {}",
+        "Datei konnte nicht geöffnet werden: {0}",
+        "Impossible d'ouvrir le fichier : {0}",
+        "No se pudo abrir el archivo: {0}",
+    ],
+    [
+        "Failed to mmap file: {}",
+        "Datei konnte nicht in den Speicher abgebildet werden: {0}",
+        "Impossible de projeter le fichier en mémoire : {0}",
+        "No se pudo mapear el archivo en memoria: {0}",
+    ],
+    [
+        "Failed to read file: {}",
+        "Datei konnte nicht gelesen werden: {0}",
+        "Impossible de lire le fichier : {0}",
+        "No se pudo leer el archivo: {0}",
+    ],
+    [
+        "Failed to read {}: {}",
+        "{0} konnte nicht gelesen werden: {1}",
+        "Impossible de lire {0} : {1}",
+        "No se pudo leer {0}: {1}",
+    ],
+    [
+        "Failed to write {}: {}",
+        "{0} konnte nicht geschrieben werden: {1}",
+        "Impossible d'écrire {0} : {1}",
+        "No se pudo escribir {0}: {1}",
+    ],
+    [
+        "Failed to create {}: {}",
+        "{0} konnte nicht erstellt werden: {1}",
+        "Impossible de créer {0} : {1}",
+        "No se pudo crear {0}: {1}",
+    ],
+    [
+        "Failed to open {}: {}",
+        "{0} konnte nicht geöffnet werden: {1}",
+        "Impossible d'ouvrir {0} : {1}",
+        "No se pudo abrir {0}: {1}",
+    ],
+    [
+        "Failed to load {}: {}",
+        "{0} konnte nicht geladen werden: {1}",
+        "Impossible de charger {0} : {1}",
+        "No se pudo cargar {0}: {1}",
+    ],
+    [
+        "Failed to replace {}: {}",
+        "{0} konnte nicht ersetzt werden: {1}",
+        "Impossible de remplacer {0} : {1}",
+        "No se pudo reemplazar {0}: {1}",
+    ],
+    [
+        "Failed to delete {}: {}",
+        "{0} konnte nicht gelöscht werden: {1}",
+        "Impossible de supprimer {0} : {1}",
+        "No se pudo eliminar {0}: {1}",
+    ],
+    [
+        "Failed to move {} to {}: {}",
+        "{0} konnte nicht nach {1} verschoben werden: {2}",
+        "Impossible de déplacer {0} vers {1} : {2}",
+        "No se pudo mover {0} a {1}: {2}",
+    ],
+    [
+        "Failed to run {}: {}",
+        "{0} konnte nicht ausgeführt werden: {1}",
+        "Impossible d'exécuter {0} : {1}",
+        "No se pudo ejecutar {0}: {1}",
+    ],
+    [
+        "Failed to encode {}: {}",
+        "{0} konnte nicht kodiert werden: {1}",
+        "Impossible d'encoder {0} : {1}",
+        "No se pudo codificar {0}: {1}",
+    ],
+    [
+        "File not found: {}",
+        "Datei nicht gefunden: {0}",
+        "Fichier introuvable : {0}",
+        "Archivo no encontrado: {0}",
+    ],
+    [
+        "Not a file: {}",
+        "Keine Datei: {0}",
+        "Ce n'est pas un fichier : {0}",
+        "No es un archivo: {0}",
+    ],
+    [
+        "Not a directory: {}",
+        "Kein Ordner: {0}",
+        "Ce n'est pas un dossier : {0}",
+        "No es una carpeta: {0}",
+    ],
+    [
+        "Path must be absolute: {}",
+        "Der Pfad muss absolut sein: {0}",
+        "Le chemin doit être absolu : {0}",
+        "La ruta debe ser absoluta: {0}",
+    ],
+    [
+        "Invalid path: {}",
+        "Ungültiger Pfad: {0}",
+        "Chemin non valide : {0}",
+        "Ruta no válida: {0}",
+    ],
+    [
+        "Access denied: {} is outside the approved folders",
+        "Zugriff verweigert: {0} liegt außerhalb der freigegebenen Ordner",
+        "Accès refusé : {0} est en dehors des dossiers autorisés",
+        "Acceso denegado: {0} está fuera de las carpetas autorizadas",
+    ],
+    [
+        "Read-only mode is enabled; run with dry_run to preview the changes",
+        "Der Nur-Lesen-Modus ist aktiv; mit dry_run lassen sich die Änderungen vorab ansehen",
+        "Le mode lecture seule est activé ; lancez avec dry_run pour prévisualiser les modifications",
+        "El modo de solo lectura está activado; ejecute con dry_run para ver los cambios",
+    ],
+    [
+        "{} was changed by another program or Sweedle instance while this ran; \
+         run it again to replace it",
+        "{0} wurde währenddessen von einem anderen Programm oder einer anderen \
+         Sweedle-Instanz geändert; zum Ersetzen erneut ausführen",
+        "{0} a été modifié par un autre programme ou une autre instance de Sweedle \
+         pendant l'exécution ; relancez pour le remplacer",
+        "{0} fue modificado por otro programa u otra instancia de Sweedle durante la \
+         ejecución; vuelva a ejecutarlo para reemplazarlo",
+    ],
+    [
+        "{} is being written by another Sweedle instance ({}, process {}); \
+         try again when it's done",
+        "{0} wird gerade von einer anderen Sweedle-Instanz geschrieben ({1}, Prozess {2}); \
+         danach erneut versuchen",
+        "{0} est en cours d'écriture par une autre instance de Sweedle ({1}, processus {2}) ; \
+         réessayez ensuite",
+        "{0} está siendo escrito por otra instancia de Sweedle ({1}, proceso {2}); \
+         inténtelo de nuevo cuando termine",
+    ],
+    [
+        "{} is being written by another Sweedle instance; try again when it's done",
+        "{0} wird gerade von einer anderen Sweedle-Instanz geschrieben; danach erneut versuchen",
+        "{0} est en cours d'écriture par une autre instance de Sweedle ; réessayez ensuite",
+        "{0} está siendo escrito por otra instancia de Sweedle; inténtelo de nuevo cuando termine",
+    ],
+    // Models
+    [
+        "Failed to parse GLTF: {}",
+        "GLTF konnte nicht gelesen werden: {0}",
+        "Impossible d'analyser le GLTF : {0}",
+        "No se pudo analizar el GLTF: {0}",
+    ],
+    [
+        "Failed to parse glTF JSON: {}",
+        "glTF-JSON konnte nicht gelesen werden: {0}",
+        "Impossible d'analyser le JSON glTF : {0}",
+        "No se pudo analizar el JSON de glTF: {0}",
+    ],
+    [
+        "Failed to serialize glTF: {}",
+        "glTF konnte nicht geschrieben werden: {0}",
+        "Impossible de sérialiser le glTF : {0}",
+        "No se pudo serializar el glTF: {0}",
+    ],
+    [
+        "No triangle meshes found in {}",
+        "Keine Dreiecksnetze in {0} gefunden",
+        "Aucun maillage triangulaire trouvé dans {0}",
+        "No se encontraron mallas de triángulos en {0}",
+    ],
+    // Library and jobs
+    [
+        "Unknown storage root: {}",
+        "Unbekannter Speicherort: {0}",
+        "Emplacement de stockage inconnu : {0}",
+        "Ubicación de almacenamiento desconocida: {0}",
+    ],
+    [
+        "Storage path is not a directory: {}",
+        "Der Speicherpfad ist kein Ordner: {0}",
+        "Le chemin de stockage n'est pas un dossier : {0}",
+        "La ruta de almacenamiento no es una carpeta: {0}",
+    ],
+    [
+        "No asset {} in the library",
+        "Kein Asset {0} in der Bibliothek",
+        "Aucune ressource {0} dans la bibliothèque",
+        "No hay ningún recurso {0} en la biblioteca",
+    ],
+    [
+        "No job with id {}",
+        "Kein Auftrag mit der ID {0}",
+        "Aucune tâche avec l'identifiant {0}",
+        "No hay ninguna tarea con el id {0}",
+    ],
+    [
+        "Job {} was not interrupted",
+        "Auftrag {0} wurde nicht unterbrochen",
+        "La tâche {0} n'a pas été interrompue",
+        "La tarea {0} no fue interrumpida",
+    ],
+    [
+        "Jobs at once must be between 1 and {}",
+        "Gleichzeitige Aufträge müssen zwischen 1 und {0} liegen",
+        "Le nombre de tâches simultanées doit être compris entre 1 et {0}",
+        "Las tareas simultáneas deben estar entre 1 y {0}",
+    ],
+    [
+        "Job panicked",
+        "Der Auftrag ist abgestürzt",
+        "La tâche a planté",
+        "La tarea falló de forma inesperada",
+    ],
+    [
+        "Report failed: {}",
+        "Bericht fehlgeschlagen: {0}",
+        "Échec du rapport : {0}",
+        "Falló el informe: {0}",
+    ],
+    [
+        "Sweedle: {} job failed",
+        "Sweedle: Auftrag {0} fehlgeschlagen",
+        "Sweedle : échec de la tâche {0}",
+        "Sweedle: falló la tarea {0}",
+    ],
+    [
+        "Sweedle: {} job finished",
+        "Sweedle: Auftrag {0} abgeschlossen",
+        "Sweedle : tâche {0} terminée",
+        "Sweedle: tarea {0} terminada",
+    ],
+    // Reports
+    ["Property", "Eigenschaft", "Propriété", "Propiedad"],
+    ["Value", "Wert", "Valeur", "Valor"],
+    ["File", "Datei", "Fichier", "Archivo"],
+    ["Formats", "Formate", "Formats", "Formatos"],
+    ["Error", "Fehler", "Erreur", "Error"],
+    ["Size", "Größe", "Taille", "Tamaño"],
+    ["Vertices", "Eckpunkte", "Sommets", "Vértices"],
+    ["Faces", "Flächen", "Faces", "Caras"],
+    ["Meshes", "Meshes", "Maillages", "Mallas"],
+    ["Materials", "Materialien", "Matériaux", "Materiales"],
+    ["Textures", "Texturen", "Textures", "Texturas"],
+    ["Dimensions", "Abmessungen", "Dimensions", "Dimensiones"],
+    ["Profile", "Profil", "Profil", "Perfil"],
+    ["Color spaces", "Farbräume", "Espaces colorimétriques", "Espacios de color"],
+    ["Checks", "Prüfungen", "Contrôles", "Comprobaciones"],
+    ["yes", "ja", "oui", "sí"],
+    ["no", "nein", "non", "no"],
+    ["within limits", "innerhalb der Grenzen", "dans les limites", "dentro de los límites"],
+    [
+        "{}% (failed: {})",
+        "{0} % (nicht bestanden: {1})",
+        "{0} % (échecs : {1})",
+        "{0} % (fallidas: {1})",
+    ],
+    ["{}%", "{0} %", "{0} %", "{0} %"],
+    ["parses", "lesbar", "lisible", "legible"],
+    ["geometry", "Geometrie", "géométrie", "geometría"],
+    ["normals", "Normalen", "normales", "normales"],
+    ["UVs", "UVs", "UV", "UV"],
+    [
+        "no degenerate faces",
+        "keine degenerierten Flächen",
+        "aucune face dégénérée",
+        "sin caras degeneradas",
+    ],
+    ["profile", "Profil", "profil", "perfil"],
+    ["color spaces", "Farbräume", "espaces colorimétriques", "espacios de color"],
+    // Validation profiles
+    [
+        "{}: {} (limit {})",
+        "{0}: {1} (Grenze {2})",
+        "{0} : {1} (limite {2})",
+        "{0}: {1} (límite {2})",
+    ],
+    ["faces", "Flächen", "faces", "caras"],
+    ["vertices", "Eckpunkte", "sommets", "vértices"],
+    ["meshes", "Meshes", "maillages", "mallas"],
+    ["materials", "Materialien", "matériaux", "materiales"],
+    ["texture size", "Texturgröße", "taille de texture", "tamaño de textura"],
+    [
+        "file size in bytes",
+        "Dateigröße in Bytes",
+        "taille du fichier en octets",
+        "tamaño del archivo en bytes",
+    ],
+    ["no normals", "keine Normalen", "pas de normales", "sin normales"],
+    ["no UVs", "keine UVs", "pas d'UV", "sin UV"],
+    // Mobile
+    [
+        "{} is not available on mobile",
+        "{0} ist auf Mobilgeräten nicht verfügbar",
+        "{0} n'est pas disponible sur mobile",
+        "{0} no está disponible en dispositivos móviles",
+    ],
+    ["The local API", "Die lokale API", "L'API locale", "La API local"],
+    [
+        "Material preview rendering",
+        "Das Rendern von Materialvorschauen",
+        "Le rendu des aperçus de matériaux",
+        "El renderizado de vistas previas de materiales",
+    ],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localize_matches_templates_and_formats_numbers() {
+        let message = "Failed to read /lib/rock.glb: File not found: /lib/rock.glb";
+        assert_eq!(localize_in(Locale::En, message), message);
+        assert_eq!(
+            localize_in(Locale::De, message),
+            "/lib/rock.glb konnte nicht gelesen werden: Datei nicht gefunden: /lib/rock.glb"
+        );
+        assert_eq!(
+            localize_in(Locale::Fr, "Failed to move a.glb to b.glb: denied"),
+            "Impossible de déplacer a.glb vers b.glb : denied"
+        );
+        assert_eq!(
+            localize_in(Locale::Es, "faces: 12 (limit 10)"),
+            "caras: 12 (límite 10)"
+        );
+        assert_eq!(localize_in(Locale::De, "Something new"), "Something new");
+
+        for entry in CATALOG {
+            let placeholders = entry[0].matches("{}").count();
+            for translation in &entry[1..] {
+                for i in 0..placeholders {
+                    assert!(
+                        translation.contains(&format!("{{{}}}", i)),
+                        "{}",
+                        translation
+                    );
+                }
+            }
+        }
+
+        assert_eq!(Locale::En.integer(1_234_567), "1,234,567");
+        assert_eq!(Locale::De.integer(1_234_567), "1.234.567");
+        assert_eq!(Locale::De.file_size(3 << 20), "3,0 MB");
+        assert_eq!(Locale::Fr.file_size(512), "512 o");
+    }
+}
@@ -0,0 +1,140 @@
+//! Thumbnails of texture and HDRI files
+//!
+//! Loose textures and environment maps in the library get a preview like
+//! models do. Float images (EXR and Radiance HDR) are exposed for their
+//! average brightness and tonemapped, since clamping their values shows most
+//! HDRIs as a white field.
+
+use crate::utils::asset_cache::{AssetCache, CacheKind};
+use image::{DynamicImage, ImageFormat, ImageReader, Rgba, Rgba32FImage, RgbaImage};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// Image formats stored in the library as texture assets
+pub const IMAGE_EXTENSIONS: [&str; 10] = [
+    "png", "jpg", "jpeg", "tga", "tif", "tiff", "bmp", "webp", "exr", "hdr",
+];
+/// Longest side of generated thumbnails
+pub const THUMBNAIL_SIZE: u32 = 256;
+/// Brightness the average pixel of a float image is exposed to
+const KEY_VALUE: f32 = 0.18;
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.as_str()))
+}
+
+/// A PNG of the image at `path` scaled to fit in `size` by `size`
+pub fn thumbnail_png(path: &Path, size: u32) -> Result<Vec<u8>, String> {
+    let image = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?
+        .decode()
+        .map_err(|e| format!("Failed to decode {}: {}", path.display(), e))?;
+    let small = image.thumbnail(size, size);
+    let rgba = match small {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            tonemap(&small.to_rgba32f())
+        }
+        other => other.to_rgba8(),
+    };
+    let mut png = Cursor::new(Vec::new());
+    rgba.write_to(&mut png, ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode thumbnail of {}: {}", path.display(), e))?;
+    Ok(png.into_inner())
+}
+
+/// The thumbnail of `image` in the cache, made first if it isn't there
+///
+/// `None` if the cache has no folder or the image is gone.
+pub fn cached(cache: &AssetCache, image: &Path, max_bytes: u64) -> Result<Option<PathBuf>, String> {
+    let Some(key) = AssetCache::source_key(image, &THUMBNAIL_SIZE.to_string()) else {
+        return Ok(None);
+    };
+    let name = format!("{}.png", key);
+    if let Some(path) = cache.locate(CacheKind::Thumbnail, &name) {
+        return Ok(Some(path));
+    }
+    let png = thumbnail_png(image, THUMBNAIL_SIZE)?;
+    cache.put(CacheKind::Thumbnail, &name, &png, max_bytes)?;
+    Ok(cache.locate(CacheKind::Thumbnail, &name))
+}
+
+/// Whether the cache lacks a thumbnail of `image` as it is now
+pub fn is_stale(cache: &AssetCache, image: &Path) -> bool {
+    AssetCache::source_key(image, &THUMBNAIL_SIZE.to_string()).is_some_and(|key| {
+        cache
+            .locate(CacheKind::Thumbnail, &format!("{}.png", key))
+            .is_none()
+    })
+}
+
+/// Map linear high dynamic range colors to sRGB with Reinhard's operator,
+/// exposed so the log-average luminance lands on `KEY_VALUE`
+pub fn tonemap(image: &Rgba32FImage) -> RgbaImage {
+    let luminance = |p: &Rgba<f32>| {
+        let [r, g, b, _] = p.0.map(radiance);
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    };
+    let count = (image.width() * image.height()).max(1) as f32;
+    let log_sum: f32 = image.pixels().map(|p| (1e-4 + luminance(p)).ln()).sum();
+    let exposure = KEY_VALUE / (log_sum / count).exp();
+
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let pixel = image.get_pixel(x, y);
+        let mut out = [0u8; 4];
+        for (channel, value) in out.iter_mut().zip(pixel.0).take(3) {
+            let value = radiance(value) * exposure;
+            *channel = to_srgb8(value / (1.0 + value));
+        }
+        out[3] = (pixel.0[3].clamp(0.0, 1.0) * 255.0).round() as u8;
+        Rgba(out)
+    })
+}
+
+/// A channel value with negatives, infinities and NaNs, which some
+/// renderers leave in their output, as black
+fn radiance(value: f32) -> f32 {
+    if value.is_finite() {
+        value.max(0.0)
+    } else {
+        0.0
+    }
+}
+
+fn to_srgb8(linear: f32) -> u8 {
+    let encoded = if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb32FImage;
+
+    #[test]
+    fn test_hdr_thumbnail_is_tonemapped() {
+        // A sky a hundred times brighter than the ground, far outside 0..1
+        let hdr = Rgb32FImage::from_fn(64, 32, |_, y| {
+            let value = if y < 16 { 400.0 } else { 4.0 };
+            image::Rgb([value, value, value])
+        });
+        let path = std::env::temp_dir().join(format!("sweedle-hdri-{}.hdr", std::process::id()));
+        DynamicImage::ImageRgb32F(hdr).save(&path).unwrap();
+        assert!(is_image(&path));
+
+        let png = thumbnail_png(&path, 16).unwrap();
+        let thumbnail = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(thumbnail.dimensions(), (16, 8));
+        let sky = thumbnail.get_pixel(0, 0).0[0];
+        let ground = thumbnail.get_pixel(0, 7).0[0];
+        assert!(sky < 255 && ground > 0 && sky > ground);
+        assert_eq!(thumbnail.get_pixel(0, 0).0[3], 255);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+//! Finding repeated meshes to write as GPU instances
+//!
+//! Scattered vegetation and props repeat the same mesh under many nodes.
+//! Flattening writes a world-space copy for each one; instancing writes
+//! the mesh once with a translation, rotation and scale per copy in
+//! `EXT_mesh_gpu_instancing`. Meshes count as the same when their local
+//! geometry is identical, whether the nodes share a glTF mesh or not.
+//! Transforms that aren't a plain rotation and scale (mirrored or sheared)
+//! can't be expressed as instances and are baked like a flattened copy.
+
+use crate::utils::gltf_geometry::PrimitiveGeometry;
+use crate::utils::mesh_files::NamedMesh;
+use nalgebra::{Matrix3, Matrix4, Point3, Rotation3, UnitQuaternion};
+use std::collections::HashMap;
+use xxhash_rust::xxh3::Xxh3;
+
+pub const INSTANCING_EXTENSION: &str = "EXT_mesh_gpu_instancing";
+/// Largest deviation from orthonormal accepted for a rotation
+const ROTATION_TOLERANCE: f32 = 1e-3;
+
+/// Placement of one instance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trs {
+    pub translation: [f32; 3],
+    /// Unit quaternion as `[x, y, z, w]`
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+/// A mesh in its local space with every placement of it
+#[derive(Debug, Clone)]
+pub struct InstancedMesh {
+    pub mesh: NamedMesh,
+    pub instances: Vec<Trs>,
+}
+
+/// A scene split into instanced meshes and world-space copies
+#[derive(Debug, Clone, Default)]
+pub struct Instancing {
+    pub instanced: Vec<InstancedMesh>,
+    pub baked: Vec<NamedMesh>,
+}
+
+/// One node's mesh before grouping
+struct Placement {
+    name: String,
+    positions: Vec<f32>,
+    indices: Vec<u32>,
+    transform: [[f32; 4]; 4],
+}
+
+/// Group decoded primitives into meshes repeated at least `min_instances`
+/// times and everything else; `name` names a glTF mesh by index
+pub fn find_instances(
+    primitives: &[PrimitiveGeometry],
+    name: impl Fn(usize) -> String,
+    min_instances: usize,
+) -> Instancing {
+    // Primitives of one mesh under one node make up one placement
+    let mut placements: Vec<Placement> = Vec::new();
+    let mut by_instance: HashMap<(Option<usize>, usize), usize> = HashMap::new();
+    for primitive in primitives {
+        let index = *by_instance
+            .entry((primitive.node_index, primitive.mesh_index))
+            .or_insert_with(|| {
+                placements.push(Placement {
+                    name: name(primitive.mesh_index),
+                    positions: Vec::new(),
+                    indices: Vec::new(),
+                    transform: primitive.transform,
+                });
+                placements.len() - 1
+            });
+        let placement = &mut placements[index];
+        let base = (placement.positions.len() / 3) as u32;
+        placement.positions.extend_from_slice(&primitive.positions);
+        placement
+            .indices
+            .extend(primitive.indices.iter().map(|i| i + base));
+    }
+
+    // Placements with the same geometry, in order of first appearance
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, placement) in placements.iter().enumerate() {
+        let candidates = by_hash.entry(geometry_hash(placement)).or_default();
+        let same = candidates.iter().copied().find(|&g| {
+            let first = &placements[groups[g][0]];
+            first.positions == placement.positions && first.indices == placement.indices
+        });
+        match same {
+            Some(g) => groups[g].push(i),
+            None => {
+                candidates.push(groups.len());
+                groups.push(vec![i]);
+            }
+        }
+    }
+
+    let mut result = Instancing::default();
+    for group in groups {
+        let (instances, baked): (Vec<_>, Vec<_>) = group
+            .iter()
+            .map(|&i| (i, decompose(placements[i].transform)))
+            .partition(|(_, trs)| trs.is_some());
+        let (instances, baked) = if instances.len() >= min_instances.max(2) {
+            (instances, baked)
+        } else {
+            (Vec::new(), group.iter().map(|&i| (i, None)).collect())
+        };
+        if let Some(&(first, _)) = instances.first() {
+            let placement = &placements[first];
+            result.instanced.push(InstancedMesh {
+                mesh: NamedMesh {
+                    name: placement.name.clone(),
+                    positions: placement.positions.clone(),
+                    indices: placement.indices.clone(),
+                    polygons: None,
+                },
+                instances: instances.iter().filter_map(|(_, trs)| *trs).collect(),
+            });
+        }
+        for (i, _) in baked {
+            let placement = &placements[i];
+            result.baked.push(NamedMesh {
+                name: placement.name.clone(),
+                positions: world_positions(&placement.positions, placement.transform),
+                indices: placement.indices.clone(),
+                polygons: None,
+            });
+        }
+    }
+    result
+}
+
+/// Split an affine transform into translation, rotation and scale, or
+/// `None` if it mirrors or shears
+pub fn decompose(transform: [[f32; 4]; 4]) -> Option<Trs> {
+    let matrix = Matrix4::from(transform);
+    let linear: Matrix3<f32> = matrix.fixed_view::<3, 3>(0, 0).into_owned();
+    let scale = [0, 1, 2].map(|axis| linear.column(axis).norm());
+    if scale.iter().any(|&s| s <= f32::EPSILON) {
+        return None;
+    }
+    let mut rotation = linear;
+    for (axis, s) in scale.iter().enumerate() {
+        rotation.column_mut(axis).unscale_mut(*s);
+    }
+    let deviation = (rotation.transpose() * rotation - Matrix3::identity()).amax();
+    if rotation.determinant() <= 0.0 || deviation > ROTATION_TOLERANCE {
+        return None;
+    }
+    let rotation =
+        UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(rotation));
+    let t = matrix.column(3);
+    Some(Trs {
+        translation: [t[0], t[1], t[2]],
+        rotation: [rotation.i, rotation.j, rotation.k, rotation.w],
+        scale,
+    })
+}
+
+fn geometry_hash(placement: &Placement) -> u64 {
+    let mut hasher = Xxh3::new();
+    for p in &placement.positions {
+        hasher.update(&p.to_le_bytes());
+    }
+    for i in &placement.indices {
+        hasher.update(&i.to_le_bytes());
+    }
+    hasher.digest()
+}
+
+fn world_positions(positions: &[f32], transform: [[f32; 4]; 4]) -> Vec<f32> {
+    let matrix = Matrix4::from(transform);
+    positions
+        .chunks_exact(3)
+        .flat_map(|p| {
+            let w = matrix.transform_point(&Point3::new(p[0], p[1], p[2]));
+            [w.x, w.y, w.z]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placed(mesh_index: usize, node: usize, transform: Matrix4<f32>) -> PrimitiveGeometry {
+        PrimitiveGeometry {
+            mesh_index,
+            primitive_index: 0,
+            node_index: Some(node),
+            material_index: None,
+            transform: transform.into(),
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            normals: None,
+            uvs: None,
+            lightmap_uvs: None,
+            colors: None,
+            indices: vec![0, 1, 2],
+        }
+    }
+
+    #[test]
+    fn test_repeated_meshes_become_instances() {
+        let turn = Matrix4::from_euler_angles(0.0, 1.0, 0.0);
+        let primitives = vec![
+            placed(0, 0, Matrix4::new_translation(&[1.0, 0.0, 0.0].into())),
+            placed(
+                0,
+                1,
+                Matrix4::new_translation(&[2.0, 0.0, 0.0].into()) * turn,
+            ),
+            // A separate glTF mesh with the same triangle counts as a copy
+            placed(1, 2, Matrix4::new_scaling(3.0)),
+            // Mirrored, so it can't be an instance
+            placed(
+                0,
+                3,
+                Matrix4::new_nonuniform_scaling(&[-1.0, 1.0, 1.0].into()),
+            ),
+        ];
+        let result = find_instances(&primitives, |i| format!("mesh {}", i), 2);
+
+        assert_eq!(result.instanced.len(), 1);
+        let instances = &result.instanced[0].instances;
+        assert_eq!(instances.len(), 3);
+        assert_eq!(instances[0].translation, [1.0, 0.0, 0.0]);
+        assert_eq!(instances[2].scale, [3.0, 3.0, 3.0]);
+        let rotation = UnitQuaternion::from_euler_angles(0.0, 1.0, 0.0);
+        let q = instances[1].rotation;
+        assert!((q[1] - rotation.j).abs() < 1e-5 && (q[3] - rotation.w).abs() < 1e-5);
+
+        assert_eq!(result.baked.len(), 1);
+        assert_eq!(result.baked[0].positions[3], -1.0);
+
+        // Too few copies to be worth instancing
+        let result = find_instances(&primitives, |i| format!("mesh {}", i), 4);
+        assert!(result.instanced.is_empty());
+        assert_eq!(result.baked.len(), 4);
+    }
+}
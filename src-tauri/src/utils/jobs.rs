@@ -0,0 +1,525 @@
+//! Background job queue
+//!
+//! Work that nobody is waiting on, such as processing files that appear in
+//! a watched folder, is queued here and run on worker threads, a few jobs
+//! at a time at most, so a burst of new files doesn't saturate the machine.
+//! How many run at once can be changed while the queue runs, which the app
+//! does as the power state changes (see [`crate::utils::power`]). Every state
+//! change is passed to an observer, which the app turns into events for the
+//! frontend. Finished jobs are kept for a while so they can be listed.
+//!
+//! The queue is saved on every change, so after a crash or a forced quit
+//! the jobs that were still queued or running come back as interrupted,
+//! and those that know how to start again can be resumed.
+
+use crate::utils::i18n;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Finished jobs kept for listing; older ones are forgotten
+const MAX_FINISHED_JOBS: usize = 200;
+
+/// The work of a job, returning a summary of what it did
+pub type JobWork = Box<dyn FnOnce() -> Result<serde_json::Value, String> + Send>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    /// The app closed before the job finished
+    Interrupted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    /// What sort of job this is, such as `watch_rule`
+    pub kind: String,
+    /// What it works on, usually a path
+    pub label: String,
+    pub state: JobState,
+    pub queued_ms: u64,
+    pub started_ms: Option<u64>,
+    pub finished_ms: Option<u64>,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    /// What it takes to run the job again after a restart; jobs without it
+    /// can't be resumed
+    #[serde(default)]
+    pub resume: Option<serde_json::Value>,
+}
+
+type Observer = Box<dyn Fn(&Job) + Send + Sync>;
+
+#[derive(Default)]
+struct Shared {
+    /// Where the jobs are saved; `None` keeps them in memory
+    path: Option<PathBuf>,
+    state: Mutex<QueueState>,
+    ready: Condvar,
+    observer: OnceLock<Observer>,
+}
+
+struct QueueState {
+    next_id: u64,
+    jobs: Vec<Job>,
+    pending: VecDeque<(u64, JobWork)>,
+    /// Worker threads started so far; they are never stopped
+    workers: usize,
+    running: usize,
+    /// Jobs that may run at once
+    concurrency: usize,
+}
+
+impl Default for QueueState {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            jobs: Vec::new(),
+            pending: VecDeque::new(),
+            workers: 0,
+            running: 0,
+            concurrency: 1,
+        }
+    }
+}
+
+/// Queue of background jobs, managed as Tauri state
+#[derive(Default)]
+pub struct JobQueue {
+    shared: Arc<Shared>,
+}
+
+impl JobQueue {
+    /// Load the jobs saved at `path`, starting empty if missing or invalid
+    ///
+    /// Jobs that were queued or running are marked interrupted.
+    pub fn load(path: PathBuf) -> Self {
+        let mut jobs: Vec<Job> = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid job list {}: {}", path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        for job in &mut jobs {
+            if matches!(job.state, JobState::Queued | JobState::Running) {
+                job.state = JobState::Interrupted;
+            }
+        }
+        let state = QueueState {
+            next_id: jobs.iter().map(|job| job.id).max().unwrap_or(0),
+            jobs,
+            ..Default::default()
+        };
+        let shared = Shared {
+            path: Some(path),
+            state: Mutex::new(state),
+            ..Default::default()
+        };
+        shared.save(&shared.state.lock().unwrap().jobs);
+        Self {
+            shared: Arc::new(shared),
+        }
+    }
+
+    /// Call `observer` with each job whenever its state changes
+    pub fn observe(&self, observer: impl Fn(&Job) + Send + Sync + 'static) {
+        if self.shared.observer.set(Box::new(observer)).is_err() {
+            log::warn!("A job observer is already installed");
+        }
+    }
+
+    /// Queue `work`; returns `None` if a job of the same kind and label is
+    /// still queued or running, so repeated triggers don't pile up
+    pub fn submit(&self, kind: &str, label: &str, work: JobWork) -> Option<u64> {
+        self.submit_with(kind, label, None, work)
+    }
+
+    /// Queue `work` like [`JobQueue::submit`], keeping `resume` so the job
+    /// can be started again if the app closes before it finishes
+    pub fn submit_resumable(
+        &self,
+        kind: &str,
+        label: &str,
+        resume: serde_json::Value,
+        work: JobWork,
+    ) -> Option<u64> {
+        self.submit_with(kind, label, Some(resume), work)
+    }
+
+    fn submit_with(
+        &self,
+        kind: &str,
+        label: &str,
+        resume: Option<serde_json::Value>,
+        work: JobWork,
+    ) -> Option<u64> {
+        let mut state = self.shared.state.lock().unwrap();
+        let duplicate = state.jobs.iter().any(|job| {
+            job.kind == kind
+                && job.label == label
+                && matches!(job.state, JobState::Queued | JobState::Running)
+        });
+        if duplicate {
+            return None;
+        }
+
+        state.next_id += 1;
+        let id = state.next_id;
+        let job = Job {
+            id,
+            kind: kind.to_string(),
+            label: label.to_string(),
+            state: JobState::Queued,
+            queued_ms: now_ms(),
+            started_ms: None,
+            finished_ms: None,
+            result: None,
+            error: None,
+            resume,
+        };
+        state.jobs.push(job.clone());
+        self.shared.save(&state.jobs);
+        drop(state);
+        // Announced before the worker can see it, so observers get the
+        // queued state first
+        self.shared.notify(&job);
+
+        let mut state = self.shared.state.lock().unwrap();
+        state.pending.push_back((id, work));
+        spawn_workers(&self.shared, &mut state);
+        drop(state);
+        self.shared.ready.notify_one();
+        Some(id)
+    }
+
+    /// How many jobs may run at once; one unless set otherwise
+    pub fn concurrency(&self) -> usize {
+        self.shared.state.lock().unwrap().concurrency
+    }
+
+    /// Let `limit` jobs, at least one, run at once
+    ///
+    /// Lowering the limit doesn't stop running jobs; no new one starts until
+    /// fewer than `limit` are running.
+    pub fn set_concurrency(&self, limit: usize) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.concurrency = limit.max(1);
+        spawn_workers(&self.shared, &mut state);
+        drop(state);
+        self.shared.ready.notify_all();
+    }
+
+    /// Every known job, oldest first
+    pub fn jobs(&self) -> Vec<Job> {
+        self.shared.state.lock().unwrap().jobs.clone()
+    }
+
+    pub fn get(&self, id: u64) -> Option<Job> {
+        let state = self.shared.state.lock().unwrap();
+        state.jobs.iter().find(|job| job.id == id).cloned()
+    }
+
+    /// Jobs the app closed on before they finished, oldest first
+    pub fn interrupted(&self) -> Vec<Job> {
+        let state = self.shared.state.lock().unwrap();
+        state
+            .jobs
+            .iter()
+            .filter(|job| job.state == JobState::Interrupted)
+            .cloned()
+            .collect()
+    }
+
+    /// Mark interrupted job `id` as failed with `error`, once it has been
+    /// resumed as another job or won't be
+    pub fn settle_interrupted(&self, id: u64, error: String) -> Result<Job, String> {
+        match self.get(id) {
+            Some(job) if job.state == JobState::Interrupted => {}
+            Some(_) => return Err(format!("Job {} was not interrupted", id)),
+            None => return Err(format!("No job with id {}", id)),
+        }
+        self.shared.update(id, |job| {
+            job.state = JobState::Failed;
+            job.error = Some(i18n::localize(&error));
+        });
+        self.get(id).ok_or_else(|| format!("No job with id {}", id))
+    }
+}
+
+impl Shared {
+    fn notify(&self, job: &Job) {
+        if let Some(observer) = self.observer.get() {
+            observer(job);
+        }
+    }
+
+    /// Apply `change` to job `id` and tell the observer
+    fn update(&self, id: u64, change: impl FnOnce(&mut Job)) {
+        let job = {
+            let mut state = self.state.lock().unwrap();
+            let Some(job) = state.jobs.iter_mut().find(|job| job.id == id) else {
+                return;
+            };
+            change(job);
+            let job = job.clone();
+
+            let finished = state
+                .jobs
+                .iter()
+                .filter(|job| matches!(job.state, JobState::Completed | JobState::Failed))
+                .count();
+            if finished > MAX_FINISHED_JOBS {
+                let mut excess = finished - MAX_FINISHED_JOBS;
+                state.jobs.retain(|job| {
+                    let drop =
+                        excess > 0 && matches!(job.state, JobState::Completed | JobState::Failed);
+                    if drop {
+                        excess -= 1;
+                    }
+                    !drop
+                });
+            }
+            self.save(&state.jobs);
+            job
+        };
+        self.notify(&job);
+    }
+
+    /// Write the jobs to disk; a queue that can't be saved keeps running
+    fn save(&self, jobs: &[Job]) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let saved = serde_json::to_string(jobs)
+            .map_err(|e| format!("Failed to serialize jobs: {}", e))
+            .and_then(|contents| {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+                }
+                let tmp = path.with_extension("json.tmp");
+                fs::write(&tmp, contents)
+                    .and_then(|_| fs::rename(&tmp, path))
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+            });
+        if let Err(e) = saved {
+            log::warn!("{}", e);
+        }
+    }
+}
+
+/// Start workers until there is one for each job that may run now
+fn spawn_workers(shared: &Arc<Shared>, state: &mut QueueState) {
+    let wanted = state.concurrency.min(state.running + state.pending.len());
+    while state.workers < wanted {
+        state.workers += 1;
+        let shared = Arc::clone(shared);
+        thread::spawn(move || work_loop(&shared));
+    }
+}
+
+fn work_loop(shared: &Shared) {
+    loop {
+        let (id, work) = {
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                if state.running < state.concurrency {
+                    if let Some(next) = state.pending.pop_front() {
+                        state.running += 1;
+                        break next;
+                    }
+                }
+                state = shared.ready.wait(state).unwrap();
+            }
+        };
+
+        shared.update(id, |job| {
+            job.state = JobState::Running;
+            job.started_ms = Some(now_ms());
+        });
+        let outcome = panic::catch_unwind(AssertUnwindSafe(work))
+            .unwrap_or_else(|_| Err("Job panicked".to_string()));
+        shared.update(id, |job| {
+            job.finished_ms = Some(now_ms());
+            match outcome {
+                Ok(result) => {
+                    job.state = JobState::Completed;
+                    job.result = Some(result);
+                }
+                Err(error) => {
+                    job.state = JobState::Failed;
+                    job.error = Some(i18n::localize(&error));
+                }
+            }
+        });
+        shared.state.lock().unwrap().running -= 1;
+        shared.ready.notify_one();
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_jobs_run_in_order_and_report() {
+        let queue = JobQueue::default();
+        let (tx, rx) = mpsc::channel();
+        queue.observe(move |job| {
+            let _ = tx.send((job.id, job.state));
+        });
+
+        let (release, wait) = mpsc::channel::<()>();
+        let first = queue
+            .submit(
+                "test",
+                "a",
+                Box::new(move || {
+                    wait.recv().map_err(|e| e.to_string())?;
+                    Ok(serde_json::json!(1))
+                }),
+            )
+            .unwrap();
+        // The same work can't be queued twice while it's pending
+        assert!(queue
+            .submit("test", "a", Box::new(|| Ok(().into())))
+            .is_none());
+        let second = queue
+            .submit("test", "b", Box::new(|| Err("broken".to_string())))
+            .unwrap();
+        release.send(()).unwrap();
+
+        let events: Vec<(u64, JobState)> = (0..6)
+            .map(|_| rx.recv_timeout(Duration::from_secs(5)).unwrap())
+            .collect();
+        let states = |id: u64| -> Vec<JobState> {
+            events.iter().filter(|e| e.0 == id).map(|e| e.1).collect()
+        };
+        use JobState::*;
+        assert_eq!(states(first), vec![Queued, Running, Completed]);
+        assert_eq!(states(second), vec![Queued, Running, Failed]);
+        // One at a time: the second only starts once the first is done
+        let done = events.iter().position(|e| *e == (first, Completed));
+        let started = events.iter().position(|e| *e == (second, Running));
+        assert!(done < started);
+
+        assert_eq!(queue.get(first).unwrap().result, Some(serde_json::json!(1)));
+        assert_eq!(queue.get(second).unwrap().error.as_deref(), Some("broken"));
+        assert_eq!(queue.jobs().len(), 2);
+    }
+
+    #[test]
+    fn test_concurrency_limits_running_jobs() {
+        let queue = JobQueue::default();
+        let (tx, rx) = mpsc::channel();
+        queue.observe(move |job| {
+            let _ = tx.send((job.id, job.state));
+        });
+        queue.set_concurrency(2);
+
+        let (release, wait) = mpsc::channel::<()>();
+        let wait = Arc::new(Mutex::new(wait));
+        let ids: Vec<u64> = ["a", "b", "c"]
+            .into_iter()
+            .map(|label| {
+                let wait = Arc::clone(&wait);
+                let work: JobWork = Box::new(move || {
+                    wait.lock().unwrap().recv().map_err(|e| e.to_string())?;
+                    Ok(().into())
+                });
+                queue.submit("test", label, work).unwrap()
+            })
+            .collect();
+
+        // Three queued, then two running while the third waits its turn
+        let mut running = Vec::new();
+        while running.len() < 2 {
+            let (id, state) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+            if state == JobState::Running {
+                running.push(id);
+            }
+        }
+        while let Ok((_, state)) = rx.recv_timeout(Duration::from_millis(100)) {
+            assert_eq!(state, JobState::Queued);
+        }
+        assert_eq!(queue.get(ids[2]).unwrap().state, JobState::Queued);
+
+        queue.set_concurrency(1);
+        assert_eq!(queue.concurrency(), 1);
+        for _ in 0..3 {
+            release.send(()).unwrap();
+        }
+        let finished = (0..4)
+            .map(|_| rx.recv_timeout(Duration::from_secs(5)).unwrap())
+            .filter(|e| e.1 == JobState::Completed)
+            .count();
+        assert_eq!(finished, 3);
+    }
+
+    #[test]
+    fn test_unfinished_jobs_load_as_interrupted() {
+        let path = std::env::temp_dir().join(format!("sweedle-jobs-{}.json", std::process::id()));
+        let queue = JobQueue::load(path.clone());
+        let (release, wait) = mpsc::channel::<()>();
+        let running = queue
+            .submit_resumable(
+                "test",
+                "a",
+                serde_json::json!({ "input": "a.glb" }),
+                Box::new(move || {
+                    wait.recv().map_err(|e| e.to_string())?;
+                    Ok(().into())
+                }),
+            )
+            .unwrap();
+        let queued = queue
+            .submit("test", "b", Box::new(|| Ok(().into())))
+            .unwrap();
+
+        // What a crash leaves behind: the file as saved while both are pending
+        let reloaded = JobQueue::load(path.clone());
+        let interrupted = reloaded.interrupted();
+        assert_eq!(
+            interrupted.iter().map(|job| job.id).collect::<Vec<_>>(),
+            [running, queued]
+        );
+        assert_eq!(
+            interrupted[0].resume,
+            Some(serde_json::json!({ "input": "a.glb" }))
+        );
+        let next = reloaded
+            .submit("test", "c", Box::new(|| Ok(().into())))
+            .unwrap();
+        assert!(next > queued);
+
+        let settled = reloaded
+            .settle_interrupted(queued, "Not resumed".to_string())
+            .unwrap();
+        assert_eq!(settled.state, JobState::Failed);
+        assert!(reloaded.settle_interrupted(queued, String::new()).is_err());
+        assert_eq!(reloaded.interrupted().len(), 1);
+
+        release.send(()).unwrap();
+        let _ = fs::remove_file(&path);
+    }
+}
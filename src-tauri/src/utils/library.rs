@@ -0,0 +1,192 @@
+//! Storage roots and their indexes
+//!
+//! Studios keep assets in more than one place, such as a local SSD for
+//! current work and a NAS for the archive. Each registered root is indexed
+//! on its own and the indexes are saved, so a root that is offline, like an
+//! unmounted share, still shows what it held when it was last seen.
+
+use crate::commands::file_ops::StorageAsset;
+use crate::utils::file_lock::{self, FileLock};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A storage folder registered by name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageRoot {
+    pub name: String,
+    pub path: String,
+}
+
+/// What a root held when it was last indexed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RootIndex {
+    pub path: String,
+    pub assets: Vec<StorageAsset>,
+    pub indexed_ms: u64,
+    /// Why the last attempt to index the root failed, if it did; the assets
+    /// are then from the last attempt that succeeded
+    pub error: Option<String>,
+}
+
+impl RootIndex {
+    /// An index of `assets` found just now
+    pub fn new(path: String, assets: Vec<StorageAsset>) -> Self {
+        Self {
+            path,
+            assets,
+            indexed_ms: now_ms(),
+            error: None,
+        }
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.assets.iter().filter_map(|asset| asset.glb_size).sum()
+    }
+}
+
+/// Root indexes by root name, saved to a JSON file and managed as Tauri state
+#[derive(Default)]
+pub struct LibraryIndex {
+    path: Option<PathBuf>,
+    roots: Mutex<BTreeMap<String, RootIndex>>,
+}
+
+impl LibraryIndex {
+    /// Load indexes from `path`, starting empty if missing or invalid
+    pub fn load(path: PathBuf) -> Self {
+        let roots = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid library index {}: {}", path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            path: Some(path),
+            roots: Mutex::new(roots),
+        }
+    }
+
+    /// The file the indexes are saved to, if any
+    pub fn file(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn get(&self, name: &str) -> Option<RootIndex> {
+        self.roots.lock().unwrap().get(name).cloned()
+    }
+
+    /// Store a fresh index of a root
+    pub fn set(&self, name: &str, index: RootIndex) -> Result<(), String> {
+        let mut roots = self.roots.lock().unwrap();
+        let _lock = self.reload(&mut roots)?;
+        roots.insert(name.to_string(), index);
+        self.save(&roots)
+    }
+
+    /// Record that indexing a root failed, keeping what it held before
+    pub fn set_error(&self, name: &str, path: String, error: String) -> Result<(), String> {
+        let mut roots = self.roots.lock().unwrap();
+        let _lock = self.reload(&mut roots)?;
+        let index = roots.entry(name.to_string()).or_insert_with(|| RootIndex {
+            indexed_ms: 0,
+            ..RootIndex::new(path, Vec::new())
+        });
+        index.error = Some(error);
+        self.save(&roots)
+    }
+
+    /// Drop the indexes of roots that are no longer registered
+    pub fn retain(&self, roots: &[StorageRoot]) -> Result<(), String> {
+        let mut indexes = self.roots.lock().unwrap();
+        let _lock = self.reload(&mut indexes)?;
+        indexes.retain(|name, index| {
+            roots
+                .iter()
+                .any(|root| root.name == *name && root.path == index.path)
+        });
+        self.save(&indexes)
+    }
+
+    /// Lock the file and pick up what other instances saved to it
+    fn reload(&self, roots: &mut BTreeMap<String, RootIndex>) -> Result<Option<FileLock>, String> {
+        self.path
+            .as_deref()
+            .map(|path| file_lock::lock_and_reload(path, roots, "library index"))
+            .transpose()
+    }
+
+    fn save(&self, roots: &BTreeMap<String, RootIndex>) -> Result<(), String> {
+        match &self.path {
+            Some(path) => file_lock::write_json(path, roots, "library index"),
+            None => Ok(()),
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(id: &str, glb_size: Option<u64>) -> StorageAsset {
+        StorageAsset {
+            id: id.to_string(),
+            path: format!("/ssd/{}", id),
+            model_path: None,
+            texture_path: None,
+            category: None,
+            has_glb: glb_size.is_some(),
+            has_obj: false,
+            has_fbx: false,
+            has_thumbnail: false,
+            glb_size,
+            thumbnail_path: None,
+            media: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_indexes_survive_reload_and_failures() {
+        let dir = std::env::temp_dir().join(format!("sweedle-library-{}", std::process::id()));
+        let path = dir.join("library_index.json");
+        let index = LibraryIndex::load(path.clone());
+        let ssd = RootIndex::new(
+            "/ssd".to_string(),
+            vec![asset("rock", Some(10)), asset("tree", Some(5))],
+        );
+        assert_eq!(ssd.total_bytes(), 15);
+        index.set("ssd", ssd).unwrap();
+        index
+            .set_error("nas", "/nas".to_string(), "offline".to_string())
+            .unwrap();
+        index
+            .set_error("ssd", "/ssd".to_string(), "offline".to_string())
+            .unwrap();
+
+        let reloaded = LibraryIndex::load(path);
+        let ssd = reloaded.get("ssd").unwrap();
+        assert_eq!(ssd.assets.len(), 2);
+        assert_eq!(ssd.error.as_deref(), Some("offline"));
+        assert_eq!(reloaded.get("nas").unwrap().indexed_ms, 0);
+
+        // Re-registering a name for another folder forgets the old index
+        let roots = [StorageRoot {
+            name: "ssd".to_string(),
+            path: "/other".to_string(),
+        }];
+        reloaded.retain(&roots).unwrap();
+        assert!(reloaded.get("ssd").is_none() && reloaded.get("nas").is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
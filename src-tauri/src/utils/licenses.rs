@@ -0,0 +1,269 @@
+//! Asset licenses and the assets each project uses
+//!
+//! Licenses are recorded per asset id rather than in the root indexes, so
+//! they survive re-indexing and follow an asset moved to another root.
+//! Projects are the ids the frontend gives them; the store only keeps which
+//! assets a project ships with, for its credits.
+
+use crate::utils::file_lock::{self, FileLock};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LicenseKind {
+    /// Public domain dedication; credit is optional
+    Cc0,
+    /// Creative Commons Attribution; credit is required
+    CcBy,
+    /// Bought or licensed for use without per-copy fees
+    RoyaltyFree,
+    /// Anything else, described by the license's `terms`
+    Custom,
+}
+
+impl LicenseKind {
+    /// The kind a free-form license string names, such as a glTF file's
+    /// `asset.extras.license`
+    pub fn from_text(text: &str) -> Self {
+        let text = text.trim().to_lowercase().replace(['_', ' '], "-");
+        if text.starts_with("cc0") || text.contains("public-domain") {
+            LicenseKind::Cc0
+        } else if text.starts_with("cc-by") || text.contains("creativecommons.org/licenses/by") {
+            LicenseKind::CcBy
+        } else if text.contains("royalty-free") || text.contains("royaltyfree") {
+            LicenseKind::RoyaltyFree
+        } else {
+            LicenseKind::Custom
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            LicenseKind::Cc0 => "CC0",
+            LicenseKind::CcBy => "CC BY",
+            LicenseKind::RoyaltyFree => "Royalty-Free",
+            LicenseKind::Custom => "Custom license",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetLicense {
+    pub kind: LicenseKind,
+    pub author: Option<String>,
+    /// Where the asset was obtained
+    pub source_url: Option<String>,
+    /// License name or terms, such as "CC-BY-4.0" or a custom license's text
+    pub terms: Option<String>,
+}
+
+impl AssetLicense {
+    /// Whether credits must name the asset
+    pub fn requires_attribution(&self) -> bool {
+        matches!(self.kind, LicenseKind::CcBy | LicenseKind::Custom)
+    }
+
+    /// A line for a credits file, like
+    /// `"Rock" by Kit, licensed under CC-BY-4.0 (https://...)`
+    pub fn attribution(&self, title: &str) -> String {
+        let mut line = format!("\"{}\"", title);
+        if let Some(author) = &self.author {
+            line.push_str(&format!(" by {}", author));
+        }
+        let terms = self.terms.as_deref().unwrap_or(self.kind.label());
+        line.push_str(&match self.kind {
+            LicenseKind::Custom => format!(", used under license: {}", terms),
+            _ => format!(", licensed under {}", terms),
+        });
+        if let Some(url) = &self.source_url {
+            line.push_str(&format!(" ({})", url));
+        }
+        line
+    }
+
+    /// The license with blank fields dropped
+    fn trimmed(self) -> Self {
+        let field = |value: Option<String>| {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        Self {
+            kind: self.kind,
+            author: field(self.author),
+            source_url: field(self.source_url),
+            terms: field(self.terms),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Records {
+    /// By asset id
+    licenses: BTreeMap<String, AssetLicense>,
+    /// Asset ids by project id
+    projects: BTreeMap<String, Vec<String>>,
+}
+
+/// Licenses and project assets, saved to a JSON file and managed as Tauri
+/// state
+#[derive(Default)]
+pub struct LicenseStore {
+    path: Option<PathBuf>,
+    records: Mutex<Records>,
+}
+
+impl LicenseStore {
+    /// Load records from `path`, starting empty if missing or invalid
+    pub fn load(path: PathBuf) -> Self {
+        let records = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid license records {}: {}", path.display(), e);
+                Records::default()
+            }),
+            Err(_) => Records::default(),
+        };
+        Self {
+            path: Some(path),
+            records: Mutex::new(records),
+        }
+    }
+
+    pub fn license(&self, asset_id: &str) -> Option<AssetLicense> {
+        self.records.lock().unwrap().licenses.get(asset_id).cloned()
+    }
+
+    /// Record an asset's license, or forget it with `None`
+    pub fn set_license(
+        &self,
+        asset_id: &str,
+        license: Option<AssetLicense>,
+    ) -> Result<Option<AssetLicense>, String> {
+        let mut records = self.records.lock().unwrap();
+        let _lock = self.reload(&mut records)?;
+        let license = license.map(AssetLicense::trimmed);
+        match &license {
+            Some(license) => records
+                .licenses
+                .insert(asset_id.to_string(), license.clone()),
+            None => records.licenses.remove(asset_id),
+        };
+        self.save(&records)?;
+        Ok(license)
+    }
+
+    /// Asset ids of a project, empty if it has none recorded
+    pub fn project_assets(&self, project_id: &str) -> Vec<String> {
+        let records = self.records.lock().unwrap();
+        records
+            .projects
+            .get(project_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Replace the assets of a project, keeping the first of any duplicates
+    pub fn set_project_assets(
+        &self,
+        project_id: &str,
+        asset_ids: Vec<String>,
+    ) -> Result<Vec<String>, String> {
+        let mut ids: Vec<String> = Vec::new();
+        for id in asset_ids {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        let mut records = self.records.lock().unwrap();
+        let _lock = self.reload(&mut records)?;
+        if ids.is_empty() {
+            records.projects.remove(project_id);
+        } else {
+            records.projects.insert(project_id.to_string(), ids.clone());
+        }
+        self.save(&records)?;
+        Ok(ids)
+    }
+
+    /// Carry the license and project entries of `from` over to the id `to`
+    pub fn rename_asset(&self, from: &str, to: &str) -> Result<(), String> {
+        let mut records = self.records.lock().unwrap();
+        let _lock = self.reload(&mut records)?;
+        if let Some(license) = records.licenses.remove(from) {
+            records.licenses.insert(to.to_string(), license);
+        }
+        for ids in records.projects.values_mut() {
+            if let Some(i) = ids.iter().position(|id| id == from) {
+                if ids.iter().any(|id| id == to) {
+                    ids.remove(i);
+                } else {
+                    ids[i] = to.to_string();
+                }
+            }
+        }
+        self.save(&records)
+    }
+
+    /// Lock the file and pick up what other instances saved to it
+    fn reload(&self, records: &mut Records) -> Result<Option<FileLock>, String> {
+        self.path
+            .as_deref()
+            .map(|path| file_lock::lock_and_reload(path, records, "license records"))
+            .transpose()
+    }
+
+    fn save(&self, records: &Records) -> Result<(), String> {
+        match &self.path {
+            Some(path) => file_lock::write_json(path, records, "license records"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_licenses_and_projects_survive_reload() {
+        let dir = std::env::temp_dir().join(format!("sweedle-licenses-{}", std::process::id()));
+        let path = dir.join("licenses.json");
+        let store = LicenseStore::load(path.clone());
+        let license = AssetLicense {
+            kind: LicenseKind::CcBy,
+            author: Some(" Kit ".to_string()),
+            source_url: Some(String::new()),
+            terms: Some("CC-BY-4.0".to_string()),
+        };
+        let saved = store.set_license("rock", Some(license)).unwrap().unwrap();
+        assert_eq!(saved.author.as_deref(), Some("Kit"));
+        assert_eq!(saved.source_url, None);
+        assert!(saved.requires_attribution());
+        assert_eq!(
+            saved.attribution("Rock"),
+            "\"Rock\" by Kit, licensed under CC-BY-4.0"
+        );
+        let ids = vec!["rock".to_string(), "tree".to_string(), "rock".to_string()];
+        assert_eq!(store.set_project_assets("p1", ids).unwrap().len(), 2);
+
+        let reloaded = LicenseStore::load(path);
+        assert_eq!(reloaded.license("rock"), Some(saved));
+        assert_eq!(reloaded.project_assets("p1"), vec!["rock", "tree"]);
+        reloaded.set_license("rock", None).unwrap();
+        assert!(reloaded.license("rock").is_none());
+
+        assert_eq!(LicenseKind::from_text("CC0 1.0"), LicenseKind::Cc0);
+        assert_eq!(LicenseKind::from_text("CC_BY_4.0"), LicenseKind::CcBy);
+        assert_eq!(
+            LicenseKind::from_text("Standard Royalty Free"),
+            LicenseKind::RoyaltyFree
+        );
+        assert_eq!(LicenseKind::from_text("Editorial"), LicenseKind::Custom);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,425 @@
+//! Lightmap UV generation
+//!
+//! Baked lighting needs a second UV set where no two faces share texels.
+//! Faces are grown into charts of similar orientation, each chart is
+//! flattened onto its seed face's plane (refusing faces that would fold over
+//! it), and the charts are packed into the unit square at one shared texel
+//! density with a gap of `padding` texels between them.
+
+use crate::utils::mesh_store::MeshData;
+use std::collections::{HashMap, VecDeque};
+
+/// Faces further than this from a chart's seed orientation start a new chart
+const MAX_CHART_ANGLE_DEGREES: f32 = 60.0;
+/// Chart rotations tried when looking for the tightest bounding box
+const ROTATION_STEPS: usize = 16;
+const PACKING_ITERATIONS: usize = 32;
+
+/// A mesh with lightmap UVs and how they were laid out
+pub struct LightmapLayout {
+    pub mesh: MeshData,
+    pub chart_count: usize,
+    /// Lightmap texels per world unit, the same for every chart
+    pub texels_per_unit: f32,
+    /// Fraction of the lightmap covered by faces
+    pub coverage: f32,
+}
+
+struct Chart {
+    faces: Vec<usize>,
+    /// Flattened corners of each face, in world units
+    corners: Vec<[[f32; 2]; 3]>,
+    min: [f32; 2],
+    size: [f32; 2],
+}
+
+/// Lay out non-overlapping lightmap UVs for a `resolution`² lightmap
+///
+/// Vertices on chart boundaries are split; faces, their other attributes
+/// and polygon information are unchanged.
+pub fn generate(mesh: &MeshData, padding: u32, resolution: u32) -> Result<LightmapLayout, String> {
+    if resolution == 0 {
+        return Err("Lightmap resolution must be positive".to_string());
+    }
+    let mut charts = build_charts(mesh);
+    if charts.is_empty() {
+        return Err("Mesh has no faces to lay out".to_string());
+    }
+
+    let pad = padding as f32 / resolution as f32;
+    // Tallest charts first so shelves are filled evenly
+    charts.sort_by(|a, b| b.size[1].total_cmp(&a.size[1]));
+    let area: f32 = charts.iter().map(|c| c.size[0] * c.size[1]).sum();
+    let longest = charts
+        .iter()
+        .map(|c| c.size[0].max(c.size[1]))
+        .fold(0.0f32, f32::max);
+    if !(area > 0.0 && area.is_finite()) {
+        return Err("Mesh has no surface area to lay out".to_string());
+    }
+    if pack(&charts, 0.0, pad).is_none() {
+        return Err(format!(
+            "{} charts don't fit a {}x{} lightmap with {} texels of padding",
+            charts.len(),
+            resolution,
+            resolution,
+            padding
+        ));
+    }
+
+    // Largest scale (UV units per world unit) at which the shelves still fit
+    let (mut lo, mut hi) = (0.0f32, (1.0 / area.sqrt()).min(1.0 / longest));
+    for _ in 0..PACKING_ITERATIONS {
+        let mid = (lo + hi) / 2.0;
+        if pack(&charts, mid, pad).is_some() {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    let scale = lo;
+    let offsets = pack(&charts, scale, pad).unwrap_or_default();
+
+    let mut corners = vec![[0.0f32; 2]; mesh.indices.len()];
+    let mut chart_of = vec![0u32; mesh.face_count()];
+    let mut covered = 0.0;
+    for (c, (chart, offset)) in charts.iter().zip(&offsets).enumerate() {
+        for (&face, tri) in chart.faces.iter().zip(&chart.corners) {
+            chart_of[face] = c as u32;
+            let uv = tri.map(|p| [0, 1].map(|a| offset[a] + (p[a] - chart.min[a]) * scale));
+            covered += signed_area(uv).abs();
+            corners[face * 3..face * 3 + 3].copy_from_slice(&uv);
+        }
+    }
+
+    // One vertex per (vertex, chart); within a chart a vertex has one UV
+    let mut ids: HashMap<(u32, u32), u32> = HashMap::new();
+    let mut order = Vec::new();
+    let mut uvs = Vec::new();
+    let indices = mesh
+        .indices
+        .iter()
+        .enumerate()
+        .map(|(k, &i)| {
+            *ids.entry((i, chart_of[k / 3])).or_insert_with(|| {
+                order.push(i as usize);
+                uvs.extend_from_slice(&corners[k]);
+                (order.len() - 1) as u32
+            })
+        })
+        .collect();
+
+    let mut out = mesh.with_vertices(&order, indices);
+    out.lightmap_uvs = Some(uvs);
+    out.polygons = mesh.polygons.clone();
+    Ok(LightmapLayout {
+        mesh: out,
+        chart_count: charts.len(),
+        texels_per_unit: scale * resolution as f32,
+        coverage: covered,
+    })
+}
+
+/// Whether two triangles overlap by more than `epsilon`
+///
+/// Triangles that only share an edge or a corner don't count. Checks the
+/// edge normals of both as separating axes, which is exact for triangles.
+pub fn triangles_overlap(a: [[f32; 2]; 3], b: [[f32; 2]; 3], epsilon: f32) -> bool {
+    for tri in [a, b] {
+        for k in 0..3 {
+            let (p, q) = (tri[k], tri[(k + 1) % 3]);
+            let axis = [q[1] - p[1], p[0] - q[0]];
+            let len = (axis[0] * axis[0] + axis[1] * axis[1]).sqrt();
+            if len == 0.0 {
+                continue;
+            }
+            let project = |t: [[f32; 2]; 3]| {
+                t.iter()
+                    .map(|v| (v[0] * axis[0] + v[1] * axis[1]) / len)
+                    .fold((f32::MAX, f32::MIN), |(lo, hi), d| (lo.min(d), hi.max(d)))
+            };
+            let ((a_lo, a_hi), (b_lo, b_hi)) = (project(a), project(b));
+            if a_hi.min(b_hi) - a_lo.max(b_lo) <= epsilon {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+pub fn signed_area(t: [[f32; 2]; 3]) -> f32 {
+    ((t[1][0] - t[0][0]) * (t[2][1] - t[0][1]) - (t[2][0] - t[0][0]) * (t[1][1] - t[0][1])) / 2.0
+}
+
+/// Grow charts over shared edges, flattening each onto its seed's plane
+fn build_charts(mesh: &MeshData) -> Vec<Chart> {
+    let face_count = mesh.face_count();
+    let point = |i: u32| {
+        let b = i as usize * 3;
+        [
+            mesh.positions[b],
+            mesh.positions[b + 1],
+            mesh.positions[b + 2],
+        ]
+    };
+
+    // Adjacency by position, so UV seams don't split charts on their own
+    let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+    let ids: Vec<u32> = (0..mesh.vertex_count() as u32)
+        .map(|i| {
+            let next = welded.len() as u32;
+            *welded.entry(point(i).map(f32::to_bits)).or_insert(next)
+        })
+        .collect();
+    let mut edge_faces: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for (f, face) in mesh.indices.chunks_exact(3).enumerate() {
+        for k in 0..3 {
+            let (a, b) = (ids[face[k] as usize], ids[face[(k + 1) % 3] as usize]);
+            edge_faces.entry((a.min(b), a.max(b))).or_default().push(f);
+        }
+    }
+
+    let normals: Vec<[f32; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| {
+            let [a, b, c] = [f[0], f[1], f[2]].map(point);
+            let n = cross(sub(b, a), sub(c, a));
+            let len = dot(n, n).sqrt();
+            if len > 0.0 {
+                n.map(|v| v / len)
+            } else {
+                [0.0; 3]
+            }
+        })
+        .collect();
+
+    // Flattened faces are bucketed on a grid about one edge long, so the
+    // fold check only looks at nearby faces
+    let edge_length: f32 = mesh
+        .indices
+        .chunks_exact(3)
+        .flat_map(|f| [(f[0], f[1]), (f[1], f[2]), (f[2], f[0])])
+        .map(|(a, b)| {
+            let d = sub(point(a), point(b));
+            dot(d, d).sqrt()
+        })
+        .sum::<f32>()
+        / mesh.indices.len().max(1) as f32;
+    let cell = if edge_length > 0.0 { edge_length } else { 1.0 };
+    let cells = |t: &[[f32; 2]; 3]| {
+        let lo = [0, 1].map(|a| (t[0][a].min(t[1][a]).min(t[2][a]) / cell).floor() as i32);
+        let hi = [0, 1].map(|a| (t[0][a].max(t[1][a]).max(t[2][a]) / cell).floor() as i32);
+        (lo[0]..=hi[0]).flat_map(move |x| (lo[1]..=hi[1]).map(move |y| (x, y)))
+    };
+
+    let min_dot = MAX_CHART_ANGLE_DEGREES.to_radians().cos();
+    let mut assigned = vec![false; face_count];
+    let mut charts = Vec::new();
+    for seed in 0..face_count {
+        if assigned[seed] {
+            continue;
+        }
+        let (u, v) = basis(normals[seed]);
+        let flatten = |f: usize| {
+            let face = &mesh.indices[f * 3..f * 3 + 3];
+            [face[0], face[1], face[2]].map(|i| {
+                let p = point(i);
+                [dot(p, u), dot(p, v)]
+            })
+        };
+
+        let mut chart = Chart {
+            faces: Vec::new(),
+            corners: Vec::new(),
+            min: [0.0; 2],
+            size: [0.0; 2],
+        };
+        let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        let mut queue = VecDeque::from([seed]);
+        assigned[seed] = true;
+        while let Some(f) = queue.pop_front() {
+            let tri = flatten(f);
+            if f != seed {
+                let folds = signed_area(tri) <= 0.0
+                    || cells(&tri).any(|c| {
+                        grid.get(&c).is_some_and(|near| {
+                            near.iter()
+                                .any(|&k| triangles_overlap(chart.corners[k], tri, 1e-6))
+                        })
+                    });
+                if folds {
+                    // Leave it for a later chart
+                    assigned[f] = false;
+                    continue;
+                }
+            }
+            for c in cells(&tri) {
+                grid.entry(c).or_default().push(chart.corners.len());
+            }
+            chart.faces.push(f);
+            chart.corners.push(tri);
+
+            let face = &mesh.indices[f * 3..f * 3 + 3];
+            for k in 0..3 {
+                let (a, b) = (ids[face[k] as usize], ids[face[(k + 1) % 3] as usize]);
+                for &n in &edge_faces[&(a.min(b), a.max(b))] {
+                    if !assigned[n] && dot(normals[n], normals[seed]) >= min_dot {
+                        assigned[n] = true;
+                        queue.push_back(n);
+                    }
+                }
+            }
+        }
+        orient(&mut chart);
+        charts.push(chart);
+    }
+    charts
+}
+
+/// Rotate a chart to its tightest bounding box and record that box
+fn orient(chart: &mut Chart) {
+    let area = |angle: f32| {
+        let (_, size) = rotated_bounds(&chart.corners, angle);
+        size[0] * size[1]
+    };
+    let best = (0..ROTATION_STEPS)
+        .map(|k| k as f32 * std::f32::consts::FRAC_PI_2 / ROTATION_STEPS as f32)
+        .min_by(|&a, &b| area(a).total_cmp(&area(b)))
+        .unwrap_or(0.0);
+
+    let (_, size) = rotated_bounds(&chart.corners, best);
+    // Wide rather than tall packs better on shelves
+    let angle = if size[1] > size[0] {
+        best - std::f32::consts::FRAC_PI_2
+    } else {
+        best
+    };
+    let (sin, cos) = angle.sin_cos();
+    for p in chart.corners.iter_mut().flatten() {
+        *p = [p[0] * cos - p[1] * sin, p[0] * sin + p[1] * cos];
+    }
+    (chart.min, chart.size) = rotated_bounds(&chart.corners, 0.0);
+}
+
+/// Lower-left corner and size of the corners' bounding box after rotating
+fn rotated_bounds(corners: &[[[f32; 2]; 3]], angle: f32) -> ([f32; 2], [f32; 2]) {
+    let (sin, cos) = angle.sin_cos();
+    let mut min = [f32::MAX; 2];
+    let mut max = [f32::MIN; 2];
+    for p in corners.iter().flatten() {
+        let r = [p[0] * cos - p[1] * sin, p[0] * sin + p[1] * cos];
+        for a in 0..2 {
+            min[a] = min[a].min(r[a]);
+            max[a] = max[a].max(r[a]);
+        }
+    }
+    (min, [max[0] - min[0], max[1] - min[1]])
+}
+
+/// Shelf-pack charts at `scale`, returning each one's lower-left corner
+fn pack(charts: &[Chart], scale: f32, pad: f32) -> Option<Vec<[f32; 2]>> {
+    let mut offsets = Vec::with_capacity(charts.len());
+    let (mut x, mut y, mut shelf) = (pad, pad, 0.0f32);
+    for chart in charts {
+        let [w, h] = chart.size.map(|s| s * scale);
+        if x + w + pad > 1.0 && x > pad {
+            x = pad;
+            y += shelf + pad;
+            shelf = 0.0;
+        }
+        if x + w + pad > 1.0 || y + h + pad > 1.0 {
+            return None;
+        }
+        offsets.push([x, y]);
+        x += w + pad;
+        shelf = shelf.max(h);
+    }
+    Some(offsets)
+}
+
+/// Two unit vectors spanning the plane perpendicular to `normal`
+fn basis(normal: [f32; 3]) -> ([f32; 3], [f32; 3]) {
+    let reference = if normal[1].abs() < 0.9 {
+        [0.0, 1.0, 0.0]
+    } else {
+        [0.0, 0.0, -1.0]
+    };
+    let u = cross(reference, normal);
+    let len = dot(u, u).sqrt();
+    if len == 0.0 {
+        return ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+    }
+    let u = u.map(|a| a / len);
+    (u, cross(normal, u))
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cube_lightmap_has_no_overlaps() {
+        let mut positions = Vec::new();
+        for i in 0..8 {
+            positions.extend([(i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32]);
+        }
+        let quads = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        let mesh = MeshData {
+            positions,
+            indices: quads
+                .iter()
+                .flat_map(|q| [q[0], q[1], q[2], q[0], q[2], q[3]])
+                .collect(),
+            polygons: Some(vec![4; 6]),
+            ..Default::default()
+        };
+
+        let layout = generate(&mesh, 4, 256).unwrap();
+        assert_eq!(layout.chart_count, 6);
+        assert_eq!(layout.mesh.vertex_count(), 24);
+        assert!(layout.mesh.validate().is_ok());
+
+        let uvs = layout.mesh.lightmap_uvs.as_ref().unwrap();
+        assert!(uvs.iter().all(|&c| (0.0..=1.0).contains(&c)));
+        let tris: Vec<[[f32; 2]; 3]> = layout
+            .mesh
+            .indices
+            .chunks_exact(3)
+            .map(|f| [f[0], f[1], f[2]].map(|i| [uvs[i as usize * 2], uvs[i as usize * 2 + 1]]))
+            .collect();
+        for (a, ta) in tris.iter().enumerate() {
+            assert!(signed_area(*ta) > 0.0);
+            for tb in &tris[a + 1..] {
+                assert!(!triangles_overlap(*ta, *tb, 1e-6));
+            }
+        }
+        // Six equal squares in a grid fill well under all of it
+        assert!(layout.coverage > 0.3 && layout.coverage < 1.0);
+
+        assert!(generate(&mesh, 200, 256).is_err());
+    }
+}
@@ -0,0 +1,493 @@
+//! Structured logs kept in memory, printed to stderr and written to
+//! rotating files
+//!
+//! `log` records and `tracing` events, such as the errors commands return
+//! through `#[instrument(err)]`, both become [`LogRecord`]s. The most recent
+//! are kept for the in-app log viewer, each is printed to stderr as
+//! env_logger did, and once the log folder is known they are appended as
+//! JSON lines to `sweedle.log`, which is rotated at 5 MiB keeping four older
+//! files. `RUST_LOG` still filters them, with `level` and `target=level`
+//! directives; without it everything at `info` and above is logged.
+
+use crate::utils::metrics::MetricsLayer;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+pub const LOG_FILE: &str = "sweedle.log";
+const MAX_FILE_BYTES: u64 = 5 << 20;
+/// Rotated files kept besides the current one, `sweedle.1.log` the newest
+const KEPT_FILES: usize = 4;
+/// Records kept in memory for `get_recent_logs`
+const RECENT: usize = 2000;
+
+/// Severity of a record, most severe first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn parse(value: &str) -> Option<Option<Self>> {
+        Some(Some(match value.to_ascii_lowercase().as_str() {
+            "off" => return Some(None),
+            "error" => LogLevel::Error,
+            "warn" => LogLevel::Warn,
+            "info" => LogLevel::Info,
+            "debug" => LogLevel::Debug,
+            "trace" => LogLevel::Trace,
+            _ => return None,
+        }))
+    }
+}
+
+impl From<log::Level> for LogLevel {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => LogLevel::Error,
+            log::Level::Warn => LogLevel::Warn,
+            log::Level::Info => LogLevel::Info,
+            log::Level::Debug => LogLevel::Debug,
+            log::Level::Trace => LogLevel::Trace,
+        }
+    }
+}
+
+impl From<tracing::Level> for LogLevel {
+    fn from(level: tracing::Level) -> Self {
+        match level {
+            tracing::Level::ERROR => LogLevel::Error,
+            tracing::Level::WARN => LogLevel::Warn,
+            tracing::Level::INFO => LogLevel::Info,
+            tracing::Level::DEBUG => LogLevel::Debug,
+            tracing::Level::TRACE => LogLevel::Trace,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Trace => "TRACE",
+        })
+    }
+}
+
+/// One logged message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub time_ms: u64,
+    pub level: LogLevel,
+    /// Module the message came from, such as `sweedle_lib::utils::jobs`
+    pub target: String,
+    pub message: String,
+    /// Spans the event happened in, outermost first, such as the command
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spans: Vec<String>,
+    /// Fields of a `tracing` event besides its message
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+}
+
+/// Which records are kept, from `RUST_LOG`-style directives
+#[derive(Debug, Clone)]
+struct Filter {
+    /// `None` logs nothing
+    default: Option<LogLevel>,
+    /// Per target prefix; the longest matching prefix wins
+    targets: Vec<(String, Option<LogLevel>)>,
+}
+
+impl Filter {
+    fn parse(spec: &str) -> Self {
+        let mut filter = Filter {
+            default: Some(LogLevel::Info),
+            targets: Vec::new(),
+        };
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => match LogLevel::parse(level) {
+                    Some(level) => filter.targets.push((target.to_string(), level)),
+                    None => eprintln!("Ignoring invalid log directive {}", directive),
+                },
+                None => match LogLevel::parse(directive) {
+                    Some(level) => filter.default = level,
+                    // A bare target logs everything from it, as env_logger did
+                    None => filter
+                        .targets
+                        .push((directive.to_string(), Some(LogLevel::Trace))),
+                },
+            }
+        }
+        filter
+    }
+
+    fn enabled(&self, target: &str, level: LogLevel) -> bool {
+        let limit = self
+            .targets
+            .iter()
+            .filter(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level);
+        limit.is_some_and(|limit| level <= limit)
+    }
+
+    fn max_level(&self) -> log::LevelFilter {
+        let most = self
+            .targets
+            .iter()
+            .map(|(_, level)| *level)
+            .chain([self.default])
+            .max()
+            .flatten();
+        match most {
+            None => log::LevelFilter::Off,
+            Some(LogLevel::Error) => log::LevelFilter::Error,
+            Some(LogLevel::Warn) => log::LevelFilter::Warn,
+            Some(LogLevel::Info) => log::LevelFilter::Info,
+            Some(LogLevel::Debug) => log::LevelFilter::Debug,
+            Some(LogLevel::Trace) => log::LevelFilter::Trace,
+        }
+    }
+}
+
+struct LogFile {
+    dir: PathBuf,
+    file: File,
+    bytes: u64,
+}
+
+/// Every record logged by the app
+pub struct Logs {
+    filter: Filter,
+    recent: Mutex<VecDeque<LogRecord>>,
+    file: Mutex<Option<LogFile>>,
+}
+
+impl Logs {
+    pub fn global() -> &'static Logs {
+        static LOGS: OnceLock<Logs> = OnceLock::new();
+        LOGS.get_or_init(|| Logs {
+            filter: Filter::parse(&std::env::var("RUST_LOG").unwrap_or_default()),
+            recent: Mutex::new(VecDeque::new()),
+            file: Mutex::new(None),
+        })
+    }
+
+    /// Start writing to `sweedle.log` in `dir`, beginning with the records
+    /// logged before the folder was known
+    pub fn open_dir(&self, dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let mut file = self.file.lock().unwrap();
+        *file = Some(open_file(dir)?);
+        for record in self.recent.lock().unwrap().iter() {
+            write_line(&mut file, record);
+        }
+        Ok(())
+    }
+
+    /// Up to the `limit` newest records at `level` or more severe, oldest
+    /// first
+    pub fn recent(&self, level: LogLevel, limit: usize) -> Vec<LogRecord> {
+        let recent = self.recent.lock().unwrap();
+        let mut records: Vec<LogRecord> = recent
+            .iter()
+            .rev()
+            .filter(|record| record.level <= level)
+            .take(limit)
+            .cloned()
+            .collect();
+        records.reverse();
+        records
+    }
+
+    /// The log files on disk, the current one first
+    pub fn files(&self) -> Vec<PathBuf> {
+        let Some(dir) = self.file.lock().unwrap().as_ref().map(|f| f.dir.clone()) else {
+            return Vec::new();
+        };
+        (0..=KEPT_FILES)
+            .map(|n| file_path(&dir, n))
+            .filter(|path| path.is_file())
+            .collect()
+    }
+
+    fn enabled(&self, target: &str, level: LogLevel) -> bool {
+        self.filter.enabled(target, level)
+    }
+
+    fn record(&self, record: LogRecord) {
+        eprintln!("{}", human(&record));
+        write_line(&mut self.file.lock().unwrap(), &record);
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() == RECENT {
+            recent.pop_front();
+        }
+        recent.push_back(record);
+    }
+}
+
+impl log::Log for Logs {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        Logs::enabled(self, metadata.target(), metadata.level().into())
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !log::Log::enabled(self, record.metadata()) {
+            return;
+        }
+        self.record(LogRecord {
+            time_ms: now_ms(),
+            level: record.level().into(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            spans: Vec::new(),
+            fields: Map::new(),
+        });
+    }
+
+    fn flush(&self) {
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+/// Passes `tracing` events on to [`Logs`]
+pub struct LogLayer;
+
+impl<S> Layer<S> for LogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let level = LogLevel::from(*metadata.level());
+        let logs = Logs::global();
+        if !logs.enabled(metadata.target(), level) {
+            return;
+        }
+        let mut fields = FieldVisitor::default();
+        event.record(&mut fields);
+        let spans = ctx
+            .event_span(event)
+            .map(|span| {
+                span.scope()
+                    .from_root()
+                    .map(|span| span.name().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        // `#[instrument(err)]` logs the error as a field with no message
+        let message = fields.message.unwrap_or_else(|| {
+            let values: Vec<String> = fields
+                .fields
+                .iter()
+                .map(|(name, value)| match value {
+                    Value::String(value) => format!("{}={}", name, value),
+                    value => format!("{}={}", name, value),
+                })
+                .collect();
+            values.join(" ")
+        });
+        logs.record(LogRecord {
+            time_ms: now_ms(),
+            level,
+            target: metadata.target().to_string(),
+            message,
+            spans,
+            fields: fields.fields,
+        });
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: Option<String>,
+    fields: Map<String, Value>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{:?}", value));
+        } else {
+            self.fields
+                .insert(field.name().to_string(), format!("{:?}", value).into());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_string());
+        } else {
+            self.fields.insert(field.name().to_string(), value.into());
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+}
+
+/// Install the logger for `log` records and the `tracing` subscriber
+/// collecting metrics and passing events on to the logs
+pub fn init() {
+    let logs = Logs::global();
+    if log::set_logger(logs).is_ok() {
+        log::set_max_level(logs.filter.max_level());
+    }
+    let subscriber = tracing_subscriber::registry()
+        .with(MetricsLayer)
+        .with(LogLayer);
+    if tracing::subscriber::set_global_default(subscriber).is_err() {
+        log::warn!("A tracing subscriber is already installed; metrics are disabled");
+    }
+}
+
+fn file_path(dir: &Path, n: usize) -> PathBuf {
+    match n {
+        0 => dir.join(LOG_FILE),
+        n => dir.join(format!("sweedle.{}.log", n)),
+    }
+}
+
+fn open_file(dir: &Path) -> Result<LogFile, String> {
+    let path = file_path(dir, 0);
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let bytes = file.metadata().map(|m| m.len()).unwrap_or(0);
+    Ok(LogFile {
+        dir: dir.to_path_buf(),
+        file,
+        bytes,
+    })
+}
+
+/// Append `record` as a JSON line, first rotating the files if it would
+/// take the current one past its size
+///
+/// Failures go to stderr only, as logging them would come back here.
+fn write_line(file: &mut Option<LogFile>, record: &LogRecord) {
+    let Some(current) = file.as_mut() else {
+        return;
+    };
+    let Ok(mut line) = serde_json::to_string(record) else {
+        return;
+    };
+    line.push('\n');
+    if current.bytes > 0 && current.bytes + line.len() as u64 > MAX_FILE_BYTES {
+        let dir = current.dir.clone();
+        for n in (0..KEPT_FILES).rev() {
+            let _ = fs::rename(file_path(&dir, n), file_path(&dir, n + 1));
+        }
+        match open_file(&dir) {
+            Ok(opened) => *current = opened,
+            Err(e) => {
+                eprintln!("{}", e);
+                *file = None;
+                return;
+            }
+        }
+    }
+    match current.file.write_all(line.as_bytes()) {
+        Ok(()) => current.bytes += line.len() as u64,
+        Err(e) => eprintln!("Failed to write {}: {}", LOG_FILE, e),
+    }
+}
+
+/// `record` as a line for a person, like env_logger's
+fn human(record: &LogRecord) -> String {
+    let time = chrono::DateTime::from_timestamp_millis(record.time_ms as i64)
+        .map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+        .unwrap_or_default();
+    let mut line = format!(
+        "[{} {:<5} {}] {}",
+        time, record.level, record.target, record.message
+    );
+    if !record.spans.is_empty() {
+        line.push_str(&format!(" ({})", record.spans.join("/")));
+    }
+    line
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_and_rotation() {
+        let filter = Filter::parse("warn,sweedle_lib::utils=debug,sweedle_lib::utils::jobs=off");
+        assert!(filter.enabled("wgpu", LogLevel::Error));
+        assert!(!filter.enabled("wgpu", LogLevel::Info));
+        assert!(filter.enabled("sweedle_lib::utils::library", LogLevel::Debug));
+        assert!(!filter.enabled("sweedle_lib::utils::jobs", LogLevel::Error));
+        assert_eq!(filter.max_level(), log::LevelFilter::Debug);
+        assert!(Filter::parse("").enabled("anything", LogLevel::Info));
+
+        let dir = std::env::temp_dir().join(format!("sweedle-logging-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut file = Some(open_file(&dir).unwrap());
+        let record = LogRecord {
+            time_ms: 0,
+            level: LogLevel::Warn,
+            target: "test".to_string(),
+            message: "x".repeat(1 << 20),
+            spans: vec!["export_bundle".to_string()],
+            fields: Map::new(),
+        };
+        for _ in 0..6 {
+            write_line(&mut file, &record);
+        }
+        assert!(file_path(&dir, 0).is_file());
+        assert!(file_path(&dir, 1).is_file());
+        let line = fs::read_to_string(file_path(&dir, 1)).unwrap();
+        let parsed: LogRecord = serde_json::from_str(line.lines().next().unwrap()).unwrap();
+        assert_eq!(parsed, record);
+        assert!(human(&record).starts_with("[1970-01-01T00:00:00Z WARN  test] xxx"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
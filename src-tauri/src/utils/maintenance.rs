@@ -0,0 +1,157 @@
+//! Scheduled library maintenance
+//!
+//! A library that only changes through Sweedle still drifts: files get
+//! edited by other tools, thumbnails fall behind their models and assets
+//! that passed validation break the limits of a stricter profile. The
+//! maintenance schedule runs the chosen checks over every storage root once
+//! a day at a set local time, and the summary says what needs attention.
+
+use crate::utils::report::AssetReport;
+use chrono::{NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+/// A check the maintenance run can do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceTask {
+    /// Compare glTF files against the hash in their provenance record
+    VerifyHashes,
+    /// Remake texture thumbnails and list models whose thumbnail is older
+    /// than the model
+    RefreshThumbnails,
+    /// Validate every asset against the validation profile in the settings
+    Revalidate,
+}
+
+impl MaintenanceTask {
+    pub const ALL: [MaintenanceTask; 3] = [
+        MaintenanceTask::VerifyHashes,
+        MaintenanceTask::RefreshThumbnails,
+        MaintenanceTask::Revalidate,
+    ];
+}
+
+/// When maintenance runs and what it does
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceSchedule {
+    pub enabled: bool,
+    /// Local time of day as `HH:MM`
+    pub time: String,
+    pub tasks: Vec<MaintenanceTask>,
+}
+
+impl Default for MaintenanceSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time: "03:00".to_string(),
+            tasks: MaintenanceTask::ALL.to_vec(),
+        }
+    }
+}
+
+impl MaintenanceSchedule {
+    /// The time of day to run at
+    pub fn time_of_day(&self) -> Result<NaiveTime, String> {
+        NaiveTime::parse_from_str(self.time.trim(), "%H:%M").map_err(|_| {
+            format!(
+                "Maintenance time must be HH:MM in 24-hour time, not '{}'",
+                self.time
+            )
+        })
+    }
+
+    /// Whether the time to run falls after `from` and no later than `to`,
+    /// for a scheduler that checks now and then
+    pub fn due_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> bool {
+        if !self.enabled || self.tasks.is_empty() {
+            return false;
+        }
+        let Ok(time) = self.time_of_day() else {
+            return false;
+        };
+        [from.date(), to.date()]
+            .into_iter()
+            .map(|date| date.and_time(time))
+            .any(|at| at > from && at <= to)
+    }
+}
+
+/// A file that no longer matches its provenance record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedFile {
+    pub root: String,
+    pub asset_id: String,
+    pub path: String,
+}
+
+/// An asset that didn't pass every check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedValidation {
+    pub root: String,
+    #[serde(flatten)]
+    pub report: AssetReport,
+}
+
+/// What a maintenance run found, sent with the `maintenance-finished` event
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintenanceSummary {
+    pub tasks: Vec<MaintenanceTask>,
+    pub started_ms: u64,
+    pub finished_ms: u64,
+    /// Assets in the roots that could be indexed
+    pub asset_count: usize,
+    /// Roots that couldn't be indexed, with why
+    pub offline_roots: Vec<String>,
+    /// glTF files whose provenance record still matches
+    pub intact_count: usize,
+    /// glTF files without a provenance record, which can't be verified
+    pub unstamped_count: usize,
+    pub changed_files: Vec<ChangedFile>,
+    /// Texture assets whose thumbnail was made again
+    pub refreshed_thumbnail_count: usize,
+    /// Models with a missing or outdated thumbnail, for the viewer to render
+    pub stale_thumbnails: Vec<String>,
+    /// Profile the assets were validated against, if any
+    pub validation_profile: Option<String>,
+    pub failed_validation: Vec<FailedValidation>,
+    /// Files that couldn't be checked, with why
+    pub errors: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_schedule_is_due_once_when_its_time_passes() {
+        let at = |day: u32, h: u32, m: u32| {
+            NaiveDate::from_ymd_opt(2026, 3, day)
+                .unwrap()
+                .and_hms_opt(h, m, 0)
+                .unwrap()
+        };
+        let mut schedule = MaintenanceSchedule {
+            enabled: true,
+            time: "00:00".to_string(),
+            ..Default::default()
+        };
+        assert!(schedule.due_between(at(1, 23, 59), at(2, 0, 0)));
+        assert!(!schedule.due_between(at(2, 0, 0), at(2, 0, 1)));
+
+        schedule.time = " 03:30".to_string();
+        assert!(schedule.due_between(at(2, 3, 29), at(2, 3, 31)));
+        assert!(!schedule.due_between(at(2, 3, 31), at(2, 4, 0)));
+        assert!(!schedule.due_between(at(2, 3, 0), at(2, 3, 29)));
+
+        schedule.enabled = false;
+        assert!(!schedule.due_between(at(2, 3, 29), at(2, 3, 31)));
+
+        schedule.enabled = true;
+        schedule.time = "25:00".to_string();
+        assert!(schedule.time_of_day().is_err());
+        assert!(!schedule.due_between(at(1, 0, 0), at(3, 0, 0)));
+    }
+}
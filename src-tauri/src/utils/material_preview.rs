@@ -0,0 +1,333 @@
+//! Swatches of glTF materials rendered on a preview sphere
+//!
+//! Each material is shaded on a unit sphere seen head-on, lit by one key
+//! light from the upper left and a sky-to-ground ambient gradient, with the
+//! metallic-roughness model glTF specifies: GGX specular, Schlick Fresnel
+//! and Lambert diffuse. The sphere's UVs wrap once around it, so tiling
+//! textures show as they would on a model. The sphere is ray-traced on the
+//! CPU, four samples a pixel, and its surroundings are left transparent.
+
+use crate::utils::gltf_document::GltfDocument;
+use crate::utils::vertex_colors::{linear_to_srgb, Texture};
+use image::{DynamicImage, RgbaImage};
+use rayon::prelude::*;
+use serde_json::Value;
+use std::f32::consts::{PI, TAU};
+
+/// Side of a swatch unless one is given
+pub const DEFAULT_PREVIEW_SIZE: u32 = 128;
+/// Largest swatch side
+pub const MAX_PREVIEW_SIZE: u32 = 1024;
+/// Direction toward the key light, upper left and in front
+const LIGHT: [f32; 3] = [-0.485, 0.728, 0.485];
+const LIGHT_INTENSITY: f32 = 2.6;
+const SKY: [f32; 3] = [0.42, 0.46, 0.52];
+const GROUND: [f32; 3] = [0.16, 0.14, 0.12];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AlphaMode {
+    Opaque,
+    Mask(f32),
+    Blend,
+}
+
+/// What shading needs of one glTF material
+pub struct PreviewMaterial {
+    pub name: String,
+    base_color: [f32; 4],
+    metallic: f32,
+    roughness: f32,
+    emissive: [f32; 3],
+    alpha_mode: AlphaMode,
+    unlit: bool,
+    base_color_texture: Option<Texture>,
+    metallic_roughness_texture: Option<Texture>,
+    /// With the `scale` its tilt is multiplied by
+    normal_texture: Option<(Texture, f32)>,
+    occlusion_texture: Option<(Texture, f32)>,
+    emissive_texture: Option<Texture>,
+}
+
+impl PreviewMaterial {
+    /// Every material of `document`, with the textures it uses decoded
+    ///
+    /// Textures that can't be decoded, such as KTX2, are treated as absent.
+    pub fn all(document: &GltfDocument) -> Vec<Self> {
+        let json = &document.json;
+        let texture = |info: &Value, data: bool| -> Option<Texture> {
+            let texture = &json["textures"][info["index"].as_u64()? as usize];
+            let image = texture["source"].as_u64()? as usize;
+            let decoded = image::load_from_memory(document.image_bytes(image)?).ok()?;
+            let from: fn(&DynamicImage) -> Result<Texture, String> = if data {
+                Texture::from_data_image
+            } else {
+                Texture::from_image
+            };
+            from(&decoded).ok()
+        };
+        let floats = |value: &Value, default: &[f32]| -> Vec<f32> {
+            value.as_array().map_or_else(
+                || default.to_vec(),
+                |a| a.iter().map(|v| v.as_f64().unwrap_or(0.0) as f32).collect(),
+            )
+        };
+
+        json["materials"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, material)| {
+                let pbr = &material["pbrMetallicRoughness"];
+                let base = floats(&pbr["baseColorFactor"], &[1.0; 4]);
+                let strength = material["extensions"]["KHR_materials_emissive_strength"]
+                    ["emissiveStrength"]
+                    .as_f64()
+                    .unwrap_or(1.0) as f32;
+                let emissive = floats(&material["emissiveFactor"], &[0.0; 3]);
+                let cutoff = material["alphaCutoff"].as_f64().unwrap_or(0.5) as f32;
+                Self {
+                    name: material["name"]
+                        .as_str()
+                        .map_or_else(|| format!("Material {}", i), str::to_string),
+                    base_color: std::array::from_fn(|k| base.get(k).copied().unwrap_or(1.0)),
+                    metallic: pbr["metallicFactor"].as_f64().unwrap_or(1.0) as f32,
+                    roughness: pbr["roughnessFactor"].as_f64().unwrap_or(1.0) as f32,
+                    emissive: std::array::from_fn(|k| {
+                        emissive.get(k).copied().unwrap_or(0.0) * strength
+                    }),
+                    alpha_mode: match material["alphaMode"].as_str() {
+                        Some("MASK") => AlphaMode::Mask(cutoff),
+                        Some("BLEND") => AlphaMode::Blend,
+                        _ => AlphaMode::Opaque,
+                    },
+                    unlit: material["extensions"].get("KHR_materials_unlit").is_some(),
+                    base_color_texture: texture(&pbr["baseColorTexture"], false),
+                    metallic_roughness_texture: texture(&pbr["metallicRoughnessTexture"], true),
+                    normal_texture: texture(&material["normalTexture"], true).map(|t| {
+                        let scale = material["normalTexture"]["scale"].as_f64();
+                        (t, scale.unwrap_or(1.0) as f32)
+                    }),
+                    occlusion_texture: texture(&material["occlusionTexture"], true).map(|t| {
+                        let strength = material["occlusionTexture"]["strength"].as_f64();
+                        (t, strength.unwrap_or(1.0) as f32)
+                    }),
+                    emissive_texture: texture(&material["emissiveTexture"], false),
+                }
+            })
+            .collect()
+    }
+
+    /// The material on the preview sphere, `size` pixels square
+    pub fn render(&self, size: u32) -> RgbaImage {
+        let size = size.clamp(1, MAX_PREVIEW_SIZE);
+        let rows: Vec<Vec<u8>> = (0..size)
+            .into_par_iter()
+            .map(|y| {
+                let mut row = Vec::with_capacity(size as usize * 4);
+                for x in 0..size {
+                    let mut sum = [0.0f32; 4];
+                    for (dx, dy) in [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)] {
+                        // The sphere fills 90% of the swatch
+                        let px = ((x as f32 + dx) / size as f32 * 2.0 - 1.0) / 0.9;
+                        let py = (1.0 - (y as f32 + dy) / size as f32 * 2.0) / 0.9;
+                        if let Some([r, g, b, a]) = self.shade(px, py) {
+                            // Premultiplied, so edge samples blend with the
+                            // transparent background
+                            sum = [sum[0] + r * a, sum[1] + g * a, sum[2] + b * a, sum[3] + a];
+                        }
+                    }
+                    let alpha = sum[3] / 4.0;
+                    let color = [0, 1, 2].map(|k| {
+                        if sum[3] > 0.0 {
+                            linear_to_srgb(sum[k] / sum[3])
+                        } else {
+                            0.0
+                        }
+                    });
+                    row.extend(
+                        [color[0], color[1], color[2], alpha].map(|c| (c * 255.0).round() as u8),
+                    );
+                }
+                row
+            })
+            .collect();
+        RgbaImage::from_raw(size, size, rows.concat()).expect("one pixel per sample")
+    }
+
+    /// Linear color and coverage where the view ray through `(px, py)`
+    /// meets the sphere, `None` if it misses
+    fn shade(&self, px: f32, py: f32) -> Option<[f32; 4]> {
+        let r2 = px * px + py * py;
+        if r2 > 1.0 {
+            return None;
+        }
+        let n = [px, py, (1.0 - r2).sqrt()];
+        let longitude = n[0].atan2(n[2]);
+        let uv = [0.5 + longitude / TAU, n[1].clamp(-1.0, 1.0).acos() / PI];
+
+        let sample = |texture: &Option<Texture>| texture.as_ref().map(|t| t.sample(uv));
+        let base_texel = sample(&self.base_color_texture).unwrap_or([1.0; 4]);
+        let base: [f32; 4] = std::array::from_fn(|k| self.base_color[k] * base_texel[k]);
+        let alpha = match self.alpha_mode {
+            AlphaMode::Opaque => 1.0,
+            AlphaMode::Mask(cutoff) if base[3] < cutoff => return None,
+            AlphaMode::Mask(_) => 1.0,
+            AlphaMode::Blend => base[3].clamp(0.0, 1.0),
+        };
+        if self.unlit {
+            return Some([base[0], base[1], base[2], alpha]);
+        }
+        let emissive_texel = sample(&self.emissive_texture).unwrap_or([1.0; 4]);
+        let emissive: [f32; 3] = std::array::from_fn(|k| self.emissive[k] * emissive_texel[k]);
+
+        let mr = sample(&self.metallic_roughness_texture).unwrap_or([1.0; 4]);
+        let metallic = (self.metallic * mr[2]).clamp(0.0, 1.0);
+        let roughness = (self.roughness * mr[1]).clamp(0.04, 1.0);
+        let occlusion = self
+            .occlusion_texture
+            .as_ref()
+            .map_or(1.0, |(t, strength)| {
+                1.0 + strength * (t.sample(uv)[0] - 1.0)
+            });
+        let normal = match &self.normal_texture {
+            Some((texture, scale)) => {
+                let t = texture.sample(uv);
+                let tilt = [
+                    (t[0] * 2.0 - 1.0) * scale,
+                    (t[1] * 2.0 - 1.0) * scale,
+                    t[2] * 2.0 - 1.0,
+                ];
+                // Tangent along increasing U, bitangent toward the top of
+                // the image, which on the sphere is up
+                let tangent = [longitude.cos(), 0.0, -longitude.sin()];
+                let bitangent = cross(n, tangent);
+                normalize(
+                    [0, 1, 2]
+                        .map(|k| tilt[0] * tangent[k] + tilt[1] * bitangent[k] + tilt[2] * n[k]),
+                )
+            }
+            None => n,
+        };
+
+        let view = [0.0, 0.0, 1.0];
+        let albedo = [base[0], base[1], base[2]];
+        let f0: [f32; 3] = std::array::from_fn(|k| 0.04 + (albedo[k] - 0.04) * metallic);
+        let diffuse: [f32; 3] = std::array::from_fn(|k| albedo[k] * (1.0 - metallic));
+
+        let n_dot_v = dot(normal, view).max(1e-4);
+        let n_dot_l = dot(normal, LIGHT).max(0.0);
+        let half = normalize([LIGHT[0] + view[0], LIGHT[1] + view[1], LIGHT[2] + view[2]]);
+        let n_dot_h = dot(normal, half).max(0.0);
+        let v_dot_h = dot(view, half).max(0.0);
+        let a2 = (roughness * roughness).powi(2);
+        let distribution = a2 / (PI * (n_dot_h * n_dot_h * (a2 - 1.0) + 1.0).powi(2));
+        let k = (roughness + 1.0).powi(2) / 8.0;
+        let geometry = n_dot_l / (n_dot_l * (1.0 - k) + k) * n_dot_v / (n_dot_v * (1.0 - k) + k);
+        let fresnel: [f32; 3] =
+            std::array::from_fn(|c| f0[c] + (1.0 - f0[c]) * (1.0 - v_dot_h).powi(5));
+
+        // Ambient: sky above, ground below, a blurrier reflection when rough
+        let reflected = {
+            let d = 2.0 * dot(normal, view);
+            [normal[0] * d, normal[1] * d, normal[2] * d - 1.0]
+        };
+        let sky = |direction: [f32; 3]| -> [f32; 3] {
+            let t = direction[1] * 0.5 + 0.5;
+            std::array::from_fn(|c| GROUND[c] + (SKY[c] - GROUND[c]) * t)
+        };
+        let irradiance = sky(normal);
+        let blurred =
+            sky(normalize([0, 1, 2].map(|c| {
+                reflected[c] + (normal[c] - reflected[c]) * roughness
+            })));
+        let ambient_fresnel: [f32; 3] = std::array::from_fn(|c| {
+            f0[c] + (1.0 - roughness).max(f0[c]) * (1.0 - n_dot_v).powi(5) * (1.0 - f0[c])
+        });
+
+        let color: [f32; 3] = std::array::from_fn(|c| {
+            let specular =
+                distribution * geometry * fresnel[c] / (4.0 * n_dot_v * n_dot_l.max(1e-4));
+            let direct =
+                (diffuse[c] * (1.0 - fresnel[c]) / PI + specular) * n_dot_l * LIGHT_INTENSITY;
+            let ambient =
+                (diffuse[c] * irradiance[c] + ambient_fresnel[c] * blurred[c]) * occlusion;
+            direct + ambient + emissive[c]
+        });
+        Some([color[0], color[1], color[2], alpha])
+    }
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = dot(v, v).sqrt();
+    if length > 0.0 {
+        v.map(|c| c / length)
+    } else {
+        [0.0, 0.0, 1.0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_swatches_follow_material_factors() {
+        let document = GltfDocument {
+            json: json!({
+                "materials": [
+                    {
+                        "name": "red plastic",
+                        "pbrMetallicRoughness": {
+                            "baseColorFactor": [0.8, 0.05, 0.05, 1.0],
+                            "metallicFactor": 0.0,
+                            "roughnessFactor": 0.6
+                        }
+                    },
+                    {
+                        "pbrMetallicRoughness": { "baseColorFactor": [1.0, 1.0, 1.0, 0.1] },
+                        "alphaMode": "MASK"
+                    },
+                    { "emissiveFactor": [0.0, 1.0, 0.0], "extensions": { "KHR_materials_unlit": {} } }
+                ]
+            }),
+            bin: Vec::new(),
+        };
+        let materials = PreviewMaterial::all(&document);
+        assert_eq!(materials.len(), 3);
+        assert_eq!(materials[1].name, "Material 1");
+
+        let red = materials[0].render(32);
+        assert_eq!(red.dimensions(), (32, 32));
+        // Corners are outside the sphere; the center is lit and red
+        assert_eq!(red.get_pixel(0, 0).0[3], 0);
+        let [r, g, b, a] = red.get_pixel(16, 16).0;
+        assert_eq!(a, 255);
+        assert!(r > 2 * g && r > 2 * b, "{:?}", (r, g, b));
+        // Lit from the upper left
+        let upper_left = red.get_pixel(10, 10).0[0];
+        let lower_right = red.get_pixel(22, 22).0[0];
+        assert!(upper_left > lower_right);
+
+        // Masked out entirely below the cutoff
+        assert!(materials[1].render(16).pixels().all(|p| p.0[3] == 0));
+        // Unlit shows the base color only
+        assert_eq!(
+            materials[2].render(8).get_pixel(4, 4).0,
+            [255, 255, 255, 255]
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Video and audio files kept alongside assets
+//!
+//! Asset folders sometimes hold a turntable video or a sound effect. Those
+//! sidecars are reported with what can be read from their headers without a
+//! decoder: duration for MP4/MOV, WAV and FLAC files, and frame size for
+//! MP4/MOV. Other formats are listed with their size only.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+pub const VIDEO_EXTENSIONS: [&str; 6] = ["mp4", "mov", "m4v", "webm", "mkv", "avi"];
+pub const AUDIO_EXTENSIONS: [&str; 6] = ["wav", "mp3", "ogg", "flac", "m4a", "aac"];
+/// Largest `moov` box read whole; real ones are well under this
+const MAX_MOOV_BYTES: u64 = 64 << 20;
+
+/// What a file's headers tell
+#[derive(Default)]
+struct Headers {
+    duration_secs: Option<f64>,
+    frame_size: Option<(u32, u32)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    Video,
+    Audio,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaFile {
+    pub path: String,
+    pub kind: MediaKind,
+    pub size_bytes: u64,
+    pub duration_secs: Option<f64>,
+    /// Frame size of the largest video track
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+pub fn media_kind(path: &Path) -> Option<MediaKind> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    if VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+        Some(MediaKind::Video)
+    } else if AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+        Some(MediaKind::Audio)
+    } else {
+        None
+    }
+}
+
+/// Media files belonging to the asset `id` whose files are in `dir`
+///
+/// When the folder is the asset's own, every media file in it counts.
+/// Otherwise only those named after the asset do: `<id>.mp4` or
+/// `<id>.<anything>.mp4`, like `rock.turntable.mp4`.
+pub fn sidecars(dir: &Path, id: &str, own_folder: bool) -> Vec<PathBuf> {
+    let walk = WalkDir::new(dir)
+        .max_depth(if own_folder { usize::MAX } else { 1 })
+        .sort_by_file_name();
+    walk.into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| media_kind(path).is_some())
+        .filter(|path| {
+            own_folder
+                || path.file_stem().is_some_and(|stem| {
+                    let stem = stem.to_string_lossy();
+                    stem == id || stem.starts_with(&format!("{}.", id))
+                })
+        })
+        .collect()
+}
+
+/// Kind, size and whatever the headers tell of a media file
+///
+/// Headers that can't be parsed leave the metadata empty rather than
+/// failing, since the file is still worth listing.
+pub fn probe(path: &Path) -> Result<MediaFile, String> {
+    let kind = media_kind(path).ok_or_else(|| format!("Not a media file: {}", path.display()))?;
+    let size_bytes = fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    let ext = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    let mut file = File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let audio = |duration_secs| Headers {
+        duration_secs,
+        frame_size: None,
+    };
+    let headers = match ext.as_str() {
+        "mp4" | "mov" | "m4v" | "m4a" => probe_mp4(&mut file, size_bytes),
+        "wav" => Some(audio(probe_wav(&mut file))),
+        "flac" => Some(audio(probe_flac(&mut file))),
+        _ => None,
+    }
+    .unwrap_or_default();
+    Ok(MediaFile {
+        path: path.to_string_lossy().to_string(),
+        kind,
+        size_bytes,
+        duration_secs: headers.duration_secs,
+        width: headers.frame_size.map(|(width, _)| width),
+        height: headers.frame_size.map(|(_, height)| height),
+    })
+}
+
+/// Duration from `mvhd` and the largest `tkhd` frame size of an ISO media
+/// file, skipping over `mdat` and everything else outside `moov`
+fn probe_mp4<R: Read + Seek>(reader: &mut R, file_len: u64) -> Option<Headers> {
+    let mut offset = 0;
+    while offset + 8 <= file_len {
+        reader.seek(SeekFrom::Start(offset)).ok()?;
+        let (kind, header_len, box_len) = box_header(reader, file_len - offset)?;
+        if &kind == b"moov" {
+            let body_len = box_len - header_len;
+            if body_len > MAX_MOOV_BYTES {
+                return None;
+            }
+            let mut moov = vec![0; body_len as usize];
+            reader.read_exact(&mut moov).ok()?;
+            return Some(read_moov(&moov));
+        }
+        offset += box_len;
+    }
+    None
+}
+
+/// Type, header length and total length of the box starting at the reader
+fn box_header<R: Read>(reader: &mut R, remaining: u64) -> Option<([u8; 4], u64, u64)> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header).ok()?;
+    let kind: [u8; 4] = header[4..8].try_into().ok()?;
+    let (header_len, box_len) = match u32::from_be_bytes(header[0..4].try_into().ok()?) {
+        0 => (8, remaining),
+        1 => {
+            let mut large = [0u8; 8];
+            reader.read_exact(&mut large).ok()?;
+            (16, u64::from_be_bytes(large))
+        }
+        len => (8, len as u64),
+    };
+    (box_len >= header_len && box_len <= remaining).then_some((kind, header_len, box_len))
+}
+
+fn read_moov(moov: &[u8]) -> Headers {
+    let mut duration = None;
+    let mut size: Option<(u32, u32)> = None;
+    for (kind, body) in boxes(moov) {
+        match &kind {
+            b"mvhd" => duration = mvhd_duration(body),
+            b"trak" => {
+                let track = boxes(body)
+                    .filter(|(kind, _)| kind == b"tkhd")
+                    .find_map(|(_, tkhd)| tkhd_size(tkhd));
+                if let Some((width, height)) = track {
+                    if size.is_none_or(|(w, h)| width * height > w * h) {
+                        size = Some((width, height));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Headers {
+        duration_secs: duration,
+        frame_size: size,
+    }
+}
+
+/// Child boxes within an in-memory box body
+fn boxes(data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let mut rest = data.get(offset..)?;
+        let (kind, header_len, box_len) = box_header(&mut rest, (data.len() - offset) as u64)?;
+        let body = data.get(offset + header_len as usize..offset + box_len as usize)?;
+        offset += box_len as usize;
+        Some((kind, body))
+    })
+}
+
+fn mvhd_duration(body: &[u8]) -> Option<f64> {
+    let (timescale, duration) = match body.first()? {
+        1 => (be_u32(body, 20)?, be_u64(body, 24)?),
+        _ => (be_u32(body, 12)?, be_u32(body, 16)? as u64),
+    };
+    (timescale > 0).then(|| duration as f64 / timescale as f64)
+}
+
+/// Width and height of a track, or `None` for tracks without a picture
+fn tkhd_size(body: &[u8]) -> Option<(u32, u32)> {
+    // Version, flags, times, track id and duration, then 52 bytes of
+    // reserved fields, layer, volume and matrix
+    let start = if *body.first()? == 1 { 36 } else { 24 } + 52;
+    let width = be_u32(body, start)? >> 16;
+    let height = be_u32(body, start + 4)? >> 16;
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+/// Duration of a RIFF WAVE file from its byte rate and data size
+fn probe_wav<R: Read + Seek>(reader: &mut R) -> Option<f64> {
+    let mut riff = [0u8; 12];
+    reader.read_exact(&mut riff).ok()?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return None;
+    }
+    let mut byte_rate = None;
+    loop {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).ok()?;
+        let len = u32::from_le_bytes(header[4..8].try_into().ok()?);
+        match &header[0..4] {
+            b"fmt " => {
+                let mut fmt = [0u8; 12];
+                reader.read_exact(&mut fmt).ok()?;
+                byte_rate = Some(u32::from_le_bytes(fmt[8..12].try_into().ok()?));
+                reader.seek(SeekFrom::Current(len as i64 - 12)).ok()?;
+            }
+            b"data" => {
+                let rate = byte_rate.filter(|&rate| rate > 0)?;
+                return Some(len as f64 / rate as f64);
+            }
+            _ => {
+                reader.seek(SeekFrom::Current(len as i64)).ok()?;
+            }
+        }
+        // Chunks are padded to an even length
+        if len % 2 == 1 {
+            reader.seek(SeekFrom::Current(1)).ok()?;
+        }
+    }
+}
+
+/// Duration of a FLAC file from the sample count in its STREAMINFO block
+fn probe_flac<R: Read>(reader: &mut R) -> Option<f64> {
+    let mut header = [0u8; 8 + 18];
+    reader.read_exact(&mut header).ok()?;
+    if &header[0..4] != b"fLaC" || header[4] & 0x7f != 0 {
+        return None;
+    }
+    let info = &header[8..];
+    let sample_rate =
+        (u32::from(info[10]) << 12) | (u32::from(info[11]) << 4) | (u32::from(info[12]) >> 4);
+    let samples = (u64::from(info[13] & 0x0f) << 32) | be_u32(info, 14)? as u64;
+    (sample_rate > 0 && samples > 0).then(|| samples as f64 / sample_rate as f64)
+}
+
+fn be_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn be_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut bytes = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    #[test]
+    fn test_probes_sidecar_headers() {
+        let dir = std::env::temp_dir().join(format!("sweedle-media-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // 2.5 seconds at 1000 units per second, one 1920x1080 video track
+        let mut mvhd = vec![0; 100];
+        mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&2500u32.to_be_bytes());
+        let mut tkhd = vec![0; 84];
+        tkhd[76..80].copy_from_slice(&(1920u32 << 16).to_be_bytes());
+        tkhd[80..84].copy_from_slice(&(1080u32 << 16).to_be_bytes());
+        let mut mp4 = mp4_box(b"ftyp", b"isom");
+        mp4.extend(mp4_box(b"mdat", &[0; 64]));
+        let mut moov = mp4_box(b"mvhd", &mvhd);
+        moov.extend(mp4_box(b"trak", &mp4_box(b"tkhd", &tkhd)));
+        mp4.extend(mp4_box(b"moov", &moov));
+        fs::write(dir.join("rock.turntable.mp4"), &mp4).unwrap();
+
+        // Half a second of 16-bit mono at 8 kHz
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&[1, 0, 1, 0]);
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&16000u32.to_le_bytes());
+        wav.extend_from_slice(&[2, 0, 16, 0]);
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend(vec![0; 8000]);
+        fs::write(dir.join("rock.wav"), &wav).unwrap();
+        fs::write(dir.join("rockfall.mp3"), b"ID3").unwrap();
+
+        let found = sidecars(&dir, "rock", false);
+        let names: Vec<_> = found.iter().map(|p| p.file_name().unwrap()).collect();
+        assert_eq!(names, ["rock.turntable.mp4", "rock.wav"]);
+        assert_eq!(sidecars(&dir, "rock", true).len(), 3);
+
+        let video = probe(&found[0]).unwrap();
+        assert_eq!(video.kind, MediaKind::Video);
+        assert_eq!(video.duration_secs, Some(2.5));
+        assert_eq!((video.width, video.height), (Some(1920), Some(1080)));
+        let audio = probe(&found[1]).unwrap();
+        assert_eq!(
+            (audio.kind, audio.duration_secs),
+            (MediaKind::Audio, Some(0.5))
+        );
+        let unknown = probe(&dir.join("rockfall.mp3")).unwrap();
+        assert_eq!((unknown.size_bytes, unknown.duration_secs), (3, None));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
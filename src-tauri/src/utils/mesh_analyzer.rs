@@ -1,4 +1,4 @@
-use rayon::prelude::*;
+use crate::utils::simd;
 
 /// Analyze mesh topology and return statistics
 pub struct MeshAnalyzer {
@@ -13,96 +13,12 @@ impl MeshAnalyzer {
 
     /// Count unique vertices (removing duplicates within epsilon)
     pub fn count_unique_vertices(&self, epsilon: f32) -> usize {
-        if self.vertices.is_empty() {
-            return 0;
-        }
-
-        let vertex_count = self.vertices.len() / 3;
-        let epsilon_sq = epsilon * epsilon;
-
-        // Simple O(n^2) duplicate detection - could be optimized with spatial hashing
-        let mut unique_count = 0;
-        let mut is_duplicate = vec![false; vertex_count];
-
-        for i in 0..vertex_count {
-            if is_duplicate[i] {
-                continue;
-            }
-
-            unique_count += 1;
-            let vi = [
-                self.vertices[i * 3],
-                self.vertices[i * 3 + 1],
-                self.vertices[i * 3 + 2],
-            ];
-
-            for j in (i + 1)..vertex_count {
-                if is_duplicate[j] {
-                    continue;
-                }
-
-                let vj = [
-                    self.vertices[j * 3],
-                    self.vertices[j * 3 + 1],
-                    self.vertices[j * 3 + 2],
-                ];
-
-                let dist_sq = (vi[0] - vj[0]).powi(2)
-                    + (vi[1] - vj[1]).powi(2)
-                    + (vi[2] - vj[2]).powi(2);
-
-                if dist_sq < epsilon_sq {
-                    is_duplicate[j] = true;
-                }
-            }
-        }
-
-        unique_count
+        simd::count_unique_vertices(&self.vertices, epsilon)
     }
 
     /// Calculate the bounding box of the mesh
     pub fn calculate_bounds(&self) -> ([f32; 3], [f32; 3]) {
-        if self.vertices.is_empty() {
-            return ([0.0; 3], [0.0; 3]);
-        }
-
-        let vertex_count = self.vertices.len() / 3;
-
-        // Parallel min/max calculation
-        let (min, max) = (0..vertex_count)
-            .into_par_iter()
-            .map(|i| {
-                let v = [
-                    self.vertices[i * 3],
-                    self.vertices[i * 3 + 1],
-                    self.vertices[i * 3 + 2],
-                ];
-                (v, v)
-            })
-            .reduce(
-                || {
-                    (
-                        [f32::MAX, f32::MAX, f32::MAX],
-                        [f32::MIN, f32::MIN, f32::MIN],
-                    )
-                },
-                |(min_a, max_a), (min_b, max_b)| {
-                    (
-                        [
-                            min_a[0].min(min_b[0]),
-                            min_a[1].min(min_b[1]),
-                            min_a[2].min(min_b[2]),
-                        ],
-                        [
-                            max_a[0].max(max_b[0]),
-                            max_a[1].max(max_b[1]),
-                            max_a[2].max(max_b[2]),
-                        ],
-                    )
-                },
-            );
-
-        (min, max)
+        simd::bounds(&self.vertices).unwrap_or(([0.0; 3], [0.0; 3]))
     }
 
     /// Find connected components in the mesh
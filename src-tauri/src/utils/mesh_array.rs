@@ -0,0 +1,214 @@
+//! Linear, radial and grid arrays of a mesh
+//!
+//! Tiled floors, fences and radial assemblies like bolt circles repeat one
+//! mesh at regular placements. An array is merged into one mesh for editing
+//! in the viewer, or written once with its placements as GPU instances,
+//! which keeps large arrays small on disk and cheap to draw.
+
+use crate::utils::instancing::{InstancedMesh, Instancing, Trs};
+use crate::utils::mesh_files::NamedMesh;
+use crate::utils::mesh_store::MeshData;
+use nalgebra::{Point3, Quaternion, Unit, UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// Most copies one array makes
+pub const MAX_COPIES: usize = 10_000;
+
+/// Placements of the copies, the first always where the mesh is
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ArrayPattern {
+    /// `count` copies in a row, each `offset` from the one before
+    Linear { count: u32, offset: [f32; 3] },
+    /// `count` copies turned about `axis` through `center`, spread evenly
+    /// over `angle_degrees` (a full turn by default)
+    Radial {
+        count: u32,
+        axis: [f32; 3],
+        center: [f32; 3],
+        angle_degrees: Option<f32>,
+    },
+    /// `counts` copies along X, Y and Z, `spacing` apart
+    Grid { counts: [u32; 3], spacing: [f32; 3] },
+}
+
+impl ArrayPattern {
+    /// Placement of every copy
+    pub fn placements(&self) -> Result<Vec<Trs>, String> {
+        let copies = match *self {
+            ArrayPattern::Linear { count, .. } | ArrayPattern::Radial { count, .. } => {
+                count as usize
+            }
+            ArrayPattern::Grid { counts, .. } => counts.iter().map(|&c| c as usize).product(),
+        };
+        if copies == 0 || copies > MAX_COPIES {
+            return Err(format!(
+                "An array makes 1 to {} copies, not {}",
+                MAX_COPIES, copies
+            ));
+        }
+        let at = |translation: [f32; 3]| Trs {
+            translation,
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: [1.0; 3],
+        };
+
+        match *self {
+            ArrayPattern::Linear { count, offset } => Ok((0..count)
+                .map(|i| at(offset.map(|d| d * i as f32)))
+                .collect()),
+            ArrayPattern::Grid { counts, spacing } => {
+                let mut placements = Vec::with_capacity(copies);
+                for z in 0..counts[2] {
+                    for y in 0..counts[1] {
+                        for x in 0..counts[0] {
+                            let step = [x, y, z];
+                            placements.push(at([0, 1, 2].map(|k| spacing[k] * step[k] as f32)));
+                        }
+                    }
+                }
+                Ok(placements)
+            }
+            ArrayPattern::Radial {
+                count,
+                axis,
+                center,
+                angle_degrees,
+            } => {
+                let axis = Unit::try_new(Vector3::from(axis), f32::EPSILON)
+                    .ok_or("A radial array needs a non-zero axis")?;
+                let sweep = angle_degrees.unwrap_or(360.0).to_radians();
+                if !sweep.is_finite() {
+                    return Err("The array's angle must be a finite number".to_string());
+                }
+                // A full turn would put the last copy on the first
+                let full_turn = (sweep.abs() - std::f32::consts::TAU).abs() < 1e-4;
+                let steps = if full_turn || count == 1 {
+                    count
+                } else {
+                    count - 1
+                };
+                let center = Point3::from(center);
+                Ok((0..count)
+                    .map(|i| {
+                        let turn =
+                            UnitQuaternion::from_axis_angle(&axis, sweep * i as f32 / steps as f32);
+                        // Turning about the center is turning about the
+                        // origin, then moving the center back where it was
+                        let translation = center - turn * center;
+                        Trs {
+                            translation: translation.into(),
+                            rotation: [turn.i, turn.j, turn.k, turn.w],
+                            scale: [1.0; 3],
+                        }
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Every copy of `mesh` in one mesh, in placement order
+///
+/// Attributes are copied as they are; normals turn with their copy.
+pub fn merge(mesh: &MeshData, placements: &[Trs]) -> MeshData {
+    let n = mesh.vertex_count();
+    let order: Vec<usize> = placements.iter().flat_map(|_| 0..n).collect();
+    let indices = (0..placements.len())
+        .flat_map(|copy| mesh.indices.iter().map(move |&i| i + (copy * n) as u32))
+        .collect();
+    let mut merged = mesh.with_vertices(&order, indices);
+    merged.polygons = mesh
+        .polygons
+        .as_ref()
+        .map(|polygons| polygons.repeat(placements.len()));
+
+    for (copy, placement) in placements.iter().enumerate() {
+        let [x, y, z, w] = placement.rotation;
+        let turn = UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z));
+        let translation = Vector3::from(placement.translation);
+        let range = copy * n * 3..(copy + 1) * n * 3;
+        for p in merged.positions[range.clone()].chunks_exact_mut(3) {
+            let moved = turn * Point3::new(p[0], p[1], p[2]) + translation;
+            p.copy_from_slice(moved.coords.as_slice());
+        }
+        if let Some(normals) = &mut merged.normals {
+            for v in normals[range].chunks_exact_mut(3) {
+                let turned = turn * Vector3::new(v[0], v[1], v[2]);
+                v.copy_from_slice(turned.as_slice());
+            }
+        }
+    }
+    merged
+}
+
+/// `mesh` written once under `name` with every placement as an instance
+pub fn instance(mesh: &MeshData, name: &str, placements: &[Trs]) -> Instancing {
+    Instancing {
+        instanced: vec![InstancedMesh {
+            mesh: NamedMesh {
+                name: name.to_string(),
+                positions: mesh.positions.clone(),
+                indices: mesh.indices.clone(),
+                polygons: mesh.polygons.clone(),
+            },
+            instances: placements.to_vec(),
+        }],
+        baked: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_place_copies() {
+        let triangle = MeshData {
+            positions: vec![1.0, 0.0, 0.0, 2.0, 0.0, 0.0, 1.0, 1.0, 0.0],
+            normals: Some([0.0, 0.0, 1.0].repeat(3)),
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+
+        let grid = ArrayPattern::Grid {
+            counts: [3, 2, 1],
+            spacing: [5.0, 4.0, 0.0],
+        };
+        let placements = grid.placements().unwrap();
+        assert_eq!(placements.len(), 6);
+        assert_eq!(placements[5].translation, [10.0, 4.0, 0.0]);
+        let merged = merge(&triangle, &placements);
+        assert_eq!(merged.face_count(), 6);
+        assert_eq!(&merged.indices[15..], [15, 16, 17]);
+
+        // Four copies a quarter turn apart about Y through the origin
+        let radial = ArrayPattern::Radial {
+            count: 4,
+            axis: [0.0, 1.0, 0.0],
+            center: [0.0; 3],
+            angle_degrees: None,
+        };
+        let merged = merge(&triangle, &radial.placements().unwrap());
+        let second = &merged.positions[9..12];
+        assert!((second[0]).abs() < 1e-5 && (second[2] + 1.0).abs() < 1e-5);
+        let normal = &merged.normals.as_ref().unwrap()[9..12];
+        assert!((normal[0] - 1.0).abs() < 1e-5);
+
+        // Half a turn spreads three copies from 0 to 180 degrees
+        let half = ArrayPattern::Radial {
+            count: 3,
+            axis: [0.0, 1.0, 0.0],
+            center: [0.0; 3],
+            angle_degrees: Some(180.0),
+        };
+        let merged = merge(&triangle, &half.placements().unwrap());
+        assert!((merged.positions[18] + 1.0).abs() < 1e-5);
+
+        let none = ArrayPattern::Linear {
+            count: 0,
+            offset: [1.0, 0.0, 0.0],
+        };
+        assert!(none.placements().is_err());
+    }
+}
@@ -0,0 +1,272 @@
+//! Where a revised mesh departs from the original
+//!
+//! Every vertex of the revision is measured against the closest point on
+//! the original's surface, signed by the side of the surface it lies on, so
+//! the viewer can paint a diverging heatmap: swellings one colour, dents
+//! the other. Vertices that moved by at least the resolution are grouped
+//! into regions along B's edges, for jumping to each change.
+
+use crate::utils::mesh_store::MeshData;
+use crate::utils::shrinkwrap::SurfaceIndex;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Share of the original's bounding box diagonal used when no resolution is
+/// given
+const DEFAULT_RESOLUTION: f32 = 0.005;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpatialDiff {
+    /// Signed distance of each vertex of B from A's surface: positive on
+    /// the side A's faces point to, negative behind them
+    pub deviations: Vec<f32>,
+    /// Largest absolute deviation: map colours over `-range..range`
+    pub range: f32,
+    pub mean_deviation: f32,
+    pub rms_deviation: f32,
+    /// Smallest deviation counted as a change
+    pub resolution: f32,
+    pub changed_vertex_count: usize,
+    /// Largest first
+    pub regions: Vec<ChangedRegion>,
+}
+
+/// Changed vertices of B joined by edges
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedRegion {
+    pub vertex_count: usize,
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+    pub centroid: [f32; 3],
+    /// Deviation of largest magnitude in the region, with its sign
+    pub peak_deviation: f32,
+    pub mean_deviation: f32,
+}
+
+/// Deviation of `b` from `a`, with changes of at least `resolution` grouped
+/// into regions
+///
+/// `resolution` defaults to 0.5% of A's bounding box diagonal.
+pub fn diff(a: &MeshData, b: &MeshData, resolution: Option<f32>) -> Result<SpatialDiff, String> {
+    let surface = SurfaceIndex::new(&a.positions, &a.indices)
+        .ok_or_else(|| "Mesh A has no faces to compare against".to_string())?;
+    let resolution = match resolution {
+        Some(r) if r.is_finite() && r > 0.0 => r,
+        Some(r) => return Err(format!("Resolution must be positive, got {}", r)),
+        None => (diagonal(&a.positions) * DEFAULT_RESOLUTION).max(f32::EPSILON),
+    };
+
+    let deviations: Vec<f32> = b
+        .positions
+        .par_chunks_exact(3)
+        .map(|p| {
+            let p = [p[0], p[1], p[2]];
+            let Some((face, q)) = surface.closest_face(p) else {
+                return 0.0;
+            };
+            let offset = sub(p, q);
+            let distance = dot(offset, offset).sqrt();
+            if dot(offset, face_normal(a, face)) < 0.0 {
+                -distance
+            } else {
+                distance
+            }
+        })
+        .collect();
+
+    let count = deviations.len().max(1) as f32;
+    let range = deviations.iter().fold(0.0f32, |m, d| m.max(d.abs()));
+    let mean_deviation = deviations.iter().map(|d| d.abs()).sum::<f32>() / count;
+    let rms_deviation = (deviations.iter().map(|d| d * d).sum::<f32>() / count).sqrt();
+    let changed: Vec<usize> = (0..deviations.len())
+        .filter(|&v| deviations[v].abs() >= resolution)
+        .collect();
+    let regions = regions(b, &deviations, &changed);
+
+    Ok(SpatialDiff {
+        range,
+        mean_deviation,
+        rms_deviation,
+        resolution,
+        changed_vertex_count: changed.len(),
+        regions,
+        deviations,
+    })
+}
+
+/// Group `changed` vertices joined by an edge between two changed
+/// vertices; vertices at the same position count as joined, so UV seams
+/// don't split a region
+fn regions(mesh: &MeshData, deviations: &[f32], changed: &[usize]) -> Vec<ChangedRegion> {
+    let positions = &mesh.positions;
+    let point = |v: usize| [positions[v * 3], positions[v * 3 + 1], positions[v * 3 + 2]];
+    let mut parent: Vec<usize> = (0..deviations.len()).collect();
+    fn root(parent: &mut [usize], mut v: usize) -> usize {
+        while parent[v] != v {
+            parent[v] = parent[parent[v]];
+            v = parent[v];
+        }
+        v
+    }
+    let join = |parent: &mut [usize], a: usize, b: usize| {
+        let (a, b) = (root(parent, a), root(parent, b));
+        parent[a.max(b)] = a.min(b);
+    };
+
+    let is_changed = {
+        let mut flags = vec![false; deviations.len()];
+        for &v in changed {
+            flags[v] = true;
+        }
+        flags
+    };
+    let mut welded: HashMap<[u32; 3], usize> = HashMap::new();
+    for &v in changed {
+        let key = point(v).map(f32::to_bits);
+        let first = *welded.entry(key).or_insert(v);
+        join(&mut parent, first, v);
+    }
+    for face in mesh.indices.chunks_exact(3) {
+        for k in 0..3 {
+            let (a, b) = (face[k] as usize, face[(k + 1) % 3] as usize);
+            if a < is_changed.len() && b < is_changed.len() && is_changed[a] && is_changed[b] {
+                join(&mut parent, a, b);
+            }
+        }
+    }
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &v in changed {
+        groups.entry(root(&mut parent, v)).or_default().push(v);
+    }
+
+    let mut regions = Vec::new();
+    for members in groups.into_values() {
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        let mut sum = [0.0f32; 3];
+        let (mut peak, mut total) = (0.0f32, 0.0f32);
+        for &v in &members {
+            let p = point(v);
+            for axis in 0..3 {
+                min[axis] = min[axis].min(p[axis]);
+                max[axis] = max[axis].max(p[axis]);
+                sum[axis] += p[axis];
+            }
+            if deviations[v].abs() > peak.abs() {
+                peak = deviations[v];
+            }
+            total += deviations[v].abs();
+        }
+        let n = members.len() as f32;
+        regions.push(ChangedRegion {
+            vertex_count: members.len(),
+            min,
+            max,
+            centroid: sum.map(|s| s / n),
+            peak_deviation: peak,
+            mean_deviation: total / n,
+        });
+    }
+    regions.sort_by(|a, b| {
+        b.vertex_count
+            .cmp(&a.vertex_count)
+            .then(b.peak_deviation.abs().total_cmp(&a.peak_deviation.abs()))
+    });
+    regions
+}
+
+fn face_normal(mesh: &MeshData, face: usize) -> [f32; 3] {
+    let corner = |i: usize| {
+        let b = mesh.indices[face * 3 + i] as usize * 3;
+        [
+            mesh.positions[b],
+            mesh.positions[b + 1],
+            mesh.positions[b + 2],
+        ]
+    };
+    let (a, b, c) = (corner(0), corner(1), corner(2));
+    cross(sub(b, a), sub(c, a))
+}
+
+fn diagonal(positions: &[f32]) -> f32 {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in positions.chunks_exact(3) {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(p[axis]);
+            max[axis] = max[axis].max(p[axis]);
+        }
+    }
+    let d = sub(max, min);
+    dot(d, d).sqrt()
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A flat `n` by `n` grid of quads on z = 0, facing +z
+    fn grid(n: u32) -> MeshData {
+        let mut positions = Vec::new();
+        for y in 0..=n {
+            for x in 0..=n {
+                positions.extend([x as f32, y as f32, 0.0]);
+            }
+        }
+        let mut indices = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * (n + 1) + x;
+                indices.extend([i, i + 1, i + n + 2, i, i + n + 2, i + n + 1]);
+            }
+        }
+        MeshData {
+            positions,
+            indices,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_signs_and_groups_changes() {
+        let a = grid(10);
+        let mut b = a.clone();
+        let vertex = |x: u32, y: u32| ((y * 11 + x) * 3 + 2) as usize;
+        // A bump near one corner, a dent and a neighbouring dent at the other
+        b.positions[vertex(1, 1)] = 0.5;
+        b.positions[vertex(8, 8)] = -0.25;
+        b.positions[vertex(9, 8)] = -0.3;
+
+        let diff = diff(&a, &b, Some(0.1)).unwrap();
+        assert_eq!(diff.deviations.len(), 121);
+        assert!((diff.deviations[vertex(1, 1) / 3] - 0.5).abs() < 1e-6);
+        assert!((diff.deviations[vertex(8, 8) / 3] + 0.25).abs() < 1e-6);
+        assert_eq!(diff.deviations[0], 0.0);
+        assert!((diff.range - 0.5).abs() < 1e-6);
+        assert_eq!(diff.changed_vertex_count, 3);
+
+        assert_eq!(diff.regions.len(), 2);
+        assert_eq!(diff.regions[0].vertex_count, 2);
+        assert!((diff.regions[0].peak_deviation + 0.3).abs() < 1e-6);
+        assert!((diff.regions[1].centroid[0] - 1.0).abs() < 1e-6);
+
+        assert!(super::diff(&a, &b, Some(0.0)).is_err());
+    }
+}
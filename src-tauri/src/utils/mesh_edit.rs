@@ -0,0 +1,225 @@
+//! Deleting, separating and cropping parts of a mesh
+//!
+//! These are the cleanup edits done by hand in the viewer: the user picks
+//! faces or vertices, and the picked faces are removed, split off into a
+//! mesh of their own, or everything outside a box is cut away. Vertices no
+//! longer used by any face are dropped. Polygons that keep all of their
+//! triangles stay polygons; those cut through fall back to triangles.
+
+use crate::utils::mesh_store::MeshData;
+use crate::utils::simd;
+use serde::{Deserialize, Serialize};
+
+/// Faces and vertices picked in the viewer
+///
+/// A face is selected if it's listed in `faces` or all of its corners are
+/// listed in `vertices`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeshSelection {
+    /// Triangle indices
+    pub faces: Vec<u32>,
+    pub vertices: Vec<u32>,
+}
+
+/// An axis-aligned box in mesh units
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CropBox {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl CropBox {
+    fn contains(&self, point: [f32; 3]) -> bool {
+        (0..3).all(|k| point[k] >= self.min[k] && point[k] <= self.max[k])
+    }
+}
+
+/// One flag per triangle, set for selected faces
+pub fn selected_faces(mesh: &MeshData, selection: &MeshSelection) -> Result<Vec<bool>, String> {
+    let face_count = mesh.face_count();
+    let mut selected = vec![false; face_count];
+    for &face in &selection.faces {
+        *selected
+            .get_mut(face as usize)
+            .ok_or_else(|| format!("Face {} is out of range ({} faces)", face, face_count))? = true;
+    }
+    if !selection.vertices.is_empty() {
+        let mut picked = vec![false; mesh.vertex_count()];
+        for &vertex in &selection.vertices {
+            *picked.get_mut(vertex as usize).ok_or_else(|| {
+                format!(
+                    "Vertex {} is out of range ({} vertices)",
+                    vertex,
+                    mesh.vertex_count()
+                )
+            })? = true;
+        }
+        for (flag, face) in selected.iter_mut().zip(mesh.indices.chunks_exact(3)) {
+            *flag |= face.iter().all(|&i| picked[i as usize]);
+        }
+    }
+    Ok(selected)
+}
+
+/// The mesh without the selected faces
+pub fn delete(mesh: &MeshData, selection: &MeshSelection) -> Result<MeshData, String> {
+    let selected = selected_faces(mesh, selection)?;
+    if !selected.contains(&true) {
+        return Err("Nothing is selected".to_string());
+    }
+    let keep: Vec<bool> = selected.iter().map(|&s| !s).collect();
+    if !keep.contains(&true) {
+        return Err("Deleting the selection would leave no faces".to_string());
+    }
+    Ok(keep_faces(mesh, &keep))
+}
+
+/// Split the selected faces off: the mesh without them, and one of them
+pub fn separate(
+    mesh: &MeshData,
+    selection: &MeshSelection,
+) -> Result<(MeshData, MeshData), String> {
+    let selected = selected_faces(mesh, selection)?;
+    if !selected.contains(&true) {
+        return Err("Nothing is selected".to_string());
+    }
+    if !selected.contains(&false) {
+        return Err("The whole mesh is selected; there is nothing to separate it from".to_string());
+    }
+    let rest: Vec<bool> = selected.iter().map(|&s| !s).collect();
+    Ok((keep_faces(mesh, &rest), keep_faces(mesh, &selected)))
+}
+
+/// The faces with every corner inside `bounds`
+pub fn crop(mesh: &MeshData, bounds: &CropBox) -> Result<MeshData, String> {
+    let ordered = (0..3).all(|k| bounds.min[k] <= bounds.max[k]);
+    if !ordered {
+        return Err("The crop box's minimum must not exceed its maximum".to_string());
+    }
+    let inside: Vec<bool> = (0..mesh.vertex_count())
+        .map(|v| bounds.contains(point(mesh, v)))
+        .collect();
+    let keep: Vec<bool> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|face| face.iter().all(|&i| inside[i as usize]))
+        .collect();
+    if !keep.contains(&true) {
+        return Err("No faces lie inside the crop box".to_string());
+    }
+    Ok(keep_faces(mesh, &keep))
+}
+
+/// The box around the corners of the selected faces
+pub fn selection_bounds(mesh: &MeshData, selection: &MeshSelection) -> Result<CropBox, String> {
+    let selected = selected_faces(mesh, selection)?;
+    let corners: Vec<f32> = mesh
+        .indices
+        .chunks_exact(3)
+        .zip(&selected)
+        .filter(|(_, &s)| s)
+        .flat_map(|(face, _)| face.iter().flat_map(|&i| point(mesh, i as usize)))
+        .collect();
+    let (min, max) = simd::bounds(&corners).ok_or("Nothing is selected")?;
+    Ok(CropBox { min, max })
+}
+
+/// A copy with only the faces flagged in `keep` and the vertices they use
+fn keep_faces(mesh: &MeshData, keep: &[bool]) -> MeshData {
+    let mut remap = vec![u32::MAX; mesh.vertex_count()];
+    let mut order = Vec::new();
+    let mut indices = Vec::new();
+    for (face, _) in mesh.indices.chunks_exact(3).zip(keep).filter(|(_, &k)| k) {
+        for &i in face {
+            let slot = &mut remap[i as usize];
+            if *slot == u32::MAX {
+                *slot = order.len() as u32;
+                order.push(i as usize);
+            }
+            indices.push(*slot);
+        }
+    }
+
+    let mut edited = mesh.with_vertices(&order, indices);
+    edited.polygons = mesh.polygons.as_ref().map(|polygons| {
+        let mut kept = Vec::new();
+        let mut first = 0;
+        for &corners in polygons {
+            let triangles = (corners as usize).saturating_sub(2);
+            let faces = &keep[first.min(keep.len())..(first + triangles).min(keep.len())];
+            match faces.iter().filter(|&&k| k).count() {
+                0 => {}
+                n if n == triangles => kept.push(corners),
+                n => kept.extend(std::iter::repeat_n(3, n)),
+            }
+            first += triangles;
+        }
+        kept
+    });
+    edited
+}
+
+fn point(mesh: &MeshData, vertex: usize) -> [f32; 3] {
+    let p = &mesh.positions[vertex * 3..vertex * 3 + 3];
+    [p[0], p[1], p[2]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delete_separate_and_crop_keep_what_they_should() {
+        // A quad made of two triangles at x 0..1, and a triangle at x 5..6
+        let mesh = MeshData {
+            positions: vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, //
+                5.0, 0.0, 0.0, 6.0, 0.0, 0.0, 5.0, 1.0, 0.0,
+            ],
+            uvs: Some((0..14).map(|i| i as f32).collect()),
+            indices: vec![0, 1, 2, 0, 2, 3, 4, 5, 6],
+            polygons: Some(vec![4, 3]),
+            ..Default::default()
+        };
+
+        let by_vertex = MeshSelection {
+            vertices: vec![4, 5, 6],
+            ..Default::default()
+        };
+        let deleted = delete(&mesh, &by_vertex).unwrap();
+        assert_eq!(deleted.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(deleted.vertex_count(), 4);
+        assert_eq!(deleted.polygons, Some(vec![4]));
+
+        // Taking one triangle of the quad leaves the other as a triangle
+        let half_quad = MeshSelection {
+            faces: vec![1],
+            ..Default::default()
+        };
+        let (rest, separated) = separate(&mesh, &half_quad).unwrap();
+        assert_eq!(rest.face_count(), 2);
+        assert_eq!(rest.polygons, Some(vec![3, 3]));
+        assert_eq!(separated.indices, [0, 1, 2]);
+        assert_eq!(separated.uvs, Some(vec![0.0, 1.0, 4.0, 5.0, 6.0, 7.0]));
+
+        let bounds = selection_bounds(&mesh, &half_quad).unwrap();
+        assert_eq!((bounds.min, bounds.max), ([0.0; 3], [1.0, 1.0, 0.0]));
+        let cropped = crop(&mesh, &bounds).unwrap();
+        assert_eq!(cropped.face_count(), 2);
+        assert_eq!(cropped.polygons, Some(vec![4]));
+
+        let everything = MeshSelection {
+            faces: vec![0, 1, 2],
+            ..Default::default()
+        };
+        assert!(delete(&mesh, &everything).is_err());
+        assert!(separate(&mesh, &everything).is_err());
+        assert!(delete(&mesh, &MeshSelection::default()).is_err());
+        let out_of_range = MeshSelection {
+            faces: vec![3],
+            ..Default::default()
+        };
+        assert!(delete(&mesh, &out_of_range).is_err());
+    }
+}
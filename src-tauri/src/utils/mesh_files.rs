@@ -0,0 +1,406 @@
+//! Triangle meshes decoded from model files on disk
+//!
+//! glTF goes through [`load_gltf`] and binary FBX through [`parse_fbx`]; OBJ
+//! and STL (binary and ASCII) are parsed here. Only positions and triangles
+//! are kept, which is all the statistics and estimators need.
+
+use crate::utils::axis_conversion::{AxisConversion, ImportAxes};
+use crate::utils::fbx::parse_fbx;
+use crate::utils::gltf_geometry::{load_gltf, read_primitives};
+use crate::utils::platform::FileBytes;
+use crate::utils::triangulate::{TriangulationOptions, TriangulationReport, Triangulator};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Model file formats that can be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshFileFormat {
+    Gltf,
+    Obj,
+    Stl,
+    Fbx,
+}
+
+impl MeshFileFormat {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "glb" | "gltf" => Ok(Self::Gltf),
+            "obj" => Ok(Self::Obj),
+            "stl" => Ok(Self::Stl),
+            "fbx" => Ok(Self::Fbx),
+            _ => Err(format!("Unsupported model format: {}", path.display())),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Gltf => "gltf",
+            Self::Obj => "obj",
+            Self::Stl => "stl",
+            Self::Fbx => "fbx",
+        }
+    }
+}
+
+/// One mesh from a file, with all of its primitives merged
+#[derive(Debug, Clone, Default)]
+pub struct NamedMesh {
+    pub name: String,
+    pub positions: Vec<f32>,
+    pub indices: Vec<u32>,
+    /// Corner count of each source polygon (see [`crate::utils::polygons`]);
+    /// `None` for formats that only store triangles
+    pub polygons: Option<Vec<u32>>,
+}
+
+impl NamedMesh {
+    /// Add triangles over `positions`, which are numbered from 0
+    pub fn append(&mut self, positions: &[f32], indices: &[u32]) {
+        let base = (self.positions.len() / 3) as u32;
+        self.positions.extend_from_slice(positions);
+        self.indices.extend(indices.iter().map(|i| i + base));
+    }
+}
+
+/// Decode every mesh in a GLB/GLTF, OBJ, STL or FBX file
+///
+/// glTF and FBX meshes are returned once per node instance in world space;
+/// OBJ objects and groups become separate meshes; an STL is a single mesh.
+/// Positions are in the file's own axes and units, and polygons are
+/// triangulated with the default options.
+pub fn load_meshes(path: &Path) -> Result<(MeshFileFormat, Vec<NamedMesh>), String> {
+    decode(path, TriangulationOptions::default()).map(|decoded| (decoded.format, decoded.meshes))
+}
+
+/// Meshes converted to Y-up meters on import
+#[derive(Debug, Clone)]
+pub struct ImportedMeshes {
+    pub format: MeshFileFormat,
+    pub meshes: Vec<NamedMesh>,
+    /// Conversion that was applied
+    pub conversion: AxisConversion,
+    pub triangulation: TriangulationReport,
+}
+
+/// Decode a model file and convert it into glTF's Y-up meters
+///
+/// `conversion` overrides everything else. Otherwise FBX files use the axes
+/// they declare, and OBJ and STL files the per-format default from `axes`;
+/// glTF is left as is. OBJ and FBX polygons are split with `triangulation`.
+pub fn import_meshes(
+    path: &Path,
+    axes: &ImportAxes,
+    conversion: Option<AxisConversion>,
+    triangulation: TriangulationOptions,
+) -> Result<ImportedMeshes, String> {
+    let Decoded {
+        format,
+        mut meshes,
+        declared,
+        triangulation,
+    } = decode(path, triangulation)?;
+    let conversion = conversion.or(declared).unwrap_or(match format {
+        MeshFileFormat::Gltf => AxisConversion::default(),
+        MeshFileFormat::Obj => axes.obj,
+        MeshFileFormat::Stl => axes.stl,
+        MeshFileFormat::Fbx => axes.fbx,
+    });
+    conversion.validate()?;
+    for mesh in &mut meshes {
+        conversion.apply(&mut mesh.positions);
+    }
+    Ok(ImportedMeshes {
+        format,
+        meshes,
+        conversion,
+        triangulation,
+    })
+}
+
+/// Meshes of a file, with the conversion it declares for itself
+struct Decoded {
+    format: MeshFileFormat,
+    meshes: Vec<NamedMesh>,
+    declared: Option<AxisConversion>,
+    triangulation: TriangulationReport,
+}
+
+fn decode(path: &Path, triangulation: TriangulationOptions) -> Result<Decoded, String> {
+    let format = MeshFileFormat::from_path(path)?;
+    let mut triangulator = Triangulator::new(triangulation);
+    let (meshes, declared) = match format {
+        MeshFileFormat::Gltf => (gltf_meshes(path)?, None),
+        MeshFileFormat::Obj => (parse_obj(&read(path)?, &mut triangulator)?, None),
+        MeshFileFormat::Stl => (vec![parse_stl(&read(path)?)?], None),
+        MeshFileFormat::Fbx => {
+            let scene = parse_fbx(&read(path)?, &mut triangulator)?;
+            (scene.meshes, scene.axes)
+        }
+    };
+    Ok(Decoded {
+        format,
+        meshes,
+        declared,
+        triangulation: triangulator.finish(),
+    })
+}
+
+fn read(path: &Path) -> Result<FileBytes, String> {
+    if !path.exists() {
+        return Err(format!("File not found: {}", path.display()));
+    }
+    FileBytes::open(path)
+}
+
+fn gltf_meshes(path: &Path) -> Result<Vec<NamedMesh>, String> {
+    let loaded = load_gltf(path)?;
+    let mut instances: BTreeMap<(Option<usize>, usize), NamedMesh> = BTreeMap::new();
+
+    for primitive in read_primitives(&loaded) {
+        let mesh = instances
+            .entry((primitive.node_index, primitive.mesh_index))
+            .or_insert_with(|| NamedMesh {
+                name: loaded
+                    .document
+                    .meshes()
+                    .nth(primitive.mesh_index)
+                    .and_then(|m| m.name().map(str::to_string))
+                    .unwrap_or_else(|| format!("mesh {}", primitive.mesh_index)),
+                ..Default::default()
+            });
+        mesh.append(&primitive.world_positions(), &primitive.indices);
+    }
+
+    Ok(instances.into_values().collect())
+}
+
+/// Triangles of one OBJ object or group, with its polygon corner counts
+type ObjGroup = (String, Vec<u32>, Vec<u32>);
+
+/// Parse the geometry of a Wavefront OBJ file
+///
+/// Polygons are split by `triangulator`, keeping their corner counts, and
+/// negative (relative) indices are supported. Texture and normal references
+/// in faces are ignored.
+pub fn parse_obj(bytes: &[u8], triangulator: &mut Triangulator) -> Result<Vec<NamedMesh>, String> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut vertices: Vec<[f32; 3]> = Vec::new();
+    let mut meshes: Vec<ObjGroup> = vec![("default".to_string(), Vec::new(), Vec::new())];
+
+    for (line_no, line) in text.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let mut v = [0.0f32; 3];
+                for slot in &mut v {
+                    *slot = tokens
+                        .next()
+                        .and_then(|t| t.parse().ok())
+                        .ok_or_else(|| format!("Invalid vertex on OBJ line {}", line_no + 1))?;
+                }
+                vertices.push(v);
+            }
+            Some("o" | "g") => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                let current = meshes.last_mut().unwrap();
+                if current.1.is_empty() {
+                    current.0 = name;
+                } else {
+                    meshes.push((name, Vec::new(), Vec::new()));
+                }
+            }
+            Some("f") => {
+                let corners = tokens
+                    .map(|t| obj_index(t, vertices.len()))
+                    .collect::<Option<Vec<u32>>>()
+                    .ok_or_else(|| format!("Invalid face on OBJ line {}", line_no + 1))?;
+                if corners.len() < 3 {
+                    return Err(format!(
+                        "Face with fewer than 3 corners on OBJ line {}",
+                        line_no + 1
+                    ));
+                }
+                let (name, indices, polygons) = meshes.last_mut().unwrap();
+                triangulator.polygon(
+                    name,
+                    polygons.len(),
+                    vertices.as_flattened(),
+                    &corners,
+                    indices,
+                );
+                polygons.push(corners.len() as u32);
+            }
+            _ => {}
+        }
+    }
+
+    let positions: Vec<f32> = vertices.iter().flatten().copied().collect();
+    Ok(meshes
+        .into_iter()
+        .filter(|(_, indices, _)| !indices.is_empty())
+        .map(|(name, indices, polygons)| {
+            let mut mesh = compact(name, &positions, &indices);
+            mesh.polygons = Some(polygons);
+            mesh
+        })
+        .collect())
+}
+
+/// Resolve an OBJ face corner (`v`, `v/vt`, `v//vn`, `v/vt/vn`) to a 0-based index
+fn obj_index(token: &str, vertex_count: usize) -> Option<u32> {
+    let index: i64 = token.split('/').next()?.parse().ok()?;
+    let resolved = if index < 0 {
+        vertex_count as i64 + index
+    } else {
+        index - 1
+    };
+    (0..vertex_count as i64)
+        .contains(&resolved)
+        .then_some(resolved as u32)
+}
+
+/// Keep only the vertices a group references, renumbering its indices
+fn compact(name: String, positions: &[f32], indices: &[u32]) -> NamedMesh {
+    let mut remap = vec![u32::MAX; positions.len() / 3];
+    let mut mesh = NamedMesh {
+        name,
+        ..Default::default()
+    };
+    for &i in indices {
+        let slot = &mut remap[i as usize];
+        if *slot == u32::MAX {
+            *slot = (mesh.positions.len() / 3) as u32;
+            let b = i as usize * 3;
+            mesh.positions.extend_from_slice(&positions[b..b + 3]);
+        }
+        mesh.indices.push(*slot);
+    }
+    mesh
+}
+
+/// Parse a binary or ASCII STL file
+pub fn parse_stl(bytes: &[u8]) -> Result<NamedMesh, String> {
+    // ASCII files start with "solid", but so do some binary headers; a
+    // binary file's size always matches its declared triangle count
+    if bytes.len() >= 84 {
+        let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as u64;
+        if 84 + count * 50 == bytes.len() as u64 {
+            return Ok(parse_binary_stl(&bytes[84..], count as usize));
+        }
+    }
+    if bytes.trim_ascii_start().starts_with(b"solid") {
+        return parse_ascii_stl(bytes);
+    }
+    Err("Invalid STL file: size doesn't match the triangle count".to_string())
+}
+
+fn parse_binary_stl(body: &[u8], count: usize) -> NamedMesh {
+    let mut mesh = NamedMesh {
+        name: "stl".to_string(),
+        positions: Vec::with_capacity(count * 9),
+        indices: (0..(count * 3) as u32).collect(),
+        polygons: None,
+    };
+    for record in body.chunks_exact(50) {
+        // 12 bytes of facet normal, then three vertices
+        for word in record[12..48].chunks_exact(4) {
+            mesh.positions
+                .push(f32::from_le_bytes([word[0], word[1], word[2], word[3]]));
+        }
+    }
+    mesh
+}
+
+fn parse_ascii_stl(bytes: &[u8]) -> Result<NamedMesh, String> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut mesh = NamedMesh {
+        name: text
+            .lines()
+            .next()
+            .and_then(|l| l.trim().strip_prefix("solid"))
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| "stl".to_string()),
+        ..Default::default()
+    };
+
+    for (line_no, line) in text.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("vertex") {
+            continue;
+        }
+        for _ in 0..3 {
+            let value = tokens
+                .next()
+                .and_then(|t| t.parse::<f32>().ok())
+                .ok_or_else(|| format!("Invalid vertex on STL line {}", line_no + 1))?;
+            mesh.positions.push(value);
+        }
+    }
+
+    let vertex_count = mesh.positions.len() / 3;
+    if !vertex_count.is_multiple_of(3) {
+        return Err(format!(
+            "STL has {} vertices, which is not a whole number of triangles",
+            vertex_count
+        ));
+    }
+    mesh.indices = (0..vertex_count as u32).collect();
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_obj_groups_and_polygons() {
+        let obj = b"v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 0 1\n\
+                    o quad\nf 1/1 2/2 3/3 4/4\n\
+                    g tri\nf -5//1 -4//1 -1//1\n";
+        let meshes = parse_obj(obj, &mut Triangulator::new(Default::default())).unwrap();
+
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[0].name, "quad");
+        assert_eq!(meshes[0].indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(meshes[0].polygons, Some(vec![4]));
+        assert_eq!(meshes[1].name, "tri");
+        // Only the three referenced vertices are kept
+        assert_eq!(
+            meshes[1].positions,
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0]
+        );
+        assert!(parse_obj(
+            b"v 0 0 0\nf 1 2 3\n",
+            &mut Triangulator::new(Default::default())
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_stl_binary_and_ascii() {
+        let mut binary = b"solid looks like ascii but isn't".to_vec();
+        binary.resize(80, 0);
+        binary.extend(1u32.to_le_bytes());
+        binary.extend([0u8; 12]);
+        for v in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            binary.extend(v.to_le_bytes());
+        }
+        binary.extend([0u8; 2]);
+        let mesh = parse_stl(&binary).unwrap();
+        assert_eq!(mesh.positions.len(), 9);
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+
+        let ascii = b"solid part\nfacet normal 0 0 1\nouter loop\n\
+                      vertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\n\
+                      endloop\nendfacet\nendsolid part\n";
+        let mesh = parse_stl(ascii).unwrap();
+        assert_eq!(mesh.name, "part");
+        assert_eq!(mesh.positions[3], 1.0);
+    }
+}
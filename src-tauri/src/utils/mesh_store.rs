@@ -0,0 +1,485 @@
+use crate::utils::polygons::{self, FaceTopology};
+use crate::utils::skin::MAX_INFLUENCES;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Triangle mesh held on the Rust side and referenced by handle
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub positions: Vec<f32>,
+    pub normals: Option<Vec<f32>>,
+    pub uvs: Option<Vec<f32>>,
+    /// Second UV set (glTF `TEXCOORD_1`), laid out without overlaps for
+    /// baked lighting
+    pub lightmap_uvs: Option<Vec<f32>>,
+    /// Linear RGBA per vertex (glTF `COLOR_0`)
+    pub colors: Option<Vec<f32>>,
+    pub indices: Vec<u32>,
+    /// Four joint indices per vertex (glTF `JOINTS_0`)
+    pub skin_joints: Option<Vec<u32>>,
+    /// Four joint weights per vertex (glTF `WEIGHTS_0`)
+    pub skin_weights: Option<Vec<f32>>,
+    /// Corner count of each polygon the triangles came from, for meshes
+    /// imported with quads or n-gons
+    pub polygons: Option<Vec<u32>>,
+}
+
+impl MeshData {
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len() / 3
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn topology(&self) -> FaceTopology {
+        polygons::topology(self.polygons.as_deref())
+    }
+
+    /// Copy the attributes of the vertices in `order`, with the given indices
+    ///
+    /// Polygon information is dropped since the new faces may not match it.
+    pub fn with_vertices(&self, order: &[usize], indices: Vec<u32>) -> MeshData {
+        fn gather<T: Copy>(order: &[usize], values: &[T], width: usize) -> Vec<T> {
+            order
+                .iter()
+                .flat_map(|&i| values[i * width..(i + 1) * width].iter().copied())
+                .collect()
+        }
+
+        MeshData {
+            positions: gather(order, &self.positions, 3),
+            normals: self.normals.as_ref().map(|n| gather(order, n, 3)),
+            uvs: self.uvs.as_ref().map(|uvs| gather(order, uvs, 2)),
+            lightmap_uvs: self.lightmap_uvs.as_ref().map(|uvs| gather(order, uvs, 2)),
+            colors: self.colors.as_ref().map(|c| gather(order, c, 4)),
+            indices,
+            skin_joints: self
+                .skin_joints
+                .as_ref()
+                .map(|joints| gather(order, joints, MAX_INFLUENCES)),
+            skin_weights: self
+                .skin_weights
+                .as_ref()
+                .map(|weights| gather(order, weights, MAX_INFLUENCES)),
+            polygons: None,
+        }
+    }
+
+    /// Check attribute lengths and index ranges
+    pub fn validate(&self) -> Result<(), String> {
+        if self.positions.is_empty() {
+            return Err("No vertices provided".to_string());
+        }
+        if !self.positions.len().is_multiple_of(3) {
+            return Err(format!(
+                "Position buffer length {} is not a multiple of 3",
+                self.positions.len()
+            ));
+        }
+        if !self.indices.len().is_multiple_of(3) {
+            return Err(format!(
+                "Index buffer length {} is not a multiple of 3",
+                self.indices.len()
+            ));
+        }
+
+        let vertex_count = self.vertex_count();
+        if let Some(normals) = &self.normals {
+            if normals.len() != vertex_count * 3 {
+                return Err(format!(
+                    "Expected {} normal components, got {}",
+                    vertex_count * 3,
+                    normals.len()
+                ));
+            }
+        }
+        if let Some(uvs) = &self.uvs {
+            if uvs.len() != vertex_count * 2 {
+                return Err(format!(
+                    "Expected {} UV components, got {}",
+                    vertex_count * 2,
+                    uvs.len()
+                ));
+            }
+        }
+        if let Some(uvs) = &self.lightmap_uvs {
+            if uvs.len() != vertex_count * 2 {
+                return Err(format!(
+                    "Expected {} lightmap UV components, got {}",
+                    vertex_count * 2,
+                    uvs.len()
+                ));
+            }
+        }
+        if let Some(colors) = &self.colors {
+            if colors.len() != vertex_count * 4 {
+                return Err(format!(
+                    "Expected {} color components, got {}",
+                    vertex_count * 4,
+                    colors.len()
+                ));
+            }
+        }
+        match (&self.skin_joints, &self.skin_weights) {
+            (Some(joints), Some(weights)) => {
+                let expected = vertex_count * MAX_INFLUENCES;
+                if joints.len() != expected || weights.len() != expected {
+                    return Err(format!(
+                        "Expected {} joint indices and weights, got {} and {}",
+                        expected,
+                        joints.len(),
+                        weights.len()
+                    ));
+                }
+            }
+            (None, None) => {}
+            _ => return Err("Joints and weights must be provided together".to_string()),
+        }
+        if let Some(polygons) = &self.polygons {
+            polygons::validate(polygons, self.face_count())?;
+        }
+        if let Some(&bad) = self.indices.iter().find(|&&i| i as usize >= vertex_count) {
+            return Err(format!(
+                "Index {} out of range for {} vertices",
+                bad, vertex_count
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Vertex attribute carried by an upload chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MeshAttribute {
+    Positions,
+    Normals,
+    Uvs,
+    LightmapUvs,
+    Colors,
+    Indices,
+    Joints,
+    Weights,
+    Polygons,
+}
+
+impl MeshAttribute {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "positions" => Ok(Self::Positions),
+            "normals" => Ok(Self::Normals),
+            "uvs" => Ok(Self::Uvs),
+            "lightmap_uvs" => Ok(Self::LightmapUvs),
+            "colors" => Ok(Self::Colors),
+            "indices" => Ok(Self::Indices),
+            "joints" => Ok(Self::Joints),
+            "weights" => Ok(Self::Weights),
+            "polygons" => Ok(Self::Polygons),
+            other => Err(format!("Unknown mesh attribute: {}", other)),
+        }
+    }
+}
+
+/// Raw little-endian bytes received so far for an upload
+#[derive(Debug, Default)]
+struct UploadSession {
+    buffers: HashMap<MeshAttribute, Vec<u8>>,
+    expected_bytes: Option<u64>,
+    received_bytes: u64,
+}
+
+/// Registry of meshes and in-flight uploads, managed as Tauri state
+#[derive(Default)]
+pub struct MeshStore {
+    next_id: AtomicU64,
+    meshes: Mutex<HashMap<u64, Arc<MeshData>>>,
+    uploads: Mutex<HashMap<u64, UploadSession>>,
+}
+
+impl MeshStore {
+    fn allocate_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Register a mesh and return its handle
+    pub fn insert(&self, mesh: MeshData) -> u64 {
+        let id = self.allocate_id();
+        self.meshes.lock().unwrap().insert(id, Arc::new(mesh));
+        id
+    }
+
+    pub fn get(&self, handle: u64) -> Result<Arc<MeshData>, String> {
+        self.meshes
+            .lock()
+            .unwrap()
+            .get(&handle)
+            .cloned()
+            .ok_or_else(|| format!("Unknown mesh handle: {}", handle))
+    }
+
+    /// Replace the mesh behind an existing handle
+    pub fn replace(&self, handle: u64, mesh: MeshData) -> Result<(), String> {
+        let mut meshes = self.meshes.lock().unwrap();
+        match meshes.get_mut(&handle) {
+            Some(slot) => {
+                *slot = Arc::new(mesh);
+                Ok(())
+            }
+            None => Err(format!("Unknown mesh handle: {}", handle)),
+        }
+    }
+
+    pub fn remove(&self, handle: u64) -> bool {
+        self.meshes.lock().unwrap().remove(&handle).is_some()
+    }
+
+    pub fn begin_upload(&self, expected_bytes: Option<u64>) -> u64 {
+        let id = self.allocate_id();
+        self.uploads.lock().unwrap().insert(
+            id,
+            UploadSession {
+                expected_bytes,
+                ..Default::default()
+            },
+        );
+        id
+    }
+
+    /// Append bytes to one attribute of an upload, returning total bytes received
+    pub fn append_chunk(
+        &self,
+        upload_id: u64,
+        attribute: MeshAttribute,
+        bytes: &[u8],
+    ) -> Result<u64, String> {
+        let mut uploads = self.uploads.lock().unwrap();
+        let session = uploads
+            .get_mut(&upload_id)
+            .ok_or_else(|| format!("Unknown upload: {}", upload_id))?;
+
+        let received = session.received_bytes + bytes.len() as u64;
+        if let Some(expected) = session.expected_bytes {
+            if received > expected {
+                return Err(format!(
+                    "Upload {} exceeded its declared size of {} bytes",
+                    upload_id, expected
+                ));
+            }
+        }
+
+        session
+            .buffers
+            .entry(attribute)
+            .or_default()
+            .extend_from_slice(bytes);
+        session.received_bytes = received;
+
+        Ok(received)
+    }
+
+    /// Decode and validate a finished upload, registering it as a mesh
+    pub fn finish_upload(&self, upload_id: u64) -> Result<u64, String> {
+        let mut session = self
+            .uploads
+            .lock()
+            .unwrap()
+            .remove(&upload_id)
+            .ok_or_else(|| format!("Unknown upload: {}", upload_id))?;
+
+        if let Some(expected) = session.expected_bytes {
+            if session.received_bytes != expected {
+                return Err(format!(
+                    "Upload {} incomplete: received {} of {} bytes",
+                    upload_id, session.received_bytes, expected
+                ));
+            }
+        }
+
+        let mut take_f32 = |attribute| -> Result<Option<Vec<f32>>, String> {
+            session
+                .buffers
+                .remove(&attribute)
+                .map(|bytes| decode_f32(&bytes))
+                .transpose()
+        };
+
+        let positions = take_f32(MeshAttribute::Positions)?.unwrap_or_default();
+        let normals = take_f32(MeshAttribute::Normals)?;
+        let uvs = take_f32(MeshAttribute::Uvs)?;
+        let lightmap_uvs = take_f32(MeshAttribute::LightmapUvs)?;
+        let colors = take_f32(MeshAttribute::Colors)?;
+        let skin_weights = take_f32(MeshAttribute::Weights)?;
+        let indices = match session.buffers.remove(&MeshAttribute::Indices) {
+            Some(bytes) => decode_u32(&bytes)?,
+            None => (0..(positions.len() / 3) as u32).collect(),
+        };
+        let skin_joints = session
+            .buffers
+            .remove(&MeshAttribute::Joints)
+            .map(|bytes| decode_u32(&bytes))
+            .transpose()?;
+        let polygons = session
+            .buffers
+            .remove(&MeshAttribute::Polygons)
+            .map(|bytes| decode_u32(&bytes))
+            .transpose()?;
+
+        let mesh = MeshData {
+            positions,
+            normals,
+            uvs,
+            lightmap_uvs,
+            colors,
+            indices,
+            skin_joints,
+            skin_weights,
+            polygons,
+        };
+        mesh.validate()?;
+
+        Ok(self.insert(mesh))
+    }
+
+    pub fn cancel_upload(&self, upload_id: u64) -> bool {
+        self.uploads.lock().unwrap().remove(&upload_id).is_some()
+    }
+}
+
+fn decode_f32(bytes: &[u8]) -> Result<Vec<f32>, String> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(format!(
+            "Float buffer of {} bytes is misaligned",
+            bytes.len()
+        ));
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+fn decode_u32(bytes: &[u8]) -> Result<Vec<u32>, String> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(format!(
+            "Index buffer of {} bytes is misaligned",
+            bytes.len()
+        ));
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes<T: Copy>(values: &[T], to_le: impl Fn(T) -> [u8; 4]) -> Vec<u8> {
+        values.iter().flat_map(|&v| to_le(v)).collect()
+    }
+
+    #[test]
+    fn test_uploads_check_declared_size_and_contents() {
+        let store = MeshStore::default();
+        let positions = bytes(
+            &[0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            f32::to_le_bytes,
+        );
+        let indices = bytes(&[0u32, 1, 2], u32::to_le_bytes);
+        let total = (positions.len() + indices.len()) as u64;
+
+        // Chunks of one attribute may split a value
+        let upload = store.begin_upload(Some(total));
+        store
+            .append_chunk(upload, MeshAttribute::Positions, &positions[..6])
+            .unwrap();
+        store
+            .append_chunk(upload, MeshAttribute::Positions, &positions[6..])
+            .unwrap();
+        assert_eq!(
+            store
+                .append_chunk(upload, MeshAttribute::Indices, &indices)
+                .unwrap(),
+            total
+        );
+        let mesh = store.get(store.finish_upload(upload).unwrap()).unwrap();
+        assert_eq!((mesh.vertex_count(), mesh.face_count()), (3, 1));
+        assert_eq!(mesh.positions[3], 1.0);
+
+        let short = store.begin_upload(Some(total));
+        store
+            .append_chunk(short, MeshAttribute::Positions, &positions)
+            .unwrap();
+        assert!(store
+            .finish_upload(short)
+            .unwrap_err()
+            .contains("incomplete"));
+
+        let long = store.begin_upload(Some(total));
+        store
+            .append_chunk(long, MeshAttribute::Positions, &positions)
+            .unwrap();
+        assert!(store
+            .append_chunk(
+                long,
+                MeshAttribute::Indices,
+                &[indices.clone(), vec![0; 4]].concat()
+            )
+            .unwrap_err()
+            .contains("exceeded"));
+
+        let misaligned = store.begin_upload(None);
+        store
+            .append_chunk(misaligned, MeshAttribute::Positions, &positions[..7])
+            .unwrap();
+        assert!(store.finish_upload(misaligned).is_err());
+
+        let out_of_range = store.begin_upload(None);
+        store
+            .append_chunk(out_of_range, MeshAttribute::Positions, &positions)
+            .unwrap();
+        store
+            .append_chunk(
+                out_of_range,
+                MeshAttribute::Indices,
+                &bytes(&[0u32, 1, 3], u32::to_le_bytes),
+            )
+            .unwrap();
+        assert!(store
+            .finish_upload(out_of_range)
+            .unwrap_err()
+            .contains("out of range"));
+    }
+
+    #[test]
+    fn test_validate_checks_attribute_lengths() {
+        let mesh = MeshData {
+            positions: vec![0.0; 9],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        assert!(mesh.validate().is_ok());
+        assert!(MeshData {
+            normals: Some(vec![0.0; 6]),
+            ..mesh.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(MeshData {
+            skin_joints: Some(vec![0; 3 * MAX_INFLUENCES]),
+            ..mesh.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(MeshData {
+            indices: vec![0, 1],
+            ..mesh
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! Per-operation timing and heap usage collected from `tracing` spans
+//!
+//! Commands and their pipeline stages are instrumented with spans. The
+//! [`MetricsLayer`] times every span from creation to close and records the
+//! result under its path (`analyze_model/hash_buffers`), and
+//! [`TrackingAllocator`] counts heap bytes so each operation can report the
+//! high-water mark reached while it ran.
+
+use serde::{Deserialize, Serialize};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+static HEAP_CURRENT: AtomicU64 = AtomicU64::new(0);
+static HEAP_PEAK: AtomicU64 = AtomicU64::new(0);
+/// Allocations past this many bytes fail; 0 means no limit
+static HEAP_LIMIT: AtomicU64 = AtomicU64::new(0);
+/// Spans currently open; the peak is restarted when the first one opens
+static ACTIVE_SPANS: AtomicUsize = AtomicUsize::new(0);
+
+/// System allocator wrapper that tracks current and peak heap usage
+pub struct TrackingAllocator;
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if over_limit(layout.size()) {
+            return std::ptr::null_mut();
+        }
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if over_limit(layout.size()) {
+            return std::ptr::null_mut();
+        }
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        HEAP_CURRENT.fetch_sub(layout.size() as u64, Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size > layout.size() && over_limit(new_size - layout.size()) {
+            return std::ptr::null_mut();
+        }
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size >= layout.size() {
+                grow(new_size - layout.size());
+            } else {
+                HEAP_CURRENT.fetch_sub((layout.size() - new_size) as u64, Ordering::Relaxed);
+            }
+        }
+        new_ptr
+    }
+}
+
+#[inline]
+fn over_limit(bytes: usize) -> bool {
+    let limit = HEAP_LIMIT.load(Ordering::Relaxed);
+    limit != 0 && HEAP_CURRENT.load(Ordering::Relaxed) + bytes as u64 > limit
+}
+
+/// Make allocations fail once the heap would exceed `bytes` (0 removes the
+/// limit); used to cap memory in worker processes
+pub fn set_heap_limit(bytes: u64) {
+    HEAP_LIMIT.store(bytes, Ordering::Relaxed);
+}
+
+#[inline]
+fn grow(bytes: usize) {
+    let current = HEAP_CURRENT.fetch_add(bytes as u64, Ordering::Relaxed) + bytes as u64;
+    HEAP_PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+/// Timing and memory summary of one operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationMetrics {
+    /// Span path, e.g. `stream_model_progressive/preview`
+    pub name: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+    pub last_ms: f64,
+    /// Highest heap usage seen while the operation ran. Operations that
+    /// overlap share one high-water mark, so this is an upper bound for them.
+    pub peak_heap_bytes: u64,
+}
+
+/// Snapshot of all collected metrics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceReport {
+    pub uptime_ms: f64,
+    pub heap_bytes: u64,
+    pub peak_heap_bytes: u64,
+    /// Slowest operations (by total time) first
+    pub operations: Vec<OperationMetrics>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Stats {
+    calls: u64,
+    errors: u64,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+    last: Duration,
+    peak_heap_bytes: u64,
+}
+
+/// Callback for finished top-level operations: name, duration, whether it failed
+type OperationObserver = Box<dyn Fn(&str, Duration, bool) + Send + Sync>;
+
+/// Collected operation statistics, shared by the layer and the report command
+pub struct Metrics {
+    started: Instant,
+    operations: Mutex<HashMap<String, Stats>>,
+    observer: OnceLock<OperationObserver>,
+}
+
+impl Metrics {
+    pub fn global() -> &'static Metrics {
+        static METRICS: OnceLock<Metrics> = OnceLock::new();
+        METRICS.get_or_init(|| Metrics {
+            started: Instant::now(),
+            operations: Mutex::new(HashMap::new()),
+            observer: OnceLock::new(),
+        })
+    }
+
+    /// Call `observer` whenever a top-level operation (a command span) closes
+    ///
+    /// Only the first observer is kept.
+    pub fn observe_operations(
+        &self,
+        observer: impl Fn(&str, Duration, bool) + Send + Sync + 'static,
+    ) {
+        if self.observer.set(Box::new(observer)).is_err() {
+            log::warn!("An operation observer is already installed");
+        }
+    }
+
+    fn record(&self, name: &str, elapsed: Duration, errors: u64, peak_heap_bytes: u64) {
+        self.update(name, elapsed, errors, peak_heap_bytes);
+        if !name.contains('/') {
+            if let Some(observer) = self.observer.get() {
+                observer(name, elapsed, errors > 0);
+            }
+        }
+    }
+
+    fn update(&self, name: &str, elapsed: Duration, errors: u64, peak_heap_bytes: u64) {
+        let mut operations = self.operations.lock().unwrap();
+        let stats = operations.entry(name.to_string()).or_insert(Stats {
+            calls: 0,
+            errors: 0,
+            total: Duration::ZERO,
+            min: Duration::MAX,
+            max: Duration::ZERO,
+            last: Duration::ZERO,
+            peak_heap_bytes: 0,
+        });
+        stats.calls += 1;
+        stats.errors += errors;
+        stats.total += elapsed;
+        stats.min = stats.min.min(elapsed);
+        stats.max = stats.max.max(elapsed);
+        stats.last = elapsed;
+        stats.peak_heap_bytes = stats.peak_heap_bytes.max(peak_heap_bytes);
+    }
+
+    pub fn report(&self) -> PerformanceReport {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let mut operations: Vec<OperationMetrics> = self
+            .operations
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, stats)| OperationMetrics {
+                name: name.clone(),
+                calls: stats.calls,
+                errors: stats.errors,
+                total_ms: ms(stats.total),
+                mean_ms: ms(stats.total) / stats.calls as f64,
+                min_ms: ms(stats.min),
+                max_ms: ms(stats.max),
+                last_ms: ms(stats.last),
+                peak_heap_bytes: stats.peak_heap_bytes,
+            })
+            .collect();
+        operations.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+
+        PerformanceReport {
+            uptime_ms: ms(self.started.elapsed()),
+            heap_bytes: HEAP_CURRENT.load(Ordering::Relaxed),
+            peak_heap_bytes: HEAP_PEAK.load(Ordering::Relaxed),
+            operations,
+        }
+    }
+
+    pub fn reset(&self) {
+        self.operations.lock().unwrap().clear();
+    }
+}
+
+/// Per-span bookkeeping stored in the span's extensions
+struct SpanTiming {
+    path: String,
+    opened: Instant,
+    errors: u64,
+}
+
+/// `tracing` layer that feeds span timings into [`Metrics::global`]
+pub struct MetricsLayer;
+
+impl<S> Layer<S> for MetricsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let name = attrs.metadata().name();
+        let path = match span.parent() {
+            Some(parent) => match parent.extensions().get::<SpanTiming>() {
+                Some(timing) => format!("{}/{}", timing.path, name),
+                None => name.to_string(),
+            },
+            None => name.to_string(),
+        };
+
+        if ACTIVE_SPANS.fetch_add(1, Ordering::Relaxed) == 0 {
+            HEAP_PEAK.store(HEAP_CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+        span.extensions_mut().insert(SpanTiming {
+            path,
+            opened: Instant::now(),
+            errors: 0,
+        });
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if *event.metadata().level() != Level::ERROR {
+            return;
+        }
+        if let Some(span) = ctx.event_span(event) {
+            if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+                timing.errors += 1;
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let timing = span.extensions_mut().remove::<SpanTiming>();
+        if let Some(timing) = timing {
+            Metrics::global().record(
+                &timing.path,
+                timing.opened.elapsed(),
+                timing.errors,
+                HEAP_PEAK.load(Ordering::Relaxed),
+            );
+            ACTIVE_SPANS.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Install the metrics layer as the global `tracing` subscriber
+///
+/// Logging stays with `env_logger`; this subscriber only collects metrics.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_nested_spans_are_timed_by_path() {
+        let subscriber = tracing_subscriber::registry().with(MetricsLayer);
+        tracing::subscriber::with_default(subscriber, || {
+            let _outer = tracing::info_span!("metrics_test_outer").entered();
+            for _ in 0..2 {
+                let _inner = tracing::info_span!("metrics_test_inner").entered();
+                tracing::error!("failed");
+            }
+        });
+
+        let report = Metrics::global().report();
+        let find = |name: &str| report.operations.iter().find(|op| op.name == name);
+        let outer = find("metrics_test_outer").unwrap();
+        let inner = find("metrics_test_outer/metrics_test_inner").unwrap();
+        assert_eq!(outer.calls, 1);
+        assert_eq!(inner.calls, 2);
+        assert_eq!(inner.errors, 2);
+        assert!(outer.total_ms >= inner.total_ms);
+    }
+}
@@ -1 +1,105 @@
+pub mod analysis_cache;
+pub mod animation;
+pub mod api_schema;
+pub mod ar_preview;
+pub mod asset_cache;
+pub mod asset_history;
+pub mod axis_conversion;
+pub mod blender;
+pub mod bone_weights;
+pub mod bundle;
+pub mod changes;
+pub mod channel_pack;
+pub mod clipboard;
+pub mod color_space;
+pub mod compute;
+pub mod curation;
+pub mod decimate;
+pub mod degenerate;
+pub mod dependencies;
+pub mod desktop;
+pub mod diagnostics;
+pub mod engine_export;
+pub mod estimate;
+pub mod external_tools;
+pub mod fbx;
+pub mod file_lock;
+pub mod flatten;
+pub mod glb_writer;
+pub mod gltf_extensions;
+pub mod gltf_document;
+pub mod gltf_geometry;
+pub mod gltf_metadata;
+pub mod gltf_subset;
+pub mod hollow;
+pub mod i18n;
+pub mod image_thumbnail;
+pub mod instancing;
+pub mod jobs;
+pub mod library;
+pub mod licenses;
+pub mod lightmap;
+pub mod logging;
+pub mod maintenance;
+pub mod material_preview;
+pub mod media;
 pub mod mesh_analyzer;
+pub mod mesh_array;
+pub mod mesh_diff;
+pub mod mesh_edit;
+pub mod mesh_files;
+pub mod mesh_store;
+pub mod metrics;
+pub mod normal_maps;
+pub mod notifications;
+pub mod obj_writer;
+pub mod oplog;
+pub mod orientation;
+pub mod path_scope;
+pub mod plane_cut;
+pub mod plate;
+pub mod platform;
+pub mod polygons;
+pub mod pose;
+pub mod power;
+pub mod preview_cache;
+pub mod primitives;
+pub mod print_writer;
+pub mod printability;
+pub mod profiles;
+pub mod provenance;
+pub mod qr;
+pub mod quarantine;
+pub mod reindex;
+pub mod relink;
+pub mod rename_pattern;
+pub mod rpc_server;
+pub mod sanitize;
+pub mod scan_cleanup;
+pub mod scene_graph;
+pub mod report;
+pub mod rest_pose;
+pub mod seams;
+pub mod section;
+pub mod section_writer;
+pub mod settings;
+pub mod shrinkwrap;
+pub mod simd;
+pub mod skin;
+pub mod smart_collections;
+pub mod storage_layout;
+pub mod symmetry;
+pub mod texel_density;
+pub mod texture_bake;
+pub mod texture_enhance;
+pub mod topology;
+pub mod triangulate;
+pub mod usage_stats;
+pub mod uv_analysis;
+pub mod uv_projection;
+pub mod validation_profiles;
+pub mod vertex_colors;
+pub mod volume_writer;
+pub mod watch_rules;
+pub mod watcher;
+pub mod web_viewer;
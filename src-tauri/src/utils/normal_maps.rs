@@ -0,0 +1,286 @@
+//! Converting normal maps between conventions and spaces
+//!
+//! Normal maps from different tools disagree in two ways. DirectX-style
+//! maps point green down where OpenGL-style ones, which glTF uses, point it
+//! up, so mixing them makes bumps look like dents. And baking tools can
+//! write object-space maps, which hold the surface direction itself rather
+//! than its tilt from the mesh's own normal, and only work on the mesh they
+//! were baked for without deforming. Converting between spaces uses that
+//! mesh: its UVs say where each texel lies, and tangent frames built from
+//! them, in the glTF convention of X along increasing U and Y toward the top
+//! of the image, relate the two.
+
+use crate::utils::compute::cpu::CpuBackend;
+use crate::utils::compute::ComputeBackend;
+use crate::utils::mesh_store::MeshData;
+use crate::utils::texture_bake::{interpolate, UvRaster};
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NormalSpace {
+    /// Relative to the surface, as materials expect
+    #[default]
+    Tangent,
+    /// Directions in the mesh's own coordinates
+    Object,
+}
+
+/// Which way green points in a tangent-space map
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GreenConvention {
+    /// Green up, as in glTF, Blender and Unity
+    #[default]
+    #[serde(rename = "opengl")]
+    OpenGl,
+    /// Green down, as in Unreal and 3ds Max
+    #[serde(rename = "directx")]
+    DirectX,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalMapFormat {
+    pub space: NormalSpace,
+    /// Ignored for object-space maps
+    pub convention: GreenConvention,
+}
+
+/// The format a map is in and the one to write
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalMapOptions {
+    pub from: NormalMapFormat,
+    pub to: NormalMapFormat,
+}
+
+impl NormalMapOptions {
+    /// Whether converting needs the mesh the map belongs to
+    pub fn needs_mesh(&self) -> bool {
+        self.from.space != self.to.space
+    }
+}
+
+/// `image` converted as `options` ask, with the UV coverage of `mesh` when
+/// one was needed
+///
+/// Texels outside the mesh's UV islands, beyond a few texels of padding,
+/// are left as they were. Alpha is kept.
+pub fn convert(
+    image: &RgbaImage,
+    options: NormalMapOptions,
+    mesh: Option<&MeshData>,
+) -> Result<(RgbaImage, Option<f32>), String> {
+    let NormalMapOptions { from, to } = options;
+    let read = |pixel: &Rgba<u8>| {
+        let mut n = [0, 1, 2].map(|c| pixel.0[c] as f32 / 255.0 * 2.0 - 1.0);
+        if from.space == NormalSpace::Tangent && from.convention == GreenConvention::DirectX {
+            n[1] = -n[1];
+        }
+        n
+    };
+    let write = |mut n: [f32; 3], alpha: u8| {
+        if to.space == NormalSpace::Tangent && to.convention == GreenConvention::DirectX {
+            n[1] = -n[1];
+        }
+        let [r, g, b] = n.map(|v| ((v * 0.5 + 0.5) * 255.0).round() as u8);
+        Rgba([r, g, b, alpha])
+    };
+
+    if !options.needs_mesh() {
+        let mut out = image.clone();
+        for pixel in out.pixels_mut() {
+            *pixel = write(read(pixel), pixel.0[3]);
+        }
+        return Ok((out, None));
+    }
+
+    let mesh = mesh.ok_or("Converting between object and tangent space needs the mesh")?;
+    let uvs = mesh
+        .uvs
+        .as_deref()
+        .ok_or("The mesh has no UVs to place the normal map with")?;
+    let normals = match &mesh.normals {
+        Some(normals) => normals.clone(),
+        None => CpuBackend.vertex_normals(&mesh.positions, &mesh.indices)?,
+    };
+    let tangents = tangents(&mesh.positions, &normals, uvs, &mesh.indices);
+    let raster = UvRaster::sized(uvs, &mesh.indices, image.width(), image.height())?;
+
+    let width = image.width() as usize;
+    let frames = raster.map(|face, bary| {
+        let f = &mesh.indices[face * 3..face * 3 + 3];
+        let normal = normalize(interpolate::<3>(&normals, f, bary)?);
+        let [tx, ty, tz, _] = interpolate::<4>(&tangents, f, bary)?;
+        let tangent = normalize(sub([tx, ty, tz], scale(normal, dot(normal, [tx, ty, tz]))));
+        // Handedness isn't interpolated; mirrored UVs flip it per face
+        let handedness = tangents[f[0] as usize * 4 + 3];
+        Some([tangent, scale(cross(normal, tangent), handedness), normal])
+    });
+    let mut out = image.clone();
+    for (i, frame) in frames.into_iter().enumerate() {
+        let Some([t, b, n]) = frame.flatten() else {
+            continue;
+        };
+        let pixel = out.get_pixel_mut((i % width) as u32, (i / width) as u32);
+        let v = read(pixel);
+        let converted = match to.space {
+            NormalSpace::Tangent => [dot(v, t), dot(v, b), dot(v, n)],
+            NormalSpace::Object => [0, 1, 2].map(|k| v[0] * t[k] + v[1] * b[k] + v[2] * n[k]),
+        };
+        *pixel = write(normalize(converted), pixel.0[3]);
+    }
+    Ok((out, Some(raster.coverage())))
+}
+
+/// glTF-style tangents, four numbers per vertex: X along increasing U and
+/// the handedness that turns `normal × tangent` toward decreasing V
+fn tangents(positions: &[f32], normals: &[f32], uvs: &[f32], indices: &[u32]) -> Vec<f32> {
+    let count = positions.len() / 3;
+    let point = |values: &[f32], i: usize| [values[i * 3], values[i * 3 + 1], values[i * 3 + 2]];
+    let mut along_u = vec![[0.0f32; 3]; count];
+    let mut along_v = vec![[0.0f32; 3]; count];
+    for f in indices.chunks_exact(3) {
+        let [a, b, c] = [f[0], f[1], f[2]].map(|i| i as usize);
+        if [a, b, c]
+            .iter()
+            .any(|&i| i >= count || i * 2 + 1 >= uvs.len())
+        {
+            continue;
+        }
+        let (e1, e2) = (
+            sub(point(positions, b), point(positions, a)),
+            sub(point(positions, c), point(positions, a)),
+        );
+        let (du1, dv1) = (uvs[b * 2] - uvs[a * 2], uvs[b * 2 + 1] - uvs[a * 2 + 1]);
+        let (du2, dv2) = (uvs[c * 2] - uvs[a * 2], uvs[c * 2 + 1] - uvs[a * 2 + 1]);
+        let det = du1 * dv2 - du2 * dv1;
+        if det.abs() < f32::EPSILON {
+            continue;
+        }
+        let u = scale(sub(scale(e1, dv2), scale(e2, dv1)), 1.0 / det);
+        let v = scale(sub(scale(e2, du1), scale(e1, du2)), 1.0 / det);
+        for i in [a, b, c] {
+            along_u[i] = add(along_u[i], u);
+            along_v[i] = add(along_v[i], v);
+        }
+    }
+
+    (0..count)
+        .flat_map(|i| {
+            let n = point(normals, i);
+            let mut t = sub(along_u[i], scale(n, dot(n, along_u[i])));
+            if dot(t, t) < 1e-12 {
+                // No usable UVs around this vertex; any perpendicular will do
+                t = cross(
+                    n,
+                    if n[0].abs() < 0.9 {
+                        [1.0, 0.0, 0.0]
+                    } else {
+                        [0.0, 1.0, 0.0]
+                    },
+                );
+            }
+            let t = normalize(t);
+            let up = scale(along_v[i], -1.0);
+            let w = if dot(cross(n, t), up) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+            [t[0], t[1], t[2], w]
+        })
+        .collect()
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    a.map(|v| v * s)
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = dot(v, v).sqrt();
+    if length > 0.0 {
+        scale(v, 1.0 / length)
+    } else {
+        [0.0, 0.0, 1.0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flip_green_and_round_trip_through_object_space() {
+        // A unit quad facing +Z, U along +X and V down the image along -Y
+        let quad = MeshData {
+            positions: vec![0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            uvs: Some(vec![0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0]),
+            indices: vec![0, 3, 2, 0, 2, 1],
+            ..Default::default()
+        };
+        // (0.48, 0.36, 0.8): tilted toward +X and up the image
+        let tangent_map = RgbaImage::from_pixel(8, 8, Rgba([189, 173, 230, 255]));
+
+        let directx = NormalMapOptions {
+            to: NormalMapFormat {
+                convention: GreenConvention::DirectX,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (flipped, coverage) = convert(&tangent_map, directx, None).unwrap();
+        assert_eq!(coverage, None);
+        assert_eq!(flipped.get_pixel(0, 0).0, [189, 82, 230, 255]);
+
+        let to_object = NormalMapOptions {
+            to: NormalMapFormat {
+                space: NormalSpace::Object,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(convert(&tangent_map, to_object, None).is_err());
+        let (object, coverage) = convert(&tangent_map, to_object, Some(&quad)).unwrap();
+        assert_eq!(coverage, Some(1.0));
+        // Tangent X is object +X and tangent Y, up the image, is object +Y
+        let [r, g, b, _] = object.get_pixel(3, 3).0;
+        assert!(r.abs_diff(189) <= 1 && g.abs_diff(173) <= 1 && b.abs_diff(230) <= 1);
+
+        let back = NormalMapOptions {
+            from: NormalMapFormat {
+                space: NormalSpace::Object,
+                ..Default::default()
+            },
+            to: NormalMapFormat {
+                convention: GreenConvention::DirectX,
+                ..Default::default()
+            },
+        };
+        let (returned, _) = convert(&object, back, Some(&quad)).unwrap();
+        for (a, b) in returned.pixels().zip(flipped.pixels()) {
+            assert!((0..3).all(|c| a.0[c].abs_diff(b.0[c]) <= 2), "{:?}", (a, b));
+        }
+    }
+}
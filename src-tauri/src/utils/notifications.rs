@@ -0,0 +1,163 @@
+//! System notifications for finished background jobs
+//!
+//! Someone who starts a long batch and minimizes the window wants to hear
+//! when it's done, not every time a watched folder picks up a file. Which
+//! outcomes are announced is chosen per job kind, and jobs quicker than a
+//! minimum duration are never announced.
+
+use crate::utils::jobs::{Job, JobState};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the window is in the background, managed as Tauri state
+///
+/// Minimizing the window takes focus from it, so this is told about focus
+/// changes; a window behind another app's counts as in the background too.
+#[derive(Default)]
+pub struct WindowFocus {
+    unfocused: AtomicBool,
+}
+
+impl WindowFocus {
+    pub fn set_focused(&self, focused: bool) {
+        self.unfocused.store(!focused, Ordering::Relaxed);
+    }
+
+    pub fn in_background(&self) -> bool {
+        self.unfocused.load(Ordering::Relaxed)
+    }
+}
+
+/// Which outcomes of a kind of job are announced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobNotifications {
+    pub completed: bool,
+    pub failed: bool,
+}
+
+impl Default for JobNotifications {
+    fn default() -> Self {
+        Self {
+            completed: true,
+            failed: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationPreferences {
+    pub enabled: bool,
+    /// Jobs that ran for less time than this finish silently
+    pub min_duration_ms: u64,
+    /// For job kinds that aren't listed in `job_kinds`
+    pub default: JobNotifications,
+    /// By job kind, such as `watch_rule` or `maintenance`
+    pub job_kinds: BTreeMap<String, JobNotifications>,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_duration_ms: 10_000,
+            default: JobNotifications::default(),
+            job_kinds: BTreeMap::new(),
+        }
+    }
+}
+
+impl NotificationPreferences {
+    /// Title and body announcing `job`, if it finished in a way these
+    /// preferences ask to hear about
+    pub fn message(&self, job: &Job) -> Option<(String, String)> {
+        if !self.enabled {
+            return None;
+        }
+        let wanted = self.job_kinds.get(&job.kind).unwrap_or(&self.default);
+        let announce = match job.state {
+            JobState::Completed => wanted.completed,
+            JobState::Failed => wanted.failed,
+            JobState::Queued | JobState::Running | JobState::Interrupted => false,
+        };
+        let duration = job.finished_ms?.saturating_sub(job.started_ms?);
+        if !announce || duration < self.min_duration_ms {
+            return None;
+        }
+
+        let kind = job.kind.replace('_', " ");
+        Some(match (&job.state, &job.error) {
+            (JobState::Failed, Some(error)) => (
+                format!("Sweedle: {} job failed", kind),
+                format!("{}\n{}", job.label, error),
+            ),
+            (JobState::Failed, None) => {
+                (format!("Sweedle: {} job failed", kind), job.label.clone())
+            }
+            _ => (
+                format!("Sweedle: {} job finished", kind),
+                format!("{} ({} s)", job.label, duration / 1000),
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_long_wanted_outcomes_are_announced() {
+        let job = |kind: &str, state, seconds: u64| Job {
+            id: 1,
+            kind: kind.to_string(),
+            label: "/assets/rock.glb".to_string(),
+            state,
+            queued_ms: 0,
+            started_ms: Some(1_000),
+            finished_ms: Some(1_000 + seconds * 1000),
+            result: None,
+            resume: None,
+            error: (state == JobState::Failed).then(|| "Out of memory".to_string()),
+        };
+        let mut preferences = NotificationPreferences::default();
+        preferences.job_kinds.insert(
+            "watch_rule".to_string(),
+            JobNotifications {
+                completed: false,
+                failed: true,
+            },
+        );
+
+        assert_eq!(
+            preferences.message(&job("maintenance", JobState::Completed, 42)),
+            Some((
+                "Sweedle: maintenance job finished".to_string(),
+                "/assets/rock.glb (42 s)".to_string()
+            ))
+        );
+        assert_eq!(
+            preferences.message(&job("watch_rule", JobState::Failed, 12)),
+            Some((
+                "Sweedle: watch rule job failed".to_string(),
+                "/assets/rock.glb\nOut of memory".to_string()
+            ))
+        );
+        assert_eq!(
+            preferences.message(&job("watch_rule", JobState::Completed, 60)),
+            None
+        );
+        assert_eq!(
+            preferences.message(&job("maintenance", JobState::Completed, 3)),
+            None
+        );
+
+        preferences.enabled = false;
+        assert_eq!(
+            preferences.message(&job("maintenance", JobState::Failed, 60)),
+            None
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Wavefront OBJ output
+//!
+//! OBJ is the one export format that keeps quads and n-gons, so meshes that
+//! were imported with polygon information can be written back as authored
+//! instead of as the triangles everything else works on.
+
+use crate::utils::mesh_store::MeshData;
+use crate::utils::polygons;
+use crate::utils::vertex_colors::linear_to_srgb;
+use std::fmt::Write;
+
+/// Encode meshes as one OBJ file with an object per mesh
+///
+/// With `preserve_polygons`, faces are written as the polygons they were
+/// imported as; otherwise, or without polygon information, as triangles.
+/// Vertex colors use the common `v x y z r g b` extension.
+pub fn write_obj(objects: &[(&str, &MeshData)], preserve_polygons: bool) -> String {
+    let mut out = String::from("# Exported by Sweedle\n");
+    // OBJ indices are 1-based and global across objects
+    let mut base = 1usize;
+
+    for &(name, mesh) in objects {
+        let _ = writeln!(out, "o {}", name);
+        match &mesh.colors {
+            Some(colors) => {
+                for (p, c) in mesh.positions.chunks_exact(3).zip(colors.chunks_exact(4)) {
+                    let [r, g, b] = [c[0], c[1], c[2]].map(linear_to_srgb);
+                    let _ = writeln!(out, "v {} {} {} {} {} {}", p[0], p[1], p[2], r, g, b);
+                }
+            }
+            None => {
+                for p in mesh.positions.chunks_exact(3) {
+                    let _ = writeln!(out, "v {} {} {}", p[0], p[1], p[2]);
+                }
+            }
+        }
+        if let Some(uvs) = &mesh.uvs {
+            for uv in uvs.chunks_exact(2) {
+                let _ = writeln!(out, "vt {} {}", uv[0], uv[1]);
+            }
+        }
+        if let Some(normals) = &mesh.normals {
+            for n in normals.chunks_exact(3) {
+                let _ = writeln!(out, "vn {} {} {}", n[0], n[1], n[2]);
+            }
+        }
+
+        let polygons = mesh.polygons.as_deref().filter(|_| preserve_polygons);
+        for face in polygons::corners(&mesh.indices, polygons) {
+            out.push('f');
+            for i in face {
+                let i = base + i as usize;
+                let _ = match (mesh.uvs.is_some(), mesh.normals.is_some()) {
+                    (false, false) => write!(out, " {}", i),
+                    (true, false) => write!(out, " {}/{}", i, i),
+                    (false, true) => write!(out, " {}//{}", i, i),
+                    (true, true) => write!(out, " {}/{}/{}", i, i, i),
+                };
+            }
+            out.push('\n');
+        }
+        base += mesh.vertex_count();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mesh_files::parse_obj;
+    use crate::utils::triangulate::Triangulator;
+
+    #[test]
+    fn test_quads_survive_a_round_trip() {
+        let mesh = MeshData {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+            indices: vec![0, 1, 2, 0, 2, 3],
+            polygons: Some(vec![4]),
+            ..Default::default()
+        };
+
+        let quads = write_obj(&[("quad", &mesh)], true);
+        assert!(quads.contains("\nf 1 2 3 4\n"));
+        let parsed =
+            parse_obj(quads.as_bytes(), &mut Triangulator::new(Default::default())).unwrap();
+        assert_eq!(parsed[0].name, "quad");
+        assert_eq!(parsed[0].indices, mesh.indices);
+        assert_eq!(parsed[0].polygons, Some(vec![4]));
+
+        let triangles = write_obj(&[("quad", &mesh)], false);
+        assert!(triangles.contains("\nf 1 3 4\n"));
+    }
+}
@@ -0,0 +1,614 @@
+//! Write-ahead log for destructive and batch operations
+//!
+//! Every step is appended and flushed to disk *before* the work it
+//! describes is considered done: an item is logged as started (with the temp
+//! file it writes to) before any output is produced, and as completed only
+//! after the output is in its final place. Opening the log after a crash
+//! replays it, deletes temp files left by items that were cut off, and keeps
+//! the unfinished operations around so they can be reported and resumed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// One unit of work within an operation, e.g. a single file conversion
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedItem {
+    pub source: String,
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record {
+    Begin {
+        op_id: u64,
+        kind: String,
+        started_at: u64,
+        items: Vec<PlannedItem>,
+    },
+    ItemStarted {
+        op_id: u64,
+        index: usize,
+        temp_path: Option<String>,
+    },
+    ItemCompleted {
+        op_id: u64,
+        index: usize,
+    },
+    ItemFailed {
+        op_id: u64,
+        index: usize,
+        error: String,
+    },
+    Finished {
+        op_id: u64,
+    },
+    Discarded {
+        op_id: u64,
+    },
+}
+
+/// State of an item when its operation was interrupted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    Pending,
+    /// Started but never completed; its temp output has been cleaned up
+    Partial,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveredItem {
+    pub index: usize,
+    pub source: String,
+    pub output: Option<String>,
+    pub status: ItemStatus,
+    pub error: Option<String>,
+    /// Temp file that was deleted during recovery
+    pub removed_temp: Option<String>,
+}
+
+/// An operation that was still running when the app last exited
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterruptedOperation {
+    pub op_id: u64,
+    pub kind: String,
+    pub started_at: u64,
+    pub completed: usize,
+    pub remaining: usize,
+    pub items: Vec<RecoveredItem>,
+}
+
+/// Items still to be done when resuming an operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumePlan {
+    pub op_id: u64,
+    pub kind: String,
+    /// `(index, item)` pairs; report progress against the original index
+    pub items: Vec<(usize, PlannedItem)>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ItemProgress {
+    temp_path: Option<String>,
+    started: bool,
+    completed: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct OperationState {
+    kind: String,
+    started_at: u64,
+    items: Vec<PlannedItem>,
+    progress: Vec<ItemProgress>,
+}
+
+impl OperationState {
+    fn item(&mut self, index: usize) -> Result<&mut ItemProgress, String> {
+        let count = self.progress.len();
+        self.progress
+            .get_mut(index)
+            .ok_or_else(|| format!("Item {} out of range for {} items", index, count))
+    }
+
+    /// Records that reproduce this state, used when compacting the log
+    fn records(&self, op_id: u64) -> Vec<Record> {
+        let mut records = vec![Record::Begin {
+            op_id,
+            kind: self.kind.clone(),
+            started_at: self.started_at,
+            items: self.items.clone(),
+        }];
+        for (index, progress) in self.progress.iter().enumerate() {
+            if progress.started {
+                records.push(Record::ItemStarted {
+                    op_id,
+                    index,
+                    temp_path: progress.temp_path.clone(),
+                });
+            }
+            if progress.completed {
+                records.push(Record::ItemCompleted { op_id, index });
+            } else if let Some(error) = &progress.error {
+                records.push(Record::ItemFailed {
+                    op_id,
+                    index,
+                    error: error.clone(),
+                });
+            }
+        }
+        records
+    }
+}
+
+struct Inner {
+    file: Option<File>,
+    next_id: u64,
+    /// Operations that have begun but not finished
+    operations: HashMap<u64, OperationState>,
+    /// Recovered operations waiting to be resumed or discarded
+    interrupted: Vec<InterruptedOperation>,
+}
+
+/// Append-only operation log, managed as Tauri state
+pub struct OperationLog {
+    inner: Mutex<Inner>,
+}
+
+impl Default for OperationLog {
+    /// In-memory log that is never written to disk
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                file: None,
+                next_id: 1,
+                operations: HashMap::new(),
+                interrupted: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl OperationLog {
+    /// Open the log at `path`, recovering operations left unfinished by a crash
+    pub fn open(path: PathBuf) -> Self {
+        let records = read_records(&path);
+        let mut next_id = 1;
+        let mut operations: HashMap<u64, OperationState> = HashMap::new();
+
+        for record in records {
+            match record {
+                Record::Begin {
+                    op_id,
+                    kind,
+                    started_at,
+                    items,
+                } => {
+                    next_id = next_id.max(op_id + 1);
+                    let progress = vec![ItemProgress::default(); items.len()];
+                    operations.insert(
+                        op_id,
+                        OperationState {
+                            kind,
+                            started_at,
+                            items,
+                            progress,
+                        },
+                    );
+                }
+                Record::ItemStarted {
+                    op_id,
+                    index,
+                    temp_path,
+                } => {
+                    if let Some(item) = item_mut(&mut operations, op_id, index) {
+                        item.started = true;
+                        item.temp_path = temp_path;
+                        item.error = None;
+                    }
+                }
+                Record::ItemCompleted { op_id, index } => {
+                    if let Some(item) = item_mut(&mut operations, op_id, index) {
+                        item.completed = true;
+                    }
+                }
+                Record::ItemFailed {
+                    op_id,
+                    index,
+                    error,
+                } => {
+                    if let Some(item) = item_mut(&mut operations, op_id, index) {
+                        item.error = Some(error);
+                    }
+                }
+                Record::Finished { op_id } | Record::Discarded { op_id } => {
+                    operations.remove(&op_id);
+                }
+            }
+        }
+
+        let mut interrupted: Vec<InterruptedOperation> = operations
+            .iter_mut()
+            .map(|(&op_id, state)| recover(op_id, state))
+            .collect();
+        interrupted.sort_by_key(|op| op.op_id);
+        if !interrupted.is_empty() {
+            log::warn!(
+                "Recovered {} interrupted operation(s) from {}",
+                interrupted.len(),
+                path.display()
+            );
+        }
+
+        // Rewrite the log with only the unfinished operations so it stays small
+        let mut ids: Vec<u64> = operations.keys().copied().collect();
+        ids.sort_unstable();
+        let records: Vec<Record> = ids
+            .iter()
+            .flat_map(|id| operations[id].records(*id))
+            .collect();
+        let file = match compact(&path, &records) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                log::warn!("Operation log disabled: {}", e);
+                None
+            }
+        };
+
+        Self {
+            inner: Mutex::new(Inner {
+                file,
+                next_id,
+                operations,
+                interrupted,
+            }),
+        }
+    }
+
+    /// Log the start of an operation and return its id
+    pub fn begin(&self, kind: &str, items: Vec<PlannedItem>) -> Result<u64, String> {
+        let mut inner = self.inner.lock().unwrap();
+        let op_id = inner.next_id;
+        let started_at = unix_now();
+
+        append(
+            &mut inner.file,
+            &Record::Begin {
+                op_id,
+                kind: kind.to_string(),
+                started_at,
+                items: items.clone(),
+            },
+        )?;
+
+        inner.next_id += 1;
+        let progress = vec![ItemProgress::default(); items.len()];
+        inner.operations.insert(
+            op_id,
+            OperationState {
+                kind: kind.to_string(),
+                started_at,
+                items,
+                progress,
+            },
+        );
+        Ok(op_id)
+    }
+
+    /// Log that an item is about to produce output, optionally in `temp_path`
+    pub fn item_started(
+        &self,
+        op_id: u64,
+        index: usize,
+        temp_path: Option<String>,
+    ) -> Result<(), String> {
+        self.update(
+            Record::ItemStarted {
+                op_id,
+                index,
+                temp_path: temp_path.clone(),
+            },
+            |item| {
+                item.started = true;
+                item.temp_path = temp_path;
+                item.error = None;
+            },
+        )
+    }
+
+    /// Log that an item's output is complete and in its final location
+    pub fn item_completed(&self, op_id: u64, index: usize) -> Result<(), String> {
+        self.update(Record::ItemCompleted { op_id, index }, |item| {
+            item.completed = true;
+        })
+    }
+
+    pub fn item_failed(&self, op_id: u64, index: usize, error: String) -> Result<(), String> {
+        self.update(
+            Record::ItemFailed {
+                op_id,
+                index,
+                error: error.clone(),
+            },
+            |item| item.error = Some(error),
+        )
+    }
+
+    /// Log that an operation ran to the end (whether or not items failed)
+    pub fn finish(&self, op_id: u64) -> Result<(), String> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.operations.contains_key(&op_id) {
+            return Err(format!("Unknown operation: {}", op_id));
+        }
+        append(&mut inner.file, &Record::Finished { op_id })?;
+        inner.operations.remove(&op_id);
+        Ok(())
+    }
+
+    /// Operations recovered at startup that haven't been resumed or discarded
+    pub fn interrupted(&self) -> Vec<InterruptedOperation> {
+        self.inner.lock().unwrap().interrupted.clone()
+    }
+
+    /// Continue an interrupted operation under its original id
+    ///
+    /// Returns the items that still have to run; completed items are skipped.
+    pub fn resume(&self, op_id: u64) -> Result<ResumePlan, String> {
+        let mut inner = self.inner.lock().unwrap();
+        let position = inner
+            .interrupted
+            .iter()
+            .position(|op| op.op_id == op_id)
+            .ok_or_else(|| format!("No interrupted operation with id {}", op_id))?;
+        inner.interrupted.remove(position);
+
+        let state = &inner.operations[&op_id];
+        let items = state
+            .items
+            .iter()
+            .zip(&state.progress)
+            .enumerate()
+            .filter(|(_, (_, progress))| !progress.completed)
+            .map(|(index, (item, _))| (index, item.clone()))
+            .collect();
+
+        Ok(ResumePlan {
+            op_id,
+            kind: state.kind.clone(),
+            items,
+        })
+    }
+
+    /// Give up on an interrupted operation and drop it from the log
+    pub fn discard(&self, op_id: u64) -> Result<(), String> {
+        let mut inner = self.inner.lock().unwrap();
+        let position = inner
+            .interrupted
+            .iter()
+            .position(|op| op.op_id == op_id)
+            .ok_or_else(|| format!("No interrupted operation with id {}", op_id))?;
+        append(&mut inner.file, &Record::Discarded { op_id })?;
+        inner.interrupted.remove(position);
+        inner.operations.remove(&op_id);
+        Ok(())
+    }
+
+    fn update(&self, record: Record, apply: impl FnOnce(&mut ItemProgress)) -> Result<(), String> {
+        let (op_id, index) = match &record {
+            Record::ItemStarted { op_id, index, .. }
+            | Record::ItemCompleted { op_id, index }
+            | Record::ItemFailed { op_id, index, .. } => (*op_id, *index),
+            _ => unreachable!("update only handles item records"),
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        let Inner {
+            file, operations, ..
+        } = &mut *inner;
+        let item = operations
+            .get_mut(&op_id)
+            .ok_or_else(|| format!("Unknown operation: {}", op_id))?
+            .item(index)?;
+        append(file, &record)?;
+        apply(item);
+        Ok(())
+    }
+}
+
+fn item_mut(
+    operations: &mut HashMap<u64, OperationState>,
+    op_id: u64,
+    index: usize,
+) -> Option<&mut ItemProgress> {
+    operations.get_mut(&op_id)?.progress.get_mut(index)
+}
+
+/// Summarize an unfinished operation, removing temp files of cut-off items
+fn recover(op_id: u64, state: &mut OperationState) -> InterruptedOperation {
+    let mut items = Vec::with_capacity(state.items.len());
+
+    for (index, (planned, progress)) in state.items.iter().zip(&mut state.progress).enumerate() {
+        let mut removed_temp = None;
+        let status = if progress.completed {
+            ItemStatus::Completed
+        } else if progress.error.is_some() {
+            ItemStatus::Failed
+        } else if progress.started {
+            if let Some(temp) = progress.temp_path.take() {
+                match fs::remove_file(&temp) {
+                    Ok(()) => removed_temp = Some(temp),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => log::warn!("Failed to remove partial output {}: {}", temp, e),
+                }
+            }
+            progress.started = false;
+            ItemStatus::Partial
+        } else {
+            ItemStatus::Pending
+        };
+
+        items.push(RecoveredItem {
+            index,
+            source: planned.source.clone(),
+            output: planned.output.clone(),
+            status,
+            error: progress.error.clone(),
+            removed_temp,
+        });
+    }
+
+    let completed = items
+        .iter()
+        .filter(|item| item.status == ItemStatus::Completed)
+        .count();
+
+    InterruptedOperation {
+        op_id,
+        kind: state.kind.clone(),
+        started_at: state.started_at,
+        completed,
+        remaining: items.len() - completed,
+        items,
+    }
+}
+
+/// Read every intact record; a torn final line from a crash is skipped
+fn read_records(path: &Path) -> Vec<Record> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(_) if line.trim().is_empty() => {}
+            Err(e) => log::warn!("Skipping unreadable operation log entry: {}", e),
+        }
+    }
+    records
+}
+
+/// Atomically replace the log with `records` and reopen it for appending
+fn compact(path: &Path, records: &[Record]) -> Result<File, String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create log directory: {}", e))?;
+    }
+
+    let tmp = path.with_extension("log.tmp");
+    let mut file = File::create(&tmp).map_err(|e| format!("Failed to write log: {}", e))?;
+    for record in records {
+        write_record(&mut file, record)?;
+    }
+    file.sync_all()
+        .map_err(|e| format!("Failed to flush log: {}", e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to replace log: {}", e))?;
+
+    OpenOptions::new()
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open log: {}", e))
+}
+
+fn append(file: &mut Option<File>, record: &Record) -> Result<(), String> {
+    let Some(file) = file else {
+        return Ok(());
+    };
+    write_record(file, record)?;
+    file.sync_data()
+        .map_err(|e| format!("Failed to flush operation log: {}", e))
+}
+
+fn write_record(file: &mut File, record: &Record) -> Result<(), String> {
+    let mut line = serde_json::to_string(record)
+        .map_err(|e| format!("Failed to serialize log entry: {}", e))?;
+    line.push('\n');
+    file.write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to write operation log: {}", e))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str) -> PlannedItem {
+        PlannedItem {
+            source: format!("{}.obj", name),
+            output: Some(format!("{}.glb", name)),
+        }
+    }
+
+    #[test]
+    fn test_recovers_interrupted_operation() {
+        let dir = std::env::temp_dir().join(format!("sweedle-oplog-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("operations.log");
+        let temp = dir.join("b.glb.partial");
+
+        let log = OperationLog::open(path.clone());
+        let finished = log.begin("convert", vec![item("x")]).unwrap();
+        log.finish(finished).unwrap();
+
+        let op = log
+            .begin("convert", vec![item("a"), item("b"), item("c")])
+            .unwrap();
+        log.item_started(op, 0, None).unwrap();
+        log.item_completed(op, 0).unwrap();
+        log.item_started(op, 1, Some(temp.to_string_lossy().to_string()))
+            .unwrap();
+        fs::write(&temp, b"half a model").unwrap();
+        drop(log);
+
+        // Simulate a crash in the middle of writing the next entry
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"type":"item_comp"#).unwrap();
+        drop(file);
+
+        let log = OperationLog::open(path.clone());
+        let interrupted = log.interrupted();
+        assert_eq!(interrupted.len(), 1);
+        let statuses: Vec<ItemStatus> = interrupted[0].items.iter().map(|i| i.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                ItemStatus::Completed,
+                ItemStatus::Partial,
+                ItemStatus::Pending
+            ]
+        );
+        assert!(!temp.exists());
+
+        let plan = log.resume(op).unwrap();
+        assert_eq!(
+            plan.items.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert!(log.begin("convert", vec![]).unwrap() > op);
+        log.finish(op).unwrap();
+        drop(log);
+
+        // Finished and compacted away; only the empty operation above remains
+        let log = OperationLog::open(path);
+        assert!(log.interrupted().iter().all(|o| o.op_id != op));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,300 @@
+//! Ranking orientations for 3D printing
+//!
+//! A candidate is a direction in the mesh that would face the build plate.
+//! Each is rated on the support it needs (overhanging area times its height
+//! above the plate), how much flat surface rests on the plate, and how tall
+//! the print stands. Candidates are the six axes, an even spread of
+//! directions, and the normals of the mesh's largest flat areas, which
+//! usually make the best bases.
+
+use crate::utils::compute::sphere_directions;
+use crate::utils::mesh_store::MeshData;
+use crate::utils::simd;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Flat areas tried as bases
+const FLAT_CANDIDATES: usize = 8;
+/// Results within this many degrees of a better one are dropped
+const DISTINCT_DEGREES: f32 = 5.0;
+/// How far a face may lean and still sit flat on the plate
+const CONTACT_DEGREES: f32 = 5.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrientationCriteria {
+    /// How much less support raises the score (default 1)
+    pub support_weight: f32,
+    /// How much more plate contact raises the score (default 0.5)
+    pub contact_weight: f32,
+    /// How much a lower print raises the score (default 0.25)
+    pub height_weight: f32,
+    /// Steepest overhang printable without support, from vertical (default 45°)
+    pub overhang_angle_deg: f32,
+    /// Evenly spread directions tried besides the axes and flat areas (default 64)
+    pub samples: u32,
+    /// Orientations returned (default 5)
+    pub max_results: usize,
+}
+
+impl Default for OrientationCriteria {
+    fn default() -> Self {
+        Self {
+            support_weight: 1.0,
+            contact_weight: 0.5,
+            height_weight: 0.25,
+            overhang_angle_deg: 45.0,
+            samples: 64,
+            max_results: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintOrientation {
+    /// Unit direction in the mesh that faces down onto the plate
+    pub down: [f32; 3],
+    /// Quaternion (x, y, z, w) turning `down` to -Y, as a glTF node rotation
+    pub rotation: [f32; 4],
+    /// 0 to 1, higher is better, relative to the other candidates
+    pub score: f32,
+    /// Overhanging area times its height above the plate, in cubic mesh units
+    pub support_volume: f32,
+    /// Area lying flat on the plate
+    pub contact_area: f32,
+    pub print_height: f32,
+}
+
+/// Best orientations to print `mesh` in, best first
+pub fn suggest(
+    mesh: &MeshData,
+    criteria: &OrientationCriteria,
+) -> Result<Vec<PrintOrientation>, String> {
+    let weights = [
+        criteria.support_weight,
+        criteria.contact_weight,
+        criteria.height_weight,
+    ];
+    if weights.iter().any(|w| !(*w >= 0.0 && w.is_finite())) || weights.iter().sum::<f32>() == 0.0 {
+        return Err("Criteria weights must be non-negative and not all zero".to_string());
+    }
+    if !(0.0..90.0).contains(&criteria.overhang_angle_deg) {
+        return Err(format!(
+            "Overhang angle must be from 0 up to 90 degrees, got {}",
+            criteria.overhang_angle_deg
+        ));
+    }
+
+    // Outward unit normal, area and centroid of each face
+    let orientation = simd::signed_volume(&mesh.positions, &mesh.indices).signum();
+    let corner = |i: u32| {
+        let b = i as usize * 3;
+        [
+            mesh.positions[b],
+            mesh.positions[b + 1],
+            mesh.positions[b + 2],
+        ]
+    };
+    let faces: Vec<([f32; 3], f32, [f32; 3])> = mesh
+        .indices
+        .chunks_exact(3)
+        .filter_map(|f| {
+            let [a, b, c] = [f[0], f[1], f[2]].map(corner);
+            let n = cross(sub(b, a), sub(c, a));
+            let doubled = length(n);
+            (doubled > 0.0).then(|| {
+                let centroid = [0, 1, 2].map(|k| (a[k] + b[k] + c[k]) / 3.0);
+                (
+                    n.map(|c| c * orientation / doubled),
+                    doubled / 2.0,
+                    centroid,
+                )
+            })
+        })
+        .collect();
+    if faces.is_empty() {
+        return Err("Mesh has no faces with area".to_string());
+    }
+    let (lo, hi) = simd::bounds(&mesh.positions).ok_or("Mesh has no vertices")?;
+    let tolerance = length(sub(hi, lo)) * 1e-3;
+
+    let mut candidates: Vec<[f32; 3]> = (0..6)
+        .map(|i| {
+            let mut d = [0.0; 3];
+            d[i / 2] = if i % 2 == 0 { -1.0 } else { 1.0 };
+            d
+        })
+        .collect();
+    candidates.extend(flat_areas(&faces));
+    candidates.extend(sphere_directions(criteria.samples));
+
+    let overhang = criteria.overhang_angle_deg.to_radians().sin();
+    let flat = CONTACT_DEGREES.to_radians().cos();
+    let measured: Vec<([f32; 3], [f32; 3])> = candidates
+        .par_iter()
+        .map(|&down| {
+            let height_of = |p: [f32; 3]| -dot(p, down);
+            let floor = mesh
+                .positions
+                .chunks_exact(3)
+                .map(|p| height_of([p[0], p[1], p[2]]))
+                .fold(f32::MAX, f32::min);
+            let top = mesh
+                .positions
+                .chunks_exact(3)
+                .map(|p| height_of([p[0], p[1], p[2]]))
+                .fold(f32::MIN, f32::max);
+            let mut support = 0.0;
+            let mut contact = 0.0;
+            for &(normal, area, centroid) in &faces {
+                let facing = dot(normal, down);
+                let height = height_of(centroid) - floor;
+                if height <= tolerance {
+                    if facing >= flat {
+                        contact += area;
+                    }
+                } else if facing > overhang {
+                    support += area * facing * height;
+                }
+            }
+            (down, [support, contact, top - floor])
+        })
+        .collect();
+
+    // Each measure is scaled by its largest value over the candidates
+    let max = [0, 1, 2].map(|k| {
+        measured
+            .iter()
+            .map(|(_, m)| m[k])
+            .fold(0.0f32, f32::max)
+            .max(f32::MIN_POSITIVE)
+    });
+    let total: f32 = weights.iter().sum();
+    let mut ranked: Vec<PrintOrientation> = measured
+        .into_iter()
+        .map(|(down, [support, contact, height])| {
+            let penalty = weights[0] * support / max[0]
+                + weights[1] * (1.0 - contact / max[1])
+                + weights[2] * height / max[2];
+            PrintOrientation {
+                down,
+                rotation: rotation_to_floor(down),
+                score: 1.0 - penalty / total,
+                support_volume: support,
+                contact_area: contact,
+                print_height: height,
+            }
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    let distinct = DISTINCT_DEGREES.to_radians().cos();
+    let mut results: Vec<PrintOrientation> = Vec::new();
+    for candidate in ranked {
+        if results.len() == criteria.max_results {
+            break;
+        }
+        if results
+            .iter()
+            .all(|kept| dot(kept.down, candidate.down) < distinct)
+        {
+            results.push(candidate);
+        }
+    }
+    Ok(results)
+}
+
+/// Area-weighted normals of the largest sets of faces facing the same way
+fn flat_areas(faces: &[([f32; 3], f32, [f32; 3])]) -> Vec<[f32; 3]> {
+    let mut groups: HashMap<[i32; 3], ([f32; 3], f32)> = HashMap::new();
+    for &(normal, area, _) in faces {
+        let key = normal.map(|c| (c * 20.0).round() as i32);
+        let group = groups.entry(key).or_insert(([0.0; 3], 0.0));
+        group.0 = [0, 1, 2].map(|k| group.0[k] + normal[k] * area);
+        group.1 += area;
+    }
+    let mut groups: Vec<([f32; 3], f32)> = groups.into_values().collect();
+    groups.sort_by(|a, b| b.1.total_cmp(&a.1));
+    groups
+        .into_iter()
+        .take(FLAT_CANDIDATES)
+        .filter_map(|(sum, _)| {
+            let len = length(sum);
+            (len > 0.0).then(|| sum.map(|c| c / len))
+        })
+        .collect()
+}
+
+/// Shortest rotation taking unit `down` to -Y
+fn rotation_to_floor(down: [f32; 3]) -> [f32; 4] {
+    let floor = [0.0, -1.0, 0.0];
+    let w = 1.0 + dot(down, floor);
+    if w < 1e-6 {
+        // Upside down: half a turn about X
+        return [1.0, 0.0, 0.0, 0.0];
+    }
+    let [x, y, z] = cross(down, floor);
+    let len = (x * x + y * y + z * z + w * w).sqrt();
+    [x / len, y / len, z / len, w / len]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slab_lies_flat() {
+        // A 2 x 0.2 x 1 slab standing on its thin end along Z
+        let slab = MeshData {
+            positions: (0..8)
+                .flat_map(|i| {
+                    [
+                        (i & 1) as f32 * 2.0,
+                        ((i >> 1) & 1) as f32 * 0.2,
+                        ((i >> 2) & 1) as f32,
+                    ]
+                })
+                .collect(),
+            indices: vec![
+                0, 2, 1, 1, 2, 3, 4, 5, 6, 5, 7, 6, 0, 1, 4, 1, 5, 4, 2, 6, 3, 3, 6, 7, 0, 4, 2, 2,
+                4, 6, 1, 3, 5, 3, 7, 5,
+            ],
+            ..Default::default()
+        };
+
+        let results = suggest(&slab, &OrientationCriteria::default()).unwrap();
+        assert_eq!(results.len(), 5);
+        let best = &results[0];
+        assert!(best.down[1].abs() > 0.999);
+        assert!((best.contact_area - 2.0).abs() < 1e-4);
+        assert!(best.support_volume < 1e-6);
+        assert!((best.print_height - 0.2).abs() < 1e-4);
+        assert!(results.windows(2).all(|w| w[0].score >= w[1].score));
+
+        // The rotation takes the chosen direction to the floor
+        let [x, y, z, w] = rotation_to_floor([1.0, 0.0, 0.0]);
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((z + half).abs() < 1e-6 && (w - half).abs() < 1e-6 && x == 0.0 && y == 0.0);
+    }
+}
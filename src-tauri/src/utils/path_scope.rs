@@ -0,0 +1,257 @@
+//! Central allow-list for filesystem paths coming from the webview
+//!
+//! File commands resolve every path through [`PathScope::check`] and refuse
+//! anything outside an approved root. Roots are the app's own directories,
+//! folders the user approved (remembered across launches) and individual
+//! files the user opened or dropped this session. Only native dialogs shown
+//! by the backend can add roots, so a compromised frontend can't widen it.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+/// Why a path is in scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScopeKind {
+    /// One of the app's own data, cache or config directories
+    App,
+    /// A folder the user approved, including everything below it
+    Directory,
+    /// A single file the user opened this session
+    File,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScopeEntry {
+    pub path: String,
+    pub kind: ScopeKind,
+    /// Kept across restarts
+    pub persistent: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct PersistedScope {
+    directories: Vec<PathBuf>,
+}
+
+#[derive(Debug, Default)]
+struct ScopeState {
+    app_dirs: Vec<PathBuf>,
+    directories: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+}
+
+/// Approved filesystem roots, managed as Tauri state
+#[derive(Default)]
+pub struct PathScope {
+    path: Option<PathBuf>,
+    state: Mutex<ScopeState>,
+}
+
+impl PathScope {
+    /// Load approved folders from `path`; `app_dirs` are always allowed
+    pub fn load(path: PathBuf, app_dirs: Vec<PathBuf>) -> Self {
+        let persisted: PersistedScope = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid path scope file {}: {}", path.display(), e);
+                PersistedScope::default()
+            }),
+            Err(_) => PersistedScope::default(),
+        };
+
+        let state = ScopeState {
+            app_dirs: app_dirs
+                .iter()
+                .filter_map(|dir| resolve(dir).ok())
+                .collect(),
+            directories: persisted
+                .directories
+                .iter()
+                .filter_map(|dir| resolve(dir).ok())
+                .collect(),
+            files: Vec::new(),
+        };
+
+        Self {
+            path: Some(path),
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Resolve `path` and make sure it lies inside an approved root
+    pub fn check(&self, path: impl AsRef<Path>) -> Result<PathBuf, String> {
+        let path = path.as_ref();
+        let resolved = resolve(path)?;
+        if self.is_allowed(&resolved) {
+            Ok(resolved)
+        } else {
+            Err(format!(
+                "Access denied: {} is outside the approved folders",
+                path.display()
+            ))
+        }
+    }
+
+    /// Whether an already resolved path is in scope
+    pub fn is_allowed(&self, resolved: &Path) -> bool {
+        let state = self.state.lock().unwrap();
+        state
+            .app_dirs
+            .iter()
+            .chain(&state.directories)
+            .any(|root| resolved.starts_with(root))
+            || state.files.iter().any(|file| file == resolved)
+    }
+
+    /// Approve a folder and everything below it, remembering it across launches
+    pub fn allow_directory(&self, path: &Path) -> Result<PathBuf, String> {
+        let resolved = resolve(path)?;
+        let mut state = self.state.lock().unwrap();
+        if !state.directories.contains(&resolved) {
+            state.directories.push(resolved.clone());
+            self.save(&state)?;
+        }
+        Ok(resolved)
+    }
+
+    /// Approve a single file for the rest of the session
+    pub fn allow_file(&self, path: &Path) -> Result<PathBuf, String> {
+        let resolved = resolve(path)?;
+        let mut state = self.state.lock().unwrap();
+        if !state.files.contains(&resolved) {
+            state.files.push(resolved.clone());
+        }
+        Ok(resolved)
+    }
+
+    /// Withdraw approval for a folder or file; app directories can't be revoked
+    pub fn revoke(&self, path: &Path) -> Result<bool, String> {
+        let resolved = resolve(path)?;
+        let mut state = self.state.lock().unwrap();
+        let directories = state.directories.len();
+        state.directories.retain(|dir| *dir != resolved);
+        let removed_directory = state.directories.len() != directories;
+        if removed_directory {
+            self.save(&state)?;
+        }
+
+        let files = state.files.len();
+        state.files.retain(|file| *file != resolved);
+        Ok(removed_directory || state.files.len() != files)
+    }
+
+    pub fn entries(&self) -> Vec<ScopeEntry> {
+        let state = self.state.lock().unwrap();
+        let entry = |path: &PathBuf, kind, persistent| ScopeEntry {
+            path: path.to_string_lossy().to_string(),
+            kind,
+            persistent,
+        };
+
+        state
+            .app_dirs
+            .iter()
+            .map(|p| entry(p, ScopeKind::App, true))
+            .chain(
+                state
+                    .directories
+                    .iter()
+                    .map(|p| entry(p, ScopeKind::Directory, true)),
+            )
+            .chain(state.files.iter().map(|p| entry(p, ScopeKind::File, false)))
+            .collect()
+    }
+
+    fn save(&self, state: &ScopeState) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(&PersistedScope {
+            directories: state.directories.clone(),
+        })
+        .map_err(|e| format!("Failed to serialize path scope: {}", e))?;
+
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| format!("Failed to write path scope: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to save path scope: {}", e))
+    }
+}
+
+/// Canonicalize an absolute path, following symlinks
+///
+/// Paths that don't exist yet are resolved through their nearest existing
+/// ancestor, so e.g. an output file can be checked before it's written.
+pub fn resolve(path: &Path) -> Result<PathBuf, String> {
+    if !path.is_absolute() {
+        return Err(format!("Path must be absolute: {}", path.display()));
+    }
+
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        match fs::canonicalize(existing) {
+            Ok(mut resolved) => {
+                for component in missing.iter().rev() {
+                    resolved.push(component);
+                }
+                return Ok(resolved);
+            }
+            Err(_) => {
+                let name = match existing.components().next_back() {
+                    Some(Component::Normal(name)) => name,
+                    _ => return Err(format!("Invalid path: {}", path.display())),
+                };
+                missing.push(name.to_os_string());
+                existing = existing
+                    .parent()
+                    .ok_or_else(|| format!("Invalid path: {}", path.display()))?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_confines_paths_to_approved_roots() {
+        let base = std::env::temp_dir().join(format!("sweedle-scope-{}", std::process::id()));
+        let approved = base.join("approved");
+        let other = base.join("other");
+        fs::create_dir_all(&approved).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        fs::write(other.join("secret.glb"), b"").unwrap();
+
+        let scope = PathScope::default();
+        assert!(scope.check(approved.join("model.glb")).is_err());
+
+        scope.allow_directory(&approved).unwrap();
+        assert!(scope.check(approved.join("model.glb")).is_ok());
+        assert!(scope.check(approved.join("new/dir/out.glb")).is_ok());
+        assert!(scope.check(approved.join("../other/secret.glb")).is_err());
+        assert!(scope
+            .check(approved.join("missing/../../other/x.glb"))
+            .is_err());
+        assert!(scope.check(other.join("secret.glb")).is_err());
+        assert!(scope.check("relative/model.glb").is_err());
+
+        scope.allow_file(&other.join("secret.glb")).unwrap();
+        assert!(scope.check(other.join("secret.glb")).is_ok());
+        assert!(scope.check(other.join("neighbour.glb")).is_err());
+
+        assert!(scope.revoke(&approved).unwrap());
+        assert!(scope.check(approved.join("model.glb")).is_err());
+
+        fs::remove_dir_all(&base).ok();
+    }
+}
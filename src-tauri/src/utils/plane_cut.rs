@@ -0,0 +1,537 @@
+//! Cutting a mesh in two along a plane
+//!
+//! Each face crossing the plane is clipped into the part in front of the
+//! plane and the part behind it, with new vertices on the crossing edges
+//! interpolated from the edge's ends. Crossings are computed from the edge's
+//! ends in a fixed order, so the faces on either side of an edge, and UV
+//! seam copies of it, land on exactly the same point and the halves stay
+//! welded along the cut.
+//!
+//! Capping closes each half with the cross-section: the cut loops are
+//! chained as for sections, loops inside other loops become holes bridged
+//! into their outline, and the outlines are ear clipped.
+
+use crate::utils::mesh_store::MeshData;
+use crate::utils::section::{self, SectionPlane};
+use crate::utils::skin::MAX_INFLUENCES;
+use crate::utils::triangulate;
+use std::collections::HashMap;
+
+/// The two halves of a cut mesh
+#[derive(Debug, Clone)]
+pub struct PlaneCut {
+    /// The part on the side the plane's normal points to
+    pub front: MeshData,
+    pub back: MeshData,
+    /// Closed cut loops, capped if asked to
+    pub closed_loop_count: usize,
+    /// Cut runs that leave through a hole in the mesh and can't be capped
+    pub open_loop_count: usize,
+}
+
+/// Where a vertex of a half comes from
+#[derive(Debug, Clone, Copy)]
+enum Source {
+    Corner(u32),
+    /// A point on the edge from `a` to `b`
+    Edge {
+        a: u32,
+        b: u32,
+        t: f32,
+    },
+}
+
+impl Source {
+    /// Identifies the vertex within a half; seam copies stay apart
+    fn key(self) -> (u32, u32) {
+        match self {
+            Source::Corner(i) => (i, i),
+            Source::Edge { a, b, .. } => (a, b),
+        }
+    }
+
+    fn position(self, mesh: &MeshData) -> [f32; 3] {
+        match self {
+            Source::Corner(i) => point(mesh, i),
+            Source::Edge { a, b, t } => {
+                let (pa, pb) = (point(mesh, a), point(mesh, b));
+                [0, 1, 2].map(|k| pa[k] + (pb[k] - pa[k]) * t)
+            }
+        }
+    }
+}
+
+/// Vertices and faces of one half as they are gathered
+#[derive(Default)]
+struct Half {
+    shared: HashMap<(u32, u32), u32>,
+    /// With the normal to give cap vertices
+    vertices: Vec<(Source, Option<[f32; 3]>)>,
+    indices: Vec<u32>,
+}
+
+impl Half {
+    fn vertex(&mut self, source: Source) -> u32 {
+        let next = self.vertices.len() as u32;
+        *self.shared.entry(source.key()).or_insert_with(|| {
+            self.vertices.push((source, None));
+            next
+        })
+    }
+
+    /// Fan a convex clipped face, skipping corners it repeats
+    fn face(&mut self, polygon: &[Source]) {
+        let mut corners: Vec<u32> = polygon.iter().map(|&s| self.vertex(s)).collect();
+        corners.dedup();
+        if corners.len() > 1 && corners.first() == corners.last() {
+            corners.pop();
+        }
+        for i in 1..corners.len().saturating_sub(1) {
+            self.indices
+                .extend([corners[0], corners[i], corners[i + 1]]);
+        }
+    }
+
+    fn build(self, mesh: &MeshData) -> MeshData {
+        let lerp = |values: &[f32], width: usize, source: Source| -> Vec<f32> {
+            let at = |i: u32| &values[i as usize * width..(i as usize + 1) * width];
+            match source {
+                Source::Corner(i) => at(i).to_vec(),
+                Source::Edge { a, b, t } => at(a)
+                    .iter()
+                    .zip(at(b))
+                    .map(|(x, y)| x + (y - x) * t)
+                    .collect(),
+            }
+        };
+        // Skin weights don't blend; take the nearer end's
+        let nearer = |source: Source| match source {
+            Source::Corner(i) => i,
+            Source::Edge { a, b, t } => {
+                if t < 0.5 {
+                    a
+                } else {
+                    b
+                }
+            }
+        };
+        let attribute = |values: &Option<Vec<f32>>, width: usize| {
+            values.as_ref().map(|values| {
+                self.vertices
+                    .iter()
+                    .flat_map(|&(source, _)| lerp(values, width, source))
+                    .collect::<Vec<f32>>()
+            })
+        };
+
+        let normals = mesh.normals.as_ref().map(|normals| {
+            self.vertices
+                .iter()
+                .flat_map(|&(source, cap)| {
+                    cap.unwrap_or_else(|| {
+                        let n = lerp(normals, 3, source);
+                        let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+                        if len > 0.0 {
+                            [n[0] / len, n[1] / len, n[2] / len]
+                        } else {
+                            [0.0; 3]
+                        }
+                    })
+                })
+                .collect()
+        });
+        MeshData {
+            positions: self
+                .vertices
+                .iter()
+                .flat_map(|&(source, _)| source.position(mesh))
+                .collect(),
+            normals,
+            uvs: attribute(&mesh.uvs, 2),
+            lightmap_uvs: attribute(&mesh.lightmap_uvs, 2),
+            colors: attribute(&mesh.colors, 4),
+            indices: self.indices,
+            skin_joints: mesh.skin_joints.as_ref().map(|joints| {
+                self.vertices
+                    .iter()
+                    .flat_map(|&(source, _)| {
+                        let i = nearer(source) as usize * MAX_INFLUENCES;
+                        joints[i..i + MAX_INFLUENCES].to_vec()
+                    })
+                    .collect()
+            }),
+            skin_weights: mesh.skin_weights.as_ref().map(|weights| {
+                self.vertices
+                    .iter()
+                    .flat_map(|&(source, _)| {
+                        let i = nearer(source) as usize * MAX_INFLUENCES;
+                        weights[i..i + MAX_INFLUENCES].to_vec()
+                    })
+                    .collect()
+            }),
+            polygons: None,
+        }
+    }
+}
+
+/// Cut `mesh` with `plane` (with a unit normal), capping the cut if `cap`
+///
+/// Vertices exactly on the plane count as in front of it, as in sections.
+/// Cap vertices take the attributes of the cut edge they sit on, with
+/// normals facing out of the cut. Polygon information is dropped.
+pub fn cut(mesh: &MeshData, plane: &SectionPlane, cap: bool) -> Result<PlaneCut, String> {
+    let distance: Vec<f32> = mesh
+        .positions
+        .chunks_exact(3)
+        .map(|p| dot(plane.normal, [p[0], p[1], p[2]]) - plane.offset)
+        .collect();
+    let crossing = |a: u32, b: u32| {
+        // The same ends in the same order give the same point every time
+        let bits = |i: u32| point(mesh, i).map(f32::to_bits);
+        let (lo, hi) = if bits(a) <= bits(b) { (a, b) } else { (b, a) };
+        let (dl, dh) = (distance[lo as usize], distance[hi as usize]);
+        let t = dl / (dl - dh);
+        if t <= 0.0 {
+            Source::Corner(lo)
+        } else if t >= 1.0 {
+            Source::Corner(hi)
+        } else {
+            Source::Edge { a: lo, b: hi, t }
+        }
+    };
+
+    let mut front = Half::default();
+    let mut back = Half::default();
+    // Cut segments between points keyed by their exact position
+    let mut nodes: HashMap<[u32; 3], u32> = HashMap::new();
+    let mut node_sources: Vec<Source> = Vec::new();
+    let mut segments: Vec<[u32; 2]> = Vec::new();
+    for face in mesh.indices.chunks_exact(3) {
+        let in_front = |i: u32| distance[i as usize] >= 0.0;
+        if face.iter().all(|&i| in_front(i)) {
+            front.face(&face.iter().map(|&i| Source::Corner(i)).collect::<Vec<_>>());
+            continue;
+        }
+        if !face.iter().any(|&i| in_front(i)) {
+            back.face(&face.iter().map(|&i| Source::Corner(i)).collect::<Vec<_>>());
+            continue;
+        }
+
+        let mut front_polygon = Vec::with_capacity(4);
+        let mut back_polygon = Vec::with_capacity(4);
+        let mut ends = Vec::with_capacity(2);
+        for k in 0..3 {
+            let (p, q) = (face[k], face[(k + 1) % 3]);
+            if in_front(p) {
+                front_polygon.push(Source::Corner(p));
+            } else {
+                back_polygon.push(Source::Corner(p));
+            }
+            if in_front(p) == in_front(q) {
+                continue;
+            }
+            let x = crossing(p, q);
+            match x {
+                // A corner on the plane is already in the front part
+                Source::Corner(_) => back_polygon.push(x),
+                Source::Edge { .. } => {
+                    front_polygon.push(x);
+                    back_polygon.push(x);
+                }
+            }
+            let next = nodes.len() as u32;
+            ends.push(
+                *nodes
+                    .entry(x.position(mesh).map(f32::to_bits))
+                    .or_insert_with(|| {
+                        node_sources.push(x);
+                        next
+                    }),
+            );
+        }
+        front.face(&front_polygon);
+        back.face(&back_polygon);
+        if let [a, b] = ends[..] {
+            if a != b {
+                segments.push([a, b]);
+            }
+        }
+    }
+    if front.indices.is_empty() || back.indices.is_empty() {
+        return Err("The plane doesn't cut through the mesh".to_string());
+    }
+
+    let chains = section::chain(&segments, node_sources.len());
+    let loops: Vec<Vec<u32>> = chains
+        .iter()
+        .filter(|(_, closed)| *closed)
+        .map(|(chain, _)| chain.clone())
+        .collect();
+    let closed_loop_count = loops.len();
+    let open_loop_count = chains.len() - closed_loop_count;
+
+    if cap {
+        let points: Vec<[f32; 3]> = node_sources.iter().map(|s| s.position(mesh)).collect();
+        let (u, v) = plane.axes();
+        let flat: Vec<[f32; 2]> = points.iter().map(|&p| [dot(p, u), dot(p, v)]).collect();
+        for outline in cap_outlines(&loops, &flat) {
+            let corners: Vec<[f32; 3]> = outline.iter().map(|&n| points[n as usize]).collect();
+            let triangles = triangulate::ear_clip(&corners)
+                .unwrap_or_else(|| (1..corners.len() - 1).map(|i| [0, i, i + 1]).collect());
+            // Outlines run counter-clockwise about the normal, which is
+            // outward for the back half's cap
+            for (half, normal, flip) in [
+                (&mut back, plane.normal, false),
+                (&mut front, plane.normal.map(|n| -n), true),
+            ] {
+                let base = half.vertices.len() as u32;
+                half.vertices.extend(
+                    outline
+                        .iter()
+                        .map(|&n| (node_sources[n as usize], Some(normal))),
+                );
+                for [a, b, c] in &triangles {
+                    let [a, b, c] = [*a, *b, *c].map(|k| base + k as u32);
+                    half.indices
+                        .extend(if flip { [a, c, b] } else { [a, b, c] });
+                }
+            }
+        }
+    }
+
+    Ok(PlaneCut {
+        front: front.build(mesh),
+        back: back.build(mesh),
+        closed_loop_count,
+        open_loop_count,
+    })
+}
+
+/// Outlines of the cross-section's solid regions, counter-clockwise in
+/// plane coordinates, with the holes inside each joined in
+///
+/// A loop inside an odd number of others is a hole in the innermost of
+/// them.
+fn cap_outlines(loops: &[Vec<u32>], flat: &[[f32; 2]]) -> Vec<Vec<u32>> {
+    let contains = |outer: &[u32], p: [f32; 2]| {
+        let mut inside = false;
+        for (i, &a) in outer.iter().enumerate() {
+            let (a, b) = (
+                flat[a as usize],
+                flat[outer[(i + 1) % outer.len()] as usize],
+            );
+            if (a[1] > p[1]) != (b[1] > p[1])
+                && p[0] < a[0] + (p[1] - a[1]) * (b[0] - a[0]) / (b[1] - a[1])
+            {
+                inside = !inside;
+            }
+        }
+        inside
+    };
+    let area = |ring: &[u32]| -> f32 {
+        (0..ring.len())
+            .map(|i| {
+                let (a, b) = (
+                    flat[ring[i] as usize],
+                    flat[ring[(i + 1) % ring.len()] as usize],
+                );
+                a[0] * b[1] - b[0] * a[1]
+            })
+            .sum::<f32>()
+            / 2.0
+    };
+
+    let parents: Vec<Vec<usize>> = loops
+        .iter()
+        .enumerate()
+        .map(|(i, ring)| {
+            (0..loops.len())
+                .filter(|&j| j != i && contains(&loops[j], flat[ring[0] as usize]))
+                .collect()
+        })
+        .collect();
+    let mut outlines: Vec<(usize, Vec<u32>)> = Vec::new();
+    let mut holes: Vec<(usize, Vec<u32>)> = Vec::new();
+    for (i, ring) in loops.iter().enumerate() {
+        let mut ring = ring.clone();
+        let depth = parents[i].len();
+        if depth.is_multiple_of(2) {
+            if area(&ring) < 0.0 {
+                ring.reverse();
+            }
+            outlines.push((i, ring));
+        } else {
+            if area(&ring) > 0.0 {
+                ring.reverse();
+            }
+            let Some(&parent) = parents[i].iter().find(|&&j| parents[j].len() == depth - 1) else {
+                continue;
+            };
+            holes.push((parent, ring));
+        }
+    }
+
+    // Bridge the holes furthest along X first, so later bridges can't cross
+    // earlier ones
+    let max_x = |ring: &[u32]| {
+        ring.iter()
+            .map(|&n| flat[n as usize][0])
+            .fold(f32::MIN, f32::max)
+    };
+    holes.sort_by(|a, b| max_x(&b.1).total_cmp(&max_x(&a.1)));
+    for (parent, hole) in holes {
+        if let Some((_, outline)) = outlines.iter_mut().find(|(i, _)| *i == parent) {
+            bridge(outline, &hole, flat);
+        }
+    }
+    outlines.into_iter().map(|(_, outline)| outline).collect()
+}
+
+/// Splice `hole` into `outline` through a cut from the hole's rightmost
+/// point to an outline point it can see
+fn bridge(outline: &mut Vec<u32>, hole: &[u32], flat: &[[f32; 2]]) {
+    let at = |n: u32| flat[n as usize];
+    let m = (0..hole.len())
+        .max_by(|&a, &b| at(hole[a])[0].total_cmp(&at(hole[b])[0]))
+        .unwrap_or(0);
+    let hm = at(hole[m]);
+    let n = outline.len();
+
+    // Nearest outline edge to the right of the hole's point
+    let mut hit: Option<(f32, usize)> = None;
+    for i in 0..n {
+        let (a, b) = (at(outline[i]), at(outline[(i + 1) % n]));
+        if (a[1] > hm[1]) == (b[1] > hm[1]) {
+            continue;
+        }
+        let x = a[0] + (hm[1] - a[1]) * (b[0] - a[0]) / (b[1] - a[1]);
+        if x >= hm[0] && hit.is_none_or(|(best, _)| x < best) {
+            hit = Some((x, i));
+        }
+    }
+    let distance = |p: [f32; 2]| (p[0] - hm[0]).powi(2) + (p[1] - hm[1]).powi(2);
+    let to = match hit {
+        Some((x, i)) => {
+            let j = (i + 1) % n;
+            let mut to = if at(outline[i])[0] > at(outline[j])[0] {
+                i
+            } else {
+                j
+            };
+            // A reflex outline corner inside the triangle from the hole to
+            // the hit would block the view; take the one closest in angle
+            let (hit_point, candidate) = ([x, hm[1]], at(outline[to]));
+            let mut best = f32::MAX;
+            for k in 0..n {
+                let p = at(outline[k]);
+                let (prev, next) = (at(outline[(k + n - 1) % n]), at(outline[(k + 1) % n]));
+                if k == to || cross2(prev, p, next) >= 0.0 || p == candidate {
+                    continue;
+                }
+                if in_triangle(p, hm, hit_point, candidate) {
+                    let angle = (p[1] - hm[1]).abs().atan2(p[0] - hm[0]);
+                    if angle < best {
+                        best = angle;
+                        to = k;
+                    }
+                }
+            }
+            to
+        }
+        // Can't happen for a hole inside its outline; join the nearest point
+        None => (0..n)
+            .min_by(|&a, &b| distance(at(outline[a])).total_cmp(&distance(at(outline[b]))))
+            .unwrap_or(0),
+    };
+
+    let mut joined = Vec::with_capacity(n + hole.len() + 2);
+    joined.extend_from_slice(&outline[..=to]);
+    joined.extend(hole[m..].iter().chain(&hole[..=m]));
+    joined.extend_from_slice(&outline[to..]);
+    *outline = joined;
+}
+
+fn in_triangle(p: [f32; 2], a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> bool {
+    let (d1, d2, d3) = (cross2(a, b, p), cross2(b, c, p), cross2(c, a, p));
+    let negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(negative && positive)
+}
+
+fn cross2(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+fn point(mesh: &MeshData, i: u32) -> [f32; 3] {
+    let p = &mesh.positions[i as usize * 3..i as usize * 3 + 3];
+    [p[0], p[1], p[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::simd;
+    use crate::utils::topology;
+
+    /// Axis-aligned box from `lo` to `hi` with unwelded faces, wound
+    /// outward, or inward for `inward`
+    fn cube(mesh: &mut MeshData, lo: f32, hi: f32, inward: bool) {
+        let corners: Vec<[f32; 3]> = (0..8)
+            .map(|i| [i & 1, (i >> 1) & 1, (i >> 2) & 1].map(|c| if c == 1 { hi } else { lo }))
+            .collect();
+        let quads = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        for quad in quads {
+            let base = mesh.vertex_count() as u32;
+            mesh.positions.extend(quad.iter().flat_map(|&c| corners[c]));
+            let mut faces = [0, 1, 2, 0, 2, 3].map(|k| base + k);
+            if inward {
+                faces.reverse();
+            }
+            mesh.indices.extend(faces);
+        }
+    }
+
+    #[test]
+    fn test_capped_halves_are_closed_including_around_holes() {
+        // A box with a box-shaped cavity, so the cut is a square annulus
+        let mut mesh = MeshData::default();
+        cube(&mut mesh, 0.0, 1.0, false);
+        cube(&mut mesh, 0.25, 0.75, true);
+        let plane = SectionPlane {
+            normal: [0.0, 0.0, 1.0],
+            offset: 0.4,
+        };
+
+        let cut = cut(&mesh, &plane, true).unwrap();
+        assert_eq!((cut.closed_loop_count, cut.open_loop_count), (2, 0));
+        // Each half of the outer box less its part of the cavity
+        for (half, expected) in [(&cut.front, 0.6 - 0.0875), (&cut.back, 0.4 - 0.0375)] {
+            let shape = topology::analyze(&half.positions, &half.indices);
+            assert_eq!(shape.boundary_loop_count, 0);
+            assert!(shape.is_manifold());
+            let volume = simd::signed_volume(&half.positions, &half.indices);
+            assert!((volume - expected).abs() < 1e-4, "{}", volume);
+        }
+
+        let open = super::cut(&mesh, &plane, false).unwrap();
+        let shape = topology::analyze(&open.back.positions, &open.back.indices);
+        assert_eq!(shape.boundary_loop_count, 2);
+
+        let missing = SectionPlane {
+            normal: [0.0, 0.0, 1.0],
+            offset: 2.0,
+        };
+        assert!(super::cut(&mesh, &missing, true).is_err());
+    }
+}
@@ -0,0 +1,255 @@
+//! Arranging models on a printer's build plate
+//!
+//! Layouts are in print space: Z-up millimeters with a corner of the plate
+//! at the origin, as slicers expect. Each model is centered over its own
+//! footprint and stood on the plate first, so an item is just a position
+//! on the plate and a turn about Z; a layout edited in the frontend exports
+//! exactly as shown. Packing fills rows first-fit, deepest footprints first.
+
+use crate::utils::mesh_files::NamedMesh;
+use serde::{Deserialize, Serialize};
+
+/// Millimeters per meter, the unit models are imported in
+const MM_PER_METER: f32 = 1000.0;
+
+/// A model placed on the plate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlateItem {
+    pub asset_id: String,
+    /// Where the center of the model's footprint sits
+    pub position_mm: [f32; 2],
+    /// Counter-clockwise turn about Z, seen from above
+    pub rotation_deg: f32,
+    /// Size of the footprint as placed, along X and Y
+    pub footprint_mm: [f32; 2],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlateLayout {
+    pub plate_mm: [f32; 2],
+    pub spacing_mm: f32,
+    pub items: Vec<PlateItem>,
+    /// Assets that didn't fit on the plate
+    pub unplaced: Vec<String>,
+}
+
+/// A model in print space, centered over its footprint and on the plate
+#[derive(Debug, Clone, Default)]
+pub struct PlateModel {
+    pub positions: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl PlateModel {
+    /// Merge imported (Y-up meter) meshes into one print-space model
+    pub fn from_meshes(meshes: &[NamedMesh]) -> Result<Self, String> {
+        let mut model = Self::default();
+        for mesh in meshes {
+            let base = (model.positions.len() / 3) as u32;
+            model.positions.extend(
+                mesh.positions
+                    .chunks_exact(3)
+                    .flat_map(|p| [p[0], -p[2], p[1]].map(|c| c * MM_PER_METER)),
+            );
+            model.indices.extend(mesh.indices.iter().map(|i| i + base));
+        }
+        let (lo, hi) = model.bounds(0.0).ok_or("Model has no vertices")?;
+        let shift = [(lo[0] + hi[0]) / 2.0, (lo[1] + hi[1]) / 2.0, lo[2]];
+        for p in model.positions.chunks_exact_mut(3) {
+            for k in 0..3 {
+                p[k] -= shift[k];
+            }
+        }
+        Ok(model)
+    }
+
+    /// Bounds after turning `rotation_deg` about Z
+    pub fn bounds(&self, rotation_deg: f32) -> Option<([f32; 3], [f32; 3])> {
+        let mut points = self.positions.chunks_exact(3).map(rotate(rotation_deg));
+        let first = points.next()?;
+        Some(points.fold((first, first), |(lo, hi), p| {
+            (
+                [0, 1, 2].map(|k| lo[k].min(p[k])),
+                [0, 1, 2].map(|k| hi[k].max(p[k])),
+            )
+        }))
+    }
+
+    /// Positions moved to where `item` puts the model
+    pub fn placed(&self, item: &PlateItem) -> Vec<f32> {
+        let turn = rotate(item.rotation_deg);
+        self.positions
+            .chunks_exact(3)
+            .flat_map(|p| {
+                let [x, y, z] = turn(p);
+                [x + item.position_mm[0], y + item.position_mm[1], z]
+            })
+            .collect()
+    }
+}
+
+fn rotate(rotation_deg: f32) -> impl Fn(&[f32]) -> [f32; 3] {
+    let (sin, cos) = rotation_deg.to_radians().sin_cos();
+    move |p| [p[0] * cos - p[1] * sin, p[0] * sin + p[1] * cos, p[2]]
+}
+
+/// Arrange `models` on a plate, `spacing_mm` apart and from the edges
+///
+/// Each footprint is turned a quarter if that lets it fit or puts its long
+/// side along X. Items keep the order of `models`.
+pub fn pack(
+    models: &[(String, PlateModel)],
+    plate_mm: [f32; 2],
+    spacing_mm: f32,
+) -> Result<PlateLayout, String> {
+    if !plate_mm.iter().all(|d| *d > 0.0 && d.is_finite()) {
+        return Err(format!(
+            "Plate dimensions must be positive, got {:?}",
+            plate_mm
+        ));
+    }
+    if !(spacing_mm >= 0.0 && spacing_mm.is_finite()) {
+        return Err(format!("Spacing must be zero or more, got {}", spacing_mm));
+    }
+
+    // Footprint and turn that packs best, or `None` if it can't fit
+    let usable = plate_mm.map(|d| d - 2.0 * spacing_mm);
+    let fits = |size: [f32; 2]| size[0] <= usable[0] && size[1] <= usable[1];
+    let choices: Vec<Option<([f32; 2], f32)>> = models
+        .iter()
+        .map(|(_, model)| {
+            let (lo, hi) = model.bounds(0.0)?;
+            let size = [hi[0] - lo[0], hi[1] - lo[1]];
+            let turned = [size[1], size[0]];
+            match (fits(size), fits(turned)) {
+                (true, true) if size[1] > size[0] => Some((turned, 90.0)),
+                (true, _) => Some((size, 0.0)),
+                (false, true) => Some((turned, 90.0)),
+                (false, false) => None,
+            }
+        })
+        .collect();
+
+    let mut order: Vec<usize> = (0..models.len())
+        .filter(|&i| choices[i].is_some())
+        .collect();
+    order.sort_by(|&a, &b| {
+        let depth = |i: usize| choices[i].map_or(0.0, |(size, _)| size[1]);
+        depth(b).total_cmp(&depth(a))
+    });
+
+    // Rows as (y, depth, x where the next item would go)
+    let mut rows: Vec<(f32, f32, f32)> = Vec::new();
+    let mut placed: Vec<Option<PlateItem>> = vec![None; models.len()];
+    for i in order {
+        let Some((size, rotation_deg)) = choices[i] else {
+            continue;
+        };
+        let right = plate_mm[0] - spacing_mm;
+        let slot = match rows
+            .iter_mut()
+            .find(|row| row.2 + size[0] <= right && size[1] <= row.1)
+        {
+            Some(row) => {
+                let x = row.2;
+                row.2 += size[0] + spacing_mm;
+                Some([x, row.0])
+            }
+            None => {
+                let y = rows.last().map_or(spacing_mm, |r| r.0 + r.1 + spacing_mm);
+                (y + size[1] <= plate_mm[1] - spacing_mm).then(|| {
+                    rows.push((y, size[1], spacing_mm + size[0] + spacing_mm));
+                    [spacing_mm, y]
+                })
+            }
+        };
+        if let Some([x, y]) = slot {
+            placed[i] = Some(PlateItem {
+                asset_id: models[i].0.clone(),
+                position_mm: [x + size[0] / 2.0, y + size[1] / 2.0],
+                rotation_deg,
+                footprint_mm: size,
+            });
+        }
+    }
+
+    let unplaced = models
+        .iter()
+        .zip(&placed)
+        .filter(|(_, item)| item.is_none())
+        .map(|((id, _), _)| id.clone())
+        .collect();
+    Ok(PlateLayout {
+        plate_mm,
+        spacing_mm,
+        items: placed.into_iter().flatten().collect(),
+        unplaced,
+    })
+}
+
+/// Whether a placed model reaches past the edge of the plate
+pub fn off_plate(model: &PlateModel, item: &PlateItem, plate_mm: [f32; 2]) -> bool {
+    model.bounds(item.rotation_deg).is_none_or(|(lo, hi)| {
+        (0..2)
+            .any(|k| lo[k] + item.position_mm[k] < 0.0 || hi[k] + item.position_mm[k] > plate_mm[k])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(size: [f32; 3]) -> PlateModel {
+        let mesh = NamedMesh {
+            // Y-up meters
+            positions: (0..8)
+                .flat_map(|i| {
+                    [
+                        (i & 1) as f32 * size[0],
+                        ((i >> 2) & 1) as f32 * size[2],
+                        ((i >> 1) & 1) as f32 * size[1],
+                    ]
+                })
+                .map(|c| c / MM_PER_METER)
+                .collect(),
+            ..Default::default()
+        };
+        PlateModel::from_meshes(&[mesh]).unwrap()
+    }
+
+    #[test]
+    fn test_pack_blocks_on_plate() {
+        let tall = block([20.0, 60.0, 5.0]);
+        let wide = block([50.0, 30.0, 10.0]);
+        let huge = block([500.0, 500.0, 10.0]);
+        assert_eq!(
+            tall.bounds(0.0),
+            Some(([-10.0, -30.0, 0.0], [10.0, 30.0, 5.0]))
+        );
+
+        let models = [
+            ("tall".to_string(), tall),
+            ("wide".to_string(), wide),
+            ("huge".to_string(), huge),
+        ];
+        let layout = pack(&models, [100.0, 100.0], 5.0).unwrap();
+        assert_eq!(layout.unplaced, vec!["huge".to_string()]);
+        assert_eq!(layout.items.len(), 2);
+
+        // The deeper wide block starts the first row; the tall one turns
+        // to lie along X and, too long to fit beside it, starts a second
+        let tall = &layout.items[0];
+        assert_eq!(tall.rotation_deg, 90.0);
+        assert_eq!(tall.footprint_mm, [60.0, 20.0]);
+        assert_eq!(tall.position_mm, [35.0, 50.0]);
+        let wide = &layout.items[1];
+        assert_eq!(wide.footprint_mm, [50.0, 30.0]);
+        assert_eq!(wide.position_mm, [30.0, 20.0]);
+
+        // Moved past the edge, an item is flagged
+        let mut moved = wide.clone();
+        assert!(!off_plate(&models[1].1, &moved, layout.plate_mm));
+        moved.position_mm[0] = 90.0;
+        assert!(off_plate(&models[1].1, &moved, layout.plate_mm));
+    }
+}
@@ -0,0 +1,147 @@
+//! What the mobile build does differently
+//!
+//! Tauri's build script sets `cfg(mobile)` for iOS and Android. Phones limit how much of a file may
+//! be memory-mapped and have few cores to spare next to the UI, so the mobile
+//! build reads files with plain streamed reads, runs parallel work on
+//! [`MOBILE_THREADS`] threads, only analyzes a model when it is asked about,
+//! and leaves out the material preview renderer, the headless server and
+//! the local API.
+
+use std::fs::File;
+use std::ops::Deref;
+use std::path::Path;
+
+#[cfg(not(mobile))]
+use memmap2::Mmap;
+
+/// Worker threads of the rayon pool on mobile
+pub const MOBILE_THREADS: usize = 2;
+
+/// The contents of a file opened for reading: memory-mapped on desktop and
+/// read into memory on mobile
+pub struct FileBytes {
+    #[cfg(not(mobile))]
+    map: Mmap,
+    #[cfg(mobile)]
+    bytes: Vec<u8>,
+}
+
+impl FileBytes {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+        Self::from_file(&file)
+    }
+
+    #[cfg(not(mobile))]
+    pub fn from_file(file: &File) -> Result<Self, String> {
+        let map = unsafe { Mmap::map(file) }.map_err(|e| format!("Failed to mmap file: {}", e))?;
+        Ok(Self { map })
+    }
+
+    #[cfg(mobile)]
+    pub fn from_file(file: &File) -> Result<Self, String> {
+        let bytes = read_streamed(file).map_err(|e| format!("Failed to read file: {}", e))?;
+        Ok(Self { bytes })
+    }
+
+    #[cfg(not(mobile))]
+    pub fn into_vec(self) -> Vec<u8> {
+        self.map.to_vec()
+    }
+
+    #[cfg(mobile)]
+    pub fn into_vec(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl Deref for FileBytes {
+    type Target = [u8];
+
+    #[cfg(not(mobile))]
+    fn deref(&self) -> &[u8] {
+        &self.map
+    }
+
+    #[cfg(mobile)]
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Read the rest of `file` into a buffer sized from its metadata
+#[cfg(any(mobile, test))]
+pub fn read_streamed(mut file: &File) -> std::io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let capacity = file.metadata().map(|m| m.len() as usize).unwrap_or(0);
+    let mut bytes = Vec::with_capacity(capacity);
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Read at most `length` bytes of `file` from `offset`, without reading
+/// the rest of it
+#[cfg(any(mobile, test))]
+pub fn read_range(mut file: &File, offset: u64, length: Option<u64>) -> std::io::Result<Vec<u8>> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let size = file.metadata()?.len();
+    if offset >= size {
+        return Ok(Vec::new());
+    }
+    let remaining = size - offset;
+    let length = length.unwrap_or(remaining).min(remaining);
+    file.seek(SeekFrom::Start(offset))?;
+    let mut bytes = Vec::with_capacity(length as usize);
+    file.take(length).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Refuse `what`, such as `The local API`, on mobile
+pub fn desktop_only(what: &str) -> Result<(), String> {
+    if !cfg!(mobile) {
+        Ok(())
+    } else {
+        Err(format!("{} is not available on mobile", what))
+    }
+}
+
+/// Run rayon's global pool on [`MOBILE_THREADS`] threads on mobile
+pub fn limit_threads() {
+    if !cfg!(mobile) {
+        return;
+    }
+    if let Err(e) = rayon::ThreadPoolBuilder::new()
+        .num_threads(MOBILE_THREADS)
+        .build_global()
+    {
+        log::warn!("Failed to limit worker threads: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_whole_and_range_reads_agree() {
+        let path = std::env::temp_dir().join(format!("sweedle-file-bytes-{}", std::process::id()));
+        let contents: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        File::create(&path).unwrap().write_all(&contents).unwrap();
+
+        assert_eq!(&*FileBytes::open(&path).unwrap(), &contents[..]);
+        assert_eq!(FileBytes::open(&path).unwrap().into_vec(), contents);
+        let file = File::open(&path).unwrap();
+        assert_eq!(read_streamed(&file).unwrap(), contents);
+        assert_eq!(
+            read_range(&file, 9_990, Some(100)).unwrap(),
+            &contents[9_990..]
+        );
+        assert_eq!(read_range(&file, 10, Some(5)).unwrap(), &contents[10..15]);
+        assert!(read_range(&file, 20_000, None).unwrap().is_empty());
+        assert_eq!(read_range(&file, 0, None).unwrap(), contents);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
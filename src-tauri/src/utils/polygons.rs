@@ -0,0 +1,141 @@
+//! Original polygon structure of triangulated meshes
+//!
+//! Everything downstream works on triangles, but artists care whether a mesh
+//! was modelled in quads. Importers record the corner count of each source
+//! polygon; since a polygon of n corners is triangulated into n - 2
+//! consecutive triangles, that's enough to rebuild the polygons on export
+//! from the outline of each group of triangles.
+
+use serde::{Deserialize, Serialize};
+
+/// Face structure of a mesh as it was authored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaceTopology {
+    /// Triangles only, or no polygon information
+    #[default]
+    Triangles,
+    /// Every polygon has four corners
+    Quads,
+    /// Quads mixed with triangles or n-gons
+    Mixed,
+}
+
+/// Classify a mesh by the corner counts of its polygons
+pub fn topology(polygons: Option<&[u32]>) -> FaceTopology {
+    let Some(polygons) = polygons.filter(|p| !p.is_empty()) else {
+        return FaceTopology::Triangles;
+    };
+    if polygons.iter().all(|&n| n == 3) {
+        FaceTopology::Triangles
+    } else if polygons.iter().all(|&n| n == 4) {
+        FaceTopology::Quads
+    } else {
+        FaceTopology::Mixed
+    }
+}
+
+/// Check that polygon corner counts account for exactly `face_count` triangles
+pub fn validate(polygons: &[u32], face_count: usize) -> Result<(), String> {
+    if let Some(&n) = polygons.iter().find(|&&n| n < 3) {
+        return Err(format!("Polygon with {} corners", n));
+    }
+    let triangles: usize = polygons.iter().map(|&n| n as usize - 2).sum();
+    if triangles != face_count {
+        return Err(format!(
+            "Polygons cover {} triangles but the mesh has {}",
+            triangles, face_count
+        ));
+    }
+    Ok(())
+}
+
+/// Corners of each polygon, rebuilt from its triangles
+///
+/// Without polygon information every triangle is its own polygon.
+pub fn corners(indices: &[u32], polygons: Option<&[u32]>) -> Vec<Vec<u32>> {
+    let Some(polygons) = polygons else {
+        return indices.chunks_exact(3).map(<[u32]>::to_vec).collect();
+    };
+    let mut faces = indices.chunks_exact(3);
+    polygons
+        .iter()
+        .map(|&n| {
+            let triangles: Vec<&[u32]> = faces.by_ref().take(n as usize - 2).collect();
+            outline(&triangles, n as usize).unwrap_or_else(|| fan_corners(&triangles))
+        })
+        .collect()
+}
+
+/// Walk the edges used by only one triangle, starting at the first corner
+///
+/// Interior diagonals are shared by two triangles in opposite directions,
+/// so what's left is the polygon's outline in its original winding. `None`
+/// if it isn't a single loop of `n` corners, as with repeated corners.
+fn outline(triangles: &[&[u32]], n: usize) -> Option<Vec<u32>> {
+    let edges: Vec<(u32, u32)> = triangles
+        .iter()
+        .flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+        .collect();
+    let boundary: Vec<(u32, u32)> = edges
+        .iter()
+        .copied()
+        .filter(|&(a, b)| !edges.contains(&(b, a)))
+        .collect();
+    if boundary.len() != n {
+        return None;
+    }
+
+    let start = triangles.first()?[0];
+    let mut corners = Vec::with_capacity(n);
+    let mut current = start;
+    for _ in 0..n {
+        corners.push(current);
+        let mut next = boundary.iter().filter(|&&(a, _)| a == current);
+        let (_, to) = *next.next()?;
+        if next.next().is_some() {
+            return None;
+        }
+        current = to;
+    }
+    (current == start).then_some(corners)
+}
+
+/// Corners assuming the triangles fan from the first corner
+fn fan_corners(triangles: &[&[u32]]) -> Vec<u32> {
+    let mut corners = Vec::with_capacity(triangles.len() + 2);
+    for (k, tri) in triangles.iter().enumerate() {
+        if k == 0 {
+            corners.extend_from_slice(tri);
+        } else {
+            corners.push(tri[2]);
+        }
+    }
+    corners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polygons_rebuild_from_fans() {
+        // A quad and a pentagon, fan-triangulated
+        let indices = [0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7, 4, 7, 8];
+        let polygons = [4, 5];
+        assert!(validate(&polygons, 5).is_ok());
+        assert!(validate(&polygons, 4).is_err());
+        assert_eq!(
+            corners(&indices, Some(&polygons)),
+            vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7, 8]]
+        );
+        // Any triangulation works, not just fans
+        assert_eq!(
+            corners(&[1, 2, 3, 3, 0, 1], Some(&[4])),
+            vec![vec![1, 2, 3, 0]]
+        );
+        assert_eq!(topology(Some(&polygons)), FaceTopology::Mixed);
+        assert_eq!(topology(Some(&[4, 4])), FaceTopology::Quads);
+        assert_eq!(topology(None), FaceTopology::Triangles);
+    }
+}
@@ -0,0 +1,416 @@
+//! Posing a glTF scene and baking the deformed meshes
+//!
+//! A pose is every node's local transform and morph weights, from the file
+//! or from a clip sampled at a time. Baking applies the pose the way a
+//! viewer draws it: morph targets first, then skinning with the joints'
+//! world transforms and inverse bind matrices, or the node's own world
+//! transform for meshes without a skin. The result is plain triangles that
+//! no longer need the skeleton.
+
+use crate::utils::animation::Clip;
+use crate::utils::gltf_document::GltfDocument;
+use crate::utils::mesh_files::NamedMesh;
+use nalgebra::{Matrix4, Point3, Quaternion, UnitQuaternion, Vector3};
+use serde_json::Value;
+
+/// glTF primitive mode for triangle lists, the default
+const TRIANGLES: u64 = 4;
+
+/// Local transforms and morph weights of every node
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    pub locals: Vec<Matrix4<f32>>,
+    /// Morph target weights set on or animated for each node
+    pub weights: Vec<Option<Vec<f32>>>,
+}
+
+/// Meshes of a posed scene in world space
+#[derive(Debug, Clone, Default)]
+pub struct BakedPose {
+    /// One per node with a mesh, named after the node
+    pub meshes: Vec<NamedMesh>,
+    pub skinned_mesh_count: usize,
+    pub morphed_mesh_count: usize,
+    /// Primitives skipped for not being triangle lists
+    pub skipped_primitive_count: usize,
+}
+
+impl Pose {
+    /// The pose stored in the file's nodes
+    pub fn rest(document: &GltfDocument) -> Pose {
+        Self::with_tracks(document, None, 0.0)
+    }
+
+    /// The pose `clip` gives at `time` seconds, held at its first and last
+    /// keys outside it; nodes it doesn't animate keep their rest transform
+    pub fn at(document: &GltfDocument, clip: &Clip, time: f32) -> Pose {
+        Self::with_tracks(document, Some(clip), time)
+    }
+
+    fn with_tracks(document: &GltfDocument, clip: Option<&Clip>, time: f32) -> Pose {
+        let nodes = document.json["nodes"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let mut locals = Vec::with_capacity(nodes.len());
+        let mut weights = Vec::with_capacity(nodes.len());
+        for (index, node) in nodes.iter().enumerate() {
+            let sampled = |path: &str| {
+                clip.into_iter()
+                    .flat_map(|clip| &clip.tracks)
+                    .find(|t| t.node == index && t.path == path)
+                    .map(|t| t.sample(time))
+            };
+            locals.push(local_transform(
+                node,
+                sampled("translation"),
+                sampled("rotation"),
+                sampled("scale"),
+            ));
+            weights.push(sampled("weights").or_else(|| floats(&node["weights"])));
+        }
+        Pose { locals, weights }
+    }
+
+    /// Each node's transform relative to the scene
+    pub fn world(&self, document: &GltfDocument) -> Vec<Matrix4<f32>> {
+        let count = self.locals.len();
+        let mut parent = vec![None; count];
+        for (index, node) in document.json["nodes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            for child in node["children"].as_array().into_iter().flatten() {
+                if let Some(child) = child.as_u64().map(|c| c as usize).filter(|&c| c < count) {
+                    parent[child] = Some(index);
+                }
+            }
+        }
+        let mut world: Vec<Option<Matrix4<f32>>> = vec![None; count];
+        for start in 0..count {
+            // Walk up to the nearest placed ancestor, then back down
+            let mut chain = vec![start];
+            while let Some(up) = parent[*chain.last().unwrap()] {
+                if world[up].is_some() || chain.contains(&up) {
+                    break;
+                }
+                chain.push(up);
+            }
+            for &node in chain.iter().rev() {
+                if world[node].is_some() {
+                    continue;
+                }
+                let above = parent[node]
+                    .and_then(|p| world[p])
+                    .unwrap_or_else(Matrix4::identity);
+                world[node] = Some(above * self.locals[node]);
+            }
+        }
+        world.into_iter().map(Option::unwrap_or_default).collect()
+    }
+}
+
+/// Deform every mesh of `document` into `pose`
+pub fn bake(document: &GltfDocument, pose: &Pose) -> Result<BakedPose, String> {
+    let world = pose.world(document);
+    let meshes = document.json["meshes"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let skins = document.json["skins"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let mut baked = BakedPose::default();
+
+    for (index, node) in document.json["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        let Some(mesh) = node["mesh"].as_u64().and_then(|m| meshes.get(m as usize)) else {
+            continue;
+        };
+        let joints = match node["skin"].as_u64() {
+            Some(skin) => {
+                let skin = skins
+                    .get(skin as usize)
+                    .ok_or_else(|| format!("Node {} uses a missing skin", index))?;
+                Some(joint_matrices(document, skin, &world)?)
+            }
+            None => None,
+        };
+        let morph_weights = pose.weights[index]
+            .clone()
+            .or_else(|| floats(&mesh["weights"]))
+            .unwrap_or_default();
+        let name = node["name"]
+            .as_str()
+            .or_else(|| mesh["name"].as_str())
+            .map_or_else(|| format!("node {}", index), str::to_string);
+        let mut posed = NamedMesh {
+            name,
+            ..Default::default()
+        };
+        let mut morphed = false;
+
+        for primitive in mesh["primitives"].as_array().into_iter().flatten() {
+            if primitive["mode"].as_u64().unwrap_or(TRIANGLES) != TRIANGLES {
+                baked.skipped_primitive_count += 1;
+                continue;
+            }
+            let Some(position) = attribute(primitive, "POSITION") else {
+                continue;
+            };
+            let mut positions = document.read_floats(position)?.values;
+            for (target, &weight) in primitive["targets"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .zip(&morph_weights)
+            {
+                let Some(deltas) = attribute(target, "POSITION").filter(|_| weight != 0.0) else {
+                    continue;
+                };
+                let deltas = document.read_floats(deltas)?.values;
+                for (p, d) in positions.iter_mut().zip(&deltas) {
+                    *p += weight * d;
+                }
+                morphed = true;
+            }
+
+            let skinning = match &joints {
+                Some(joints) => Some(skin_weights(document, primitive, positions.len() / 3)?)
+                    .filter(|influences| !influences.is_empty())
+                    .map(|influences| (joints, influences)),
+                None => None,
+            };
+            let positions: Vec<f32> = positions
+                .chunks_exact(3)
+                .enumerate()
+                .flat_map(|(vertex, p)| {
+                    let matrix = skinning
+                        .as_ref()
+                        .and_then(|(joints, influences)| blend(joints, &influences[vertex]))
+                        .unwrap_or(world[index]);
+                    let p = matrix.transform_point(&Point3::new(p[0], p[1], p[2]));
+                    [p.x, p.y, p.z]
+                })
+                .collect();
+            let indices = match primitive["indices"].as_u64() {
+                Some(indices) => document.read_ints(indices as usize)?.values,
+                None => (0..(positions.len() / 3) as u32).collect(),
+            };
+            posed.append(&positions, &indices);
+        }
+
+        if posed.indices.is_empty() {
+            continue;
+        }
+        baked.skinned_mesh_count += usize::from(joints.is_some());
+        baked.morphed_mesh_count += usize::from(morphed);
+        baked.meshes.push(posed);
+    }
+    Ok(baked)
+}
+
+/// World transform times inverse bind matrix for each joint of `skin`
+fn joint_matrices(
+    document: &GltfDocument,
+    skin: &Value,
+    world: &[Matrix4<f32>],
+) -> Result<Vec<Matrix4<f32>>, String> {
+    let inverse_binds = match skin["inverseBindMatrices"].as_u64() {
+        Some(accessor) => document.read_floats(accessor as usize)?.values,
+        None => Vec::new(),
+    };
+    skin["joints"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(j, joint)| {
+            let joint = joint
+                .as_u64()
+                .and_then(|n| world.get(n as usize))
+                .ok_or_else(|| format!("Skin joint {} is not a node", j))?;
+            let inverse_bind = inverse_binds
+                .get(j * 16..(j + 1) * 16)
+                .map_or_else(Matrix4::identity, Matrix4::from_column_slice);
+            Ok(joint * inverse_bind)
+        })
+        .collect()
+}
+
+/// Joint and weight pairs of each vertex, over every `JOINTS_n` and
+/// `WEIGHTS_n` set; empty if the primitive has none
+pub fn skin_weights(
+    document: &GltfDocument,
+    primitive: &Value,
+    vertex_count: usize,
+) -> Result<Vec<Vec<(usize, f32)>>, String> {
+    let mut influences = Vec::new();
+    for set in 0.. {
+        let (Some(joints), Some(weights)) = (
+            attribute(primitive, &format!("JOINTS_{}", set)),
+            attribute(primitive, &format!("WEIGHTS_{}", set)),
+        ) else {
+            break;
+        };
+        let joints = document.read_ints(joints)?;
+        let weights = document.read_floats(weights)?;
+        influences.resize(vertex_count, Vec::new());
+        for (vertex, influence) in influences.iter_mut().enumerate() {
+            let range = vertex * joints.width..(vertex + 1) * joints.width;
+            let (Some(joints), Some(weights)) =
+                (joints.values.get(range.clone()), weights.values.get(range))
+            else {
+                break;
+            };
+            influence.extend(
+                joints
+                    .iter()
+                    .zip(weights)
+                    .filter(|(_, &w)| w > 0.0)
+                    .map(|(&j, &w)| (j as usize, w)),
+            );
+        }
+    }
+    Ok(influences)
+}
+
+/// The weighted sum of joint matrices, with the weights normalized; `None`
+/// for a vertex with no weight
+fn blend(joints: &[Matrix4<f32>], influences: &[(usize, f32)]) -> Option<Matrix4<f32>> {
+    let total: f32 = influences.iter().map(|(_, w)| w).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    Some(
+        influences
+            .iter()
+            .filter_map(|&(j, w)| joints.get(j).map(|m| m * (w / total)))
+            .fold(Matrix4::zeros(), |sum, m| sum + m),
+    )
+}
+
+/// A node's `matrix`, or its translation, rotation and scale with the
+/// given ones in place of the stored ones
+///
+/// glTF doesn't animate nodes with a matrix, so those ignore overrides.
+fn local_transform(
+    node: &Value,
+    translation: Option<Vec<f32>>,
+    rotation: Option<Vec<f32>>,
+    scale: Option<Vec<f32>>,
+) -> Matrix4<f32> {
+    if let Some(matrix) = floats(&node["matrix"]).filter(|m| m.len() == 16) {
+        return Matrix4::from_column_slice(&matrix);
+    }
+    let pick = |sampled: Option<Vec<f32>>, key: &str, width: usize| {
+        sampled
+            .or_else(|| floats(&node[key]))
+            .filter(|v| v.len() == width)
+    };
+    let t = pick(translation, "translation", 3).unwrap_or_else(|| vec![0.0; 3]);
+    let r = pick(rotation, "rotation", 4).unwrap_or_else(|| vec![0.0, 0.0, 0.0, 1.0]);
+    let s = pick(scale, "scale", 3).unwrap_or_else(|| vec![1.0; 3]);
+    let rotation = UnitQuaternion::from_quaternion(Quaternion::new(r[3], r[0], r[1], r[2]));
+    Matrix4::new_translation(&Vector3::new(t[0], t[1], t[2]))
+        * rotation.to_homogeneous()
+        * Matrix4::new_nonuniform_scaling(&Vector3::new(s[0], s[1], s[2]))
+}
+
+fn attribute(primitive: &Value, name: &str) -> Option<usize> {
+    primitive["attributes"]
+        .get(name)
+        .or_else(|| primitive.get(name))
+        .and_then(Value::as_u64)
+        .map(|a| a as usize)
+}
+
+fn floats(value: &Value) -> Option<Vec<f32>> {
+    value.as_array().map(|values| {
+        values
+            .iter()
+            .map(|v| v.as_f64().unwrap_or(0.0) as f32)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::animation::{read_clips, write_clips, Interpolation, Track};
+    use serde_json::json;
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    #[test]
+    fn test_bake_skins_and_morphs_at_time() {
+        let mut document = GltfDocument {
+            json: json!({ "asset": { "version": "2.0" } }),
+            bin: Vec::new(),
+        };
+        // Root at the origin with Tip one unit up; the triangle's top
+        // corner follows Tip, the others Root
+        let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0];
+        let position = document.push_floats(&positions, "VEC3", true);
+        let target =
+            document.push_floats(&[0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0], "VEC3", true);
+        let joints = document.push_ints(&[0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0], "VEC4");
+        let weights = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+        let weights = document.push_floats(&weights, "VEC4", false);
+        let mut inverse_binds = Matrix4::<f32>::identity().as_slice().to_vec();
+        inverse_binds.extend(Matrix4::new_translation(&Vector3::new(0.0, -1.0, 0.0)).as_slice());
+        let inverse_binds = document.push_floats(&inverse_binds, "MAT4", false);
+        document.json["nodes"] = json!([
+            { "name": "Root", "children": [1] },
+            { "name": "Tip", "translation": [0.0, 1.0, 0.0] },
+            { "name": "Body", "mesh": 0, "skin": 0 },
+        ]);
+        document.json["meshes"] = json!([{
+            "weights": [0.5],
+            "primitives": [{
+                "attributes": { "POSITION": position, "JOINTS_0": joints, "WEIGHTS_0": weights },
+                "targets": [{ "POSITION": target }],
+            }],
+        }]);
+        document.json["skins"] =
+            json!([{ "joints": [0, 1], "inverseBindMatrices": inverse_binds }]);
+        let turn = Clip {
+            name: Some("Turn".to_string()),
+            tracks: vec![Track {
+                node: 0,
+                path: "rotation".to_string(),
+                interpolation: Interpolation::Linear,
+                times: vec![0.0, 1.0],
+                values: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, FRAC_1_SQRT_2, FRAC_1_SQRT_2],
+                width: 4,
+            }],
+            extras: None,
+        };
+        write_clips(&mut document, &[turn]);
+
+        let rest = bake(&document, &Pose::rest(&document)).unwrap();
+        assert_eq!(rest.meshes.len(), 1);
+        assert_eq!(rest.skinned_mesh_count, 1);
+        assert_eq!(rest.morphed_mesh_count, 1);
+        let expected = [0.0, 0.0, 0.5, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0];
+        for (a, b) in rest.meshes[0].positions.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-5, "{:?}", rest.meshes[0].positions);
+        }
+
+        let clip = &read_clips(&document).unwrap()[0];
+        let posed = bake(&document, &Pose::at(&document, clip, 1.0)).unwrap();
+        assert_eq!(posed.meshes[0].name, "Body");
+        assert_eq!(posed.meshes[0].indices, vec![0, 1, 2]);
+        // A quarter turn about Z: +X goes to +Y, +Y to -X
+        let expected = [0.0, 0.0, 0.5, 0.0, 1.0, 0.0, -2.0, 0.0, 0.0];
+        for (a, b) in posed.meshes[0].positions.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-5, "{:?}", posed.meshes[0].positions);
+        }
+    }
+}
@@ -0,0 +1,367 @@
+//! Power and thermal state, for throttling background jobs
+//!
+//! Batch jobs on a laptop that runs from its battery, or that is already
+//! hot, drain it and spin its fans up, so [`PowerThrottling`] turns the job
+//! queue down to one job at a time then. What can be read depends on the
+//! platform: Linux has both in sysfs, macOS reports both through `pmset`,
+//! and Windows only says where the power comes from.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Most jobs the queue may be set to run at once
+pub const MAX_CONCURRENT_JOBS: usize = 16;
+
+/// When the job queue is throttled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleMode {
+    /// On battery power or under thermal pressure
+    #[default]
+    Auto,
+    Always,
+    /// Run `max_jobs` whatever the power state
+    Never,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerThrottling {
+    pub mode: ThrottleMode,
+    /// Jobs run at once while not throttled; throttled, one runs
+    pub max_jobs: usize,
+}
+
+impl Default for PowerThrottling {
+    fn default() -> Self {
+        Self {
+            mode: ThrottleMode::Auto,
+            max_jobs: 2,
+        }
+    }
+}
+
+/// How close the machine is to slowing itself down to keep cool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThermalPressure {
+    Nominal,
+    /// Past the point where the system starts throttling
+    Elevated,
+    Critical,
+}
+
+/// What could be read of the power state; `None` where it couldn't
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PowerState {
+    pub on_battery: Option<bool>,
+    pub battery_percent: Option<u8>,
+    pub thermal: Option<ThermalPressure>,
+}
+
+/// Why the job queue is throttled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleReason {
+    /// The settings say to always throttle
+    Setting,
+    Battery,
+    Thermal,
+}
+
+/// Result of `get_power_status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerStatus {
+    pub state: PowerState,
+    pub throttling: PowerThrottling,
+    pub throttled_by: Option<ThrottleReason>,
+    /// Jobs the queue runs at once now
+    pub job_limit: usize,
+}
+
+impl PowerThrottling {
+    /// Why the queue should be throttled in `state`, if it should
+    pub fn reason(&self, state: &PowerState) -> Option<ThrottleReason> {
+        match self.mode {
+            ThrottleMode::Never => None,
+            ThrottleMode::Always => Some(ThrottleReason::Setting),
+            ThrottleMode::Auto if state.on_battery == Some(true) => Some(ThrottleReason::Battery),
+            ThrottleMode::Auto if state.thermal > Some(ThermalPressure::Nominal) => {
+                Some(ThrottleReason::Thermal)
+            }
+            ThrottleMode::Auto => None,
+        }
+    }
+
+    pub fn status(&self, state: PowerState) -> PowerStatus {
+        let throttled_by = self.reason(&state);
+        let job_limit = if throttled_by.is_some() {
+            1
+        } else {
+            self.max_jobs.clamp(1, MAX_CONCURRENT_JOBS)
+        };
+        PowerStatus {
+            state,
+            throttling: self.clone(),
+            throttled_by,
+            job_limit,
+        }
+    }
+}
+
+/// Read the power source, battery level and thermal pressure
+pub fn read_state() -> PowerState {
+    if cfg!(target_os = "macos") {
+        let pmset = |arg: &str| {
+            Command::new("pmset")
+                .args(["-g", arg])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        };
+        let (on_battery, battery_percent) = pmset("batt")
+            .map(|output| parse_pmset_battery(&output))
+            .unwrap_or_default();
+        PowerState {
+            on_battery,
+            battery_percent,
+            thermal: pmset("therm").and_then(|output| parse_pmset_thermal(&output)),
+        }
+    } else if cfg!(windows) {
+        system_power_status()
+    } else {
+        let (on_battery, battery_percent) = sysfs_power(Path::new("/sys/class/power_supply"));
+        PowerState {
+            on_battery,
+            battery_percent,
+            thermal: sysfs_thermal(Path::new("/sys/class/thermal")),
+        }
+    }
+}
+
+/// Power source and battery level from `pmset -g batt`, which starts with
+/// `Now drawing from 'Battery Power'` and lists batteries like
+/// `-InternalBattery-0 (id=4653155) 85%; discharging; 4:12 remaining`
+fn parse_pmset_battery(output: &str) -> (Option<bool>, Option<u8>) {
+    let on_battery = output
+        .lines()
+        .find_map(|line| line.strip_prefix("Now drawing from "))
+        .map(|source| source.contains("Battery Power"));
+    let battery_percent = output
+        .split_whitespace()
+        .find_map(|word| word.strip_suffix("%;"))
+        .and_then(|percent| percent.parse().ok());
+    (on_battery, battery_percent)
+}
+
+/// Thermal pressure from the `CPU_Speed_Limit = 100` line of
+/// `pmset -g therm`, the percentage of full speed the CPU is allowed
+fn parse_pmset_thermal(output: &str) -> Option<ThermalPressure> {
+    let limit: u32 = output.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "CPU_Speed_Limit")
+            .then(|| value.trim().parse().ok())
+            .flatten()
+    })?;
+    Some(match limit {
+        100.. => ThermalPressure::Nominal,
+        51..=99 => ThermalPressure::Elevated,
+        _ => ThermalPressure::Critical,
+    })
+}
+
+/// Power source and battery level from `/sys/class/power_supply`
+///
+/// Batteries of devices such as mice are left out. A machine without a
+/// battery is never on battery.
+fn sysfs_power(dir: &Path) -> (Option<bool>, Option<u8>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (None, None);
+    };
+    let read = |supply: &Path, name: &str| {
+        fs::read_to_string(supply.join(name))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut on_mains = false;
+    let mut discharging = false;
+    let mut battery_percent = None;
+    for entry in entries.flatten() {
+        let supply = entry.path();
+        if read(&supply, "scope") == "Device" {
+            continue;
+        }
+        match read(&supply, "type").as_str() {
+            "Mains" | "USB" => on_mains |= read(&supply, "online") == "1",
+            "Battery" => {
+                discharging |= read(&supply, "status") == "Discharging";
+                battery_percent = battery_percent.or(read(&supply, "capacity").parse().ok());
+            }
+            _ => {}
+        }
+    }
+    (Some(discharging && !on_mains), battery_percent)
+}
+
+/// The highest thermal pressure of the zones in `/sys/class/thermal`,
+/// from their temperatures and their passive and critical trip points
+fn sysfs_thermal(dir: &Path) -> Option<ThermalPressure> {
+    let read = |path: &Path| -> Option<i64> { fs::read_to_string(path).ok()?.trim().parse().ok() };
+    let mut pressure = None;
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let zone = entry.path();
+        let is_zone = zone
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("thermal_zone"));
+        let Some(temp) = is_zone.then(|| read(&zone.join("temp"))).flatten() else {
+            continue;
+        };
+        for trip in 0.. {
+            let Ok(kind) = fs::read_to_string(zone.join(format!("trip_point_{}_type", trip)))
+            else {
+                break;
+            };
+            let level = match kind.trim() {
+                "passive" => ThermalPressure::Elevated,
+                "hot" | "critical" => ThermalPressure::Critical,
+                _ => ThermalPressure::Nominal,
+            };
+            // Zones without real trip points report them as 0 or below
+            let Some(limit) =
+                read(&zone.join(format!("trip_point_{}_temp", trip))).filter(|&limit| limit > 0)
+            else {
+                continue;
+            };
+            let reached = if temp >= limit {
+                level
+            } else {
+                ThermalPressure::Nominal
+            };
+            pressure = pressure.max(Some(reached));
+        }
+    }
+    pressure
+}
+
+#[cfg(windows)]
+fn system_power_status() -> PowerState {
+    #[repr(C)]
+    #[derive(Default)]
+    struct SystemPowerStatus {
+        ac_line_status: u8,
+        battery_flag: u8,
+        battery_life_percent: u8,
+        system_status_flag: u8,
+        battery_life_time: u32,
+        battery_full_life_time: u32,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetSystemPowerStatus(status: *mut SystemPowerStatus) -> i32;
+    }
+    let mut status = SystemPowerStatus::default();
+    // SAFETY: the struct matches SYSTEM_POWER_STATUS and is only read once
+    // the call has filled it in
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return PowerState::default();
+    }
+    // 128 means there is no battery, 255 that the value is unknown
+    let has_battery = status.battery_flag & 128 == 0;
+    PowerState {
+        on_battery: match status.ac_line_status {
+            0 => Some(true),
+            1 => Some(false),
+            _ => None,
+        },
+        battery_percent: (has_battery && status.battery_life_percent <= 100)
+            .then_some(status.battery_life_percent),
+        thermal: None,
+    }
+}
+
+#[cfg(not(windows))]
+fn system_power_status() -> PowerState {
+    PowerState::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_power_state_and_throttles() {
+        let battery = "Now drawing from 'Battery Power'\n \
+            -InternalBattery-0 (id=4653155)\t85%; discharging; 4:12 remaining present: true\n";
+        assert_eq!(parse_pmset_battery(battery), (Some(true), Some(85)));
+        assert_eq!(
+            parse_pmset_battery("Now drawing from 'AC Power'\n"),
+            (Some(false), None)
+        );
+        let therm = "Note: No thermal warning level has been recorded\n\
+            \tCPU_Scheduler_Limit \t= 100\n\tCPU_Speed_Limit \t= 70\n";
+        assert_eq!(parse_pmset_thermal(therm), Some(ThermalPressure::Elevated));
+        assert_eq!(parse_pmset_thermal("nothing"), None);
+
+        let root = std::env::temp_dir().join(format!("sweedle-power-{}", std::process::id()));
+        let write = |path: &str, value: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, value).unwrap();
+        };
+        write("supply/AC/type", "Mains\n");
+        write("supply/AC/online", "0\n");
+        write("supply/BAT0/type", "Battery\n");
+        write("supply/BAT0/status", "Discharging\n");
+        write("supply/BAT0/capacity", "42\n");
+        write("supply/mouse/type", "Battery\n");
+        write("supply/mouse/scope", "Device\n");
+        write("supply/mouse/capacity", "5\n");
+        assert_eq!(sysfs_power(&root.join("supply")), (Some(true), Some(42)));
+        write("supply/AC/online", "1\n");
+        assert_eq!(sysfs_power(&root.join("supply")), (Some(false), Some(42)));
+
+        write("thermal/thermal_zone0/temp", "91000\n");
+        write("thermal/thermal_zone0/trip_point_0_type", "passive\n");
+        write("thermal/thermal_zone0/trip_point_0_temp", "90000\n");
+        write("thermal/thermal_zone0/trip_point_1_type", "critical\n");
+        write("thermal/thermal_zone0/trip_point_1_temp", "105000\n");
+        write("thermal/thermal_zone1/temp", "40000\n");
+        write("thermal/thermal_zone1/trip_point_0_type", "critical\n");
+        write("thermal/thermal_zone1/trip_point_0_temp", "0\n");
+        assert_eq!(
+            sysfs_thermal(&root.join("thermal")),
+            Some(ThermalPressure::Elevated)
+        );
+        assert_eq!(sysfs_thermal(&root.join("missing")), None);
+        fs::remove_dir_all(&root).unwrap();
+
+        let throttling = PowerThrottling::default();
+        let plugged = PowerState {
+            on_battery: Some(false),
+            battery_percent: Some(90),
+            thermal: Some(ThermalPressure::Nominal),
+        };
+        assert_eq!(throttling.status(plugged.clone()).job_limit, 2);
+        let hot = PowerState {
+            thermal: Some(ThermalPressure::Elevated),
+            ..plugged.clone()
+        };
+        assert_eq!(
+            throttling.status(hot.clone()).throttled_by,
+            Some(ThrottleReason::Thermal)
+        );
+        assert_eq!(throttling.status(hot.clone()).job_limit, 1);
+        let never = PowerThrottling {
+            mode: ThrottleMode::Never,
+            max_jobs: 4,
+        };
+        assert_eq!(never.status(hot).job_limit, 4);
+        // Unknown state doesn't throttle
+        assert_eq!(throttling.reason(&PowerState::default()), None);
+    }
+}
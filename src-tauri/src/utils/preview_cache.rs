@@ -0,0 +1,126 @@
+//! Decimated previews cached by the geometry they were made from
+//!
+//! Entries hold the preview's own positions and indices, so a preview can
+//! be sent before its source is decoded. They are keyed by
+//! [`geometry_hash`] instead of the file's version, so a scan whose
+//! textures or materials are updated keeps its cached preview.
+
+use crate::utils::decimate::DecimatedMesh;
+use xxhash_rust::xxh3::Xxh3;
+
+const MAGIC: &[u8; 4] = b"SWPD";
+const VERSION: u32 = 3;
+const HEADER_LEN: usize = 16;
+
+/// Hash of merged world-space positions and indices
+pub fn geometry_hash(positions: &[f32], indices: &[u32]) -> u64 {
+    let mut hasher = Xxh3::new();
+    hasher.update(&(positions.len() as u64).to_le_bytes());
+    for p in positions {
+        hasher.update(&p.to_le_bytes());
+    }
+    for i in indices {
+        hasher.update(&i.to_le_bytes());
+    }
+    hasher.digest()
+}
+
+/// Encode `preview`'s positions and indices as a cache entry
+pub fn encode(preview: &DecimatedMesh) -> Vec<u8> {
+    let mut bytes =
+        Vec::with_capacity(HEADER_LEN + preview.positions.len() * 4 + preview.indices.len() * 4);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&((preview.positions.len() / 3) as u32).to_le_bytes());
+    bytes.extend_from_slice(&(preview.indices.len() as u32).to_le_bytes());
+    for position in &preview.positions {
+        bytes.extend_from_slice(&position.to_le_bytes());
+    }
+    for index in &preview.indices {
+        bytes.extend_from_slice(&index.to_le_bytes());
+    }
+    bytes
+}
+
+/// Rebuild a preview from an entry made by [`encode`]
+pub fn decode(bytes: &[u8]) -> Option<DecimatedMesh> {
+    if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+        return None;
+    }
+    let word = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    if word(4) != VERSION {
+        return None;
+    }
+    let vertex_count = word(8) as usize;
+    let index_count = word(12) as usize;
+    if bytes.len() != HEADER_LEN + (vertex_count * 3 + index_count) * 4 {
+        return None;
+    }
+
+    let (position_bytes, index_bytes) = bytes[HEADER_LEN..].split_at(vertex_count * 12);
+    let positions: Vec<f32> = position_bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    let indices: Vec<u32> = index_bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    if indices.iter().any(|&i| i as usize >= vertex_count) {
+        return None;
+    }
+
+    Some(DecimatedMesh {
+        positions,
+        indices,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::decimate::cluster_decimate;
+
+    #[test]
+    fn test_round_trip() {
+        // A 20 x 20 grid of quads, decimated to about a tenth
+        let n = 21;
+        let mut positions = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                positions.extend([x as f32 * 0.1, y as f32 * 0.1, ((x * y) % 7) as f32 * 0.01]);
+            }
+        }
+        let mut indices: Vec<u32> = Vec::new();
+        for y in 0..n - 1 {
+            for x in 0..n - 1 {
+                let (i, n) = ((y * n + x) as u32, n as u32);
+                indices.extend([i, i + 1, i + n, i + 1, i + n + 1, i + n]);
+            }
+        }
+        let preview = cluster_decimate(&positions, &indices, 80);
+        assert!(preview.indices.len() < indices.len());
+
+        let bytes = encode(&preview);
+        assert_eq!(
+            bytes.len(),
+            HEADER_LEN + (preview.positions.len() + preview.indices.len()) * 4
+        );
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded.positions, preview.positions);
+        assert_eq!(decoded.indices, preview.indices);
+
+        // Torn or older entries are refused
+        assert!(decode(&bytes[..bytes.len() - 1]).is_none());
+        let mut old = bytes.clone();
+        old[4..8].copy_from_slice(&2u32.to_le_bytes());
+        assert!(decode(&old).is_none());
+        let mut moved = positions.clone();
+        moved[0] += 1.0;
+        assert_ne!(
+            geometry_hash(&moved, &indices),
+            geometry_hash(&positions, &indices)
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Index buffer layouts for constrained render targets
+//!
+//! Embedded GL parts are often bound by vertex shading and index bandwidth
+//! rather than triangle count. Two layouts help there: triangle lists
+//! reordered for the post-transform cache (Tipsify, Sander et al. 2007) with
+//! vertices stored in first-use order, and triangle strips, which need
+//! roughly one index per triangle instead of three.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Post-transform cache size assumed when reordering and measuring
+pub const CACHE_SIZE: usize = 16;
+
+/// Strip index that starts a new strip when primitive restart is enabled
+pub const RESTART_INDEX: u32 = u32::MAX;
+
+/// How indices are laid out for export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrimitiveLayout {
+    /// Triangle list as stored
+    #[default]
+    Triangles,
+    /// Triangle list reordered for the vertex cache, vertices in fetch order
+    Optimized,
+    /// Triangle strip
+    TriangleStrip,
+}
+
+/// Reorder triangles so recently shaded vertices are reused
+pub fn optimize_vertex_cache(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let face_count = indices.len() / 3;
+    let mut offsets = vec![0usize; vertex_count + 1];
+    for &i in indices {
+        offsets[i as usize + 1] += 1;
+    }
+    for v in 0..vertex_count {
+        offsets[v + 1] += offsets[v];
+    }
+    let mut fill = offsets.clone();
+    let mut adjacency = vec![0u32; indices.len()];
+    for (f, face) in indices.chunks_exact(3).enumerate() {
+        for &i in face {
+            adjacency[fill[i as usize]] = f as u32;
+            fill[i as usize] += 1;
+        }
+    }
+
+    let mut live: Vec<usize> = (0..vertex_count)
+        .map(|v| offsets[v + 1] - offsets[v])
+        .collect();
+    let mut cache_time = vec![0usize; vertex_count];
+    let mut emitted = vec![false; face_count];
+    let mut dead_end: Vec<u32> = Vec::new();
+    let mut output = Vec::with_capacity(indices.len());
+    let mut time = CACHE_SIZE + 1;
+    let mut cursor = 0usize;
+    let mut fanning = (0..vertex_count).find(|&v| live[v] > 0);
+
+    while let Some(f) = fanning {
+        let mut candidates: Vec<u32> = Vec::new();
+        for &face in &adjacency[offsets[f]..offsets[f + 1]] {
+            if emitted[face as usize] {
+                continue;
+            }
+            emitted[face as usize] = true;
+            for &v in &indices[face as usize * 3..face as usize * 3 + 3] {
+                output.push(v);
+                dead_end.push(v);
+                candidates.push(v);
+                live[v as usize] -= 1;
+                if time - cache_time[v as usize] > CACHE_SIZE {
+                    cache_time[v as usize] = time;
+                    time += 1;
+                }
+            }
+        }
+
+        // Prefer a candidate still in the cache that won't be evicted while
+        // its remaining triangles are emitted
+        let best = candidates
+            .iter()
+            .filter(|&&v| live[v as usize] > 0)
+            .map(|&v| {
+                let age = time - cache_time[v as usize];
+                let priority = if age + 2 * live[v as usize] <= CACHE_SIZE {
+                    age as isize
+                } else {
+                    -1
+                };
+                (priority, v)
+            })
+            .max_by_key(|&(priority, _)| priority);
+
+        fanning = match best {
+            Some((_, v)) => Some(v as usize),
+            None => {
+                let mut next = None;
+                while let Some(v) = dead_end.pop() {
+                    if live[v as usize] > 0 {
+                        next = Some(v as usize);
+                        break;
+                    }
+                }
+                next.or_else(|| {
+                    while cursor < vertex_count && live[cursor] == 0 {
+                        cursor += 1;
+                    }
+                    (cursor < vertex_count).then_some(cursor)
+                })
+            }
+        };
+    }
+    output
+}
+
+/// Order vertices by first use, returning the new order and remapped indices
+///
+/// `order[new] = old`; vertices no triangle uses keep their relative order
+/// at the end.
+pub fn optimize_vertex_fetch(indices: &[u32], vertex_count: usize) -> (Vec<usize>, Vec<u32>) {
+    let mut remap = vec![u32::MAX; vertex_count];
+    let mut order = Vec::with_capacity(vertex_count);
+    let remapped = indices
+        .iter()
+        .map(|&i| {
+            let slot = &mut remap[i as usize];
+            if *slot == u32::MAX {
+                *slot = order.len() as u32;
+                order.push(i as usize);
+            }
+            *slot
+        })
+        .collect();
+    order.extend((0..vertex_count).filter(|&v| remap[v] == u32::MAX));
+    (order, remapped)
+}
+
+/// Average number of vertices shaded per triangle with a FIFO cache
+pub fn average_cache_miss_ratio(indices: &[u32]) -> f32 {
+    let face_count = indices.len() / 3;
+    if face_count == 0 {
+        return 0.0;
+    }
+    let mut cache: VecDeque<u32> = VecDeque::with_capacity(CACHE_SIZE + 1);
+    let mut misses = 0;
+    for &i in indices {
+        if !cache.contains(&i) {
+            misses += 1;
+            cache.push_back(i);
+            if cache.len() > CACHE_SIZE {
+                cache.pop_front();
+            }
+        }
+    }
+    misses as f32 / face_count as f32
+}
+
+/// Convert a triangle list into strips, keeping each triangle's winding
+///
+/// Strips are joined with [`RESTART_INDEX`] when `primitive_restart` is set,
+/// otherwise with degenerate triangles. Degenerate input triangles are
+/// dropped.
+pub fn stripify(indices: &[u32], primitive_restart: bool) -> Vec<u32> {
+    let faces: Vec<[u32; 3]> = indices
+        .chunks_exact(3)
+        .map(|f| [f[0], f[1], f[2]])
+        .filter(|f| f[0] != f[1] && f[1] != f[2] && f[0] != f[2])
+        .collect();
+    let mut by_edge: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for (f, face) in faces.iter().enumerate() {
+        for k in 0..3 {
+            by_edge
+                .entry((face[k], face[(k + 1) % 3]))
+                .or_default()
+                .push(f);
+        }
+    }
+
+    let mut used = vec![false; faces.len()];
+    let take_neighbour = |used: &mut Vec<bool>, edge: (u32, u32)| -> Option<u32> {
+        let &f = by_edge.get(&edge)?.iter().find(|&&f| !used[f])?;
+        used[f] = true;
+        let face = faces[f];
+        face.iter().copied().find(|&v| v != edge.0 && v != edge.1)
+    };
+
+    let mut output: Vec<u32> = Vec::with_capacity(faces.len() + 2);
+    for start in 0..faces.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+
+        // Start on the rotation that has somewhere to go
+        let face = faces[start];
+        let rotation = (0..3)
+            .find(|&k| {
+                let edge = (face[(k + 2) % 3], face[(k + 1) % 3]);
+                by_edge
+                    .get(&edge)
+                    .is_some_and(|fs| fs.iter().any(|&f| !used[f]))
+            })
+            .unwrap_or(0);
+        let mut strip = vec![
+            face[rotation],
+            face[(rotation + 1) % 3],
+            face[(rotation + 2) % 3],
+        ];
+
+        loop {
+            let n = strip.len();
+            // Triangle n - 3 has the strip's parity; its successor shares the
+            // last edge in the opposite direction
+            let edge = if (n - 3).is_multiple_of(2) {
+                (strip[n - 1], strip[n - 2])
+            } else {
+                (strip[n - 2], strip[n - 1])
+            };
+            match take_neighbour(&mut used, edge) {
+                Some(v) => strip.push(v),
+                None => break,
+            }
+        }
+
+        if let Some(&last) = output.last() {
+            if primitive_restart {
+                output.push(RESTART_INDEX);
+            } else {
+                // Keep the next strip starting on an even triangle
+                output.push(last);
+                if output.len().is_multiple_of(2) {
+                    output.push(last);
+                }
+                output.push(strip[0]);
+            }
+        }
+        output.extend_from_slice(&strip);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(n: u32) -> Vec<u32> {
+        let row = n + 1;
+        let mut indices = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * row + x;
+                indices.extend_from_slice(&[i, i + 1, i + row, i + 1, i + row + 1, i + row]);
+            }
+        }
+        indices
+    }
+
+    /// Triangles of a strip with their winding restored, rotated to a canonical form
+    fn decode_strip(strip: &[u32]) -> Vec<[u32; 3]> {
+        let mut faces = Vec::new();
+        for run in strip.split(|&i| i == RESTART_INDEX) {
+            for k in 0..run.len().saturating_sub(2) {
+                let mut f = if k % 2 == 0 {
+                    [run[k], run[k + 1], run[k + 2]]
+                } else {
+                    [run[k + 1], run[k], run[k + 2]]
+                };
+                if f[0] == f[1] || f[1] == f[2] || f[0] == f[2] {
+                    continue;
+                }
+                let min = (0..3).min_by_key(|&i| f[i]).unwrap();
+                f.rotate_left(min);
+                faces.push(f);
+            }
+        }
+        faces.sort_unstable();
+        faces
+    }
+
+    #[test]
+    fn test_strips_keep_every_triangle_and_winding() {
+        let indices = grid(8);
+        let mut expected: Vec<[u32; 3]> = Vec::new();
+        for f in indices.chunks_exact(3) {
+            let mut f = [f[0], f[1], f[2]];
+            let min = (0..3).min_by_key(|&i| f[i]).unwrap();
+            f.rotate_left(min);
+            expected.push(f);
+        }
+        expected.sort_unstable();
+
+        for restart in [false, true] {
+            let strip = stripify(&indices, restart);
+            assert_eq!(decode_strip(&strip), expected);
+            assert!(strip.len() < indices.len());
+        }
+    }
+
+    #[test]
+    fn test_cache_optimization_keeps_triangles_and_helps() {
+        let indices = grid(32);
+        // Shuffle triangle order deterministically to start from a bad layout
+        let mut faces: Vec<&[u32]> = indices.chunks_exact(3).collect();
+        faces.sort_by_key(|f| (f[0].wrapping_mul(2654435761)) % 1021);
+        let shuffled: Vec<u32> = faces.concat();
+
+        let optimized = optimize_vertex_cache(&shuffled, 33 * 33);
+        assert_eq!(optimized.len(), shuffled.len());
+        assert!(average_cache_miss_ratio(&optimized) < average_cache_miss_ratio(&shuffled));
+
+        let (order, remapped) = optimize_vertex_fetch(&optimized, 33 * 33);
+        assert_eq!(order.len(), 33 * 33);
+        assert_eq!(remapped[0], 0);
+        assert!(remapped
+            .iter()
+            .zip(&optimized)
+            .all(|(&new, &old)| order[new as usize] == old as usize));
+    }
+}
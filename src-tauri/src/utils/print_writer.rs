@@ -0,0 +1,207 @@
+//! Binary STL and 3MF output for 3D printing
+//!
+//! Both take print-space geometry (Z-up millimeters). STL is one triangle
+//! soup with facet normals and no units; 3MF keeps each model as its own
+//! object in millimeters, in the zip package layout the spec requires.
+
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::fmt::Write as _;
+use std::io::Write;
+
+/// A model to write: name, positions and triangle indices
+pub type PrintObject<'a> = (&'a str, &'a [f32], &'a [u32]);
+
+/// Encode every object's triangles into one binary STL
+pub fn write_stl(objects: &[PrintObject]) -> Vec<u8> {
+    let count: usize = objects
+        .iter()
+        .map(|(_, _, indices)| indices.len() / 3)
+        .sum();
+    let mut out = Vec::with_capacity(84 + count * 50);
+    let mut header = [b' '; 80];
+    header[..19].copy_from_slice(b"Exported by Sweedle");
+    out.extend_from_slice(&header);
+    out.extend_from_slice(&(count as u32).to_le_bytes());
+    for (_, positions, indices) in objects {
+        for face in indices.chunks_exact(3) {
+            let [a, b, c] = [face[0], face[1], face[2]].map(|i| {
+                let p = i as usize * 3;
+                [positions[p], positions[p + 1], positions[p + 2]]
+            });
+            let u = [0, 1, 2].map(|k| b[k] - a[k]);
+            let v = [0, 1, 2].map(|k| c[k] - a[k]);
+            let n = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+            let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+            let n = if len > 0.0 {
+                n.map(|c| c / len)
+            } else {
+                [0.0; 3]
+            };
+            for value in [n, a, b, c].iter().flatten() {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            out.extend_from_slice(&[0, 0]);
+        }
+    }
+    out
+}
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+  <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml"/>
+</Types>
+"#;
+
+const RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Target="/3D/3dmodel.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/>
+</Relationships>
+"#;
+
+/// Encode objects as a 3MF package in millimeters
+pub fn write_3mf(objects: &[PrintObject]) -> Result<Vec<u8>, String> {
+    let mut model = String::new();
+    let _ = writeln!(model, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        model,
+        r#"<model unit="millimeter" xml:lang="en-US" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">"#
+    );
+    model.push_str(" <resources>\n");
+    for (id, (name, positions, indices)) in objects.iter().enumerate() {
+        let _ = writeln!(
+            model,
+            r#"  <object id="{}" name="{}" type="model">"#,
+            id + 1,
+            escape(name)
+        );
+        model.push_str("   <mesh>\n    <vertices>\n");
+        for p in positions.chunks_exact(3) {
+            let _ = writeln!(
+                model,
+                r#"     <vertex x="{}" y="{}" z="{}"/>"#,
+                p[0], p[1], p[2]
+            );
+        }
+        model.push_str("    </vertices>\n    <triangles>\n");
+        for f in indices.chunks_exact(3) {
+            let _ = writeln!(
+                model,
+                r#"     <triangle v1="{}" v2="{}" v3="{}"/>"#,
+                f[0], f[1], f[2]
+            );
+        }
+        model.push_str("    </triangles>\n   </mesh>\n  </object>\n");
+    }
+    model.push_str(" </resources>\n <build>\n");
+    for id in 1..=objects.len() {
+        let _ = writeln!(model, r#"  <item objectid="{}"/>"#, id);
+    }
+    model.push_str(" </build>\n</model>\n");
+
+    zip(&[
+        ("[Content_Types].xml", CONTENT_TYPES.as_bytes()),
+        ("_rels/.rels", RELATIONSHIPS.as_bytes()),
+        ("3D/3dmodel.model", model.as_bytes()),
+    ])
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A zip archive of deflated files, dated 1980-01-01
+fn zip(files: &[(&str, &[u8])]) -> Result<Vec<u8>, String> {
+    const DOS_DATE: u16 = 0x21;
+    let mut out = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in files {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        let compressed = encoder
+            .write_all(data)
+            .and_then(|_| encoder.finish())
+            .map_err(|e| format!("Failed to compress {}: {}", name, e))?;
+
+        // Version, flags, deflate, time, date, CRC, sizes and name length,
+        // which both headers share
+        let mut common = Vec::new();
+        for value in [20u16, 0, 8, 0, DOS_DATE] {
+            common.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [crc.sum(), compressed.len() as u32, data.len() as u32] {
+            common.extend_from_slice(&value.to_le_bytes());
+        }
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        directory.extend_from_slice(&common);
+        // Comment length, disk, internal and external attributes
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&(out.len() as u32).to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        out.extend_from_slice(&common);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&compressed);
+    }
+
+    let offset = out.len() as u32;
+    out.extend_from_slice(&directory);
+    out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    for _ in 0..2 {
+        out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    }
+    out.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    out.extend_from_slice(&offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_stl_and_3mf_of_a_triangle() {
+        let positions = [0.0, 0.0, 0.0, 10.0, 0.0, 0.0, 0.0, 10.0, 0.0];
+        let objects: [PrintObject; 1] = [("a & b", &positions, &[0, 1, 2])];
+
+        let stl = write_stl(&objects);
+        assert_eq!(stl.len(), 84 + 50);
+        assert_eq!(&stl[80..84], &1u32.to_le_bytes());
+        // Facet normal is +Z
+        assert_eq!(&stl[92..96], &1.0f32.to_le_bytes());
+
+        let package = write_3mf(&objects).unwrap();
+        assert_eq!(&package[..4], b"PK\x03\x04");
+        assert_eq!(&package[package.len() - 22..][..4], b"PK\x05\x06");
+
+        // The model is the last file; its data follows its header and name
+        let name = b"3D/3dmodel.model";
+        let start = package.windows(name.len()).position(|w| w == name).unwrap() + name.len();
+        let mut model = String::new();
+        DeflateDecoder::new(&package[start..])
+            .read_to_string(&mut model)
+            .unwrap();
+        assert!(model.contains(r#"unit="millimeter""#));
+        assert!(model.contains(r#"name="a &amp; b""#));
+        assert!(model.contains(r#"<vertex x="10" y="0" z="0"/>"#));
+        assert!(model.contains(r#"<item objectid="1"/>"#));
+    }
+}
@@ -0,0 +1,327 @@
+//! Printability checks and a rough print time and material estimate
+//!
+//! The estimate slices the mesh without generating toolpaths. For FDM, each
+//! sampled layer's outline gives the wall path and its enclosed area the
+//! infill path, both run at the profile's speeds; supports, skins, travel
+//! and acceleration are ignored, so real prints take somewhat longer. Resin
+//! time only depends on the layer count. Meshes are Y-up meters, as
+//! imports are, and print along Y.
+
+use crate::utils::degenerate::{self, FaceThresholds};
+use crate::utils::mesh_store::MeshData;
+use crate::utils::section::{self, Polyline, SectionPlane};
+use crate::utils::simd;
+use crate::utils::topology;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Layers sliced for an FDM estimate; the rest are interpolated
+const MAX_SAMPLED_LAYERS: usize = 200;
+const MM_PER_METER: f32 = 1000.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrintTechnology {
+    /// Filament extrusion
+    #[default]
+    Fdm,
+    /// Masked or laser resin curing, one exposure per layer
+    Resin,
+}
+
+/// How fast a printer works
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeedProfile {
+    /// FDM wall speed, mm/s (default 40)
+    pub perimeter_mm_s: f32,
+    /// FDM infill speed, mm/s (default 60)
+    pub infill_mm_s: f32,
+    /// Resin exposure per layer, seconds (default 2.5)
+    pub exposure_s: f32,
+    /// Resin exposure for the first layers, seconds (default 25)
+    pub bottom_exposure_s: f32,
+    pub bottom_layer_count: u32,
+    /// Resin peel and retract time per layer, seconds (default 6)
+    pub lift_s: f32,
+}
+
+impl Default for SpeedProfile {
+    fn default() -> Self {
+        Self {
+            perimeter_mm_s: 40.0,
+            infill_mm_s: 60.0,
+            exposure_s: 2.5,
+            bottom_exposure_s: 25.0,
+            bottom_layer_count: 5,
+            lift_s: 6.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrintSettings {
+    pub technology: PrintTechnology,
+    /// Defaults to 0.2 mm for FDM and 0.05 mm for resin
+    pub layer_height_mm: Option<f32>,
+    /// FDM infill, 0 to 100 (default 20); resin parts print solid
+    pub infill_percent: Option<f32>,
+    pub speed: SpeedProfile,
+    /// FDM perimeters per layer (default 2)
+    pub wall_count: Option<u32>,
+    /// FDM extrusion width (default 0.45 mm)
+    pub line_width_mm: Option<f32>,
+    /// Default 1.75 mm
+    pub filament_diameter_mm: Option<f32>,
+    /// Defaults to 1.24 g/cm³ (PLA) for FDM and 1.1 g/cm³ for resin
+    pub material_density_g_cm3: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintEstimate {
+    pub technology: PrintTechnology,
+    pub layer_count: usize,
+    pub print_time_s: f32,
+    pub material_volume_cm3: f32,
+    pub material_mass_g: f32,
+    /// Filament used, for FDM
+    pub filament_length_m: Option<f32>,
+}
+
+/// Whether a mesh can be printed as is, and what printing it would take
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintabilityReport {
+    /// Bounding box size along X, Y (up) and Z
+    pub size_mm: [f32; 3],
+    pub volume_cm3: f32,
+    pub surface_area_cm2: f32,
+    /// Closed and manifold, so slicers agree on inside and outside
+    pub is_watertight: bool,
+    pub shell_count: usize,
+    pub boundary_loop_count: usize,
+    pub non_manifold_edge_count: usize,
+    pub degenerate_face_count: usize,
+    pub estimate: PrintEstimate,
+}
+
+/// Check a mesh and estimate printing it with `settings`
+pub fn report(mesh: &MeshData, settings: &PrintSettings) -> Result<PrintabilityReport, String> {
+    let estimate = estimate(mesh, settings)?;
+    let topology = topology::analyze(&mesh.positions, &mesh.indices);
+    let (degenerate_face_count, _) =
+        degenerate::count(&mesh.positions, &mesh.indices, &FaceThresholds::default());
+    let (lo, hi) = bounds(&mesh.positions);
+
+    Ok(PrintabilityReport {
+        size_mm: [0, 1, 2].map(|k| (hi[k] - lo[k]).max(0.0) * MM_PER_METER),
+        volume_cm3: simd::signed_volume(&mesh.positions, &mesh.indices).abs() * 1e6,
+        surface_area_cm2: simd::surface_area(&mesh.positions, &mesh.indices) * 1e4,
+        is_watertight: topology.is_manifold() && topology.boundary_loop_count == 0,
+        shell_count: topology.shell_count,
+        boundary_loop_count: topology.boundary_loop_count,
+        non_manifold_edge_count: topology.non_manifold_edge_count,
+        degenerate_face_count,
+        estimate,
+    })
+}
+
+/// Estimated print time and material use
+pub fn estimate(mesh: &MeshData, settings: &PrintSettings) -> Result<PrintEstimate, String> {
+    let resin = settings.technology == PrintTechnology::Resin;
+    let layer_height = settings
+        .layer_height_mm
+        .unwrap_or(if resin { 0.05 } else { 0.2 });
+    let density = settings
+        .material_density_g_cm3
+        .unwrap_or(if resin { 1.1 } else { 1.24 });
+    let infill = settings.infill_percent.unwrap_or(20.0);
+    let wall_count = settings.wall_count.unwrap_or(2);
+    let line_width = settings.line_width_mm.unwrap_or(0.45);
+    let filament_diameter = settings.filament_diameter_mm.unwrap_or(1.75);
+    let speed = &settings.speed;
+
+    for (name, value) in [
+        ("Layer height", layer_height),
+        ("Material density", density),
+        ("Line width", line_width),
+        ("Filament diameter", filament_diameter),
+        ("Perimeter speed", speed.perimeter_mm_s),
+        ("Infill speed", speed.infill_mm_s),
+    ] {
+        if !(value > 0.0 && value.is_finite()) {
+            return Err(format!("{} must be a positive number, got {}", name, value));
+        }
+    }
+    if !(0.0..=100.0).contains(&infill) {
+        return Err(format!("Infill must be between 0 and 100%, got {}", infill));
+    }
+    let (lo, hi) = bounds(&mesh.positions);
+    let height = (hi[1] - lo[1]) * MM_PER_METER;
+    if !(height > 0.0 && height.is_finite()) {
+        return Err("Mesh has no height to print".to_string());
+    }
+    let layer_count = (height / layer_height).ceil() as usize;
+
+    if resin {
+        let bottom = (speed.bottom_layer_count as usize).min(layer_count);
+        let print_time_s = bottom as f32 * speed.bottom_exposure_s
+            + (layer_count - bottom) as f32 * speed.exposure_s
+            + layer_count as f32 * speed.lift_s;
+        let material_volume_cm3 = simd::signed_volume(&mesh.positions, &mesh.indices).abs() * 1e6;
+        return Ok(PrintEstimate {
+            technology: settings.technology,
+            layer_count,
+            print_time_s,
+            material_volume_cm3,
+            material_mass_g: material_volume_cm3 * density,
+            filament_length_m: None,
+        });
+    }
+
+    // Wall and infill path lengths of evenly spread layers, in mm
+    let samples = layer_count.min(MAX_SAMPLED_LAYERS);
+    let paths: Vec<(f32, f32)> = (0..samples)
+        .into_par_iter()
+        .map(|i| {
+            let y = lo[1] + (hi[1] - lo[1]) * (i as f32 + 0.5) / samples as f32;
+            let plane = SectionPlane {
+                normal: [0.0, 1.0, 0.0],
+                offset: y,
+            };
+            let section = section::slice(mesh, &plane);
+            let outline = section.polylines.iter().map(|p| p.length).sum::<f32>() * MM_PER_METER;
+            let area = enclosed_area(&section.polylines) * MM_PER_METER * MM_PER_METER;
+            let walls = outline * wall_count as f32;
+            let interior = (area - walls * line_width).max(0.0);
+            (walls, interior * infill / 100.0 / line_width)
+        })
+        .collect();
+    let per_sample = layer_count as f32 / samples as f32;
+    let walls: f32 = paths.iter().map(|p| p.0).sum::<f32>() * per_sample;
+    let infill_path: f32 = paths.iter().map(|p| p.1).sum::<f32>() * per_sample;
+
+    let volume_mm3 = (walls + infill_path) * line_width * layer_height;
+    let filament_area = std::f32::consts::PI * (filament_diameter / 2.0).powi(2);
+    let material_volume_cm3 = volume_mm3 / 1000.0;
+    Ok(PrintEstimate {
+        technology: settings.technology,
+        layer_count,
+        print_time_s: walls / speed.perimeter_mm_s + infill_path / speed.infill_mm_s,
+        material_volume_cm3,
+        material_mass_g: material_volume_cm3 * density,
+        filament_length_m: Some(volume_mm3 / filament_area / MM_PER_METER),
+    })
+}
+
+/// Area inside a layer's closed loops, with loops nested an odd number of
+/// times counted as holes
+fn enclosed_area(polylines: &[Polyline]) -> f32 {
+    let loops: Vec<&[[f32; 2]]> = polylines
+        .iter()
+        .filter(|p| p.closed)
+        .map(|p| p.points.as_slice())
+        .collect();
+    loops
+        .iter()
+        .enumerate()
+        .map(|(i, points)| {
+            let depth = loops
+                .iter()
+                .enumerate()
+                .filter(|&(j, other)| j != i && contains(other, points[0]))
+                .count();
+            let area = shoelace(points).abs();
+            if depth % 2 == 0 {
+                area
+            } else {
+                -area
+            }
+        })
+        .sum::<f32>()
+        .max(0.0)
+}
+
+fn shoelace(points: &[[f32; 2]]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            a[0] * b[1] - b[0] * a[1]
+        })
+        .sum::<f32>()
+        / 2.0
+}
+
+/// Even-odd point in polygon test
+fn contains(polygon: &[[f32; 2]], p: [f32; 2]) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[j]);
+        if (a[1] > p[1]) != (b[1] > p[1])
+            && p[0] < a[0] + (p[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0])
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+fn bounds(positions: &[f32]) -> ([f32; 3], [f32; 3]) {
+    let mut lo = [f32::MAX; 3];
+    let mut hi = [f32::MIN; 3];
+    for p in positions.chunks_exact(3) {
+        for k in 0..3 {
+            lo[k] = lo[k].min(p[k]);
+            hi[k] = hi[k].max(p[k]);
+        }
+    }
+    (lo, hi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_for_a_20mm_cube() {
+        let corners: Vec<f32> = (0..8)
+            .flat_map(|i| [i & 1, (i >> 1) & 1, (i >> 2) & 1].map(|c| c as f32 * 0.02))
+            .collect();
+        let mesh = MeshData {
+            positions: corners,
+            indices: vec![
+                0, 2, 1, 1, 2, 3, 4, 5, 6, 5, 7, 6, 0, 1, 4, 1, 5, 4, 2, 6, 3, 3, 6, 7, 0, 4, 2, 2,
+                4, 6, 1, 3, 5, 3, 7, 5,
+            ],
+            ..Default::default()
+        };
+
+        // Solid infill: every layer is 80 mm of outline and 400 mm² of area
+        let solid = PrintSettings {
+            infill_percent: Some(100.0),
+            wall_count: Some(1),
+            ..Default::default()
+        };
+        let fdm = estimate(&mesh, &solid).unwrap();
+        assert_eq!(fdm.layer_count, 100);
+        let expected_mm3 = 100.0 * (80.0 + (400.0 - 80.0 * 0.45) / 0.45) * 0.45 * 0.2;
+        assert!((fdm.material_volume_cm3 * 1000.0 - expected_mm3).abs() < 1.0);
+        assert!(fdm.filament_length_m.unwrap() > 2.0);
+
+        let resin = PrintSettings {
+            technology: PrintTechnology::Resin,
+            ..Default::default()
+        };
+        let resin = estimate(&mesh, &resin).unwrap();
+        assert_eq!(resin.layer_count, 400);
+        assert!((resin.material_volume_cm3 - 8.0).abs() < 1e-3);
+        assert!((resin.print_time_s - (5.0 * 25.0 + 395.0 * 2.5 + 400.0 * 6.0)).abs() < 1e-2);
+
+        let report = report(&mesh, &PrintSettings::default()).unwrap();
+        assert!(report.is_watertight);
+        assert!((report.size_mm[1] - 20.0).abs() < 1e-3);
+    }
+}
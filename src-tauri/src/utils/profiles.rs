@@ -0,0 +1,272 @@
+//! Named processing profiles
+//!
+//! A profile bundles the steps a model usually goes through before it's
+//! handed on: an optional scan cleanup, simplification to a face budget,
+//! a size limit for its textures and the format to export. Profiles live in
+//! the settings so users can edit the built-in ones or add their own.
+
+use crate::utils::decimate::cluster_decimate;
+use crate::utils::glb_writer::write_glb;
+use crate::utils::mesh_files::NamedMesh;
+use crate::utils::mesh_store::MeshData;
+use crate::utils::obj_writer::write_obj;
+use crate::utils::print_writer::write_stl;
+use crate::utils::scan_cleanup::{self, ScanPreset, StageReport};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Millimeters per meter, for STL output
+const MM_PER_METER: f32 = 1000.0;
+/// Image extensions picked up as a model's textures
+const TEXTURE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileFormat {
+    Glb,
+    Obj,
+    /// Binary STL in Z-up millimeters, for slicers
+    Stl,
+}
+
+impl ProfileFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Glb => "glb",
+            Self::Obj => "obj",
+            Self::Stl => "stl",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessingProfile {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Scan cleanup run first; `None` skips it
+    #[serde(default)]
+    pub cleanup: Option<ScanPreset>,
+    /// Face budget the mesh is simplified to
+    #[serde(default)]
+    pub max_faces: Option<usize>,
+    /// Largest texture side; bigger textures beside the model are scaled down
+    #[serde(default)]
+    pub max_texture_size: Option<u32>,
+    pub format: ProfileFormat,
+}
+
+/// Profiles in the settings, with the built-in ones by default
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ProcessingProfiles(pub Vec<ProcessingProfile>);
+
+impl Default for ProcessingProfiles {
+    fn default() -> Self {
+        let profile =
+            |name: &str, description: &str, cleanup, max_faces, size, format| ProcessingProfile {
+                name: name.to_string(),
+                description: description.to_string(),
+                cleanup,
+                max_faces: Some(max_faces),
+                max_texture_size: size,
+                format,
+            };
+        Self(vec![
+            profile(
+                "Mobile game prop",
+                "Low face count and small textures for phones",
+                None,
+                5_000,
+                Some(1024),
+                ProfileFormat::Glb,
+            ),
+            profile(
+                "Web viewer",
+                "Moderate detail that loads quickly in a browser",
+                None,
+                50_000,
+                Some(2048),
+                ProfileFormat::Glb,
+            ),
+            profile(
+                "Print prep",
+                "Cleaned up, closed and exported as STL for slicing",
+                Some(ScanPreset::Standard),
+                500_000,
+                None,
+                ProfileFormat::Stl,
+            ),
+        ])
+    }
+}
+
+impl ProcessingProfile {
+    /// The name in lower case with anything but letters and digits as `_`,
+    /// added to the names of files the profile writes
+    pub fn file_suffix(&self) -> String {
+        self.name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect()
+    }
+
+    /// Where the profile writes its version of `source` in `out_dir`
+    pub fn output_path(&self, out_dir: &Path, source: &Path, extension: &str) -> PathBuf {
+        let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+        out_dir.join(format!("{}_{}.{}", stem, self.file_suffix(), extension))
+    }
+}
+
+impl ProcessingProfiles {
+    /// The profile called `name`, ignoring case
+    pub fn find(&self, name: &str) -> Result<&ProcessingProfile, String> {
+        self.0
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| {
+                let names: Vec<&str> = self.0.iter().map(|p| p.name.as_str()).collect();
+                format!(
+                    "No processing profile named '{}' (have: {})",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// A mesh after a profile's geometry steps
+pub struct Processed {
+    pub mesh: MeshData,
+    /// What each cleanup stage did, empty without cleanup
+    pub cleanup: Vec<StageReport>,
+    pub original_face_count: usize,
+}
+
+/// Run the cleanup and simplification steps of `profile` on `mesh`
+pub fn process(mesh: MeshData, profile: &ProcessingProfile) -> Result<Processed, String> {
+    let original_face_count = mesh.face_count();
+    if original_face_count == 0 {
+        return Err("Model has no faces".to_string());
+    }
+    if profile.max_faces == Some(0) {
+        return Err("Face budget must be at least 1".to_string());
+    }
+
+    let (mut mesh, cleanup) = match profile.cleanup {
+        Some(preset) => {
+            let mut settings = preset.settings();
+            if let Some(max_faces) = profile.max_faces {
+                settings.target_faces = settings.target_faces.min(max_faces);
+            }
+            let cleaned = scan_cleanup::cleanup(&mesh, &settings)?;
+            (cleaned.mesh, cleaned.stages)
+        }
+        None => (mesh, Vec::new()),
+    };
+    if let Some(max_faces) = profile.max_faces.filter(|&m| mesh.face_count() > m) {
+        let decimated = cluster_decimate(&mesh.positions, &mesh.indices, max_faces);
+        mesh = MeshData {
+            positions: decimated.positions,
+            indices: decimated.indices,
+            ..Default::default()
+        };
+    }
+    Ok(Processed {
+        mesh,
+        cleanup,
+        original_face_count,
+    })
+}
+
+/// Encode a processed mesh in `format`
+pub fn encode(name: &str, mesh: &MeshData, format: ProfileFormat) -> Result<Vec<u8>, String> {
+    match format {
+        ProfileFormat::Glb => write_glb(&[NamedMesh {
+            name: name.to_string(),
+            positions: mesh.positions.clone(),
+            indices: mesh.indices.clone(),
+            polygons: None,
+        }]),
+        ProfileFormat::Obj => Ok(write_obj(&[(name, mesh)], false).into_bytes()),
+        ProfileFormat::Stl => {
+            let positions: Vec<f32> = mesh
+                .positions
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], -p[2], p[1]].map(|c| c * MM_PER_METER))
+                .collect();
+            Ok(write_stl(&[(name, &positions, &mesh.indices)]))
+        }
+    }
+}
+
+/// Images beside `model` whose names start with the model's name, such as
+/// `scan.jpg` or `scan_diffuse.png` for `scan.obj`
+pub fn sibling_textures(model: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(stem)) = (model.parent(), model.file_stem()) else {
+        return Vec::new();
+    };
+    let stem = stem.to_string_lossy().to_lowercase();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut textures: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let extension = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            TEXTURE_EXTENSIONS.contains(&extension.as_str()) && name.starts_with(&stem)
+        })
+        .collect();
+    textures.sort();
+    textures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_simplifies_to_budget() {
+        let profiles = ProcessingProfiles::default();
+        assert_eq!(
+            profiles.find(" web VIEWER").unwrap().max_faces,
+            Some(50_000)
+        );
+        assert!(profiles.find("Nope").unwrap_err().contains("Print prep"));
+
+        // A 20 x 20 grid of quads, 800 triangles
+        let n = 21;
+        let mesh = MeshData {
+            positions: (0..n * n)
+                .flat_map(|i| [(i % n) as f32, 0.0, (i / n) as f32])
+                .collect(),
+            indices: (0..n - 1)
+                .flat_map(|y| (0..n - 1).map(move |x| y * n + x))
+                .flat_map(|i| [i, i + n, i + 1, i + 1, i + n, i + n + 1])
+                .map(|i| i as u32)
+                .collect(),
+            ..Default::default()
+        };
+        let profile = ProcessingProfile {
+            max_faces: Some(100),
+            ..profiles.find("Mobile game prop").unwrap().clone()
+        };
+        let processed = process(mesh, &profile).unwrap();
+        assert_eq!(processed.original_face_count, 800);
+        assert!(processed.mesh.face_count() <= 100);
+        assert!(processed.cleanup.is_empty());
+
+        let stl = encode("grid", &processed.mesh, ProfileFormat::Stl).unwrap();
+        assert_eq!(stl.len(), 84 + processed.mesh.face_count() * 50);
+    }
+}
@@ -0,0 +1,222 @@
+//! Provenance records embedded in exported glTF files
+//!
+//! The record lives in the document's top-level `extras` under
+//! `sweedle_provenance`, which other tools keep when they pass extras
+//! through. It carries a hash of everything else in the file, so a copy
+//! edited outside Sweedle can be told apart from the one that was stamped.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use xxhash_rust::xxh3::Xxh3;
+
+/// Key of the record in the top-level `extras`
+pub const EXTRAS_KEY: &str = "sweedle_provenance";
+
+/// Something done to an asset on its way to the file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessingStep {
+    /// Command or tool that made the change, such as `decimate`
+    pub operation: String,
+    pub details: Option<String>,
+    /// When it happened; stamping fills in the current time for 0
+    #[serde(default)]
+    pub at_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub author: Option<String>,
+    pub source_url: Option<String>,
+    /// Hash of the file as it was first imported
+    pub import_hash: Option<String>,
+    /// Oldest first
+    pub history: Vec<ProcessingStep>,
+    /// Hash of the rest of the file when the record was embedded
+    pub content_hash: String,
+}
+
+/// What to add to a file's record
+///
+/// Fields left `None` keep what an existing record says, and `steps` are
+/// appended to its history.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProvenanceStamp {
+    pub author: Option<String>,
+    pub source_url: Option<String>,
+    pub import_hash: Option<String>,
+    pub steps: Vec<ProcessingStep>,
+}
+
+/// A file's record and whether the file still matches it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvenanceCheck {
+    pub provenance: Option<Provenance>,
+    /// Hash of the file as it is now, without the record
+    pub content_hash: String,
+    /// Whether the file has a record and hasn't changed since it was
+    /// embedded
+    pub intact: bool,
+}
+
+/// The record in a parsed glTF document, if there is a readable one
+pub fn extract(document: &Value) -> Option<Provenance> {
+    let record = document.get("extras")?.get(EXTRAS_KEY)?;
+    match serde_json::from_value(record.clone()) {
+        Ok(provenance) => Some(provenance),
+        Err(e) => {
+            log::warn!("Ignoring unreadable provenance record: {}", e);
+            None
+        }
+    }
+}
+
+/// Compare a document and its binary chunk against their record
+pub fn verify(document: &Value, bin: &[u8]) -> ProvenanceCheck {
+    let provenance = extract(document);
+    let content_hash = content_hash(document, bin);
+    ProvenanceCheck {
+        intact: provenance
+            .as_ref()
+            .is_some_and(|p| p.content_hash == content_hash),
+        provenance,
+        content_hash,
+    }
+}
+
+/// Add `stamp` to the document's record, creating one if there is none,
+/// and seal it with the hash of the document and `bin`
+pub fn embed(
+    document: &mut Value,
+    bin: &[u8],
+    stamp: ProvenanceStamp,
+) -> Result<Provenance, String> {
+    let mut provenance = extract(document).unwrap_or(Provenance {
+        author: None,
+        source_url: None,
+        import_hash: None,
+        history: Vec::new(),
+        content_hash: String::new(),
+    });
+    let field = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    provenance.author = field(stamp.author).or(provenance.author);
+    provenance.source_url = field(stamp.source_url).or(provenance.source_url);
+    provenance.import_hash = field(stamp.import_hash).or(provenance.import_hash);
+    let now = now_ms();
+    provenance
+        .history
+        .extend(stamp.steps.into_iter().map(|step| ProcessingStep {
+            at_ms: if step.at_ms == 0 { now } else { step.at_ms },
+            ..step
+        }));
+    provenance.content_hash = content_hash(document, bin);
+
+    let root = document
+        .as_object_mut()
+        .ok_or("glTF document is not an object")?;
+    let extras = root
+        .entry("extras")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or("Can't add provenance: extras is not an object")?;
+    let record = serde_json::to_value(&provenance)
+        .map_err(|e| format!("Failed to serialize provenance: {}", e))?;
+    extras.insert(EXTRAS_KEY.to_string(), record);
+    Ok(provenance)
+}
+
+/// Hash of a document without its record, and of its binary chunk
+fn content_hash(document: &Value, bin: &[u8]) -> String {
+    let mut rest = document.clone();
+    if let Some(extras) = rest.get_mut("extras").and_then(Value::as_object_mut) {
+        extras.remove(EXTRAS_KEY);
+        if extras.is_empty() {
+            rest.as_object_mut().map(|root| root.remove("extras"));
+        }
+    }
+    let mut hasher = Xxh3::new();
+    hasher.update(rest.to_string().as_bytes());
+    hasher.update(bin);
+    format!("{:032x}", hasher.digest128())
+}
+
+/// Hash of a file's contents, read in pieces
+pub fn file_hash(path: &Path) -> Result<String, String> {
+    let mut file =
+        fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0u8; 1 << 20];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:032x}", hasher.digest128()))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_embedded_record_detects_changes() {
+        let mut document = json!({ "asset": { "version": "2.0" }, "meshes": [] });
+        let bin = [1u8, 2, 3, 4];
+        assert!(!verify(&document, &bin).intact);
+
+        let stamp = ProvenanceStamp {
+            author: Some("Kit".to_string()),
+            import_hash: Some("abc".to_string()),
+            steps: vec![ProcessingStep {
+                operation: "import".to_string(),
+                details: None,
+                at_ms: 0,
+            }],
+            ..Default::default()
+        };
+        embed(&mut document, &bin, stamp).unwrap();
+        let check = verify(&document, &bin);
+        assert!(check.intact);
+        assert!(check.provenance.unwrap().history[0].at_ms > 0);
+
+        // Stamping again keeps the record's fields and extends its history
+        let step = ProcessingStep {
+            operation: "decimate".to_string(),
+            details: Some("50%".to_string()),
+            at_ms: 7,
+        };
+        let stamp = ProvenanceStamp {
+            steps: vec![step],
+            ..Default::default()
+        };
+        let provenance = embed(&mut document, &bin, stamp).unwrap();
+        assert_eq!(provenance.author.as_deref(), Some("Kit"));
+        assert_eq!(provenance.history.len(), 2);
+        assert!(verify(&document, &bin).intact);
+
+        assert!(!verify(&document, &[1, 2, 3, 5]).intact);
+        document["meshes"] = json!([{ "name": "edited" }]);
+        let check = verify(&document, &bin);
+        assert!(!check.intact && check.provenance.is_some());
+    }
+}
@@ -0,0 +1,469 @@
+//! QR codes for short text such as URLs
+//!
+//! Encodes bytes at error correction level M in versions 1 to 10, which
+//! holds up to 213 bytes: enough for a LAN address and a token. The layout
+//! follows ISO/IEC 18004; the mask is chosen by the standard's penalty
+//! rules.
+
+/// Codewords per version: (error correction per block, blocks and data
+/// codewords of the first group, blocks and data codewords of the second)
+const LEVEL_M: [(usize, usize, usize, usize, usize); 10] = [
+    (10, 1, 16, 0, 0),
+    (16, 1, 28, 0, 0),
+    (26, 1, 44, 0, 0),
+    (18, 2, 32, 0, 0),
+    (24, 2, 43, 0, 0),
+    (16, 4, 27, 0, 0),
+    (18, 4, 31, 0, 0),
+    (22, 2, 38, 2, 39),
+    (22, 3, 36, 2, 37),
+    (26, 4, 43, 1, 44),
+];
+/// Centres of the alignment patterns per version
+const ALIGNMENT: [&[usize]; 10] = [
+    &[],
+    &[6, 18],
+    &[6, 22],
+    &[6, 26],
+    &[6, 30],
+    &[6, 34],
+    &[6, 22, 38],
+    &[6, 24, 42],
+    &[6, 26, 46],
+    &[6, 28, 50],
+];
+/// Format bits of level M
+const LEVEL_M_BITS: u32 = 0b00;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrCode {
+    pub version: usize,
+    /// Modules per side
+    pub size: usize,
+    /// Row by row, `true` for dark
+    pub modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// The smallest code holding `data`
+    pub fn encode(data: &[u8]) -> Result<Self, String> {
+        let version = (1..=LEVEL_M.len())
+            .find(|&v| 4 + count_bits(v) + data.len() * 8 <= data_capacity(v) * 8)
+            .ok_or_else(|| format!("{} bytes are too many for a QR code", data.len()))?;
+        let codewords = interleave(version, &data_codewords(version, data));
+
+        let mut code = Grid::new(version);
+        code.draw_function_patterns();
+        code.draw_codewords(&codewords);
+        let mask = (0..8)
+            .min_by_key(|&mask| {
+                let mut masked = code.clone();
+                masked.apply_mask(mask);
+                masked.draw_format(mask);
+                masked.penalty()
+            })
+            .unwrap_or(0);
+        code.apply_mask(mask);
+        code.draw_format(mask);
+
+        Ok(Self {
+            version,
+            size: code.size,
+            modules: code.modules,
+        })
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    /// The code as an SVG image with a quiet zone of `border` modules
+    pub fn svg(&self, border: usize) -> String {
+        let side = self.size + 2 * border;
+        let mut path = String::new();
+        for y in 0..self.size {
+            for x in (0..self.size).filter(|&x| self.is_dark(x, y)) {
+                path.push_str(&format!("M{},{}h1v1h-1z", x + border, y + border));
+            }
+        }
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {side} {side}\" \
+             shape-rendering=\"crispEdges\"><rect width=\"100%\" height=\"100%\" \
+             fill=\"#fff\"/><path d=\"{path}\" fill=\"#000\"/></svg>"
+        )
+    }
+}
+
+/// Modules being laid out, with those of function patterns marked
+#[derive(Clone)]
+struct Grid {
+    version: usize,
+    size: usize,
+    modules: Vec<bool>,
+    function: Vec<bool>,
+}
+
+impl Grid {
+    fn new(version: usize) -> Self {
+        let size = 4 * version + 17;
+        Self {
+            version,
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self) {
+        for i in 0..self.size {
+            self.set_function(6, i, i.is_multiple_of(2));
+            self.set_function(i, 6, i.is_multiple_of(2));
+        }
+        let far = self.size - 4;
+        for (x, y) in [(3, 3), (far, 3), (3, far)] {
+            self.draw_finder(x, y);
+        }
+        let centres = ALIGNMENT[self.version - 1];
+        let last = centres.len().saturating_sub(1);
+        for (i, &x) in centres.iter().enumerate() {
+            for (j, &y) in centres.iter().enumerate() {
+                // Corners taken by finder patterns
+                if (i == 0 && (j == 0 || j == last)) || (i == last && j == 0) {
+                    continue;
+                }
+                for dy in 0..5usize {
+                    for dx in 0..5usize {
+                        let ring = dx.abs_diff(2).max(dy.abs_diff(2));
+                        self.set_function(x + dx - 2, y + dy - 2, ring != 1);
+                    }
+                }
+            }
+        }
+        // Reserved now, filled in once the mask is known
+        self.draw_format(0);
+        self.draw_version();
+    }
+
+    /// A finder pattern centred on `(x, y)` with its light separator
+    fn draw_finder(&mut self, x: usize, y: usize) {
+        for dy in -4i64..=4 {
+            for dx in -4i64..=4 {
+                let (mx, my) = (x as i64 + dx, y as i64 + dy);
+                if (0..self.size as i64).contains(&mx) && (0..self.size as i64).contains(&my) {
+                    let ring = dx.abs().max(dy.abs());
+                    self.set_function(mx as usize, my as usize, ring != 2 && ring != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_format(&mut self, mask: u32) {
+        let bits = format_bits(mask);
+        let bit = |i: usize| (bits >> i) & 1 == 1;
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        let size = self.size;
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version(&mut self) {
+        if self.version < 7 {
+            return;
+        }
+        let mut remainder = self.version as u32;
+        for _ in 0..12 {
+            remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1F25);
+        }
+        let bits = (self.version as u32) << 12 | remainder;
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 == 1;
+            let (a, b) = (self.size - 11 + i % 3, i / 3);
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Place codewords in the zigzag from the bottom right corner, two
+    /// columns at a time, skipping the vertical timing pattern
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let mut bit = 0;
+        let mut right = self.size - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vertical in 0..self.size {
+                let y = if upward {
+                    self.size - 1 - vertical
+                } else {
+                    vertical
+                };
+                for x in [right, right - 1] {
+                    if !self.function[y * self.size + x] && bit < codewords.len() * 8 {
+                        self.modules[y * self.size + x] =
+                            (codewords[bit / 8] >> (7 - bit % 8)) & 1 == 1;
+                        bit += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let flip = match mask {
+                    0 => (x + y).is_multiple_of(2),
+                    1 => y.is_multiple_of(2),
+                    2 => x.is_multiple_of(3),
+                    3 => (x + y).is_multiple_of(3),
+                    4 => (x / 3 + y / 2).is_multiple_of(2),
+                    5 => (x * y % 2 + x * y % 3) == 0,
+                    6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+                    _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+                };
+                let i = y * self.size + x;
+                if flip && !self.function[i] {
+                    self.modules[i] = !self.modules[i];
+                }
+            }
+        }
+    }
+
+    /// Penalty score of the standard's four rules; lower scans better
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let lines = (0..size).flat_map(|i| {
+            [
+                (0..size).map(|j| self.get(j, i)).collect::<Vec<_>>(),
+                (0..size).map(|j| self.get(i, j)).collect::<Vec<_>>(),
+            ]
+        });
+        let finder_like = [true, false, true, true, true, false, true];
+        let mut score = 0;
+        for line in lines {
+            let mut run = 1;
+            for j in 1..=size {
+                if j < size && line[j] == line[j - 1] {
+                    run += 1;
+                    continue;
+                }
+                if run >= 5 {
+                    score += run - 2;
+                }
+                run = 1;
+            }
+            for start in 0..=size - finder_like.len() {
+                if line[start..start + 7] != finder_like {
+                    continue;
+                }
+                let light = |from: usize, to: usize| (from..to).all(|k| !line[k]);
+                if (start >= 4 && light(start - 4, start))
+                    || (start + 11 <= size && light(start + 7, start + 11))
+                {
+                    score += 40;
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.get(x, y);
+                if self.get(x + 1, y) == dark
+                    && self.get(x, y + 1) == dark
+                    && self.get(x + 1, y + 1) == dark
+                {
+                    score += 3;
+                }
+            }
+        }
+        let dark = self.modules.iter().filter(|&&m| m).count();
+        let percent = dark * 100 / self.modules.len();
+        score + percent.abs_diff(50) / 5 * 10
+    }
+}
+
+/// The 15 format bits of level M with `mask`, error corrected and masked
+fn format_bits(mask: u32) -> u32 {
+    let data = LEVEL_M_BITS << 3 | mask;
+    let mut remainder = data;
+    for _ in 0..10 {
+        remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+    }
+    (data << 10 | remainder) ^ 0x5412
+}
+
+fn count_bits(version: usize) -> usize {
+    if version < 10 {
+        8
+    } else {
+        16
+    }
+}
+
+fn data_capacity(version: usize) -> usize {
+    let (_, blocks1, data1, blocks2, data2) = LEVEL_M[version - 1];
+    blocks1 * data1 + blocks2 * data2
+}
+
+/// `data` in byte mode, terminated and padded to the version's capacity
+fn data_codewords(version: usize, data: &[u8]) -> Vec<u8> {
+    let mut bits = Bits::default();
+    bits.push(0b0100, 4);
+    bits.push(data.len() as u32, count_bits(version));
+    for &byte in data {
+        bits.push(byte as u32, 8);
+    }
+    let capacity = data_capacity(version) * 8;
+    bits.push(0, (capacity - bits.len).min(4));
+    bits.push(0, (8 - bits.len % 8) % 8);
+    let mut codewords = bits.bytes;
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if codewords.len() >= capacity / 8 {
+            break;
+        }
+        codewords.push(pad);
+    }
+    codewords
+}
+
+/// Split data codewords into blocks, add each block's error correction and
+/// interleave them
+fn interleave(version: usize, data: &[u8]) -> Vec<u8> {
+    let (ec_len, blocks1, data1, blocks2, data2) = LEVEL_M[version - 1];
+    let divisor = rs_divisor(ec_len);
+    let mut blocks = Vec::new();
+    let mut rest = data;
+    for len in std::iter::repeat_n(data1, blocks1).chain(std::iter::repeat_n(data2, blocks2)) {
+        let (block, remaining) = rest.split_at(len);
+        blocks.push((block, rs_remainder(block, &divisor)));
+        rest = remaining;
+    }
+    let mut out = Vec::new();
+    for i in 0..data1.max(data2) {
+        out.extend(blocks.iter().filter_map(|(block, _)| block.get(i)));
+    }
+    for i in 0..ec_len {
+        out.extend(blocks.iter().map(|(_, ec)| ec[i]));
+    }
+    out
+}
+
+#[derive(Default)]
+struct Bits {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl Bits {
+    fn push(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            if self.len.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if (value >> i) & 1 == 1 {
+                let last = self.bytes.len() - 1;
+                self.bytes[last] |= 0x80 >> (self.len % 8);
+            }
+            self.len += 1;
+        }
+    }
+}
+
+/// Generator polynomial of Reed-Solomon codes with `degree` error
+/// correction codewords, highest coefficient first and the leading 1 left
+/// out
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_mul(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_mul(root, 0x02);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_mul(d, factor);
+        }
+    }
+    result
+}
+
+/// Product in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((y as u32 >> i) & 1) * x as u32;
+    }
+    z as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qr_matches_reference_codewords() {
+        // "HELLO WORLD" at 1-M, from the worked example of the standard
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        assert_eq!(
+            rs_remainder(&data, &rs_divisor(10)),
+            vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+        assert_eq!(format_bits(0), 0b101010000010010);
+
+        let url = "http://192.168.1.23:49152/3f9a0c1e77d24b6a/";
+        let code = QrCode::encode(url.as_bytes()).unwrap();
+        assert_eq!(code.version, 4);
+        assert_eq!(code.size, 33);
+        // Finder patterns: dark corners, light separators
+        for (x, y) in [(0, 0), (32, 0), (0, 32)] {
+            assert!(code.is_dark(x, y));
+        }
+        assert!(!code.is_dark(7, 7) && !code.is_dark(25, 7) && !code.is_dark(7, 25));
+        assert!(code.is_dark(8, 25));
+        assert!(QrCode::encode(&[b'a'; 214]).is_err());
+        assert!(code.svg(4).starts_with("<svg"));
+    }
+}
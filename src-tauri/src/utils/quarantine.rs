@@ -0,0 +1,551 @@
+//! Hardened parsing for downloaded and imported assets
+//!
+//! Untrusted files are parsed by a copy of this executable started with
+//! [`WORKER_FLAG`]. The worker caps its own heap through the tracking
+//! allocator, the parent kills it when the time limit passes, and both a
+//! crash and a timeout count as a rejection. Inside the worker, structural
+//! counts are checked before anything is decoded so absurd declarations
+//! (billions of accessors, views past the end of their buffer) are refused
+//! cheaply. OBJ, STL and FBX files have no such structure and are decoded in
+//! full, under the same limits.
+
+use crate::utils::analysis_cache::ModelSource;
+use crate::utils::mesh_files::{self, MeshFileFormat};
+use crate::utils::metrics;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Command-line flag that turns the executable into a parse worker
+pub const WORKER_FLAG: &str = "--sweedle-parse-worker";
+
+/// Resource and structure limits for untrusted files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParseLimits {
+    pub max_file_bytes: u64,
+    pub max_json_bytes: u64,
+    pub max_heap_bytes: u64,
+    pub timeout_ms: u64,
+    pub max_accessors: usize,
+    pub max_buffer_views: usize,
+    pub max_buffers: usize,
+    pub max_meshes: usize,
+    pub max_primitives: usize,
+    pub max_nodes: usize,
+    pub max_materials: usize,
+    pub max_textures: usize,
+    pub max_images: usize,
+    /// Largest element count a single accessor may declare
+    pub max_accessor_elements: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: 1 << 30,
+            max_json_bytes: 64 << 20,
+            max_heap_bytes: 2 << 30,
+            timeout_ms: 30_000,
+            max_accessors: 100_000,
+            max_buffer_views: 100_000,
+            max_buffers: 1_000,
+            max_meshes: 50_000,
+            max_primitives: 200_000,
+            max_nodes: 200_000,
+            max_materials: 10_000,
+            max_textures: 10_000,
+            max_images: 10_000,
+            max_accessor_elements: 1 << 28,
+        }
+    }
+}
+
+impl ParseLimits {
+    /// Tighten caller-supplied limits; none may exceed the defaults
+    pub fn clamped(self) -> Self {
+        let max = Self::default();
+        Self {
+            max_file_bytes: self.max_file_bytes.min(max.max_file_bytes),
+            max_json_bytes: self.max_json_bytes.min(max.max_json_bytes),
+            max_heap_bytes: self.max_heap_bytes.min(max.max_heap_bytes),
+            timeout_ms: self.timeout_ms.min(max.timeout_ms),
+            max_accessors: self.max_accessors.min(max.max_accessors),
+            max_buffer_views: self.max_buffer_views.min(max.max_buffer_views),
+            max_buffers: self.max_buffers.min(max.max_buffers),
+            max_meshes: self.max_meshes.min(max.max_meshes),
+            max_primitives: self.max_primitives.min(max.max_primitives),
+            max_nodes: self.max_nodes.min(max.max_nodes),
+            max_materials: self.max_materials.min(max.max_materials),
+            max_textures: self.max_textures.min(max.max_textures),
+            max_images: self.max_images.min(max.max_images),
+            max_accessor_elements: self.max_accessor_elements.min(max.max_accessor_elements),
+        }
+    }
+}
+
+/// What a file contains, once it has passed every check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InspectionReport {
+    pub file_size_bytes: u64,
+    pub accessors: usize,
+    pub meshes: usize,
+    pub primitives: usize,
+    pub nodes: usize,
+    pub materials: usize,
+    pub textures: usize,
+    pub images: usize,
+    pub vertex_count: usize,
+    pub face_count: usize,
+}
+
+/// Outcome of a sandboxed parse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantineVerdict {
+    pub path: String,
+    pub passed: bool,
+    pub reasons: Vec<String>,
+    pub report: Option<InspectionReport>,
+    pub elapsed_ms: u64,
+}
+
+/// What the worker prints on stdout
+#[derive(Debug, Serialize, Deserialize)]
+struct WorkerOutput {
+    reasons: Vec<String>,
+    report: Option<InspectionReport>,
+}
+
+/// Run the worker if this process was started as one
+///
+/// Returns the exit code to use, or `None` for a normal app launch.
+pub fn worker_main() -> Option<i32> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) != Some(WORKER_FLAG) {
+        return None;
+    }
+
+    let limits: ParseLimits = args
+        .get(2)
+        .and_then(|json| serde_json::from_str(json).ok())
+        .unwrap_or_default();
+    let Some(path) = args.get(3) else {
+        eprintln!("usage: {} <limits-json> <path>", WORKER_FLAG);
+        return Some(2);
+    };
+
+    metrics::set_heap_limit(limits.max_heap_bytes);
+    let output = match inspect(Path::new(path), &limits) {
+        Ok(report) => WorkerOutput {
+            reasons: Vec::new(),
+            report: Some(report),
+        },
+        Err(reasons) => WorkerOutput {
+            reasons,
+            report: None,
+        },
+    };
+
+    match serde_json::to_string(&output) {
+        Ok(json) => {
+            println!("{}", json);
+            Some(0)
+        }
+        Err(_) => Some(1),
+    }
+}
+
+/// Parse `path` in a worker process under `limits`
+pub fn parse_in_sandbox(path: &Path, limits: &ParseLimits) -> Result<QuarantineVerdict, String> {
+    let started = Instant::now();
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate executable: {}", e))?;
+    let limits_json =
+        serde_json::to_string(limits).map_err(|e| format!("Failed to serialize limits: {}", e))?;
+
+    let mut child = Command::new(exe)
+        .arg(WORKER_FLAG)
+        .arg(limits_json)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start parse worker: {}", e))?;
+
+    let stdout = child.stdout.take().map(read_to_end);
+    let stderr = child.stderr.take().map(read_to_end);
+
+    let deadline = started + Duration::from_millis(limits.timeout_ms);
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(10)),
+            Err(e) => return Err(format!("Failed to wait for parse worker: {}", e)),
+        }
+    };
+
+    let stdout = stdout.and_then(|h| h.join().ok()).unwrap_or_default();
+    let stderr = stderr.and_then(|h| h.join().ok()).unwrap_or_default();
+
+    let verdict = |reasons: Vec<String>, report: Option<InspectionReport>| QuarantineVerdict {
+        path: path.to_string_lossy().to_string(),
+        passed: reasons.is_empty() && report.is_some(),
+        reasons,
+        report,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    };
+
+    let Some(status) = status else {
+        return Ok(verdict(
+            vec![format!("Parsing took longer than {} ms", limits.timeout_ms)],
+            None,
+        ));
+    };
+
+    if status.success() {
+        if let Ok(output) = serde_json::from_slice::<WorkerOutput>(&stdout) {
+            return Ok(verdict(output.reasons, output.report));
+        }
+    }
+
+    let stderr = String::from_utf8_lossy(&stderr);
+    let reason = if stderr.contains("memory allocation of") {
+        format!(
+            "Parsing needed more than {} bytes of memory",
+            limits.max_heap_bytes
+        )
+    } else {
+        format!("Parse worker crashed ({})", status)
+    };
+    Ok(verdict(vec![reason], None))
+}
+
+fn read_to_end(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        bytes
+    })
+}
+
+/// Check a file against `limits`, returning every problem found
+///
+/// Runs in the worker process; call [`parse_in_sandbox`] instead.
+pub fn inspect(path: &Path, limits: &ParseLimits) -> Result<InspectionReport, Vec<String>> {
+    let fail = |reason: String| vec![reason];
+
+    let file_size_bytes = std::fs::metadata(path)
+        .map_err(|e| fail(format!("Failed to read file: {}", e)))?
+        .len();
+    if file_size_bytes > limits.max_file_bytes {
+        return Err(fail(format!(
+            "File is {} bytes, more than the {} byte limit",
+            file_size_bytes, limits.max_file_bytes
+        )));
+    }
+
+    if MeshFileFormat::from_path(path).is_ok_and(|format| format != MeshFileFormat::Gltf) {
+        return inspect_meshes(path, file_size_bytes, limits);
+    }
+
+    let source = ModelSource::open(path).map_err(fail)?;
+    if source.json().len() as u64 > limits.max_json_bytes {
+        return Err(fail(format!(
+            "JSON is {} bytes, more than the {} byte limit",
+            source.json().len(),
+            limits.max_json_bytes
+        )));
+    }
+
+    let root = gltf::json::Root::from_slice(source.json())
+        .map_err(|e| fail(format!("Failed to parse GLTF: {}", e)))?;
+
+    let mut reasons = Vec::new();
+    let mut limit = |what: &str, count: usize, max: usize| {
+        if count > max {
+            reasons.push(format!("{} {} (limit {})", count, what, max));
+        }
+    };
+    let primitive_count = root.meshes.iter().map(|m| m.primitives.len()).sum();
+    limit("accessors", root.accessors.len(), limits.max_accessors);
+    limit(
+        "buffer views",
+        root.buffer_views.len(),
+        limits.max_buffer_views,
+    );
+    limit("buffers", root.buffers.len(), limits.max_buffers);
+    limit("meshes", root.meshes.len(), limits.max_meshes);
+    limit("primitives", primitive_count, limits.max_primitives);
+    limit("nodes", root.nodes.len(), limits.max_nodes);
+    limit("materials", root.materials.len(), limits.max_materials);
+    limit("textures", root.textures.len(), limits.max_textures);
+    limit("images", root.images.len(), limits.max_images);
+    if !reasons.is_empty() {
+        return Err(reasons);
+    }
+
+    let document =
+        gltf::Document::from_json(root).map_err(|e| fail(format!("Invalid GLTF: {}", e)))?;
+
+    check_ranges(&document, source.bin(), limits)?;
+
+    // Ranges are known to be sound, so decoding can't read out of bounds
+    let buffers = gltf::import_buffers(&document, None, source.bin().map(<[u8]>::to_vec))
+        .map_err(|e| fail(format!("Failed to load GLTF buffers: {}", e)))?;
+    let get_buffer = |buffer: gltf::Buffer| buffers.get(buffer.index()).map(|data| &data.0[..]);
+    let mut vertex_count = 0;
+    let mut face_count = 0;
+    for mesh in document.meshes() {
+        for primitive in mesh.primitives() {
+            let vertices = primitive
+                .get(&gltf::Semantic::Positions)
+                .map(|a| a.count())
+                .unwrap_or(0);
+            vertex_count += vertices;
+
+            let reader = primitive.reader(get_buffer);
+            match reader.read_indices() {
+                Some(indices) => {
+                    let mut count = 0;
+                    for index in indices.into_u32() {
+                        if index as usize >= vertices {
+                            return Err(fail(format!(
+                                "Mesh {} primitive {} indexes vertex {} of {}",
+                                mesh.index(),
+                                primitive.index(),
+                                index,
+                                vertices
+                            )));
+                        }
+                        count += 1;
+                    }
+                    face_count += count / 3;
+                }
+                None => face_count += vertices / 3,
+            }
+        }
+    }
+
+    Ok(InspectionReport {
+        file_size_bytes,
+        accessors: document.accessors().len(),
+        meshes: document.meshes().len(),
+        primitives: primitive_count,
+        nodes: document.nodes().len(),
+        materials: document.materials().len(),
+        textures: document.textures().len(),
+        images: document.images().len(),
+        vertex_count,
+        face_count,
+    })
+}
+
+/// Decode an OBJ, STL or FBX file and check that its triangles only use
+/// vertices it has
+fn inspect_meshes(
+    path: &Path,
+    file_size_bytes: u64,
+    limits: &ParseLimits,
+) -> Result<InspectionReport, Vec<String>> {
+    let (_, meshes) = mesh_files::load_meshes(path).map_err(|e| vec![e])?;
+    if meshes.len() > limits.max_meshes {
+        return Err(vec![format!(
+            "{} meshes (limit {})",
+            meshes.len(),
+            limits.max_meshes
+        )]);
+    }
+
+    let mut vertex_count = 0;
+    let mut face_count = 0;
+    for mesh in &meshes {
+        let vertices = mesh.positions.len() / 3;
+        let largest = vertices.max(mesh.indices.len());
+        if largest > limits.max_accessor_elements {
+            return Err(vec![format!(
+                "Mesh {} has {} elements (limit {})",
+                mesh.name, largest, limits.max_accessor_elements
+            )]);
+        }
+        if let Some(index) = mesh.indices.iter().find(|&&i| i as usize >= vertices) {
+            return Err(vec![format!(
+                "Mesh {} indexes vertex {} of {}",
+                mesh.name, index, vertices
+            )]);
+        }
+        vertex_count += vertices;
+        face_count += mesh.indices.len() / 3;
+    }
+
+    Ok(InspectionReport {
+        file_size_bytes,
+        accessors: 0,
+        meshes: meshes.len(),
+        primitives: meshes.len(),
+        nodes: 0,
+        materials: 0,
+        textures: 0,
+        images: 0,
+        vertex_count,
+        face_count,
+    })
+}
+
+/// Make sure every buffer, view and accessor stays inside the data it claims
+fn check_ranges(
+    document: &gltf::Document,
+    bin: Option<&[u8]>,
+    limits: &ParseLimits,
+) -> Result<(), Vec<String>> {
+    let mut reasons = Vec::new();
+
+    for buffer in document.buffers() {
+        match buffer.source() {
+            gltf::buffer::Source::Bin => {
+                let actual = bin.map(<[u8]>::len).unwrap_or(0);
+                if buffer.length() > actual {
+                    reasons.push(format!(
+                        "Buffer {} declares {} bytes but the binary chunk has {}",
+                        buffer.index(),
+                        buffer.length(),
+                        actual
+                    ));
+                }
+            }
+            gltf::buffer::Source::Uri(uri) if uri.starts_with("data:") => {}
+            gltf::buffer::Source::Uri(uri) => reasons.push(format!(
+                "Buffer {} references external file {}; quarantined assets must be self-contained",
+                buffer.index(),
+                uri
+            )),
+        }
+    }
+
+    for view in document.views() {
+        let end = view.offset().checked_add(view.length());
+        if end.is_none_or(|end| end > view.buffer().length()) {
+            reasons.push(format!(
+                "Buffer view {} runs past the end of buffer {}",
+                view.index(),
+                view.buffer().index()
+            ));
+        }
+    }
+
+    for accessor in document.accessors() {
+        if accessor.count() > limits.max_accessor_elements {
+            reasons.push(format!(
+                "Accessor {} declares {} elements (limit {})",
+                accessor.index(),
+                accessor.count(),
+                limits.max_accessor_elements
+            ));
+            continue;
+        }
+        let Some(view) = accessor.view() else {
+            continue;
+        };
+        let stride = view.stride().unwrap_or(accessor.size());
+        let needed = accessor.count().checked_sub(1).map(|last| {
+            last.checked_mul(stride)
+                .and_then(|n| n.checked_add(accessor.offset()))
+                .and_then(|n| n.checked_add(accessor.size()))
+        });
+        if let Some(needed) = needed {
+            if needed.is_none_or(|needed| needed > view.length()) {
+                reasons.push(format!(
+                    "Accessor {} reads past the end of buffer view {}",
+                    accessor.index(),
+                    view.index()
+                ));
+            }
+        }
+    }
+
+    if reasons.is_empty() {
+        Ok(())
+    } else {
+        Err(reasons)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_gltf(path: &Path, accessor_count: usize) {
+        let json = format!(
+            r#"{{"asset":{{"version":"2.0"}},
+            "buffers":[{{"byteLength":36,"uri":"data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"}}],
+            "bufferViews":[{{"buffer":0,"byteLength":36}}],
+            "accessors":[{{"bufferView":0,"componentType":5126,"count":{},"type":"VEC3",
+                "min":[0,0,0],"max":[1,1,0]}}],
+            "meshes":[{{"primitives":[{{"attributes":{{"POSITION":0}}}}]}}]}}"#,
+            accessor_count
+        );
+        std::fs::write(path, json).unwrap();
+    }
+
+    #[test]
+    fn test_inspect_rejects_out_of_range_accessors() {
+        let dir = std::env::temp_dir().join(format!("sweedle-quarantine-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("asset.gltf");
+        let limits = ParseLimits::default();
+
+        write_gltf(&path, 3);
+        let report = inspect(&path, &limits).unwrap();
+        assert_eq!((report.vertex_count, report.face_count), (3, 1));
+
+        write_gltf(&path, 4);
+        let reasons = inspect(&path, &limits).unwrap_err();
+        assert!(reasons[0].contains("reads past the end"));
+
+        let tight = ParseLimits {
+            max_accessors: 0,
+            ..Default::default()
+        };
+        write_gltf(&path, 3);
+        assert!(inspect(&path, &tight).unwrap_err()[0].contains("accessors"));
+
+        let obj = dir.join("asset.obj");
+        std::fs::write(&obj, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let report = inspect(&obj, &limits).unwrap();
+        assert_eq!((report.vertex_count, report.face_count), (3, 1));
+        std::fs::write(&obj, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 9\n").unwrap();
+        assert!(inspect(&obj, &limits).is_err());
+        let few_elements = ParseLimits {
+            max_accessor_elements: 2,
+            ..Default::default()
+        };
+        std::fs::write(&obj, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        assert!(inspect(&obj, &few_elements).unwrap_err()[0].contains("elements"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_caller_limits_cannot_exceed_defaults() {
+        let loose = ParseLimits {
+            max_file_bytes: u64::MAX,
+            timeout_ms: u64::MAX,
+            max_accessor_elements: usize::MAX,
+            max_meshes: 3,
+            ..Default::default()
+        }
+        .clamped();
+        let defaults = ParseLimits::default();
+        assert_eq!(loose.max_file_bytes, defaults.max_file_bytes);
+        assert_eq!(loose.timeout_ms, defaults.timeout_ms);
+        assert_eq!(loose.max_accessor_elements, defaults.max_accessor_elements);
+        assert_eq!(loose.max_meshes, 3);
+    }
+}
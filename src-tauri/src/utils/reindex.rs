@@ -0,0 +1,196 @@
+//! Vertex welding across all attributes
+//!
+//! Exporters that write one vertex per face corner (most OBJ writers do)
+//! leave three times the vertices a mesh needs. Welding maps every vertex to
+//! the first one it matches in every attribute, like meshoptimizer's
+//! `generateVertexRemap`, and rebuilds the index buffer around the survivors.
+
+use crate::utils::mesh_store::MeshData;
+use crate::utils::skin::MAX_INFLUENCES;
+use std::collections::HashMap;
+
+/// A mesh after welding
+#[derive(Debug, Clone, Default)]
+pub struct Reindexed {
+    pub mesh: MeshData,
+    /// New index of each old vertex, `u32::MAX` for vertices no face uses
+    pub remap: Vec<u32>,
+    /// Triangles that collapsed because their corners were welded together
+    pub removed_face_count: usize,
+}
+
+/// Weld vertices that match in every attribute and rebuild the index buffer
+///
+/// With a `tolerance` of 0 vertices must be bit-identical. Otherwise
+/// positions may be up to `tolerance` apart, other float attributes may
+/// differ by up to `tolerance` per component, and joint indices must still
+/// match exactly. Unreferenced vertices are dropped.
+pub fn reindex(mesh: &MeshData, tolerance: f32) -> Reindexed {
+    let vertex_count = mesh.vertex_count();
+    let mut referenced = vec![false; vertex_count];
+    for &i in &mesh.indices {
+        if let Some(slot) = referenced.get_mut(i as usize) {
+            *slot = true;
+        }
+    }
+
+    let vertices = VertexView::new(mesh);
+    let mut remap = vec![u32::MAX; vertex_count];
+    let mut kept: Vec<usize> = Vec::new();
+
+    if tolerance.is_nan() || tolerance <= 0.0 {
+        let mut seen: HashMap<Vec<u32>, u32> = HashMap::new();
+        for i in (0..vertex_count).filter(|&i| referenced[i]) {
+            remap[i] = *seen.entry(vertices.bits(i)).or_insert_with(|| {
+                kept.push(i);
+                (kept.len() - 1) as u32
+            });
+        }
+    } else {
+        let cell_of = |i: usize| {
+            [0, 1, 2].map(|axis| (mesh.positions[i * 3 + axis] / tolerance).floor() as i64)
+        };
+        let mut cells: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+        for i in (0..vertex_count).filter(|&i| referenced[i]) {
+            cells.entry(cell_of(i)).or_default().push(i as u32);
+        }
+
+        // Greedy, like `simd::count_unique_vertices`: each unwelded vertex
+        // absorbs every later match
+        for i in 0..vertex_count {
+            if !referenced[i] || remap[i] != u32::MAX {
+                continue;
+            }
+            let id = kept.len() as u32;
+            kept.push(i);
+            remap[i] = id;
+
+            let [cx, cy, cz] = cell_of(i);
+            for dz in -1..=1 {
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let Some(members) = cells.get(&[cx + dx, cy + dy, cz + dz]) else {
+                            continue;
+                        };
+                        for &j in members {
+                            let j = j as usize;
+                            if j > i && remap[j] == u32::MAX && vertices.matches(i, j, tolerance) {
+                                remap[j] = id;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut indices = Vec::with_capacity(mesh.indices.len());
+    let mut removed_face_count = 0;
+    for face in mesh.indices.chunks_exact(3) {
+        let tri = [face[0], face[1], face[2]].map(|i| remap[i as usize]);
+        if tri[0] == tri[1] || tri[1] == tri[2] || tri[0] == tri[2] {
+            removed_face_count += 1;
+        } else {
+            indices.extend_from_slice(&tri);
+        }
+    }
+
+    Reindexed {
+        mesh: mesh.with_vertices(&kept, indices),
+        remap,
+        removed_face_count,
+    }
+}
+
+/// Every attribute of a mesh, addressed per vertex
+struct VertexView<'a> {
+    mesh: &'a MeshData,
+    /// Float attributes other than positions, with their widths
+    others: Vec<(&'a [f32], usize)>,
+}
+
+impl<'a> VertexView<'a> {
+    fn new(mesh: &'a MeshData) -> Self {
+        let others = [
+            (mesh.normals.as_deref(), 3),
+            (mesh.uvs.as_deref(), 2),
+            (mesh.lightmap_uvs.as_deref(), 2),
+            (mesh.colors.as_deref(), 4),
+            (mesh.skin_weights.as_deref(), MAX_INFLUENCES),
+        ]
+        .into_iter()
+        .filter_map(|(values, width)| values.map(|v| (v, width)))
+        .collect();
+        Self { mesh, others }
+    }
+
+    fn joints(&self, i: usize) -> &[u32] {
+        self.mesh
+            .skin_joints
+            .as_deref()
+            .map(|j| &j[i * MAX_INFLUENCES..(i + 1) * MAX_INFLUENCES])
+            .unwrap_or(&[])
+    }
+
+    /// Exact key of a vertex; -0.0 and 0.0 compare equal
+    fn bits(&self, i: usize) -> Vec<u32> {
+        let mut key: Vec<u32> = self.mesh.positions[i * 3..i * 3 + 3]
+            .iter()
+            .chain(
+                self.others
+                    .iter()
+                    .flat_map(|&(values, width)| &values[i * width..(i + 1) * width]),
+            )
+            .map(|v| (v + 0.0).to_bits())
+            .collect();
+        key.extend_from_slice(self.joints(i));
+        key
+    }
+
+    fn matches(&self, a: usize, b: usize, tolerance: f32) -> bool {
+        let p = &self.mesh.positions;
+        let distance_sq: f32 = (0..3)
+            .map(|axis| (p[a * 3 + axis] - p[b * 3 + axis]).powi(2))
+            .sum();
+        distance_sq <= tolerance * tolerance
+            && self.others.iter().all(|&(values, width)| {
+                (0..width)
+                    .all(|k| (values[a * width + k] - values[b * width + k]).abs() <= tolerance)
+            })
+            && self.joints(a) == self.joints(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reindex_welds_only_matching_vertices() {
+        // A quad written as two triangles with one vertex per corner; the
+        // corners on the diagonal match except that one has a different UV
+        let mesh = MeshData {
+            positions: vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, // first
+                0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, // second
+            ],
+            uvs: Some(vec![
+                0.0, 0.0, 1.0, 0.0, 1.0, 1.0, // first
+                0.0, 0.0, 0.5, 1.0, 0.0, 1.0, // second
+            ]),
+            indices: (0..6).collect(),
+            ..Default::default()
+        };
+
+        let exact = reindex(&mesh, 0.0);
+        assert_eq!(exact.mesh.vertex_count(), 5);
+        assert_eq!(exact.mesh.indices, vec![0, 1, 2, 0, 3, 4]);
+        assert_eq!(exact.removed_face_count, 0);
+        assert!(exact.mesh.validate().is_ok());
+
+        // A loose tolerance also welds the corner whose UV differs
+        let loose = reindex(&mesh, 0.6);
+        assert_eq!(loose.mesh.vertex_count(), 4);
+        assert_eq!(loose.remap[4], loose.remap[2]);
+    }
+}
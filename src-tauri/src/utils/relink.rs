@@ -0,0 +1,346 @@
+//! Pointing broken references at files that moved
+//!
+//! A missing dependency is looked for by file name, ignoring case, under the
+//! folders the user suggests. Several files with that name are fine as long
+//! as they have the same contents; differing ones are left for the user to
+//! pick from. glTF, OBJ and MTL references are rewritten to the found file.
+//! Buffers must live in a glTF file's folder and binary FBX files can't be
+//! edited in place, so for those the found file is copied to where the
+//! reference expects it instead.
+
+use crate::utils::analysis_cache::ModelSource;
+use crate::utils::dependencies::{mtl_references, obj_references, Dependency, DependencyKind};
+use crate::utils::glb_writer::container;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+use xxhash_rust::xxh3::xxh3_64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelinkStatus {
+    /// The reference was rewritten to the found file
+    Relinked,
+    /// The found file was copied to where the reference points
+    Copied,
+    /// Files with the name differ in contents
+    Ambiguous,
+    NotFound,
+}
+
+/// What happened to one missing dependency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Relink {
+    pub kind: DependencyKind,
+    pub reference: String,
+    pub referenced_by: String,
+    pub status: RelinkStatus,
+    /// The file the dependency now resolves to
+    pub found: Option<String>,
+    /// Reference written in place of the old one, when it changed
+    pub new_reference: Option<String>,
+    /// Distinct files with the right name, when the match is ambiguous
+    pub candidates: Vec<String>,
+}
+
+/// How to repair one dependency
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fix {
+    Rewrite {
+        new_reference: String,
+    },
+    Copy {
+        to: PathBuf,
+        /// Set when the copy doesn't land where the reference points
+        new_reference: Option<String>,
+    },
+}
+
+/// File name a reference asks for, lowercased for matching
+pub fn wanted_name(reference: &str) -> Option<String> {
+    let name = reference.replace('\\', "/");
+    let name = name.rsplit('/').next()?.trim();
+    (!name.is_empty()).then(|| name.to_lowercase())
+}
+
+/// Files under `dirs` whose lowercased names are in `names`, by name
+pub fn find_candidates(dirs: &[PathBuf], names: &HashSet<String>) -> HashMap<String, Vec<PathBuf>> {
+    let mut found: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for dir in dirs {
+        for entry in WalkDir::new(dir).into_iter().flatten() {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            if names.contains(&name) {
+                let paths = found.entry(name).or_default();
+                if !paths.iter().any(|p| p == entry.path()) {
+                    paths.push(entry.path().to_path_buf());
+                }
+            }
+        }
+    }
+    found
+}
+
+/// The single file among `candidates`, treating identical copies as one;
+/// otherwise the distinct ones
+pub fn choose(candidates: &[PathBuf]) -> Result<PathBuf, Vec<PathBuf>> {
+    let mut distinct: Vec<(u64, u64, &PathBuf)> = Vec::new();
+    for path in candidates {
+        let Ok(bytes) = fs::read(path) else {
+            continue;
+        };
+        let key = (bytes.len() as u64, xxh3_64(&bytes));
+        if !distinct.iter().any(|(len, hash, _)| (*len, *hash) == key) {
+            distinct.push((key.0, key.1, path));
+        }
+    }
+    match distinct.as_slice() {
+        [(_, _, only)] => Ok((*only).clone()),
+        _ => Err(distinct.into_iter().map(|(_, _, p)| p.clone()).collect()),
+    }
+}
+
+/// How to make `dependency` resolve to `found`
+pub fn fix(dependency: &Dependency, found: &Path) -> Fix {
+    let referrer = Path::new(&dependency.referenced_by);
+    let dir = referrer.parent().unwrap_or(Path::new(""));
+    let name = found.file_name().unwrap_or_default();
+    let is_fbx = referrer
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("fbx"));
+    let relative = relative_path(dir, found);
+    let inside = relative
+        .as_ref()
+        .is_some_and(|r| !r.starts_with("../") && r != "..");
+    if is_fbx || (dependency.kind == DependencyKind::Buffer && !inside) {
+        // Relative references are restored in place. Absolute ones point at
+        // another machine, so the file goes beside the referring one, where
+        // FBX importers look too
+        return match dependency.relative_path {
+            Some(_) => Fix::Copy {
+                to: PathBuf::from(&dependency.path),
+                new_reference: None,
+            },
+            None => Fix::Copy {
+                to: dir.join(name),
+                new_reference: (!is_fbx).then(|| encode_uri(&name.to_string_lossy())),
+            },
+        };
+    }
+    let new_reference = relative.unwrap_or_else(|| found.to_string_lossy().replace('\\', "/"));
+    let new_reference = if is_gltf(referrer) {
+        encode_uri(&new_reference)
+    } else {
+        new_reference
+    };
+    Fix::Rewrite { new_reference }
+}
+
+/// A glTF JSON document with each `uri` in `replacements` swapped
+pub fn rewrite_gltf(json: &[u8], replacements: &HashMap<String, String>) -> Result<Value, String> {
+    let mut document: Value =
+        serde_json::from_slice(json).map_err(|e| format!("Failed to parse glTF JSON: {}", e))?;
+    for key in ["buffers", "images"] {
+        let Some(items) = document.get_mut(key).and_then(Value::as_array_mut) else {
+            continue;
+        };
+        for item in items {
+            let new = item["uri"].as_str().and_then(|uri| replacements.get(uri));
+            if let Some(new) = new.cloned() {
+                item["uri"] = Value::String(new);
+            }
+        }
+    }
+    Ok(document)
+}
+
+/// An OBJ or MTL file with each reference in `replacements` swapped on the
+/// lines that name it, leaving everything else untouched
+pub fn rewrite_lines(text: &str, is_mtl: bool, replacements: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let references = if is_mtl {
+            mtl_references(line)
+        } else {
+            obj_references(line)
+        };
+        let mut line = line.to_string();
+        for (_, reference) in references {
+            let Some(new) = replacements.get(&reference) else {
+                continue;
+            };
+            if let Some(at) = line.rfind(&reference) {
+                line.replace_range(at..at + reference.len(), new);
+            }
+        }
+        out.push_str(&line);
+    }
+    out
+}
+
+/// Contents of `referrer` with the references in `replacements` swapped
+pub fn rewritten(
+    referrer: &Path,
+    replacements: &HashMap<String, String>,
+) -> Result<Vec<u8>, String> {
+    let extension = referrer
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "gltf" | "glb" => {
+            let source = ModelSource::open(referrer)?;
+            let document = rewrite_gltf(source.json(), replacements)?;
+            if extension == "glb" {
+                container(&document, source.bin().unwrap_or_default())
+            } else {
+                serde_json::to_vec_pretty(&document)
+                    .map_err(|e| format!("Failed to serialize glTF: {}", e))
+            }
+        }
+        "obj" | "mtl" => {
+            let bytes = fs::read(referrer)
+                .map_err(|e| format!("Failed to read {}: {}", referrer.display(), e))?;
+            let text = String::from_utf8_lossy(&bytes);
+            Ok(rewrite_lines(&text, extension == "mtl", replacements).into_bytes())
+        }
+        _ => Err(format!(
+            "References in {} can't be rewritten",
+            referrer.display()
+        )),
+    }
+}
+
+fn is_gltf(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("gltf") || e.eq_ignore_ascii_case("glb"))
+}
+
+/// `to` relative to the folder `from`, with `/` separators; `None` when they
+/// don't share a root, such as on different drives
+pub(crate) fn relative_path(from: &Path, to: &Path) -> Option<String> {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    if from.first() != to.first() {
+        return None;
+    }
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let parts: Vec<String> = std::iter::repeat_n("..".to_string(), from.len() - common)
+        .chain(
+            to[common..]
+                .iter()
+                .map(|c| c.as_os_str().to_string_lossy().to_string()),
+        )
+        .collect();
+    Some(parts.join("/"))
+}
+
+/// Percent-encode the characters that can't appear as is in a URI reference
+pub(crate) fn encode_uri(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/!$&'()*+,;=:@".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependency(kind: DependencyKind, referenced_by: &str, reference: &str) -> Dependency {
+        let dir = Path::new(referenced_by).parent().unwrap();
+        Dependency {
+            kind,
+            reference: reference.to_string(),
+            referenced_by: referenced_by.to_string(),
+            path: dir.join(reference).to_string_lossy().to_string(),
+            relative_path: Some(reference.to_string()),
+            exists: false,
+            size_bytes: None,
+        }
+    }
+
+    #[test]
+    fn test_moved_textures_are_found_and_relinked() {
+        let dir = std::env::temp_dir().join(format!("sweedle-relink-{}", std::process::id()));
+        for folder in ["a", "b", "c"] {
+            fs::create_dir_all(dir.join(folder)).unwrap();
+        }
+        fs::write(dir.join("a/Bark.png"), b"bark").unwrap();
+        fs::write(dir.join("b/bark.png"), b"bark").unwrap();
+        fs::write(dir.join("a/leaf.png"), b"leaf").unwrap();
+        fs::write(dir.join("c/leaf.png"), b"other leaf").unwrap();
+
+        let names = HashSet::from(["bark.png".to_string(), "leaf.png".to_string()]);
+        let found = find_candidates(std::slice::from_ref(&dir), &names);
+        assert_eq!(found["bark.png"].len(), 2);
+        // Identical copies count as one, differing ones can't be told apart
+        let bark = choose(&found["bark.png"]).unwrap();
+        assert_eq!(choose(&found["leaf.png"]).unwrap_err().len(), 2);
+        assert_eq!(
+            wanted_name("C:\\art\\Bark.png").as_deref(),
+            Some("bark.png")
+        );
+
+        let model = dir.join("models/tree.gltf");
+        let image = dependency(
+            DependencyKind::Image,
+            &model.to_string_lossy(),
+            "tex/bark.png",
+        );
+        let new_reference = match fix(&image, &dir.join("a/my bark.png")) {
+            Fix::Rewrite { new_reference } => new_reference,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(new_reference, "../a/my%20bark.png");
+        let buffer = dependency(DependencyKind::Buffer, &model.to_string_lossy(), "tree.bin");
+        assert_eq!(
+            fix(&buffer, &dir.join("b/tree.bin")),
+            Fix::Copy {
+                to: dir.join("models/tree.bin"),
+                new_reference: None,
+            }
+        );
+        let fbx = dependency(
+            DependencyKind::Texture,
+            &dir.join("models/tree.fbx").to_string_lossy(),
+            "tex/bark.png",
+        );
+        assert_eq!(
+            fix(&fbx, &bark),
+            Fix::Copy {
+                to: dir.join("models/tex/bark.png"),
+                new_reference: None,
+            }
+        );
+
+        let replacements =
+            HashMap::from([("tex/bark.png".to_string(), "../a/bark.png".to_string())]);
+        let document = rewrite_gltf(
+            br#"{"images":[{"uri":"tex/bark.png"},{"uri":"leaf.png"}]}"#,
+            &replacements,
+        )
+        .unwrap();
+        assert_eq!(document["images"][0]["uri"], "../a/bark.png");
+        assert_eq!(document["images"][1]["uri"], "leaf.png");
+        assert_eq!(
+            rewrite_lines(
+                "newmtl bark\r\nmap_Kd -bm 1 tex/bark.png\r\nKd tex/bark.png\r\n",
+                true,
+                &replacements
+            ),
+            "newmtl bark\r\nmap_Kd -bm 1 ../a/bark.png\r\nKd tex/bark.png\r\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,187 @@
+//! Patterns for renaming assets in bulk
+//!
+//! A pattern such as `{name}_{index:3}` or `prop_{format}_{polycount}`
+//! gives each asset its new id. Placeholders:
+//!
+//! - `{name}`: the asset's current id
+//! - `{index}`: its 1-based position among the renamed assets, and
+//!   `{index:N}` the same padded with zeros to N digits
+//! - `{format}`: extension of its model, or image for a texture asset
+//! - `{polycount}`: triangles in its model
+//! - `{category}`: its category, or `uncategorized`
+
+use crate::utils::storage_layout::UNCATEGORIZED;
+
+/// Longest id a pattern may produce
+const MAX_ID_LEN: usize = 200;
+/// Widest `{index:N}`
+const MAX_INDEX_WIDTH: usize = 9;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    Name,
+    /// With the number of digits to pad to
+    Index(usize),
+    Format,
+    Polycount,
+    Category,
+}
+
+/// A parsed rename pattern
+#[derive(Debug, Clone)]
+pub struct RenamePattern {
+    tokens: Vec<Token>,
+}
+
+/// What the placeholders of a pattern stand for, for one asset
+#[derive(Debug, Clone, Default)]
+pub struct RenameValues<'a> {
+    pub name: &'a str,
+    pub index: usize,
+    pub format: &'a str,
+    /// `None` when not needed or not readable
+    pub polycount: Option<u64>,
+    pub category: Option<&'a str>,
+}
+
+impl RenamePattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("Invalid rename pattern {}: {}", pattern, reason);
+        let mut tokens = Vec::new();
+        let mut rest = pattern;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                tokens.push(Token::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| invalid("unclosed {"))?;
+            let token = match &rest[start + 1..start + end] {
+                "name" => Token::Name,
+                "index" => Token::Index(0),
+                "format" => Token::Format,
+                "polycount" => Token::Polycount,
+                "category" => Token::Category,
+                other => match other.strip_prefix("index:").map(str::parse::<usize>) {
+                    Some(Ok(width)) if (1..=MAX_INDEX_WIDTH).contains(&width) => {
+                        Token::Index(width)
+                    }
+                    Some(_) => {
+                        return Err(invalid(&format!(
+                            "{{index:N}} pads to 1 to {} digits",
+                            MAX_INDEX_WIDTH
+                        )))
+                    }
+                    None => return Err(invalid(&format!("unknown placeholder {{{}}}", other))),
+                },
+            };
+            tokens.push(token);
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            tokens.push(Token::Literal(rest.to_string()));
+        }
+
+        let literals: String = tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Literal(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        if tokens.is_empty() {
+            return Err(invalid("empty"));
+        }
+        if let Some(c) = literals.chars().find(|&c| !allowed(c)) {
+            return Err(invalid(&format!("ids can't contain {:?}", c)));
+        }
+        Ok(Self { tokens })
+    }
+
+    /// Whether `polycount` must be filled in
+    pub fn needs_polycount(&self) -> bool {
+        self.tokens.contains(&Token::Polycount)
+    }
+
+    /// The new id of an asset
+    pub fn fill(&self, values: &RenameValues) -> Result<String, String> {
+        let mut id = String::new();
+        for token in &self.tokens {
+            match token {
+                Token::Literal(text) => id.push_str(text),
+                Token::Name => id.push_str(values.name),
+                Token::Index(width) => id.push_str(&format!("{:0w$}", values.index, w = *width)),
+                Token::Format => id.push_str(values.format),
+                Token::Polycount => {
+                    let count = values
+                        .polycount
+                        .ok_or_else(|| format!("Can't count the triangles of {}", values.name))?;
+                    id.push_str(&count.to_string());
+                }
+                Token::Category => id.push_str(
+                    &values
+                        .category
+                        .filter(|c| !c.is_empty())
+                        .unwrap_or(UNCATEGORIZED)
+                        .replace(['/', '\\'], "_"),
+                ),
+            }
+        }
+        let id: String = id.chars().filter(|&c| allowed(c)).collect();
+        let id = id.trim().trim_end_matches('.');
+        if id.is_empty() || id.starts_with('.') {
+            return Err(format!("{} would be renamed to {:?}", values.name, id));
+        }
+        if id.len() > MAX_ID_LEN {
+            return Err(format!(
+                "{} would get an id longer than {} characters",
+                values.name, MAX_ID_LEN
+            ));
+        }
+        Ok(id.to_string())
+    }
+}
+
+/// Whether `c` can be part of an id on every platform
+fn allowed(c: char) -> bool {
+    !c.is_control() && !matches!(c, '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_placeholders_and_reject_bad_patterns() {
+        let values = RenameValues {
+            name: "rock",
+            index: 7,
+            format: "glb",
+            polycount: Some(1200),
+            category: Some("Nature/Stones"),
+        };
+        let fill = |pattern: &str| RenamePattern::parse(pattern).unwrap().fill(&values);
+        assert_eq!(fill("{name}_{index:3}").unwrap(), "rock_007");
+        assert_eq!(
+            fill("{category}-{format}-{polycount}").unwrap(),
+            "Nature_Stones-glb-1200"
+        );
+        assert_eq!(fill("{index} ").unwrap(), "7");
+        assert!(!RenamePattern::parse("{name}").unwrap().needs_polycount());
+        let polycount = RenamePattern::parse("{name}_{polycount}").unwrap();
+        assert!(polycount.needs_polycount());
+        assert!(polycount
+            .fill(&RenameValues {
+                polycount: None,
+                ..values.clone()
+            })
+            .is_err());
+
+        for bad in ["", "{nam}", "{name", "a/{name}", "{index:0}", "{index:x}"] {
+            assert!(RenamePattern::parse(bad).is_err(), "{}", bad);
+        }
+        // A pattern that leaves nothing usable fails per asset
+        assert!(fill("...").is_err());
+    }
+}
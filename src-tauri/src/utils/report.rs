@@ -0,0 +1,344 @@
+//! Per-asset review reports in CSV or JSON
+//!
+//! CSV has one row per asset with a fixed set of columns so a whole delivery
+//! can be sorted and filtered in a spreadsheet; JSON keeps the same fields
+//! nested.
+
+use crate::utils::i18n::{localize_in, Locale};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+/// Result of each check run against an asset
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationResult {
+    /// The model parsed and its buffers loaded
+    pub parses: bool,
+    pub has_geometry: bool,
+    pub has_normals: bool,
+    pub has_uvs: bool,
+    pub no_degenerate_faces: bool,
+    /// Limits of the validation profile the asset breaks; `None` when no
+    /// profile was applied
+    #[serde(default)]
+    pub profile_violations: Option<Vec<String>>,
+    /// Textures encoded for the wrong color space; `None` when the model's
+    /// textures weren't checked
+    #[serde(default)]
+    pub color_space_issues: Option<Vec<String>>,
+}
+
+impl ValidationResult {
+    fn checks(&self) -> Vec<bool> {
+        let mut checks = vec![
+            self.parses,
+            self.has_geometry,
+            self.has_normals,
+            self.has_uvs,
+            self.no_degenerate_faces,
+        ];
+        if let Some(violations) = &self.profile_violations {
+            checks.push(violations.is_empty());
+        }
+        if let Some(issues) = &self.color_space_issues {
+            checks.push(issues.is_empty());
+        }
+        checks
+    }
+
+    /// Percentage of checks passed, staying within the profile counting as
+    /// one when a profile was applied
+    pub fn score(&self) -> u32 {
+        let checks = self.checks();
+        let passed = checks.iter().filter(|&&c| c).count();
+        (passed * 100 / checks.len()) as u32
+    }
+}
+
+/// Everything reported for one asset
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssetReport {
+    pub id: String,
+    /// Model that was analyzed, if the asset has a GLB/GLTF
+    pub model_path: Option<String>,
+    /// Model formats present for the asset (`glb`, `obj`, ...)
+    pub formats: Vec<String>,
+    pub file_size_bytes: u64,
+    pub vertex_count: usize,
+    pub face_count: usize,
+    pub mesh_count: usize,
+    pub material_count: usize,
+    pub has_textures: bool,
+    /// Longest side of the largest texture in pixels, 0 without textures
+    #[serde(default)]
+    pub max_texture_size: u32,
+    pub bounds_min: Option<[f32; 3]>,
+    pub bounds_max: Option<[f32; 3]>,
+    pub validation: ValidationResult,
+    /// Validation profile the asset was checked against
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub score: u32,
+    /// Why analysis failed, if it did
+    pub error: Option<String>,
+}
+
+/// Top level of a JSON report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportDocument {
+    pub generated_ms: u64,
+    pub assets: Vec<AssetReport>,
+}
+
+const CSV_HEADER: [&str; 26] = [
+    "id",
+    "model_path",
+    "formats",
+    "file_size_bytes",
+    "vertex_count",
+    "face_count",
+    "mesh_count",
+    "material_count",
+    "has_textures",
+    "max_texture_size",
+    "min_x",
+    "min_y",
+    "min_z",
+    "max_x",
+    "max_y",
+    "max_z",
+    "parses",
+    "has_geometry",
+    "has_normals",
+    "has_uvs",
+    "no_degenerate_faces",
+    "profile",
+    "profile_violations",
+    "color_space_issues",
+    "score",
+    "error",
+];
+
+/// Render a report in `format`
+pub fn render(
+    assets: &[AssetReport],
+    format: ReportFormat,
+    generated_ms: u64,
+) -> Result<String, String> {
+    match format {
+        ReportFormat::Csv => Ok(to_csv(assets)),
+        ReportFormat::Json => serde_json::to_string_pretty(&ReportDocument {
+            generated_ms,
+            assets: assets.to_vec(),
+        })
+        .map_err(|e| format!("Failed to serialize report: {}", e)),
+    }
+}
+
+fn to_csv(assets: &[AssetReport]) -> String {
+    let mut out = CSV_HEADER.join(",");
+    out.push_str("\r\n");
+
+    for asset in assets {
+        let axis = |bounds: Option<[f32; 3]>, i: usize| {
+            bounds.map(|b| b[i].to_string()).unwrap_or_default()
+        };
+        let v = &asset.validation;
+        let row = [
+            asset.id.clone(),
+            asset.model_path.clone().unwrap_or_default(),
+            asset.formats.join(";"),
+            asset.file_size_bytes.to_string(),
+            asset.vertex_count.to_string(),
+            asset.face_count.to_string(),
+            asset.mesh_count.to_string(),
+            asset.material_count.to_string(),
+            asset.has_textures.to_string(),
+            asset.max_texture_size.to_string(),
+            axis(asset.bounds_min, 0),
+            axis(asset.bounds_min, 1),
+            axis(asset.bounds_min, 2),
+            axis(asset.bounds_max, 0),
+            axis(asset.bounds_max, 1),
+            axis(asset.bounds_max, 2),
+            v.parses.to_string(),
+            v.has_geometry.to_string(),
+            v.has_normals.to_string(),
+            v.has_uvs.to_string(),
+            v.no_degenerate_faces.to_string(),
+            asset.profile.clone().unwrap_or_default(),
+            v.profile_violations
+                .as_ref()
+                .map(|violations| violations.join(";"))
+                .unwrap_or_default(),
+            v.color_space_issues
+                .as_ref()
+                .map(|issues| issues.join(";"))
+                .unwrap_or_default(),
+            asset.score.to_string(),
+            asset.error.clone().unwrap_or_default(),
+        ];
+        let fields: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// A Markdown summary of one asset for pasting into chat or an issue,
+/// written in `locale`
+pub fn markdown(asset: &AssetReport, locale: Locale) -> String {
+    let t = |text: &str| localize_in(locale, text);
+    let count = |value: usize| locale.integer(value as u64);
+    let mut rows = vec![("Formats", asset.formats.join(", "))];
+    if let Some(model) = &asset.model_path {
+        rows.insert(0, ("File", format!("`{}`", model)));
+    }
+    if let Some(error) = &asset.error {
+        rows.push(("Error", t(error).replace('|', "\\|")));
+    }
+    if asset.validation.parses {
+        rows.extend([
+            ("Size", locale.file_size(asset.file_size_bytes)),
+            ("Vertices", count(asset.vertex_count)),
+            ("Faces", count(asset.face_count)),
+            ("Meshes", count(asset.mesh_count)),
+            ("Materials", count(asset.material_count)),
+            ("Textures", t(if asset.has_textures { "yes" } else { "no" })),
+        ]);
+    }
+    if let (Some(min), Some(max)) = (asset.bounds_min, asset.bounds_max) {
+        let [x, y, z] = [0, 1, 2].map(|i| locale.decimal((max[i] - min[i]) as f64, 3));
+        rows.push(("Dimensions", format!("{} × {} × {}", x, y, z)));
+    }
+    let v = &asset.validation;
+    let failed: Vec<String> = [
+        (v.parses, "parses"),
+        (v.has_geometry, "geometry"),
+        (v.has_normals, "normals"),
+        (v.has_uvs, "UVs"),
+        (v.no_degenerate_faces, "no degenerate faces"),
+        (
+            v.profile_violations.as_ref().is_none_or(Vec::is_empty),
+            "profile",
+        ),
+        (
+            v.color_space_issues.as_ref().is_none_or(Vec::is_empty),
+            "color spaces",
+        ),
+    ]
+    .into_iter()
+    .filter(|(passed, _)| !passed)
+    .map(|(_, check)| t(check))
+    .collect();
+    if let (Some(profile), Some(violations)) = (&asset.profile, &v.profile_violations) {
+        let result = if violations.is_empty() {
+            t("within limits")
+        } else {
+            let violations: Vec<String> = violations.iter().map(|v| t(v)).collect();
+            violations.join(", ")
+        };
+        rows.push(("Profile", format!("{}: {}", profile, result)));
+    }
+    if let Some(issues) = v.color_space_issues.as_ref().filter(|i| !i.is_empty()) {
+        let issues: Vec<String> = issues.iter().map(|issue| t(issue)).collect();
+        rows.push(("Color spaces", issues.join(", ").replace('|', "\\|")));
+    }
+    let checks = if failed.is_empty() {
+        t(&format!("{}%", asset.score))
+    } else {
+        t(&format!("{}% (failed: {})", asset.score, failed.join(", ")))
+    };
+    rows.push(("Checks", checks));
+
+    let mut out = format!(
+        "**{}**\n\n| {} | {} |\n| --- | --- |\n",
+        asset.id,
+        t("Property"),
+        t("Value")
+    );
+    for (name, value) in rows {
+        out.push_str(&format!("| {} | {} |\n", t(name), value));
+    }
+    out
+}
+
+/// Quote a field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_escapes_fields_and_matches_header() {
+        let asset = AssetReport {
+            id: "crate, \"large\"".to_string(),
+            formats: vec!["glb".to_string(), "obj".to_string()],
+            bounds_min: Some([-1.0, 0.0, -1.0]),
+            bounds_max: Some([1.0, 2.0, 1.0]),
+            validation: ValidationResult {
+                parses: true,
+                has_geometry: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let csv = render(&[asset], ReportFormat::Csv, 0).unwrap();
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+
+        assert!(lines[0].starts_with("id,model_path,formats,"));
+        assert!(lines[1].starts_with("\"crate, \"\"large\"\"\",,glb;obj,"));
+        assert!(lines[1].contains(",-1,0,-1,1,2,1,true,true,false,false,false,"));
+        // Commas inside the quoted id don't count as separators
+        assert_eq!(
+            lines[1].matches(',').count(),
+            lines[0].matches(',').count() + 1
+        );
+        assert_eq!(ValidationResult::default().score(), 0);
+    }
+
+    #[test]
+    fn test_markdown_summary() {
+        let asset = AssetReport {
+            id: "rock".to_string(),
+            model_path: Some("/lib/rock/rock.glb".to_string()),
+            formats: vec!["glb".to_string()],
+            file_size_bytes: 3 << 20,
+            vertex_count: 1_234_567,
+            bounds_min: Some([-1.0, 0.0, -1.0]),
+            bounds_max: Some([1.0, 2.0, 1.0]),
+            validation: ValidationResult {
+                parses: true,
+                has_geometry: true,
+                has_normals: true,
+                no_degenerate_faces: true,
+                ..Default::default()
+            },
+            score: 80,
+            ..Default::default()
+        };
+        let summary = markdown(&asset, Locale::En);
+        assert!(summary.starts_with("**rock**\n\n| Property | Value |\n"));
+        assert!(summary.contains("| File | `/lib/rock/rock.glb` |\n"));
+        assert!(summary.contains("| Size | 3.0 MB |\n"));
+        assert!(summary.contains("| Vertices | 1,234,567 |\n"));
+        assert!(summary.contains("| Dimensions | 2.000 × 2.000 × 2.000 |\n"));
+        assert!(summary.ends_with("| Checks | 80% (failed: UVs) |\n"));
+        let summary = markdown(&asset, Locale::De);
+        assert!(summary.contains("| Eigenschaft | Wert |\n"));
+        assert!(summary.contains("| Eckpunkte | 1.234.567 |\n"));
+        assert!(summary.ends_with("| Prüfungen | 80 % (nicht bestanden: UVs) |\n"));
+    }
+}
@@ -0,0 +1,624 @@
+//! T-pose and A-pose: telling them apart and converting between them
+//!
+//! The upper arms are found by name among the skin joints ("LeftArm",
+//! "upperarm_l", "Arm.R" ...), and their side from the joint's position:
+//! glTF characters face +Z, so the left arm is on +X. An arm's pose is the
+//! angle its upper arm drops below horizontal in the bind pose, taken from
+//! the inverse bind matrices.
+//!
+//! Converting rotates each upper arm about its shoulder joint to the
+//! target angle, keeping its heading. The skinned vertices, normals and
+//! morph targets are deformed with the rig, the arm joints' inverse bind
+//! matrices follow, and the nodes' rest transforms are updated. Animations
+//! store absolute joint rotations, so they play as before.
+
+use crate::utils::gltf_document::{items_mut, GltfDocument};
+use crate::utils::pose::{skin_weights, Pose};
+use nalgebra::{Matrix3, Matrix4, Point3, Rotation3, UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Arms within this many degrees of horizontal are in a T-pose
+const T_POSE_TOLERANCE_DEGREES: f32 = 15.0;
+/// Arms dropping more than this aren't in an A-pose either
+const A_POSE_MAX_DEGREES: f32 = 65.0;
+/// Drop angle of the arms after converting to an A-pose
+const A_POSE_DEGREES: f32 = 45.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestPoseKind {
+    TPose,
+    APose,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArmPose {
+    pub side: Side,
+    /// Node of the upper arm joint
+    pub joint: usize,
+    pub name: Option<String>,
+    /// Angle of the upper arm below horizontal; negative if raised
+    pub drop_degrees: f32,
+    pub kind: RestPoseKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestPoseAnalysis {
+    /// The arms' pose if they agree, `other` if not; `None` without arms
+    pub kind: Option<RestPoseKind>,
+    pub arms: Vec<ArmPose>,
+}
+
+/// An upper arm joint, with the joint it points at
+struct Arm {
+    side: Side,
+    shoulder: usize,
+    elbow: usize,
+}
+
+/// The rest pose of the document's skeleton
+pub fn analyze(document: &GltfDocument) -> RestPoseAnalysis {
+    let bind = bind_worlds(document);
+    let arms: Vec<ArmPose> = find_arms(document, &bind)
+        .into_iter()
+        .map(|arm| {
+            let direction = position(&bind[arm.elbow]) - position(&bind[arm.shoulder]);
+            let drop_degrees = drop_angle(&direction).to_degrees();
+            ArmPose {
+                side: arm.side,
+                joint: arm.shoulder,
+                name: document.json["nodes"][arm.shoulder]["name"]
+                    .as_str()
+                    .map(str::to_string),
+                drop_degrees,
+                kind: classify(drop_degrees),
+            }
+        })
+        .collect();
+    let kind = arms.first().map(|first| {
+        if arms.iter().all(|arm| arm.kind == first.kind) {
+            first.kind
+        } else {
+            RestPoseKind::Other
+        }
+    });
+    RestPoseAnalysis { kind, arms }
+}
+
+/// Re-pose the skeleton's rest pose into `target` and return the new pose
+pub fn convert(
+    document: &mut GltfDocument,
+    target: RestPoseKind,
+) -> Result<RestPoseAnalysis, String> {
+    let angle = match target {
+        RestPoseKind::TPose => 0.0f32,
+        RestPoseKind::APose => A_POSE_DEGREES.to_radians(),
+        RestPoseKind::Other => {
+            return Err("Rest poses convert to a T-pose or an A-pose".to_string())
+        }
+    };
+    let bind = bind_worlds(document);
+    let arms = find_arms(document, &bind);
+    if arms.is_empty() {
+        return Err("No upper arm joints found in the skeleton".to_string());
+    }
+    let rest = Pose::rest(document);
+    let rest_world = rest.world(document);
+    let parents = parents(document);
+
+    // The rotation of each arm, about its shoulder, in bind space
+    let mut deltas: Vec<Option<Matrix4<f32>>> = vec![None; bind.len()];
+    let mut locals = rest.locals.clone();
+    for arm in &arms {
+        let shoulder = position(&bind[arm.shoulder]);
+        let direction = position(&bind[arm.elbow]) - shoulder;
+        let mut heading = Vector3::new(direction.x, 0.0, direction.z);
+        if heading.norm() < 1e-6 {
+            heading = Vector3::x() * if arm.side == Side::Left { 1.0 } else { -1.0 };
+        }
+        let wanted = heading.normalize() * angle.cos() - Vector3::y() * angle.sin();
+        let rotation = Rotation3::rotation_between(&direction, &wanted)
+            .ok_or_else(|| format!("Can't turn the arm at node {}", arm.shoulder))?
+            .to_homogeneous();
+        let about = |pivot: Vector3<f32>| {
+            Matrix4::new_translation(&pivot) * rotation * Matrix4::new_translation(&-pivot)
+        };
+        let delta = about(shoulder);
+        for node in subtree(document, arm.shoulder) {
+            if let Some(slot) = deltas.get_mut(node) {
+                *slot = Some(delta);
+            }
+        }
+        let posed = about(position(&rest_world[arm.shoulder])) * rest_world[arm.shoulder];
+        let parent = parents[arm.shoulder]
+            .map(|p| rest_world[p])
+            .unwrap_or_else(Matrix4::identity);
+        locals[arm.shoulder] = parent.try_inverse().unwrap_or_else(Matrix4::identity) * posed;
+    }
+
+    deform_skinned_meshes(document, &deltas)?;
+    move_inverse_binds(document, &deltas)?;
+    for arm in &arms {
+        if let Some(node) = items_mut(&mut document.json, "nodes").nth(arm.shoulder) {
+            set_local_transform(node, &locals[arm.shoulder]);
+        }
+    }
+    document.compact();
+    Ok(analyze(document))
+}
+
+/// Apply each vertex's blend of joint `deltas` to the skinned primitives
+fn deform_skinned_meshes(
+    document: &mut GltfDocument,
+    deltas: &[Option<Matrix4<f32>>],
+) -> Result<(), String> {
+    let mut skinned: Vec<(usize, usize)> = document.json["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|node| {
+            Some((
+                node["mesh"].as_u64()? as usize,
+                node["skin"].as_u64()? as usize,
+            ))
+        })
+        .collect();
+    skinned.sort_unstable();
+    skinned.dedup();
+    let skins = document.json["skins"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let mut meshes = document.json["meshes"].take();
+    let mut replaced: HashMap<(usize, usize, usize), usize> = HashMap::new();
+
+    for (mesh, skin) in skinned {
+        let joints: Vec<usize> = skins
+            .get(skin)
+            .and_then(|s| s["joints"].as_array())
+            .into_iter()
+            .flatten()
+            .map(|j| j.as_u64().unwrap_or(u64::MAX) as usize)
+            .collect();
+        let Some(mesh) = meshes.get_mut(mesh) else {
+            continue;
+        };
+        for primitive in items_mut(mesh, "primitives") {
+            let Some(position) = attribute(primitive, "POSITION") else {
+                continue;
+            };
+            let count = document.read_floats(position)?.values.len() / 3;
+            let influences = skin_weights(document, primitive, count)?;
+            let blends: Vec<Option<Matrix4<f32>>> = influences
+                .iter()
+                .map(|influence| blend(influence, &joints, deltas))
+                .collect();
+            if blends.iter().all(Option::is_none) {
+                continue;
+            }
+            let key = |accessor: usize| (accessor, position, skin);
+            let mut rewrite = |accessor: usize, kind: Deform| -> Result<usize, String> {
+                if let Some(&done) = replaced.get(&key(accessor)) {
+                    return Ok(done);
+                }
+                let data = document.read_floats(accessor)?;
+                let mut values = data.values;
+                for (vertex, element) in values.chunks_mut(data.width).enumerate() {
+                    if let Some(Some(matrix)) = blends.get(vertex) {
+                        kind.apply(matrix, element);
+                    }
+                }
+                let kind_name = if data.width == 4 { "VEC4" } else { "VEC3" };
+                let bounds = matches!(kind, Deform::Point | Deform::Offset);
+                let pushed = document.push_floats(&values, kind_name, bounds);
+                replaced.insert(key(accessor), pushed);
+                Ok(pushed)
+            };
+
+            for (name, kind) in [
+                ("POSITION", Deform::Point),
+                ("NORMAL", Deform::Direction),
+                ("TANGENT", Deform::Direction),
+            ] {
+                if let Some(accessor) = attribute(primitive, name) {
+                    primitive["attributes"][name] = json!(rewrite(accessor, kind)?);
+                }
+            }
+            for target in items_mut(primitive, "targets") {
+                for (name, kind) in [("POSITION", Deform::Offset), ("NORMAL", Deform::Offset)] {
+                    if let Some(accessor) = target.get(name).and_then(Value::as_u64) {
+                        target[name] = json!(rewrite(accessor as usize, kind)?);
+                    }
+                }
+            }
+        }
+    }
+    if !meshes.is_null() {
+        document.json["meshes"] = meshes;
+    }
+    Ok(())
+}
+
+/// Give the moved joints the inverse bind matrices of their new place
+fn move_inverse_binds(
+    document: &mut GltfDocument,
+    deltas: &[Option<Matrix4<f32>>],
+) -> Result<(), String> {
+    let skins = document.json["skins"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    for (index, skin) in skins.iter().enumerate() {
+        let joints: Vec<usize> = skin["joints"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|j| j.as_u64().unwrap_or(u64::MAX) as usize)
+            .collect();
+        if !joints
+            .iter()
+            .any(|&j| deltas.get(j).is_some_and(Option::is_some))
+        {
+            continue;
+        }
+        let mut matrices = match skin["inverseBindMatrices"].as_u64() {
+            Some(accessor) => document.read_floats(accessor as usize)?.values,
+            None => Vec::new(),
+        };
+        matrices.resize(joints.len() * 16, 0.0);
+        for (k, &joint) in joints.iter().enumerate() {
+            let slot = &mut matrices[k * 16..(k + 1) * 16];
+            let inverse_bind = if skin["inverseBindMatrices"].is_u64() {
+                Matrix4::from_column_slice(slot)
+            } else {
+                Matrix4::identity()
+            };
+            let moved = match deltas.get(joint).copied().flatten() {
+                Some(delta) => inverse_bind * delta.try_inverse().unwrap_or_else(Matrix4::identity),
+                None => inverse_bind,
+            };
+            slot.copy_from_slice(moved.as_slice());
+        }
+        let accessor = document.push_floats(&matrices, "MAT4", false);
+        if let Some(skin) = items_mut(&mut document.json, "skins").nth(index) {
+            skin["inverseBindMatrices"] = json!(accessor);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Deform {
+    /// Positions: the full transform
+    Point,
+    /// Normals and tangents: rotated and renormalized
+    Direction,
+    /// Morph target deltas: rotated only
+    Offset,
+}
+
+impl Deform {
+    fn apply(self, matrix: &Matrix4<f32>, element: &mut [f32]) {
+        let v = Vector3::new(element[0], element[1], element[2]);
+        let moved = match self {
+            Deform::Point => matrix.transform_point(&Point3::from(v)).coords,
+            Deform::Direction => {
+                let d = matrix.transform_vector(&v);
+                if d.norm() > 0.0 {
+                    d.normalize()
+                } else {
+                    d
+                }
+            }
+            Deform::Offset => matrix.transform_vector(&v),
+        };
+        element[..3].copy_from_slice(moved.as_slice());
+    }
+}
+
+/// A vertex's weighted blend of its joints' deltas, the unmoved joints
+/// counting as identity; `None` if none of its joints moved
+fn blend(
+    influences: &[(usize, f32)],
+    joints: &[usize],
+    deltas: &[Option<Matrix4<f32>>],
+) -> Option<Matrix4<f32>> {
+    let delta = |k: usize| {
+        joints
+            .get(k)
+            .and_then(|&j| deltas.get(j).copied().flatten())
+    };
+    if !influences.iter().any(|&(k, _)| delta(k).is_some()) {
+        return None;
+    }
+    let total: f32 = influences.iter().map(|(_, w)| w).sum();
+    Some(
+        influences
+            .iter()
+            .map(|&(k, w)| delta(k).unwrap_or_else(Matrix4::identity) * (w / total))
+            .fold(Matrix4::zeros(), |sum, m| sum + m),
+    )
+}
+
+/// World transforms of the rest pose, with each skin joint placed where
+/// its inverse bind matrix says it was bound
+fn bind_worlds(document: &GltfDocument) -> Vec<Matrix4<f32>> {
+    let mut world = Pose::rest(document).world(document);
+    for skin in document.json["skins"].as_array().into_iter().flatten() {
+        let Some(matrices) = skin["inverseBindMatrices"]
+            .as_u64()
+            .and_then(|a| document.read_floats(a as usize).ok())
+        else {
+            continue;
+        };
+        for (k, joint) in skin["joints"].as_array().into_iter().flatten().enumerate() {
+            let (Some(joint), Some(matrix)) = (
+                joint
+                    .as_u64()
+                    .map(|j| j as usize)
+                    .filter(|&j| j < world.len()),
+                matrices.values.get(k * 16..(k + 1) * 16),
+            ) else {
+                continue;
+            };
+            if let Some(bound) = Matrix4::from_column_slice(matrix).try_inverse() {
+                world[joint] = bound;
+            }
+        }
+    }
+    world
+}
+
+/// The upper arm joint of each side, nearest the root, that has a child
+/// joint
+fn find_arms(document: &GltfDocument, bind: &[Matrix4<f32>]) -> Vec<Arm> {
+    let joints: Vec<usize> = document.json["skins"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|skin| skin["joints"].as_array().cloned().unwrap_or_default())
+        .filter_map(|j| j.as_u64().map(|j| j as usize))
+        .filter(|&j| j < bind.len())
+        .collect();
+    let parents = parents(document);
+    let depth = |mut node: usize| {
+        let mut depth = 0;
+        while let Some(parent) = parents[node].filter(|_| depth < parents.len()) {
+            node = parent;
+            depth += 1;
+        }
+        depth
+    };
+
+    let mut arms: Vec<(Side, usize, usize, usize)> = Vec::new();
+    for &joint in &joints {
+        let name = document.json["nodes"][joint]["name"]
+            .as_str()
+            .unwrap_or_default();
+        if !is_upper_arm(name) {
+            continue;
+        }
+        let Some(elbow) = document.json["nodes"][joint]["children"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|c| c.as_u64().map(|c| c as usize))
+            .find(|c| joints.contains(c))
+        else {
+            continue;
+        };
+        let x = position(&bind[joint]).x;
+        let side = match x {
+            x if x > 1e-6 => Side::Left,
+            x if x < -1e-6 => Side::Right,
+            _ => continue,
+        };
+        let depth = depth(joint);
+        match arms.iter_mut().find(|arm| arm.0 == side) {
+            Some(arm) if arm.3 <= depth => {}
+            Some(arm) => *arm = (side, joint, elbow, depth),
+            None => arms.push((side, joint, elbow, depth)),
+        }
+    }
+    arms.sort_by_key(|arm| arm.0 == Side::Right);
+    arms.into_iter()
+        .map(|(side, shoulder, elbow, _)| Arm {
+            side,
+            shoulder,
+            elbow,
+        })
+        .collect()
+}
+
+/// Whether a joint name is an upper arm's, ignoring case and separators
+fn is_upper_arm(name: &str) -> bool {
+    let name: String = name
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    name.contains("arm")
+        && !["forearm", "lowerarm", "armature", "twist", "roll", "armpit"]
+            .iter()
+            .any(|other| name.contains(other))
+}
+
+fn classify(drop_degrees: f32) -> RestPoseKind {
+    if drop_degrees.abs() <= T_POSE_TOLERANCE_DEGREES {
+        RestPoseKind::TPose
+    } else if drop_degrees <= A_POSE_MAX_DEGREES && drop_degrees > 0.0 {
+        RestPoseKind::APose
+    } else {
+        RestPoseKind::Other
+    }
+}
+
+/// Radians `direction` points below horizontal (+Y is up)
+fn drop_angle(direction: &Vector3<f32>) -> f32 {
+    (-direction.y / direction.norm().max(f32::EPSILON))
+        .clamp(-1.0, 1.0)
+        .asin()
+}
+
+fn position(matrix: &Matrix4<f32>) -> Vector3<f32> {
+    Vector3::new(matrix[(0, 3)], matrix[(1, 3)], matrix[(2, 3)])
+}
+
+fn parents(document: &GltfDocument) -> Vec<Option<usize>> {
+    let nodes = document.json["nodes"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let mut parents = vec![None; nodes.len()];
+    for (index, node) in nodes.iter().enumerate() {
+        for child in node["children"].as_array().into_iter().flatten() {
+            if let Some(slot) = child.as_u64().and_then(|c| parents.get_mut(c as usize)) {
+                *slot = Some(index);
+            }
+        }
+    }
+    parents
+}
+
+/// `root` and every node below it
+fn subtree(document: &GltfDocument, root: usize) -> Vec<usize> {
+    let mut nodes = vec![root];
+    let mut next = 0;
+    while let Some(&node) = nodes.get(next) {
+        for child in document.json["nodes"][node]["children"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            if let Some(child) = child.as_u64().map(|c| c as usize) {
+                if !nodes.contains(&child) {
+                    nodes.push(child);
+                }
+            }
+        }
+        next += 1;
+    }
+    nodes
+}
+
+/// Store `matrix` in a node as translation, rotation and scale
+fn set_local_transform(node: &mut Value, matrix: &Matrix4<f32>) {
+    let translation = position(matrix);
+    let linear = matrix.fixed_view::<3, 3>(0, 0).into_owned();
+    let scale = Vector3::new(
+        linear.column(0).norm(),
+        linear.column(1).norm(),
+        linear.column(2).norm(),
+    );
+    let rotation = Matrix3::from_columns(&[
+        linear.column(0) / scale.x.max(f32::EPSILON),
+        linear.column(1) / scale.y.max(f32::EPSILON),
+        linear.column(2) / scale.z.max(f32::EPSILON),
+    ]);
+    let rotation = UnitQuaternion::from_matrix(&rotation);
+    let Some(node) = node.as_object_mut() else {
+        return;
+    };
+    node.remove("matrix");
+    node.insert("translation".to_string(), json!(translation.as_slice()));
+    node.insert(
+        "rotation".to_string(),
+        json!([rotation.i, rotation.j, rotation.k, rotation.w]),
+    );
+    node.insert("scale".to_string(), json!(scale.as_slice()));
+}
+
+fn attribute(primitive: &Value, name: &str) -> Option<usize> {
+    primitive["attributes"][name].as_u64().map(|a| a as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    /// Shoulders at (±0.2, 1.5, 0) with forearms 0.3 away, 45° down, and a
+    /// triangle skinned to the left upper arm
+    fn a_posed() -> GltfDocument {
+        let mut document = GltfDocument {
+            json: json!({ "asset": { "version": "2.0" } }),
+            bin: Vec::new(),
+        };
+        let reach = 0.3 * FRAC_1_SQRT_2;
+        let elbow = [0.2 + reach, 1.5 - reach, 0.0];
+        let positions = [0.2, 1.5, 0.0, elbow[0], elbow[1], 0.0, 0.2, 1.5, 0.1];
+        let position = document.push_floats(&positions, "VEC3", true);
+        let joints = document.push_ints(&[1; 12], "VEC4");
+        let weights = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+        let weights = document.push_floats(&weights, "VEC4", false);
+        let worlds = [
+            [0.0, 1.0, 0.0],
+            [0.2, 1.5, 0.0],
+            elbow,
+            [-0.2, 1.5, 0.0],
+            [-elbow[0], elbow[1], 0.0],
+        ];
+        let inverse_binds: Vec<f32> = worlds
+            .iter()
+            .flat_map(|p| {
+                Matrix4::new_translation(&-Vector3::new(p[0], p[1], p[2]))
+                    .as_slice()
+                    .to_vec()
+            })
+            .collect();
+        let inverse_binds = document.push_floats(&inverse_binds, "MAT4", false);
+        document.json["nodes"] = json!([
+            { "name": "Hips", "translation": [0.0, 1.0, 0.0], "children": [1, 3] },
+            { "name": "mixamorig:LeftArm", "translation": [0.2, 0.5, 0.0], "children": [2] },
+            { "name": "mixamorig:LeftForeArm", "translation": [reach, -reach, 0.0] },
+            { "name": "mixamorig:RightArm", "translation": [-0.2, 0.5, 0.0], "children": [4] },
+            { "name": "mixamorig:RightForeArm", "translation": [-reach, -reach, 0.0] },
+            { "name": "Body", "mesh": 0, "skin": 0 },
+        ]);
+        document.json["meshes"] = json!([{ "primitives": [{
+            "attributes": { "POSITION": position, "JOINTS_0": joints, "WEIGHTS_0": weights },
+        }] }]);
+        document.json["skins"] =
+            json!([{ "joints": [0, 1, 2, 3, 4], "inverseBindMatrices": inverse_binds }]);
+        document
+    }
+
+    #[test]
+    fn test_detects_and_converts_a_pose_to_t_pose() {
+        let mut document = a_posed();
+        let before = analyze(&document);
+        assert_eq!(before.kind, Some(RestPoseKind::APose));
+        assert_eq!(before.arms.len(), 2);
+        assert_eq!(before.arms[0].side, Side::Left);
+        assert_eq!(before.arms[0].joint, 1);
+        assert!((before.arms[1].drop_degrees - 45.0).abs() < 1e-3);
+
+        let after = convert(&mut document, RestPoseKind::TPose).unwrap();
+        assert_eq!(after.kind, Some(RestPoseKind::TPose));
+        assert!(after.arms.iter().all(|arm| arm.drop_degrees.abs() < 1e-3));
+
+        // The elbow vertex swings up level with the shoulder
+        let mesh = &document.json["meshes"][0]["primitives"][0]["attributes"]["POSITION"];
+        let positions = document
+            .read_floats(mesh.as_u64().unwrap() as usize)
+            .unwrap();
+        let expected = [0.2, 1.5, 0.0, 0.5, 1.5, 0.0, 0.2, 1.5, 0.1];
+        for (a, b) in positions.values.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-5, "{:?}", positions.values);
+        }
+        // and the rest pose puts the forearm joint there too
+        let world = Pose::rest(&document).world(&document);
+        assert!((position(&world[2]) - Vector3::new(0.5, 1.5, 0.0)).norm() < 1e-5);
+        assert!(convert(&mut document, RestPoseKind::Other).is_err());
+    }
+}
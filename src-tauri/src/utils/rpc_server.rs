@@ -0,0 +1,598 @@
+//! JSON-RPC over HTTP for running without a window and for other tools
+//!
+//! Started with `--headless`, the app opens no window and answers JSON-RPC
+//! 2.0 requests POSTed to `/rpc`, so render farms and build machines can
+//! drive the processing pipeline. With the local API turned on, a windowed
+//! app answers the same requests on loopback for plugins in Blender or
+//! Unreal. `/v1/rpc` answers version 1 of the API, whose methods keep their
+//! parameters and results; `/rpc` answers the newest version. Every request
+//! must carry the server's token as `Authorization: Bearer <token>`;
+//! `GET /health` answers without one so supervisors can check the server
+//! is up. Requests are plain HTTP, so the server listens on loopback unless
+//! told otherwise.
+
+use crate::utils::api_schema::API_VERSION;
+use crate::utils::ar_preview;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+pub const HEADLESS_FLAG: &str = "--headless";
+pub const TOKEN_ENV: &str = "SWEEDLE_RPC_TOKEN";
+/// Where the server listens unless `--listen` says otherwise
+const DEFAULT_LISTEN: &str = "127.0.0.1:7420";
+/// Largest request head read before giving up on a client
+const MAX_HEAD_BYTES: usize = 8 * 1024;
+/// Largest request body accepted
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+/// How long a client may take to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Tokens shorter than this are too easy to guess
+const MIN_TOKEN_LEN: usize = 16;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The command ran and returned an error
+const COMMAND_FAILED: i64 = -32000;
+
+/// How a headless run was asked to serve
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadlessOptions {
+    pub listen: SocketAddr,
+    /// Folders approved for RPC calls on top of those already approved
+    pub allow: Vec<PathBuf>,
+}
+
+impl HeadlessOptions {
+    /// Options from the command line, or `None` without `--headless`
+    ///
+    /// `--listen <address:port>` sets where to listen and each
+    /// `--allow <folder>` approves a folder that calls may read and write.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Result<Self, String>> {
+        let args: Vec<String> = args.into_iter().skip(1).collect();
+        if !args.iter().any(|arg| arg == HEADLESS_FLAG) {
+            return None;
+        }
+        let mut options = Self {
+            listen: DEFAULT_LISTEN.parse().unwrap(),
+            allow: Vec::new(),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                HEADLESS_FLAG => {}
+                "--listen" => match value().and_then(|v| {
+                    v.parse()
+                        .map_err(|_| format!("Not an address and port: {}", v))
+                }) {
+                    Ok(listen) => options.listen = listen,
+                    Err(e) => return Some(Err(e)),
+                },
+                "--allow" => match value() {
+                    Ok(folder) => options.allow.push(PathBuf::from(folder)),
+                    Err(e) => return Some(Err(e)),
+                },
+                other => return Some(Err(format!("Unknown headless option {}", other))),
+            }
+        }
+        Some(Ok(options))
+    }
+}
+
+/// The token clients must send: `SWEEDLE_RPC_TOKEN` if set, otherwise the
+/// one saved in `token_file`, made and saved there the first time
+pub fn load_token(token_file: &Path) -> Result<String, String> {
+    if let Ok(token) = std::env::var(TOKEN_ENV) {
+        let token = token.trim().to_string();
+        if token.len() < MIN_TOKEN_LEN {
+            return Err(format!(
+                "{} must be at least {} characters",
+                TOKEN_ENV, MIN_TOKEN_LEN
+            ));
+        }
+        return Ok(token);
+    }
+    if let Ok(saved) = std::fs::read_to_string(token_file) {
+        let saved = saved.trim();
+        if saved.len() >= MIN_TOKEN_LEN {
+            // Token files used to be saved readable by everyone
+            if let Err(e) = make_private(token_file) {
+                log::warn!("Failed to restrict {}: {}", token_file.display(), e);
+            }
+            return Ok(saved.to_string());
+        }
+    }
+    let token = ar_preview::token()?;
+    if let Some(parent) = token_file.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    write_private(token_file, &token)
+        .map_err(|e| format!("Failed to write {}: {}", token_file.display(), e))?;
+    Ok(token)
+}
+
+/// Write `contents` to `path`, readable only by its owner on Unix
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    // The mode only applies to a file being created
+    make_private(path)?;
+    file.write_all(contents.as_bytes())
+}
+
+#[cfg(unix)]
+fn make_private(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+}
+
+/// Files in the user's profile are private to them already
+#[cfg(not(unix))]
+fn make_private(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Why a call produced no result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcError {
+    MethodNotFound(String),
+    InvalidParams(String),
+    /// The command's own error
+    Failed(String),
+}
+
+impl RpcError {
+    fn to_json(&self) -> Value {
+        let (code, message) = match self {
+            Self::MethodNotFound(method) => (METHOD_NOT_FOUND, format!("No method {}", method)),
+            Self::InvalidParams(message) => (INVALID_PARAMS, message.clone()),
+            Self::Failed(message) => (COMMAND_FAILED, message.clone()),
+        };
+        json!({ "code": code, "message": message })
+    }
+}
+
+pub type RpcFuture = Pin<Box<dyn Future<Output = Result<Value, RpcError>> + Send>>;
+/// Runs a method with its named parameters
+pub type Dispatch = Arc<dyn Fn(String, Value) -> RpcFuture + Send + Sync>;
+
+#[derive(Debug, Clone, Deserialize)]
+struct Call {
+    jsonrpc: Option<String>,
+    method: Option<String>,
+    #[serde(default)]
+    params: Value,
+    /// Absent for notifications, which get no response
+    id: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Value>,
+    id: Value,
+}
+
+fn response(id: Value, outcome: Result<Value, Value>) -> Value {
+    let (result, error) = match outcome {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+    serde_json::to_value(Response {
+        jsonrpc: "2.0",
+        result,
+        error,
+        id,
+    })
+    .unwrap_or(Value::Null)
+}
+
+fn error(code: i64, message: &str) -> Value {
+    json!({ "code": code, "message": message })
+}
+
+/// The calls in a request body and whether it was a batch, or the response
+/// telling the client its body made no sense
+fn parse_calls(body: &[u8]) -> Result<(Vec<Result<Call, Value>>, bool), Value> {
+    let parsed: Value = serde_json::from_slice(body)
+        .map_err(|e| response(Value::Null, Err(error(PARSE_ERROR, &e.to_string()))))?;
+    let (items, batch) = match parsed {
+        Value::Array(items) if !items.is_empty() => (items, true),
+        Value::Array(_) => {
+            return Err(response(
+                Value::Null,
+                Err(error(INVALID_REQUEST, "Empty batch")),
+            ))
+        }
+        item => (vec![item], false),
+    };
+    let calls = items
+        .into_iter()
+        .map(|item| {
+            let id = item.get("id").cloned().unwrap_or(Value::Null);
+            match serde_json::from_value::<Call>(item) {
+                Ok(call) if call.jsonrpc.as_deref() == Some("2.0") && call.method.is_some() => {
+                    Ok(call)
+                }
+                _ => Err(response(
+                    id,
+                    Err(error(INVALID_REQUEST, "Not a JSON-RPC 2.0 request")),
+                )),
+            }
+        })
+        .collect();
+    Ok((calls, batch))
+}
+
+/// Run the calls in a request body; `None` when all were notifications
+async fn run_calls(body: &[u8], dispatch: &Dispatch) -> Option<Value> {
+    let (calls, batch) = match parse_calls(body) {
+        Ok(parsed) => parsed,
+        Err(response) => return Some(response),
+    };
+    let mut responses = Vec::new();
+    for call in calls {
+        let call = match call {
+            Ok(call) => call,
+            Err(response) => {
+                responses.push(response);
+                continue;
+            }
+        };
+        let params = match call.params {
+            Value::Null => Value::Object(Default::default()),
+            params @ Value::Object(_) => params,
+            _ => {
+                let invalid = RpcError::InvalidParams(
+                    "Params must be an object of named arguments".to_string(),
+                );
+                responses.push(response(
+                    call.id.unwrap_or(Value::Null),
+                    Err(invalid.to_json()),
+                ));
+                continue;
+            }
+        };
+        let method = call.method.unwrap_or_default();
+        let outcome = dispatch(method, params).await;
+        if let Some(id) = call.id {
+            responses.push(response(id, outcome.map_err(|e| e.to_json())));
+        }
+    }
+    match (batch, responses.len()) {
+        (_, 0) => None,
+        (false, _) => responses.pop(),
+        (true, _) => Some(Value::Array(responses)),
+    }
+}
+
+/// Where a running server can be reached
+#[derive(Debug, Clone)]
+pub struct ServerAddress {
+    pub address: SocketAddr,
+    pub url: String,
+}
+
+struct Running {
+    url: String,
+    stop: oneshot::Sender<()>,
+}
+
+/// The RPC server, managed as Tauri state; at most one runs at a time
+#[derive(Default)]
+pub struct RpcServer {
+    running: Arc<Mutex<Option<Running>>>,
+}
+
+impl RpcServer {
+    /// Listen on `listen` and answer requests until `stop`, replacing any
+    /// server already running
+    ///
+    /// Returns once listening; the server itself runs on the async runtime.
+    pub async fn start(
+        &self,
+        listen: SocketAddr,
+        token: String,
+        dispatch: Dispatch,
+    ) -> Result<ServerAddress, String> {
+        self.stop();
+        let listener = TcpListener::bind(listen)
+            .await
+            .map_err(|e| format!("Failed to listen on {}: {}", listen, e))?;
+        let address = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to read the server address: {}", e))?;
+        if !address.ip().is_loopback() {
+            log::warn!(
+                "RPC server on {} is reachable from the network over plain HTTP",
+                address
+            );
+        }
+        let url = format!("http://{}/v{}/rpc", address, API_VERSION);
+
+        let (stop, stopped) = oneshot::channel();
+        *self.running.lock().unwrap() = Some(Running {
+            url: url.clone(),
+            stop,
+        });
+        let (served_url, running) = (url.clone(), self.running.clone());
+        tokio::spawn(async move {
+            serve(listener, token.into(), dispatch, stopped).await;
+            let mut running = running.lock().unwrap();
+            if running.as_ref().is_some_and(|r| r.url == served_url) {
+                *running = None;
+            }
+        });
+        Ok(ServerAddress { address, url })
+    }
+
+    /// Stop the running server; `false` if none was running
+    pub fn stop(&self) -> bool {
+        match self.running.lock().unwrap().take() {
+            Some(running) => {
+                let _ = running.stop.send(());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn url(&self) -> Option<String> {
+        self.running.lock().unwrap().as_ref().map(|r| r.url.clone())
+    }
+}
+
+async fn serve(
+    listener: TcpListener,
+    token: Arc<str>,
+    dispatch: Dispatch,
+    mut stopped: oneshot::Receiver<()>,
+) {
+    loop {
+        tokio::select! {
+            _ = &mut stopped => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let (token, dispatch) = (token.clone(), dispatch.clone());
+                    tokio::spawn(async move {
+                        if let Err(e) = respond(stream, &token, &dispatch).await {
+                            log::debug!("RPC request failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => log::warn!("RPC server failed to accept: {}", e),
+            },
+        }
+    }
+}
+
+/// Whether `path` is where RPC requests go: `/rpc`, or `/v<N>/rpc` for a
+/// version this server answers
+fn is_rpc_path(path: &str) -> bool {
+    path == "/rpc"
+        || path
+            .strip_prefix("/v")
+            .and_then(|rest| rest.strip_suffix("/rpc"))
+            .and_then(|version| version.parse::<u32>().ok())
+            == Some(API_VERSION)
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct RequestHead {
+    method: String,
+    path: String,
+    content_length: usize,
+    authorization: Option<String>,
+}
+
+fn parse_head(head: &str) -> Option<RequestHead> {
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let mut parsed = RequestHead {
+        method: request_line.next()?.to_string(),
+        path: request_line.next()?.split('?').next()?.to_string(),
+        ..Default::default()
+    };
+    for line in lines.take_while(|line| !line.is_empty()) {
+        let (name, value) = line.split_once(':')?;
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            parsed.content_length = value.parse().ok()?;
+        } else if name.eq_ignore_ascii_case("authorization") {
+            parsed.authorization = Some(value.to_string());
+        }
+    }
+    Some(parsed)
+}
+
+/// Whether the request carries `token`, compared in constant time
+fn authorized(head: &RequestHead, token: &str) -> bool {
+    let Some(sent) = head
+        .authorization
+        .as_deref()
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    sent.len() == token.len()
+        && sent
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn respond(mut stream: TcpStream, token: &str, dispatch: &Dispatch) -> std::io::Result<()> {
+    let request = tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await;
+    let (status, body) = match request {
+        Err(_) => ("408 Request Timeout", None),
+        Ok(Err(e)) => return Err(e),
+        Ok(Ok(None)) => ("400 Bad Request", None),
+        Ok(Ok(Some((head, _)))) if head.content_length > MAX_BODY_BYTES => {
+            ("413 Payload Too Large", None)
+        }
+        Ok(Ok(Some((head, body)))) => match (head.method.as_str(), head.path.as_str()) {
+            ("GET", "/health") => (
+                "200 OK",
+                Some(json!({ "status": "ok", "api_version": API_VERSION })),
+            ),
+            (_, path) if !is_rpc_path(path) => ("404 Not Found", None),
+            _ if !authorized(&head, token) => ("401 Unauthorized", None),
+            ("POST", _) => match run_calls(&body, dispatch).await {
+                Some(response) => ("200 OK", Some(response)),
+                None => ("204 No Content", None),
+            },
+            _ => ("405 Method Not Allowed", None),
+        },
+    };
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Sweedle-Api-Version: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        API_VERSION,
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// The head and body of a request, `None` if it isn't valid HTTP; the body
+/// is left unread when it's over the limit
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<(RequestHead, Vec<u8>)>> {
+    let mut received = Vec::new();
+    let mut buffer = [0u8; 8 * 1024];
+    let head_end = loop {
+        if let Some(end) = received.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if received.len() > MAX_HEAD_BYTES {
+            return Ok(None);
+        }
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        received.extend_from_slice(&buffer[..read]);
+    };
+    let Some(head) = parse_head(&String::from_utf8_lossy(&received[..head_end])) else {
+        return Ok(None);
+    };
+    let mut body = received.split_off(head_end);
+    if head.content_length > MAX_BODY_BYTES {
+        return Ok(Some((head, Vec::new())));
+    }
+    while body.len() < head.content_length {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        body.extend_from_slice(&buffer[..read]);
+    }
+    body.truncate(head.content_length);
+    Ok(Some((head, body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_need_the_token_and_valid_calls() {
+        let args = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>();
+        assert!(HeadlessOptions::from_args(args("sweedle")).is_none());
+        let options = HeadlessOptions::from_args(args(
+            "sweedle --headless --listen 0.0.0.0:9000 --allow /farm/in",
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(options.listen.port(), 9000);
+        assert_eq!(options.allow, [PathBuf::from("/farm/in")]);
+        assert!(
+            HeadlessOptions::from_args(args("sweedle --headless --listen"))
+                .unwrap()
+                .is_err()
+        );
+        assert!(HeadlessOptions::from_args(args("sweedle --headless --gui"))
+            .unwrap()
+            .is_err());
+
+        let head = parse_head(
+            "POST /rpc?x=1 HTTP/1.1\r\nHost: farm\r\ncontent-length: 42\r\n\
+             Authorization: Bearer 0123456789abcdef\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!((head.method.as_str(), head.path.as_str()), ("POST", "/rpc"));
+        assert_eq!(head.content_length, 42);
+        assert!(authorized(&head, "0123456789abcdef"));
+        assert!(!authorized(&head, "0123456789abcdeg"));
+        assert!(!authorized(&RequestHead::default(), "0123456789abcdef"));
+        assert!(parse_head("POST /rpc HTTP/1.1\r\nContent-Length: lots\r\n\r\n").is_none());
+        assert!(is_rpc_path("/rpc") && is_rpc_path("/v1/rpc"));
+        assert!(!is_rpc_path("/v2/rpc") && !is_rpc_path("/v/rpc") && !is_rpc_path("/rpc/x"));
+
+        let (calls, batch) = parse_calls(
+            br#"[{"jsonrpc":"2.0","method":"get_library","id":1},
+                 {"jsonrpc":"2.0","method":"list_jobs"},
+                 {"method":"get_library","id":2}]"#,
+        )
+        .unwrap();
+        assert!(batch);
+        assert_eq!(calls[0].as_ref().unwrap().id, Some(json!(1)));
+        assert_eq!(calls[1].as_ref().unwrap().id, None);
+        let invalid = calls[2].as_ref().unwrap_err();
+        assert_eq!(
+            (invalid["id"].clone(), invalid["error"]["code"].clone()),
+            (json!(2), json!(INVALID_REQUEST))
+        );
+        let parse_failed = parse_calls(b"{not json").unwrap_err();
+        assert_eq!(parse_failed["error"]["code"], json!(PARSE_ERROR));
+        assert!(parse_calls(b"[]").is_err());
+
+        let failed = response(
+            json!("a"),
+            Err(RpcError::Failed("File not found".to_string()).to_json()),
+        );
+        assert_eq!(failed["error"]["code"], json!(COMMAND_FAILED));
+        assert!(failed.get("result").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_token_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("sweedle-rpc-token-{}", std::process::id()));
+        let file = dir.join("api-token");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&file, "short").unwrap();
+
+        let token = load_token(&file).unwrap();
+        assert!(token.len() >= MIN_TOKEN_LEN);
+        assert_eq!(load_token(&file).unwrap(), token);
+        let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,288 @@
+//! NaN, infinity and out-of-range data detection and cleanup
+//!
+//! A single NaN position poisons bounds, normals and the camera fit, and an
+//! index past the vertex buffer crashes the upload to the GPU, so the viewer
+//! breaks without saying why. Exporters also write sentinel coordinates
+//! like 1e30 for "unused" vertices, which wreck framing the same way.
+
+use crate::utils::mesh_store::MeshData;
+use serde::{Deserialize, Serialize};
+
+/// Coordinates past this magnitude (a thousand kilometers, in meters) are
+/// taken to be garbage rather than geometry
+pub const MAX_COORDINATE: f32 = 1e6;
+
+/// Bad data found in a mesh
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataIssues {
+    /// Vertices with a NaN or infinite position component
+    pub non_finite_vertex_count: usize,
+    /// Finite vertices beyond the coordinate limit on some axis
+    pub absurd_vertex_count: usize,
+    /// Indices that don't name a vertex
+    pub out_of_range_index_count: usize,
+    /// NaN or infinite normal, UV, color or skin weight components
+    pub non_finite_attribute_count: usize,
+}
+
+impl DataIssues {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn merge(&mut self, other: &DataIssues) {
+        self.non_finite_vertex_count += other.non_finite_vertex_count;
+        self.absurd_vertex_count += other.absurd_vertex_count;
+        self.out_of_range_index_count += other.out_of_range_index_count;
+        self.non_finite_attribute_count += other.non_finite_attribute_count;
+    }
+
+    /// Comma-separated list of the problems found, e.g. for an issue message
+    pub fn summary(&self) -> String {
+        [
+            (self.non_finite_vertex_count, "NaN or infinite positions"),
+            (self.absurd_vertex_count, "coordinates out of range"),
+            (self.out_of_range_index_count, "indices out of range"),
+            (
+                self.non_finite_attribute_count,
+                "NaN or infinite attribute values",
+            ),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, what)| format!("{} {}", count, what))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+/// What `sanitize` does with vertices whose position is bad
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SanitizeMode {
+    /// Remove the vertices and every face using them
+    #[default]
+    Drop,
+    /// Pull infinite and out-of-range coordinates back to the limit; NaN
+    /// vertices have no sensible place and are still dropped
+    Clamp,
+}
+
+/// What `sanitize` found and changed
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SanitizeReport {
+    pub issues: DataIssues,
+    pub dropped_face_count: usize,
+    pub removed_vertex_count: usize,
+    pub clamped_vertex_count: usize,
+    /// Bad attribute components set to zero
+    pub zeroed_attribute_count: usize,
+}
+
+/// Non-finite and out-of-range vertex counts for a stream of positions
+pub fn scan_positions(
+    positions: impl IntoIterator<Item = [f32; 3]>,
+    max_coordinate: f32,
+) -> (usize, usize) {
+    positions
+        .into_iter()
+        .fold((0, 0), |(non_finite, absurd), p| {
+            match classify(p, max_coordinate) {
+                Position::NonFinite => (non_finite + 1, absurd),
+                Position::Absurd => (non_finite, absurd + 1),
+                Position::Good => (non_finite, absurd),
+            }
+        })
+}
+
+/// Indices in the stream that don't name one of `vertex_count` vertices
+pub fn scan_indices(indices: impl IntoIterator<Item = u32>, vertex_count: usize) -> usize {
+    indices
+        .into_iter()
+        .filter(|&i| i as usize >= vertex_count)
+        .count()
+}
+
+/// Everything wrong with a mesh's data, without changing it
+pub fn scan(mesh: &MeshData, max_coordinate: f32) -> DataIssues {
+    let (non_finite_vertex_count, absurd_vertex_count) =
+        scan_positions(points(&mesh.positions), max_coordinate);
+    DataIssues {
+        non_finite_vertex_count,
+        absurd_vertex_count,
+        out_of_range_index_count: scan_indices(mesh.indices.iter().copied(), mesh.vertex_count()),
+        non_finite_attribute_count: attributes(mesh)
+            .flat_map(|values| values.iter())
+            .filter(|v| !v.is_finite())
+            .count(),
+    }
+}
+
+/// Remove or repair bad data so the mesh is safe to draw
+///
+/// Faces with an index out of range are dropped, bad positions are handled
+/// according to `mode`, and NaN or infinite attribute components are zeroed.
+/// Polygon information is dropped when any face is.
+pub fn sanitize(
+    mesh: &MeshData,
+    mode: SanitizeMode,
+    max_coordinate: f32,
+) -> (MeshData, SanitizeReport) {
+    let mut report = SanitizeReport {
+        issues: scan(mesh, max_coordinate),
+        ..Default::default()
+    };
+    let mut out = mesh.clone();
+    if report.issues.is_empty() {
+        return (out, report);
+    }
+
+    // Clamp first, so only what can't be clamped is dropped
+    let mut keep: Vec<bool> = Vec::with_capacity(mesh.vertex_count());
+    for p in out.positions.chunks_exact_mut(3) {
+        let position = classify([p[0], p[1], p[2]], max_coordinate);
+        let clampable = mode == SanitizeMode::Clamp && !p.iter().any(|v| v.is_nan());
+        if position != Position::Good && clampable {
+            for v in p.iter_mut() {
+                *v = v.clamp(-max_coordinate, max_coordinate);
+            }
+            report.clamped_vertex_count += 1;
+            keep.push(true);
+        } else {
+            keep.push(position == Position::Good);
+        }
+    }
+
+    for values in attributes_mut(&mut out) {
+        for v in values.iter_mut().filter(|v| !v.is_finite()) {
+            *v = 0.0;
+            report.zeroed_attribute_count += 1;
+        }
+    }
+
+    let vertex_count = out.vertex_count();
+    let mut indices = Vec::with_capacity(out.indices.len());
+    for face in out.indices.chunks_exact(3) {
+        if face
+            .iter()
+            .all(|&i| (i as usize) < vertex_count && keep[i as usize])
+        {
+            indices.extend_from_slice(face);
+        }
+    }
+    report.dropped_face_count = out.face_count() - indices.len() / 3;
+
+    // Renumber the vertices that are left
+    let mut remap = vec![u32::MAX; vertex_count];
+    let mut order = Vec::with_capacity(vertex_count);
+    for (v, _) in keep.iter().enumerate().filter(|(_, &kept)| kept) {
+        remap[v] = order.len() as u32;
+        order.push(v);
+    }
+    report.removed_vertex_count = vertex_count - order.len();
+    let indices = indices.into_iter().map(|i| remap[i as usize]).collect();
+    let polygons = out.polygons.take();
+    let mut out = out.with_vertices(&order, indices);
+    if report.dropped_face_count == 0 {
+        out.polygons = polygons;
+    }
+    (out, report)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Position {
+    Good,
+    NonFinite,
+    Absurd,
+}
+
+fn classify(p: [f32; 3], max_coordinate: f32) -> Position {
+    if p.iter().any(|v| !v.is_finite()) {
+        Position::NonFinite
+    } else if p.iter().any(|v| v.abs() > max_coordinate) {
+        Position::Absurd
+    } else {
+        Position::Good
+    }
+}
+
+fn points(positions: &[f32]) -> impl Iterator<Item = [f32; 3]> + '_ {
+    positions.chunks_exact(3).map(|p| [p[0], p[1], p[2]])
+}
+
+fn attributes(mesh: &MeshData) -> impl Iterator<Item = &Vec<f32>> {
+    [
+        &mesh.normals,
+        &mesh.uvs,
+        &mesh.lightmap_uvs,
+        &mesh.colors,
+        &mesh.skin_weights,
+    ]
+    .into_iter()
+    .flatten()
+}
+
+fn attributes_mut(mesh: &mut MeshData) -> impl Iterator<Item = &mut Vec<f32>> {
+    [
+        &mut mesh.normals,
+        &mut mesh.uvs,
+        &mut mesh.lightmap_uvs,
+        &mut mesh.colors,
+        &mut mesh.skin_weights,
+    ]
+    .into_iter()
+    .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_drops_or_clamps_bad_vertices() {
+        // Vertex 3 is NaN and vertex 4 an exporter's "unused" sentinel
+        let mesh = MeshData {
+            positions: [
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [f32::NAN, 0.0, 0.0],
+                [1e30, 0.0, 0.0],
+            ]
+            .concat(),
+            uvs: Some(
+                [
+                    [0.0, 0.0],
+                    [1.0, f32::INFINITY],
+                    [0.0, 1.0],
+                    [0.0; 2],
+                    [0.0; 2],
+                ]
+                .concat(),
+            ),
+            indices: vec![0, 1, 2, 0, 2, 3, 0, 1, 4, 0, 1, 9],
+            ..Default::default()
+        };
+
+        let issues = scan(&mesh, MAX_COORDINATE);
+        assert_eq!(issues.non_finite_vertex_count, 1);
+        assert_eq!(issues.absurd_vertex_count, 1);
+        assert_eq!(issues.out_of_range_index_count, 1);
+        assert_eq!(issues.non_finite_attribute_count, 1);
+
+        let (dropped, report) = sanitize(&mesh, SanitizeMode::Drop, MAX_COORDINATE);
+        assert_eq!(report.dropped_face_count, 3);
+        assert_eq!(report.removed_vertex_count, 2);
+        assert_eq!(report.zeroed_attribute_count, 1);
+        assert_eq!(dropped.indices, vec![0, 1, 2]);
+        assert!(dropped.validate().is_ok());
+        assert!(scan(&dropped, MAX_COORDINATE).is_empty());
+
+        // Clamping keeps the far vertex, pulled in to the limit
+        let (clamped, report) = sanitize(&mesh, SanitizeMode::Clamp, MAX_COORDINATE);
+        assert_eq!(report.clamped_vertex_count, 1);
+        assert_eq!(report.dropped_face_count, 2);
+        assert_eq!(clamped.indices, vec![0, 1, 2, 0, 1, 3]);
+        assert_eq!(&clamped.positions[9..12], &[MAX_COORDINATE, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,451 @@
+//! One-shot cleanup of photogrammetry and 3D scan meshes
+//!
+//! Scans come in with loose floaters, small holes where the camera didn't
+//! see, millions of noisy triangles and an arbitrary origin. The pipeline
+//! welds the mesh by position, then runs a fixed list of stages, each with
+//! its own report so the user can see which made a difference. Presets only
+//! choose how hard each stage works.
+
+use crate::utils::compute::cpu::CpuBackend;
+use crate::utils::compute::ComputeBackend;
+use crate::utils::decimate::cluster_decimate;
+use crate::utils::mesh_store::MeshData;
+use crate::utils::seams::UnionFind;
+use crate::utils::simd;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Taubin smoothing factors: shrink by `LAMBDA`, then grow back by `MU`
+const LAMBDA: f32 = 0.5;
+const MU: f32 = -0.53;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanPreset {
+    /// Only obvious debris and pinholes; keeps detail and density
+    Light,
+    #[default]
+    Standard,
+    /// Game-ready density with heavy smoothing, for noisy phone scans
+    Aggressive,
+}
+
+/// How hard each stage works
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CleanupSettings {
+    /// Pieces with fewer faces than this fraction of the largest are removed
+    pub min_shell_fraction: f32,
+    /// Holes with more boundary edges than this are left open
+    pub max_hole_edges: usize,
+    pub target_faces: usize,
+    pub smoothing_iterations: u32,
+    /// Largest dimension to rescale to, in meters; `None` keeps the size
+    pub fit_size: Option<f32>,
+}
+
+impl ScanPreset {
+    pub fn settings(self) -> CleanupSettings {
+        let (min_shell_fraction, max_hole_edges, target_faces, smoothing_iterations) = match self {
+            Self::Light => (0.001, 32, 500_000, 0),
+            Self::Standard => (0.01, 128, 200_000, 2),
+            Self::Aggressive => (0.05, 512, 50_000, 5),
+        };
+        CleanupSettings {
+            min_shell_fraction,
+            max_hole_edges,
+            target_faces,
+            smoothing_iterations,
+            fit_size: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupStage {
+    RemoveFloaters,
+    FillHoles,
+    Decimate,
+    Smooth,
+    Recenter,
+    GenerateNormals,
+    CompressTextures,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageReport {
+    pub stage: CleanupStage,
+    /// The stage had nothing to do or doesn't apply
+    pub skipped: bool,
+    pub detail: String,
+    /// Mesh size after the stage
+    pub vertex_count: usize,
+    pub face_count: usize,
+    pub elapsed_ms: f64,
+}
+
+pub struct Cleanup {
+    pub mesh: MeshData,
+    pub stages: Vec<StageReport>,
+}
+
+/// Run every stage over a scan's geometry
+///
+/// Only positions and faces are used; the result has generated normals and
+/// no other attributes.
+pub fn cleanup(mesh: &MeshData, settings: &CleanupSettings) -> Result<Cleanup, String> {
+    if mesh.indices.is_empty() {
+        return Err("Scan has no faces".to_string());
+    }
+    if let Some(size) = settings.fit_size {
+        if !(size > 0.0 && size.is_finite()) {
+            return Err(format!("Fit size must be a positive number, got {}", size));
+        }
+    }
+
+    let mut mesh = weld(&mesh.positions, &mesh.indices);
+    let mut stages = Vec::new();
+
+    run_stage(
+        &mut stages,
+        CleanupStage::RemoveFloaters,
+        &mut mesh,
+        &|mesh| {
+            let removed = remove_floaters(mesh, settings.min_shell_fraction);
+            Ok((removed == 0, format!("Removed {} loose pieces", removed)))
+        },
+    )?;
+    run_stage(&mut stages, CleanupStage::FillHoles, &mut mesh, &|mesh| {
+        let (filled, left_open) = fill_holes(mesh, settings.max_hole_edges);
+        Ok((
+            filled == 0,
+            format!(
+                "Filled {} holes, left {} larger than {} edges open",
+                filled, left_open, settings.max_hole_edges
+            ),
+        ))
+    })?;
+    run_stage(&mut stages, CleanupStage::Decimate, &mut mesh, &|mesh| {
+        let before = mesh.face_count();
+        if before <= settings.target_faces {
+            return Ok((
+                true,
+                format!("Already at or under {} faces", settings.target_faces),
+            ));
+        }
+        let decimated = cluster_decimate(&mesh.positions, &mesh.indices, settings.target_faces);
+        mesh.positions = decimated.positions;
+        mesh.indices = decimated.indices;
+        Ok((
+            false,
+            format!("Reduced {} faces to {}", before, mesh.face_count()),
+        ))
+    })?;
+    run_stage(&mut stages, CleanupStage::Smooth, &mut mesh, &|mesh| {
+        if settings.smoothing_iterations == 0 {
+            return Ok((true, "Smoothing is off in this preset".to_string()));
+        }
+        smooth(mesh, settings.smoothing_iterations);
+        Ok((
+            false,
+            format!("{} smoothing passes", settings.smoothing_iterations),
+        ))
+    })?;
+    run_stage(&mut stages, CleanupStage::Recenter, &mut mesh, &|mesh| {
+        recenter(mesh, settings.fit_size).map(|scale| {
+            (
+                false,
+                format!("Centered on the ground, scaled by {}", scale),
+            )
+        })
+    })?;
+    run_stage(
+        &mut stages,
+        CleanupStage::GenerateNormals,
+        &mut mesh,
+        &|mesh| {
+            mesh.normals = Some(CpuBackend.vertex_normals(&mesh.positions, &mesh.indices)?);
+            Ok((false, "Smooth vertex normals".to_string()))
+        },
+    )?;
+    run_stage(
+        &mut stages,
+        CleanupStage::CompressTextures,
+        &mut mesh,
+        &|_| {
+            Ok((
+                true,
+                "Scans are loaded without their textures, so there are none to compress"
+                    .to_string(),
+            ))
+        },
+    )?;
+
+    Ok(Cleanup { mesh, stages })
+}
+
+/// What a stage did: whether it was skipped, and a line about it
+type StageOutcome = Result<(bool, String), String>;
+
+/// Run one stage and add its report
+fn run_stage(
+    stages: &mut Vec<StageReport>,
+    stage: CleanupStage,
+    mesh: &mut MeshData,
+    work: &dyn Fn(&mut MeshData) -> StageOutcome,
+) -> Result<(), String> {
+    let started = Instant::now();
+    let (skipped, detail) = work(mesh)?;
+    stages.push(StageReport {
+        stage,
+        skipped,
+        detail,
+        vertex_count: mesh.vertex_count(),
+        face_count: mesh.face_count(),
+        elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+    });
+    Ok(())
+}
+
+/// One vertex per distinct position; faces collapsed by welding are dropped
+fn weld(positions: &[f32], indices: &[u32]) -> MeshData {
+    let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+    let mut points = Vec::new();
+    let ids: Vec<u32> = positions
+        .chunks_exact(3)
+        .map(|p| {
+            let next = welded.len() as u32;
+            *welded
+                .entry([p[0], p[1], p[2]].map(f32::to_bits))
+                .or_insert_with(|| {
+                    points.extend_from_slice(p);
+                    next
+                })
+        })
+        .collect();
+    MeshData {
+        positions: points,
+        indices: indices
+            .chunks_exact(3)
+            .map(|f| [f[0], f[1], f[2]].map(|i| ids[i as usize]))
+            .filter(|f| f[0] != f[1] && f[1] != f[2] && f[0] != f[2])
+            .flatten()
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Drop pieces smaller than `min_fraction` of the largest, returning how many
+fn remove_floaters(mesh: &mut MeshData, min_fraction: f32) -> usize {
+    let mut pieces = UnionFind::new(mesh.vertex_count());
+    for f in mesh.indices.chunks_exact(3) {
+        pieces.union(f[0], f[1]);
+        pieces.union(f[1], f[2]);
+    }
+    let roots: Vec<u32> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| pieces.find(f[0]))
+        .collect();
+    let mut sizes: HashMap<u32, usize> = HashMap::new();
+    for &root in &roots {
+        *sizes.entry(root).or_default() += 1;
+    }
+    let largest = sizes.values().copied().max().unwrap_or(0);
+    let keep = |root: &u32| sizes[root] as f32 >= largest as f32 * min_fraction;
+    let removed = sizes.keys().filter(|root| !keep(root)).count();
+    if removed > 0 {
+        let indices = mesh
+            .indices
+            .chunks_exact(3)
+            .zip(&roots)
+            .filter(|(_, root)| keep(root))
+            .flat_map(|(f, _)| f.iter().copied())
+            .collect();
+        compact(mesh, indices);
+    }
+    removed
+}
+
+/// Cap holes of up to `max_edges` edges with a fan around their center
+///
+/// Returns the holes filled and the boundary loops left open.
+fn fill_holes(mesh: &mut MeshData, max_edges: usize) -> (usize, usize) {
+    let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+    for f in mesh.indices.chunks_exact(3) {
+        for k in 0..3 {
+            let (a, b) = (f[k], f[(k + 1) % 3]);
+            *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+    }
+    // A hole runs against the faces around it, so its caps match their winding
+    let mut hole_edges: HashMap<u32, Vec<u32>> = HashMap::new();
+    for f in mesh.indices.chunks_exact(3) {
+        for k in 0..3 {
+            let (a, b) = (f[k], f[(k + 1) % 3]);
+            if edges[&(a.min(b), a.max(b))] == 1 {
+                hole_edges.entry(b).or_default().push(a);
+            }
+        }
+    }
+
+    let mut starts: Vec<u32> = hole_edges.keys().copied().collect();
+    starts.sort_unstable();
+    let (mut filled, mut left_open) = (0, 0);
+    for start in starts {
+        while let Some(first) = hole_edges.get_mut(&start).and_then(|out| out.pop()) {
+            let mut ring = vec![start];
+            let mut at = first;
+            while at != start {
+                ring.push(at);
+                match hole_edges.get_mut(&at).and_then(|out| out.pop()) {
+                    Some(next) => at = next,
+                    None => break,
+                }
+            }
+            if at != start || ring.len() > max_edges {
+                left_open += 1;
+                continue;
+            }
+
+            let mut center = [0.0f32; 3];
+            for &v in &ring {
+                for (k, c) in center.iter_mut().enumerate() {
+                    *c += mesh.positions[v as usize * 3 + k] / ring.len() as f32;
+                }
+            }
+            let c = mesh.vertex_count() as u32;
+            mesh.positions.extend_from_slice(&center);
+            for k in 0..ring.len() {
+                mesh.indices
+                    .extend([ring[k], ring[(k + 1) % ring.len()], c]);
+            }
+            filled += 1;
+        }
+    }
+    (filled, left_open)
+}
+
+/// Taubin smoothing, which evens out noise without shrinking the mesh;
+/// boundary vertices stay put
+fn smooth(mesh: &mut MeshData, iterations: u32) {
+    let vertex_count = mesh.vertex_count();
+    let mut neighbours: Vec<Vec<u32>> = vec![Vec::new(); vertex_count];
+    let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+    for f in mesh.indices.chunks_exact(3) {
+        for k in 0..3 {
+            let (a, b) = (f[k], f[(k + 1) % 3]);
+            let count = edges.entry((a.min(b), a.max(b))).or_default();
+            if *count == 0 {
+                neighbours[a as usize].push(b);
+                neighbours[b as usize].push(a);
+            }
+            *count += 1;
+        }
+    }
+    let mut fixed = vec![false; vertex_count];
+    for (&(a, b), _) in edges.iter().filter(|(_, &count)| count != 2) {
+        fixed[a as usize] = true;
+        fixed[b as usize] = true;
+    }
+
+    for _ in 0..iterations {
+        for factor in [LAMBDA, MU] {
+            let before = mesh.positions.clone();
+            for v in (0..vertex_count).filter(|&v| !fixed[v] && !neighbours[v].is_empty()) {
+                let around = &neighbours[v];
+                for k in 0..3 {
+                    let average = around
+                        .iter()
+                        .map(|&n| before[n as usize * 3 + k])
+                        .sum::<f32>()
+                        / around.len() as f32;
+                    mesh.positions[v * 3 + k] += (average - before[v * 3 + k]) * factor;
+                }
+            }
+        }
+    }
+}
+
+/// Center over the origin on the ground, optionally scaled so the largest
+/// dimension is `fit_size`; returns the scale applied
+fn recenter(mesh: &mut MeshData, fit_size: Option<f32>) -> Result<f32, String> {
+    let (lo, hi) = simd::bounds(&mesh.positions).ok_or("Scan has no vertices left")?;
+    let largest = (0..3).map(|k| hi[k] - lo[k]).fold(0.0f32, f32::max);
+    let scale = match fit_size {
+        Some(size) if largest > 0.0 => size / largest,
+        _ => 1.0,
+    };
+    let anchor = [(lo[0] + hi[0]) / 2.0, lo[1], (lo[2] + hi[2]) / 2.0];
+    for p in mesh.positions.chunks_exact_mut(3) {
+        for k in 0..3 {
+            p[k] = (p[k] - anchor[k]) * scale;
+        }
+    }
+    Ok(scale)
+}
+
+/// Keep only the vertices `indices` use
+fn compact(mesh: &mut MeshData, indices: Vec<u32>) {
+    let mut remap = vec![u32::MAX; mesh.vertex_count()];
+    let mut order = Vec::new();
+    let indices = indices
+        .into_iter()
+        .map(|v| {
+            if remap[v as usize] == u32::MAX {
+                remap[v as usize] = order.len() as u32;
+                order.push(v as usize);
+            }
+            remap[v as usize]
+        })
+        .collect();
+    *mesh = mesh.with_vertices(&order, indices);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cleanup_removes_floater_and_fills_hole() {
+        // A cube missing its top face, unwelded at a seam, plus a stray
+        // triangle far away
+        let mut positions: Vec<f32> = (0..8)
+            .flat_map(|i| [i & 1, (i >> 1) & 1, (i >> 2) & 1].map(|c| c as f32 + 1.0))
+            .collect();
+        positions.extend([1.0, 1.0, 1.0]);
+        positions.extend([9.0, 9.0, 9.0, 9.5, 9.0, 9.0, 9.0, 9.5, 9.0]);
+        let indices = vec![
+            8, 2, 1, 1, 2, 3, 4, 5, 6, 5, 7, 6, 0, 1, 4, 1, 5, 4, 0, 4, 2, 2, 4, 6, 1, 3, 5, 3, 7,
+            5, 9, 10, 11,
+        ];
+        let scan = MeshData {
+            positions,
+            indices,
+            ..Default::default()
+        };
+
+        let settings = CleanupSettings {
+            min_shell_fraction: 0.5,
+            ..ScanPreset::Light.settings()
+        };
+        let result = cleanup(&scan, &settings).unwrap();
+        let stages: Vec<CleanupStage> = result.stages.iter().map(|s| s.stage).collect();
+        assert_eq!(stages.len(), 7);
+        assert_eq!(stages[0], CleanupStage::RemoveFloaters);
+        assert!(!result.stages[0].skipped);
+        assert!(!result.stages[1].skipped);
+        assert!(result.stages[6].skipped);
+
+        // Cube plus the cap's center, closed again and standing on the ground
+        let mesh = &result.mesh;
+        assert_eq!(mesh.vertex_count(), 9);
+        assert_eq!(mesh.face_count(), 10 + 4);
+        let volume = simd::signed_volume(&mesh.positions, &mesh.indices);
+        assert!((volume.abs() - 1.0).abs() < 1e-5);
+        let (lo, hi) = simd::bounds(&mesh.positions).unwrap();
+        assert_eq!((lo, hi), ([-0.5, 0.0, -0.5], [0.5, 1.0, 0.5]));
+        assert!(mesh.normals.is_some());
+    }
+}
@@ -0,0 +1,299 @@
+//! Node hierarchy of a glTF scene, with its lights and cameras
+//!
+//! Imports flatten a scene into meshes, which leaves lights and cameras set
+//! up in a DCC tool invisible. The scene graph lists every node with its
+//! local and world transform and what it carries. Lights come from
+//! `KHR_lights_punctual`, which is read from the raw JSON since the glTF
+//! parser is built without it.
+
+use crate::utils::analysis_cache::validate_document;
+use crate::utils::axis_conversion::AxisConversion;
+use nalgebra::Matrix4;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::f32::consts::FRAC_PI_4;
+
+pub const LIGHTS_EXTENSION: &str = "KHR_lights_punctual";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneNode {
+    pub index: usize,
+    pub name: Option<String>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+    pub light: Option<usize>,
+    /// Column-major transform relative to the parent
+    pub local_transform: [[f32; 4]; 4],
+    /// Column-major transform relative to the scene
+    pub world_transform: [[f32; 4]; 4],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneLight {
+    pub index: usize,
+    pub name: Option<String>,
+    pub kind: LightKind,
+    /// Linear RGB
+    pub color: [f32; 3],
+    /// Candela for point and spot lights, lux for directional ones
+    pub intensity: f32,
+    /// Distance the light reaches; `None` is unlimited
+    pub range: Option<f32>,
+    /// Spot cone angles in radians
+    pub inner_cone_angle: Option<f32>,
+    pub outer_cone_angle: Option<f32>,
+    /// Nodes placing this light; it shines down their -Z axis
+    pub nodes: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CameraProjection {
+    Perspective {
+        /// Vertical field of view in radians
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        /// `None` is an infinite projection
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneCamera {
+    pub index: usize,
+    pub name: Option<String>,
+    pub projection: CameraProjection,
+    /// Nodes placing this camera; it looks down their -Z axis
+    pub nodes: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneGraph {
+    /// Top-level nodes of the default scene, or every parentless node
+    pub roots: Vec<usize>,
+    pub nodes: Vec<SceneNode>,
+    pub lights: Vec<SceneLight>,
+    pub cameras: Vec<SceneCamera>,
+}
+
+/// Read the scene graph of a glTF JSON document
+pub fn parse(json: &[u8]) -> Result<SceneGraph, String> {
+    let raw: Value =
+        serde_json::from_slice(json).map_err(|e| format!("Failed to parse GLTF: {}", e))?;
+    let root =
+        gltf::json::Root::from_slice(json).map_err(|e| format!("Failed to parse GLTF: {}", e))?;
+    let document = validate_document(root)?;
+
+    let mut nodes: Vec<SceneNode> = document
+        .nodes()
+        .map(|node| SceneNode {
+            index: node.index(),
+            name: node.name().map(str::to_string),
+            parent: None,
+            children: node.children().map(|child| child.index()).collect(),
+            mesh: node.mesh().map(|mesh| mesh.index()),
+            camera: node.camera().map(|camera| camera.index()),
+            light: raw["nodes"][node.index()]["extensions"][LIGHTS_EXTENSION]["light"]
+                .as_u64()
+                .map(|light| light as usize),
+            local_transform: node.transform().matrix(),
+            world_transform: node.transform().matrix(),
+        })
+        .collect();
+    for i in 0..nodes.len() {
+        for child in nodes[i].children.clone() {
+            nodes[child].parent = Some(i);
+        }
+    }
+
+    let roots: Vec<usize> = match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => scene.nodes().map(|node| node.index()).collect(),
+        None => (0..nodes.len())
+            .filter(|&i| nodes[i].parent.is_none())
+            .collect(),
+    };
+    let mut stack: Vec<(usize, Matrix4<f32>)> =
+        roots.iter().map(|&i| (i, Matrix4::identity())).collect();
+    let mut visited = vec![false; nodes.len()];
+    while let Some((i, parent)) = stack.pop() {
+        // Valid documents are trees, but don't trust that
+        if std::mem::replace(&mut visited[i], true) {
+            continue;
+        }
+        let world = parent * Matrix4::from(nodes[i].local_transform);
+        nodes[i].world_transform = world.into();
+        stack.extend(nodes[i].children.iter().map(|&child| (child, world)));
+    }
+
+    let placed_by = |pick: fn(&SceneNode) -> Option<usize>, index: usize| -> Vec<usize> {
+        nodes
+            .iter()
+            .filter(|node| pick(node) == Some(index))
+            .map(|node| node.index)
+            .collect()
+    };
+    let lights = raw["extensions"][LIGHTS_EXTENSION]["lights"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, light)| {
+            let number = |key: &str| light[key].as_f64().map(|v| v as f32);
+            let kind = match light["type"].as_str() {
+                Some("directional") => LightKind::Directional,
+                Some("spot") => LightKind::Spot,
+                _ => LightKind::Point,
+            };
+            let spot = &light["spot"];
+            let cone = |key: &str, default: f32| {
+                (kind == LightKind::Spot).then(|| spot[key].as_f64().map_or(default, |v| v as f32))
+            };
+            let mut color = [1.0; 3];
+            for (c, value) in color
+                .iter_mut()
+                .zip(light["color"].as_array().into_iter().flatten())
+            {
+                *c = value.as_f64().unwrap_or(1.0) as f32;
+            }
+            SceneLight {
+                index,
+                name: light["name"].as_str().map(str::to_string),
+                kind,
+                color,
+                intensity: number("intensity").unwrap_or(1.0),
+                range: number("range"),
+                inner_cone_angle: cone("innerConeAngle", 0.0),
+                outer_cone_angle: cone("outerConeAngle", FRAC_PI_4),
+                nodes: placed_by(|node| node.light, index),
+            }
+        })
+        .collect();
+    let cameras = document
+        .cameras()
+        .map(|camera| SceneCamera {
+            index: camera.index(),
+            name: camera.name().map(str::to_string),
+            projection: match camera.projection() {
+                gltf::camera::Projection::Perspective(p) => CameraProjection::Perspective {
+                    yfov: p.yfov(),
+                    aspect_ratio: p.aspect_ratio(),
+                    znear: p.znear(),
+                    zfar: p.zfar(),
+                },
+                gltf::camera::Projection::Orthographic(o) => CameraProjection::Orthographic {
+                    xmag: o.xmag(),
+                    ymag: o.ymag(),
+                    znear: o.znear(),
+                    zfar: o.zfar(),
+                },
+            },
+            nodes: placed_by(|node| node.camera, camera.index()),
+        })
+        .collect();
+
+    Ok(SceneGraph {
+        roots,
+        nodes,
+        lights,
+        cameras,
+    })
+}
+
+/// A world transform after the axis conversion applied to imported
+/// geometry, keeping the node's own scale
+pub fn convert_transform(conversion: &AxisConversion, transform: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    if conversion.is_identity() {
+        return transform;
+    }
+    let mut basis = Matrix4::identity();
+    for axis in 0..3 {
+        let mut unit = [0.0; 3];
+        unit[axis] = 1.0;
+        let converted = conversion.convert(unit);
+        for row in 0..3 {
+            basis[(row, axis)] = converted[row];
+        }
+    }
+    let unscale = Matrix4::new_scaling(1.0 / conversion.unit_scale);
+    (basis * Matrix4::from(transform) * unscale).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::axis_conversion::UpAxis;
+    use serde_json::json;
+
+    #[test]
+    fn test_lights_and_cameras_with_world_transforms() {
+        let document = json!({
+            "asset": { "version": "2.0" },
+            "extensionsUsed": [LIGHTS_EXTENSION],
+            "extensions": { LIGHTS_EXTENSION: { "lights": [
+                { "type": "spot", "name": "key", "intensity": 40, "color": [1, 0.5, 0.25] },
+                { "type": "directional" }
+            ]}},
+            "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.8, "znear": 0.1 } }],
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [
+                { "name": "rig", "translation": [0, 2, 0], "children": [1, 2] },
+                { "name": "cam", "camera": 0, "translation": [0, 0, 5] },
+                { "name": "lamp", "translation": [1, 0, 0],
+                  "extensions": { LIGHTS_EXTENSION: { "light": 0 } } }
+            ]
+        });
+        let graph = parse(document.to_string().as_bytes()).unwrap();
+
+        assert_eq!(graph.roots, vec![0]);
+        assert_eq!(graph.nodes[2].parent, Some(0));
+        assert_eq!(graph.nodes[2].world_transform[3][..3], [1.0, 2.0, 0.0]);
+        assert_eq!(graph.nodes[1].world_transform[3][..3], [0.0, 2.0, 5.0]);
+
+        let key = &graph.lights[0];
+        assert_eq!(key.kind, LightKind::Spot);
+        assert_eq!(key.intensity, 40.0);
+        assert_eq!(key.color, [1.0, 0.5, 0.25]);
+        assert_eq!(key.outer_cone_angle, Some(FRAC_PI_4));
+        assert_eq!(key.nodes, vec![2]);
+        assert_eq!(graph.lights[1].kind, LightKind::Directional);
+        assert!(graph.lights[1].inner_cone_angle.is_none());
+        assert!(graph.lights[1].nodes.is_empty());
+
+        assert!(matches!(
+            graph.cameras[0].projection,
+            CameraProjection::Perspective { yfov, zfar: None, .. } if yfov == 0.8
+        ));
+        assert_eq!(graph.cameras[0].nodes, vec![1]);
+
+        // Z-up centimeters: up becomes +Y and distances shrink, scale doesn't
+        let conversion = AxisConversion::new(UpAxis::Z, 0.01);
+        let converted = convert_transform(&conversion, graph.nodes[1].world_transform);
+        let expected = conversion.convert([0.0, 2.0, 5.0]);
+        for axis in 0..3 {
+            assert!((converted[3][axis] - expected[axis]).abs() < 1e-6);
+            let column = Matrix4::from(converted).column(axis).xyz().norm();
+            assert!((column - 1.0).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! UV seams and material boundaries of indexed meshes
+//!
+//! Exporters split a vertex wherever its attributes jump, so a UV seam shows
+//! up as an edge that two faces share by position but not by index. Feeding
+//! that to the clustering decimator as-is averages UVs across islands and
+//! pulls the two sides of a seam apart; [`find_seams`] returns the vertices
+//! to lock and the groups that keep islands and materials from mixing.
+
+use std::collections::HashMap;
+
+/// A face using an edge, with the edge's vertices ordered by position id
+type EdgeUse = (usize, [u32; 2]);
+
+/// Constraints that keep simplification from distorting textures
+#[derive(Debug, Clone, Default)]
+pub struct SeamConstraints {
+    /// Vertices on a seam or material boundary, which must not move
+    pub locked: Vec<bool>,
+    /// One id per (UV island, material) pair for each vertex
+    pub groups: Vec<u32>,
+    pub seam_edge_count: usize,
+    pub material_edge_count: usize,
+}
+
+/// Find attribute seams and, with `face_materials`, material boundaries
+///
+/// An edge is a seam when the faces sharing it by position reference
+/// different vertices, and a material boundary when those faces have
+/// different materials. UV islands are the face sets connected through
+/// shared vertex indices.
+pub fn find_seams(
+    positions: &[f32],
+    indices: &[u32],
+    face_materials: Option<&[u32]>,
+) -> SeamConstraints {
+    let vertex_count = positions.len() / 3;
+    let material = |face: usize| face_materials.and_then(|m| m.get(face)).copied();
+
+    // Weld by exact position; -0.0 and 0.0 are the same point
+    let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+    let position_ids: Vec<u32> = positions
+        .chunks_exact(3)
+        .map(|p| {
+            let key = [p[0], p[1], p[2]].map(|v| (v + 0.0).to_bits());
+            let next = welded.len() as u32;
+            *welded.entry(key).or_insert(next)
+        })
+        .collect();
+
+    let mut islands = UnionFind::new(vertex_count);
+    let mut edges: HashMap<(u32, u32), Vec<EdgeUse>> = HashMap::new();
+
+    for (f, face) in indices.chunks_exact(3).enumerate() {
+        if face.iter().any(|&i| i as usize >= vertex_count) {
+            continue;
+        }
+        islands.union(face[0], face[1]);
+        islands.union(face[1], face[2]);
+
+        for k in 0..3 {
+            let (a, b) = (face[k], face[(k + 1) % 3]);
+            let (pa, pb) = (position_ids[a as usize], position_ids[b as usize]);
+            if pa == pb {
+                continue;
+            }
+            let (key, pair) = if pa < pb {
+                ((pa, pb), [a, b])
+            } else {
+                ((pb, pa), [b, a])
+            };
+            edges.entry(key).or_default().push((f, pair));
+        }
+    }
+
+    let mut constraints = SeamConstraints {
+        locked: vec![false; vertex_count],
+        ..Default::default()
+    };
+    for uses in edges.values() {
+        let (first_face, first_pair) = uses[0];
+        let seam = uses.iter().any(|&(_, pair)| pair != first_pair);
+        let boundary = uses
+            .iter()
+            .any(|&(f, _)| material(f) != material(first_face));
+        constraints.seam_edge_count += seam as usize;
+        constraints.material_edge_count += boundary as usize;
+        if seam || boundary {
+            for &(_, [a, b]) in uses {
+                constraints.locked[a as usize] = true;
+                constraints.locked[b as usize] = true;
+            }
+        }
+    }
+
+    // A vertex shared by faces of different materials is on a boundary too
+    let mut vertex_materials: Vec<Option<Option<u32>>> = vec![None; vertex_count];
+    for (f, face) in indices.chunks_exact(3).enumerate() {
+        for &i in face {
+            let Some(slot) = vertex_materials.get_mut(i as usize) else {
+                continue;
+            };
+            match slot {
+                None => *slot = Some(material(f)),
+                Some(m) if *m != material(f) => constraints.locked[i as usize] = true,
+                _ => {}
+            }
+        }
+    }
+
+    let mut group_ids: HashMap<(u32, Option<u32>), u32> = HashMap::new();
+    constraints.groups = (0..vertex_count as u32)
+        .map(|i| {
+            let key = (islands.find(i), vertex_materials[i as usize].flatten());
+            let next = group_ids.len() as u32;
+            *group_ids.entry(key).or_insert(next)
+        })
+        .collect();
+    constraints
+}
+
+/// Disjoint sets over `0..len`, merged toward the lowest member
+pub(crate) struct UnionFind {
+    parent: Vec<u32>,
+}
+
+impl UnionFind {
+    pub(crate) fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len as u32).collect(),
+        }
+    }
+
+    pub(crate) fn find(&mut self, mut i: u32) -> u32 {
+        while self.parent[i as usize] != i {
+            let grandparent = self.parent[self.parent[i as usize] as usize];
+            self.parent[i as usize] = grandparent;
+            i = grandparent;
+        }
+        i
+    }
+
+    pub(crate) fn union(&mut self, a: u32, b: u32) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[a.max(b) as usize] = a.min(b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_vertices_form_a_locked_seam() {
+        // Two quads side by side; the shared edge at x = 1 is split in UV space
+        let positions = [
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, // left
+            1.0, 0.0, 0.0, 2.0, 0.0, 0.0, 2.0, 1.0, 0.0, 1.0, 1.0, 0.0, // right
+        ];
+        let indices = [0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7];
+        let seams = find_seams(&positions, &indices, None);
+
+        assert_eq!(seams.seam_edge_count, 1);
+        assert_eq!(seams.material_edge_count, 0);
+        let locked: Vec<usize> = (0..8).filter(|&i| seams.locked[i]).collect();
+        assert_eq!(locked, vec![1, 2, 4, 7]);
+        assert_ne!(seams.groups[0], seams.groups[5]);
+
+        // Welded and split by material instead
+        let indices = [0, 1, 2, 0, 2, 3, 1, 5, 6, 1, 6, 2];
+        let seams = find_seams(&positions, &indices, Some(&[0, 0, 1, 1]));
+        assert_eq!(seams.seam_edge_count, 0);
+        assert_eq!(seams.material_edge_count, 1);
+        assert!(seams.locked[1] && seams.locked[2] && !seams.locked[0]);
+        assert_ne!(seams.groups[0], seams.groups[5]);
+    }
+}
@@ -0,0 +1,259 @@
+//! Planar cross-sections of a mesh
+//!
+//! Every face crossing the plane contributes one segment, with its ends on
+//! the face's crossing edges. Ends are keyed by edge between welded
+//! positions, so segments from neighbouring faces share them exactly and
+//! chain into polylines: closed loops for a watertight mesh, open runs
+//! where the plane leaves through a hole.
+
+use crate::utils::mesh_store::MeshData;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Plane of points `p` with `dot(normal, p) == offset`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SectionPlane {
+    pub normal: [f32; 3],
+    pub offset: f32,
+}
+
+impl SectionPlane {
+    /// The same plane with a unit normal
+    pub fn normalized(&self) -> Result<Self, String> {
+        let len = dot(self.normal, self.normal).sqrt();
+        if !(len > 0.0 && len.is_finite() && self.offset.is_finite()) {
+            return Err("Section plane needs a finite, non-zero normal".to_string());
+        }
+        Ok(Self {
+            normal: self.normal.map(|n| n / len),
+            offset: self.offset / len,
+        })
+    }
+
+    /// Unit axes spanning the plane, with `u × v` along the normal
+    ///
+    /// `u` follows world X unless the normal is close to it, so a section
+    /// across Z reads as a plan view with X right and Y up.
+    pub fn axes(&self) -> ([f32; 3], [f32; 3]) {
+        let n = self.normal;
+        let helper = if n[0].abs() > 0.9 {
+            [0.0, 1.0, 0.0]
+        } else {
+            [1.0, 0.0, 0.0]
+        };
+        let along = dot(helper, n);
+        let u = [0, 1, 2].map(|k| helper[k] - along * n[k]);
+        let len = dot(u, u).sqrt();
+        let u = u.map(|c| c / len);
+        (u, cross(n, u))
+    }
+}
+
+/// A chain of section segments in plane coordinates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Polyline {
+    pub points: Vec<[f32; 2]>,
+    /// The last point joins back to the first
+    pub closed: bool,
+    /// Total length, in mesh units
+    pub length: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Section {
+    pub polylines: Vec<Polyline>,
+    /// World point at the plane's (0, 0)
+    pub origin: [f32; 3],
+    /// World directions of the plane's X and Y axes
+    pub u_axis: [f32; 3],
+    pub v_axis: [f32; 3],
+}
+
+impl Section {
+    /// Smallest and largest plane coordinates, `None` for an empty section
+    pub fn bounds(&self) -> Option<([f32; 2], [f32; 2])> {
+        let mut points = self.polylines.iter().flat_map(|p| &p.points);
+        let first = *points.next()?;
+        Some(points.fold((first, first), |(lo, hi), p| {
+            (
+                [lo[0].min(p[0]), lo[1].min(p[1])],
+                [hi[0].max(p[0]), hi[1].max(p[1])],
+            )
+        }))
+    }
+}
+
+/// Cut `mesh` with `plane` (with a unit normal)
+///
+/// Vertices exactly on the plane count as in front of it, so faces lying in
+/// the plane add nothing and a cut through a vertex isn't doubled.
+pub fn slice(mesh: &MeshData, plane: &SectionPlane) -> Section {
+    let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+    let mut points: Vec<[f32; 3]> = Vec::new();
+    let ids: Vec<u32> = mesh
+        .positions
+        .chunks_exact(3)
+        .map(|p| {
+            let next = welded.len() as u32;
+            *welded
+                .entry([p[0], p[1], p[2]].map(f32::to_bits))
+                .or_insert_with(|| {
+                    points.push([p[0], p[1], p[2]]);
+                    next
+                })
+        })
+        .collect();
+    let distance: Vec<f32> = points
+        .iter()
+        .map(|&p| dot(plane.normal, p) - plane.offset)
+        .collect();
+
+    // Crossing points by welded edge, and the segment each face adds
+    let mut crossings: HashMap<(u32, u32), u32> = HashMap::new();
+    let mut nodes: Vec<[f32; 3]> = Vec::new();
+    let mut segments: Vec<[u32; 2]> = Vec::new();
+    for face in mesh.indices.chunks_exact(3) {
+        let corners = [face[0], face[1], face[2]].map(|i| ids[i as usize]);
+        let mut ends = Vec::with_capacity(2);
+        for k in 0..3 {
+            let (a, b) = (corners[k], corners[(k + 1) % 3]);
+            let (da, db) = (distance[a as usize], distance[b as usize]);
+            if (da >= 0.0) == (db >= 0.0) {
+                continue;
+            }
+            let node = *crossings.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let t = da / (da - db);
+                let (pa, pb) = (points[a as usize], points[b as usize]);
+                nodes.push([0, 1, 2].map(|axis| pa[axis] + (pb[axis] - pa[axis]) * t));
+                (nodes.len() - 1) as u32
+            });
+            ends.push(node);
+        }
+        if let [start, end] = ends[..] {
+            segments.push([start, end]);
+        }
+    }
+
+    let origin = plane.normal.map(|n| n * plane.offset);
+    let (u_axis, v_axis) = plane.axes();
+    let to_plane = |p: [f32; 3]| {
+        let d = [0, 1, 2].map(|k| p[k] - origin[k]);
+        [dot(d, u_axis), dot(d, v_axis)]
+    };
+
+    Section {
+        polylines: chain(&segments, nodes.len())
+            .into_iter()
+            .map(|(chain, closed)| {
+                let points: Vec<[f32; 2]> =
+                    chain.iter().map(|&n| to_plane(nodes[n as usize])).collect();
+                let mut length: f32 = points.windows(2).map(|w| distance_2d(w[0], w[1])).sum();
+                if closed {
+                    length += distance_2d(points[points.len() - 1], points[0]);
+                }
+                Polyline {
+                    points,
+                    closed,
+                    length,
+                }
+            })
+            .collect(),
+        origin,
+        u_axis,
+        v_axis,
+    }
+}
+
+/// Join segments sharing ends into node chains, open ones first
+///
+/// Segments are undirected, so faces wound inconsistently still chain.
+pub(crate) fn chain(segments: &[[u32; 2]], node_count: usize) -> Vec<(Vec<u32>, bool)> {
+    let mut incident: Vec<Vec<usize>> = vec![Vec::new(); node_count];
+    for (s, &[a, b]) in segments.iter().enumerate() {
+        incident[a as usize].push(s);
+        incident[b as usize].push(s);
+    }
+
+    let mut used = vec![false; segments.len()];
+    let mut chains = Vec::new();
+    // Open runs have to start at an end, or they'd be walked in two pieces
+    let ends = (0..node_count as u32).filter(|&n| incident[n as usize].len() == 1);
+    let starts: Vec<u32> = ends.chain(0..node_count as u32).collect();
+    for start in starts {
+        while let Some(&first) = incident[start as usize].iter().find(|&&s| !used[s]) {
+            let mut nodes = vec![start];
+            let mut at = start;
+            let mut next = Some(first);
+            while let Some(s) = next {
+                used[s] = true;
+                let [a, b] = segments[s];
+                at = if a == at { b } else { a };
+                if at == start {
+                    break;
+                }
+                nodes.push(at);
+                next = incident[at as usize].iter().copied().find(|&s| !used[s]);
+            }
+            let closed = at == start && nodes.len() > 2;
+            chains.push((nodes, closed));
+        }
+    }
+    chains
+}
+
+fn distance_2d(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slice_cube_gives_closed_square() {
+        // Unit cube with its faces split along different diagonals and the
+        // seams between faces unwelded, as an export with UVs would have
+        let corners: Vec<[f32; 3]> = (0..8)
+            .map(|i| [i & 1, (i >> 1) & 1, (i >> 2) & 1].map(|c| c as f32))
+            .collect();
+        let quads = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        let mut mesh = MeshData::default();
+        for quad in quads {
+            let base = mesh.vertex_count() as u32;
+            mesh.positions.extend(quad.iter().flat_map(|&c| corners[c]));
+            mesh.indices.extend([0, 1, 2, 0, 2, 3].map(|k| base + k));
+        }
+
+        let plane = SectionPlane {
+            normal: [0.0, 0.0, 2.0],
+            offset: 1.0,
+        };
+        let section = slice(&mesh, &plane.normalized().unwrap());
+        assert_eq!(section.polylines.len(), 1);
+        let square = &section.polylines[0];
+        assert!(square.closed);
+        assert!((square.length - 4.0).abs() < 1e-5);
+        assert_eq!(section.bounds(), Some(([0.0, 0.0], [1.0, 1.0])));
+        assert_eq!(section.u_axis, [1.0, 0.0, 0.0]);
+        assert_eq!(section.v_axis, [0.0, 1.0, 0.0]);
+    }
+}
@@ -0,0 +1,121 @@
+//! SVG and DXF output for cross-sections
+//!
+//! Both are written at real-world scale in millimeters, which laser cutter
+//! and CNC software import without asking. SVG's Y axis points down, so
+//! profiles are flipped to keep the plane's V axis pointing up.
+
+use crate::utils::section::Section;
+use std::fmt::Write;
+
+/// Millimeters per mesh unit, for meshes in meters as imports are
+pub const MM_PER_UNIT: f32 = 1000.0;
+
+/// Encode a section as an SVG document sized in millimeters
+///
+/// `scale` is millimeters per mesh unit. The drawing is cropped to the
+/// section's bounds; an empty section gives an empty document.
+pub fn write_svg(section: &Section, scale: f32) -> String {
+    let (lo, hi) = section.bounds().unwrap_or(([0.0; 2], [0.0; 2]));
+    let width = (hi[0] - lo[0]) * scale;
+    let height = (hi[1] - lo[1]) * scale;
+
+    let mut out = String::new();
+    let _ = writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}mm" height="{h}mm" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height
+    );
+    for polyline in &section.polylines {
+        let mut d = String::new();
+        for (i, p) in polyline.points.iter().enumerate() {
+            let x = (p[0] - lo[0]) * scale;
+            let y = (hi[1] - p[1]) * scale;
+            let _ = write!(d, "{}{} {} ", if i == 0 { 'M' } else { 'L' }, x, y);
+        }
+        if polyline.closed {
+            d.push('Z');
+        }
+        let _ = writeln!(
+            out,
+            r#"  <path d="{}" fill="none" stroke="black" stroke-width="0.1"/>"#,
+            d.trim_end()
+        );
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Encode a section as an ASCII DXF drawing in millimeters
+///
+/// Uses R12 `POLYLINE` entities, which every importer reads, with the
+/// `$INSUNITS` header set so newer ones pick up the units. Coordinates are
+/// plane coordinates times `scale`, so the drawing origin is the plane's.
+pub fn write_dxf(section: &Section, scale: f32) -> String {
+    let mut out = String::new();
+    let mut group = |code: u32, value: &dyn std::fmt::Display| {
+        let _ = write!(out, "{}\n{}\n", code, value);
+    };
+
+    group(0, &"SECTION");
+    group(2, &"HEADER");
+    group(9, &"$ACADVER");
+    group(1, &"AC1009");
+    // 4 is millimeters
+    group(9, &"$INSUNITS");
+    group(70, &4);
+    group(0, &"ENDSEC");
+
+    group(0, &"SECTION");
+    group(2, &"ENTITIES");
+    for polyline in &section.polylines {
+        group(0, &"POLYLINE");
+        group(8, &"0");
+        group(66, &1);
+        group(70, &(polyline.closed as u32));
+        for p in &polyline.points {
+            group(0, &"VERTEX");
+            group(8, &"0");
+            group(10, &(p[0] * scale));
+            group(20, &(p[1] * scale));
+            group(30, &0.0);
+        }
+        group(0, &"SEQEND");
+        group(8, &"0");
+    }
+    group(0, &"ENDSEC");
+    group(0, &"EOF");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::section::Polyline;
+
+    #[test]
+    fn test_square_section_in_millimeters() {
+        let section = Section {
+            polylines: vec![Polyline {
+                points: vec![[0.0, 0.0], [0.5, 0.0], [0.5, 0.25], [0.0, 0.25]],
+                closed: true,
+                length: 1.5,
+            }],
+            origin: [0.0; 3],
+            u_axis: [1.0, 0.0, 0.0],
+            v_axis: [0.0, 1.0, 0.0],
+        };
+
+        let svg = write_svg(&section, MM_PER_UNIT);
+        assert!(svg.contains(r#"width="500mm" height="250mm" viewBox="0 0 500 250""#));
+        // Y is flipped, so the first corner is at the bottom left
+        assert!(svg.contains(r#"d="M0 250 L500 250 L500 0 L0 0 Z""#));
+
+        let dxf = write_dxf(&section, MM_PER_UNIT);
+        assert!(dxf.contains("$INSUNITS\n70\n4\n"));
+        assert_eq!(dxf.matches("VERTEX").count(), 4);
+        assert!(dxf.contains("10\n500\n20\n250\n"));
+        assert!(dxf.ends_with("0\nEOF\n"));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::utils::asset_cache::CacheSettings;
+use crate::utils::axis_conversion::ImportAxes;
+use crate::utils::external_tools::ExternalTool;
+use crate::utils::file_lock;
+use crate::utils::i18n::{self, Locale};
+use crate::utils::library::StorageRoot;
+use crate::utils::maintenance::MaintenanceSchedule;
+use crate::utils::notifications::NotificationPreferences;
+use crate::utils::power::PowerThrottling;
+use crate::utils::profiles::ProcessingProfiles;
+use crate::utils::smart_collections::SmartCollection;
+use crate::utils::storage_layout::StorageLayout;
+use crate::utils::triangulate::TriangulationOptions;
+use crate::utils::validation_profiles::ValidationProfile;
+use crate::utils::watch_rules::WatchRule;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Which implementation heavy mesh processing runs on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingBackend {
+    /// Use the GPU when one is available, otherwise the CPU
+    #[default]
+    Auto,
+    Cpu,
+    Gpu,
+}
+
+/// Persistent application settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub processing_backend: ProcessingBackend,
+    /// Refuse every command that would write or delete files (dry runs still work)
+    pub read_only: bool,
+    /// Axis and unit conversion applied to imported OBJ, STL and FBX files
+    pub import_axes: ImportAxes,
+    /// How imported quads and n-gons are split into triangles
+    pub triangulation: TriangulationOptions,
+    /// Named bundles of processing steps for `process_asset`
+    pub processing_profiles: ProcessingProfiles,
+    /// Profiles run automatically on files arriving in watched folders
+    pub watch_rules: Vec<WatchRule>,
+    /// Where models go inside storage folders
+    pub storage_layout: StorageLayout,
+    /// Storage folders that together make up the library
+    pub storage_roots: Vec<StorageRoot>,
+    /// Saved searches over the library
+    pub smart_collections: Vec<SmartCollection>,
+    /// Size limit of the thumbnail, preview and texture cache
+    pub cache: CacheSettings,
+    /// Command-line tools that can be run on assets
+    pub external_tools: Vec<ExternalTool>,
+    /// Blender executable for `edit_in_blender`; empty looks in the usual
+    /// install location, then on the `PATH`
+    pub blender_executable: String,
+    /// `gltf-transform` executable for `export_web_viewer`; empty looks on
+    /// the `PATH`
+    pub gltf_transform_executable: String,
+    /// Applications the user picked for `open_with`
+    pub open_with_applications: Vec<String>,
+    /// Id of the external tool `enhance_texture` upscales with, such as a
+    /// Real-ESRGAN build; empty upscales with the built-in filters
+    pub texture_upscaler: String,
+    /// Validation profiles of the user's own, added to the built-in ones; a
+    /// profile named like a built-in one replaces it
+    pub validation_profiles: Vec<ValidationProfile>,
+    /// Profile reports check assets against unless told otherwise; empty
+    /// checks against none
+    pub validation_profile: String,
+    /// When the library health checks run by themselves
+    pub maintenance: MaintenanceSchedule,
+    /// Which finished background jobs are announced while the window is
+    /// minimized
+    pub notification_preferences: NotificationPreferences,
+    /// How many background jobs run at once, and when fewer run to spare
+    /// the battery and keep the machine cool
+    pub power_throttling: PowerThrottling,
+    /// Answer the API on a loopback port while the window is open, for
+    /// plugins in other tools such as Blender or Unreal
+    pub local_api: bool,
+    /// Language of the messages, reports and notifications the app writes
+    pub locale: Locale,
+}
+
+impl AppSettings {
+    /// `sent` with the settings that have commands of their own kept from
+    /// `self`
+    ///
+    /// Those commands check and apply what they're given, such as whether
+    /// a watched folder is approved, so a whole-settings update must not
+    /// change them.
+    pub fn with_edits(self, sent: AppSettings) -> AppSettings {
+        AppSettings {
+            watch_rules: self.watch_rules,
+            storage_layout: self.storage_layout,
+            storage_roots: self.storage_roots,
+            smart_collections: self.smart_collections,
+            external_tools: self.external_tools,
+            open_with_applications: self.open_with_applications,
+            blender_executable: self.blender_executable,
+            gltf_transform_executable: self.gltf_transform_executable,
+            maintenance: self.maintenance,
+            power_throttling: self.power_throttling,
+            local_api: self.local_api,
+            ..sent
+        }
+    }
+}
+
+/// Settings loaded from and saved to a JSON file, managed as Tauri state
+pub struct SettingsStore {
+    path: Option<PathBuf>,
+    settings: Mutex<AppSettings>,
+}
+
+impl Default for SettingsStore {
+    /// In-memory settings that are never written to disk
+    fn default() -> Self {
+        Self {
+            path: None,
+            settings: Mutex::new(AppSettings::default()),
+        }
+    }
+}
+
+impl SettingsStore {
+    /// Load settings from `path`, falling back to defaults if missing or invalid
+    pub fn load(path: PathBuf) -> Self {
+        let settings = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid settings file {}: {}", path.display(), e);
+                AppSettings::default()
+            }),
+            Err(_) => AppSettings::default(),
+        };
+        i18n::set_locale(settings.locale);
+
+        Self {
+            path: Some(path),
+            settings: Mutex::new(settings),
+        }
+    }
+
+    pub fn get(&self) -> AppSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    /// Modify settings in place and persist them
+    ///
+    /// `f` sees the settings as last saved by any instance sharing the file.
+    pub fn update(&self, f: impl FnOnce(&mut AppSettings)) -> Result<AppSettings, String> {
+        let mut settings = self.settings.lock().unwrap();
+        let _lock = match &self.path {
+            Some(path) => Some(file_lock::lock_and_reload(
+                path,
+                &mut *settings,
+                "settings file",
+            )?),
+            None => None,
+        };
+        let mut updated = settings.clone();
+        f(&mut updated);
+        self.save(&updated)?;
+        i18n::set_locale(updated.locale);
+        *settings = updated.clone();
+        Ok(updated)
+    }
+
+    fn save(&self, settings: &AppSettings) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(settings)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+        let tmp = file_lock::temp_path(path);
+        fs::write(&tmp, json).map_err(|e| format!("Failed to write settings: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to save settings: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_keeps_settings_with_their_own_commands() {
+        let store = SettingsStore::default();
+        store
+            .update(|s| {
+                s.local_api = true;
+                s.blender_executable = "/opt/blender".into();
+            })
+            .unwrap();
+
+        let mut sent = store.get();
+        sent.local_api = false;
+        sent.power_throttling.max_jobs = 1_000;
+        sent.watch_rules.push(WatchRule::default());
+        sent.read_only = true;
+        sent.blender_executable = "/tmp/blender".into();
+        sent.gltf_transform_executable = "/tmp/gltf-transform".into();
+        let saved = store
+            .update(|current| *current = current.clone().with_edits(sent))
+            .unwrap();
+
+        assert!(saved.local_api);
+        assert_eq!(
+            saved.power_throttling.max_jobs,
+            PowerThrottling::default().max_jobs
+        );
+        assert!(saved.watch_rules.is_empty());
+        assert!(saved.read_only);
+        assert_eq!(saved.blender_executable, "/opt/blender");
+        assert!(saved.gltf_transform_executable.is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+//! Shrinkwrapping a template mesh onto a target surface
+//!
+//! Each iteration relaxes the template toward the average of its
+//! neighbours, which keeps its edge flow even, then snaps every vertex to
+//! the closest point on the target. Run on a clean quad template over a
+//! scan, this is a rough retopology: the result has the template's layout
+//! and the scan's shape.
+
+use crate::utils::compute::cpu::{closest_point_on_triangle, CpuBackend};
+use crate::utils::compute::ComputeBackend;
+use crate::utils::mesh_store::MeshData;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Largest iteration count accepted
+pub const MAX_ITERATIONS: u32 = 100;
+/// Triangles per BVH leaf
+const LEAF_SIZE: usize = 4;
+
+pub struct Shrinkwrap {
+    pub mesh: MeshData,
+    /// How far vertices moved from where the template had them
+    pub mean_displacement: f32,
+    pub max_displacement: f32,
+}
+
+/// Wrap `source` onto `target` over `iterations` relax-and-project passes
+///
+/// `smoothing` is how far (0 to 1) each relax step moves a vertex toward
+/// its neighbours' average. Vertices at the same position move together, so
+/// seams stay closed; normals, if any, are recomputed.
+pub fn shrinkwrap(
+    source: &MeshData,
+    target: &MeshData,
+    iterations: u32,
+    smoothing: f32,
+) -> Result<Shrinkwrap, String> {
+    if !(1..=MAX_ITERATIONS).contains(&iterations) {
+        return Err(format!(
+            "Iterations must be between 1 and {}, got {}",
+            MAX_ITERATIONS, iterations
+        ));
+    }
+    if !(0.0..=1.0).contains(&smoothing) {
+        return Err(format!(
+            "Smoothing must be between 0 and 1, got {}",
+            smoothing
+        ));
+    }
+    let surface =
+        SurfaceIndex::new(&target.positions, &target.indices).ok_or("Target mesh has no faces")?;
+
+    // One point per distinct position, linked to its edge neighbours
+    let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+    let mut points: Vec<[f32; 3]> = Vec::new();
+    let ids: Vec<u32> = source
+        .positions
+        .chunks_exact(3)
+        .map(|p| {
+            let next = welded.len() as u32;
+            *welded
+                .entry([p[0], p[1], p[2]].map(f32::to_bits))
+                .or_insert_with(|| {
+                    points.push([p[0], p[1], p[2]]);
+                    next
+                })
+        })
+        .collect();
+    let mut neighbours: Vec<Vec<u32>> = vec![Vec::new(); points.len()];
+    for face in source.indices.chunks_exact(3) {
+        for k in 0..3 {
+            let (a, b) = (ids[face[k] as usize], ids[face[(k + 1) % 3] as usize]);
+            if a != b && !neighbours[a as usize].contains(&b) {
+                neighbours[a as usize].push(b);
+                neighbours[b as usize].push(a);
+            }
+        }
+    }
+
+    let original = points.clone();
+    for iteration in 0..iterations {
+        if iteration > 0 && smoothing > 0.0 {
+            points = relax(&points, &neighbours, smoothing);
+        }
+        points = points
+            .par_iter()
+            .map(|&p| surface.closest(p).unwrap_or(p))
+            .collect();
+    }
+
+    let displacements: Vec<f32> = original
+        .iter()
+        .zip(&points)
+        .map(|(a, b)| distance(*a, *b))
+        .collect();
+    let mean_displacement = displacements.iter().sum::<f32>() / displacements.len().max(1) as f32;
+    let max_displacement = displacements.iter().copied().fold(0.0f32, f32::max);
+
+    let mut mesh = source.clone();
+    mesh.positions = ids.iter().flat_map(|&id| points[id as usize]).collect();
+    if mesh.normals.is_some() {
+        mesh.normals = Some(CpuBackend.vertex_normals(&mesh.positions, &mesh.indices)?);
+    }
+    Ok(Shrinkwrap {
+        mesh,
+        mean_displacement,
+        max_displacement,
+    })
+}
+
+/// Move every point `amount` of the way to its neighbours' average
+fn relax(points: &[[f32; 3]], neighbours: &[Vec<u32>], amount: f32) -> Vec<[f32; 3]> {
+    points
+        .par_iter()
+        .zip(neighbours)
+        .map(|(&p, around)| {
+            if around.is_empty() {
+                return p;
+            }
+            let mut average = [0.0f32; 3];
+            for &n in around {
+                for (axis, value) in average.iter_mut().enumerate() {
+                    *value += points[n as usize][axis] / around.len() as f32;
+                }
+            }
+            std::array::from_fn(|axis| p[axis] + (average[axis] - p[axis]) * amount)
+        })
+        .collect()
+}
+
+/// Bounding volume hierarchy answering closest-point queries on a mesh
+pub struct SurfaceIndex {
+    /// Corners of each triangle and the face it came from
+    triangles: Vec<([[f32; 3]; 3], u32)>,
+    nodes: Vec<Node>,
+}
+
+/// Box around either a run of triangles (a leaf) or two child nodes
+struct Node {
+    min: [f32; 3],
+    max: [f32; 3],
+    /// First triangle for a leaf, second child for an inner node (the first
+    /// child follows its parent)
+    start: u32,
+    /// Triangle count, 0 for an inner node
+    count: u32,
+}
+
+impl SurfaceIndex {
+    /// `None` if the mesh has no faces with all indices in range
+    pub fn new(positions: &[f32], indices: &[u32]) -> Option<Self> {
+        let vertex_count = positions.len() / 3;
+        let mut triangles: Vec<([[f32; 3]; 3], u32)> = indices
+            .chunks_exact(3)
+            .enumerate()
+            .filter(|(_, f)| f.iter().all(|&i| (i as usize) < vertex_count))
+            .map(|(face, f)| {
+                let corners = [f[0], f[1], f[2]].map(|i| {
+                    let b = i as usize * 3;
+                    [positions[b], positions[b + 1], positions[b + 2]]
+                });
+                (corners, face as u32)
+            })
+            .collect();
+        if triangles.is_empty() {
+            return None;
+        }
+
+        let mut nodes = Vec::with_capacity(triangles.len() / LEAF_SIZE * 2 + 1);
+        let len = triangles.len();
+        build(&mut triangles, 0, len, &mut nodes);
+        Some(Self { triangles, nodes })
+    }
+
+    /// Closest point on the surface to `p`
+    pub fn closest(&self, p: [f32; 3]) -> Option<[f32; 3]> {
+        self.closest_face(p).map(|(_, q)| q)
+    }
+
+    /// Closest point on the surface to `p` and the face it lies on
+    pub fn closest_face(&self, p: [f32; 3]) -> Option<(usize, [f32; 3])> {
+        let mut best: Option<(f32, usize, [f32; 3])> = None;
+        let mut stack = vec![0usize];
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            if best.is_some_and(|(d, _, _)| box_distance_squared(node, p) >= d) {
+                continue;
+            }
+            if node.count > 0 {
+                let start = node.start as usize;
+                for &([a, b, c], face) in &self.triangles[start..start + node.count as usize] {
+                    let q = closest_point_on_triangle(p, a, b, c);
+                    let d = distance_squared(p, q);
+                    if best.is_none_or(|(b, _, _)| d < b) {
+                        best = Some((d, face as usize, q));
+                    }
+                }
+            } else {
+                // Visit the nearer child first so the other is more often pruned
+                let (left, right) = (n + 1, node.start as usize);
+                let near_left = box_distance_squared(&self.nodes[left], p)
+                    <= box_distance_squared(&self.nodes[right], p);
+                let (near, far) = if near_left {
+                    (left, right)
+                } else {
+                    (right, left)
+                };
+                stack.push(far);
+                stack.push(near);
+            }
+        }
+        best.map(|(_, face, q)| (face, q))
+    }
+}
+
+/// Add a node for `triangles[start..end]` and its subtree, splitting at the
+/// centroid median of the longest axis
+fn build(triangles: &mut [([[f32; 3]; 3], u32)], start: usize, end: usize, nodes: &mut Vec<Node>) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for corner in triangles[start..end].iter().flat_map(|t| &t.0) {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(corner[axis]);
+            max[axis] = max[axis].max(corner[axis]);
+        }
+    }
+    let index = nodes.len();
+    nodes.push(Node {
+        min,
+        max,
+        start: start as u32,
+        count: (end - start) as u32,
+    });
+    if end - start <= LEAF_SIZE {
+        return;
+    }
+
+    let axis = (0..3)
+        .max_by(|&a, &b| (max[a] - min[a]).total_cmp(&(max[b] - min[b])))
+        .unwrap_or(0);
+    let centroid = |(t, _): &([[f32; 3]; 3], u32)| t[0][axis] + t[1][axis] + t[2][axis];
+    let middle = (start + end) / 2;
+    triangles[start..end]
+        .select_nth_unstable_by(middle - start, |a, b| centroid(a).total_cmp(&centroid(b)));
+
+    build(triangles, start, middle, nodes);
+    nodes[index].start = nodes.len() as u32;
+    nodes[index].count = 0;
+    build(triangles, middle, end, nodes);
+}
+
+fn box_distance_squared(node: &Node, p: [f32; 3]) -> f32 {
+    (0..3)
+        .map(|axis| {
+            let d = (node.min[axis] - p[axis])
+                .max(p[axis] - node.max[axis])
+                .max(0.0);
+            d * d
+        })
+        .sum()
+}
+
+fn distance_squared(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|axis| (a[axis] - b[axis]).powi(2)).sum()
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    distance_squared(a, b).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shrinkwrap_lands_on_target() {
+        // A 3x3 vertex grid above a large ground quad at z = 0
+        let source = MeshData {
+            positions: (0..9)
+                .flat_map(|i| [(i % 3) as f32, (i / 3) as f32, 2.0])
+                .collect(),
+            indices: vec![
+                0, 1, 4, 0, 4, 3, 1, 2, 5, 1, 5, 4, 3, 4, 7, 3, 7, 6, 4, 5, 8, 4, 8, 7,
+            ],
+            ..Default::default()
+        };
+        let target = MeshData {
+            positions: vec![
+                -5.0, -5.0, 0.0, 5.0, -5.0, 0.0, 5.0, 5.0, 0.0, -5.0, 5.0, 0.0,
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3],
+            ..Default::default()
+        };
+
+        let wrapped = shrinkwrap(&source, &target, 1, 0.5).unwrap();
+        assert!(wrapped.mesh.positions.chunks_exact(3).all(|p| p[2] == 0.0));
+        assert_eq!(&wrapped.mesh.positions[12..15], &[1.0, 1.0, 0.0]);
+        assert!((wrapped.max_displacement - 2.0).abs() < 1e-6);
+
+        // Relaxing pulls the corners toward the middle but keeps them on it
+        let relaxed = shrinkwrap(&source, &target, 3, 0.5).unwrap();
+        assert!(relaxed.mesh.positions.chunks_exact(3).all(|p| p[2] == 0.0));
+        assert!(relaxed.mesh.positions[0] > 0.0);
+        assert_eq!(&relaxed.mesh.positions[12..15], &[1.0, 1.0, 0.0]);
+
+        assert!(shrinkwrap(&source, &target, 0, 0.5).is_err());
+    }
+}
@@ -0,0 +1,345 @@
+//! SIMD geometry kernels for the hot loops in mesh statistics and analysis
+//!
+//! Face sums load each corner as one `f32x4` and transpose four faces into
+//! x/y/z lanes; bounds and welding distance checks run eight vertices at a
+//! time in `f32x8`. Rayon splits large meshes across threads. Faces
+//! referencing out-of-range vertices contribute nothing, matching the scalar
+//! code they replace.
+
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::ops::{Add, Mul, Sub};
+use wide::{f32x4, f32x8, CmpLt};
+
+const LANES: usize = 8;
+const FACE_LANES: usize = 4;
+/// Faces per rayon task
+const FACE_BLOCK: usize = 4096;
+/// Vertices per rayon task when computing bounds
+const VERTEX_BLOCK: usize = 8192;
+
+/// Four triangles with coordinates split into lanes, `v[corner][axis]`
+struct TriangleLanes {
+    v: [[f32x4; 3]; 3],
+}
+
+// The gathers are `#[inline]` so they fold into the rayon closures; left as
+// calls across codegen units, face sums run about three times slower.
+impl TriangleLanes {
+    /// Gather four faces with one 16-byte load per corner
+    ///
+    /// Returns `None` if any corner is the last vertex (the load would run
+    /// past the buffer) or out of range.
+    #[inline]
+    fn gather_fast(positions: &[f32], faces: &[u32; FACE_LANES * 3]) -> Option<Self> {
+        let max = faces.iter().copied().fold(0, u32::max) as usize;
+        if max * 3 + 4 > positions.len() {
+            return None;
+        }
+
+        let load = |i: u32| {
+            let b = i as usize * 3;
+            let v = &positions[b..b + 4];
+            f32x4::new([v[0], v[1], v[2], v[3]])
+        };
+        let corner = |k: usize| {
+            let [x, y, z, _] = f32x4::transpose([
+                load(faces[k]),
+                load(faces[3 + k]),
+                load(faces[6 + k]),
+                load(faces[9 + k]),
+            ]);
+            [x, y, z]
+        };
+        Some(Self {
+            v: [corner(0), corner(1), corner(2)],
+        })
+    }
+
+    /// Gather up to four faces; missing or invalid faces become degenerate
+    #[inline]
+    fn gather(positions: &[f32], faces: &[u32]) -> Self {
+        let mut corners = [[f32x4::ZERO; FACE_LANES]; 3];
+        for (lane, face) in faces.chunks_exact(3).enumerate() {
+            let base = [face[0], face[1], face[2]].map(|i| i as usize * 3);
+            if base.iter().any(|&b| b + 2 >= positions.len()) {
+                continue;
+            }
+            for (corner, &b) in base.iter().enumerate() {
+                corners[corner][lane] =
+                    f32x4::new([positions[b], positions[b + 1], positions[b + 2], 0.0]);
+            }
+        }
+
+        Self {
+            v: corners.map(|rows| {
+                let [x, y, z, _] = f32x4::transpose(rows);
+                [x, y, z]
+            }),
+        }
+    }
+}
+
+fn cross<T>(a: [T; 3], b: [T; 3]) -> [T; 3]
+where
+    T: Copy + Mul<Output = T> + Sub<Output = T>,
+{
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn sub<T: Copy + Sub<Output = T>>(a: [T; 3], b: [T; 3]) -> [T; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot<T>(a: [T; 3], b: [T; 3]) -> T
+where
+    T: Copy + Mul<Output = T> + Add<Output = T>,
+{
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Sum a per-triangle kernel over all faces
+fn sum_faces(
+    positions: &[f32],
+    indices: &[u32],
+    kernel: impl Fn(&TriangleLanes) -> f32x4 + Sync,
+) -> f32 {
+    let faces = &indices[..indices.len() - indices.len() % 3];
+    faces
+        .par_chunks(FACE_BLOCK * 3)
+        .map(|block| sum_block(positions, block, &kernel))
+        .sum()
+}
+
+fn sum_block(positions: &[f32], block: &[u32], kernel: &impl Fn(&TriangleLanes) -> f32x4) -> f32 {
+    let mut acc = f32x4::ZERO;
+    let mut groups = block.chunks_exact(FACE_LANES * 3);
+    for group in &mut groups {
+        let lanes = TriangleLanes::gather_fast(positions, group.try_into().unwrap())
+            .unwrap_or_else(|| TriangleLanes::gather(positions, group));
+        acc += kernel(&lanes);
+    }
+    if !groups.remainder().is_empty() {
+        acc += kernel(&TriangleLanes::gather(positions, groups.remainder()));
+    }
+    acc.reduce_add()
+}
+
+/// Total surface area of an indexed triangle mesh
+pub fn surface_area(positions: &[f32], indices: &[u32]) -> f32 {
+    sum_faces(positions, indices, |t| {
+        let c = cross(sub(t.v[1], t.v[0]), sub(t.v[2], t.v[0]));
+        dot(c, c).sqrt() * f32x4::splat(0.5)
+    })
+}
+
+/// Sum of signed tetrahedron volumes against the origin
+///
+/// The absolute value is the enclosed volume for a closed mesh.
+pub fn signed_volume(positions: &[f32], indices: &[u32]) -> f32 {
+    sum_faces(positions, indices, |t| {
+        dot(t.v[0], cross(t.v[1], t.v[2])) * f32x4::splat(1.0 / 6.0)
+    })
+}
+
+/// Axis-aligned bounds of interleaved xyz positions
+///
+/// Returns `None` when there are no vertices.
+pub fn bounds(positions: &[f32]) -> Option<([f32; 3], [f32; 3])> {
+    let positions = &positions[..positions.len() - positions.len() % 3];
+    if positions.is_empty() {
+        return None;
+    }
+
+    let merge = |(min_a, max_a): ([f32; 3], [f32; 3]), (min_b, max_b): ([f32; 3], [f32; 3])| {
+        (
+            [0, 1, 2].map(|i| min_a[i].min(min_b[i])),
+            [0, 1, 2].map(|i| max_a[i].max(max_b[i])),
+        )
+    };
+
+    Some(
+        positions
+            .par_chunks(VERTEX_BLOCK * 3)
+            .map(block_bounds)
+            .reduce(|| ([f32::MAX; 3], [f32::MIN; 3]), merge),
+    )
+}
+
+/// Bounds of one block, eight vertices (three registers) per step
+///
+/// 24 interleaved floats fill three registers exactly, so lane `j` of
+/// register `k` always holds axis `(8k + j) % 3`.
+fn block_bounds(block: &[f32]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32x8::splat(f32::MAX); 3];
+    let mut max = [f32x8::splat(f32::MIN); 3];
+
+    let mut chunks = block.chunks_exact(LANES * 3);
+    for chunk in &mut chunks {
+        for k in 0..3 {
+            let mut lanes = [0.0; LANES];
+            lanes.copy_from_slice(&chunk[k * LANES..(k + 1) * LANES]);
+            let r = f32x8::new(lanes);
+            min[k] = min[k].min(r);
+            max[k] = max[k].max(r);
+        }
+    }
+
+    let mut out_min = [f32::MAX; 3];
+    let mut out_max = [f32::MIN; 3];
+    for k in 0..3 {
+        let (lo, hi) = (min[k].to_array(), max[k].to_array());
+        for j in 0..LANES {
+            let axis = (k * LANES + j) % 3;
+            out_min[axis] = out_min[axis].min(lo[j]);
+            out_max[axis] = out_max[axis].max(hi[j]);
+        }
+    }
+    for v in chunks.remainder().chunks_exact(3) {
+        for axis in 0..3 {
+            out_min[axis] = out_min[axis].min(v[axis]);
+            out_max[axis] = out_max[axis].max(v[axis]);
+        }
+    }
+
+    (out_min, out_max)
+}
+
+/// Count vertices remaining after welding those closer than `epsilon`
+///
+/// Same greedy result as the pairwise scan: walking vertices in order, each
+/// unwelded vertex absorbs every later vertex within `epsilon`. Candidates
+/// come from a spatial hash with `epsilon`-sized cells, and distances to a
+/// cell's vertices are tested eight at a time.
+pub fn count_unique_vertices(positions: &[f32], epsilon: f32) -> usize {
+    let vertex_count = positions.len() / 3;
+    if epsilon.is_nan() || epsilon <= 0.0 {
+        return vertex_count;
+    }
+
+    let cell_of =
+        |i: usize| [0, 1, 2].map(|axis| (positions[i * 3 + axis] / epsilon).floor() as i64);
+
+    let mut cells: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+    for i in 0..vertex_count {
+        cells.entry(cell_of(i)).or_default().push(i as u32);
+    }
+
+    let epsilon_sq = f32x8::splat(epsilon * epsilon);
+    let mut is_duplicate = vec![false; vertex_count];
+    let mut unique_count = 0;
+
+    for i in 0..vertex_count {
+        if is_duplicate[i] {
+            continue;
+        }
+        unique_count += 1;
+
+        let p = [0, 1, 2].map(|axis| f32x8::splat(positions[i * 3 + axis]));
+        let [cx, cy, cz] = cell_of(i);
+
+        for dz in -1..=1 {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let Some(members) = cells.get(&[cx + dx, cy + dy, cz + dz]) else {
+                        continue;
+                    };
+                    for group in members.chunks(LANES) {
+                        let mut lanes = [[f32::MAX; LANES]; 3];
+                        for (lane, &j) in group.iter().enumerate() {
+                            for axis in 0..3 {
+                                lanes[axis][lane] = positions[j as usize * 3 + axis];
+                            }
+                        }
+                        let d = sub(lanes.map(f32x8::new), p);
+                        let mask = dot(d, d).cmp_lt(epsilon_sq).move_mask();
+
+                        for (lane, &j) in group.iter().enumerate() {
+                            if mask & (1 << lane) != 0 && j as usize > i {
+                                is_duplicate[j as usize] = true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    unique_count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid_mesh(n: usize) -> (Vec<f32>, Vec<u32>) {
+        let mut positions = Vec::new();
+        for y in 0..=n {
+            for x in 0..=n {
+                let (fx, fy) = (x as f32 / n as f32, y as f32 / n as f32);
+                positions.extend([fx, fy, (fx * 3.0).sin() * 0.1]);
+            }
+        }
+        let mut indices = Vec::new();
+        let row = (n + 1) as u32;
+        for y in 0..n as u32 {
+            for x in 0..n as u32 {
+                let i = y * row + x;
+                indices.extend([i, i + 1, i + row, i + 1, i + row + 1, i + row]);
+            }
+        }
+        (positions, indices)
+    }
+
+    #[test]
+    fn test_kernels_match_scalar() {
+        let (positions, mut indices) = grid_mesh(37);
+        indices.extend([0, 1, 9999]);
+
+        let mut area = 0.0f64;
+        let mut volume = 0.0f64;
+        for face in indices.chunks_exact(3) {
+            if face.iter().any(|&i| i as usize * 3 + 2 >= positions.len()) {
+                continue;
+            }
+            let v = [face[0], face[1], face[2]].map(|i| {
+                let b = i as usize * 3;
+                [0, 1, 2].map(|a| positions[b + a] as f64)
+            });
+            let e1 = [0, 1, 2].map(|a| v[1][a] - v[0][a]);
+            let e2 = [0, 1, 2].map(|a| v[2][a] - v[0][a]);
+            let c = [
+                e1[1] * e2[2] - e1[2] * e2[1],
+                e1[2] * e2[0] - e1[0] * e2[2],
+                e1[0] * e2[1] - e1[1] * e2[0],
+            ];
+            area += (c[0] * c[0] + c[1] * c[1] + c[2] * c[2]).sqrt() / 2.0;
+            volume += (v[0][0] * (v[1][1] * v[2][2] - v[1][2] * v[2][1])
+                + v[0][1] * (v[1][2] * v[2][0] - v[1][0] * v[2][2])
+                + v[0][2] * (v[1][0] * v[2][1] - v[1][1] * v[2][0]))
+                / 6.0;
+        }
+
+        assert!((surface_area(&positions, &indices) as f64 - area).abs() < 1e-3);
+        assert!((signed_volume(&positions, &indices) as f64 - volume).abs() < 1e-4);
+
+        let (min, max) = bounds(&positions).unwrap();
+        assert_eq!(min[0], 0.0);
+        assert_eq!(max[1], 1.0);
+        assert!(bounds(&[]).is_none());
+    }
+
+    #[test]
+    fn test_welding_matches_pairwise() {
+        let (mut positions, _) = grid_mesh(20);
+        let copy = positions.clone();
+        positions.extend(copy.iter().map(|v| v + 1e-5));
+
+        assert_eq!(count_unique_vertices(&positions, 1e-3), 21 * 21);
+        assert_eq!(count_unique_vertices(&positions, 0.0), 2 * 21 * 21);
+    }
+}
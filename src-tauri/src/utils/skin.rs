@@ -0,0 +1,132 @@
+//! Joint influences of skinned meshes
+//!
+//! Influences are stored glTF style: four joint indices and four weights per
+//! vertex. Simplification merges vertices, so their influences have to be
+//! merged as well; anything left over after the strongest four is dropped
+//! and the rest re-normalized, which keeps the result animatable.
+
+use crate::utils::decimate::DecimatedMesh;
+use std::collections::HashMap;
+
+/// Joints that can influence one vertex
+pub const MAX_INFLUENCES: usize = 4;
+
+/// Make each vertex's weights non-negative and sum to 1
+///
+/// Vertices without any weight are bound fully to their first joint.
+pub fn normalize_influences(weights: &mut [f32]) {
+    for vertex in weights.chunks_exact_mut(MAX_INFLUENCES) {
+        vertex.iter_mut().for_each(|w| *w = w.max(0.0));
+        let total: f32 = vertex.iter().sum();
+        if total > 0.0 {
+            vertex.iter_mut().for_each(|w| *w /= total);
+        } else {
+            vertex.copy_from_slice(&[1.0, 0.0, 0.0, 0.0]);
+        }
+    }
+}
+
+/// Combine the influences of the input vertices merged into each output vertex
+///
+/// Weights of the same joint are summed over the merged vertices; the four
+/// strongest joints are kept and re-normalized.
+pub fn merge_influences(
+    decimated: &DecimatedMesh,
+    joints: &[u32],
+    weights: &[f32],
+) -> (Vec<u32>, Vec<f32>) {
+    let vertex_count = decimated.positions.len() / 3;
+    let mut totals: Vec<HashMap<u32, f32>> = vec![HashMap::new(); vertex_count];
+
+    for (i, &out) in decimated.vertex_map.iter().enumerate() {
+        let Some(total) = totals.get_mut(out as usize) else {
+            continue;
+        };
+        let range = i * MAX_INFLUENCES..(i + 1) * MAX_INFLUENCES;
+        for (&joint, &weight) in joints[range.clone()].iter().zip(&weights[range]) {
+            if weight > 0.0 {
+                *total.entry(joint).or_default() += weight;
+            }
+        }
+    }
+
+    let mut out_joints = Vec::with_capacity(vertex_count * MAX_INFLUENCES);
+    let mut out_weights = Vec::with_capacity(vertex_count * MAX_INFLUENCES);
+    for total in totals {
+        let mut influences: Vec<(u32, f32)> = total.into_iter().collect();
+        influences.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        influences.resize(MAX_INFLUENCES, (0, 0.0));
+        out_joints.extend(influences.iter().map(|&(joint, _)| joint));
+        out_weights.extend(influences.iter().map(|&(_, weight)| weight));
+    }
+    normalize_influences(&mut out_weights);
+    (out_joints, out_weights)
+}
+
+/// Regions of a skinned mesh bound mostly to the same joint
+#[derive(Debug, Clone, Default)]
+pub struct JointRegions {
+    /// Dominant joint of each vertex
+    pub groups: Vec<u32>,
+    /// Vertices sharing an edge with a vertex of another region
+    pub locked: Vec<bool>,
+}
+
+/// Split a mesh by dominant joint so clustering never merges across bones
+///
+/// Without this, vertices on limbs that happen to be close together (legs,
+/// fingers) end up in the same cluster and get stretched when animated.
+/// Region borders are locked so neighbouring regions still meet.
+pub fn joint_regions(joints: &[u32], weights: &[f32], indices: &[u32]) -> JointRegions {
+    let groups: Vec<u32> = joints
+        .chunks_exact(MAX_INFLUENCES)
+        .zip(weights.chunks_exact(MAX_INFLUENCES))
+        .map(|(joints, weights)| {
+            let strongest = (0..MAX_INFLUENCES)
+                .max_by(|&a, &b| weights[a].total_cmp(&weights[b]).then(b.cmp(&a)))
+                .unwrap_or(0);
+            joints[strongest]
+        })
+        .collect();
+
+    let mut locked = vec![false; groups.len()];
+    for face in indices.chunks_exact(3) {
+        if face.iter().any(|&i| i as usize >= groups.len()) {
+            continue;
+        }
+        let [a, b, c] = [face[0], face[1], face[2]].map(|i| i as usize);
+        if groups[a] != groups[b] || groups[b] != groups[c] {
+            for i in [a, b, c] {
+                locked[i] = true;
+            }
+        }
+    }
+    JointRegions { groups, locked }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merged_influences_keep_strongest_and_normalize() {
+        // Two input vertices merged into one output vertex
+        let decimated = DecimatedMesh {
+            positions: vec![0.0; 3],
+            vertex_map: vec![0, 0],
+            ..Default::default()
+        };
+        let joints = [1, 2, 3, 4, 1, 5, 6, 0];
+        let weights = [0.4, 0.3, 0.2, 0.1, 0.6, 0.25, 0.15, 0.0];
+        let (out_joints, out_weights) = merge_influences(&decimated, &joints, &weights);
+
+        assert_eq!(out_joints, vec![1, 2, 5, 3]);
+        let total: f32 = out_weights.iter().sum();
+        assert!((total - 1.0).abs() < 1e-6);
+        assert!((out_weights[0] - 1.0 / 1.75).abs() < 1e-6);
+
+        let mut empty = [0.0, -1.0, 0.0, 0.0];
+        normalize_influences(&mut empty);
+        assert_eq!(empty, [1.0, 0.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,201 @@
+//! Smart collections: saved searches over the library
+//!
+//! A smart collection stores rules rather than members, so it keeps up
+//! with the library by itself: "STL models over a million triangles" picks
+//! up a new heavy STL the next time it's evaluated. Collections live in the
+//! settings and are evaluated against the root indexes when asked.
+
+use serde::{Deserialize, Serialize};
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// What an asset must be to belong to a collection; every rule narrows it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CollectionRules {
+    /// File extensions without the dot, such as "glb" or "stl"; an asset
+    /// with a file of any of them matches, and empty matches every asset
+    pub formats: Vec<String>,
+    /// Triangle counts are read from glTF models only, so these rules leave
+    /// out assets without one
+    pub min_triangles: Option<u64>,
+    pub max_triangles: Option<u64>,
+    /// `Some(false)` for assets without a category, `Some(true)` for those
+    /// with one
+    pub categorized: Option<bool>,
+    /// Only assets in this category, ignoring case
+    pub category: Option<String>,
+    /// Only assets whose model or image file was written in the last this
+    /// many days, as importing one does
+    pub added_within_days: Option<u32>,
+    /// Only assets rated at least this many stars
+    pub min_rating: Option<u8>,
+    pub favorites_only: bool,
+    /// `Some(false)` for assets without a recorded license
+    pub licensed: Option<bool>,
+    /// Only assets whose id contains this, ignoring case
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmartCollection {
+    pub id: String,
+    pub name: String,
+    pub rules: CollectionRules,
+}
+
+/// What the rules look at of one asset
+#[derive(Debug, Clone, Default)]
+pub struct AssetFacts<'a> {
+    pub id: &'a str,
+    /// Extensions of the asset's files, lowercase
+    pub formats: Vec<String>,
+    pub category: Option<&'a str>,
+    pub rating: Option<u8>,
+    pub favorite: bool,
+    pub licensed: bool,
+    /// `None` when not needed or not readable
+    pub triangles: Option<u64>,
+    pub modified_ms: Option<u64>,
+}
+
+impl CollectionRules {
+    /// Whether `triangles` must be filled in to evaluate the rules
+    pub fn needs_triangles(&self) -> bool {
+        self.min_triangles.is_some() || self.max_triangles.is_some()
+    }
+
+    /// Whether `modified_ms` must be filled in to evaluate the rules
+    pub fn needs_modified(&self) -> bool {
+        self.added_within_days.is_some()
+    }
+
+    pub fn matches(&self, asset: &AssetFacts, now_ms: u64) -> bool {
+        let contains = |text: &str, part: &str| text.to_lowercase().contains(&part.to_lowercase());
+        let formats_match = self.formats.is_empty()
+            || self.formats.iter().any(|format| {
+                let format = format.trim().trim_start_matches('.').to_lowercase();
+                asset.formats.contains(&format)
+            });
+        let triangles_match = !self.needs_triangles()
+            || asset.triangles.is_some_and(|count| {
+                self.min_triangles.is_none_or(|min| count >= min)
+                    && self.max_triangles.is_none_or(|max| count <= max)
+            });
+        let recent = self.added_within_days.is_none_or(|days| {
+            asset
+                .modified_ms
+                .is_some_and(|ms| now_ms.saturating_sub(ms) <= days as u64 * DAY_MS)
+        });
+        formats_match
+            && triangles_match
+            && recent
+            && self
+                .categorized
+                .is_none_or(|categorized| asset.category.is_some() == categorized)
+            && self.category.as_ref().is_none_or(|category| {
+                asset
+                    .category
+                    .is_some_and(|c| c.eq_ignore_ascii_case(category.trim()))
+            })
+            && self
+                .min_rating
+                .is_none_or(|min| asset.rating.is_some_and(|r| r >= min))
+            && (!self.favorites_only || asset.favorite)
+            && self
+                .licensed
+                .is_none_or(|licensed| asset.licensed == licensed)
+            && self
+                .text
+                .as_deref()
+                .is_none_or(|text| contains(asset.id, text.trim()))
+    }
+}
+
+/// Check collections about to be saved to the settings
+pub fn validate(collections: &[SmartCollection]) -> Result<(), String> {
+    for (i, collection) in collections.iter().enumerate() {
+        if collection.id.trim().is_empty() || collection.name.trim().is_empty() {
+            return Err("Smart collections need an id and a name".to_string());
+        }
+        if collections[..i].iter().any(|c| c.id == collection.id) {
+            return Err(format!(
+                "Smart collection {} is listed twice",
+                collection.id
+            ));
+        }
+        let rules = &collection.rules;
+        if let (Some(min), Some(max)) = (rules.min_triangles, rules.max_triangles) {
+            if min > max {
+                return Err(format!(
+                    "{} asks for at least {} and at most {} triangles",
+                    collection.name, min, max
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_match_heavy_stl_and_recent_uncategorized_imports() {
+        let now = 100 * DAY_MS;
+        let heavy_stl = AssetFacts {
+            id: "Statue",
+            formats: vec!["stl".to_string()],
+            triangles: Some(2_000_000),
+            modified_ms: Some(now - 30 * DAY_MS),
+            ..Default::default()
+        };
+        let new_rock = AssetFacts {
+            id: "rock",
+            formats: vec!["glb".to_string(), "obj".to_string()],
+            modified_ms: Some(now - 2 * DAY_MS),
+            ..Default::default()
+        };
+
+        let heavy = CollectionRules {
+            formats: vec![".STL".to_string()],
+            min_triangles: Some(1_000_000),
+            ..Default::default()
+        };
+        assert!(heavy.needs_triangles() && !heavy.needs_modified());
+        assert!(heavy.matches(&heavy_stl, now));
+        assert!(!heavy.matches(&new_rock, now));
+
+        let recent = CollectionRules {
+            categorized: Some(false),
+            added_within_days: Some(7),
+            ..Default::default()
+        };
+        assert!(recent.matches(&new_rock, now));
+        assert!(!recent.matches(&heavy_stl, now));
+        let categorized = AssetFacts {
+            category: Some("Props"),
+            ..new_rock.clone()
+        };
+        assert!(!recent.matches(&categorized, now));
+
+        // An unknown triangle count never satisfies a triangle rule
+        let below = CollectionRules {
+            max_triangles: Some(10),
+            ..Default::default()
+        };
+        assert!(!below.matches(&new_rock, now));
+        assert!(CollectionRules::default().matches(&new_rock, now));
+
+        let collection = |id: &str| SmartCollection {
+            id: id.to_string(),
+            name: "Heavy".to_string(),
+            rules: heavy.clone(),
+        };
+        assert!(validate(&[collection("a"), collection("b")]).is_ok());
+        assert!(validate(&[collection("a"), collection("a")]).is_err());
+        assert!(validate(&[collection(" ")]).is_err());
+    }
+}
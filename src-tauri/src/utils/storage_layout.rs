@@ -0,0 +1,720 @@
+//! Where assets live inside a storage folder
+//!
+//! A layout template names each model file relative to the storage folder,
+//! with placeholders for the asset's `{id}`, the file's `{ext}`, the asset's
+//! `{category}`, and `{yyyy}`, `{mm}` and `{dd}` for the day it was added.
+//! The default keeps each asset in a folder of its own. Thumbnails sit next
+//! to the model: as `thumbnail.png` when the folder belongs to one asset and
+//! as `<id>.thumbnail.png` when it's shared. Image files laid out the same
+//! way are texture assets, unless their asset also has a model.
+
+use crate::utils::dependencies;
+use crate::utils::image_thumbnail;
+use crate::utils::media;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// One folder per asset; the original layout
+pub const PER_ASSET: &str = "{id}/{id}.{ext}";
+/// Every file directly in the storage folder
+pub const FLAT: &str = "{id}.{ext}";
+pub const BY_CATEGORY: &str = "{category}/{id}/{id}.{ext}";
+pub const BY_DATE: &str = "{yyyy}/{mm}/{id}/{id}.{ext}";
+/// Category of assets that haven't been given one
+pub const UNCATEGORIZED: &str = "uncategorized";
+/// Model formats an asset is stored in
+const MODEL_EXTENSIONS: [&str; 3] = ["glb", "obj", "fbx"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageLayout {
+    pub template: String,
+}
+
+impl Default for StorageLayout {
+    fn default() -> Self {
+        Self {
+            template: PER_ASSET.to_string(),
+        }
+    }
+}
+
+impl StorageLayout {
+    pub fn parse(&self) -> Result<Template, String> {
+        Template::parse(&self.template)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Field {
+    Id,
+    Ext,
+    Category,
+    Year,
+    Month,
+    Day,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    Field(Field),
+}
+
+/// A parsed layout template
+#[derive(Debug, Clone)]
+pub struct Template {
+    components: Vec<Vec<Token>>,
+}
+
+/// A calendar day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// The UTC day `time` falls on
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let days = time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| (d.as_secs() / 86_400) as i64);
+        // Days to civil date, after Howard Hinnant's algorithm
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        Self {
+            year: yoe + era * 400 + i64::from(month <= 2),
+            month,
+            day,
+        }
+    }
+}
+
+/// Values filled into or read back from a template
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AssetLocation {
+    pub id: String,
+    /// Model file extension, without the dot
+    pub ext: String,
+    pub category: Option<String>,
+    pub date: Option<Date>,
+}
+
+/// An asset found in a storage folder
+#[derive(Debug, Clone)]
+pub struct LaidOutAsset {
+    pub id: String,
+    pub category: Option<String>,
+    /// Folder holding the model files
+    pub dir: PathBuf,
+    /// Model files, in `glb`, `obj`, `fbx` order
+    pub models: Vec<PathBuf>,
+    /// Image files of a texture asset; empty when the asset has models
+    pub images: Vec<PathBuf>,
+}
+
+impl Template {
+    /// Parse a template such as `{category}/{id}/{id}.{ext}`
+    ///
+    /// The file name must contain `{id}` and end in `.{ext}`, and no other
+    /// part may use `{ext}`.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("Invalid layout template {}: {}", template, reason);
+        let mut components = Vec::new();
+        for part in template.split('/') {
+            if part.is_empty() || part == "." || part == ".." || part.contains('\\') {
+                return Err(invalid("each part must be a plain folder or file name"));
+            }
+            let mut tokens = Vec::new();
+            let mut rest = part;
+            while let Some(start) = rest.find('{') {
+                if start > 0 {
+                    tokens.push(Token::Literal(rest[..start].to_string()));
+                }
+                let end = rest[start..]
+                    .find('}')
+                    .ok_or_else(|| invalid("unclosed {"))?;
+                let field = match &rest[start + 1..start + end] {
+                    "id" => Field::Id,
+                    "ext" => Field::Ext,
+                    "category" => Field::Category,
+                    "yyyy" => Field::Year,
+                    "mm" => Field::Month,
+                    "dd" => Field::Day,
+                    other => return Err(invalid(&format!("unknown placeholder {{{}}}", other))),
+                };
+                if matches!(tokens.last(), Some(Token::Field(_))) {
+                    return Err(invalid("placeholders must be separated"));
+                }
+                tokens.push(Token::Field(field));
+                rest = &rest[start + end + 1..];
+            }
+            if !rest.is_empty() {
+                tokens.push(Token::Literal(rest.to_string()));
+            }
+            components.push(tokens);
+        }
+
+        let (file, folders) = components.split_last().ok_or_else(|| invalid("empty"))?;
+        let ends_in_ext = matches!(
+            file.as_slice(),
+            [.., Token::Literal(dot), Token::Field(Field::Ext)] if dot.ends_with('.')
+        );
+        if !ends_in_ext || !file.contains(&Token::Field(Field::Id)) {
+            return Err(invalid("file name must contain {id} and end in .{ext}"));
+        }
+        let ext_in_folder = folders
+            .iter()
+            .any(|tokens| tokens.contains(&Token::Field(Field::Ext)));
+        if ext_in_folder
+            || file
+                .iter()
+                .filter(|t| **t == Token::Field(Field::Ext))
+                .count()
+                > 1
+        {
+            return Err(invalid("{ext} may only end the file name"));
+        }
+        Ok(Self { components })
+    }
+
+    /// Whether each asset gets a folder of its own
+    pub fn per_asset_folder(&self) -> bool {
+        let folders = &self.components[..self.components.len() - 1];
+        folders
+            .iter()
+            .any(|tokens| tokens.contains(&Token::Field(Field::Id)))
+    }
+
+    /// Path of a model file relative to the storage folder
+    pub fn fill(&self, location: &AssetLocation) -> PathBuf {
+        let date = location.date.unwrap_or_default();
+        let category = match location.category.as_deref() {
+            None | Some("" | "." | "..") => UNCATEGORIZED.to_string(),
+            Some(category) => category.replace(['/', '\\'], "_"),
+        };
+        self.components
+            .iter()
+            .map(|tokens| {
+                tokens
+                    .iter()
+                    .map(|token| match token {
+                        Token::Literal(text) => text.clone(),
+                        Token::Field(Field::Id) => location.id.clone(),
+                        Token::Field(Field::Ext) => location.ext.clone(),
+                        Token::Field(Field::Category) => category.clone(),
+                        Token::Field(Field::Year) => format!("{:04}", date.year),
+                        Token::Field(Field::Month) => format!("{:02}", date.month),
+                        Token::Field(Field::Day) => format!("{:02}", date.day),
+                    })
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    /// Read the placeholders back from a path relative to the storage folder
+    pub fn matches(&self, relative: &Path) -> Option<AssetLocation> {
+        let parts: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        if parts.len() != self.components.len() {
+            return None;
+        }
+        let mut captures = Vec::new();
+        for (tokens, part) in self.components.iter().zip(&parts) {
+            if !match_tokens(tokens, part, &mut captures) {
+                return None;
+            }
+        }
+
+        let mut values: HashMap<Field, String> = HashMap::new();
+        for (field, value) in captures {
+            if values.get(&field).is_some_and(|seen| *seen != value) {
+                return None;
+            }
+            values.insert(field, value);
+        }
+        let number = |field| values.get(&field).and_then(|v| v.parse().ok());
+        Some(AssetLocation {
+            id: values.get(&Field::Id)?.clone(),
+            ext: values.get(&Field::Ext)?.clone(),
+            category: values.get(&Field::Category).cloned(),
+            date: number(Field::Year).map(|year| Date {
+                year,
+                month: number(Field::Month).unwrap_or(1) as u32,
+                day: number(Field::Day).unwrap_or(1) as u32,
+            }),
+        })
+    }
+
+    /// Thumbnail of the asset `id` whose model is in `dir`
+    pub fn thumbnail(&self, dir: &Path, id: &str) -> PathBuf {
+        if self.per_asset_folder() {
+            dir.join("thumbnail.png")
+        } else {
+            dir.join(format!("{}.thumbnail.png", id))
+        }
+    }
+
+    /// Every asset laid out under `root`, sorted by folder and id
+    pub fn scan(&self, root: &Path) -> Result<Vec<LaidOutAsset>, String> {
+        let mut assets: Vec<LaidOutAsset> = Vec::new();
+        let mut by_key: HashMap<(PathBuf, String), usize> = HashMap::new();
+        let walk = WalkDir::new(root)
+            .min_depth(self.components.len())
+            .max_depth(self.components.len())
+            .sort_by_file_name();
+        for entry in walk {
+            let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            let Some(location) = self.matches(relative) else {
+                continue;
+            };
+            let is_model = MODEL_EXTENSIONS.contains(&location.ext.to_lowercase().as_str());
+            let is_thumbnail =
+                entry.file_name() == "thumbnail.png" || location.id.ends_with(".thumbnail");
+            let is_image = image_thumbnail::is_image(entry.path()) && !is_thumbnail;
+            if !is_model && !is_image {
+                continue;
+            }
+            let dir = entry.path().parent().unwrap_or(root).to_path_buf();
+            let index = *by_key
+                .entry((dir.clone(), location.id.clone()))
+                .or_insert_with(|| {
+                    assets.push(LaidOutAsset {
+                        id: location.id.clone(),
+                        category: location.category.clone(),
+                        dir,
+                        models: Vec::new(),
+                        images: Vec::new(),
+                    });
+                    assets.len() - 1
+                });
+            let files = if is_model {
+                &mut assets[index].models
+            } else {
+                &mut assets[index].images
+            };
+            files.push(entry.path().to_path_buf());
+        }
+        for asset in &mut assets {
+            // Images beside a model of the same name are its textures
+            if !asset.models.is_empty() {
+                asset.images.clear();
+            }
+            asset.models.sort_by_key(|model| {
+                let ext = model
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                MODEL_EXTENSIONS.iter().position(|known| *known == ext)
+            });
+        }
+        Ok(assets)
+    }
+}
+
+/// Match one path component, recording what each placeholder stands for
+///
+/// Placeholders take as much as they can, so `{id}.{ext}` reads
+/// `rock.v2.glb` as `rock.v2` and `glb`.
+fn match_tokens(tokens: &[Token], text: &str, captures: &mut Vec<(Field, String)>) -> bool {
+    match tokens.split_first() {
+        None => text.is_empty(),
+        Some((Token::Literal(literal), rest)) => text
+            .strip_prefix(literal.as_str())
+            .is_some_and(|tail| match_tokens(rest, tail, captures)),
+        Some((Token::Field(field), rest)) => {
+            for end in (1..=text.len()).rev() {
+                if !text.is_char_boundary(end) {
+                    continue;
+                }
+                let (value, tail) = text.split_at(end);
+                let valid = match field {
+                    Field::Year => value.len() == 4 && value.bytes().all(|b| b.is_ascii_digit()),
+                    Field::Month | Field::Day => {
+                        value.len() == 2 && value.bytes().all(|b| b.is_ascii_digit())
+                    }
+                    Field::Ext => !value.contains('.'),
+                    Field::Id | Field::Category => true,
+                };
+                if !valid {
+                    continue;
+                }
+                captures.push((*field, value.to_string()));
+                if match_tokens(rest, tail, captures) {
+                    return true;
+                }
+                captures.pop();
+            }
+            false
+        }
+    }
+}
+
+/// Files to move to reorganize a storage folder from one layout to another
+#[derive(Debug, Clone, Default)]
+pub struct MigrationPlan {
+    pub asset_count: usize,
+    pub moves: Vec<(PathBuf, PathBuf)>,
+    /// Folders that held a single asset and should be empty afterwards
+    pub emptied: Vec<PathBuf>,
+}
+
+/// Plan moving every asset under `root` from the `from` layout to `to`
+pub fn plan_migration(
+    root: &Path,
+    from: &Template,
+    to: &Template,
+    categories: &HashMap<String, String>,
+) -> Result<MigrationPlan, String> {
+    plan_moves(&from.scan(root)?, from, root, to, categories)
+        .map_err(|e| format!("Can't reorganize {}: {}", root.display(), e))
+}
+
+/// Plan moving `assets`, laid out as `from`, to where `to` puts them under
+/// `root`
+///
+/// An asset takes its model or image files and thumbnail along, plus
+/// everything else in its folder when it had one of its own, or the
+/// dependencies of its models and the media named after it when the folder
+/// was shared. `categories` assigns categories by asset id over those read
+/// from the current layout; dates come from the first file's modification
+/// time. Fails without planning anything if two files would land in the
+/// same place or a destination already exists.
+pub fn plan_moves(
+    assets: &[LaidOutAsset],
+    from: &Template,
+    root: &Path,
+    to: &Template,
+    categories: &HashMap<String, String>,
+) -> Result<MigrationPlan, String> {
+    let mut placements = Vec::with_capacity(assets.len());
+    for asset in assets {
+        let category = categories
+            .get(&asset.id)
+            .cloned()
+            .or_else(|| asset.category.clone());
+        placements.push((asset, place_asset(asset, from, root, to, category)?));
+    }
+    plan_placements(placements, from)
+}
+
+/// Plan renaming each of `assets`, laid out as `layout` under `root`, to
+/// the id paired with it
+///
+/// Files are placed as `plan_moves` places them, with media named after
+/// the asset in a shared folder renamed along. Fails on the same conflicts.
+pub fn plan_renames(
+    assets: &[(LaidOutAsset, String)],
+    layout: &Template,
+    root: &Path,
+) -> Result<MigrationPlan, String> {
+    let mut placements = Vec::with_capacity(assets.len());
+    for (asset, id) in assets {
+        let category = asset.category.clone();
+        placements.push((
+            asset,
+            place_asset_as(asset, id, layout, root, layout, category)?,
+        ));
+    }
+    plan_placements(placements, layout)
+}
+
+fn plan_placements(
+    placements: Vec<(&LaidOutAsset, Option<PlacedAsset>)>,
+    from: &Template,
+) -> Result<MigrationPlan, String> {
+    let mut plan = MigrationPlan {
+        asset_count: placements.len(),
+        ..Default::default()
+    };
+    let mut claimed: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut moved: HashSet<PathBuf> = HashSet::new();
+    let mut conflicts = Vec::new();
+
+    for (asset, placed) in placements {
+        let Some(placed) = placed else {
+            continue;
+        };
+        for (source, dest) in placed.files {
+            if let Some(previous) = claimed.get(&dest) {
+                if *previous != source {
+                    conflicts.push(format!(
+                        "{} and {} would both move to {}",
+                        previous.display(),
+                        source.display(),
+                        dest.display()
+                    ));
+                }
+                continue;
+            }
+            if !moved.insert(source.clone()) {
+                conflicts.push(format!("{} is shared by several assets", source.display()));
+                continue;
+            }
+            claimed.insert(dest.clone(), source.clone());
+            if source != dest {
+                plan.moves.push((source, dest));
+            }
+        }
+        if from.per_asset_folder() && placed.dir != asset.dir {
+            plan.emptied.push(asset.dir.clone());
+        }
+    }
+
+    for (source, dest) in &plan.moves {
+        if dest.exists() {
+            conflicts.push(format!(
+                "{} would replace existing {}",
+                source.display(),
+                dest.display()
+            ));
+        }
+    }
+    if !conflicts.is_empty() {
+        return Err(conflicts.join("; "));
+    }
+    Ok(plan)
+}
+
+/// Where the files of an asset go under another layout
+#[derive(Debug, Clone, Default)]
+pub struct PlacedAsset {
+    /// Folder the asset's models land in
+    pub dir: PathBuf,
+    /// Each file and its destination
+    pub files: Vec<(PathBuf, PathBuf)>,
+}
+
+/// Every file of `asset`, laid out as `from`, with where it goes when `to`
+/// puts the asset under `root` in `category`
+///
+/// The files are those `plan_moves` takes along. `None` if the asset has no
+/// model or image file.
+pub fn place_asset(
+    asset: &LaidOutAsset,
+    from: &Template,
+    root: &Path,
+    to: &Template,
+    category: Option<String>,
+) -> Result<Option<PlacedAsset>, String> {
+    place_asset_as(asset, &asset.id, from, root, to, category)
+}
+
+/// `place_asset` for the asset renamed to `id`
+///
+/// Media named after the asset in a shared folder are renamed with it;
+/// other files keep their names, since models may refer to them.
+fn place_asset_as(
+    asset: &LaidOutAsset,
+    id: &str,
+    from: &Template,
+    root: &Path,
+    to: &Template,
+    category: Option<String>,
+) -> Result<Option<PlacedAsset>, String> {
+    let Some(first) = asset.models.first().or(asset.images.first()) else {
+        return Ok(None);
+    };
+    let added = fs::metadata(first)
+        .and_then(|m| m.modified())
+        .unwrap_or(UNIX_EPOCH);
+    let location = AssetLocation {
+        id: id.to_string(),
+        ext: String::new(),
+        category,
+        date: Some(Date::from_system_time(added)),
+    };
+    let place = |ext: &str| {
+        root.join(to.fill(&AssetLocation {
+            ext: ext.to_string(),
+            ..location.clone()
+        }))
+    };
+    let new_dir = place("glb").parent().unwrap_or(root).to_path_buf();
+
+    let mut files: Vec<(PathBuf, PathBuf)> = asset
+        .models
+        .iter()
+        .chain(&asset.images)
+        .map(|model| {
+            let ext = model.extension().unwrap_or_default().to_string_lossy();
+            (model.clone(), place(&ext))
+        })
+        .collect();
+    let thumbnail = from.thumbnail(&asset.dir, &asset.id);
+    if thumbnail.is_file() {
+        files.push((thumbnail.clone(), to.thumbnail(&new_dir, id)));
+    }
+    let mut sidecars = Vec::new();
+    let mut others: Vec<PathBuf> = if from.per_asset_folder() {
+        WalkDir::new(&asset.dir)
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .filter(|path| {
+                !asset.models.contains(path) && !asset.images.contains(path) && *path != thumbnail
+            })
+            .collect()
+    } else {
+        let mut found = Vec::new();
+        for model in &asset.models {
+            found.extend(
+                dependencies::resolve(model)?
+                    .into_iter()
+                    .filter(|d| d.exists && d.relative_path.is_some())
+                    .map(|d| PathBuf::from(d.path)),
+            );
+        }
+        sidecars = media::sidecars(&asset.dir, &asset.id, false);
+        found.extend(sidecars.iter().cloned());
+        found
+    };
+    others.sort();
+    others.dedup();
+    for other in others {
+        let relative = other.strip_prefix(&asset.dir).unwrap_or(&other);
+        let mut dest = new_dir.join(relative);
+        if sidecars.contains(&other) && id != asset.id {
+            // `<id>.mp4` or `<id>.<anything>.mp4`; the id is the prefix
+            let name = dest.file_name().unwrap_or_default().to_string_lossy();
+            let renamed = format!("{}{}", id, &name[asset.id.len().min(name.len())..]);
+            dest.set_file_name(renamed);
+        }
+        files.push((other.clone(), dest));
+    }
+    Ok(Some(PlacedAsset {
+        dir: new_dir,
+        files,
+    }))
+}
+
+/// Remove folders a carried-out plan emptied, and parents it left empty,
+/// up to `root`; folders with anything left in them stay
+pub fn remove_emptied(plan: &MigrationPlan, root: &Path) {
+    for dir in &plan.emptied {
+        let mut dir = dir.as_path();
+        while dir != root && dir.starts_with(root) && fs::remove_dir(dir).is_ok() {
+            dir = dir.parent().unwrap_or(root);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_and_migration() {
+        let leap_day = UNIX_EPOCH + std::time::Duration::from_secs(19_782 * 86_400 + 3_600);
+        let date = Date::from_system_time(leap_day);
+        assert_eq!((date.year, date.month, date.day), (2024, 2, 29));
+
+        let by_date = Template::parse("{yyyy}/{mm}/{id}.{ext}").unwrap();
+        let location = AssetLocation {
+            id: "rock.v2".to_string(),
+            ext: "glb".to_string(),
+            category: None,
+            date: Some(date),
+        };
+        let path = by_date.fill(&location);
+        assert_eq!(path, PathBuf::from("2024/02/rock.v2.glb"));
+        let read = by_date.matches(&path).unwrap();
+        assert_eq!((read.id.as_str(), read.ext.as_str()), ("rock.v2", "glb"));
+        assert!(by_date.matches(Path::new("24/02/rock.glb")).is_none());
+        assert!(!by_date.per_asset_folder());
+        assert!(Template::parse(PER_ASSET)
+            .unwrap()
+            .matches(Path::new("rock/tree.glb"))
+            .is_none());
+        for bad in [
+            "{id}",
+            "{id}.{ext}/x.{ext}",
+            "../{id}.{ext}",
+            "{id}{category}.{ext}",
+            "{size}/{id}.{ext}",
+        ] {
+            assert!(Template::parse(bad).is_err(), "{}", bad);
+        }
+
+        let root = std::env::temp_dir().join(format!("sweedle-layout-{}", std::process::id()));
+        fs::create_dir_all(root.join("rock/textures")).unwrap();
+        fs::create_dir_all(root.join("tree")).unwrap();
+        fs::write(root.join("rock/rock.glb"), b"glb").unwrap();
+        fs::write(root.join("rock/rock.obj"), b"mtllib rock.mtl\n").unwrap();
+        fs::write(root.join("rock/rock.mtl"), b"map_Kd textures/rock.png\n").unwrap();
+        fs::write(root.join("rock/textures/rock.png"), b"png").unwrap();
+        fs::write(root.join("rock/thumbnail.png"), b"thumb").unwrap();
+        fs::write(root.join("tree/tree.fbx"), b"fbx").unwrap();
+
+        let per_asset = Template::parse(PER_ASSET).unwrap();
+        let by_category = Template::parse(BY_CATEGORY).unwrap();
+        let flat = Template::parse(FLAT).unwrap();
+        let assets = per_asset.scan(&root).unwrap();
+        assert_eq!(assets.len(), 2);
+        assert_eq!(
+            assets[0].models,
+            vec![root.join("rock/rock.glb"), root.join("rock/rock.obj")]
+        );
+
+        let categories = HashMap::from([("rock".to_string(), "props".to_string())]);
+        let plan = plan_migration(&root, &per_asset, &by_category, &categories).unwrap();
+        let relative = |path: &Path| path.strip_prefix(&root).unwrap().to_path_buf();
+        let moves: Vec<(PathBuf, PathBuf)> = plan
+            .moves
+            .iter()
+            .map(|(from, to)| (relative(from), relative(to)))
+            .collect();
+        assert_eq!(plan.asset_count, 2);
+        assert!(moves.contains(&(
+            "rock/textures/rock.png".into(),
+            "props/rock/textures/rock.png".into()
+        )));
+        assert!(moves.contains(&(
+            "rock/thumbnail.png".into(),
+            "props/rock/thumbnail.png".into()
+        )));
+        assert!(moves.contains(&("tree/tree.fbx".into(), "uncategorized/tree/tree.fbx".into())));
+        assert_eq!(moves.len(), 6);
+        assert_eq!(plan.emptied, vec![root.join("rock"), root.join("tree")]);
+
+        // Flattening renames thumbnails and keeps dependencies beside the model
+        let plan = plan_migration(&root, &per_asset, &flat, &HashMap::new()).unwrap();
+        let moves: Vec<PathBuf> = plan.moves.iter().map(|(_, to)| relative(to)).collect();
+        assert!(moves.contains(&"rock.thumbnail.png".into()));
+        assert!(moves.contains(&"textures/rock.png".into()));
+
+        // Two assets can't end up in the same place
+        fs::create_dir_all(root.join("props/rock")).unwrap();
+        fs::write(root.join("props/rock/rock.glb"), b"other").unwrap();
+        assert!(plan_migration(&root, &per_asset, &by_category, &categories).is_err());
+
+        // Renaming moves the folder and model files but keeps what models refer to
+        let renamed = [(assets[0].clone(), "stone".to_string())];
+        let plan = plan_renames(&renamed, &per_asset, &root).unwrap();
+        let moves: Vec<PathBuf> = plan.moves.iter().map(|(_, to)| relative(to)).collect();
+        assert!(moves.contains(&"stone/stone.glb".into()));
+        assert!(moves.contains(&"stone/rock.mtl".into()));
+        assert!(moves.contains(&"stone/thumbnail.png".into()));
+        assert_eq!(plan.emptied, vec![root.join("rock")]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
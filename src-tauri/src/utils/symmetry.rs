@@ -0,0 +1,498 @@
+//! Mirror symmetry of meshes
+//!
+//! Assets are modelled around an axis, so the planes tried are the three
+//! axis planes through the centroid, each re-centered on the midpoints of
+//! matched vertex pairs. A vertex counts as mirrored when its reflection
+//! lands within a tolerance of another vertex.
+
+use crate::utils::mesh_store::MeshData;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Default tolerance as a fraction of the bounding box diagonal
+pub const DEFAULT_TOLERANCE: f32 = 0.002;
+
+/// The first matching pass uses a wider radius, since asymmetric detail
+/// pulls the centroid off the true plane
+const COARSE_RADIUS: f32 = 16.0;
+
+/// Regions reported by [`detect`], largest first
+const MAX_REGIONS: usize = 32;
+
+/// Plane of points `p` with `dot(normal, p) == offset`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MirrorPlane {
+    pub normal: [f32; 3],
+    pub offset: f32,
+}
+
+impl MirrorPlane {
+    pub fn distance(&self, p: [f32; 3]) -> f32 {
+        dot(self.normal, p) - self.offset
+    }
+
+    pub fn reflect(&self, p: [f32; 3]) -> [f32; 3] {
+        let d = 2.0 * self.distance(p);
+        [0, 1, 2].map(|axis| p[axis] - d * self.normal[axis])
+    }
+
+    fn reflect_direction(&self, v: [f32; 3]) -> [f32; 3] {
+        let d = 2.0 * dot(self.normal, v);
+        [0, 1, 2].map(|axis| v[axis] - d * self.normal[axis])
+    }
+
+    /// The same plane with a unit normal
+    pub fn normalized(&self) -> Result<Self, String> {
+        let len = dot(self.normal, self.normal).sqrt();
+        if !(len > 0.0 && len.is_finite() && self.offset.is_finite()) {
+            return Err("Mirror plane needs a finite, non-zero normal".to_string());
+        }
+        Ok(Self {
+            normal: self.normal.map(|n| n / len),
+            offset: self.offset / len,
+        })
+    }
+}
+
+/// What `mirror_mesh` does with the mesh
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorMode {
+    /// Rebuild the back half as a mirror of the front half
+    #[default]
+    Symmetrize,
+    /// Mirror the whole mesh
+    Flip,
+}
+
+/// Connected vertices that have no mirrored counterpart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsymmetricRegion {
+    pub vertex_count: usize,
+    pub centroid: [f32; 3],
+    pub bounds_min: [f32; 3],
+    pub bounds_max: [f32; 3],
+}
+
+/// How well one plane mirrors the mesh
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaneScore {
+    pub plane: MirrorPlane,
+    /// Fraction of vertices with a mirrored counterpart
+    pub score: f32,
+}
+
+/// Result of [`detect`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymmetryReport {
+    pub plane: MirrorPlane,
+    pub score: f32,
+    /// Every plane tried, best first
+    pub candidates: Vec<PlaneScore>,
+    pub tolerance: f32,
+    pub asymmetric_vertex_count: usize,
+    /// Largest regions first
+    pub asymmetric_regions: Vec<AsymmetricRegion>,
+}
+
+/// Find the dominant mirror plane and the regions that break it
+///
+/// `tolerance` is in mesh units; by default it is a small fraction of the
+/// bounding box diagonal.
+pub fn detect(mesh: &MeshData, tolerance: Option<f32>) -> Result<SymmetryReport, String> {
+    let points: Vec<[f32; 3]> = mesh
+        .positions
+        .chunks_exact(3)
+        .map(|p| [p[0], p[1], p[2]])
+        .collect();
+    if points.is_empty() {
+        return Err("No vertices provided".to_string());
+    }
+
+    let (min, max) = bounds(&points);
+    let diagonal = dot(sub(max, min), sub(max, min)).sqrt();
+    let tolerance = tolerance.unwrap_or(diagonal * DEFAULT_TOLERANCE);
+    if !(tolerance > 0.0 && tolerance.is_finite()) {
+        return Err("Mesh is degenerate or tolerance is not positive".to_string());
+    }
+
+    let coarse = PointIndex::new(&points, tolerance * COARSE_RADIUS);
+    let fine = PointIndex::new(&points, tolerance);
+    let centroid = points
+        .iter()
+        .fold([0.0f64; 3], |acc, p| {
+            [0, 1, 2].map(|a| acc[a] + p[a] as f64)
+        })
+        .map(|sum| (sum / points.len() as f64) as f32);
+
+    let mut candidates: Vec<(PlaneScore, Vec<bool>)> = (0..3)
+        .into_par_iter()
+        .map(|axis| {
+            let mut normal = [0.0; 3];
+            normal[axis] = 1.0;
+            let plane = MirrorPlane {
+                normal,
+                offset: centroid[axis],
+            };
+            let (plane, matched) = refine([&coarse, &fine], &points, plane);
+            let score = matched.iter().filter(|&&m| m).count() as f32 / points.len() as f32;
+            (PlaneScore { plane, score }, matched)
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.0.score.total_cmp(&a.0.score));
+
+    let (best, matched) = candidates[0].clone();
+    let regions = asymmetric_regions(&points, &mesh.indices, &matched);
+
+    Ok(SymmetryReport {
+        plane: best.plane,
+        score: best.score,
+        candidates: candidates.into_iter().map(|(score, _)| score).collect(),
+        tolerance,
+        asymmetric_vertex_count: matched.iter().filter(|&&m| !m).count(),
+        asymmetric_regions: regions,
+    })
+}
+
+/// Match vertices across `plane`, then move it onto the matched pairs' midpoints
+///
+/// Each index in `passes` is used once to re-center, from widest to finest;
+/// the last one also decides which vertices count as matched.
+fn refine(
+    passes: [&PointIndex; 2],
+    points: &[[f32; 3]],
+    plane: MirrorPlane,
+) -> (MirrorPlane, Vec<bool>) {
+    let pairs = |index: &PointIndex, plane: &MirrorPlane| -> Vec<Option<usize>> {
+        points
+            .iter()
+            .map(|&p| index.nearest(plane.reflect(p)))
+            .collect()
+    };
+
+    let mut plane = plane;
+    for index in passes {
+        let matches = pairs(index, &plane);
+        let (sum, count) = matches
+            .iter()
+            .enumerate()
+            .filter_map(|(i, m)| m.map(|j| (i, j)))
+            .fold((0.0f64, 0usize), |(sum, count), (i, j)| {
+                let mid = dot(plane.normal, points[i]) + dot(plane.normal, points[j]);
+                (sum + mid as f64 / 2.0, count + 1)
+            });
+        if count == 0 {
+            break;
+        }
+        plane.offset = (sum / count as f64) as f32;
+    }
+
+    let matched = pairs(passes[1], &plane)
+        .iter()
+        .map(Option::is_some)
+        .collect();
+    (plane, matched)
+}
+
+/// Group unmatched vertices connected by triangle edges
+fn asymmetric_regions(
+    points: &[[f32; 3]],
+    indices: &[u32],
+    matched: &[bool],
+) -> Vec<AsymmetricRegion> {
+    let mut neighbours: HashMap<u32, Vec<u32>> = HashMap::new();
+    for face in indices.chunks_exact(3) {
+        for k in 0..3 {
+            let (a, b) = (face[k], face[(k + 1) % 3]);
+            if !matched[a as usize] && !matched[b as usize] {
+                neighbours.entry(a).or_default().push(b);
+                neighbours.entry(b).or_default().push(a);
+            }
+        }
+    }
+
+    let mut visited = matched.to_vec();
+    let mut regions = Vec::new();
+    for start in 0..points.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut stack = vec![start as u32];
+        let mut members = Vec::new();
+        while let Some(v) = stack.pop() {
+            members.push(points[v as usize]);
+            for &n in neighbours.get(&v).into_iter().flatten() {
+                if !visited[n as usize] {
+                    visited[n as usize] = true;
+                    stack.push(n);
+                }
+            }
+        }
+
+        let (bounds_min, bounds_max) = bounds(&members);
+        let sum = members.iter().fold([0.0f32; 3], |acc, p| {
+            [acc[0] + p[0], acc[1] + p[1], acc[2] + p[2]]
+        });
+        regions.push(AsymmetricRegion {
+            vertex_count: members.len(),
+            centroid: sum.map(|s| s / members.len() as f32),
+            bounds_min,
+            bounds_max,
+        });
+    }
+    regions.sort_by_key(|r| std::cmp::Reverse(r.vertex_count));
+    regions.truncate(MAX_REGIONS);
+    regions
+}
+
+/// Mirror the whole mesh across `plane`, keeping faces outward
+pub fn flip(mesh: &MeshData, plane: &MirrorPlane) -> MeshData {
+    let mut flipped = mesh.clone();
+    reflect_vertices(&mut flipped, plane, 0..mesh.vertex_count());
+    for face in flipped.indices.chunks_exact_mut(3) {
+        face.swap(1, 2);
+    }
+    flipped
+}
+
+/// Replace the half of the mesh behind `plane` with a mirror of the front half
+///
+/// Faces are kept when their centroid is in front of the plane, and their
+/// vertices behind it are snapped onto the plane. Vertices within
+/// `tolerance` of the plane are shared by both halves so the result is
+/// closed along the seam.
+pub fn symmetrize(mesh: &MeshData, plane: &MirrorPlane, tolerance: f32) -> MeshData {
+    let point = |i: u32| {
+        let b = i as usize * 3;
+        [
+            mesh.positions[b],
+            mesh.positions[b + 1],
+            mesh.positions[b + 2],
+        ]
+    };
+
+    let kept_faces: Vec<&[u32]> = mesh
+        .indices
+        .chunks_exact(3)
+        .filter(|f| {
+            let c = [0, 1, 2].map(|a| (point(f[0])[a] + point(f[1])[a] + point(f[2])[a]) / 3.0);
+            plane.distance(c) >= 0.0
+        })
+        .collect();
+
+    let mut remap = vec![u32::MAX; mesh.vertex_count()];
+    let mut order = Vec::new();
+    for &i in kept_faces.iter().flat_map(|f| f.iter()) {
+        if remap[i as usize] == u32::MAX {
+            remap[i as usize] = order.len() as u32;
+            order.push(i as usize);
+        }
+    }
+    let front: Vec<u32> = kept_faces
+        .iter()
+        .flat_map(|f| f.iter().map(|&i| remap[i as usize]))
+        .collect();
+    let mut half = mesh.with_vertices(&order, front);
+
+    let mut on_plane = vec![false; order.len()];
+    for (v, p) in half.positions.chunks_exact_mut(3).enumerate() {
+        let d = plane.distance([p[0], p[1], p[2]]);
+        if d < tolerance {
+            for (value, n) in p.iter_mut().zip(plane.normal) {
+                *value -= d * n;
+            }
+            on_plane[v] = true;
+        }
+    }
+
+    // Mirrored copies of every vertex off the plane
+    let base = order.len() as u32;
+    let mut mirror_of = vec![0u32; order.len()];
+    let mut copies = Vec::new();
+    for v in 0..order.len() {
+        mirror_of[v] = if on_plane[v] {
+            v as u32
+        } else {
+            copies.push(v);
+            base + copies.len() as u32 - 1
+        };
+    }
+
+    let mut mirrored = half.with_vertices(&copies, Vec::new());
+    reflect_vertices(&mut mirrored, plane, 0..copies.len());
+
+    let mut indices = half.indices.clone();
+    for face in half.indices.chunks_exact(3) {
+        indices.extend([face[0], face[2], face[1]].map(|i| mirror_of[i as usize]));
+    }
+
+    half.positions.extend(mirrored.positions);
+    if let (Some(n), Some(m)) = (&mut half.normals, mirrored.normals) {
+        n.extend(m);
+    }
+    if let (Some(uv), Some(m)) = (&mut half.uvs, mirrored.uvs) {
+        uv.extend(m);
+    }
+    if let (Some(uv), Some(m)) = (&mut half.lightmap_uvs, mirrored.lightmap_uvs) {
+        uv.extend(m);
+    }
+    if let (Some(c), Some(m)) = (&mut half.colors, mirrored.colors) {
+        c.extend(m);
+    }
+    if let (Some(j), Some(m)) = (&mut half.skin_joints, mirrored.skin_joints) {
+        j.extend(m);
+    }
+    if let (Some(w), Some(m)) = (&mut half.skin_weights, mirrored.skin_weights) {
+        w.extend(m);
+    }
+    half.indices = indices;
+    half
+}
+
+fn reflect_vertices(mesh: &mut MeshData, plane: &MirrorPlane, range: std::ops::Range<usize>) {
+    for v in range {
+        let b = v * 3;
+        let p = plane.reflect([
+            mesh.positions[b],
+            mesh.positions[b + 1],
+            mesh.positions[b + 2],
+        ]);
+        mesh.positions[b..b + 3].copy_from_slice(&p);
+        if let Some(normals) = &mut mesh.normals {
+            let n = plane.reflect_direction([normals[b], normals[b + 1], normals[b + 2]]);
+            normals[b..b + 3].copy_from_slice(&n);
+        }
+    }
+}
+
+/// Spatial hash answering "is there a vertex within `radius`"
+struct PointIndex<'a> {
+    points: &'a [[f32; 3]],
+    radius: f32,
+    cells: HashMap<[i64; 3], Vec<u32>>,
+}
+
+impl<'a> PointIndex<'a> {
+    fn new(points: &'a [[f32; 3]], radius: f32) -> Self {
+        let mut cells: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+        for (i, &p) in points.iter().enumerate() {
+            cells.entry(cell(p, radius)).or_default().push(i as u32);
+        }
+        Self {
+            points,
+            radius,
+            cells,
+        }
+    }
+
+    fn nearest(&self, p: [f32; 3]) -> Option<usize> {
+        let [cx, cy, cz] = cell(p, self.radius);
+        let mut best: Option<(f32, usize)> = None;
+        for dz in -1..=1 {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    for &j in self
+                        .cells
+                        .get(&[cx + dx, cy + dy, cz + dz])
+                        .into_iter()
+                        .flatten()
+                    {
+                        let d = sub(self.points[j as usize], p);
+                        let d = dot(d, d);
+                        if d <= self.radius * self.radius && best.is_none_or(|(b, _)| d < b) {
+                            best = Some((d, j as usize));
+                        }
+                    }
+                }
+            }
+        }
+        best.map(|(_, j)| j)
+    }
+}
+
+fn cell(p: [f32; 3], size: f32) -> [i64; 3] {
+    p.map(|v| (v / size).floor() as i64)
+}
+
+fn bounds(points: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    points
+        .iter()
+        .fold(([f32::MAX; 3], [f32::MIN; 3]), |(lo, hi), p| {
+            (
+                [0, 1, 2].map(|a| lo[a].min(p[a])),
+                [0, 1, 2].map(|a| hi[a].max(p[a])),
+            )
+        })
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A curved sheet mirrored across x = 5, except for one bump at x > 5
+    fn bumped_grid() -> MeshData {
+        let n = 16;
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for y in 0..=n {
+            for x in 0..=n {
+                let (fx, fy) = (x as f32 / n as f32 * 2.0 - 1.0, y as f32 / n as f32);
+                let bump = if x == 12 && y == 8 { 0.3 } else { 0.0 };
+                let z = fy * fy * 0.5 + bump;
+                positions.extend_from_slice(&[fx + 5.0, fy, z]);
+            }
+        }
+        let row = n + 1;
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * row + x;
+                indices.extend_from_slice(&[i, i + 1, i + row, i + 1, i + row + 1, i + row]);
+            }
+        }
+        MeshData {
+            positions,
+            indices,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_detect_finds_plane_and_bump() {
+        let mesh = bumped_grid();
+        let report = detect(&mesh, Some(0.01)).unwrap();
+
+        assert_eq!(report.plane.normal, [1.0, 0.0, 0.0]);
+        assert!((report.plane.offset - 5.0).abs() < 1e-4);
+        // The bump and the vertex it should mirror
+        assert_eq!(report.asymmetric_vertex_count, 2);
+        assert_eq!(report.asymmetric_regions.len(), 2);
+    }
+
+    #[test]
+    fn test_symmetrize_mirrors_front_half() {
+        let mesh = bumped_grid();
+        let plane = MirrorPlane {
+            normal: [1.0, 0.0, 0.0],
+            offset: 5.0,
+        };
+        let result = symmetrize(&mesh, &plane, 1e-4);
+
+        assert!(result.validate().is_ok());
+        assert_eq!(result.face_count(), mesh.face_count());
+        assert_eq!(result.vertex_count(), mesh.vertex_count());
+        assert_eq!(
+            detect(&result, Some(0.01)).unwrap().asymmetric_vertex_count,
+            0
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Uniform texel density across UV islands
+//!
+//! Kitbashed meshes combine parts unwrapped at different scales, so a shared
+//! texture looks sharp on one part and blurry on the next. Each island is
+//! scaled about the center of its UV bounds until its texels per centimeter
+//! match the target; positions are taken to be in meters, as imports are.
+
+use crate::utils::lightmap::signed_area;
+use crate::utils::mesh_store::MeshData;
+use crate::utils::uv_analysis::{islands, UvSet};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// One island whose UVs were rescaled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IslandResize {
+    pub island: usize,
+    pub face_count: usize,
+    /// Texels per centimeter before rescaling
+    pub density_before: f32,
+    /// Factor applied to the island's UVs
+    pub scale: f32,
+}
+
+pub struct DensityNormalization {
+    pub mesh: MeshData,
+    pub island_count: usize,
+    pub resized: Vec<IslandResize>,
+    /// Islands with no UV or surface area, left as they were
+    pub skipped_island_count: usize,
+}
+
+/// Rescale every island to `texels_per_cm` on a `texture_size`² texture
+///
+/// Islands already within `tolerance` (a fraction, e.g. 0.01) of the target
+/// are left alone. Vertices shared by two islands are split so each can be
+/// scaled on its own. Islands may overlap or leave [0, 1] afterwards, so
+/// the layout usually needs packing again.
+pub fn normalize(
+    mesh: &MeshData,
+    uv_set: UvSet,
+    texels_per_cm: f32,
+    texture_size: u32,
+    tolerance: f32,
+) -> Result<DensityNormalization, String> {
+    if !(texels_per_cm > 0.0 && texels_per_cm.is_finite()) {
+        return Err(format!(
+            "Target density must be positive, got {}",
+            texels_per_cm
+        ));
+    }
+    let uvs = match uv_set {
+        UvSet::Base => mesh.uvs.as_deref().ok_or("Mesh has no UVs"),
+        UvSet::Lightmap => mesh
+            .lightmap_uvs
+            .as_deref()
+            .ok_or("Mesh has no lightmap UVs"),
+    }?;
+
+    let (face_island, island_count) = islands(mesh, uvs);
+    let point = |i: u32| {
+        let b = i as usize * 3;
+        [
+            mesh.positions[b],
+            mesh.positions[b + 1],
+            mesh.positions[b + 2],
+        ]
+    };
+    let uv = |i: u32| [uvs[i as usize * 2], uvs[i as usize * 2 + 1]];
+
+    // UV and world area, face count and UV bounds per island
+    let mut uv_area = vec![0.0f64; island_count];
+    let mut world_area = vec![0.0f64; island_count];
+    let mut face_count = vec![0usize; island_count];
+    let mut lo = vec![[f32::MAX; 2]; island_count];
+    let mut hi = vec![[f32::MIN; 2]; island_count];
+    for (face, &island) in mesh.indices.chunks_exact(3).zip(&face_island) {
+        let [a, b, c] = [face[0], face[1], face[2]].map(point);
+        let e1 = [0, 1, 2].map(|k| b[k] - a[k]);
+        let e2 = [0, 1, 2].map(|k| c[k] - a[k]);
+        let n = [
+            e1[1] * e2[2] - e1[2] * e2[1],
+            e1[2] * e2[0] - e1[0] * e2[2],
+            e1[0] * e2[1] - e1[1] * e2[0],
+        ];
+        world_area[island] += ((n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt() / 2.0) as f64;
+        let corners = [face[0], face[1], face[2]].map(uv);
+        uv_area[island] += signed_area(corners).abs() as f64;
+        face_count[island] += 1;
+        for p in corners {
+            for k in 0..2 {
+                lo[island][k] = lo[island][k].min(p[k]);
+                hi[island][k] = hi[island][k].max(p[k]);
+            }
+        }
+    }
+
+    // Texels per centimeter of world surface
+    let texels = texture_size as f64 / 100.0;
+    let mut scales = vec![1.0f32; island_count];
+    let mut resized = Vec::new();
+    let mut skipped_island_count = 0;
+    for island in 0..island_count {
+        if !(uv_area[island] > 0.0 && world_area[island] > 0.0) {
+            skipped_island_count += 1;
+            continue;
+        }
+        let density = (uv_area[island] / world_area[island]).sqrt() * texels;
+        let scale = (texels_per_cm as f64 / density) as f32;
+        if (scale - 1.0).abs() <= tolerance {
+            continue;
+        }
+        scales[island] = scale;
+        resized.push(IslandResize {
+            island,
+            face_count: face_count[island],
+            density_before: density as f32,
+            scale,
+        });
+    }
+
+    // One vertex per (vertex, island), so shared corners can move apart
+    let mut ids: HashMap<(u32, usize), u32> = HashMap::new();
+    let mut order = Vec::new();
+    let mut scaled = Vec::new();
+    let indices = mesh
+        .indices
+        .iter()
+        .enumerate()
+        .map(|(k, &i)| {
+            let island = face_island[k / 3];
+            *ids.entry((i, island)).or_insert_with(|| {
+                let center = [0, 1].map(|a| (lo[island][a] + hi[island][a]) / 2.0);
+                let p = uv(i);
+                scaled.extend([0, 1].map(|a| center[a] + (p[a] - center[a]) * scales[island]));
+                order.push(i as usize);
+                (order.len() - 1) as u32
+            })
+        })
+        .collect();
+
+    let mut out = mesh.with_vertices(&order, indices);
+    match uv_set {
+        UvSet::Base => out.uvs = Some(scaled),
+        UvSet::Lightmap => out.lightmap_uvs = Some(scaled),
+    }
+    out.polygons = mesh.polygons.clone();
+    Ok(DensityNormalization {
+        mesh: out,
+        island_count,
+        resized,
+        skipped_island_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_islands_scale_to_the_target_density() {
+        // Two 1 m quads, one unwrapped at twice the scale of the other
+        let quad = |x: f32| {
+            [[x, 0.0], [x + 1.0, 0.0], [x + 1.0, 1.0], [x, 1.0]].map(|[px, py]| [px, py, 0.0])
+        };
+        let mesh = MeshData {
+            positions: [quad(0.0), quad(2.0)].concat().concat(),
+            uvs: Some(vec![
+                0.0, 0.0, 0.5, 0.0, 0.5, 0.5, 0.0, 0.5, // 512 texels per meter
+                0.5, 0.5, 0.75, 0.5, 0.75, 0.75, 0.5, 0.75, // 256 texels per meter
+            ]),
+            indices: vec![0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7],
+            ..Default::default()
+        };
+
+        let result = normalize(&mesh, UvSet::Base, 5.12, 1024, 0.01).unwrap();
+        assert_eq!(result.island_count, 2);
+        // The first island is already at 5.12 texels per centimeter
+        assert_eq!(result.resized.len(), 1);
+        assert_eq!(result.resized[0].island, 1);
+        assert!((result.resized[0].density_before - 2.56).abs() < 1e-4);
+        assert!((result.resized[0].scale - 2.0).abs() < 1e-4);
+
+        let uvs = result.mesh.uvs.unwrap();
+        assert_eq!(&uvs[8..10], &[0.375, 0.375]);
+        assert_eq!(&uvs[12..14], &[0.875, 0.875]);
+        assert_eq!(&uvs[0..8], &mesh.uvs.as_ref().unwrap()[0..8]);
+    }
+}
@@ -0,0 +1,348 @@
+//! Baking surface data into textures laid out on a mesh's UVs
+//!
+//! The target's UV triangles are rasterized once, giving the face and
+//! barycentric coordinates at each texel center; any color that can be
+//! worked out from a point on the surface can then be baked. Texels just
+//! outside the islands copy their neighbours so filtering and mipmaps
+//! don't pull in the empty background along UV seams.
+//!
+//! Meshes without UVs, such as vertex-colored scans, are first unwrapped
+//! with the lightmap packer, which leaves room for that padding.
+
+use crate::utils::lightmap;
+use crate::utils::mesh_store::MeshData;
+use crate::utils::shrinkwrap::SurfaceIndex;
+use crate::utils::vertex_colors::{linear_to_srgb, Texture};
+use image::RgbaImage;
+use rayon::prelude::*;
+
+/// Largest texture side accepted
+pub const MAX_RESOLUTION: u32 = 8192;
+/// Texels grown around each island
+const PADDING: u32 = 4;
+
+/// Which face covers each texel of a texture, and where
+pub struct UvRaster {
+    width: u32,
+    height: u32,
+    /// Face and barycentric coordinates at each texel center, row by row
+    texels: Vec<Option<(usize, [f32; 3])>>,
+    covered: usize,
+}
+
+impl UvRaster {
+    pub fn new(uvs: &[f32], indices: &[u32], resolution: u32) -> Result<Self, String> {
+        if !(1..=MAX_RESOLUTION).contains(&resolution) {
+            return Err(format!(
+                "Resolution must be between 1 and {}, got {}",
+                MAX_RESOLUTION, resolution
+            ));
+        }
+        Self::sized(uvs, indices, resolution, resolution)
+    }
+
+    /// A raster of a `width` × `height` texture, such as an existing map
+    pub fn sized(uvs: &[f32], indices: &[u32], width: u32, height: u32) -> Result<Self, String> {
+        if width.max(height) > MAX_RESOLUTION || width.min(height) == 0 {
+            return Err(format!(
+                "Texture sides must be between 1 and {}, got {}×{}",
+                MAX_RESOLUTION, width, height
+            ));
+        }
+        let (columns, rows) = (width as usize, height as usize);
+        let mut texels = vec![None; columns * rows];
+        let scale = [width as f32, height as f32];
+        for (face, f) in indices.chunks_exact(3).enumerate() {
+            let Some([a, b, c]) =
+                corner_uvs(uvs, f).map(|t| t.map(|p| [p[0] * scale[0], p[1] * scale[1]]))
+            else {
+                continue;
+            };
+            let area = edge(a, b, c);
+            if area == 0.0 {
+                continue;
+            }
+            let lo = [0, 1].map(|k| a[k].min(b[k]).min(c[k]).floor().max(0.0) as usize);
+            let hi = [0, 1].map(|k| {
+                (a[k].max(b[k]).max(c[k]).ceil().max(0.0) as usize).min([columns, rows][k])
+            });
+            for y in lo[1]..hi[1] {
+                for x in lo[0]..hi[0] {
+                    let p = [x as f32 + 0.5, y as f32 + 0.5];
+                    let bary = [edge(b, c, p), edge(c, a, p), edge(a, b, p)].map(|w| w / area);
+                    if bary.iter().all(|&w| w >= -1e-6) {
+                        texels[y * columns + x] = Some((face, bary));
+                    }
+                }
+            }
+        }
+        let covered = texels.iter().filter(|t| t.is_some()).count();
+
+        for _ in 0..PADDING {
+            let previous = texels.clone();
+            for (i, texel) in texels.iter_mut().enumerate() {
+                if texel.is_some() {
+                    continue;
+                }
+                let (x, y) = (i % columns, i / columns);
+                let neighbours = [
+                    (x > 0).then(|| i - 1),
+                    (x + 1 < columns).then(|| i + 1),
+                    (y > 0).then(|| i - columns),
+                    (y + 1 < rows).then(|| i + columns),
+                ];
+                *texel = neighbours.into_iter().flatten().find_map(|n| previous[n]);
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            texels,
+            covered,
+        })
+    }
+
+    /// Fraction of texels inside a UV island, before padding
+    pub fn coverage(&self) -> f32 {
+        self.covered as f32 / self.texels.len() as f32
+    }
+
+    /// `value` at every texel with a face, in parallel
+    pub fn map<T: Send>(&self, value: impl Fn(usize, [f32; 3]) -> T + Sync) -> Vec<Option<T>> {
+        self.texels
+            .par_iter()
+            .map(|texel| texel.map(|(face, bary)| value(face, bary)))
+            .collect()
+    }
+
+    /// sRGB-encoded image of linear RGBA colors, one per texel
+    ///
+    /// Texels without a color are transparent black.
+    pub fn image(&self, colors: &[Option<[f32; 4]>]) -> RgbaImage {
+        let bytes = colors
+            .iter()
+            .flat_map(|color| {
+                color.map_or([0; 4], |[r, g, b, a]| {
+                    [
+                        linear_to_srgb(r),
+                        linear_to_srgb(g),
+                        linear_to_srgb(b),
+                        a.clamp(0.0, 1.0),
+                    ]
+                    .map(|c| (c * 255.0).round() as u8)
+                })
+            })
+            .collect();
+        RgbaImage::from_raw(self.width, self.height, bytes).expect("one color per texel")
+    }
+
+    /// Image of the linear RGBA `shade` gives each texel's surface point
+    pub fn bake(&self, shade: impl Fn(usize, [f32; 3]) -> [f32; 4] + Sync) -> RgbaImage {
+        self.image(&self.map(shade))
+    }
+}
+
+/// A mesh unwrapped onto one texture holding its vertex colors
+pub struct BakedColors {
+    /// The mesh with the new UVs and without vertex colors
+    pub mesh: MeshData,
+    pub texture: RgbaImage,
+    pub chart_count: usize,
+    pub coverage: f32,
+}
+
+/// Unwrap `mesh` and bake its vertex colors into a `resolution`² texture
+///
+/// Any UVs the mesh had are replaced. Vertices on chart boundaries are
+/// split, as for lightmaps.
+pub fn bake_vertex_colors(mesh: &MeshData, resolution: u32) -> Result<BakedColors, String> {
+    if mesh.colors.is_none() {
+        return Err("Mesh has no vertex colors to bake".to_string());
+    }
+    if !(1..=MAX_RESOLUTION).contains(&resolution) {
+        return Err(format!(
+            "Resolution must be between 1 and {}, got {}",
+            MAX_RESOLUTION, resolution
+        ));
+    }
+    let layout = lightmap::generate(mesh, 2 * PADDING, resolution)?;
+    let mut mesh = layout.mesh;
+    mesh.uvs = mesh.lightmap_uvs.take();
+    let colors = mesh.colors.take().unwrap_or_default();
+
+    let uvs = mesh.uvs.as_deref().unwrap_or_default();
+    let raster = UvRaster::new(uvs, &mesh.indices, resolution)?;
+    let texture = raster.bake(|face, bary| {
+        let f = &mesh.indices[face * 3..face * 3 + 3];
+        interpolate(&colors, f, bary).unwrap_or([0.0, 0.0, 0.0, 1.0])
+    });
+    Ok(BakedColors {
+        coverage: raster.coverage(),
+        mesh,
+        texture,
+        chart_count: layout.chart_count,
+    })
+}
+
+/// Textures of `source` carried over to `target`'s UVs
+///
+/// Each texel of the target samples the textures at the closest point on
+/// the source, so the two should have the same shape, as after a remesh
+/// or retopology. Returns one image per texture and the UV coverage.
+pub fn reproject(
+    source: &MeshData,
+    target: &MeshData,
+    textures: &[Texture],
+    resolution: u32,
+) -> Result<(Vec<RgbaImage>, f32), String> {
+    let source_uvs = source.uvs.as_deref().ok_or("Source mesh has no UVs")?;
+    let target_uvs = target
+        .uvs
+        .as_deref()
+        .ok_or("Target mesh has no UVs to bake onto")?;
+    let surface =
+        SurfaceIndex::new(&source.positions, &source.indices).ok_or("Source mesh has no faces")?;
+    let raster = UvRaster::new(target_uvs, &target.indices, resolution)?;
+
+    let lookups = raster.map(|face, bary| {
+        let f = &target.indices[face * 3..face * 3 + 3];
+        let point = interpolate(&target.positions, f, bary)?;
+        let (hit, q) = surface.closest_face(point)?;
+        let f = &source.indices[hit * 3..hit * 3 + 3];
+        let [a, b, c] = [f[0], f[1], f[2]].map(|i| {
+            let p = i as usize * 3;
+            [
+                source.positions[p],
+                source.positions[p + 1],
+                source.positions[p + 2],
+            ]
+        });
+        interpolate::<2>(source_uvs, f, barycentric(q, a, b, c))
+    });
+
+    let images = textures
+        .iter()
+        .map(|texture| {
+            let colors: Vec<Option<[f32; 4]>> = lookups
+                .par_iter()
+                .map(|uv| (*uv).flatten().map(|uv| texture.sample(uv)))
+                .collect();
+            raster.image(&colors)
+        })
+        .collect();
+    Ok((images, raster.coverage()))
+}
+
+fn corner_uvs(uvs: &[f32], f: &[u32]) -> Option<[[f32; 2]; 3]> {
+    let corner = |i: u32| {
+        let b = i as usize * 2;
+        Some([*uvs.get(b)?, *uvs.get(b + 1)?])
+    };
+    Some([corner(f[0])?, corner(f[1])?, corner(f[2])?])
+}
+
+/// Twice the signed area of `a`, `b`, `p`
+fn edge(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+/// `N`-wide attribute at barycentric `bary` on face `f`
+pub(crate) fn interpolate<const N: usize>(
+    values: &[f32],
+    f: &[u32],
+    bary: [f32; 3],
+) -> Option<[f32; N]> {
+    let mut out = [0.0; N];
+    for (&i, w) in f.iter().zip(bary) {
+        let corner = values.get(i as usize * N..(i as usize + 1) * N)?;
+        for (o, v) in out.iter_mut().zip(corner) {
+            *o += v * w;
+        }
+    }
+    Some(out)
+}
+
+/// Barycentric coordinates of `p`, on or near triangle `a`, `b`, `c`
+fn barycentric(p: [f32; 3], a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    let v0 = sub(b, a);
+    let v1 = sub(c, a);
+    let v2 = sub(p, a);
+    let (d00, d01, d11) = (dot(v0, v0), dot(v0, v1), dot(v1, v1));
+    let (d20, d21) = (dot(v2, v0), dot(v2, v1));
+    let denom = d00 * d11 - d01 * d01;
+    if denom == 0.0 {
+        return [1.0, 0.0, 0.0];
+    }
+    let v = (d11 * d20 - d01 * d21) / denom;
+    let w = (d00 * d21 - d01 * d20) / denom;
+    [1.0 - v - w, v, w]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgba};
+
+    #[test]
+    fn test_reproject_onto_mirrored_uvs() {
+        // A unit quad in the XZ plane; the source maps X to U
+        let quad = |uvs: Vec<f32>| MeshData {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            uvs: Some(uvs),
+            indices: vec![0, 1, 2, 0, 2, 3],
+            ..Default::default()
+        };
+        let source = quad(vec![0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0]);
+        // The target mirrors U and only uses the top half of the texture
+        let target = quad(vec![1.0, 0.0, 0.0, 0.0, 0.0, 0.5, 1.0, 0.5]);
+        let image = RgbaImage::from_fn(4, 1, |x, _| Rgba([x as u8 * 85, 0, 0, 255]));
+        let texture = Texture::from_image(&DynamicImage::ImageRgba8(image)).unwrap();
+
+        let (images, coverage) = reproject(&source, &target, &[texture], 4).unwrap();
+        assert_eq!(coverage, 0.5);
+        let baked = &images[0];
+        for x in 0..4 {
+            let expected = (3 - x) as u8 * 85;
+            assert_eq!(baked.get_pixel(x, 0).0, [expected, 0, 0, 255]);
+            // Padding repeats the last row of the island below it
+            assert_eq!(baked.get_pixel(x, 3), baked.get_pixel(x, 1));
+        }
+    }
+
+    #[test]
+    fn test_bake_vertex_colors_unwraps_and_fills() {
+        // A flat quad colored mid grey in linear terms
+        let mesh = MeshData {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            colors: Some([0.5, 0.5, 0.5, 1.0].repeat(4)),
+            indices: vec![0, 2, 1, 0, 3, 2],
+            ..Default::default()
+        };
+        let baked = bake_vertex_colors(&mesh, 64).unwrap();
+        assert!(baked.mesh.colors.is_none());
+        assert!(baked.mesh.lightmap_uvs.is_none());
+        assert_eq!(
+            baked.mesh.uvs.as_ref().map(Vec::len),
+            Some(baked.mesh.vertex_count() * 2)
+        );
+        assert_eq!(baked.chart_count, 1);
+        assert!(baked.coverage > 0.5);
+
+        let grey = (linear_to_srgb(0.5) * 255.0).round() as u8;
+        let covered = baked.texture.pixels().filter(|p| p.0[3] == 255).count();
+        assert!(covered as f32 >= baked.coverage * 64.0 * 64.0);
+        assert!(baked
+            .texture
+            .pixels()
+            .all(|p| p.0 == [grey, grey, grey, 255] || p.0 == [0; 4]));
+    }
+}
@@ -0,0 +1,206 @@
+//! Upscaling and denoising low-resolution textures
+//!
+//! Legacy assets often come with 256² textures full of compression noise.
+//! Enhancing denoises first, with an edge-preserving bilateral filter, then
+//! upscales: with Lanczos resampling and an unsharp mask on the CPU, or
+//! with an external upscaler registered as a tool, such as the ncnn-vulkan
+//! builds of Real-ESRGAN, which run ESRGAN models on the GPU. A tool is
+//! run on a copy of the image in a scratch folder and whatever image it
+//! writes there is taken as its result, scaled to the exact size asked for
+//! if the tool's model upscales by another factor.
+
+use crate::utils::external_tools::{self, ExternalTool};
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, RgbaImage};
+use rayon::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Largest upscaling factor
+pub const MAX_SCALE: u32 = 8;
+/// Largest side of an enhanced texture
+pub const MAX_SIZE: u32 = 16_384;
+/// Neighbours within this many pixels are averaged by the denoiser
+const DENOISE_RADIUS: i32 = 2;
+
+/// Check the options of an enhancement of a `width` × `height` image
+pub fn check(width: u32, height: u32, scale: u32, denoise: f32) -> Result<(), String> {
+    if !(1..=MAX_SCALE).contains(&scale) {
+        return Err(format!(
+            "Textures are upscaled 1 to {} times, not {}",
+            MAX_SCALE, scale
+        ));
+    }
+    if !(0.0..=1.0).contains(&denoise) {
+        return Err(format!(
+            "Denoising strength goes from 0 to 1, not {}",
+            denoise
+        ));
+    }
+    if width.max(height).saturating_mul(scale) > MAX_SIZE {
+        return Err(format!(
+            "A {}×{} texture upscaled {} times is larger than {}px",
+            width, height, scale, MAX_SIZE
+        ));
+    }
+    Ok(())
+}
+
+/// `image` smoothed where neighbouring pixels are alike, keeping edges
+///
+/// `strength` from 0 to 1 sets how different two colors may be and still
+/// be averaged; alpha is kept as it is.
+pub fn denoise(image: &RgbaImage, strength: f32) -> RgbaImage {
+    if strength <= 0.0 {
+        return image.clone();
+    }
+    let (width, height) = image.dimensions();
+    let range = 2.0 * (60.0 * strength).powi(2);
+    let spatial = 2.0 * 1.5f32.powi(2);
+    let rows: Vec<Vec<u8>> = (0..height)
+        .into_par_iter()
+        .map(|y| {
+            let mut row = Vec::with_capacity(width as usize * 4);
+            for x in 0..width {
+                let center = image.get_pixel(x, y).0;
+                let (mut sum, mut total) = ([0.0f32; 3], 0.0f32);
+                for dy in -DENOISE_RADIUS..=DENOISE_RADIUS {
+                    for dx in -DENOISE_RADIUS..=DENOISE_RADIUS {
+                        let nx = (x as i32 + dx).clamp(0, width as i32 - 1) as u32;
+                        let ny = (y as i32 + dy).clamp(0, height as i32 - 1) as u32;
+                        let other = image.get_pixel(nx, ny).0;
+                        let difference: f32 = (0..3)
+                            .map(|c| (other[c] as f32 - center[c] as f32).powi(2))
+                            .sum();
+                        let weight =
+                            (-((dx * dx + dy * dy) as f32) / spatial - difference / range).exp();
+                        for c in 0..3 {
+                            sum[c] += other[c] as f32 * weight;
+                        }
+                        total += weight;
+                    }
+                }
+                row.extend(sum.map(|s| (s / total).round() as u8));
+                row.push(center[3]);
+            }
+            row
+        })
+        .collect();
+    RgbaImage::from_raw(width, height, rows.concat()).expect("one pixel per input pixel")
+}
+
+/// `image` resampled `scale` times larger, sharpened to offset the blur
+pub fn upscale(image: &RgbaImage, scale: u32) -> RgbaImage {
+    if scale <= 1 {
+        return image.clone();
+    }
+    let (width, height) = image.dimensions();
+    let resized = imageops::resize(image, width * scale, height * scale, FilterType::Lanczos3);
+    imageops::unsharpen(&resized, 0.5 * scale as f32, 2)
+}
+
+/// `image` upscaled `scale` times by an external `tool`
+///
+/// The tool's `{path}` is a PNG in a scratch folder, where it should write
+/// its result under another name.
+pub fn upscale_with_tool(
+    tool: &ExternalTool,
+    image: &RgbaImage,
+    scale: u32,
+) -> Result<RgbaImage, String> {
+    static RUNS: AtomicU64 = AtomicU64::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "sweedle-enhance-{}-{}",
+        std::process::id(),
+        RUNS.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let result = run_tool(tool, image, scale, &dir);
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+fn run_tool(
+    tool: &ExternalTool,
+    image: &RgbaImage,
+    scale: u32,
+    dir: &Path,
+) -> Result<RgbaImage, String> {
+    let input = dir.join("texture.png");
+    image
+        .save_with_format(&input, ImageFormat::Png)
+        .map_err(|e| format!("Failed to write {}: {}", input.display(), e))?;
+    let before = external_tools::snapshot(dir);
+    let output = external_tools::run(tool, &input)?;
+    if !output.success() {
+        let reason = if output.timed_out {
+            "timed out".to_string()
+        } else {
+            output
+                .stderr
+                .trim()
+                .lines()
+                .last()
+                .unwrap_or("failed")
+                .to_string()
+        };
+        return Err(format!("Upscaler {} {}", tool.name, reason));
+    }
+
+    let written = external_tools::changed_files(&before, &external_tools::snapshot(dir))
+        .into_iter()
+        .filter(|path| *path != input)
+        .filter_map(|path| image::open(&path).ok())
+        .max_by_key(|image| image.width() as u64 * image.height() as u64)
+        .ok_or_else(|| format!("Upscaler {} wrote no image", tool.name))?
+        .to_rgba8();
+    let (width, height) = (image.width() * scale, image.height() * scale);
+    Ok(if written.dimensions() == (width, height) {
+        written
+    } else {
+        imageops::resize(&written, width, height, FilterType::Lanczos3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// Mean absolute difference of each pixel from its right neighbour, a
+    /// rough measure of noise on flat textures
+    fn roughness(image: &RgbaImage) -> f32 {
+        let mut total = 0.0;
+        for y in 0..image.height() {
+            for x in 1..image.width() {
+                let (a, b) = (image.get_pixel(x - 1, y), image.get_pixel(x, y));
+                total += (a.0[0] as f32 - b.0[0] as f32).abs();
+            }
+        }
+        total / (image.height() * (image.width() - 1)) as f32
+    }
+
+    #[test]
+    fn test_denoise_keeps_edges_and_upscale_sizes() {
+        // Dark and light halves with speckle noise on both
+        let noisy = RgbaImage::from_fn(16, 16, |x, y| {
+            let base = if x < 8 { 40 } else { 200 };
+            let speckle = if (x * 7 + y * 3) % 5 == 0 { 12 } else { 0 };
+            Rgba([base + speckle, base, base, 255])
+        });
+        let smooth = denoise(&noisy, 0.5);
+        assert!(roughness(&smooth) < roughness(&noisy));
+        // The edge is kept sharp: no pixel drifts toward the other half
+        assert!(smooth.get_pixel(7, 8).0[0] < 60);
+        assert!(smooth.get_pixel(8, 8).0[0] > 190);
+        assert_eq!(denoise(&noisy, 0.0), noisy);
+
+        let large = upscale(&smooth, 4);
+        assert_eq!(large.dimensions(), (64, 64));
+        assert_eq!(large.get_pixel(5, 5).0[3], 255);
+        assert!(check(16, 16, 4, 0.5).is_ok());
+        assert!(check(16, 16, 9, 0.5).is_err());
+        assert!(check(4096, 16, 8, 0.0).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+//! Surface topology: shells, boundaries, Euler characteristic and genus
+//!
+//! Vertices are welded by exact position first, so UV and normal seams don't
+//! read as boundaries. A shell is a set of faces connected through edges.
+//! For a closed, orientable shell the Euler characteristic V - E + F is
+//! 2 - 2g, which gives its genus g: 0 for a sphere, 1 for a torus.
+
+use crate::utils::seams::UnionFind;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Shells and boundary loops listed individually, largest first
+pub const MAX_LISTED: usize = 100;
+
+/// One connected piece of a mesh
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shell {
+    pub face_count: usize,
+    pub euler_characteristic: i64,
+    pub boundary_loop_count: usize,
+    /// No boundary, and no edge shared by more than two faces
+    pub is_closed: bool,
+    /// Handles through a closed shell; `None` when it isn't closed
+    pub genus: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Topology {
+    /// Distinct edges between welded vertices
+    pub edge_count: usize,
+    pub euler_characteristic: i64,
+    pub shell_count: usize,
+    /// Up to `MAX_LISTED` shells, by face count
+    pub shells: Vec<Shell>,
+    pub boundary_loop_count: usize,
+    /// Length of up to `MAX_LISTED` boundary loops, longest first
+    pub boundary_loop_lengths: Vec<f32>,
+    /// Edges shared by more than two faces
+    pub non_manifold_edge_count: usize,
+    /// Vertices whose faces don't form a single fan, like a bowtie's center
+    pub non_manifold_vertex_count: usize,
+}
+
+impl Topology {
+    pub fn is_manifold(&self) -> bool {
+        self.non_manifold_edge_count == 0 && self.non_manifold_vertex_count == 0
+    }
+
+    /// Add another mesh's topology, as if both were one mesh
+    pub fn merge(&mut self, other: &Topology) {
+        self.edge_count += other.edge_count;
+        self.euler_characteristic += other.euler_characteristic;
+        self.shell_count += other.shell_count;
+        self.shells.extend(other.shells.iter().cloned());
+        self.shells
+            .sort_by_key(|shell| std::cmp::Reverse(shell.face_count));
+        self.shells.truncate(MAX_LISTED);
+        self.boundary_loop_count += other.boundary_loop_count;
+        self.boundary_loop_lengths
+            .extend_from_slice(&other.boundary_loop_lengths);
+        self.boundary_loop_lengths.sort_by(|a, b| b.total_cmp(a));
+        self.boundary_loop_lengths.truncate(MAX_LISTED);
+        self.non_manifold_edge_count += other.non_manifold_edge_count;
+        self.non_manifold_vertex_count += other.non_manifold_vertex_count;
+    }
+}
+
+/// How many faces use an edge, and the first two of them
+struct Edge {
+    face_count: usize,
+    faces: [u32; 2],
+}
+
+/// Topology of a triangle mesh
+///
+/// Faces with an index out of range or two corners at the same position
+/// are left out.
+pub fn analyze(positions: &[f32], indices: &[u32]) -> Topology {
+    let vertex_count = positions.len() / 3;
+    let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+    let mut representative = Vec::new();
+    let ids: Vec<u32> = (0..vertex_count)
+        .map(|v| {
+            let p = &positions[v * 3..v * 3 + 3];
+            let next = welded.len() as u32;
+            *welded
+                .entry([p[0], p[1], p[2]].map(f32::to_bits))
+                .or_insert_with(|| {
+                    representative.push(v);
+                    next
+                })
+        })
+        .collect();
+
+    let faces: Vec<[u32; 3]> = indices
+        .chunks_exact(3)
+        .filter(|f| f.iter().all(|&i| (i as usize) < vertex_count))
+        .map(|f| [f[0], f[1], f[2]].map(|i| ids[i as usize]))
+        .filter(|f| f[0] != f[1] && f[1] != f[2] && f[0] != f[2])
+        .collect();
+
+    // Faces joined through edges make shells
+    let mut shells = UnionFind::new(faces.len());
+    let mut edges: HashMap<(u32, u32), Edge> = HashMap::new();
+    for (f, face) in faces.iter().enumerate() {
+        for k in 0..3 {
+            let (a, b) = (face[k], face[(k + 1) % 3]);
+            let edge = edges.entry((a.min(b), a.max(b))).or_insert(Edge {
+                face_count: 0,
+                faces: [f as u32; 2],
+            });
+            if edge.face_count < 2 {
+                edge.faces[edge.face_count] = f as u32;
+            }
+            edge.face_count += 1;
+            shells.union(edge.faces[0], f as u32);
+        }
+    }
+
+    // Corners joined through manifold edges make the fans around a vertex
+    let mut fans = UnionFind::new(faces.len() * 3);
+    let corner = |f: u32, v: u32| {
+        let k = faces[f as usize].iter().position(|&c| c == v).unwrap();
+        f * 3 + k as u32
+    };
+    let mut non_manifold_edge_count = 0;
+    for (&(a, b), edge) in &edges {
+        match edge.face_count {
+            2 => {
+                let [f, g] = edge.faces;
+                fans.union(corner(f, a), corner(g, a));
+                fans.union(corner(f, b), corner(g, b));
+            }
+            1 => {}
+            _ => non_manifold_edge_count += 1,
+        }
+    }
+
+    let mut fan_of = vec![u32::MAX; welded.len()];
+    let mut split = vec![false; welded.len()];
+    for (f, face) in faces.iter().enumerate() {
+        for (k, &v) in face.iter().enumerate() {
+            let root = fans.find((f * 3 + k) as u32);
+            if fan_of[v as usize] == u32::MAX {
+                fan_of[v as usize] = root;
+            } else if fan_of[v as usize] != root {
+                split[v as usize] = true;
+            }
+        }
+    }
+
+    let mut numbering: HashMap<u32, usize> = HashMap::new();
+    let shell_of: Vec<usize> = (0..faces.len() as u32)
+        .map(|f| {
+            let root = shells.find(f);
+            let next = numbering.len();
+            *numbering.entry(root).or_insert(next)
+        })
+        .collect();
+    let shell_count = numbering.len();
+
+    let mut shell_faces = vec![0usize; shell_count];
+    let mut shell_edges = vec![0i64; shell_count];
+    let mut shell_open = vec![false; shell_count];
+    let mut shell_loops = vec![0usize; shell_count];
+    let mut shell_vertices: HashSet<(usize, u32)> = HashSet::new();
+    for (f, face) in faces.iter().enumerate() {
+        shell_faces[shell_of[f]] += 1;
+        shell_vertices.extend(face.iter().map(|&v| (shell_of[f], v)));
+    }
+    let mut boundary = Vec::new();
+    for (&(a, b), edge) in &edges {
+        let shell = shell_of[edge.faces[0] as usize];
+        shell_edges[shell] += 1;
+        if edge.face_count != 2 {
+            shell_open[shell] = true;
+        }
+        if edge.face_count == 1 {
+            boundary.push((a, b, shell));
+        }
+    }
+    let mut shell_vertex_counts = vec![0i64; shell_count];
+    for &(shell, _) in &shell_vertices {
+        shell_vertex_counts[shell] += 1;
+    }
+
+    // Walk boundary edges into loops; edges are undirected, so a flipped
+    // face doesn't break a loop into pieces
+    let mut incident: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (e, &(a, b, _)) in boundary.iter().enumerate() {
+        incident.entry(a).or_default().push(e);
+        incident.entry(b).or_default().push(e);
+    }
+    let length = |a: u32, b: u32| {
+        let (p, q) = (representative[a as usize], representative[b as usize]);
+        (0..3)
+            .map(|k| (positions[p * 3 + k] - positions[q * 3 + k]).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    };
+    let mut used = vec![false; boundary.len()];
+    let mut boundary_loop_lengths = Vec::new();
+    for start in 0..boundary.len() {
+        if used[start] {
+            continue;
+        }
+        let (origin, mut at, shell) = boundary[start];
+        used[start] = true;
+        let mut total = length(origin, at);
+        while at != origin {
+            let Some(&e) = incident[&at].iter().find(|&&e| !used[e]) else {
+                break;
+            };
+            used[e] = true;
+            let (a, b, _) = boundary[e];
+            let next = if a == at { b } else { a };
+            total += length(at, next);
+            at = next;
+        }
+        shell_loops[shell] += 1;
+        boundary_loop_lengths.push(total);
+    }
+    let boundary_loop_count = boundary_loop_lengths.len();
+    boundary_loop_lengths.sort_by(|a, b| b.total_cmp(a));
+    boundary_loop_lengths.truncate(MAX_LISTED);
+
+    let mut listed: Vec<Shell> = (0..shell_count)
+        .map(|s| {
+            let euler_characteristic =
+                shell_vertex_counts[s] - shell_edges[s] + shell_faces[s] as i64;
+            let is_closed = !shell_open[s];
+            // An odd characteristic means a non-orientable surface
+            let genus = (is_closed && euler_characteristic <= 2 && euler_characteristic % 2 == 0)
+                .then(|| ((2 - euler_characteristic) / 2) as u32);
+            Shell {
+                face_count: shell_faces[s],
+                euler_characteristic,
+                boundary_loop_count: shell_loops[s],
+                is_closed,
+                genus,
+            }
+        })
+        .collect();
+    listed.sort_by_key(|shell| std::cmp::Reverse(shell.face_count));
+    listed.truncate(MAX_LISTED);
+
+    let used_vertices = fan_of.iter().filter(|&&fan| fan != u32::MAX).count();
+    Topology {
+        edge_count: edges.len(),
+        euler_characteristic: used_vertices as i64 - edges.len() as i64 + faces.len() as i64,
+        shell_count,
+        shells: listed,
+        boundary_loop_count,
+        boundary_loop_lengths,
+        non_manifold_edge_count,
+        non_manifold_vertex_count: split.iter().filter(|&&s| s).count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cube_and_open_square_shells() {
+        // A closed cube (8 corners, 12 faces) next to a unit square
+        let mut positions: Vec<f32> = (0..8)
+            .flat_map(|i| [i & 1, (i >> 1) & 1, (i >> 2) & 1].map(|c| c as f32))
+            .collect();
+        let mut indices = vec![
+            0, 2, 1, 1, 2, 3, // z = 0
+            4, 5, 6, 5, 7, 6, // z = 1
+            0, 1, 4, 1, 5, 4, // y = 0
+            2, 6, 3, 3, 6, 7, // y = 1
+            0, 4, 2, 2, 4, 6, // x = 0
+            1, 3, 5, 3, 7, 5, // x = 1
+        ];
+        positions.extend([3.0, 0.0, 0.0, 4.0, 0.0, 0.0, 4.0, 1.0, 0.0, 3.0, 1.0, 0.0]);
+        indices.extend([8, 9, 10, 8, 10, 11]);
+
+        let topology = analyze(&positions, &indices);
+        assert_eq!(topology.shell_count, 2);
+        assert_eq!(topology.edge_count, 18 + 5);
+        // Sphere (2) plus disc (1)
+        assert_eq!(topology.euler_characteristic, 3);
+        assert!(topology.is_manifold());
+
+        let cube = &topology.shells[0];
+        assert_eq!(cube.face_count, 12);
+        assert!(cube.is_closed);
+        assert_eq!(cube.genus, Some(0));
+        let square = &topology.shells[1];
+        assert!(!square.is_closed);
+        assert_eq!(square.genus, None);
+        assert_eq!(square.boundary_loop_count, 1);
+        assert_eq!(topology.boundary_loop_count, 1);
+        assert!((topology.boundary_loop_lengths[0] - 4.0).abs() < 1e-6);
+    }
+}
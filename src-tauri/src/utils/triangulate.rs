@@ -0,0 +1,356 @@
+//! Triangulation of imported quads and n-gons
+//!
+//! Fanning from the first corner is only safe for convex, planar polygons;
+//! on concave ones it produces overlapping triangles and on warped ones long
+//! slivers. Ear clipping on the polygon's best-fit plane handles concave
+//! outlines, and picking the best-shaped ear at each step avoids most
+//! slivers. Polygons that still come out badly are reported so they can be
+//! fixed in the source file.
+
+use serde::{Deserialize, Serialize};
+
+/// Polygons larger than this clip the first ear found instead of the best
+const MAX_QUALITY_CORNERS: usize = 64;
+
+/// Polygons listed individually in a report; the counts cover all of them
+const MAX_REPORTED: usize = 100;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriangulationMode {
+    /// Fan from the first corner, as most exporters do
+    Fan,
+    /// Clip the best-shaped ear first; handles concave polygons
+    #[default]
+    EarClip,
+}
+
+/// How imported polygons are split into triangles
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TriangulationOptions {
+    pub mode: TriangulationMode,
+    /// Largest distance of a corner from the polygon's plane, relative to the
+    /// polygon's size, for it to still count as planar
+    pub planarity_tolerance: f32,
+    /// Triangles with a smaller angle than this count as slivers
+    pub sliver_angle_degrees: f32,
+}
+
+impl Default for TriangulationOptions {
+    fn default() -> Self {
+        Self {
+            mode: TriangulationMode::EarClip,
+            planarity_tolerance: 0.01,
+            sliver_angle_degrees: 5.0,
+        }
+    }
+}
+
+/// A polygon that didn't triangulate cleanly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolygonIssue {
+    pub mesh: String,
+    /// Index of the polygon within its mesh
+    pub polygon: usize,
+    pub corner_count: u32,
+    /// Largest corner distance from the polygon's plane, relative to its size
+    pub planarity_deviation: f32,
+    /// Sliver triangles the polygon produced
+    pub sliver_count: usize,
+}
+
+/// What triangulating a file's polygons ran into
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TriangulationReport {
+    pub mode: TriangulationMode,
+    /// Polygons with more than three corners
+    pub ngon_count: usize,
+    pub non_planar_count: usize,
+    pub sliver_count: usize,
+    /// Non-planar polygons and polygons that produced slivers, up to a limit
+    pub issues: Vec<PolygonIssue>,
+}
+
+/// Triangulates polygons one at a time, collecting a report
+pub struct Triangulator {
+    options: TriangulationOptions,
+    report: TriangulationReport,
+}
+
+impl Triangulator {
+    pub fn new(options: TriangulationOptions) -> Self {
+        Self {
+            options,
+            report: TriangulationReport {
+                mode: options.mode,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Append the triangles of one polygon to `indices`
+    ///
+    /// `positions` are flat XYZ values that `corners` index into. The
+    /// polygon's winding is kept; polygons with fewer than three corners
+    /// produce nothing.
+    pub fn polygon(
+        &mut self,
+        mesh: &str,
+        polygon: usize,
+        positions: &[f32],
+        corners: &[u32],
+        indices: &mut Vec<u32>,
+    ) {
+        if corners.len() < 3 {
+            return;
+        }
+        if corners.len() == 3 {
+            indices.extend_from_slice(corners);
+            return;
+        }
+
+        let points: Vec<[f32; 3]> = corners
+            .iter()
+            .map(|&i| {
+                let b = i as usize * 3;
+                [positions[b], positions[b + 1], positions[b + 2]]
+            })
+            .collect();
+        let triangles = match self.options.mode {
+            TriangulationMode::Fan => fan(points.len()),
+            TriangulationMode::EarClip => ear_clip(&points).unwrap_or_else(|| fan(points.len())),
+        };
+        for [a, b, c] in &triangles {
+            indices.extend([corners[*a], corners[*b], corners[*c]]);
+        }
+
+        let deviation = planarity_deviation(&points);
+        let sliver_limit = self.options.sliver_angle_degrees.to_radians();
+        let sliver_count = triangles
+            .iter()
+            .filter(|t| min_angle(t.map(|k| points[k])) < sliver_limit)
+            .count();
+
+        let non_planar = deviation > self.options.planarity_tolerance;
+        self.report.ngon_count += 1;
+        self.report.non_planar_count += non_planar as usize;
+        self.report.sliver_count += sliver_count;
+        if (non_planar || sliver_count > 0) && self.report.issues.len() < MAX_REPORTED {
+            self.report.issues.push(PolygonIssue {
+                mesh: mesh.to_string(),
+                polygon,
+                corner_count: corners.len() as u32,
+                planarity_deviation: deviation,
+                sliver_count,
+            });
+        }
+    }
+
+    pub fn finish(self) -> TriangulationReport {
+        self.report
+    }
+}
+
+fn fan(n: usize) -> Vec<[usize; 3]> {
+    (1..n - 1).map(|i| [0, i, i + 1]).collect()
+}
+
+/// Ear clipping on the polygon's best-fit plane
+///
+/// Returns `None` for degenerate or self-intersecting outlines that run out
+/// of ears, which are fanned instead.
+pub(crate) fn ear_clip(points: &[[f32; 3]]) -> Option<Vec<[usize; 3]>> {
+    let normal = newell_normal(points);
+    let length = dot(normal, normal).sqrt();
+    if !(length > 0.0 && length.is_finite()) {
+        return None;
+    }
+    let normal = normal.map(|c| c / length);
+    let reference = if normal[0].abs() < 0.9 {
+        [1.0, 0.0, 0.0]
+    } else {
+        [0.0, 1.0, 0.0]
+    };
+    let u = normalize(cross(reference, normal));
+    let v = cross(normal, u);
+    // Counter-clockwise in 2D, since the normal follows the winding
+    let flat: Vec<[f32; 2]> = points.iter().map(|&p| [dot(p, u), dot(p, v)]).collect();
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear_at = |k: usize| {
+            let [a, b, c] = [(k + n - 1) % n, k, (k + 1) % n].map(|i| remaining[i]);
+            is_ear(&flat, &remaining, a, b, c).then_some([a, b, c])
+        };
+        // Starting at the second corner makes ties on convex polygons come
+        // out as the usual fan
+        let mut candidates = (1..n).chain([0]).filter_map(|k| ear_at(k).map(|t| (k, t)));
+        let ear = if n <= MAX_QUALITY_CORNERS {
+            let mut best: Option<(f32, usize, [usize; 3])> = None;
+            for (k, t) in candidates {
+                let quality = min_angle(t.map(|i| points[i]));
+                if best.is_none_or(|(q, _, _)| quality > q) {
+                    best = Some((quality, k, t));
+                }
+            }
+            best.map(|(_, k, t)| (k, t))
+        } else {
+            candidates.next()
+        };
+        let (k, triangle) = ear?;
+        triangles.push(triangle);
+        remaining.remove(k);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    Some(triangles)
+}
+
+/// Whether `b` is a convex corner whose triangle contains no other corner
+fn is_ear(flat: &[[f32; 2]], remaining: &[usize], a: usize, b: usize, c: usize) -> bool {
+    let [pa, pb, pc] = [flat[a], flat[b], flat[c]];
+    if cross2(pa, pb, pc) <= 0.0 {
+        return false;
+    }
+    remaining.iter().all(|&i| {
+        let p = flat[i];
+        if i == a || i == b || i == c || p == pa || p == pb || p == pc {
+            return true;
+        }
+        // Points on an edge count as inside so nothing touches the ear
+        !(cross2(pa, pb, p) >= 0.0 && cross2(pb, pc, p) >= 0.0 && cross2(pc, pa, p) >= 0.0)
+    })
+}
+
+/// Largest corner distance from the best-fit plane, relative to the
+/// polygon's bounding box diagonal
+fn planarity_deviation(points: &[[f32; 3]]) -> f32 {
+    let normal = newell_normal(points);
+    let length = dot(normal, normal).sqrt();
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in points {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(p[axis]);
+            max[axis] = max[axis].max(p[axis]);
+        }
+    }
+    let size = dot(sub(max, min), sub(max, min)).sqrt();
+    if !(length > 0.0 && size > 0.0) {
+        return 0.0;
+    }
+    let normal = normal.map(|c| c / length);
+    let count = points.len() as f32;
+    let centroid = [0, 1, 2].map(|axis| points.iter().map(|p| p[axis]).sum::<f32>() / count);
+    let deviation = points
+        .iter()
+        .map(|&p| dot(sub(p, centroid), normal).abs())
+        .fold(0.0f32, f32::max);
+    deviation / size
+}
+
+/// Smallest interior angle of a triangle in radians, 0 if degenerate
+fn min_angle([a, b, c]: [[f32; 3]; 3]) -> f32 {
+    let angle = |p: [f32; 3], q: [f32; 3], r: [f32; 3]| {
+        let (x, y) = (sub(q, p), sub(r, p));
+        let lengths = (dot(x, x) * dot(y, y)).sqrt();
+        if lengths > 0.0 {
+            (dot(x, y) / lengths).clamp(-1.0, 1.0).acos()
+        } else {
+            0.0
+        }
+    };
+    angle(a, b, c).min(angle(b, c, a)).min(angle(c, a, b))
+}
+
+/// Polygon normal that stays robust for concave and slightly warped outlines
+fn newell_normal(points: &[[f32; 3]]) -> [f32; 3] {
+    let mut normal = [0.0f32; 3];
+    for (i, p) in points.iter().enumerate() {
+        let q = points[(i + 1) % points.len()];
+        normal[0] += (p[1] - q[1]) * (p[2] + q[2]);
+        normal[1] += (p[2] - q[2]) * (p[0] + q[0]);
+        normal[2] += (p[0] - q[0]) * (p[1] + q[1]);
+    }
+    normal
+}
+
+fn cross2(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = dot(v, v).sqrt();
+    v.map(|a| a / len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ear_clipping_handles_concave_polygons() {
+        // An arrowhead with a notch at the bottom; fanning from a base corner
+        // spans the notch with a diagonal outside the polygon
+        let positions = [
+            0.0, 0.5, 0.0, // notch
+            -1.0, -1.0, 0.0, //
+            0.0, 2.0, 0.0, //
+            1.0, -1.0, 0.0,
+        ];
+        let corners = [3, 2, 1, 0];
+        let area = |indices: &[u32]| -> f32 {
+            indices
+                .chunks_exact(3)
+                .map(|t| {
+                    let p = |i: u32| [positions[i as usize * 3], positions[i as usize * 3 + 1]];
+                    let [a, b, c] = [p(t[0]), p(t[1]), p(t[2])];
+                    cross2(a, b, c) / 2.0
+                })
+                .sum()
+        };
+
+        let mut ear_clip = Triangulator::new(TriangulationOptions::default());
+        let mut indices = Vec::new();
+        ear_clip.polygon("arrow", 0, &positions, &corners, &mut indices);
+        assert_eq!(indices.len(), 6);
+        // Every triangle keeps the polygon's winding, so none overlap
+        assert!(indices.chunks_exact(3).all(|t| area(t) > 0.0));
+        assert!((area(&indices) - 1.5).abs() < 1e-5);
+        assert_eq!(ear_clip.finish().ngon_count, 1);
+
+        let mut fan = Triangulator::new(TriangulationOptions {
+            mode: TriangulationMode::Fan,
+            ..Default::default()
+        });
+        let mut fanned = Vec::new();
+        fan.polygon("arrow", 0, &positions, &corners, &mut fanned);
+        assert!(fanned.chunks_exact(3).any(|t| area(t) < 0.0));
+
+        // A warped quad is reported as non-planar
+        let warped = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.5, 0.0, 1.0, 0.0];
+        let mut triangulator = Triangulator::new(TriangulationOptions::default());
+        triangulator.polygon("warped", 3, &warped, &[0, 1, 2, 3], &mut Vec::new());
+        let report = triangulator.finish();
+        assert_eq!(report.non_planar_count, 1);
+        assert_eq!(report.issues[0].polygon, 3);
+    }
+}
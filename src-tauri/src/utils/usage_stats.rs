@@ -0,0 +1,328 @@
+//! Local usage statistics for the dashboard
+//!
+//! Records which assets are opened, how long commands take and how the
+//! library grows. Everything stays in a JSON file in the app data directory;
+//! nothing is ever sent anywhere.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Writes are batched so busy sessions don't rewrite the file per command
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+/// Library samples kept per folder (one per day)
+const MAX_LIBRARY_SAMPLES: usize = 730;
+
+/// How often and how recently an asset was opened
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssetUsage {
+    pub opens: u64,
+    pub first_opened_ms: u64,
+    pub last_opened_ms: u64,
+}
+
+/// Totals for one command across all sessions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OperationUsage {
+    pub calls: u64,
+    pub failures: u64,
+    pub total_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Size of a library folder on one day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibrarySample {
+    /// Start of the day (UTC) in milliseconds since the Unix epoch
+    pub day_ms: u64,
+    pub asset_count: usize,
+    pub total_bytes: u64,
+}
+
+/// Everything that is persisted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct UsageData {
+    since_ms: u64,
+    assets: HashMap<String, AssetUsage>,
+    operations: HashMap<String, OperationUsage>,
+    library: BTreeMap<String, Vec<LibrarySample>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetUsageEntry {
+    pub path: String,
+    #[serde(flatten)]
+    pub usage: AssetUsage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationUsageEntry {
+    pub name: String,
+    pub mean_ms: f64,
+    #[serde(flatten)]
+    pub usage: OperationUsage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryHistory {
+    pub folder: String,
+    pub samples: Vec<LibrarySample>,
+}
+
+/// Chart-ready snapshot returned by `get_usage_stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    /// When collection started
+    pub since_ms: u64,
+    /// Most opened first
+    pub top_assets: Vec<AssetUsageEntry>,
+    /// Most total time first
+    pub operations: Vec<OperationUsageEntry>,
+    pub library: Vec<LibraryHistory>,
+}
+
+struct Inner {
+    data: UsageData,
+    dirty: bool,
+    last_save: Instant,
+}
+
+/// Usage statistics loaded from and saved to a JSON file, managed as Tauri state
+pub struct UsageStats {
+    path: Option<PathBuf>,
+    inner: Mutex<Inner>,
+}
+
+impl Default for UsageStats {
+    /// In-memory statistics that are never written to disk
+    fn default() -> Self {
+        Self::with_data(None, UsageData::default())
+    }
+}
+
+impl UsageStats {
+    /// Load statistics from `path`, starting fresh if missing or invalid
+    pub fn load(path: PathBuf) -> Self {
+        let data = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::warn!(
+                    "Ignoring invalid usage stats file {}: {}",
+                    path.display(),
+                    e
+                );
+                UsageData::default()
+            }),
+            Err(_) => UsageData::default(),
+        };
+        Self::with_data(Some(path), data)
+    }
+
+    fn with_data(path: Option<PathBuf>, mut data: UsageData) -> Self {
+        if data.since_ms == 0 {
+            data.since_ms = now_ms();
+        }
+        Self {
+            path,
+            inner: Mutex::new(Inner {
+                data,
+                dirty: false,
+                last_save: Instant::now(),
+            }),
+        }
+    }
+
+    /// Count an asset being opened
+    pub fn record_open(&self, path: &Path) {
+        let now = now_ms();
+        self.modify(|data| {
+            let usage = data
+                .assets
+                .entry(path.to_string_lossy().to_string())
+                .or_insert_with(|| AssetUsage {
+                    first_opened_ms: now,
+                    ..Default::default()
+                });
+            usage.opens += 1;
+            usage.last_opened_ms = now;
+        });
+    }
+
+    /// Add one finished command to its totals
+    pub fn record_operation(&self, name: &str, elapsed: Duration, failed: bool) {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        self.modify(|data| {
+            let usage = data.operations.entry(name.to_string()).or_default();
+            usage.calls += 1;
+            usage.failures += failed as u64;
+            usage.total_ms += ms;
+            usage.max_ms = usage.max_ms.max(ms);
+        });
+    }
+
+    /// Record the current size of a library folder, replacing today's sample
+    pub fn record_library(&self, folder: &Path, asset_count: usize, total_bytes: u64) {
+        let day_ms = now_ms() / DAY_MS * DAY_MS;
+        self.modify(|data| {
+            let samples = data
+                .library
+                .entry(folder.to_string_lossy().to_string())
+                .or_default();
+            if samples.last().is_some_and(|s| s.day_ms == day_ms) {
+                samples.pop();
+            }
+            samples.push(LibrarySample {
+                day_ms,
+                asset_count,
+                total_bytes,
+            });
+            if samples.len() > MAX_LIBRARY_SAMPLES {
+                samples.drain(..samples.len() - MAX_LIBRARY_SAMPLES);
+            }
+        });
+    }
+
+    /// Snapshot for charting, with at most `top` assets
+    pub fn report(&self, top: usize) -> UsageReport {
+        let inner = self.inner.lock().unwrap();
+        let data = &inner.data;
+
+        let mut top_assets: Vec<AssetUsageEntry> = data
+            .assets
+            .iter()
+            .map(|(path, usage)| AssetUsageEntry {
+                path: path.clone(),
+                usage: usage.clone(),
+            })
+            .collect();
+        top_assets.sort_by(|a, b| {
+            b.usage
+                .opens
+                .cmp(&a.usage.opens)
+                .then(b.usage.last_opened_ms.cmp(&a.usage.last_opened_ms))
+        });
+        top_assets.truncate(top);
+
+        let mut operations: Vec<OperationUsageEntry> = data
+            .operations
+            .iter()
+            .map(|(name, usage)| OperationUsageEntry {
+                name: name.clone(),
+                mean_ms: usage.total_ms / usage.calls.max(1) as f64,
+                usage: usage.clone(),
+            })
+            .collect();
+        operations.sort_by(|a, b| b.usage.total_ms.total_cmp(&a.usage.total_ms));
+
+        let library = data
+            .library
+            .iter()
+            .map(|(folder, samples)| LibraryHistory {
+                folder: folder.clone(),
+                samples: samples.clone(),
+            })
+            .collect();
+
+        UsageReport {
+            since_ms: data.since_ms,
+            top_assets,
+            operations,
+            library,
+        }
+    }
+
+    /// Forget everything collected so far
+    pub fn clear(&self) -> Result<(), String> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.data = UsageData {
+            since_ms: now_ms(),
+            ..Default::default()
+        };
+        self.save(&mut inner)
+    }
+
+    /// Write pending changes to disk now
+    pub fn flush(&self) -> Result<(), String> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.dirty {
+            return Ok(());
+        }
+        self.save(&mut inner)
+    }
+
+    fn modify(&self, f: impl FnOnce(&mut UsageData)) {
+        let mut inner = self.inner.lock().unwrap();
+        f(&mut inner.data);
+        inner.dirty = true;
+        if inner.last_save.elapsed() >= SAVE_INTERVAL {
+            if let Err(e) = self.save(&mut inner) {
+                log::warn!("{}", e);
+            }
+        }
+    }
+
+    fn save(&self, inner: &mut Inner) -> Result<(), String> {
+        inner.last_save = Instant::now();
+        let Some(path) = &self.path else {
+            inner.dirty = false;
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create usage stats directory: {}", e))?;
+        }
+        let json = serde_json::to_string(&inner.data)
+            .map_err(|e| format!("Failed to serialize usage stats: {}", e))?;
+
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| format!("Failed to write usage stats: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to save usage stats: {}", e))?;
+        inner.dirty = false;
+        Ok(())
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_survive_reload() {
+        let dir = std::env::temp_dir().join(format!("sweedle-usage-{}", std::process::id()));
+        let path = dir.join("usage_stats.json");
+
+        let stats = UsageStats::load(path.clone());
+        stats.record_open(Path::new("/lib/a.glb"));
+        stats.record_open(Path::new("/lib/b.glb"));
+        stats.record_open(Path::new("/lib/b.glb"));
+        stats.record_operation("analyze_model", Duration::from_millis(40), false);
+        stats.record_operation("analyze_model", Duration::from_millis(20), true);
+        stats.record_library(Path::new("/lib"), 2, 100);
+        stats.record_library(Path::new("/lib"), 3, 150);
+        stats.flush().unwrap();
+
+        let report = UsageStats::load(path).report(10);
+        assert_eq!(report.top_assets[0].path, "/lib/b.glb");
+        assert_eq!(report.top_assets[0].usage.opens, 2);
+        assert_eq!(report.operations[0].usage.calls, 2);
+        assert_eq!(report.operations[0].usage.failures, 1);
+        assert!((report.operations[0].mean_ms - 30.0).abs() < 1e-6);
+        // Same-day samples replace each other
+        assert_eq!(report.library[0].samples.len(), 1);
+        assert_eq!(report.library[0].samples[0].asset_count, 3);
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
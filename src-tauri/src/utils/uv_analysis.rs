@@ -0,0 +1,548 @@
+//! UV layout inspection
+//!
+//! Faces are grouped into islands (connected through edges whose corners
+//! agree in both position and UV), then checked for overlaps, flipped
+//! winding and use of UV space outside the unit square. The same
+//! classification drives the SVG and PNG renders shown in the inspector.
+
+use crate::utils::lightmap::{signed_area, triangles_overlap};
+use crate::utils::mesh_store::MeshData;
+use image::{ImageFormat, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::io::Cursor;
+
+/// Island pairs reported at most; the face count covers the rest
+const MAX_REPORTED_OVERLAPS: usize = 100;
+const MAX_REPORTED_TILES: usize = 64;
+/// UV area below which a face counts as collapsed
+const DEGENERATE_AREA: f32 = 1e-12;
+const OVERLAP_EPSILON: f32 = 1e-6;
+
+/// Which of a mesh's UV sets to inspect
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UvSet {
+    /// `TEXCOORD_0`
+    #[default]
+    Base,
+    /// `TEXCOORD_1`
+    Lightmap,
+}
+
+/// Render output of `render_uv_layout`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UvRenderFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UvBounds {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+/// Texels per world unit across faces, for a square texture
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TexelDensity {
+    pub texture_size: u32,
+    pub min: f32,
+    pub max: f32,
+    /// Weighted by world-space area
+    pub mean: f32,
+    pub median: f32,
+}
+
+/// Findings of `analyze_uvs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UvAnalysis {
+    pub uv_set: UvSet,
+    pub bounds: UvBounds,
+    pub island_count: usize,
+    pub texel_density: TexelDensity,
+    /// Overlapping island pairs; `[i, i]` is an island folding over itself
+    pub overlapping_islands: Vec<[usize; 2]>,
+    pub overlapping_face_count: usize,
+    /// Faces wound against the rest of their island
+    pub flipped_face_count: usize,
+    /// Islands wound against the rest of the mesh, as from mirroring
+    pub mirrored_island_count: usize,
+    /// Faces whose UVs collapse to a line or point
+    pub degenerate_face_count: usize,
+    /// Faces with a corner outside [0, 1]
+    pub out_of_range_face_count: usize,
+    /// Integer UV tiles used by face centers, for UDIM-style layouts
+    pub tiles: Vec<[i32; 2]>,
+}
+
+/// Per-face classification shared by the report and the renders
+pub struct UvLayout {
+    corners: Vec<[[f32; 2]; 3]>,
+    islands: Vec<usize>,
+    island_count: usize,
+    overlapping: Vec<bool>,
+    flipped: Vec<bool>,
+    overlap_pairs: BTreeSet<[usize; 2]>,
+    mirrored_island_count: usize,
+}
+
+impl UvLayout {
+    pub fn new(mesh: &MeshData, uv_set: UvSet) -> Result<Self, String> {
+        let uvs = match uv_set {
+            UvSet::Base => mesh.uvs.as_deref().ok_or("Mesh has no UVs"),
+            UvSet::Lightmap => mesh
+                .lightmap_uvs
+                .as_deref()
+                .ok_or("Mesh has no lightmap UVs"),
+        }?;
+        if mesh.indices.is_empty() {
+            return Err("No indices provided".to_string());
+        }
+
+        let uv = |i: u32| [uvs[i as usize * 2], uvs[i as usize * 2 + 1]];
+        let corners: Vec<[[f32; 2]; 3]> = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|f| [uv(f[0]), uv(f[1]), uv(f[2])])
+            .collect();
+
+        let (islands, island_count) = islands(mesh, uvs);
+        let areas: Vec<f32> = corners.iter().map(|&t| signed_area(t)).collect();
+
+        // Winding is judged by majority so either UV convention works
+        let mut island_area = vec![0.0f64; island_count];
+        for (&island, &area) in islands.iter().zip(&areas) {
+            island_area[island] += area as f64;
+        }
+        let total: f64 = island_area.iter().sum();
+        let mirrored_island_count = island_area
+            .iter()
+            .filter(|&&a| a != 0.0 && a.signum() != total.signum())
+            .count();
+        let flipped = islands
+            .iter()
+            .zip(&areas)
+            .map(|(&island, &area)| {
+                area.abs() > DEGENERATE_AREA
+                    && island_area[island] != 0.0
+                    && (area as f64).signum() != island_area[island].signum()
+            })
+            .collect();
+
+        let (overlapping, overlap_pairs) = overlaps(&corners, &areas, &islands);
+        Ok(Self {
+            corners,
+            islands,
+            island_count,
+            overlapping,
+            flipped,
+            overlap_pairs,
+            mirrored_island_count,
+        })
+    }
+
+    pub fn analyze(&self, mesh: &MeshData, uv_set: UvSet, texture_size: u32) -> UvAnalysis {
+        let mut min = [f32::MAX; 2];
+        let mut max = [f32::MIN; 2];
+        for p in self.corners.iter().flatten() {
+            for a in 0..2 {
+                min[a] = min[a].min(p[a]);
+                max[a] = max[a].max(p[a]);
+            }
+        }
+
+        let out_of_range_face_count = self
+            .corners
+            .iter()
+            .filter(|t| t.iter().flatten().any(|c| !(0.0..=1.0).contains(c)))
+            .count();
+        let tiles: BTreeSet<[i32; 2]> = self
+            .corners
+            .iter()
+            .map(|t| [0, 1].map(|a| ((t[0][a] + t[1][a] + t[2][a]) / 3.0).floor() as i32))
+            .collect();
+
+        UvAnalysis {
+            uv_set,
+            bounds: UvBounds { min, max },
+            island_count: self.island_count,
+            texel_density: self.texel_density(mesh, texture_size),
+            overlapping_islands: self
+                .overlap_pairs
+                .iter()
+                .take(MAX_REPORTED_OVERLAPS)
+                .copied()
+                .collect(),
+            overlapping_face_count: self.overlapping.iter().filter(|&&o| o).count(),
+            flipped_face_count: self.flipped.iter().filter(|&&f| f).count(),
+            mirrored_island_count: self.mirrored_island_count,
+            degenerate_face_count: self
+                .corners
+                .iter()
+                .filter(|&&t| signed_area(t).abs() <= DEGENERATE_AREA)
+                .count(),
+            out_of_range_face_count,
+            tiles: tiles.into_iter().take(MAX_REPORTED_TILES).collect(),
+        }
+    }
+
+    fn texel_density(&self, mesh: &MeshData, texture_size: u32) -> TexelDensity {
+        let point = |i: u32| {
+            let b = i as usize * 3;
+            [
+                mesh.positions[b],
+                mesh.positions[b + 1],
+                mesh.positions[b + 2],
+            ]
+        };
+        let mut samples: Vec<(f32, f32)> = mesh
+            .indices
+            .chunks_exact(3)
+            .zip(&self.corners)
+            .filter_map(|(f, &uv)| {
+                let [a, b, c] = [f[0], f[1], f[2]].map(point);
+                let e1 = [0, 1, 2].map(|k| b[k] - a[k]);
+                let e2 = [0, 1, 2].map(|k| c[k] - a[k]);
+                let n = [
+                    e1[1] * e2[2] - e1[2] * e2[1],
+                    e1[2] * e2[0] - e1[0] * e2[2],
+                    e1[0] * e2[1] - e1[1] * e2[0],
+                ];
+                let world = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt() / 2.0;
+                let uv = signed_area(uv).abs();
+                (world > 0.0 && uv > DEGENERATE_AREA)
+                    .then(|| ((uv / world).sqrt() * texture_size as f32, world))
+            })
+            .collect();
+        if samples.is_empty() {
+            return TexelDensity {
+                texture_size,
+                ..Default::default()
+            };
+        }
+
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let weight: f32 = samples.iter().map(|s| s.1).sum();
+        TexelDensity {
+            texture_size,
+            min: samples[0].0,
+            max: samples[samples.len() - 1].0,
+            mean: samples.iter().map(|s| s.0 * s.1).sum::<f32>() / weight,
+            median: samples[samples.len() / 2].0,
+        }
+    }
+
+    /// Faces filled by island, overlaps in red and flipped faces in orange
+    pub fn render_svg(&self, size: u32) -> String {
+        let s = size as f32;
+        let mut paths = vec![String::new(); self.island_count];
+        let mut overlapping = String::new();
+        let mut flipped = String::new();
+        for (f, t) in self.corners.iter().enumerate() {
+            let d = format!(
+                "M{:.2} {:.2}L{:.2} {:.2}L{:.2} {:.2}Z",
+                t[0][0] * s,
+                t[0][1] * s,
+                t[1][0] * s,
+                t[1][1] * s,
+                t[2][0] * s,
+                t[2][1] * s
+            );
+            if self.overlapping[f] {
+                overlapping.push_str(&d);
+            } else if self.flipped[f] {
+                flipped.push_str(&d);
+            }
+            paths[self.islands[f]].push_str(&d);
+        }
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {s} {s}\" width=\"{s}\" height=\"{s}\">\
+             <rect width=\"{s}\" height=\"{s}\" fill=\"#1e1e1e\"/>",
+            s = size
+        );
+        svg.push_str("<g stroke=\"#101010\" stroke-width=\"0.5\" stroke-linejoin=\"round\">");
+        for (island, d) in paths.iter().enumerate() {
+            let [r, g, b] = island_color(island);
+            let _ = write!(svg, "<path fill=\"rgb({},{},{})\" d=\"{}\"/>", r, g, b, d);
+        }
+        if !flipped.is_empty() {
+            let _ = write!(svg, "<path fill=\"#ff9800\" d=\"{}\"/>", flipped);
+        }
+        if !overlapping.is_empty() {
+            let _ = write!(svg, "<path fill=\"#e53935\" d=\"{}\"/>", overlapping);
+        }
+        svg.push_str("</g>");
+        let _ = write!(
+            svg,
+            "<rect width=\"{s}\" height=\"{s}\" fill=\"none\" stroke=\"#9e9e9e\"/></svg>",
+            s = size
+        );
+        svg
+    }
+
+    /// Raster version of [`Self::render_svg`]
+    pub fn render_png(&self, size: u32) -> Result<Vec<u8>, String> {
+        let mut image = RgbaImage::from_pixel(size, size, Rgba([30, 30, 30, 255]));
+        let s = size as f32;
+        for (f, t) in self.corners.iter().enumerate() {
+            let color = if self.overlapping[f] {
+                [229, 57, 53]
+            } else if self.flipped[f] {
+                [255, 152, 0]
+            } else {
+                island_color(self.islands[f])
+            };
+            let px = t.map(|p| [p[0] * s, p[1] * s]);
+            fill_triangle(&mut image, px, Rgba([color[0], color[1], color[2], 255]));
+            for k in 0..3 {
+                draw_line(&mut image, px[k], px[(k + 1) % 3], Rgba([16, 16, 16, 255]));
+            }
+        }
+
+        let mut out = Cursor::new(Vec::new());
+        image
+            .write_to(&mut out, ImageFormat::Png)
+            .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+        Ok(out.into_inner())
+    }
+}
+
+/// Island of each face and the island count
+///
+/// Faces are joined over edges whose corners match in position and UV, so
+/// vertices split for normals don't break islands apart.
+pub fn islands(mesh: &MeshData, uvs: &[f32]) -> (Vec<usize>, usize) {
+    let mut welded: HashMap<[u32; 5], u32> = HashMap::new();
+    let ids: Vec<u32> = (0..mesh.vertex_count())
+        .map(|i| {
+            let p = &mesh.positions[i * 3..i * 3 + 3];
+            let key = [p[0], p[1], p[2], uvs[i * 2], uvs[i * 2 + 1]].map(f32::to_bits);
+            let next = welded.len() as u32;
+            *welded.entry(key).or_insert(next)
+        })
+        .collect();
+
+    let face_count = mesh.face_count();
+    let mut parent: Vec<usize> = (0..face_count).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut edge_face: HashMap<(u32, u32), usize> = HashMap::new();
+    for (f, face) in mesh.indices.chunks_exact(3).enumerate() {
+        for k in 0..3 {
+            let (a, b) = (ids[face[k] as usize], ids[face[(k + 1) % 3] as usize]);
+            match edge_face.entry((a.min(b), a.max(b))) {
+                std::collections::hash_map::Entry::Occupied(e) => {
+                    let (ra, rb) = (find(&mut parent, *e.get()), find(&mut parent, f));
+                    parent[ra] = rb;
+                }
+                std::collections::hash_map::Entry::Vacant(e) => {
+                    e.insert(f);
+                }
+            }
+        }
+    }
+
+    let mut numbering: HashMap<usize, usize> = HashMap::new();
+    let islands = (0..face_count)
+        .map(|f| {
+            let root = find(&mut parent, f);
+            let next = numbering.len();
+            *numbering.entry(root).or_insert(next)
+        })
+        .collect();
+    (islands, numbering.len())
+}
+
+/// Faces that overlap another face, and the island pairs involved
+///
+/// Faces are bucketed on a grid over the UV bounds; each pair is tested
+/// once, in the first cell both of them cover.
+fn overlaps(
+    corners: &[[[f32; 2]; 3]],
+    areas: &[f32],
+    islands: &[usize],
+) -> (Vec<bool>, BTreeSet<[usize; 2]>) {
+    let mut overlapping = vec![false; corners.len()];
+    let mut pairs = BTreeSet::new();
+
+    let mut min = [f32::MAX; 2];
+    let mut max = [f32::MIN; 2];
+    for p in corners.iter().flatten() {
+        for a in 0..2 {
+            min[a] = min[a].min(p[a]);
+            max[a] = max[a].max(p[a]);
+        }
+    }
+    let extent = (max[0] - min[0]).max(max[1] - min[1]);
+    if !(extent > 0.0 && extent.is_finite()) {
+        return (overlapping, pairs);
+    }
+    let resolution = (corners.len() as f32).sqrt().clamp(1.0, 1024.0);
+    let cell = extent / resolution;
+    let cell_range = |t: &[[f32; 2]; 3]| {
+        let lo = [0, 1].map(|a| ((t[0][a].min(t[1][a]).min(t[2][a]) - min[a]) / cell) as i32);
+        let hi = [0, 1].map(|a| ((t[0][a].max(t[1][a]).max(t[2][a]) - min[a]) / cell) as i32);
+        (lo, hi)
+    };
+
+    let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (f, t) in corners.iter().enumerate() {
+        if areas[f].abs() <= DEGENERATE_AREA {
+            continue;
+        }
+        let (lo, hi) = cell_range(t);
+        for x in lo[0]..=hi[0] {
+            for y in lo[1]..=hi[1] {
+                grid.entry((x, y)).or_default().push(f);
+            }
+        }
+    }
+
+    for (&(x, y), faces) in &grid {
+        for (k, &a) in faces.iter().enumerate() {
+            let (lo_a, _) = cell_range(&corners[a]);
+            for &b in &faces[k + 1..] {
+                let (lo_b, _) = cell_range(&corners[b]);
+                if (lo_a[0].max(lo_b[0]), lo_a[1].max(lo_b[1])) != (x, y) {
+                    continue;
+                }
+                if triangles_overlap(corners[a], corners[b], OVERLAP_EPSILON) {
+                    overlapping[a] = true;
+                    overlapping[b] = true;
+                    let (i, j) = (islands[a], islands[b]);
+                    pairs.insert([i.min(j), i.max(j)]);
+                }
+            }
+        }
+    }
+    (overlapping, pairs)
+}
+
+/// Muted color per island, spread around the hue circle
+fn island_color(island: usize) -> [u8; 3] {
+    let hue = (island as f32 * 137.508).rem_euclid(360.0) / 60.0;
+    let (s, v) = (0.45, 0.75);
+    let c = v * s;
+    let x = c * (1.0 - (hue.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    [r, g, b].map(|channel| ((channel + v - c) * 255.0).round() as u8)
+}
+
+fn fill_triangle(image: &mut RgbaImage, t: [[f32; 2]; 3], color: Rgba<u8>) {
+    let area = signed_area(t);
+    if area.abs() <= DEGENERATE_AREA {
+        return;
+    }
+    let (w, h) = (image.width() as f32, image.height() as f32);
+    let lo = [0, 1].map(|a| t[0][a].min(t[1][a]).min(t[2][a]).floor().max(0.0));
+    let hi = [0, 1].map(|a| t[0][a].max(t[1][a]).max(t[2][a]).ceil());
+    let hi = [hi[0].min(w - 1.0), hi[1].min(h - 1.0)];
+    if lo[0] > hi[0] || lo[1] > hi[1] {
+        return;
+    }
+
+    for y in lo[1] as u32..=hi[1] as u32 {
+        for x in lo[0] as u32..=hi[0] as u32 {
+            let p = [x as f32 + 0.5, y as f32 + 0.5];
+            let inside = (0..3).all(|k| {
+                let edge = signed_area([t[k], t[(k + 1) % 3], p]);
+                edge == 0.0 || edge.signum() == area.signum()
+            });
+            if inside {
+                image.put_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+fn draw_line(image: &mut RgbaImage, a: [f32; 2], b: [f32; 2], color: Rgba<u8>) {
+    let steps = (b[0] - a[0]).abs().max((b[1] - a[1]).abs()).ceil().min(1e5) as u32;
+    for k in 0..=steps {
+        let t = if steps == 0 {
+            0.0
+        } else {
+            k as f32 / steps as f32
+        };
+        let (x, y) = (a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t);
+        if x >= 0.0 && y >= 0.0 && (x as u32) < image.width() && (y as u32) < image.height() {
+            image.put_pixel(x as u32, y as u32, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uv_analysis_finds_overlaps_and_flips() {
+        // Three separate quads: the second overlaps the first, the third is
+        // mirrored and sits outside the unit square
+        let quad = |x: f32| {
+            [
+                x,
+                0.0,
+                0.0,
+                x + 1.0,
+                0.0,
+                0.0,
+                x + 1.0,
+                1.0,
+                0.0,
+                x,
+                1.0,
+                0.0,
+            ]
+        };
+        let mesh = MeshData {
+            positions: [quad(0.0), quad(2.0), quad(4.0)].concat(),
+            uvs: Some(vec![
+                0.0, 0.0, 0.5, 0.0, 0.5, 0.5, 0.0, 0.5, // first
+                0.25, 0.25, 0.75, 0.25, 0.75, 0.75, 0.25, 0.75, // overlapping
+                1.5, 0.0, 1.0, 0.0, 1.0, 0.5, 1.5, 0.5, // mirrored, out of range
+            ]),
+            indices: [0u32, 4, 8]
+                .iter()
+                .flat_map(|&b| [b, b + 1, b + 2, b, b + 2, b + 3])
+                .collect(),
+            ..Default::default()
+        };
+
+        let layout = UvLayout::new(&mesh, UvSet::Base).unwrap();
+        let report = layout.analyze(&mesh, UvSet::Base, 1024);
+        assert_eq!(report.island_count, 3);
+        assert_eq!(report.overlapping_islands, vec![[0, 1]]);
+        assert_eq!(report.overlapping_face_count, 4);
+        assert_eq!(report.mirrored_island_count, 1);
+        assert_eq!(report.flipped_face_count, 0);
+        assert_eq!(report.out_of_range_face_count, 2);
+        assert_eq!(report.tiles, vec![[0, 0], [1, 0]]);
+        assert_eq!(report.bounds.max, [1.5, 0.75]);
+        // Half a UV unit per world unit on every face
+        assert!((report.texel_density.min - 512.0).abs() < 1e-3);
+        assert!((report.texel_density.max - 512.0).abs() < 1e-3);
+
+        assert!(layout.render_svg(256).contains("#e53935"));
+        let png = layout.render_png(64).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        assert!(UvLayout::new(&mesh, UvSet::Lightmap).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+//! Planar, cylindrical and box UV projection
+//!
+//! A quick alternative to unwrapping for hard-surface props. UVs are computed
+//! per face corner and the mesh is re-indexed afterwards, so vertices where
+//! the projection is discontinuous (box faces, the cylinder's wrap-around)
+//! are split and everything else stays shared.
+
+use crate::utils::mesh_store::MeshData;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+/// Projection used by `project_uvs`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UvProjection {
+    /// Project along `axis`; defaults to the mesh's thinnest axis
+    Planar { axis: Option<[f32; 3]> },
+    /// Wrap around `axis` (default +Y) through the mesh center
+    Cylindrical { axis: Option<[f32; 3]> },
+    /// Project each face along its dominant axis
+    Box,
+}
+
+/// Compute UVs with `projection`, splitting vertices where they disagree
+///
+/// UVs span [0, 1] over the mesh's largest extent (the cylinder's U covers
+/// one turn), multiplied by `scale` for tiling textures.
+pub fn project(mesh: &MeshData, projection: UvProjection, scale: f32) -> Result<MeshData, String> {
+    let (min, max) = bounds(&mesh.positions);
+    let extent = [0, 1, 2].map(|a| max[a] - min[a]);
+    let size = extent.iter().copied().fold(0.0f32, f32::max);
+    if !(size > 0.0 && size.is_finite()) {
+        return Err("Mesh has no extent to project onto".to_string());
+    }
+    let center = [0, 1, 2].map(|a| (min[a] + max[a]) / 2.0);
+    let point = |i: u32| {
+        let b = i as usize * 3;
+        [
+            mesh.positions[b],
+            mesh.positions[b + 1],
+            mesh.positions[b + 2],
+        ]
+    };
+
+    // UV of each face corner, and the box side it was projected on
+    let mut corners: Vec<[f32; 2]> = Vec::with_capacity(mesh.indices.len());
+    let mut charts: Vec<u8> = Vec::new();
+    match projection {
+        UvProjection::Planar { axis } => {
+            let axis = match axis {
+                Some(axis) => unit(axis)?,
+                None => {
+                    let thinnest = (0..3).min_by(|&a, &b| extent[a].total_cmp(&extent[b]));
+                    let mut axis = [0.0; 3];
+                    axis[thinnest.unwrap_or(2)] = 1.0;
+                    axis
+                }
+            };
+            let (u, v) = basis(axis);
+            for &i in &mesh.indices {
+                let p = sub(point(i), center);
+                corners.push([dot(p, u) / size + 0.5, dot(p, v) / size + 0.5]);
+            }
+        }
+        UvProjection::Cylindrical { axis } => {
+            let axis = unit(axis.unwrap_or([0.0, 1.0, 0.0]))?;
+            let (u, v) = basis(axis);
+            for face in mesh.indices.chunks_exact(3) {
+                let mut uvs = [face[0], face[1], face[2]].map(|i| {
+                    let p = sub(point(i), center);
+                    let angle = dot(p, v).atan2(dot(p, u));
+                    [(angle + PI) / (2.0 * PI), dot(p, axis) / size + 0.5]
+                });
+                // A face straddling the wrap-around gets its low side moved
+                // past 1 so it doesn't stretch across the whole texture
+                let (lo, hi) = uvs.iter().fold((f32::MAX, f32::MIN), |(lo, hi), uv| {
+                    (lo.min(uv[0]), hi.max(uv[0]))
+                });
+                if hi - lo > 0.5 {
+                    for uv in &mut uvs {
+                        if uv[0] < 0.5 {
+                            uv[0] += 1.0;
+                        }
+                    }
+                }
+                corners.extend(uvs);
+            }
+        }
+        UvProjection::Box => {
+            for face in mesh.indices.chunks_exact(3) {
+                let [a, b, c] = [face[0], face[1], face[2]].map(point);
+                let n = cross(sub(b, a), sub(c, a));
+                let dominant = (0..3)
+                    .max_by(|&x, &y| n[x].abs().total_cmp(&n[y].abs()))
+                    .unwrap_or(2);
+                // The two other axes, ordered so each side reads unmirrored
+                let (ua, va) = match dominant {
+                    0 => (2, 1),
+                    1 => (0, 2),
+                    _ => (0, 1),
+                };
+                let handedness = if dominant == 2 { 1.0 } else { -1.0 };
+                let flip = n[dominant].signum() * handedness;
+                let side = dominant as u8 * 2 + (n[dominant] < 0.0) as u8;
+                charts.extend([side; 3]);
+                for p in [a, b, c] {
+                    let p = sub(p, center);
+                    corners.push([flip * p[ua] / size + 0.5, p[va] / size + 0.5]);
+                }
+            }
+        }
+    }
+
+    charts.resize(corners.len(), 0);
+    Ok(split_by_corner_uvs(mesh, &corners, &charts, scale))
+}
+
+/// Rebuild a mesh with one vertex per distinct (vertex, UV, chart)
+fn split_by_corner_uvs(
+    mesh: &MeshData,
+    corners: &[[f32; 2]],
+    charts: &[u8],
+    scale: f32,
+) -> MeshData {
+    let mut ids: HashMap<(u32, [u32; 2], u8), u32> = HashMap::new();
+    let mut order = Vec::new();
+    let mut uvs = Vec::new();
+    let indices = mesh
+        .indices
+        .iter()
+        .zip(corners.iter().zip(charts))
+        .map(|(&i, (uv, &chart))| {
+            let uv = uv.map(|c| c * scale);
+            *ids.entry((i, uv.map(f32::to_bits), chart))
+                .or_insert_with(|| {
+                    order.push(i as usize);
+                    uvs.extend_from_slice(&uv);
+                    (order.len() - 1) as u32
+                })
+        })
+        .collect();
+
+    let mut projected = mesh.with_vertices(&order, indices);
+    projected.uvs = Some(uvs);
+    // Faces are unchanged, only their corners were split
+    projected.polygons = mesh.polygons.clone();
+    projected
+}
+
+/// Two unit vectors spanning the plane perpendicular to `axis`
+fn basis(axis: [f32; 3]) -> ([f32; 3], [f32; 3]) {
+    // Keep world up as V where possible so projections read upright
+    let reference = if axis[1].abs() < 0.9 {
+        [0.0, 1.0, 0.0]
+    } else {
+        [0.0, 0.0, -1.0]
+    };
+    let u = normalize(cross(reference, axis));
+    let v = cross(axis, u);
+    (u, v)
+}
+
+fn unit(axis: [f32; 3]) -> Result<[f32; 3], String> {
+    let len = dot(axis, axis).sqrt();
+    if !(len > 0.0 && len.is_finite()) {
+        return Err(format!("Invalid projection axis: {:?}", axis));
+    }
+    Ok(axis.map(|a| a / len))
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = dot(v, v).sqrt();
+    v.map(|a| a / len)
+}
+
+fn bounds(positions: &[f32]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in positions.chunks_exact(3) {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(p[axis]);
+            max[axis] = max[axis].max(p[axis]);
+        }
+    }
+    (min, max)
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit cube with 8 shared corners and outward winding
+    fn cube() -> MeshData {
+        let mut positions = Vec::new();
+        for i in 0..8 {
+            positions.extend([(i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32]);
+        }
+        let quads = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        let indices = quads
+            .iter()
+            .flat_map(|q| [q[0], q[1], q[2], q[0], q[2], q[3]])
+            .collect();
+        MeshData {
+            positions,
+            indices,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_box_projection_splits_cube_faces() {
+        let mesh = cube();
+        let projected = project(&mesh, UvProjection::Box, 1.0).unwrap();
+
+        // Each side gets its own four corners
+        assert_eq!(projected.vertex_count(), 24);
+        assert_eq!(projected.face_count(), 12);
+        assert!(projected.validate().is_ok());
+        let uvs = projected.uvs.unwrap();
+        assert!(uvs.iter().all(|&c| (0.0..=1.0).contains(&c)));
+
+        // Planar projection is continuous, so nothing is split
+        let planar = project(
+            &mesh,
+            UvProjection::Planar {
+                axis: Some([0.0, 0.0, 1.0]),
+            },
+            2.0,
+        )
+        .unwrap();
+        assert_eq!(planar.vertex_count(), 8);
+        assert!(planar.uvs.unwrap().iter().all(|&c| c == 0.0 || c == 2.0));
+    }
+}
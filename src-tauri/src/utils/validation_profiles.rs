@@ -0,0 +1,225 @@
+//! Per-platform validation profiles
+//!
+//! A validation profile is a set of limits an asset has to stay within for
+//! a target platform: face, vertex, mesh and material counts, texture size,
+//! file size, and whether normals and UVs are required. The built-in
+//! profiles ship as data in `validation_profiles.json`; profiles in the
+//! settings are added to them, and replace a built-in of the same name.
+
+use crate::utils::gltf_geometry::{external_buffer_path, LoadedGltf};
+use crate::utils::report::AssetReport;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::path::Path;
+
+const BUILT_IN: &str = include_str!("validation_profiles.json");
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidationProfile {
+    pub name: String,
+    pub description: String,
+    pub max_faces: Option<usize>,
+    pub max_vertices: Option<usize>,
+    /// Meshes, roughly the draw calls the asset costs
+    pub max_meshes: Option<usize>,
+    pub max_materials: Option<usize>,
+    /// Largest texture side in pixels
+    pub max_texture_size: Option<u32>,
+    pub max_file_size_bytes: Option<u64>,
+    pub require_normals: bool,
+    pub require_uvs: bool,
+}
+
+/// A profile as listed, with where it comes from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListedProfile {
+    #[serde(flatten)]
+    pub profile: ValidationProfile,
+    /// Shipped with the app rather than defined in the settings
+    pub built_in: bool,
+    /// A settings profile that replaces the built-in of the same name
+    pub overrides_built_in: bool,
+}
+
+/// The profiles shipped with the app
+pub fn built_in() -> Vec<ValidationProfile> {
+    serde_json::from_str(BUILT_IN).unwrap_or_default()
+}
+
+/// The built-in profiles, with `custom` ones replacing those of the same
+/// name (ignoring case), followed by the other custom ones
+pub fn list(custom: &[ValidationProfile]) -> Vec<ListedProfile> {
+    let same = |a: &ValidationProfile, b: &ValidationProfile| {
+        a.name.trim().eq_ignore_ascii_case(b.name.trim())
+    };
+    let built_in = built_in();
+    let mut listed: Vec<ListedProfile> = built_in
+        .iter()
+        .map(|profile| match custom.iter().find(|c| same(c, profile)) {
+            Some(replacement) => ListedProfile {
+                profile: replacement.clone(),
+                built_in: false,
+                overrides_built_in: true,
+            },
+            None => ListedProfile {
+                profile: profile.clone(),
+                built_in: true,
+                overrides_built_in: false,
+            },
+        })
+        .collect();
+    for profile in custom {
+        if !built_in.iter().any(|b| same(b, profile)) {
+            listed.push(ListedProfile {
+                profile: profile.clone(),
+                built_in: false,
+                overrides_built_in: false,
+            });
+        }
+    }
+    listed
+}
+
+/// The profile called `name`, ignoring case
+pub fn find(custom: &[ValidationProfile], name: &str) -> Result<ValidationProfile, String> {
+    let listed = list(custom);
+    listed
+        .iter()
+        .find(|p| p.profile.name.trim().eq_ignore_ascii_case(name.trim()))
+        .map(|p| p.profile.clone())
+        .ok_or_else(|| {
+            let names: Vec<&str> = listed.iter().map(|p| p.profile.name.as_str()).collect();
+            format!(
+                "No validation profile named '{}' (have: {})",
+                name,
+                names.join(", ")
+            )
+        })
+}
+
+impl ValidationProfile {
+    /// The limits `asset` breaks, described for a person
+    pub fn violations(&self, asset: &AssetReport) -> Vec<String> {
+        let mut violations = Vec::new();
+        let mut over = |what: &str, value: u64, limit: Option<u64>| {
+            if let Some(limit) = limit.filter(|&limit| value > limit) {
+                violations.push(format!("{}: {} (limit {})", what, value, limit));
+            }
+        };
+        over(
+            "faces",
+            asset.face_count as u64,
+            self.max_faces.map(|m| m as u64),
+        );
+        over(
+            "vertices",
+            asset.vertex_count as u64,
+            self.max_vertices.map(|m| m as u64),
+        );
+        over(
+            "meshes",
+            asset.mesh_count as u64,
+            self.max_meshes.map(|m| m as u64),
+        );
+        over(
+            "materials",
+            asset.material_count as u64,
+            self.max_materials.map(|m| m as u64),
+        );
+        over(
+            "texture size",
+            asset.max_texture_size as u64,
+            self.max_texture_size.map(u64::from),
+        );
+        over(
+            "file size in bytes",
+            asset.file_size_bytes,
+            self.max_file_size_bytes,
+        );
+        if self.require_normals && !asset.validation.has_normals {
+            violations.push("no normals".to_string());
+        }
+        if self.require_uvs && !asset.validation.has_uvs {
+            violations.push("no UVs".to_string());
+        }
+        violations
+    }
+}
+
+/// Longest side of the largest image in a glTF file; images that can't be
+/// read are skipped
+pub fn largest_texture(loaded: &LoadedGltf, base: &Path) -> u32 {
+    loaded
+        .document
+        .images()
+        .filter_map(|image| match image.source() {
+            gltf::image::Source::View { view, .. } => {
+                let buffer = loaded.buffers.get(view.buffer().index())?;
+                let bytes = buffer.get(view.offset()..view.offset() + view.length())?;
+                image::ImageReader::new(Cursor::new(bytes))
+                    .with_guessed_format()
+                    .ok()?
+                    .into_dimensions()
+                    .ok()
+            }
+            gltf::image::Source::Uri { uri, .. } if !uri.starts_with("data:") => {
+                image::image_dimensions(external_buffer_path(base, uri).ok()?).ok()
+            }
+            gltf::image::Source::Uri { .. } => None,
+        })
+        .map(|(width, height)| width.max(height))
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::report::ValidationResult;
+
+    #[test]
+    fn test_built_in_profiles_custom_overrides_and_limits() {
+        let built_in = built_in();
+        let names: Vec<&str> = built_in.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Quest / mobile VR", "WebGL", "Console", "Desktop"]);
+
+        let custom = [
+            ValidationProfile {
+                name: "webgl".to_string(),
+                max_faces: Some(10),
+                ..Default::default()
+            },
+            ValidationProfile {
+                name: "Kiosk".to_string(),
+                ..Default::default()
+            },
+        ];
+        let listed = list(&custom);
+        assert_eq!(listed.len(), 5);
+        assert!(listed[1].overrides_built_in && !listed[1].built_in);
+        assert_eq!(listed[4].profile.name, "Kiosk");
+        assert_eq!(find(&custom, " WEBGL ").unwrap().max_faces, Some(10));
+        assert!(find(&custom, "Switch").is_err());
+
+        let asset = AssetReport {
+            face_count: 30_000,
+            vertex_count: 20_000,
+            max_texture_size: 4096,
+            validation: ValidationResult {
+                has_normals: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let quest = find(&[], "quest / mobile vr").unwrap();
+        assert_eq!(
+            quest.violations(&asset),
+            [
+                "faces: 30000 (limit 20000)",
+                "texture size: 4096 (limit 2048)",
+                "no UVs"
+            ]
+        );
+    }
+}
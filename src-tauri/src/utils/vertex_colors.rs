@@ -0,0 +1,125 @@
+//! Vertex color helpers
+//!
+//! Colors are stored linear, as glTF `COLOR_0` is. Textures are sRGB-encoded,
+//! so they are decoded before sampling to keep the bilinear filter in linear
+//! space, and OBJ output is encoded back since readers treat it as sRGB.
+
+use image::DynamicImage;
+
+/// Texture decoded to linear RGBA, sampled with repeat wrapping
+pub struct Texture {
+    width: u32,
+    height: u32,
+    texels: Vec<[f32; 4]>,
+}
+
+impl Texture {
+    pub fn from_image(image: &DynamicImage) -> Result<Self, String> {
+        Self::decode(image, srgb_to_linear)
+    }
+
+    /// A texture holding data rather than color, such as a normal or
+    /// metallic-roughness map, whose values are used as they are
+    pub fn from_data_image(image: &DynamicImage) -> Result<Self, String> {
+        Self::decode(image, |c| c)
+    }
+
+    fn decode(image: &DynamicImage, to_linear: fn(f32) -> f32) -> Result<Self, String> {
+        let rgba = image.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        if width == 0 || height == 0 {
+            return Err("Texture has no pixels".to_string());
+        }
+        let texels = rgba
+            .pixels()
+            .map(|p| {
+                let [r, g, b, a] = p.0.map(|c| c as f32 / 255.0);
+                [to_linear(r), to_linear(g), to_linear(b), a]
+            })
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            texels,
+        })
+    }
+
+    /// Bilinear sample at a glTF UV, whose origin is the top-left corner
+    pub fn sample(&self, uv: [f32; 2]) -> [f32; 4] {
+        let x = uv[0] * self.width as f32 - 0.5;
+        let y = uv[1] * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let texel = |dx: f32, dy: f32| {
+            let tx = (x0 + dx).rem_euclid(self.width as f32) as usize;
+            let ty = (y0 + dy).rem_euclid(self.height as f32) as usize;
+            self.texels[ty * self.width as usize + tx]
+        };
+        let (a, b, c, d) = (
+            texel(0.0, 0.0),
+            texel(1.0, 0.0),
+            texel(0.0, 1.0),
+            texel(1.0, 1.0),
+        );
+
+        std::array::from_fn(|k| {
+            let top = a[k] + (b[k] - a[k]) * fx;
+            let bottom = c[k] + (d[k] - c[k]) * fx;
+            top + (bottom - top) * fy
+        })
+    }
+}
+
+/// Linear RGBA per vertex, sampled from `texture` at each vertex's UV
+pub fn bake(uvs: &[f32], texture: &Texture) -> Vec<f32> {
+    uvs.chunks_exact(2)
+        .flat_map(|uv| texture.sample([uv[0], uv[1]]))
+        .collect()
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_bake_samples_in_linear_space() {
+        // Black and white texels side by side, repeated vertically
+        let image = RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+        let texture = Texture::from_image(&DynamicImage::ImageRgba8(image)).unwrap();
+
+        let colors = bake(&[0.25, 0.5, 0.75, 0.5, 0.5, 0.5, 0.0, 0.5], &texture);
+        assert_eq!(&colors[0..4], &[0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(&colors[4..8], &[1.0, 1.0, 1.0, 1.0]);
+        // Halfway between is half as bright in linear terms, not sRGB
+        assert!((colors[8] - 0.5).abs() < 1e-6);
+        // The left edge wraps around to the white texel
+        assert!((colors[12] - 0.5).abs() < 1e-6);
+
+        assert!((linear_to_srgb(srgb_to_linear(0.3)) - 0.3).abs() < 1e-6);
+    }
+}
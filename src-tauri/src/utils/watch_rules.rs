@@ -0,0 +1,93 @@
+//! Rules that process files arriving in watched folders
+//!
+//! A rule names a folder, the file extensions it cares about and a
+//! processing profile. Whenever a matching file is added or changed under
+//! the folder, the profile is run on it as a background job and the result
+//! written to the folder's `processed` subfolder, which the rule itself
+//! ignores so its own output doesn't trigger it again.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Subfolder of a rule's folder that receives its output
+pub const OUTPUT_FOLDER: &str = "processed";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchRule {
+    pub folder: String,
+    /// Extensions without the dot, matched ignoring case (default the
+    /// model formats that can be imported)
+    pub extensions: Vec<String>,
+    /// Name of the processing profile to run
+    pub profile: String,
+    /// Also load the result into the mesh store
+    pub import_result: bool,
+    pub enabled: bool,
+}
+
+impl Default for WatchRule {
+    fn default() -> Self {
+        Self {
+            folder: String::new(),
+            extensions: ["glb", "gltf", "obj", "stl", "fbx"]
+                .map(String::from)
+                .to_vec(),
+            profile: String::new(),
+            import_result: false,
+            enabled: true,
+        }
+    }
+}
+
+impl WatchRule {
+    pub fn output_dir(&self) -> PathBuf {
+        Path::new(&self.folder).join(OUTPUT_FOLDER)
+    }
+
+    /// Whether a change to `path` should run this rule
+    ///
+    /// `path` and the rule's folder are compared as given, so both should
+    /// be resolved paths.
+    pub fn matches(&self, path: &Path) -> bool {
+        if !self.enabled || self.folder.is_empty() {
+            return false;
+        }
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy())
+            .unwrap_or_default();
+        path.starts_with(&self.folder)
+            && !path.starts_with(self.output_dir())
+            && self
+                .extensions
+                .iter()
+                .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&extension))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_matches_new_models_only() {
+        let rule = WatchRule {
+            folder: "/scans".to_string(),
+            extensions: vec![".OBJ".to_string()],
+            profile: "Print prep".to_string(),
+            ..Default::default()
+        };
+        assert!(rule.matches(Path::new("/scans/bust.obj")));
+        assert!(rule.matches(Path::new("/scans/day2/Bust.Obj")));
+        assert!(!rule.matches(Path::new("/scans/bust.mtl")));
+        assert!(!rule.matches(Path::new("/scans-old/bust.obj")));
+        assert!(!rule.matches(Path::new("/scans/processed/bust_print_prep.obj")));
+
+        let disabled = WatchRule {
+            enabled: false,
+            ..rule
+        };
+        assert!(!disabled.matches(Path::new("/scans/bust.obj")));
+    }
+}
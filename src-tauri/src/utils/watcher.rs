@@ -0,0 +1,76 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Events arriving within this window are delivered as one batch
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Recursive file watchers keyed by watched root, managed as Tauri state
+#[derive(Default)]
+pub struct DirectoryWatchers {
+    watchers: Mutex<HashMap<PathBuf, RecommendedWatcher>>,
+}
+
+impl DirectoryWatchers {
+    /// Start watching `root`, calling `on_change` with each debounced batch
+    /// of changed paths
+    ///
+    /// Returns `false` if the directory was already being watched.
+    pub fn watch<F>(&self, root: &Path, on_change: F) -> Result<bool, String>
+    where
+        F: Fn(&Path, Vec<PathBuf>) + Send + 'static,
+    {
+        let root = fs::canonicalize(root).map_err(|e| format!("Failed to resolve path: {}", e))?;
+        let mut watchers = self.watchers.lock().unwrap();
+        if watchers.contains_key(&root) {
+            return Ok(false);
+        }
+
+        let (tx, rx) = mpsc::channel::<notify::Result<notify::Event>>();
+        let mut watcher = notify::recommended_watcher(tx)
+            .map_err(|e| format!("Failed to create watcher: {}", e))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", root.display(), e))?;
+
+        // The channel closes when the watcher is dropped, ending the thread
+        let thread_root = root.clone();
+        thread::spawn(move || {
+            while let Ok(first) = rx.recv() {
+                let mut paths = BTreeSet::new();
+                let mut collect = |event: notify::Result<notify::Event>| match event {
+                    Ok(event) => paths.extend(event.paths),
+                    Err(e) => log::warn!("Watch error in {}: {}", thread_root.display(), e),
+                };
+                collect(first);
+                let closed = loop {
+                    match rx.recv_timeout(DEBOUNCE) {
+                        Ok(event) => collect(event),
+                        Err(RecvTimeoutError::Timeout) => break false,
+                        Err(RecvTimeoutError::Disconnected) => break true,
+                    }
+                };
+                if !paths.is_empty() {
+                    on_change(&thread_root, paths.into_iter().collect());
+                }
+                if closed {
+                    break;
+                }
+            }
+        });
+
+        watchers.insert(root, watcher);
+        Ok(true)
+    }
+
+    /// Stop watching `root`; returns `false` if it wasn't being watched
+    pub fn unwatch(&self, root: &Path) -> bool {
+        let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        self.watchers.lock().unwrap().remove(&root).is_some()
+    }
+}
@@ -0,0 +1,93 @@
+//! Static web pages showing a single model
+//!
+//! An export is a folder with the model as a Draco and KTX2 compressed GLB,
+//! a poster image shown while it loads, and an `index.html` using Google's
+//! `<model-viewer>`, which fetches its decoders from a CDN. The folder works
+//! from any static host. Compression is done by `gltf-transform`, since
+//! neither encoder is available natively.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Page written into the export folder, with `{{title}}`, `{{model}}` and
+/// `{{poster}}` filled in
+pub const VIEWER_PAGE: &str = include_str!("web_viewer.html");
+pub const PAGE_FILE: &str = "index.html";
+pub const MODEL_FILE: &str = "model.glb";
+pub const POSTER_FILE: &str = "poster.png";
+
+/// The viewer page for a model titled `title`
+pub fn page(title: &str) -> String {
+    VIEWER_PAGE
+        .replace("{{title}}", &escape(title))
+        .replace("{{model}}", MODEL_FILE)
+        .replace("{{poster}}", POSTER_FILE)
+}
+
+/// Compress `input` into the GLB `output` with Draco geometry and KTX2
+/// textures
+///
+/// `executable` is the `gltf-transform` command line tool; empty looks it
+/// up on the `PATH`. KTX2 encoding also needs KTX-Software's `toktx` on the
+/// `PATH`. A `.gltf` input is packed into the GLB with its buffers and
+/// images.
+pub fn optimize(executable: &str, input: &Path, output: &Path) -> Result<(), String> {
+    let executable = match executable.trim() {
+        "" => "gltf-transform",
+        configured => configured,
+    };
+    let result = Command::new(executable)
+        .arg("optimize")
+        .arg(input)
+        .arg(output)
+        .args(["--compress", "draco", "--texture-compress", "ktx2"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| {
+            format!(
+                "Failed to start {} (install @gltf-transform/cli or set its path): {}",
+                executable, e
+            )
+        })?;
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(format!(
+            "{} failed on {}: {}",
+            executable,
+            input.display(),
+            stderr.trim()
+        ));
+    }
+    Ok(())
+}
+
+/// `text` with the characters that are special in HTML text and attributes
+/// escaped
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_escapes_title() {
+        let html = page("Rock \"A\" <lod0> & more");
+        assert!(html.contains("<title>Rock &quot;A&quot; &lt;lod0&gt; &amp; more</title>"));
+        assert!(html.contains("src=\"model.glb\""));
+        assert!(html.contains("poster=\"poster.png\""));
+        assert!(!html.contains("{{"));
+    }
+}